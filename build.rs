@@ -4,6 +4,11 @@ use std::process::Command;
 
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
+
+    // The embedded runtime only loads voices the application bundles itself
+    if std::env::var("CARGO_FEATURE_FULL").is_err() {
+        return;
+    }
     
     // Create models directory if it doesn't exist
     let models_dir = Path::new("models");
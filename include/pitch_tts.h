@@ -0,0 +1,67 @@
+#ifndef PITCH_TTS_H
+#define PITCH_TTS_H
+
+/* Generated by cbindgen from src/lib/ffi.rs; do not edit. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Synthesized audio: `len` mono samples at `sample_rate`, pitch and tempo applied
+typedef struct PitchTtsAudio {
+  float *samples;
+  size_t len;
+  uint32_t sample_rate;
+} PitchTtsAudio;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Synthesize `text` with `voice` (downloading it if needed), pitch shifted by `pitch` (1.0 = unchanged)
+// and stretched by `tempo`, into `*out`.
+//
+// # Safety
+// `text` and `voice` must be valid NUL-terminated strings and `out` valid for writes.
+int pitch_tts_synthesize(const char *text, const char *voice, float pitch, float tempo, PitchTtsAudio *out);
+
+// Free audio from `pitch_tts_synthesize` and reset it to empty. Null and empty audio are ignored.
+//
+// # Safety
+// `audio` must be null or point to audio filled in by `pitch_tts_synthesize`, not already freed.
+void pitch_tts_free_audio(PitchTtsAudio *audio);
+
+// The voice catalog as a JSON array into `*out`, as `list --json` prints it (ID, language, quality,
+// sample rate, whether installed, ...). Only installed voices when `installed_only` is nonzero.
+//
+// # Safety
+// `out` must be valid for writes.
+int pitch_tts_list_voices(int installed_only, char **out);
+
+// Lipsync JSON for `text` spoken in `len` samples at `sample_rate` (as from `pitch_tts_synthesize`)
+// into `*out`: word timings with ARPAbet phonemes, fitted to the audio with the built-in aligner when
+// `aligned` is nonzero and estimated from the text otherwise. Neither needs WhisperX.
+//
+// # Safety
+// `text` must be a valid NUL-terminated string, `samples` valid for `len` reads, and `out` valid for writes.
+int pitch_tts_lipsync(const char *text,
+                      const float *samples,
+                      size_t len,
+                      uint32_t sample_rate,
+                      int aligned,
+                      char **out);
+
+// Free a string from `pitch_tts_list_voices` or `pitch_tts_lipsync`. Null is ignored.
+//
+// # Safety
+// `s` must be null or a string returned by this library, not already freed.
+void pitch_tts_free_string(char *s);
+
+// The message of the last failure on this thread, or null if nothing has failed. Valid until the
+// next failing call on the thread; don't free it.
+const char *pitch_tts_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PITCH_TTS_H */
@@ -157,16 +157,19 @@ def is_cli_available():
     return runner.test_command("text-to-face")
 
 def get_app_data_dir():
-    # Match the Rust ProjectDirs logic
+    # Match the Rust data_dir logic (without --data-dir, which the add-on doesn't pass)
+    override = os.environ.get("PITCH_TTS_DATA_DIR")
+    if override:
+        return override
     home = os.path.expanduser("~")
     if platform.system() == "Darwin":
-        return os.path.join(home, "Library", "Application Support", "com.yourorg.text-to-face")
+        return os.path.join(home, "Library", "Application Support", "pitch-tts")
     elif platform.system() == "Linux":
-        return os.path.join(home, ".local", "share", "com.yourorg.text-to-face")
+        return os.path.join(os.environ.get("XDG_DATA_HOME") or os.path.join(home, ".local", "share"), "pitch-tts")
     elif platform.system() == "Windows":
-        return os.path.join(os.environ.get("APPDATA", os.path.join(home, "AppData", "Roaming")), "com.yourorg.text-to-face")
+        return os.path.join(os.environ.get("APPDATA", os.path.join(home, "AppData", "Roaming")), "pitch-tts", "data")
     else:
-        return os.path.join(home, ".text-to-face")
+        return os.path.join(home, ".pitch-tts")
 
 def get_generated_wav_path(blend_filepath, voice_id, text):
     base = os.path.splitext(os.path.basename(blend_filepath))[0] if blend_filepath else "untitled"
@@ -0,0 +1,76 @@
+use crate::output;
+use text_to_face::audiobook::{read_chapter_files, split_chapters, synthesize_chapters, AudiobookMetadata, AudiobookOptions, MANIFEST_FILE};
+use text_to_face::sidecar::{is_up_to_date, write_sidecar, ExportSidecar};
+use text_to_face::PitchArg;
+use crate::print_warnings;
+use colored::*;
+use std::fs;
+use std::path::Path;
+
+pub fn handle_audiobook(input: &str, voice: &str, output: &str, pitch: &PitchArg, tempo: f32, metadata: AudiobookMetadata, options: AudiobookOptions, sidecar: bool) {
+    let read = if Path::new(input).is_dir() {
+        // The book-level sidecar records every chapter, so a change to any of them re-renders the book
+        read_chapter_files(Path::new(input))
+            .map(|chapters| (chapters.iter().map(|chapter| format!("# {}\n{}", chapter.title, chapter.text)).collect::<Vec<_>>().join("\n\n"), chapters))
+            .map_err(|e| e.to_string())
+    } else {
+        fs::read_to_string(input).map(|text| (text.clone(), split_chapters(&text))).map_err(|e| e.to_string())
+    };
+    let (text, chapters) = match read {
+        Ok(read) => read,
+        Err(e) => {
+            fail!("Failed to read {}: {}", input, e);
+            return;
+        }
+    };
+    // Ensure output directory exists
+    if let Some(parent) = Path::new(output).parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                fail!("Failed to create output directory: {}", e);
+                return;
+            }
+        }
+    }
+    let mut meta = ExportSidecar::new(&text, voice, pitch.as_factor(), tempo, "low", output);
+    meta.effects.extend(metadata.describe().into_iter().chain(options.describe()));
+    // Chapter files are checked one by one instead
+    if !options.chapter_files && !options.force && is_up_to_date(Path::new(output), &meta) {
+        note!("{} is up to date, skipping (use --force to re-render)", output);
+        output::append("skipped", output);
+        return;
+    }
+    note!("Rendering audiobook with voice: {} to {} (pitch: {}, tempo: {})", voice, output, pitch.as_factor(), tempo);
+    let report = match synthesize_chapters(&chapters, voice, pitch, tempo, output, &metadata, &options) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}", "Error:".red());
+            fail!("{}", e);
+            return;
+        }
+    };
+    print_warnings(&report.warnings);
+    if options.chapter_files {
+        for i in 1..=chapters.len() {
+            let chapter = Path::new(output).join(format!("chapter_{:03}.wav", i));
+            text_to_face::session::record_output(&chapter);
+            output::record_file("audio", &chapter);
+        }
+        output::record_file("manifest", Path::new(output).join(MANIFEST_FILE));
+        note!("{} chapters and {} written to {}", chapters.len(), MANIFEST_FILE, output);
+        return;
+    }
+    text_to_face::session::record_output(Path::new(output));
+    output::record_file("audio", output);
+    if sidecar {
+        meta.finalize(None);
+        meta.warnings = report.warnings;
+        match write_sidecar(Path::new(output), &meta) {
+            Ok(path) => {
+                output::record_file("sidecar", &path);
+                note!("Sidecar written to {}", path.display())
+            }
+            Err(e) => fail!("Failed to write sidecar: {}", e),
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use crate::output;
+use text_to_face::batch::{read_batch, run_batch, BatchOptions};
+use colored::*;
+use std::path::Path;
+
+pub fn handle_batch(input: &str, options: BatchOptions, report_path: Option<&str>) {
+    let rows = match read_batch(Path::new(input)) {
+        Ok(rows) => rows,
+        Err(e) => {
+            fail!("Failed to read {}: {}", input, e);
+            output::exit(1);
+        }
+    };
+    if rows.is_empty() {
+        note!("{} has no rows to render", input);
+        return;
+    }
+    let report = run_batch(&rows, &options);
+    output::record("batch", &report);
+    note!("{} {} rendered, {} up to date, {} failed", "[Batch]".green(), report.rendered, report.skipped, report.failures.len());
+    for failure in &report.failures {
+        fail!("  row {} ({}): {}", failure.row, failure.output, failure.error);
+    }
+    if let Some(path) = report_path {
+        match serde_json::to_string_pretty(&report).map_err(|e| e.to_string()).and_then(|json| std::fs::write(path, json).map_err(|e| e.to_string())) {
+            Ok(()) => {
+                output::record_file("report", path);
+                note!("Report written to {}", path)
+            }
+            Err(e) => fail!("Failed to write report: {}", e),
+        }
+    }
+    if !report.failures.is_empty() {
+        output::exit(1);
+    }
+}
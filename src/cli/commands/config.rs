@@ -0,0 +1,50 @@
+use crate::output;
+use crate::config::{config_path, Config, KEYS};
+
+pub fn handle_config_get(key: Option<&str>) {
+    let config = load();
+    match key {
+        Some(key) if !KEYS.contains(&key) => fail!("Unknown config key '{}' (expected one of: {})", key, KEYS.join(", ")),
+        Some(key) => {
+            output::record(key, config.get(key));
+            match config.get(key) {
+                Some(value) => note!("{}", value),
+                None => note!("{} is not set", key),
+            }
+        }
+        None => {
+            for key in KEYS {
+                output::record(key, config.get(key));
+                note!("{} = {}", key, config.get(key).unwrap_or_else(|| "(not set)".to_string()));
+            }
+        }
+    }
+}
+
+pub fn handle_config_set(key: &str, value: Option<&str>) {
+    let mut config = load();
+    if let Err(e) = config.set(key, value) {
+        fail!("{}", e);
+        output::exit(1);
+    }
+    let path = config_path();
+    match config.save(&path) {
+        Ok(()) => match value {
+            _ if output::is_json() => {
+                output::record(key, value);
+                output::record_file("config", &path);
+            }
+            Some(value) => note!("Set {} = {} in {}", key, value, path.display()),
+            None => note!("Unset {} in {}", key, path.display()),
+        },
+        Err(e) => fail!("Failed to write {}: {}", path.display(), e),
+    }
+}
+
+/// The config file as it is, or an empty one if it can't be read
+fn load() -> Config {
+    Config::load(&config_path()).unwrap_or_else(|e| {
+        eprintln!("Ignoring {}: {}", config_path().display(), e);
+        Config::default()
+    })
+}
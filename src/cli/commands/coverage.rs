@@ -0,0 +1,46 @@
+use crate::output;
+use colored::*;
+use text_to_face::coverage::{script_coverage, CoverageReport};
+
+fn print_counts(title: &str, counts: &std::collections::BTreeMap<String, usize>, report: &CoverageReport) {
+    note!("{}", title.bold());
+    let mut sorted: Vec<(&String, &usize)> = counts.iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    for (name, count) in sorted {
+        let share = report.share(*count);
+        note!("  {:<4} {:>6}  {:>5.1}%  {}", name, count, share * 100.0, "█".repeat((share * 100.0).round() as usize));
+    }
+}
+
+pub fn handle_coverage(script: &str, voice: &str, as_json: bool) {
+    let text = match std::fs::read_to_string(script) {
+        Ok(text) => text,
+        Err(e) => {
+            fail!("Failed to read {}: {}", script, e);
+            return;
+        }
+    };
+    let report = match script_coverage(&text, voice) {
+        Ok(report) => report,
+        Err(e) => {
+            fail!("Error: {}", e);
+            return;
+        }
+    };
+    if as_json {
+        output::print_document(&report);
+        return;
+    }
+    note!("{}: {} words, {} phonemes ({})", script, report.words, report.phoneme_count, report.alphabet);
+    print_counts("Visemes", &report.visemes, &report);
+    print_counts("Phonemes", &report.phonemes, &report);
+    if !report.missing_visemes.is_empty() {
+        note!("{} {}", "Visemes never used:".yellow(), report.missing_visemes.join(" "));
+    }
+    if !report.missing_phonemes.is_empty() {
+        note!("{} {}", "Phonemes never used:".yellow(), report.missing_phonemes.join(" "));
+    }
+    if !report.unknown_words.is_empty() {
+        note!("{} {}", "Not in CMUdict (not counted):".red(), report.unknown_words.join(", "));
+    }
+}
@@ -0,0 +1,116 @@
+use crate::output;
+use crate::LipsyncLevel;
+use clap::ValueEnum;
+use colored::*;
+use text_to_face::daemon::{self, default_socket, DaemonOptions, JobSpec, JobState, JobStatus, Request, Response};
+use text_to_face::PitchArg;
+
+/// Send `request`, turning an error response into a failure
+fn send(socket: Option<&str>, request: &Request) -> Option<Response> {
+    let socket = socket.map_or_else(default_socket, str::to_string);
+    match daemon::send(&socket, request) {
+        Ok(Response::Error(e)) => fail!("Error: {}", e),
+        Ok(response) => return Some(response),
+        Err(e) => fail!("Error: {}", e),
+    }
+    None
+}
+
+fn print_job(job: &JobStatus) {
+    let state = match job.state {
+        JobState::Done => "done".green(),
+        JobState::Failed => "failed".red(),
+        JobState::Cancelled => "cancelled".yellow(),
+        JobState::Queued | JobState::Running => format!("{:?}", job.state).to_lowercase().cyan(),
+    };
+    let seconds = job.seconds.map(|s| format!(" in {:.2}s", s)).unwrap_or_default();
+    note!("{} {}{} -> {}", format!("[Job {}]", job.id).cyan(), state, seconds, job.spec.output);
+    if let Some(error) = &job.error {
+        note!("  {}", error);
+    }
+    for warning in &job.warnings {
+        note!("  {} {}", "warning:".yellow(), warning);
+    }
+}
+
+pub fn handle_daemon(socket: Option<&str>, voice: &str, stop: bool) {
+    if stop {
+        if send(socket, &Request::Shutdown).is_some() {
+            note!("Daemon stopping");
+        }
+        return;
+    }
+    let options = DaemonOptions { socket: socket.map_or_else(default_socket, str::to_string), default_voice: voice.to_string() };
+    if let Err(e) = daemon::run(&options) {
+        fail!("Error: {}", e);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_enqueue(text: &str, output: &str, voice: Option<&str>, pitch: &PitchArg, tempo: f32, lipsync: Option<LipsyncLevel>, wait: bool, socket: Option<&str>) {
+    // The daemon runs in its own working directory
+    let output = match std::path::absolute(output) {
+        Ok(path) => path.to_string_lossy().into_owned(),
+        Err(e) => {
+            fail!("Error: {}: {}", output, e);
+            return;
+        }
+    };
+    let spec = JobSpec {
+        text: text.to_string(),
+        voice: voice.map(str::to_string),
+        pitch: pitch.as_factor(),
+        tempo,
+        output,
+        lipsync: lipsync.and_then(|level| level.to_possible_value()).map(|value| value.get_name().to_string()),
+    };
+    let Some(Response::Job(job)) = send(socket, &Request::Enqueue(spec)) else {
+        return;
+    };
+    output::record("job", job.id);
+    if !wait {
+        note!("{} queued", format!("[Job {}]", job.id).cyan());
+        return;
+    }
+    if let Some(Response::Job(job)) = send(socket, &Request::Wait { job: job.id }) {
+        print_job(&job);
+        output::record("state", job.state);
+        match job.state {
+            JobState::Done => {
+                output::record_file("audio", &job.spec.output);
+                if let Some(json) = &job.lipsync_json {
+                    output::record_file("lipsync", json);
+                }
+                for warning in &job.warnings {
+                    output::append("warnings", serde_json::json!({ "message": warning }));
+                }
+            }
+            _ => output::record_error(job.error.unwrap_or_else(|| format!("Job {} was cancelled", job.id))),
+        }
+    }
+}
+
+pub fn handle_status(job: Option<u64>, socket: Option<&str>) {
+    match send(socket, &Request::Status { job }) {
+        Some(Response::Job(job)) => {
+            print_job(&job);
+            output::record("jobs", [&job]);
+        }
+        Some(Response::Jobs(jobs)) => {
+            if jobs.is_empty() {
+                note!("No jobs");
+            }
+            jobs.iter().for_each(print_job);
+            output::record("jobs", &jobs);
+        }
+        _ => {}
+    }
+}
+
+pub fn handle_cancel(job: u64, socket: Option<&str>) {
+    if let Some(Response::Job(job)) = send(socket, &Request::Cancel { job }) {
+        output::record("job", job.id);
+        let stopped = if job.state == JobState::Running { "stopping" } else { "cancelled" };
+        note!("{} {}", format!("[Job {}]", job.id).cyan(), stopped);
+    }
+}
@@ -0,0 +1,24 @@
+use crate::output;
+use text_to_face::devices::output_devices;
+
+pub fn handle_devices(json: bool) {
+    let devices = match output_devices() {
+        Ok(devices) => devices,
+        Err(e) => {
+            fail!("Failed to list audio devices: {}", e);
+            return;
+        }
+    };
+    if json {
+        output::print_document(&devices);
+        return;
+    }
+    if devices.is_empty() {
+        note!("No audio output devices found");
+        return;
+    }
+    note!("Output devices (pass a name, or part of one, to --device):");
+    for device in devices {
+        note!("  {}{}", device.name, if device.default { " (default)" } else { "" });
+    }
+}
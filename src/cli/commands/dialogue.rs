@@ -0,0 +1,37 @@
+use crate::output;
+use colored::*;
+use std::time::Duration;
+use text_to_face::dialogue::{parse_script, synthesize_dialogue};
+use text_to_face::stereo::pan_wav;
+use text_to_face::write_wav;
+
+pub fn handle_dialogue(script_path: &str, output: &str, gap: Duration, pitch: f32, tempo: f32) {
+    let script = match std::fs::read_to_string(script_path).map_err(|e| e.to_string()).and_then(|script| parse_script(&script)) {
+        Ok(script) => script,
+        Err(e) => {
+            fail!("Failed to read script {}: {}", script_path, e);
+            return;
+        }
+    };
+    note!("Performing {} lines with {} characters", script.lines.len(), script.cast.len());
+    let (samples, sample_rate, spoken) = match synthesize_dialogue(&script, gap, pitch, tempo) {
+        Ok(performed) => performed,
+        Err(e) => {
+            fail!("Error: {}", e);
+            return;
+        }
+    };
+    for line in &spoken {
+        note!("{} {} {}", format!("[{:.2}s-{:.2}s]", line.start, line.end).cyan(), line.character, line.text);
+    }
+    output::record("lines", &spoken);
+    output::record("duration", samples.len() as f32 / sample_rate as f32);
+    match write_wav(output, &samples, sample_rate).and_then(|()| pan_wav(output)) {
+        Ok(()) => {
+            text_to_face::session::record_output(std::path::Path::new(output));
+            output::record_file("audio", output);
+            note!("Dialogue WAV written to {}", output)
+        }
+        Err(e) => fail!("Failed to write {}: {}", output, e),
+    }
+}
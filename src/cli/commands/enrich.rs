@@ -0,0 +1,35 @@
+use crate::output;
+use colored::*;
+use text_to_face::enrich::enrich_alignment;
+use crate::print_warnings;
+
+pub fn handle_enrich(input: &str, text: Option<&str>, output: Option<&str>, lipsync_with_llm: Option<&str>) {
+    let value: serde_json::Value = match std::fs::read_to_string(input).map_err(|e| e.to_string()).and_then(|s| serde_json::from_str(&s).map_err(|e| e.to_string())) {
+        Ok(value) => value,
+        Err(e) => {
+            fail!("Failed to read {}: {}", input, e);
+            return;
+        }
+    };
+    let (json, report) = match enrich_alignment(&value, text, lipsync_with_llm) {
+        Ok(enriched) => enriched,
+        Err(e) => {
+            fail!("Failed to enrich {}: {}", input, e);
+            return;
+        }
+    };
+    print_warnings(&report.warnings);
+    let enriched = serde_json::to_string_pretty(&json).unwrap();
+    match output {
+        Some(path) => match std::fs::write(path, enriched) {
+            Ok(()) => {
+                output::record_file("lipsync", path);
+                note!("{} Lipsync JSON with phonemes and visemes written to {}", "[Enrich]".cyan(), path)
+            }
+            Err(e) => fail!("Failed to write {}: {}", path, e),
+        },
+        // Under --json the enriched alignment is part of the result instead
+        None if output::is_json() => output::record("lipsync", &json),
+        None => note!("{}", enriched),
+    }
+}
@@ -1,15 +1,108 @@
-use text_to_face::{PitchArg, synthesize_and_handle};
+use crate::output;
+use text_to_face::{PitchArg, handle_processed_samples, synthesize_and_handle};
+use text_to_face::alignment::{set_transcript_alignment, transcript_alignment};
+use text_to_face::morph::{synthesize_morph, MorphSpec};
+use text_to_face::session;
+use text_to_face::sidecar::{is_up_to_date, sha256_file, write_sidecar, ExportSidecar};
+use text_to_face::blender::{shape_key_keyframes, write_blender_keyframes, DEFAULT_SHAPE_PREFIX};
+use text_to_face::gltf::{write_gltf_animation, BlendshapeSet};
+use text_to_face::unity::{unity_clip, write_unity_animation};
+use text_to_face::karaoke::write_karaoke_json;
+use text_to_face::lipsync_stream::{write_lipsync_stream, DEFAULT_CHUNK_SECONDS};
+use text_to_face::live2d::write_motion3_json;
+use text_to_face::markers::{markers, write_wav_markers, MarkerSet};
+use text_to_face::mixing::{load_music, mix_with_ducking, DuckSettings};
+use text_to_face::preview::write_preview_html;
+use text_to_face::prosody::{add_prosody_to_json, DEFAULT_FRAME_RATE};
+use text_to_face::directives::strip_directives;
+use text_to_face::emphasis::strip_emphasis;
+use text_to_face::pause_markers::strip_markers;
+use text_to_face::encode::{encode_wav, AudioFormat, EncodeSettings};
+use text_to_face::respelling::strip_respellings;
+use text_to_face::rhubarb::{mouth_cues, write_rhubarb};
+use text_to_face::stereo::{pan_wav, read_unpanned_wav, stereo_output};
+use text_to_face::subtitles::write_subtitles;
+use text_to_face::sprites::{sprite_track, write_sprite_track, SpriteLayout};
+use text_to_face::verify::write_transcript_report;
+use text_to_face::visemes::{add_scheme_track_to_json, viseme_track_from_json, VisemeScheme};
+use text_to_face::tags::{tag_wav, AudioTags};
+use text_to_face::takes::{set_variation, take_path, take_variation};
+use text_to_face::trim::{trim_export, trim_silence_export, TrimRange};
+use colored::*;
 use std::fs;
 use std::path::Path;
-use crate::LipsyncLevel;
+use crate::{print_warnings, LipsyncFormat, LipsyncLevel};
 
-pub fn handle_export(voice: &str, output: Option<&str>, text: &str, pitch: &PitchArg, tempo: f32, lipsync: LipsyncLevel, json_output: &str, lipsync_with_llm: Option<String>) {
+/// Extra outputs and behaviour for an export beyond the core synthesis parameters
+#[derive(Clone)]
+pub struct ExportOptions {
+    /// Tags embedded in the exported audio
+    pub tags: AudioTags,
+    /// Write a `.meta.json` sidecar
+    pub sidecar: bool,
+    /// Re-render even if the sidecar says the output is up to date
+    pub force: bool,
+    /// Write `<name>.karaoke.json` word highlight data
+    pub karaoke: bool,
+    /// Caption files to write, `.srt` or `.vtt`
+    pub subtitles: Vec<String>,
+    /// Longest caption line in characters
+    pub subtitle_line_length: usize,
+    /// Lipsync format written besides the lipsync JSON
+    pub lipsync_format: LipsyncFormat,
+    /// Write a self-contained `<name>.preview.html` review page
+    pub preview: bool,
+    /// Write `<name>.stream.ndjson` multiplexing audio chunks with word and viseme events
+    pub stream: bool,
+    /// Sprite sheet layout; writes a per-video-frame mouth sprite index track
+    pub sprite_layout: Option<String>,
+    /// Sprite track output path (`.csv` or `.json`, default `<name>.sprites.json`)
+    pub sprite_track: Option<String>,
+    /// Blender shape-key keyframes output (`.py` script or `.json`)
+    pub blender_keys: Option<String>,
+    /// Live2D `motion3.json` mouth animation output
+    pub live2d_motion: Option<String>,
+    /// Binary glTF (`.glb`) morph-target animation output
+    pub gltf: Option<String>,
+    /// Unity animation clip output (`.anim` or `.json`)
+    pub unity_anim: Option<String>,
+    /// Path from the Animator to the mesh the Unity clip keys
+    pub unity_mesh_path: String,
+    /// Blendshape names the glTF and Unity animations drive
+    pub blendshapes: BlendshapeSet,
+    /// Embed word and/or sentence markers in the WAV as cue points
+    pub markers: Option<MarkerSet>,
+    /// Blend two voices instead of synthesizing with `voice`
+    pub morph: Option<MorphSpec>,
+    /// Write `<name>.verify.json` comparing the recognized transcript with the input text
+    pub verify: bool,
+    /// Add F0 and energy contours to the lipsync JSON
+    pub prosody: bool,
+    /// Add a viseme track in this scheme to the lipsync JSON
+    pub viseme_scheme: Option<VisemeScheme>,
+    /// Background music to mix under the speech into `<name>.mix.wav`
+    pub music: Option<String>,
+    /// Music level and ducking for `music`
+    pub duck: DuckSettings,
+    /// Video frame rate for frame-based outputs
+    pub fps: f32,
+    /// Render this many variations into `<name>_take1.wav`, `<name>_take2.wav`, ...
+    pub takes: usize,
+    /// Part of the rendered audio (and lipsync) to keep
+    pub trim: TrimRange,
+    /// Cut leading and trailing audio quieter than this many dBFS, after `trim`
+    pub trim_silence: Option<f32>,
+    /// Format the audio is delivered in; other formats are encoded from the rendered WAV
+    pub encoding: EncodeSettings,
+}
+
+pub fn handle_export(voice: &str, output: Option<&str>, text: &str, pitch: &PitchArg, tempo: f32, lipsync: LipsyncLevel, json_output: &str, lipsync_with_llm: Option<String>, options: ExportOptions) {
     use std::path::PathBuf;
     let (wav_path, json_path): (PathBuf, PathBuf) = if let Some(path) = output {
         let p = Path::new(path);
-        if p.extension().map(|e| e == "wav").unwrap_or(false) {
-            // --output is a file path
-            let wav = p.to_path_buf();
+        if AudioFormat::from_path(p).is_some() {
+            // --output is a file path; other formats are encoded from a WAV rendered beside it
+            let wav = p.with_extension("wav");
             let base = p.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
             let dir = p.parent().unwrap_or_else(|| Path::new("."));
             let json = dir.join(format!("{}.json", base));
@@ -17,7 +110,7 @@ pub fn handle_export(voice: &str, output: Option<&str>, text: &str, pitch: &Pitc
         } else {
             // --output is a directory
             let dir = p;
-            let filename = generate_filename_from_text(text);
+            let filename = generate_filename_from_text(&spoken_text(text));
             let base = Path::new(&filename).file_stem().and_then(|s| s.to_str()).unwrap_or("output");
             let wav = dir.join(&filename);
             let json = dir.join(format!("{}.json", base));
@@ -25,7 +118,7 @@ pub fn handle_export(voice: &str, output: Option<&str>, text: &str, pitch: &Pitc
         }
     } else {
         // No output specified, use CWD
-        let filename = generate_filename_from_text(text);
+        let filename = generate_filename_from_text(&spoken_text(text));
         let base = Path::new(&filename).file_stem().and_then(|s| s.to_str()).unwrap_or("output");
         let wav = Path::new(&filename).to_path_buf();
         let json = Path::new(&format!("{}.json", base)).to_path_buf();
@@ -35,25 +128,455 @@ pub fn handle_export(voice: &str, output: Option<&str>, text: &str, pitch: &Pitc
     if let Some(parent) = wav_path.parent() {
         if !parent.exists() {
             if let Err(e) = fs::create_dir_all(parent) {
-                eprintln!("Failed to create output directory: {}", e);
+                fail!("Failed to create output directory: {}", e);
+                return;
+            }
+        }
+    }
+    // Word-timing based outputs need an alignment pass
+    let needs_timings = options.karaoke || !options.subtitles.is_empty() || options.lipsync_format != LipsyncFormat::Native || options.sprite_layout.is_some() || options.blender_keys.is_some() || options.live2d_motion.is_some() || options.gltf.is_some() || options.unity_anim.is_some() || options.markers.is_some() || options.verify || options.prosody || options.viseme_scheme.is_some() || options.music.is_some();
+    let lipsync = if needs_timings && lipsync == LipsyncLevel::Low {
+        note!("Word-timing outputs need lipsync data; enabling --lipsync high");
+        LipsyncLevel::High
+    } else if options.verify && matches!(lipsync, LipsyncLevel::Estimate | LipsyncLevel::Align | LipsyncLevel::Piper) {
+        // Estimated and built-in aligned timings come from the text itself, so there's no transcript to check
+        note!("--verify needs a recognized transcript; using --lipsync high instead of {:?}", lipsync);
+        LipsyncLevel::High
+    } else {
+        lipsync
+    };
+    if options.verify && transcript_alignment().is_some() {
+        // Aligning the input text would make every transcript match it
+        note!("--verify needs WhisperX's speech recognition; ignoring --align-only");
+        set_transcript_alignment(None);
+    }
+    if options.takes > 1 {
+        for take in 1..=options.takes {
+            let variation = take_variation(take);
+            note!("{}", format!("Take {}/{} ({})", take, options.takes, variation.describe()).bold());
+            // Explicit output paths get the take number too, so takes don't overwrite each other
+            let numbered = |path: &Option<String>| path.as_deref().map(|p| take_path(Path::new(p), take).to_string_lossy().to_string());
+            let take_options = ExportOptions {
+                tags: options.tags.clone().with_param("take", take),
+                sprite_track: numbered(&options.sprite_track),
+                blender_keys: numbered(&options.blender_keys),
+                live2d_motion: numbered(&options.live2d_motion),
+                gltf: numbered(&options.gltf),
+                unity_anim: numbered(&options.unity_anim),
+                subtitles: options.subtitles.iter().map(|p| take_path(Path::new(p), take).to_string_lossy().to_string()).collect(),
+                ..options.clone()
+            };
+            set_variation(Some(variation));
+            export_to(voice, text, pitch, tempo, lipsync, lipsync_with_llm.as_deref(), &take_options, &take_path(&wav_path, take), &take_path(&json_path, take));
+        }
+        set_variation(None);
+        return;
+    }
+    export_to(voice, text, pitch, tempo, lipsync, lipsync_with_llm.as_deref(), &options, &wav_path, &json_path);
+}
+
+/// Render one export to `wav_path`, with lipsync in `json_path` and the extra outputs beside them.
+/// The WAV is encoded into the requested format (beside it) once every output has been derived from it.
+#[allow(clippy::too_many_arguments)]
+fn export_to(voice: &str, text: &str, pitch: &PitchArg, tempo: f32, lipsync: LipsyncLevel, lipsync_with_llm: Option<&str>, options: &ExportOptions, wav_path: &Path, json_path: &Path) {
+    let lipsync_name = format!("{:?}", lipsync).to_lowercase();
+    let encoded = options.encoding.format != AudioFormat::Wav;
+    let audio_path = wav_path.with_extension(options.encoding.format.extension());
+    let mut meta = ExportSidecar::new(text, voice, pitch.as_factor(), tempo, &lipsync_name, audio_path.to_str().unwrap());
+    if !options.trim.is_whole() {
+        meta.effects.push(options.trim.describe());
+    }
+    if let Some(threshold) = options.trim_silence {
+        meta.effects.push(format!("trim_silence={}dB", threshold));
+    }
+    if stereo_output().is_stereo() {
+        meta.effects.push(stereo_output().describe());
+    }
+    if encoded {
+        meta.effects.push(options.encoding.describe());
+    }
+    // The tags, markers, music, and lipsync extras are written into the outputs too
+    meta.effects.extend(options.tags.describe());
+    meta.effects.extend(options.markers.map(|set| format!("markers={:?}", set).to_lowercase()));
+    if let Some(music) = &options.music {
+        let fingerprint = sha256_file(Path::new(music)).map(|hash| hash[..12].to_string()).unwrap_or_else(|_| music.clone());
+        meta.effects.push(format!("music={}", fingerprint));
+        meta.effects.push(options.duck.describe());
+    }
+    if options.prosody {
+        meta.effects.push("prosody".to_string());
+    }
+    meta.effects.extend(options.viseme_scheme.map(|scheme| format!("visemes={}", scheme.name())));
+    // A morph synthesizes with its two voices instead of `voice`
+    meta.effects.extend(options.morph.as_ref().map(|morph| format!("morph={}", morph.label())));
+    if !options.force && is_up_to_date(&audio_path, &meta) {
+        note!("{:?} is up to date, skipping (use --force to re-render)", audio_path);
+        output::append("skipped", audio_path.to_string_lossy());
+        return;
+    }
+    note!("Exporting voice: {} to {:?} (pitch: {}, tempo: {})", voice, audio_path, pitch.as_factor(), tempo);
+    let lipsync_json = if lipsync != LipsyncLevel::Low { Some(json_path.to_str().unwrap()) } else { None };
+    let result = if let Some(morph) = &options.morph {
+        synthesize_morph(text, morph, pitch, tempo).and_then(|(samples, sample_rate)| {
+            handle_processed_samples(text, &samples, sample_rate, pitch.as_factor(), tempo, wav_path.to_str(), false, lipsync, lipsync_json, lipsync_with_llm)
+        })
+    } else {
+        synthesize_and_handle(
+            text,
+            voice,
+            pitch,
+            tempo,
+            Some(wav_path.to_str().unwrap()), // Output WAV file
+            false, // Do not play audio
+            lipsync,
+            lipsync_json,
+            lipsync_with_llm,
+        )
+    };
+    let report = match result {
+        Ok(report) => report,
+        Err(e) => {
+            fail!("Error: {}", e);
+            return;
+        }
+    };
+    print_warnings(&report.warnings);
+    if wav_path.exists() && !options.trim.is_whole() {
+        let lipsync_json = lipsync_json.filter(|_| json_path.exists());
+        match trim_export(wav_path.to_str().unwrap(), lipsync_json, &options.trim) {
+            Ok(duration) => note!("Trimmed to {:.2}s from {:.2}s in", duration, options.trim.start),
+            Err(e) => {
+                fail!("Failed to trim export: {}", e);
+                return;
+            }
+        }
+    }
+    if let Some(threshold) = options.trim_silence.filter(|_| wav_path.exists()) {
+        let lipsync_json = lipsync_json.filter(|_| json_path.exists());
+        match trim_silence_export(wav_path.to_str().unwrap(), lipsync_json, threshold) {
+            Ok(range) if range.is_whole() => note!("No silence to trim below {} dB", threshold),
+            Ok(range) => note!("Trimmed {:.2}s of leading silence{}", range.start, range.max_duration.map(|d| format!(", kept {:.2}s", d)).unwrap_or_default()),
+            Err(e) => {
+                fail!("Failed to trim silence: {}", e);
                 return;
             }
         }
     }
-    println!("Exporting voice: {} to {:?} (pitch: {}, tempo: {})", voice, wav_path, pitch.as_factor(), tempo);
-    synthesize_and_handle(
-        text,
-        voice,
-        pitch,
-        tempo,
-        Some(wav_path.to_str().unwrap()), // Output WAV file
-        false, // Do not play audio
-        lipsync,
-        if lipsync != LipsyncLevel::Low { Some(json_path.to_str().unwrap()) } else { None },
-        lipsync_with_llm.as_deref(),
+    // Panned after trimming, and before tags and markers are appended to the file
+    if let Some(Err(e)) = wav_path.exists().then(|| pan_wav(wav_path.to_str().unwrap())) {
+        fail!("Failed to pan export: {}", e);
+        return;
+    }
+    if wav_path.exists() {
+        let tags = options.tags.clone().with_param("lipsync", &lipsync_name);
+        if let Err(e) = tag_wav(wav_path.to_str().unwrap(), &tags) {
+            fail!("Failed to write metadata tags: {}", e);
+        }
+        if let Some(set) = options.markers.filter(|_| !encoded) {
+            match write_markers(json_path.to_str().unwrap(), wav_path.to_str().unwrap(), set) {
+                Ok(count) => note!("{} markers embedded in {}", count, wav_path.display()),
+                Err(e) => fail!("Failed to embed markers: {}", e),
+            }
+        }
+        if options.prosody {
+            match add_prosody_to_json(wav_path.to_str().unwrap(), json_path.to_str().unwrap(), DEFAULT_FRAME_RATE) {
+                Ok(contour) => note!("Prosody contours ({} frames) added to {}", contour.frames.len(), json_path.display()),
+                Err(e) => fail!("Failed to add prosody contours: {}", e),
+            }
+        }
+        if let Some(scheme) = options.viseme_scheme {
+            match add_scheme_track_to_json(json_path.to_str().unwrap(), scheme) {
+                Ok(count) => note!("{} viseme track ({} events) added to {}", scheme.name(), count, json_path.display()),
+                Err(e) => fail!("Failed to add viseme track: {}", e),
+            }
+        }
+        if options.markers.is_some() && encoded {
+            note!("Markers are only embedded in WAV exports; skipping --markers for {}", options.encoding.format.extension());
+        }
+        if lipsync != LipsyncLevel::Low && json_path.exists() {
+            session::record_output(json_path);
+            output::record_file("lipsync", json_path);
+        }
+        if options.karaoke {
+            let karaoke_path = json_path.with_extension("karaoke.json");
+            match write_karaoke_json(&spoken_text(text), json_path.to_str().unwrap(), karaoke_path.to_str().unwrap()) {
+                Ok(()) => {
+                    output::record_file("karaoke", &karaoke_path);
+                    note!("Karaoke highlight data written to {}", karaoke_path.display());
+                }
+                Err(e) => fail!("Failed to write karaoke JSON: {}", e),
+            }
+        }
+        for subtitles_path in &options.subtitles {
+            match write_subtitles(&spoken_text(text), json_path.to_str().unwrap(), subtitles_path, options.subtitle_line_length) {
+                Ok(cues) => {
+                    session::record_output(Path::new(subtitles_path));
+                    output::record_file("subtitles", subtitles_path);
+                    note!("{} subtitle cues written to {}", cues, subtitles_path);
+                }
+                Err(e) => fail!("Failed to write subtitles: {}", e),
+            }
+        }
+        if options.lipsync_format == LipsyncFormat::Rhubarb {
+            let tsv_path = json_path.with_extension("rhubarb.tsv");
+            let rhubarb_path = json_path.with_extension("rhubarb.json");
+            match write_rhubarb_cues(json_path.to_str().unwrap(), wav_path.to_str().unwrap(), &audio_path, &[&tsv_path, &rhubarb_path]) {
+                Ok(()) => {
+                    output::record_file("rhubarb", &tsv_path);
+                    output::record_file("rhubarb", &rhubarb_path);
+                    note!("Rhubarb mouth cues written to {} and {}", tsv_path.display(), rhubarb_path.display());
+                }
+                Err(e) => fail!("Failed to write Rhubarb mouth cues: {}", e),
+            }
+        }
+        if let Some(layout_path) = &options.sprite_layout {
+            let track_path = options.sprite_track.clone().unwrap_or_else(|| wav_path.with_extension("sprites.json").to_string_lossy().to_string());
+            match write_sprites(layout_path, json_path.to_str().unwrap(), wav_path.to_str().unwrap(), &track_path, options.fps) {
+                Ok(()) => {
+                    output::record_file("sprites", &track_path);
+                    note!("Sprite track written to {}", track_path);
+                }
+                Err(e) => fail!("Failed to write sprite track: {}", e),
+            }
+        }
+        if let Some(keys_path) = &options.blender_keys {
+            let audio = audio_reference(wav_path, &audio_path);
+            let result = viseme_track_from_json(json_path.to_str().unwrap()).and_then(|visemes| {
+                let keyframes = shape_key_keyframes(&visemes, options.fps, 1, DEFAULT_SHAPE_PREFIX);
+                write_blender_keyframes(keys_path, &keyframes, options.fps, audio.as_deref())
+            });
+            match result {
+                Ok(()) => {
+                    output::record_file("blender", keys_path);
+                    note!("Blender shape-key keyframes written to {}", keys_path);
+                }
+                Err(e) => fail!("Failed to write Blender keyframes: {}", e),
+            }
+        }
+        if let Some(motion_path) = &options.live2d_motion {
+            match write_live2d(json_path.to_str().unwrap(), wav_path.to_str().unwrap(), motion_path, options.fps) {
+                Ok(()) => {
+                    output::record_file("live2d", motion_path);
+                    note!("Live2D motion written to {}", motion_path);
+                }
+                Err(e) => fail!("Failed to write Live2D motion: {}", e),
+            }
+        }
+        if let Some(gltf_path) = &options.gltf {
+            match write_gltf(json_path.to_str().unwrap(), wav_path, audio_reference(wav_path, &audio_path).as_deref(), gltf_path, options.blendshapes) {
+                Ok(()) => {
+                    output::record_file("gltf", gltf_path);
+                    note!("glTF lipsync animation written to {}", gltf_path);
+                }
+                Err(e) => fail!("Failed to write glTF animation: {}", e),
+            }
+        }
+        if let Some(anim_path) = &options.unity_anim {
+            match write_unity(json_path.to_str().unwrap(), wav_path, anim_path, options) {
+                Ok(()) => {
+                    output::record_file("unity", anim_path);
+                    note!("Unity animation clip written to {}", anim_path);
+                }
+                Err(e) => fail!("Failed to write Unity animation: {}", e),
+            }
+        }
+        if options.verify {
+            let report_path = json_path.with_extension("verify.json");
+            match write_transcript_report(&spoken_text(text), json_path.to_str().unwrap(), report_path.to_str().unwrap()) {
+                Ok(report) if report.is_clean() => {
+                    output::record_file("verify", &report_path);
+                    note!("Transcript verified: all {} words match", report.input_words);
+                }
+                Ok(report) => {
+                    output::record_file("verify", &report_path);
+                    note!("Transcript mismatches ({} of {} words), report written to {}:", report.mismatches.len(), report.input_words, report_path.display());
+                    for m in &report.mismatches {
+                        note!(
+                            "  {:>7.2}s {:?}: expected {:?}, heard {:?}",
+                            m.start,
+                            m.kind,
+                            m.expected.as_deref().unwrap_or("-"),
+                            m.recognized.as_deref().unwrap_or("-")
+                        );
+                    }
+                }
+                Err(e) => fail!("Failed to verify transcript: {}", e),
+            }
+        }
+        if let Some(music_path) = &options.music {
+            let mix_path = wav_path.with_extension("mix.wav");
+            match write_mix(music_path, json_path.to_str().unwrap(), wav_path.to_str().unwrap(), mix_path.to_str().unwrap(), &options.duck) {
+                Ok(()) => {
+                    if let Err(e) = tag_wav(mix_path.to_str().unwrap(), &tags) {
+                        fail!("Failed to write metadata tags: {}", e);
+                    }
+                    let mix_audio = mix_path.with_extension(options.encoding.format.extension());
+                    match deliver(&mix_path, &mix_audio, &options.encoding, &tags) {
+                        Ok(()) => {
+                            session::record_output(&mix_audio);
+                            output::record_file("mix", &mix_audio);
+                            note!("Mix with ducked music written to {}", mix_audio.display());
+                        }
+                        Err(e) => fail!("Failed to encode mix: {}", e),
+                    }
+                }
+                Err(e) => fail!("Failed to mix music: {}", e),
+            }
+        }
+        if options.preview {
+            let preview_path = wav_path.with_extension("preview.html");
+            let lipsync_json = if lipsync != LipsyncLevel::Low && json_path.exists() { json_path.to_str() } else { None };
+            match write_preview_html(preview_path.to_str().unwrap(), wav_path.to_str().unwrap(), &spoken_text(text), lipsync_json) {
+                Ok(()) => {
+                    output::record_file("preview", &preview_path);
+                    note!("Preview page written to {}", preview_path.display());
+                }
+                Err(e) => fail!("Failed to write preview page: {}", e),
+            }
+        }
+        if options.stream {
+            let stream_path = wav_path.with_extension("stream.ndjson");
+            let lipsync_json = if lipsync != LipsyncLevel::Low && json_path.exists() { json_path.to_str() } else { None };
+            match write_lipsync_stream(stream_path.to_str().unwrap(), wav_path.to_str().unwrap(), lipsync_json, &strip_emphasis(&strip_markers(&strip_directives(text))), DEFAULT_CHUNK_SECONDS) {
+                Ok(frames) => {
+                    session::record_output(&stream_path);
+                    output::record_file("stream", &stream_path);
+                    note!("Lipsync stream ({} frames) written to {}", frames, stream_path.display());
+                }
+                Err(e) => fail!("Failed to write lipsync stream: {}", e),
+            }
+        }
+        if output::is_json() {
+            record_export(voice, wav_path, &audio_path, json_path.exists().then_some(json_path));
+        }
+        if let Err(e) = deliver(wav_path, &audio_path, &options.encoding, &tags) {
+            fail!("Failed to encode {}: {}", audio_path.display(), e);
+            return;
+        }
+        if encoded {
+            note!("Encoded {}", audio_path.display());
+        }
+        session::record_output(&audio_path);
+        output::record_file("audio", &audio_path);
+        if options.sidecar {
+            meta.finalize(if lipsync != LipsyncLevel::Low { json_path.to_str() } else { None });
+            meta.warnings = report.warnings;
+            match write_sidecar(&audio_path, &meta) {
+                Ok(path) => {
+                    output::record_file("sidecar", &path);
+                    note!("Sidecar written to {}", path.display());
+                }
+                Err(e) => fail!("Failed to write sidecar: {}", e),
+            }
+        }
+    }
+}
+
+/// The words as spoken: no respellings, voice directives, pause markers, or emphasis markup
+fn spoken_text(text: &str) -> String {
+    strip_respellings(&strip_emphasis(&strip_markers(&strip_directives(text))))
+}
+
+/// Describe one rendered export under `exports` in the `--json` result: where the audio went, how
+/// long it is, and its word timings when there's lipsync
+fn record_export(voice: &str, wav_path: &Path, audio_path: &Path, json_path: Option<&Path>) {
+    let spec = hound::WavReader::open(wav_path).map(|reader| (reader.duration(), reader.spec().sample_rate));
+    let (frames, sample_rate) = spec.unwrap_or((0, 0));
+    let words = json_path.and_then(|path| text_to_face::read_word_timings(path.to_str()?).ok());
+    output::append(
+        "exports",
+        serde_json::json!({
+            "voice": voice,
+            "audio": audio_path.to_string_lossy(),
+            "lipsync": json_path.map(|path| path.to_string_lossy()),
+            "duration": if sample_rate > 0 { frames as f32 / sample_rate as f32 } else { 0.0 },
+            "sample_rate": sample_rate,
+            "words": words,
+        }),
     );
 }
 
+/// Encode a rendered WAV into `audio_path` and remove the WAV; a no-op for WAV exports
+fn deliver(wav_path: &Path, audio_path: &Path, encoding: &EncodeSettings, tags: &AudioTags) -> Result<(), Box<dyn std::error::Error>> {
+    if encoding.format == AudioFormat::Wav {
+        return Ok(());
+    }
+    encode_wav(wav_path, audio_path, encoding, tags)?;
+    fs::remove_file(wav_path)?;
+    Ok(())
+}
+
+/// Absolute path of the delivered audio, for outputs that reference it (the WAV itself may be encoded away)
+fn audio_reference(wav_path: &Path, audio_path: &Path) -> Option<String> {
+    let absolute = fs::canonicalize(wav_path).ok()?;
+    Some(absolute.with_file_name(audio_path.file_name()?).to_string_lossy().to_string())
+}
+
+/// Build the per-video-frame sprite index track from the lipsync JSON
+fn write_sprites(layout_path: &str, json_path: &str, wav_path: &str, track_path: &str, fps: f32) -> Result<(), Box<dyn std::error::Error>> {
+    let layout = SpriteLayout::from_file(layout_path)?;
+    let visemes = viseme_track_from_json(json_path)?;
+    let (samples, sample_rate) = text_to_face::read_wav(wav_path)?;
+    let duration = samples.len() as f32 / sample_rate as f32;
+    write_sprite_track(track_path, &sprite_track(&visemes, &layout, fps, duration), fps)
+}
+
+/// Build Rhubarb mouth cues from the lipsync JSON, lasting as long as the audio, into each of `paths`
+fn write_rhubarb_cues(json_path: &str, wav_path: &str, audio_path: &Path, paths: &[&Path]) -> Result<(), Box<dyn std::error::Error>> {
+    let words = text_to_face::read_word_timings(json_path)?;
+    let (samples, sample_rate) = text_to_face::read_wav(wav_path)?;
+    let duration = samples.len() as f32 / sample_rate as f32;
+    let cues = mouth_cues(&words, duration);
+    for path in paths {
+        write_rhubarb(path.to_str().unwrap(), &cues, &audio_path.to_string_lossy(), duration)?;
+    }
+    Ok(())
+}
+
+/// Build the Live2D mouth motion from the lipsync JSON, lasting as long as the audio
+fn write_live2d(json_path: &str, wav_path: &str, motion_path: &str, fps: f32) -> Result<(), Box<dyn std::error::Error>> {
+    let visemes = viseme_track_from_json(json_path)?;
+    let (samples, sample_rate) = text_to_face::read_wav(wav_path)?;
+    let duration = samples.len() as f32 / sample_rate as f32;
+    write_motion3_json(motion_path, &visemes, duration, fps)
+}
+
+/// Build the glTF morph-target animation from the lipsync JSON, lasting as long as the audio
+fn write_gltf(json_path: &str, wav_path: &Path, audio: Option<&str>, gltf_path: &str, blendshapes: BlendshapeSet) -> Result<(), Box<dyn std::error::Error>> {
+    let visemes = viseme_track_from_json(json_path)?;
+    let (samples, sample_rate) = text_to_face::read_wav(wav_path.to_str().unwrap())?;
+    let duration = samples.len() as f32 / sample_rate as f32;
+    write_gltf_animation(gltf_path, &visemes, blendshapes, duration, audio)
+}
+
+/// Build the Unity animation clip from the lipsync JSON, lasting as long as the audio and named after it
+fn write_unity(json_path: &str, wav_path: &Path, anim_path: &str, options: &ExportOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let visemes = viseme_track_from_json(json_path)?;
+    let (samples, sample_rate) = text_to_face::read_wav(wav_path.to_str().unwrap())?;
+    let duration = samples.len() as f32 / sample_rate as f32;
+    let name = wav_path.file_stem().and_then(|s| s.to_str()).unwrap_or("lipsync");
+    write_unity_animation(anim_path, &unity_clip(&visemes, options.blendshapes, duration, options.fps, name, &options.unity_mesh_path))
+}
+
+/// Embed the lipsync JSON's word and/or sentence starts in the exported WAV as cue points; returns how many
+fn write_markers(json_path: &str, wav_path: &str, set: MarkerSet) -> Result<usize, Box<dyn std::error::Error>> {
+    let words = text_to_face::read_word_timings(json_path)?;
+    let sample_rate = hound::WavReader::open(wav_path)?.spec().sample_rate;
+    let markers = markers(&words, sample_rate, set);
+    write_wav_markers(wav_path, &markers)?;
+    Ok(markers.len())
+}
+
+/// Mix the music under the exported speech, ducking it wherever the lipsync JSON has a word
+fn write_mix(music_path: &str, json_path: &str, wav_path: &str, mix_path: &str, duck: &DuckSettings) -> Result<(), Box<dyn std::error::Error>> {
+    let timings = text_to_face::read_word_timings(json_path)?;
+    let (speech, sample_rate) = read_unpanned_wav(wav_path)?;
+    let music = load_music(music_path, sample_rate)?;
+    text_to_face::write_wav(mix_path, &mix_with_ducking(&speech, &music, sample_rate, &timings, duck), sample_rate)?;
+    pan_wav(mix_path)?;
+    Ok(())
+}
+
 /// Clean a string for use as a folder name (alphanumeric and underscores only)
 fn clean_for_folder(s: &str) -> String {
     s.chars()
@@ -0,0 +1,49 @@
+use crate::output;
+use text_to_face::inspect::{inspect_file, Inspection};
+
+fn format_db(db: f32) -> String {
+    if db.is_finite() { format!("{:.1} dBFS", db) } else { "silent".to_string() }
+}
+
+pub fn handle_inspect(files: &[String], as_json: bool) {
+    let mut results = serde_json::Map::new();
+    for path in files {
+        let inspection = match inspect_file(path) {
+            Ok(inspection) => inspection,
+            Err(e) => {
+                fail!("Failed to inspect {}: {}", path, e);
+                continue;
+            }
+        };
+        if as_json {
+            results.insert(path.clone(), serde_json::to_value(&inspection).unwrap());
+            continue;
+        }
+        note!("{}", path);
+        match inspection {
+            Inspection::Audio(stats) => {
+                note!("  Duration:       {:.3}s", stats.duration);
+                note!("  Sample rate:    {} Hz", stats.sample_rate);
+                note!("  Format:         {} channel(s), {}-bit", stats.channels, stats.bits_per_sample);
+                note!("  Peak:           {}", format_db(stats.peak_dbfs));
+                note!("  RMS:            {}", format_db(stats.rms_dbfs));
+            }
+            Inspection::Json(stats) => {
+                note!("  Kind:           {}", stats.kind);
+                note!("  Schema version: {}", stats.schema_version.as_deref().unwrap_or("unversioned"));
+                if let Some(words) = stats.word_count {
+                    note!("  Words:          {}", words);
+                }
+                if let Some(duration) = stats.duration {
+                    note!("  Duration:       {:.3}s", duration);
+                }
+                if let Some(coverage) = stats.phoneme_coverage {
+                    note!("  Phonemes:       {:.0}% of words", coverage * 100.0);
+                }
+            }
+        }
+    }
+    if as_json {
+        output::print_document(&results);
+    }
+}
@@ -0,0 +1,114 @@
+use colored::*;
+use std::io::{BufRead, IsTerminal, Write};
+use std::str::FromStr;
+use text_to_face::playback::{SpeakMode, Speaker};
+use text_to_face::rate::rated_tempo;
+use text_to_face::rules::reload_rules;
+use text_to_face::PitchArg;
+
+const HELP: &str = "\
+/voice [ID]     switch voice (without an ID, show the current one)
+/pitch [X]      pitch factor or preset, e.g. 1.2 or deep
+/tempo [X]      tempo factor (2.0 = slower, 0.5 = faster)
+/stop           stop speaking
+/reload         reload the rules file and the voice model
+/help           show this list
+/quit           exit (or Ctrl-D)";
+
+/// The settings the REPL shows and changes
+struct Settings {
+    voice: String,
+    pitch: PitchArg,
+    tempo: f32,
+}
+
+fn prompt() {
+    if std::io::stdin().is_terminal() && !crate::output::is_json() {
+        print!("{} ", ">".cyan());
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Run a `/command`. Returns `false` to quit.
+fn command(line: &str, speaker: &Speaker, settings: &mut Settings) -> bool {
+    let (name, argument) = line.split_once(char::is_whitespace).map_or((line, ""), |(name, argument)| (name, argument.trim()));
+    match (name, argument) {
+        ("/quit" | "/exit", _) => return false,
+        ("/help", _) => note!("{}", HELP),
+        ("/stop", _) => speaker.stop(),
+        ("/reload", _) => {
+            match reload_rules() {
+                Ok(count) => note!("Reloaded rules ({} active)", count),
+                Err(e) => fail!("Failed to reload rules, keeping the previous ones: {}", e),
+            }
+            match speaker.reload() {
+                Ok(()) => note!("Reloaded voice model"),
+                Err(e) => fail!("Failed to reload voice, keeping the previous one: {}", e),
+            }
+        }
+        ("/voice", "") => note!("Voice: {}", settings.voice),
+        ("/voice", voice) => match speaker.set_voice(voice) {
+            Ok(()) => {
+                settings.voice = voice.to_string();
+                note!("Voice: {}", voice);
+            }
+            Err(e) => fail!("Failed to load {}, keeping {}: {}", voice, settings.voice, e),
+        },
+        ("/pitch", "") => note!("Pitch: {}", settings.pitch.as_factor()),
+        ("/pitch", pitch) => match PitchArg::from_str(pitch) {
+            Ok(pitch) => {
+                speaker.set_pitch(&pitch);
+                note!("Pitch: {}", pitch.as_factor());
+                settings.pitch = pitch;
+            }
+            Err(e) => fail!("{}", e),
+        },
+        ("/tempo", "") => note!("Tempo: {}", settings.tempo),
+        ("/tempo", tempo) => match tempo.parse::<f32>() {
+            Ok(tempo) if tempo > 0.0 => {
+                // Adjusted for --rate, like the starting tempo
+                speaker.set_tempo(rated_tempo(tempo));
+                settings.tempo = tempo;
+                note!("Tempo: {}", tempo);
+            }
+            _ => fail!("Tempo must be a positive number, got {}", tempo),
+        },
+        (name, _) => fail!("Unknown command {}; type /help for the list", name),
+    }
+    true
+}
+
+pub fn handle_interactive(voice: &str, pitch: &PitchArg, tempo: f32, mode: SpeakMode) {
+    let speaker = match Speaker::new(voice, pitch.clone(), rated_tempo(tempo)) {
+        Ok(speaker) => speaker,
+        Err(e) => {
+            fail!("Error: {}", e);
+            return;
+        }
+    };
+    let mut settings = Settings { voice: voice.to_string(), pitch: pitch.clone(), tempo };
+    note!("Speaking each line with voice {}; type /help for commands, /quit or Ctrl-D to exit", voice);
+    prompt();
+    for line in std::io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                fail!("Failed to read stdin: {}", e);
+                break;
+            }
+        };
+        match line.trim() {
+            "" => {}
+            line if line.starts_with('/') => {
+                if !command(line, &speaker, &mut settings) {
+                    // Quitting stops speech rather than waiting for it
+                    speaker.stop();
+                    return;
+                }
+            }
+            text => speaker.speak(text, mode),
+        }
+        prompt();
+    }
+    speaker.wait();
+}
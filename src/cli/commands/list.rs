@@ -1,62 +1,84 @@
-use text_to_face::{get_available_voices, get_voices_by_language, get_models_dir, Voice};
-use serde_json;
-use std::fs;
+use crate::output;
+use text_to_face::{get_available_voices, voice_capabilities, Voice, VoiceCapabilities, VoiceFilter};
+use serde::Serialize;
+use std::collections::HashMap;
 
-fn is_voice_installed(voice: &Voice) -> bool {
-    let models_dir = get_models_dir();
-    let model_path = models_dir.join(format!("{}.onnx", voice.id));
-    let config_path = models_dir.join(format!("{}.onnx.json", voice.id));
-    model_path.exists() && config_path.exists()
+/// A voice with its capabilities, as listed in `list --json`
+#[derive(Serialize)]
+struct VoiceListing {
+    #[serde(flatten)]
+    voice: Voice,
+    #[serde(flatten)]
+    capabilities: VoiceCapabilities,
 }
 
-pub fn handle_list(by_language: bool, as_json: bool, installed: bool, not_installed: bool) {
-    let all_voices = get_available_voices();
-    let filtered_voices: Vec<Voice> = if installed {
-        all_voices.iter().cloned().filter(|v| is_voice_installed(v)).collect()
-    } else if not_installed {
-        all_voices.iter().cloned().filter(|v| !is_voice_installed(v)).collect()
-    } else {
-        all_voices.clone()
-    };
+fn format_size(bytes: Option<u64>) -> String {
+    bytes.map(|b| format!("{:.1} MB", b as f64 / 1_000_000.0)).unwrap_or_else(|| "-".to_string())
+}
+
+fn print_table(listings: &[VoiceListing], indent: &str) {
+    note!("{}{:<38} {:<18} {:>6} {:>8} {:>9} {:>9} {:>8}", indent, "VOICE", "LANGUAGE", "RATE", "SPEAKERS", "INSTALLED", "SIZE", "LIPSYNC");
+    for listing in listings {
+        let capabilities = &listing.capabilities;
+        note!(
+            "{}{:<38} {:<18} {:>6} {:>8} {:>9} {:>9} {:>8}",
+            indent,
+            listing.voice.id,
+            listing.voice.language,
+            capabilities.sample_rate,
+            capabilities.speakers.map(|n| n.to_string()).unwrap_or_else(|| "?".to_string()),
+            if capabilities.installed { "yes" } else { "no" },
+            format_size(capabilities.size_bytes),
+            capabilities.lipsync
+        );
+    }
+}
+
+pub fn handle_list(by_language: bool, as_json: bool, installed: bool, not_installed: bool, filter: &VoiceFilter) {
+    let listings: Vec<VoiceListing> = get_available_voices()
+        .into_iter()
+        .filter(|voice| filter.matches(voice))
+        .map(|voice| VoiceListing { capabilities: voice_capabilities(&voice), voice })
+        .filter(|l| if installed { l.capabilities.installed } else if not_installed { !l.capabilities.installed } else { true })
+        .collect();
 
     if as_json {
         if by_language {
             // Group filtered voices by language
-            use std::collections::HashMap;
-            let mut by_lang: HashMap<String, Vec<Voice>> = HashMap::new();
-            for v in &filtered_voices {
-                by_lang.entry(v.language.clone()).or_default().push(v.clone());
+            let mut by_lang: HashMap<String, Vec<&VoiceListing>> = HashMap::new();
+            for listing in &listings {
+                by_lang.entry(listing.voice.language.clone()).or_default().push(listing);
             }
-            println!("{}", serde_json::to_string_pretty(&by_lang).unwrap());
+            output::print_document(&by_lang);
         } else {
-            println!("{}", serde_json::to_string_pretty(&filtered_voices).unwrap());
+            output::print_document(&listings);
         }
         return;
     }
 
+    if listings.is_empty() {
+        note!("No voices match; `list` without filters shows the whole catalog");
+        return;
+    }
     if by_language {
-        println!("Available voices by language:");
-        use std::collections::HashMap;
-        let mut by_lang: HashMap<String, Vec<Voice>> = HashMap::new();
-        for v in &filtered_voices {
-            by_lang.entry(v.language.clone()).or_default().push(v.clone());
+        note!("Available voices by language:");
+        let mut by_lang: HashMap<String, Vec<VoiceListing>> = HashMap::new();
+        for listing in listings {
+            by_lang.entry(listing.voice.language.clone()).or_default().push(listing);
         }
         for (language, voices) in by_lang.iter() {
-            println!("\n{}:", language);
-            for voice in voices {
-                println!("  {} - {}", voice.id, voice.quality);
-            }
+            note!("\n{}:", language);
+            print_table(voices, "  ");
         }
     } else {
         if installed {
-            println!("Installed voices:");
+            note!("Installed voices:");
         } else if not_installed {
-            println!("Not installed voices:");
+            note!("Not installed voices:");
         } else {
-            println!("Available voices:");
-        }
-        for voice in &filtered_voices {
-            println!("  {} - {} ({})", voice.id, voice.display_name, voice.language);
+            note!("Available voices:");
         }
+        print_table(&listings, "  ");
     }
-} 
\ No newline at end of file
+    note!("\nRATE is in Hz; SPEAKERS is known once a voice is installed. LIPSYNC is what --lipsync high gives for the voice's language: aligned words with ARPAbet phonemes, or aligned words only.");
+}
@@ -0,0 +1,48 @@
+use std::io::BufRead;
+use text_to_face::playback::{SpeakMode, Speaker};
+use text_to_face::rules::reload_rules;
+use text_to_face::PitchArg;
+
+/// Command that stops speech without saying anything new
+const STOP_COMMAND: &str = "/stop";
+/// Command that reloads the rules file and the voice model without restarting
+const RELOAD_COMMAND: &str = "/reload";
+
+fn reload(speaker: &Speaker) {
+    match reload_rules() {
+        Ok(count) => note!("Reloaded rules ({} active)", count),
+        Err(e) => fail!("Failed to reload rules, keeping the previous ones: {}", e),
+    }
+    match speaker.reload() {
+        Ok(()) => note!("Reloaded voice model"),
+        Err(e) => fail!("Failed to reload voice, keeping the previous one: {}", e),
+    }
+}
+
+pub fn handle_read(voice: &str, pitch: &PitchArg, tempo: f32, mode: SpeakMode) {
+    let speaker = match Speaker::new(voice, pitch.clone(), tempo) {
+        Ok(speaker) => speaker,
+        Err(e) => {
+            fail!("Error: {}", e);
+            return;
+        }
+    };
+    note!("Reading stdin with voice {} ({:?} mode); type {} to stop speaking, {} to reload rules and voice, Ctrl-D to finish", voice, mode, STOP_COMMAND, RELOAD_COMMAND);
+    for line in std::io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                fail!("Failed to read stdin: {}", e);
+                break;
+            }
+        };
+        match line.trim() {
+            "" => {}
+            STOP_COMMAND => speaker.stop(),
+            RELOAD_COMMAND => reload(&speaker),
+            text => speaker.speak(text, mode),
+        }
+    }
+    // Let whatever was queued finish before exiting
+    speaker.wait();
+}
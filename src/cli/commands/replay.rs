@@ -0,0 +1,63 @@
+use crate::output;
+use crate::{apply_global_options, run, Cli, Commands};
+use clap::Parser;
+use colored::*;
+use std::path::Path;
+use text_to_face::session::{self, compare_entries, read_session};
+
+pub fn handle_replay(log: &str, only: Option<usize>) {
+    let entries = match read_session(Path::new(log)) {
+        Ok(entries) => entries,
+        Err(e) => {
+            fail!("Failed to read session log: {}", e);
+            return;
+        }
+    };
+    if let Some(n) = only
+        && (n == 0 || n > entries.len())
+    {
+        fail!("{} has {} entries; --entry {} is out of range", log, entries.len(), n);
+        return;
+    }
+    let mut identical = 0;
+    let mut replayed = 0;
+    for (i, entry) in entries.iter().enumerate().filter(|(i, _)| only.is_none_or(|n| n == i + 1)) {
+        note!("{} {}", format!("[{}/{}]", i + 1, entries.len()).cyan(), entry.args.iter().skip(1).cloned().collect::<Vec<_>>().join(" "));
+        let mut cli = match Cli::try_parse_from(&entry.args) {
+            Ok(cli) => cli,
+            Err(e) => {
+                fail!("Can't parse the logged command line: {}", e);
+                continue;
+            }
+        };
+        cli.session_log = None;
+        match &mut cli.command {
+            Some(Commands::Replay { .. }) => continue,
+            // Re-render even though the logged outputs are up to date
+            Some(Commands::Export { force, .. } | Commands::Audiobook { force, .. }) => *force = true,
+            _ => {}
+        }
+        if let Err(e) = apply_global_options(&cli) {
+            fail!("{}", e);
+            continue;
+        }
+        session::start(None, entry.args.clone());
+        run(&cli);
+        let Ok(Some(result)) = session::finish() else {
+            continue;
+        };
+        replayed += 1;
+        let differences = compare_entries(entry, &result);
+        output::append("replays", serde_json::json!({ "entry": i + 1, "args": entry.args, "differences": differences }));
+        if differences.is_empty() {
+            identical += 1;
+            note!("  {}", "identical".green());
+        }
+        for difference in differences {
+            note!("  {}", difference.yellow());
+        }
+    }
+    output::record("replayed", replayed);
+    output::record("identical", identical);
+    note!("{} of {} replayed entries produced identical audio", identical, replayed);
+}
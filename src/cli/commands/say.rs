@@ -1,9 +1,62 @@
-use text_to_face::{PitchArg, synthesize_and_handle};
-use crate::LipsyncLevel;
+use crate::output;
+use text_to_face::{PitchArg, handle_processed_samples, synthesize_and_handle, synthesize_ssml};
+use text_to_face::morph::{synthesize_morph, MorphSpec};
+use text_to_face::low_memory;
+use text_to_face::playback::play_streamed;
+use text_to_face::directives::{has_directives, strip_directives};
+use text_to_face::emphasis::{has_emphasis, strip_emphasis};
+use text_to_face::pause_markers::{has_pauses, strip_markers};
+use text_to_face::streaming::synth_streaming;
+use text_to_face::vmc::{estimated_viseme_track, stream_lipsync, VmcSender};
+use crate::{print_lipsync, print_report, LipsyncLevel};
+use std::time::Instant;
 
-pub fn handle_say(voice: &str, text: &str, pitch: &PitchArg, tempo: f32, lipsync: LipsyncLevel) {
-    println!("Playing voice: {} (pitch: {})", voice, pitch.as_factor());
-    synthesize_and_handle(
+#[allow(clippy::too_many_arguments)]
+/// `streamed` plays each sentence as soon as it's synthesized, for long piped input
+pub fn handle_say(voice: &str, text: &str, pitch: &PitchArg, tempo: f32, lipsync: LipsyncLevel, vmc: Option<&str>, morph: Option<&MorphSpec>, ssml: bool, streamed: bool) {
+    if ssml {
+        note!("Playing SSML with voice: {} (pitch: {})", voice, pitch.as_factor());
+        match synthesize_ssml(text, voice, pitch, tempo) {
+            Ok((samples, sample_rate, spoken)) => match handle_processed_samples(&spoken, &samples, sample_rate, pitch.as_factor(), tempo, None, true, lipsync, None, None) {
+                Ok(report) => print_report(&report),
+                Err(e) => fail!("Error: {}", e),
+            },
+            Err(e) => fail!("Error: {}", e),
+        }
+        return;
+    }
+    if let Some(morph) = morph {
+        note!("Playing morph: {} (pitch: {})", morph.label(), pitch.as_factor());
+        match synthesize_morph(text, morph, pitch, tempo) {
+            Ok((samples, sample_rate)) => {
+                match handle_processed_samples(text, &samples, sample_rate, pitch.as_factor(), tempo, None, true, lipsync, None, None) {
+                    Ok(report) => print_report(&report),
+                    Err(e) => fail!("Error: {}", e),
+                }
+            }
+            Err(e) => fail!("Error: {}", e),
+        }
+        return;
+    }
+    note!("Playing voice: {} (pitch: {})", voice, pitch.as_factor());
+    output::record("voice", voice);
+    if let Some(address) = vmc {
+        say_with_vmc(voice, text, pitch, tempo, lipsync, address);
+        return;
+    }
+    // Paragraph pauses need the whole text too, so piped input with --paragraph-pause plays all at once
+    let streamed = streamed && text_to_face::pauses::pauses().paragraph.is_none();
+    if (low_memory::is_enabled() || streamed) && lipsync == LipsyncLevel::Low && !has_directives(text) && !has_pauses(text) && !has_emphasis(text) {
+        // Alignment needs the whole utterance, so only plain playback is streamed; directives switch voices
+        // and pause markers and emphasis markup need the whole text
+        let play = if low_memory::is_enabled() { low_memory::say_streamed } else { play_streamed };
+        let result = synth_streaming(text, voice, pitch, tempo).and_then(play);
+        if let Err(e) = result {
+            fail!("Error: {}", e);
+        }
+        return;
+    }
+    let result = synthesize_and_handle(
         text,
         voice,
         pitch,
@@ -11,7 +64,64 @@ pub fn handle_say(voice: &str, text: &str, pitch: &PitchArg, tempo: f32, lipsync
         None, // No output WAV
         true, // Play audio
         lipsync,
-        None, // Print lipsync JSON to terminal if lipsync is true
+        None, // Lipsync JSON comes back in the report and is printed
         None, // lipsync_with_llm: not used in 'say' command
     );
-} 
\ No newline at end of file
+    match result {
+        Ok(report) => print_report(&report),
+        Err(e) => fail!("Error: {}", e),
+    }
+}
+
+/// Play the speech while streaming mouth blendshapes to a VMC receiver (VSeeFace, VTube Studio, ...)
+fn say_with_vmc(voice: &str, text: &str, pitch: &PitchArg, tempo: f32, lipsync: LipsyncLevel, address: &str) {
+    let sender = match VmcSender::connect(address) {
+        Ok(sender) => sender,
+        Err(e) => {
+            fail!("Failed to connect to VMC receiver at {}: {}", address, e);
+            return;
+        }
+    };
+    let (samples, sample_rate) = match text_to_face::synthesize_processed(text, voice, pitch, tempo) {
+        Ok(synthesized) => synthesized,
+        Err(e) => {
+            fail!("Error: {}", e);
+            return;
+        }
+    };
+    let text = &strip_emphasis(&strip_markers(&strip_directives(text)));
+    // Alignment runs after playback, so the live mouth follows phonemes estimated from the text
+    let visemes = estimated_viseme_track(&text_to_face::text_to_arpabet(text, None), &samples, sample_rate);
+    note!("Streaming VMC blendshapes to {}", address);
+    match text_to_face::devices::open_output_stream() {
+        Ok((_stream, handle)) => match rodio::Sink::try_new(&handle) {
+            Ok(sink) => {
+                sink.append(text_to_face::stereo::playback_buffer(&samples, sample_rate));
+                if let Err(e) = stream_lipsync(&sender, &visemes, &samples, sample_rate, Instant::now()) {
+                    fail!("VMC streaming failed: {}", e);
+                }
+                sink.sleep_until_end();
+            }
+            Err(e) => fail!("Failed to start audio playback: {}", e),
+        },
+        Err(e) => fail!("No audio output device: {}", e),
+    }
+    if matches!(lipsync, LipsyncLevel::Estimate | LipsyncLevel::Align | LipsyncLevel::Piper) {
+        let write_lipsync = if lipsync == LipsyncLevel::Align { text_to_face::write_aligned_lipsync } else { text_to_face::write_estimated_lipsync };
+        match write_lipsync(text, &samples, sample_rate, None, None) {
+            Ok(Some(json)) => print_lipsync(&json),
+            Ok(None) => {}
+            Err(e) => fail!("Lipsync failed: {}", e),
+        }
+    } else if lipsync != LipsyncLevel::Low {
+        let temp_wav = "temp_lipsync.wav";
+        if let Err(e) = text_to_face::write_wav(temp_wav, &samples, sample_rate) {
+            fail!("Failed to write WAV for lipsync: {}", e);
+            return;
+        }
+        if let Err(e) = text_to_face::run_whisperx_on_wav(temp_wav, None, lipsync == LipsyncLevel::High, text, None) {
+            fail!("Lipsync failed: {}", e);
+        }
+        let _ = std::fs::remove_file(temp_wav);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::output;
+use text_to_face::singing::{parse_note_list, read_midi_melody, sing};
+use text_to_face::stereo::pan_wav;
+use text_to_face::write_wav;
+
+pub fn handle_sing(text: &str, melody: Option<&str>, midi: Option<&str>, bpm: Option<f32>, voice: &str, output: Option<&str>) {
+    let (notes, midi_bpm) = match (melody, midi) {
+        (Some(melody), _) => match parse_note_list(melody) {
+            Ok(notes) => (notes, None),
+            Err(e) => {
+                fail!("Invalid melody: {}", e);
+                return;
+            }
+        },
+        (None, Some(path)) => match read_midi_melody(path) {
+            Ok(result) => result,
+            Err(e) => {
+                fail!("Failed to read MIDI file {}: {}", path, e);
+                return;
+            }
+        },
+        (None, None) => {
+            fail!("Provide a melody with --melody \"C4 D4 E4\" or --midi song.mid");
+            return;
+        }
+    };
+    let bpm = bpm.or(midi_bpm).unwrap_or(120.0);
+    note!("Singing {} notes at {} BPM with voice {}", notes.len(), bpm, voice);
+    output::record("voice", voice);
+    output::record("notes", notes.len());
+    output::record("bpm", bpm);
+    let (samples, sample_rate) = match sing(text, voice, &notes, bpm) {
+        Ok(sung) => sung,
+        Err(e) => {
+            fail!("Error: {}", e);
+            return;
+        }
+    };
+    output::record("duration", samples.len() as f32 / sample_rate as f32);
+    match output {
+        Some(path) => match write_wav(path, &samples, sample_rate).and_then(|()| pan_wav(path)) {
+            Ok(()) => {
+                text_to_face::session::record_output(std::path::Path::new(path));
+                output::record_file("audio", path);
+                note!("Sung WAV written to {}", path)
+            }
+            Err(e) => fail!("Failed to write {}: {}", path, e),
+        },
+        None => {
+            if let Ok((_stream, handle)) = text_to_face::devices::open_output_stream() {
+                if let Ok(sink) = rodio::Sink::try_new(&handle) {
+                    sink.append(text_to_face::stereo::playback_buffer(&samples, sample_rate));
+                    sink.sleep_until_end();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::output;
+use text_to_face::voicechanger::{list_devices, start_voice_changer};
+use text_to_face::PitchArg;
+
+pub fn handle_voicechanger(pitch: &PitchArg, device: Option<&str>, output: Option<&str>, show_devices: bool) {
+    if show_devices {
+        match list_devices() {
+            Ok((inputs, outputs)) => {
+                output::record("inputs", &inputs);
+                output::record("outputs", &outputs);
+                note!("Input devices:");
+                for name in inputs {
+                    note!("  {}", name);
+                }
+                note!("Output devices:");
+                for name in outputs {
+                    note!("  {}", name);
+                }
+            }
+            Err(e) => fail!("Failed to list audio devices: {}", e),
+        }
+        return;
+    }
+    match start_voice_changer(device, output, pitch.as_factor()) {
+        Ok(changer) => {
+            note!("Voice changer running: {} (pitch {}). Press Ctrl-C to stop.", changer.description, pitch.as_factor());
+            loop {
+                std::thread::park();
+            }
+        }
+        Err(e) => fail!("Failed to start voice changer: {}", e),
+    }
+}
@@ -0,0 +1,272 @@
+use crate::output;
+use crate::logger;
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::path::Path;
+use text_to_face::progress::{with_handler, DownloadProgress, ProgressHandler};
+use text_to_face::pinning::{lockfile_path, pin_voice, short, upgrade_voice, voice_lock, Upgrade, VoiceLockfile};
+use text_to_face::local_voices::{add_local_voice, find_local_voice, forget_local_voice};
+use text_to_face::speakers::voice_speakers;
+use text_to_face::{download_quantized_voice_files, download_voice_files, get_available_voices, installed_voices, remove_voice_files, voice_matches_language, Voice};
+
+/// The voices named on the command line: one ID, every voice of `language`, or (with `all`) every voice in `voices`
+fn selected_voices(voices: Vec<Voice>, id: Option<&str>, language: Option<&str>, all: bool) -> Result<Vec<Voice>, String> {
+    let selected: Vec<Voice> = match (id, language) {
+        (Some(id), _) => voices.into_iter().filter(|v| v.id == id).collect(),
+        (None, Some(language)) => voices.into_iter().filter(|v| voice_matches_language(&v.id, language)).collect(),
+        (None, None) if all => voices,
+        (None, None) => return Err("Name a voice, or use --language or --all".to_string()),
+    };
+    match (id, language) {
+        (Some(id), _) if selected.is_empty() => Err(format!("Voice '{}' not found. Run `text-to-face list` to see available voices.", id)),
+        (None, Some(language)) if selected.is_empty() => Err(format!("No voices for language '{}'", language)),
+        _ => Ok(selected),
+    }
+}
+
+/// One progress line for every download of a bulk install: voices finished and megabytes so far
+struct BulkProgress {
+    voices: usize,
+    installed: AtomicUsize,
+    /// Bytes done and expected, by file
+    files: Mutex<HashMap<String, (u64, Option<u64>)>>,
+}
+
+impl BulkProgress {
+    fn draw(&self) {
+        let files = self.files.lock().unwrap();
+        let done: u64 = files.values().map(|(done, _)| done).sum();
+        let expected: u64 = files.values().map(|(done, total)| total.unwrap_or(*done)).sum();
+        let installed = self.installed.load(Ordering::Relaxed);
+        eprint!(
+            "\r  [{}/{} voices] {} {:.1}/{:.1} MB",
+            installed,
+            self.voices,
+            logger::bar(installed as u64, self.voices as u64, 30),
+            done as f64 / 1_000_000.0,
+            expected as f64 / 1_000_000.0
+        );
+        let _ = std::io::stderr().flush();
+    }
+}
+
+impl ProgressHandler for BulkProgress {
+    fn download(&self, progress: &DownloadProgress) {
+        self.files.lock().unwrap().insert(progress.label.to_string(), (progress.done, progress.total));
+        if progress.finished {
+            // The installed voice's message goes on a line of its own
+            logger::clear_progress();
+        } else {
+            self.draw();
+        }
+    }
+}
+
+pub fn handle_voices_install(id: Option<&str>, language: Option<&str>, all: bool, quantized: bool, jobs: usize) {
+    let voices = match selected_voices(get_available_voices(), id, language, all) {
+        Ok(voices) => voices,
+        Err(e) => {
+            fail!("{}", e);
+            return;
+        }
+    };
+    if let [voice] = voices.as_slice() {
+        install_voice(voice, quantized);
+        return;
+    }
+    if voices.is_empty() {
+        return;
+    }
+    let workers = jobs.clamp(1, voices.len());
+    note!("Installing {} voices, {} at a time", voices.len(), workers);
+    let progress = Arc::new(BulkProgress { voices: voices.len(), installed: AtomicUsize::new(0), files: Mutex::new(HashMap::new()) });
+    let next = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    logger::draw_download_bars(false);
+    with_handler(progress.clone(), || {
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| {
+                    while let Some(voice) = voices.get(next.fetch_add(1, Ordering::SeqCst)) {
+                        if !install_voice(voice, quantized) {
+                            failed.fetch_add(1, Ordering::Relaxed);
+                        }
+                        progress.installed.fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+            }
+        })
+    });
+    logger::draw_download_bars(true);
+    logger::clear_progress();
+    let failed = failed.into_inner();
+    note!("Installed {} of {} voices", voices.len() - failed, voices.len());
+}
+
+/// Install one voice (and its int8 model with `quantized`), reporting the outcome. Returns whether the voice installed.
+fn install_voice(voice: &Voice, quantized: bool) -> bool {
+    match download_voice_files(voice) {
+        Ok((model_path, _)) => match voice_lock(&voice.id) {
+            Some(lock) => {
+                output::append("installed", serde_json::json!({ "voice": voice.id, "model": model_path, "revision": lock.revision, "pinned": lock.pinned }));
+                note!("Installed {} ({}) at revision {}{}", voice.id, model_path, short(&lock.revision), if lock.pinned { " (pinned)" } else { "" })
+            }
+            None => {
+                output::append("installed", serde_json::json!({ "voice": voice.id, "model": model_path }));
+                note!("Installed {} ({})", voice.id, model_path)
+            }
+        },
+        Err(e) => {
+            fail!("Failed to install {}: {}", voice.id, e);
+            return false;
+        }
+    }
+    if quantized {
+        match download_quantized_voice_files(voice) {
+            Ok((model_path, _)) => {
+                output::append("installed", serde_json::json!({ "voice": voice.id, "model": model_path, "quantized": true }));
+                note!("Installed int8 model for {} ({}); use it with --quantized", voice.id, model_path)
+            }
+            Err(e) => fail!("{}", e),
+        }
+    }
+    true
+}
+
+pub fn handle_voices_remove(id: Option<&str>, language: Option<&str>, all: bool) {
+    let installed = installed_voices();
+    if let Some(id) = id
+        && !installed.iter().any(|v| v.id == id)
+    {
+        fail!("{} isn't installed", id);
+        return;
+    }
+    let voices = match selected_voices(installed, id, language, all) {
+        Ok(voices) => voices,
+        Err(e) => {
+            fail!("{}", e);
+            return;
+        }
+    };
+    if voices.is_empty() {
+        note!("No voices are installed");
+        return;
+    }
+    let mut total = 0;
+    for voice in &voices {
+        // A local voice is unregistered too, since its files were copied from elsewhere
+        match remove_voice_files(&voice.id).and_then(|freed| Ok((freed, forget_local_voice(&voice.id)?))) {
+            Ok((freed, local)) => {
+                output::append("removed", serde_json::json!({ "voice": voice.id, "freed_bytes": freed, "local": local }));
+                note!("Removed {}{} ({:.1} MB)", voice.id, if local { " and unregistered it" } else { "" }, freed as f64 / 1_000_000.0);
+                total += freed;
+            }
+            Err(e) => fail!("Failed to remove {}: {}", voice.id, e),
+        }
+    }
+    if voices.len() > 1 {
+        note!("Freed {:.1} MB", total as f64 / 1_000_000.0);
+    }
+}
+
+pub fn handle_voices_pin(id: &str) {
+    if find_local_voice(id).is_some() {
+        fail!("{} is a local voice, so it has no upstream revision to pin", id);
+        return;
+    }
+    match pin_voice(id) {
+        Ok(lock) => {
+            output::record("pinned", serde_json::json!({ "voice": id, "revision": lock.revision, "lockfile": lockfile_path() }));
+            note!("Pinned {} at revision {} in {}", id, short(&lock.revision), lockfile_path().display())
+        }
+        Err(e) => fail!("Failed to pin {}: {}", id, e),
+    }
+}
+
+pub fn handle_voices_upgrade(id: Option<&str>, language: Option<&str>) {
+    let ids: Vec<String> = match id {
+        Some(id) => vec![id.to_string()],
+        // Every recorded voice, and installed voices from before revisions were recorded
+        None => match VoiceLockfile::load(&lockfile_path()) {
+            Ok(lockfile) => {
+                let mut ids: BTreeSet<String> = lockfile.voices.into_keys().collect();
+                ids.extend(installed_voices().into_iter().map(|v| v.id));
+                // Local voices have no upstream to upgrade from
+                ids.into_iter().filter(|id| language.is_none_or(|language| voice_matches_language(id, language)) && find_local_voice(id).is_none()).collect()
+            }
+            Err(e) => {
+                fail!("{}", e);
+                return;
+            }
+        },
+    };
+    if ids.is_empty() {
+        note!("No installed or recorded voices in {}; install one with `voices install <id>`", lockfile_path().display());
+        return;
+    }
+    let voices = get_available_voices();
+    for id in ids {
+        let Some(voice) = voices.iter().find(|v| v.id == id) else {
+            fail!("Voice '{}' not found. Run `text-to-face list` to see available voices.", id);
+            continue;
+        };
+        if find_local_voice(&id).is_some() {
+            fail!("{} is a local voice; run `voices add-local` again to update it", id);
+            continue;
+        }
+        match upgrade_voice(voice) {
+            Ok(Upgrade::UpToDate(lock)) => {
+                output::append("upgrades", serde_json::json!({ "voice": id, "revision": lock.revision, "upgraded": false }));
+                note!("{} is up to date at revision {}", id, short(&lock.revision))
+            }
+            Ok(Upgrade::Upgraded { from, to }) => {
+                output::append("upgrades", serde_json::json!({ "voice": id, "from": from.as_ref().map(|l| &l.revision), "revision": to.revision, "upgraded": true }));
+                note!(
+                "Upgraded {} from {} to revision {}{}",
+                id,
+                from.as_ref().map(|l| short(&l.revision)).unwrap_or("an unrecorded revision"),
+                short(&to.revision),
+                if to.pinned { " (pinned)" } else { "" }
+                )
+            }
+            Err(e) => fail!("Failed to upgrade {}: {}", id, e),
+        }
+    }
+}
+
+pub fn handle_voices_speakers(id: &str, as_json: bool) {
+    let speakers = match voice_speakers(id) {
+        Ok(speakers) => speakers,
+        Err(e) => {
+            fail!("Failed to read the speakers of {}: {}", id, e);
+            return;
+        }
+    };
+    if as_json {
+        let listing: Vec<serde_json::Value> = speakers.iter().map(|(id, name)| serde_json::json!({ "id": id, "name": name })).collect();
+        output::print_document(&listing);
+        return;
+    }
+    if speakers.is_empty() {
+        note!("{} has a single speaker", id);
+        return;
+    }
+    note!("{:>5}  NAME", "ID");
+    for (speaker_id, name) in &speakers {
+        note!("{:>5}  {}", speaker_id, name);
+    }
+    note!("{} speakers; pick one with --speaker-id <ID or NAME>", speakers.len());
+}
+
+pub fn handle_voices_add_local(id: &str, model: &str, config: Option<&str>) {
+    let config = config.map_or_else(|| format!("{}.json", model), str::to_string);
+    match add_local_voice(id, Path::new(model), Path::new(&config)) {
+        Ok(voice) => {
+            output::record("added", serde_json::json!({ "voice": voice.id, "language": voice.language, "quality": voice.quality, "model": voice.model_path, "config": voice.config_path }));
+            note!("Added {} ({}, {}); use it with --voice {}", voice.id, voice.language, voice.quality, voice.id);
+        }
+        Err(e) => fail!("Failed to add {}: {}", id, e),
+    }
+}
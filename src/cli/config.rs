@@ -0,0 +1,184 @@
+//! The persistent configuration file (`~/.config/pitch-tts/config.toml` on Linux): defaults for the
+//! voice, pitch, tempo, lipsync, and where models and exports go. Its values become the default
+//! values of the matching flags, so anything given on the command line still wins. An
+//! `[abbreviations]` table adds to the built-in abbreviation expansions.
+
+use clap::builder::PossibleValue;
+use clap::{Command, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use text_to_face::abbreviations::AcronymMode;
+use text_to_face::{LipsyncLevel, PitchArg};
+
+/// Settings read from the config file; unset keys keep the built-in defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    /// A factor or preset name, as for `--pitch`
+    #[serde(skip_serializing_if = "Option::is_none", deserialize_with = "string_or_number")]
+    pub pitch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tempo: Option<f32>,
+    /// Where voice models are downloaded and loaded from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub models_dir: Option<PathBuf>,
+    /// Directory for `export` (when `--output` isn't given) and `batch`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<String>,
+    /// Lipsync level: low, estimate, align, or high
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lipsync: Option<String>,
+    /// Ollama model for ARPAbet phonemes of unknown words
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lipsync_with_llm: Option<String>,
+    /// How acronyms are read: auto, spell, or word
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acronyms: Option<String>,
+    /// Words as written to what is said, e.g. `"Rd." = "Road"`; edited in the file rather than with `config set`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub abbreviations: BTreeMap<String, String>,
+}
+
+/// Keys of the config file, in the order `config get` lists them
+pub const KEYS: [&str; 8] = ["voice", "pitch", "tempo", "models_dir", "output_dir", "lipsync", "lipsync_with_llm", "acronyms"];
+
+/// `pitch = 1.2` and `pitch = "deep"` are both accepted
+fn string_or_number<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Text(String),
+        Number(f64),
+    }
+    Ok(Option::<Value>::deserialize(deserializer)?.map(|value| match value {
+        Value::Text(text) => text,
+        Value::Number(number) => number.to_string(),
+    }))
+}
+
+/// Location of the config file: `pitch-tts/config.toml` in the user's config directory
+pub fn config_path() -> PathBuf {
+    directories::BaseDirs::new()
+        .map(|dirs| dirs.config_dir().to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("pitch-tts")
+        .join("config.toml")
+}
+
+impl Config {
+    /// Read the config file; a missing file is an empty config
+    pub fn load(path: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => {
+                let config: Config = toml::from_str(&contents)?;
+                for key in KEYS {
+                    if let Some(value) = config.get(key) {
+                        validate(key, &value)?;
+                    }
+                }
+                Ok(config)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// A key's value as text, if it is set
+    pub fn get(&self, key: &str) -> Option<String> {
+        match key {
+            "voice" => self.voice.clone(),
+            "pitch" => self.pitch.clone(),
+            "tempo" => self.tempo.map(|t| t.to_string()),
+            "models_dir" => self.models_dir.as_ref().map(|p| p.display().to_string()),
+            "output_dir" => self.output_dir.clone(),
+            "lipsync" => self.lipsync.clone(),
+            "lipsync_with_llm" => self.lipsync_with_llm.clone(),
+            "acronyms" => self.acronyms.clone(),
+            _ => None,
+        }
+    }
+
+    /// Set a key from text after checking the value, or clear it with `None`
+    pub fn set(&mut self, key: &str, value: Option<&str>) -> Result<(), String> {
+        if !KEYS.contains(&key) {
+            return Err(format!("Unknown config key '{}' (expected one of: {})", key, KEYS.join(", ")));
+        }
+        if let Some(value) = value {
+            validate(key, value)?;
+        }
+        let text = value.map(str::to_string);
+        match key {
+            "voice" => self.voice = text,
+            "pitch" => self.pitch = text,
+            "tempo" => self.tempo = value.map(|v| v.parse().unwrap()),
+            "models_dir" => self.models_dir = text.map(PathBuf::from),
+            "output_dir" => self.output_dir = text,
+            "lipsync" => self.lipsync = text,
+            "acronyms" => self.acronyms = text,
+            _ => self.lipsync_with_llm = text,
+        }
+        Ok(())
+    }
+
+    /// Make the config's values the defaults of the matching flags. `pitch` and `tempo` don't apply
+    /// to the voice changer, whose pitch is for a live microphone, or to `dialogue`, where they scale
+    /// every character's own.
+    pub fn apply_defaults(&self, mut command: Command) -> Command {
+        let defaults: [(&str, Option<String>); 6] = [
+            ("voice", self.voice.clone()),
+            ("pitch", self.pitch.clone()),
+            ("tempo", self.tempo.map(|t| t.to_string())),
+            ("lipsync", self.lipsync.clone()),
+            ("lipsync_with_llm", self.lipsync_with_llm.clone()),
+            ("output_dir", self.output_dir.clone()),
+        ];
+        let names: Vec<String> = command.get_subcommands().map(|s| s.get_name().to_string()).collect();
+        for name in names {
+            for (key, value) in &defaults {
+                let Some(value) = value else { continue };
+                if matches!(name.as_str(), "voicechanger" | "dialogue") && matches!(*key, "pitch" | "tempo") {
+                    continue;
+                }
+                // `export` takes a directory as its output
+                let arg = if *key == "output_dir" && name == "export" { "output" } else { key };
+                let has_arg = command.find_subcommand(&name).is_some_and(|s| s.get_arguments().any(|a| a.get_id() == arg));
+                if has_arg {
+                    // Defaults live for the whole run, so leaking the few config strings is fine
+                    let value: &'static str = value.clone().leak();
+                    command = command.mut_subcommand(&name, |s| s.mut_arg(arg, |a| a.default_value(value)));
+                }
+            }
+        }
+        command
+    }
+}
+
+/// Check a value the way its flag would
+fn validate(key: &str, value: &str) -> Result<(), String> {
+    match key {
+        "pitch" => PitchArg::from_str(value).map(|_| ()),
+        "tempo" => match value.parse::<f32>() {
+            Ok(tempo) if tempo > 0.0 => Ok(()),
+            _ => Err(format!("Invalid tempo '{}': expected a positive number", value)),
+        },
+        "lipsync" => <LipsyncLevel as ValueEnum>::from_str(value, true).map(|_| ()).map_err(|_| {
+            let levels: Vec<String> = LipsyncLevel::value_variants().iter().filter_map(|l| l.to_possible_value()).map(|v: PossibleValue| v.get_name().to_string()).collect();
+            format!("Invalid lipsync level '{}': expected one of {}", value, levels.join(", "))
+        }),
+        "acronyms" => AcronymMode::from_str(value).map(|_| ()),
+        _ if value.trim().is_empty() => Err(format!("{} can't be empty", key)),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,77 @@
+//! Terminal output for the library's log messages and download progress. The library itself prints
+//! nothing; this puts its messages where the CLI has always shown them.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::io::{IsTerminal, Write};
+use text_to_face::http::DownloadProgress;
+
+/// Messages from the library: information on stdout (stderr under `--json`), warnings and errors on stderr
+struct CliLogger;
+
+impl Log for CliLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Dependencies (HTTP, ONNX Runtime) log too; only the library's own messages are shown
+        metadata.target().starts_with("text_to_face") && metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            Level::Error | Level::Warn => eprintln!("{}", record.args()),
+            _ => note!("{}", record.args()),
+        }
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Route the library's messages to the terminal, down to debug detail unless `RUST_LOG` names a
+/// level (`error`, `warn`, `info`, `debug`, or `off`), and draw download progress on stderr
+pub fn init() {
+    let level = std::env::var("RUST_LOG").ok().and_then(|level| level.parse().ok()).unwrap_or(LevelFilter::Debug);
+    if log::set_logger(&CliLogger).is_ok() {
+        log::set_max_level(level);
+    }
+    draw_download_bars(true);
+}
+
+/// Turn the per-file download bars on (when stderr is a terminal) or off, e.g. while concurrent
+/// downloads show one bar for all of them
+pub fn draw_download_bars(enabled: bool) {
+    let enabled = enabled && std::io::stderr().is_terminal();
+    text_to_face::http::set_progress_handler(if enabled { Some(draw_progress) } else { None });
+}
+
+/// Clear the progress line, so a message can be printed in its place
+pub fn clear_progress() {
+    if std::io::stderr().is_terminal() {
+        eprint!("\r\x1b[2K");
+        let _ = std::io::stderr().flush();
+    }
+}
+
+/// A bar of `width` characters, `done` of `total` full
+pub fn bar(done: u64, total: u64, width: u64) -> String {
+    let filled = (done.min(total) * width / total.max(1)) as usize;
+    format!("[{}{}]", "#".repeat(filled), " ".repeat(width as usize - filled))
+}
+
+/// A progress line on stderr, redrawn in place
+fn draw_progress(progress: &DownloadProgress) {
+    let mb = |bytes: u64| bytes as f64 / 1_000_000.0;
+    let line = match progress.total {
+        Some(total) if total > 0 => {
+            format!("{:>3}% {} {:.1}/{:.1} MB", progress.done * 100 / total, bar(progress.done, total, 30), mb(progress.done), mb(total))
+        }
+        _ => format!("{:.1} MB", mb(progress.done)),
+    };
+    eprint!("\r  {} {}", progress.label, line);
+    if progress.finished {
+        eprintln!();
+    }
+    let _ = std::io::stderr().flush();
+}
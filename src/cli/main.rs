@@ -1,11 +1,61 @@
-use clap::{Parser, Subcommand};
-use commands::export::handle_export;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use commands::audiobook::handle_audiobook;
+use commands::batch::handle_batch;
+use commands::config::{handle_config_get, handle_config_set};
+use commands::coverage::handle_coverage;
+use commands::daemon::{handle_cancel, handle_daemon, handle_enqueue, handle_status};
+use commands::devices::handle_devices;
+use commands::dialogue::handle_dialogue;
+use commands::enrich::handle_enrich;
+use commands::export::{handle_export, ExportOptions};
+use commands::inspect::handle_inspect;
+use commands::interactive::handle_interactive;
 use commands::list::handle_list;
+use commands::read::handle_read;
+use commands::replay::handle_replay;
 use commands::say::handle_say;
-use text_to_face::{synth_with_voice_config, PitchArg};
-use rodio::buffer::SamplesBuffer;
+use commands::sing::handle_sing;
+use commands::voicechanger::handle_voicechanger;
+use commands::voices::{handle_voices_add_local, handle_voices_install, handle_voices_pin, handle_voices_remove, handle_voices_speakers, handle_voices_upgrade};
+use text_to_face::{synth_with_voice_config, voice_sample_rate, PitchArg, VoiceFilter};
+use std::io::{IsTerminal, Read};
 use std::str::FromStr;
-use text_to_face::LipsyncLevel;
+use text_to_face::{LipsyncFormat, LipsyncLevel, SynthesisReport};
+use text_to_face::abbreviations::{AbbreviationSettings, AcronymMode};
+use text_to_face::accelerator::{set_accelerator, Accelerator};
+use text_to_face::alignment::set_transcript_alignment;
+use text_to_face::effects::{self, VoiceEffect};
+use text_to_face::dsp::StretchQuality;
+use text_to_face::emoji::EmojiMode;
+use text_to_face::encode::{parse_opus_frame_ms, AudioFormat, EncodeSettings};
+use text_to_face::gltf::BlendshapeSet;
+use text_to_face::limits::{set_limits, ResourceLimits};
+use text_to_face::llm_fallback::{self, LlmFallbackSettings};
+use text_to_face::markdown::{self, CodeBlocks, InputFormat};
+use text_to_face::markers::MarkerSet;
+use text_to_face::mixing::DuckSettings;
+use text_to_face::morph::MorphSpec;
+use text_to_face::nice::{self, NiceSettings};
+use text_to_face::stereo::{set_stereo_output, StereoOutput};
+use text_to_face::subtitles::{parse_subtitles_path, DEFAULT_MAX_LINE_CHARS};
+use text_to_face::pauses::{parse_pause, set_pauses, PauseSettings};
+use text_to_face::playback::SpeakMode;
+use text_to_face::rate::{rated_tempo, set_rate, RatePreset, RateSettings};
+use std::time::Duration;
+use std::path::{Path, PathBuf};
+use text_to_face::phoneme_sources::{self, PhonemeSourceSettings, SourceOrder};
+use text_to_face::rules::load_rules;
+use text_to_face::server::ServerOptions;
+use text_to_face::session;
+use text_to_face::speakers::{self, SpeakerSelection};
+use text_to_face::tags::AudioTags;
+use text_to_face::trim::TrimRange;
+use text_to_face::visemes::VisemeScheme;
+use text_to_face::warnings::Warning;
+use colored::*;
+
+#[macro_use]
+mod output;
 
 
 #[derive(Parser)]
@@ -28,29 +78,187 @@ struct Cli {
     /// Pitch factor or preset (e.g. 1.2, slomo, deep, child, helium)
     #[arg(long, value_parser = PitchArg::from_str, help = "Pitch factor (0.5 = octave down, 2.0 = octave up) or preset (slomo, deep, child, helium)")]
     pitch: Option<PitchArg>,
+
+    /// Output device for --voice/--text playback (see `devices`)
+    #[arg(long)]
+    device: Option<String>,
+
+    /// Low-memory profile for Raspberry Pi-class devices: sentence-by-sentence synthesis, int8 models when installed, ALSA playback via aplay
+    #[arg(long, global = true)]
+    low_memory: bool,
+
+    /// Be polite on shared machines: lower this process's priority and keep synthesis, alignment, and encoding to a few CPU cores
+    #[arg(long, global = true)]
+    nice: bool,
+
+    /// CPU cores to use under --nice (default: a quarter of the machine's)
+    #[arg(long, global = true, value_name = "N", requires = "nice")]
+    nice_threads: Option<usize>,
+
+    /// Run voice models on this ONNX Runtime execution provider; GPU providers need a build with the matching feature (cuda, coreml, directml)
+    #[arg(long, global = true, value_enum, default_value = "cpu")]
+    accelerator: Accelerator,
+
+    /// Voice models to keep loaded between synthesis calls in batch, server, and interactive modes (0 disables; ignored under --low-memory)
+    #[arg(long, global = true, value_name = "N", default_value_t = text_to_face::model_cache::DEFAULT_CAPACITY)]
+    model_cache: usize,
+
+    /// Use int8 quantized voice models where installed (see `voices install --quantized`)
+    #[arg(long, global = true)]
+    quantized: bool,
+
+    /// Keep voices, CMUdict, rules, and the other app data here instead of the platform's data directory (or $PITCH_TTS_DATA_DIR)
+    #[arg(long, global = true, value_name = "DIR")]
+    data_dir: Option<String>,
+
+    /// Voice manifest adding, hiding, or re-pointing catalog voices (defaults to voices.toml or voices.json in the app data directory)
+    #[arg(long, global = true, value_name = "FILE")]
+    catalog: Option<String>,
+
+    /// Voice lockfile recording installed revisions (defaults to voices.lock.json in the models directory)
+    #[arg(long, global = true, value_name = "FILE")]
+    voice_lock: Option<String>,
+
+    /// Don't spell out numbers, ordinals, and dates for the voice's language before synthesis
+    #[arg(long, global = true)]
+    no_normalize: bool,
+
+    /// Speaker of a multi-speaker voice, by ID or name (see `voices speakers <voice>`)
+    #[arg(long, global = true, value_name = "ID", value_parser = SpeakerSelection::from_str)]
+    speaker_id: Option<SpeakerSelection>,
+
+    /// Speaking rate preset: slow, normal, fast, or very-fast (combines with --tempo; adjusted for the voice's language)
+    #[arg(long, global = true, value_name = "PRESET", value_parser = RatePreset::from_str)]
+    rate: Option<RatePreset>,
+
+    /// Character voice effect applied after pitch and tempo: robot, megaphone, telephone, or whisper
+    #[arg(long, global = true, value_name = "EFFECT", value_parser = VoiceEffect::from_str)]
+    effect: Option<VoiceEffect>,
+
+    /// Channels of exported and played audio: 1 (mono) or 2 (stereo)
+    #[arg(long, global = true, value_name = "N", value_parser = clap::value_parser!(u16).range(1..=2))]
+    channels: Option<u16>,
+
+    /// Stereo position from -1.0 (left) to 1.0 (right), 0.0 being center; implies --channels 2
+    #[arg(long, global = true, value_name = "PAN", allow_negative_numbers = true)]
+    pan: Option<f32>,
+
+    /// Silence between sentences instead of the model's own, e.g. 300ms or 0.5s
+    #[arg(long, global = true, value_name = "DURATION", value_parser = parse_pause)]
+    sentence_pause: Option<Duration>,
+
+    /// Silence between paragraphs (separated by blank lines), e.g. 800ms or 1s
+    #[arg(long, global = true, value_name = "DURATION", value_parser = parse_pause)]
+    paragraph_pause: Option<Duration>,
+
+    /// Read an ellipsis between words ("Well... maybe") as exactly this much silence, e.g. 600ms; `[pause 1s]` markers always work
+    #[arg(long, global = true, value_name = "DURATION", value_parser = parse_pause)]
+    ellipsis_pause: Option<Duration>,
+
+    /// Vary each sentence's pitch by up to this fraction (and its tempo by half that), e.g. 0.05, so long narrations sound less monotone
+    #[arg(long, global = true, value_name = "AMOUNT", value_parser = text_to_face::humanize::parse_amount)]
+    humanize: Option<f32>,
+
+    /// How acronyms are read: auto (known ones, e.g. NASA as a word and FBI spelled out), spell, or word (default: the config file's, or auto)
+    #[arg(long, global = true, value_name = "MODE", value_parser = AcronymMode::from_str)]
+    acronyms: Option<AcronymMode>,
+
+    /// What to do with emoji and symbols in the text: strip them, speak their names (English voices), or ignore them (leave them to the phonemizer)
+    #[arg(long, global = true, value_name = "MODE", default_value = "strip", value_parser = EmojiMode::from_str)]
+    emoji: EmojiMode,
+
+    /// How --tempo stretches audio: fast (resampling, which also moves the pitch) or high (WSOLA, which keeps the pitch and avoids glitches)
+    #[arg(long, global = true, value_name = "QUALITY", default_value = "fast", value_parser = StretchQuality::from_str)]
+    stretch_quality: StretchQuality,
+
+    /// Most unknown words sent to the --lipsync-with-llm model per lipsync run; the rest get empty phonemes
+    #[arg(long, global = true, value_name = "N")]
+    llm_max_words: Option<usize>,
+
+    /// Sampling temperature for the --lipsync-with-llm model (lower is more consistent)
+    #[arg(long, global = true, value_name = "T")]
+    llm_temperature: Option<f32>,
+
+    /// Prompt template file for the --lipsync-with-llm model; `{word}` is replaced with the word
+    #[arg(long, global = true, value_name = "FILE")]
+    llm_prompt: Option<String>,
+
+    /// Fail lipsync exports when a word gets no phonemes from any phoneme source, instead of leaving them empty
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Lipsync by aligning the input text directly, skipping WhisperX's speech recognition (faster, and no misheard words)
+    #[arg(long, global = true)]
+    align_only: bool,
+
+    /// Language code of the alignment model for --align-only (default: the voice's language)
+    #[arg(long, global = true, value_name = "LANG", requires = "align_only")]
+    align_language: Option<String>,
+
+    /// Refuse texts longer than this many characters
+    #[arg(long, global = true, value_name = "CHARS")]
+    max_text_chars: Option<usize>,
+
+    /// Stop synthesis once the audio is longer than this many seconds
+    #[arg(long, global = true, value_name = "SECONDS")]
+    max_audio_seconds: Option<f32>,
+
+    /// Stop synthesis once the audio needs more than this many megabytes of memory
+    #[arg(long, global = true, value_name = "MB")]
+    max_memory_mb: Option<usize>,
+
+    /// Print one JSON object on stdout describing the result (files written, durations, timings, warnings, errors) and send messages to stderr
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Append every synthesis (command line, parameters, audio and output hashes) to this JSONL session log; re-run it with `replay`
+    #[arg(long, global = true, value_name = "FILE")]
+    session_log: Option<String>,
+
+    /// Text preprocessing rules file (`regex => replacement` per line), applied before synthesis (default: rules.txt in the app data directory, if present)
+    #[arg(long, global = true, value_name = "FILE")]
+    rules: Option<String>,
+
+    /// Order to try phoneme sources in (lexicon, cmudict, g2p, llm), e.g. `cmudict,llm`; prefix with `LANG=` to set it for one language only. Repeatable
+    #[arg(long, global = true, value_name = "[LANG=]SOURCES")]
+    phoneme_sources: Vec<SourceOrder>,
+
+    /// Pronunciation lexicon (`WORD PH1 PH2 ...` or `WORD {respelling}` per line, or a CMUdict-style .dict), tried before CMUdict by default. Repeatable; later files win (default: lexicon.txt in the app data directory, if present)
+    #[arg(long, global = true, value_name = "FILE")]
+    lexicon: Vec<String>,
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// List all available voices
     List {
         /// Group voices by language
         #[arg(short, long)]
         by_language: bool,
-        /// Output as JSON
-        #[arg(long)]
-        json: bool,
         /// Only show installed voices
         #[arg(long)]
         installed: bool,
         /// Only show not installed voices
         #[arg(long)]
         not_installed: bool,
+        /// Only voices for a language (en) or locale (en_GB)
+        #[arg(long)]
+        language: Option<String>,
+        /// Only voices of this quality
+        #[arg(long, value_parser = ["x_low", "low", "medium", "high"])]
+        quality: Option<String>,
+        /// Only voices from this country, e.g. GB
+        #[arg(long)]
+        country: Option<String>,
+        /// Only voices whose ID, name, or language contains this text
+        #[arg(long, value_name = "TEXT")]
+        search: Option<String>,
     },
     
     /// Synthesize speech and play it
     Say {
-        /// Text to synthesize (defaults to a fun Scottish phrase)
+        /// Text to synthesize, or `-` to read it from stdin (defaults to a fun Scottish phrase)
         #[arg(default_value = "Well hello there! I'm Alba, your Scottish friend. How about we go for a wee walk in the highlands? The weather is absolutely bonnie today!")]
         text: String,
         
@@ -69,11 +277,39 @@ enum Commands {
         /// Lipsync level: low (default) or high (adds ARPAbet phonemes)
         #[arg(long, value_enum, default_value = "low")]
         lipsync: LipsyncLevel,
+
+        /// Stream VRM mouth blendshapes (A, I, U, E, O) over the VMC protocol while playing, e.g. to VSeeFace
+        #[arg(long, value_name = "HOST:PORT", num_args = 0..=1, default_missing_value = text_to_face::vmc::DEFAULT_VMC_ADDRESS)]
+        vmc: Option<String>,
+
+        /// Experimental: blend two voices, e.g. en_GB-alba-medium:en_US-joe-medium:0.4 (0.0 = first voice, 1.0 = second)
+        #[arg(long, value_name = "VOICE_A:VOICE_B:AMOUNT", value_parser = MorphSpec::from_str)]
+        morph: Option<MorphSpec>,
+
+        /// Read the text as SSML: <break>, <prosody rate pitch>, <emphasis>, and <say-as>
+        #[arg(long, conflicts_with_all = ["vmc", "morph", "input_format"])]
+        ssml: bool,
+
+        /// Read the text from stdin (same as passing `-` as the text)
+        #[arg(long)]
+        stdin: bool,
+
+        /// Read the text as plain text or as Markdown (formatting stripped, code blocks left out, longer pauses at headings and paragraphs)
+        #[arg(long, value_enum, default_value = "plain")]
+        input_format: InputFormat,
+
+        /// With --input-format markdown: skip code blocks, or announce each one ("Rust code block omitted.")
+        #[arg(long, value_enum, default_value = "skip")]
+        code_blocks: CodeBlocks,
+
+        /// Output device name or part of it, e.g. a second sound card or virtual cable (see `devices`; defaults to the system output)
+        #[arg(long)]
+        device: Option<String>,
     },
     
     /// Export speech to WAV file
     Export {
-        /// Text to synthesize (defaults to a fun Scottish phrase)
+        /// Text to synthesize, or `-` to read it from stdin (defaults to a fun Scottish phrase)
         #[arg(default_value = "Well hello there! I'm Alba, your Scottish friend. How about we go for a wee walk in the highlands? The weather is absolutely bonnie today!")]
         text: String,
         
@@ -81,7 +317,7 @@ enum Commands {
         #[arg(short, long, default_value = "en_GB-alba-medium")]
         voice: String,
         
-        /// Output WAV file path (auto-generated from text if not provided, saved to output_/ directory with output_ prefix)
+        /// Output file path (.wav, .flac, .mp3, or .ogg) or directory (auto-generated from text if not provided, saved to output_/ directory with output_ prefix)
         #[arg(short, long)]
         output: Option<String>,
         
@@ -106,32 +342,965 @@ enum Commands {
         /// Output JSON file for lipsync data (default: output.json, saved to output_/ directory with output_ prefix, only used if --lipsync is set)
         #[arg(long, default_value = "output.json")]
         json_output: String,
+
+        /// Also write lipsync in another tool's format: rhubarb writes Rhubarb Lip Sync mouth cues to <name>.rhubarb.tsv and <name>.rhubarb.json (implies --lipsync high)
+        #[arg(long, value_enum, default_value = "native")]
+        lipsync_format: LipsyncFormat,
+
+        /// Title tag embedded in the exported file
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Artist tag embedded in the exported file (e.g. the character name)
+        #[arg(long)]
+        artist: Option<String>,
+
+        /// Comment tag embedded in the exported file (synthesis parameters are always appended)
+        #[arg(long)]
+        comment: Option<String>,
+
+        /// Write <output>.meta.json recording the crate version, synthesis parameters, and output hashes
+        #[arg(long)]
+        sidecar: bool,
+
+        /// Re-render even if the sidecar shows the output is up to date
+        #[arg(long)]
+        force: bool,
+
+        /// Write <name>.karaoke.json with word timings, character offsets, and sentence grouping for read-along players (implies --lipsync high)
+        #[arg(long)]
+        karaoke: bool,
+
+        /// Write captions from the word timings as SubRip (.srt) or WebVTT (.vtt), by extension; repeat for both (implies --lipsync high)
+        #[arg(long, value_name = "FILE", value_parser = parse_subtitles_path)]
+        subtitles: Vec<String>,
+
+        /// Longest subtitle line in characters; cues hold up to two lines
+        #[arg(long, value_name = "CHARS", default_value_t = DEFAULT_MAX_LINE_CHARS)]
+        subtitle_line_length: usize,
+
+        /// Write <name>.preview.html embedding the audio, waveform, highlighted transcript, and an animated mouth (use with --lipsync high)
+        #[arg(long)]
+        preview: bool,
+
+        /// Write <name>.stream.ndjson: audio chunks interleaved with time-stamped word and viseme events, for web avatars (aligned with --lipsync high, estimated otherwise)
+        #[arg(long)]
+        stream: bool,
+
+        /// Sprite sheet layout JSON (viseme -> frame); writes a per-video-frame mouth sprite track (implies --lipsync high)
+        #[arg(long, value_name = "LAYOUT")]
+        sprite_layout: Option<String>,
+
+        /// Output path for the sprite track, .csv or .json (default: <name>.sprites.json)
+        #[arg(long, value_name = "FILE")]
+        sprite_track: Option<String>,
+
+        /// Write Blender shape-key keyframes (viseme_AA, viseme_BM, ...) as a .py script or .json (implies --lipsync high)
+        #[arg(long, value_name = "FILE")]
+        blender_keys: Option<String>,
+
+        /// Write a Live2D motion3.json animating ParamMouthOpenY and ParamMouthForm (implies --lipsync high)
+        #[arg(long, value_name = "FILE")]
+        live2d_motion: Option<String>,
+
+        /// Write a binary glTF (.glb) with a placeholder mouth mesh and a morph-target animation of the lipsync, for previewing in any glTF viewer or importing into 3D tools (implies --lipsync high)
+        #[arg(long, value_name = "FILE")]
+        gltf: Option<String>,
+
+        /// Write a Unity animation clip keying blendshape weights: a .anim asset to drop into Assets, or .json for runtime loading (implies --lipsync high)
+        #[arg(long, value_name = "FILE")]
+        unity_anim: Option<String>,
+
+        /// Path from the Animator to the object with the SkinnedMeshRenderer, e.g. Armature/Head (default: the Animator's own object)
+        #[arg(long, value_name = "PATH", default_value = "", hide_default_value = true, requires = "unity_anim")]
+        unity_mesh_path: String,
+
+        /// Blendshape names the glTF and Unity animations drive
+        #[arg(long, value_enum, default_value = "visemes")]
+        blendshapes: BlendshapeSet,
+
+        /// Embed markers in the WAV at word and/or sentence starts, shown natively by DAWs like Reaper and Pro Tools (implies --lipsync high)
+        #[arg(long, value_enum, value_name = "WHICH")]
+        markers: Option<MarkerSet>,
+
+        /// Experimental: blend two voices, e.g. en_GB-alba-medium:en_US-joe-medium:0.4 (0.0 = first voice, 1.0 = second)
+        #[arg(long, value_name = "VOICE_A:VOICE_B:AMOUNT", value_parser = MorphSpec::from_str)]
+        morph: Option<MorphSpec>,
+
+        /// Compare the recognized transcript with the input text and write <name>.verify.json listing mismatched words (implies --lipsync high)
+        #[arg(long)]
+        verify: bool,
+
+        /// Add F0 (pitch) and energy contours at 100 frames per second to the lipsync JSON under `prosody`, for driving eyebrow and head motion (implies --lipsync high)
+        #[arg(long)]
+        prosody: bool,
+
+        /// Add a viseme track with start and end times to the lipsync JSON under `visemes`, in a published viseme set (implies --lipsync high)
+        #[arg(long, value_enum, value_name = "SCHEME")]
+        viseme_scheme: Option<VisemeScheme>,
+
+        /// Mix background music under the speech and write <name>.mix.wav, ducking the music while words are spoken (implies --lipsync high)
+        #[arg(long, value_name = "FILE")]
+        music: Option<String>,
+
+        /// How far the music drops under speech, in dB
+        #[arg(long, value_name = "DB", default_value = "12", requires = "music")]
+        duck: f32,
+
+        /// Music level relative to the original file, in dB
+        #[arg(long, value_name = "DB", default_value = "-6", allow_hyphen_values = true, requires = "music")]
+        music_gain: f32,
+
+        /// Video frame rate for frame-based animation outputs
+        #[arg(long, default_value = "24")]
+        fps: f32,
+
+        /// Render N variations of the line (different noise and pace) into <name>_take1.wav, <name>_take2.wav, ...
+        #[arg(long, value_name = "N", default_value = "1", value_parser = clap::value_parser!(u16).range(1..))]
+        takes: u16,
+
+        /// Keep only the audio from this many seconds in, shifting the lipsync timings to match
+        #[arg(long, value_name = "SECONDS", default_value = "0", conflicts_with = "verify")]
+        start_at: f32,
+
+        /// Keep at most this many seconds of audio (after --start-at), dropping lipsync timings past the cut
+        #[arg(long, value_name = "SECONDS", conflicts_with = "verify")]
+        max_duration: Option<f32>,
+
+        /// Cut leading and trailing silence quieter than THRESHOLD_DB dBFS (default -50), shifting the lipsync timings to match
+        #[arg(long, value_name = "THRESHOLD_DB", num_args = 0..=1, default_missing_value = "-50", allow_negative_numbers = true)]
+        trim_silence: Option<f32>,
+
+        /// Audio format (default: from the --output extension, else wav); mp3 and ogg are encoded with ffmpeg, opus with libopus
+        #[arg(long, value_enum)]
+        format: Option<AudioFormat>,
+
+        /// Constant bitrate in kbps for mp3 and ogg, or the target bitrate for opus (default: variable bitrate at --quality, or 32 for opus)
+        #[arg(long, value_name = "KBPS")]
+        bitrate: Option<u32>,
+
+        /// Opus frame duration in milliseconds: 2.5, 5, 10, 20, 40, or 60
+        #[arg(long, value_name = "MS", default_value = "20", value_parser = parse_opus_frame_ms)]
+        frame_size: f32,
+
+        /// Variable-bitrate quality for mp3 and ogg, 0 (smallest) to 10 (best)
+        #[arg(long, default_value = "5", conflicts_with = "bitrate")]
+        quality: f32,
+
+        /// Read the text from stdin (same as passing `-` as the text)
+        #[arg(long)]
+        stdin: bool,
+
+        /// Read the text as plain text or as Markdown (formatting stripped, code blocks left out, longer pauses at headings and paragraphs)
+        #[arg(long, value_enum, default_value = "plain")]
+        input_format: InputFormat,
+
+        /// With --input-format markdown: skip code blocks, or announce each one ("Rust code block omitted.")
+        #[arg(long, value_enum, default_value = "skip")]
+        code_blocks: CodeBlocks,
+    },
+    /// Render a text project into a single chaptered M4B/M4A audiobook
+    Audiobook {
+        /// Input text file, where chapters start at markdown headings (`# Title`) or "Chapter N" lines, or a directory of chapter files (.txt/.md, in name order)
+        input: String,
+
+        /// Voice ID to use (defaults to en_GB-alba-medium)
+        #[arg(short, long, default_value = "en_GB-alba-medium")]
+        voice: String,
+
+        /// Output audiobook path (.m4b or .m4a), or the directory for --chapter-files (default: audiobook.m4b, or audiobook/)
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Write each chapter to its own numbered WAV (chapter_001.wav, ...) in the output directory, with manifest.json, instead of one M4B
+        #[arg(long)]
+        chapter_files: bool,
+
+        /// Pitch factor or preset (e.g. 1.2, slomo, deep, child, helium)
+        #[arg(short, long, value_parser = PitchArg::from_str, default_value = "1.0", help = "Pitch factor (0.5 = octave down, 2.0 = octave up) or preset (slomo, deep, child, helium)")]
+        pitch: PitchArg,
+
+        /// Tempo factor (1.0 = normal, 2.0 = twice as slow, 0.5 = twice as fast)
+        #[arg(long, default_value = "1.0", help = "Tempo factor (1.0 = normal, 2.0 = slower, 0.5 = faster)")]
+        tempo: f32,
+
+        /// Book title
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Book author
+        #[arg(long)]
+        author: Option<String>,
+
+        /// Narrator credit (defaults to the voice ID)
+        #[arg(long)]
+        narrator: Option<String>,
+
+        /// Publication year
+        #[arg(long)]
+        year: Option<String>,
+
+        /// AAC bitrate in kbps
+        #[arg(long, default_value = "64")]
+        bitrate: u32,
+
+        /// Write <output>.meta.json recording the crate version, synthesis parameters, and output hashes
+        #[arg(long)]
+        sidecar: bool,
+
+        /// Re-render even if the sidecar shows the output is up to date
+        #[arg(long)]
+        force: bool,
+
+        /// Lipsync level for per-chapter JSON: low (default, none) or high (adds ARPAbet phonemes)
+        #[arg(long, value_enum, default_value = "low")]
+        lipsync: LipsyncLevel,
+
+        /// Ollama model for ARPAbet phoneme generation of unknown words
+        #[arg(long, value_name = "MODEL")]
+        lipsync_with_llm: Option<String>,
+
+        /// Number of WhisperX alignments to run concurrently (0 = based on CPU count; lower it if GPU memory is tight)
+        #[arg(long, default_value = "0")]
+        align_jobs: usize,
+
+        /// Compare each chapter's recognized transcript with its text and write <stem>.chapter_NNN.verify.json (implies --lipsync high)
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Render every row of a JSONL or CSV file of {text, voice, pitch, tempo, output}, skipping rows that are already up to date
+    Batch {
+        /// Input file: JSON Lines, or CSV (by .csv extension) with a header row
+        input: String,
+
+        /// Voice for rows that don't name one
+        #[arg(short, long, default_value = "en_GB-alba-medium")]
+        voice: String,
+
+        /// Tempo for rows that don't set one (1.0 = normal, 2.0 = slower, 0.5 = faster)
+        #[arg(long, default_value = "1.0")]
+        tempo: f32,
+
+        /// Directory that row outputs are written to (rows without an output become NNNN.wav)
+        #[arg(short, long, default_value = ".")]
+        output_dir: String,
+
+        /// Number of rows to synthesize concurrently (0 = based on CPU count)
+        #[arg(short, long, default_value = "0")]
+        jobs: usize,
+
+        /// Re-render every row, even those whose sidecar shows an up-to-date output
+        #[arg(long)]
+        force: bool,
+
+        /// Write a JSON report of rendered, skipped, and failed rows
+        #[arg(long, value_name = "PATH")]
+        report: Option<String>,
+    },
+    /// Experimental: sing lyrics to a melody (note list or MIDI file)
+    Sing {
+        /// Lyrics; use hyphens to mark syllables (e.g. "twin-kle twin-kle lit-tle star")
+        text: String,
+
+        /// Melody as a note list, e.g. "C4 C4 G4 G4 A4 A4 G4:2" (R = rest, :beats sets the length)
+        #[arg(long, conflicts_with = "midi")]
+        melody: Option<String>,
+
+        /// Read the melody from a MIDI file (first track with notes)
+        #[arg(long, value_name = "FILE")]
+        midi: Option<String>,
+
+        /// Tempo in beats per minute (default: from the MIDI file, otherwise 120)
+        #[arg(long)]
+        bpm: Option<f32>,
+
+        /// Voice ID to use (defaults to en_GB-alba-medium)
+        #[arg(short, long, default_value = "en_GB-alba-medium")]
+        voice: String,
+
+        /// Output WAV file (plays the result when omitted)
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Output device name or part of it, e.g. a second sound card or virtual cable (see `devices`; defaults to the system output)
+        #[arg(long)]
+        device: Option<String>,
+    },
+
+    /// Continuous read mode: speak each line from stdin as it arrives ("/stop" silences speech, "/reload" picks up rule and voice changes)
+    Read {
+        /// Voice ID to use (defaults to en_GB-alba-medium)
+        #[arg(short, long, default_value = "en_GB-alba-medium")]
+        voice: String,
+
+        /// Pitch factor or preset (e.g. 1.2, slomo, deep, child, helium)
+        #[arg(short, long, value_parser = PitchArg::from_str, default_value = "1.0")]
+        pitch: PitchArg,
+
+        /// Tempo factor (1.0 = normal, 2.0 = slower, 0.5 = faster)
+        #[arg(long, default_value = "1.0")]
+        tempo: f32,
+
+        /// interrupt: new input cuts off current speech; queue: new input waits its turn
+        #[arg(long, value_enum, default_value = "interrupt")]
+        mode: SpeakMode,
+
+        /// Output device name or part of it, e.g. a second sound card or virtual cable (see `devices`; defaults to the system output)
+        #[arg(long)]
+        device: Option<String>,
+    },
+
+    /// Type lines to hear them spoken at once, changing voice, pitch, and tempo with /voice, /pitch, and /tempo as you go
+    Interactive {
+        /// Voice ID to start with (defaults to en_GB-alba-medium)
+        #[arg(short, long, default_value = "en_GB-alba-medium")]
+        voice: String,
+
+        /// Pitch factor or preset to start with (e.g. 1.2, slomo, deep, child, helium)
+        #[arg(short, long, value_parser = PitchArg::from_str, default_value = "1.0")]
+        pitch: PitchArg,
+
+        /// Tempo factor to start with (1.0 = normal, 2.0 = slower, 0.5 = faster)
+        #[arg(long, default_value = "1.0")]
+        tempo: f32,
+
+        /// interrupt: a new line cuts off current speech; queue: it waits its turn
+        #[arg(long, value_enum, default_value = "interrupt")]
+        mode: SpeakMode,
+
+        /// Output device name or part of it (see `devices`; defaults to the system output)
+        #[arg(long)]
+        device: Option<String>,
+    },
+
+    /// Perform a dialogue script (`NAME: line`, with a `NAME = voice` cast) into one WAV, each character in their own voice
+    Dialogue {
+        /// Script file: cast lines (`ALBA = en_GB-alba-medium pitch=1.1`) followed by `ALBA: Hello there` lines
+        script: String,
+
+        /// WAV file to write
+        #[arg(short, long)]
+        output: String,
+
+        /// Silence between lines, e.g. 400ms or 1s
+        #[arg(long, default_value = "400ms", value_parser = parse_pause)]
+        gap: Duration,
+
+        /// Pitch factor or preset applied on top of each character's (e.g. 1.2, deep)
+        #[arg(short, long, value_parser = PitchArg::from_str, default_value = "1.0")]
+        pitch: PitchArg,
+
+        /// Tempo factor applied on top of each character's (1.0 = normal, 2.0 = slower, 0.5 = faster)
+        #[arg(long, default_value = "1.0")]
+        tempo: f32,
+    },
+
+    /// Re-run the commands recorded in a --session-log and report whether the audio still matches
+    Replay {
+        /// Session log (JSONL) to replay
+        log: String,
+
+        /// Only replay this entry (1-based)
+        #[arg(long)]
+        entry: Option<usize>,
+    },
+
+    /// Count which phonemes and visemes a script uses, to see which mouth shapes it needs
+    Coverage {
+        /// Script text file
+        script: String,
+
+        /// Voice the script is for; English voices use CMUdict, others the voice's own phonemizer
+        #[arg(short, long, default_value = "en_GB-alba-medium")]
+        voice: String,
+    },
+
+    /// Write an index.html review page for every rendered line in a directory: audio, duration, voice, warnings, and lipsync
+    Gallery {
+        /// Directory of exports (searched recursively)
+        dir: String,
+
+        /// Page title
+        #[arg(long, default_value = "Render review")]
+        title: String,
     },
+
+    /// Add ARPAbet phonemes and visemes to an alignment made elsewhere (WhisperX or similar JSON)
+    Enrich {
+        /// Alignment JSON: WhisperX output, `segments` with `words`, a `words` list, or a list of words
+        input: String,
+
+        /// The text that was spoken; phonemes are looked up for its words (default: the aligned words)
+        #[arg(short, long)]
+        text: Option<String>,
+
+        /// Output JSON file (default: print to stdout)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+
+        /// Ollama model for words no other phoneme source knows (e.g., llama3.2)
+        #[arg(long, value_name = "MODEL")]
+        lipsync_with_llm: Option<String>,
+    },
+
+    /// Print duration, levels, word count, phoneme coverage, and schema version of WAV and JSON outputs
+    Inspect {
+        /// WAV or JSON files to inspect
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+
+    /// Real-time voice changer: pitch-shift live microphone input to speakers or a virtual device
+    Voicechanger {
+        /// Pitch factor or preset (e.g. 1.2, slomo, deep, child, helium)
+        #[arg(short, long, value_parser = PitchArg::from_str, default_value = "deep")]
+        pitch: PitchArg,
+
+        /// Input device name or part of it (defaults to the system microphone)
+        #[arg(long)]
+        device: Option<String>,
+
+        /// Output device name or part of it, e.g. a virtual audio cable (defaults to the system output)
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// List input and output devices and exit
+        #[arg(long)]
+        list_devices: bool,
+    },
+
+    /// Run an HTTP server with /synthesize, /voices, and /lipsync endpoints for game engines and web apps
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        bind: String,
+
+        /// Voice for requests that don't name one
+        #[arg(short, long, default_value = "en_GB-alba-medium")]
+        voice: String,
+
+        /// Allow browser requests from this origin (sent as Access-Control-Allow-Origin, e.g. `*`)
+        #[arg(long, value_name = "ORIGIN")]
+        allow_origin: Option<String>,
+    },
+
+    /// Keep voice models loaded and run synthesis jobs sent with `enqueue`, for interactive apps that can't wait on a cold start
+    Daemon {
+        /// Unix socket to listen on (`host:port` on Windows; default: pitch-tts.sock in the runtime directory)
+        #[arg(long)]
+        socket: Option<String>,
+
+        /// Voice for jobs that don't name one, loaded at startup
+        #[arg(short, long, default_value = "en_GB-alba-medium")]
+        voice: String,
+
+        /// Ask the running daemon to stop after its current job instead of starting one
+        #[arg(long)]
+        stop: bool,
+    },
+
+    /// Queue a synthesis job on the running daemon and print its job ID
+    Enqueue {
+        /// Text to speak
+        text: String,
+
+        /// WAV file to write
+        #[arg(short, long)]
+        output: String,
+
+        /// Voice (default: the daemon's)
+        #[arg(short, long)]
+        voice: Option<String>,
+
+        /// Pitch factor or preset (e.g. 1.2, slomo, deep, child, helium)
+        #[arg(long, value_parser = PitchArg::from_str, default_value = "1.0")]
+        pitch: PitchArg,
+
+        /// Tempo (1.0 = normal, 2.0 = slower, 0.5 = faster)
+        #[arg(long, default_value = "1.0")]
+        tempo: f32,
+
+        /// Also write lipsync JSON next to the WAV
+        #[arg(long, value_enum)]
+        lipsync: Option<LipsyncLevel>,
+
+        /// Wait for the job to finish and report how it went
+        #[arg(long)]
+        wait: bool,
+
+        /// The daemon's socket (see `daemon --socket`)
+        #[arg(long)]
+        socket: Option<String>,
+    },
+
+    /// Show the daemon's queued, running, and recent jobs, or one job by ID
+    Status {
+        job: Option<u64>,
+
+        /// The daemon's socket (see `daemon --socket`)
+        #[arg(long)]
+        socket: Option<String>,
+    },
+
+    /// Cancel a queued daemon job, or stop a running one
+    Cancel {
+        job: u64,
+
+        /// The daemon's socket (see `daemon --socket`)
+        #[arg(long)]
+        socket: Option<String>,
+    },
+
+    /// Manage installed voice models
+    Voices {
+        #[command(subcommand)]
+        action: VoicesAction,
+    },
+
+    /// List audio output devices, for --device
+    Devices,
+
+    /// Show or change defaults in the config file (see `config path`)
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
     /// Show information about data directories
     Info,
 }
 
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print one setting, or all of them
+    Get {
+        /// voice, pitch, tempo, models_dir, output_dir, lipsync, or lipsync_with_llm
+        key: Option<String>,
+    },
+    /// Change a setting; flags given on the command line still override it
+    Set {
+        key: String,
+        value: String,
+    },
+    /// Remove a setting, going back to the built-in default
+    Unset {
+        key: String,
+    },
+    /// Print the location of the config file
+    Path,
+}
+
+#[derive(Subcommand)]
+enum VoicesAction {
+    /// Download voice models so they're available offline
+    Install {
+        /// Voice ID to install (e.g., en_GB-alba-medium)
+        #[arg(required_unless_present_any = ["language", "all"])]
+        id: Option<String>,
+
+        /// Install every voice for a language (en) or locale (en_GB)
+        #[arg(long, conflicts_with = "id")]
+        language: Option<String>,
+
+        /// Install every voice in the catalog
+        #[arg(long, conflicts_with_all = ["id", "language"])]
+        all: bool,
+
+        /// Also install the int8 quantized model, where upstream provides one
+        #[arg(long)]
+        quantized: bool,
+
+        /// Voices to download at once with --language or --all (0 is treated as 1)
+        #[arg(short, long, default_value = "4")]
+        jobs: usize,
+    },
+    /// Delete installed voice models to free disk space
+    #[command(visible_alias = "uninstall")]
+    Remove {
+        /// Voice ID to remove
+        #[arg(required_unless_present_any = ["language", "all"])]
+        id: Option<String>,
+
+        /// Remove every installed voice for a language (en) or locale (en_GB)
+        #[arg(long, conflicts_with = "id")]
+        language: Option<String>,
+
+        /// Remove every installed voice
+        #[arg(long, conflicts_with_all = ["id", "language"])]
+        all: bool,
+    },
+    /// Pin an installed voice to its recorded upstream revision, so every install gets identical files
+    Pin {
+        /// Voice ID to pin
+        id: String,
+    },
+    /// Move voices to the latest upstream revision (pinned voices are re-pinned there)
+    #[command(visible_alias = "update")]
+    Upgrade {
+        /// Voice ID to upgrade (defaults to every installed voice and every voice in the lockfile)
+        id: Option<String>,
+
+        /// Only upgrade voices for a language (en) or locale (en_GB)
+        #[arg(long, conflicts_with = "id")]
+        language: Option<String>,
+    },
+    /// List the speakers of a multi-speaker voice, for --speaker-id
+    Speakers {
+        /// Voice ID (e.g., en_GB-vctk-medium)
+        id: String,
+    },
+    /// Register a Piper voice you trained yourself, so it can be used like a catalog voice
+    AddLocal {
+        /// ID to use it by, e.g. with --voice
+        #[arg(long)]
+        id: String,
+
+        /// The voice's .onnx model
+        #[arg(long, value_name = "FILE")]
+        model: String,
+
+        /// The voice's config (defaults to the model's path with .json added)
+        #[arg(long, value_name = "FILE")]
+        config: Option<String>,
+    },
+}
+
 fn print_info() {
     let app_data = text_to_face::get_app_data_dir();
     let models_dir = text_to_face::get_models_dir();
     let cmudict_path = text_to_face::get_cmudict_path();
-    println!("App data directory: {}", app_data.display());
-    println!("Models directory: {}", models_dir.display());
-    println!("CMUdict path: {}", cmudict_path.display());
+    note!("Config file: {}", config::config_path().display());
+    note!("App data directory: {}", app_data.display());
+    note!("Models directory: {}", models_dir.display());
+    note!("CMUdict path: {}", cmudict_path.display());
+    note!("Rules file: {}", text_to_face::rules::default_rules_path().display());
+    note!("Lexicon file: {}", phoneme_sources::default_lexicon_path().display());
+    note!("Voice overrides: {}", text_to_face::overrides::override_path("<voice>").display());
+    match text_to_face::catalog::manifest_path() {
+        Some(path) => note!("Voice manifest: {}", path.display()),
+        None => note!("Voice manifest: none (add voices.toml to the app data directory)"),
+    }
 }
 
+mod config;
+mod logger;
+
 mod commands {
     pub mod list;
     pub mod say;
     pub mod export;
+    pub mod audiobook;
+    pub mod batch;
+    pub mod config;
+    pub mod coverage;
+    pub mod daemon;
+    pub mod devices;
+    pub mod dialogue;
+    pub mod enrich;
+    pub mod inspect;
+    pub mod interactive;
+    pub mod read;
+    pub mod replay;
+    pub mod sing;
+    pub mod voicechanger;
+    pub mod voices;
 }
 
 fn main() {
-    let cli = Cli::parse();
+    // The config file's values become flag defaults, so it's read before the command line is parsed
+    let config = config::Config::load(&config::config_path()).unwrap_or_else(|e| {
+        eprintln!("Ignoring {}: {}", config::config_path().display(), e);
+        config::Config::default()
+    });
+    let matches = config.apply_defaults(Cli::command()).get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    text_to_face::set_models_dir(config.models_dir);
+    // Checked when the config file was loaded
+    let acronyms = cli.acronyms.or_else(|| config.acronyms.and_then(|mode| mode.parse().ok())).unwrap_or_default();
+    text_to_face::abbreviations::set_settings(AbbreviationSettings { acronyms, user: config.abbreviations });
+    output::start(matches.subcommand_name().unwrap_or(""), cli.json);
+    logger::init();
+    if let Err(e) = apply_global_options(&cli) {
+        fail!("{}", e);
+        output::exit(1);
+    }
+    // Replays record their own entries, so the replay command itself is never logged
+    let recording = cli.session_log.is_some() && !matches!(cli.command, Some(Commands::Replay { .. }));
+    if recording {
+        session::start(cli.session_log.as_ref().map(PathBuf::from), std::env::args().collect());
+    }
+    run(&cli);
+    // Anything not already reported with its command's output
+    print_warnings(&text_to_face::warnings::take_warnings());
+    if recording && let Err(e) = session::finish() {
+        fail!("Failed to write session log: {}", e);
+    }
+    if !output::finish() {
+        std::process::exit(1);
+    }
+}
+
+/// Print a synthesis's lipsync JSON, when it wasn't written to a file, and then its warnings
+pub fn print_report(report: &SynthesisReport) {
+    if let Some(json) = &report.lipsync_json {
+        print_lipsync(json);
+    }
+    print_warnings(&report.warnings);
+}
+
+/// Print lipsync JSON that has no file of its own, or put it under `lipsync` in the `--json` result
+pub fn print_lipsync(json: &serde_json::Value) {
+    if output::is_json() {
+        output::record("lipsync", json);
+    } else {
+        println!("{}", serde_json::to_string_pretty(json).unwrap_or_default());
+    }
+}
+
+/// Print a synthesis's warnings after its output
+pub fn print_warnings(warnings: &[Warning]) {
+    if warnings.is_empty() {
+        return;
+    }
+    output::record_warnings(warnings);
+    eprintln!("{}", format!("{} warning(s):", warnings.len()).yellow());
+    for warning in warnings {
+        eprintln!("  {}", warning);
+    }
+}
+
+/// Apply the global flags to the library's settings
+fn apply_global_options(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    // Before anything loads a voice, so thread pools start on the pinned cores
+    if cli.nice {
+        for problem in nice::enable(NiceSettings::new(cli.nice_threads)) {
+            eprintln!("{} {}", "[Nice]".yellow(), problem);
+        }
+    }
+    text_to_face::data_dir::set_data_dir(cli.data_dir.as_ref().map(PathBuf::from));
+    text_to_face::data_dir::migrate();
+    text_to_face::low_memory::set_enabled(cli.low_memory);
+    set_accelerator(cli.accelerator)?;
+    text_to_face::model_cache::set_capacity(cli.model_cache);
+    text_to_face::set_prefer_quantized(cli.quantized);
+    text_to_face::normalize::set_enabled(!cli.no_normalize);
+    text_to_face::pinning::set_lockfile_path(cli.voice_lock.as_ref().map(PathBuf::from));
+    // Checked up front, since a broken manifest is otherwise only logged and ignored
+    if let Some(path) = &cli.catalog {
+        text_to_face::catalog::CatalogManifest::load(Path::new(path))?.apply(text_to_face::builtin_voices()).map_err(|e| format!("{}: {}", path, e))?;
+    }
+    text_to_face::catalog::set_manifest_path(cli.catalog.as_ref().map(PathBuf::from));
+    speakers::set_speaker(cli.speaker_id.clone());
+    set_pauses(PauseSettings { sentence: cli.sentence_pause, paragraph: cli.paragraph_pause });
+    text_to_face::pause_markers::set_ellipsis_pause(cli.ellipsis_pause);
+    text_to_face::humanize::set_amount(cli.humanize.unwrap_or(0.0));
+    text_to_face::emoji::set_mode(cli.emoji);
+    text_to_face::dsp::set_stretch_quality(cli.stretch_quality);
+    let prompt_template = cli.llm_prompt.as_deref().map(|path| LlmFallbackSettings::load_prompt_template(Path::new(path))).transpose()?;
+    llm_fallback::set_settings(LlmFallbackSettings { max_words: cli.llm_max_words, temperature: cli.llm_temperature, prompt_template, strict: cli.strict });
+    set_limits(ResourceLimits {
+        max_text_chars: cli.max_text_chars,
+        max_audio_seconds: cli.max_audio_seconds,
+        max_memory_bytes: cli.max_memory_mb.map(|mb| mb * 1_000_000),
+    });
+    let voice = match &cli.command {
+        Some(Commands::Say { voice, .. } | Commands::Export { voice, .. } | Commands::Audiobook { voice, .. } | Commands::Batch { voice, .. } | Commands::Read { voice, .. } | Commands::Interactive { voice, .. } | Commands::Daemon { voice, .. }) => Some(voice.as_str()),
+        _ => None,
+    };
+    // Voice IDs start with their language, e.g. en_GB-alba-medium
+    let voice_language = voice.and_then(|v| v.split(['_', '-']).next()).map(str::to_string);
+    let language = cli.align_language.clone().or_else(|| voice_language.clone());
+    set_transcript_alignment(cli.align_only.then(|| language.unwrap_or_else(|| "en".to_string())));
+    load_rules(cli.rules.as_deref().map(Path::new)).map_err(|e| format!("Failed to load rules: {}", e))?;
+    effects::set_effect(cli.effect);
+    set_stereo_output(StereoOutput::new(cli.channels, cli.pan)?);
+    set_rate(cli.rate.map(|rate| rate.settings(voice_language.as_deref())).unwrap_or(RateSettings::NORMAL));
+    phoneme_sources::set_settings(PhonemeSourceSettings { orders: cli.phoneme_sources.clone(), language: voice_language.clone() });
+    let device = match &cli.command {
+        Some(Commands::Say { device, .. } | Commands::Read { device, .. } | Commands::Interactive { device, .. } | Commands::Sing { device, .. }) => device.clone(),
+        None => cli.device.clone(),
+        _ => None,
+    };
+    text_to_face::devices::set_output_device(device);
+    let lexicons: Vec<&Path> = cli.lexicon.iter().map(Path::new).collect();
+    phoneme_sources::load_lexicons(&lexicons).map_err(|e| format!("Failed to load lexicon: {}", e))?;
+    Ok(())
+}
+
+/// The text to read: the text argument, or everything on stdin when `piped` (`-` or `--stdin`), converted
+/// from Markdown for `--input-format markdown`
+fn input_text(text: &str, piped: bool, format: InputFormat, code_blocks: CodeBlocks) -> String {
+    let text = read_input(text, piped, format);
+    if format == InputFormat::Plain {
+        return text;
+    }
+    // Markdown gets the longer paragraph pause unless --paragraph-pause set one
+    let pauses = text_to_face::pauses::pauses();
+    set_pauses(PauseSettings { paragraph: pauses.paragraph.or(Some(markdown::PARAGRAPH_PAUSE)), ..pauses });
+    let text = markdown::markdown_to_text(&text, code_blocks);
+    if text.is_empty() {
+        fail!("Nothing to read in the Markdown");
+        output::exit(1);
+    }
+    text
+}
+
+/// The text argument or stdin. Hard-wrapped plain lines are joined, keeping blank lines as paragraph
+/// breaks, so piped notes are read as sentences rather than lines.
+fn read_input(text: &str, piped: bool, format: InputFormat) -> String {
+    if !piped {
+        return text.to_string();
+    }
+    if std::io::stdin().is_terminal() {
+        note!("Reading text from stdin (end with Ctrl-D)");
+    }
+    let mut input = String::new();
+    if let Err(e) = std::io::stdin().read_to_string(&mut input) {
+        fail!("Failed to read stdin: {}", e);
+        output::exit(1);
+    }
+    // Markdown's line structure is kept for its own parsing
+    let text = if format == InputFormat::Markdown { input } else { text_to_face::pauses::split_paragraphs(&input).join("\n\n") };
+    if text.trim().is_empty() {
+        fail!("No text on stdin");
+        output::exit(1);
+    }
+    text
+}
+
+fn run(cli: &Cli) {
     match &cli.command {
-        Some(Commands::List { by_language, json, installed, not_installed }) => handle_list(*by_language, *json, *installed, *not_installed),
-        Some(Commands::Say { voice, text, pitch, tempo, lipsync }) => handle_say(voice, text, pitch, *tempo, *lipsync),
-        Some(Commands::Export { voice, output, text, pitch, tempo, lipsync, json_output, lipsync_with_llm }) => handle_export(voice, output.as_deref(), text, pitch, *tempo, *lipsync, json_output, lipsync_with_llm.clone()),
+        Some(Commands::List { by_language, installed, not_installed, language, quality, country, search }) => {
+            let filter = VoiceFilter { language: language.clone(), quality: quality.clone(), country: country.clone(), search: search.clone() };
+            handle_list(*by_language, cli.json, *installed, *not_installed, &filter)
+        }
+        Some(Commands::Say { voice, text, pitch, tempo, lipsync, vmc, morph, ssml, stdin, input_format, code_blocks, .. }) => {
+            let piped = *stdin || text == "-";
+            let text = &input_text(text, piped, *input_format, *code_blocks);
+            handle_say(voice, text, pitch, rated_tempo(*tempo), *lipsync, vmc.as_deref(), morph.as_ref(), *ssml, piped)
+        }
+        Some(Commands::Export { voice, output, text, pitch, tempo, lipsync, json_output, lipsync_format, lipsync_with_llm, title, artist, comment, sidecar, force, karaoke, subtitles, subtitle_line_length, preview, stream, sprite_layout, sprite_track, blender_keys, live2d_motion, gltf, unity_anim, unity_mesh_path, blendshapes, markers, morph, verify, prosody, viseme_scheme, music, duck, music_gain, fps, takes, start_at, max_duration, trim_silence, format, bitrate, frame_size, quality, stdin, input_format, code_blocks }) => {
+            let tempo = &rated_tempo(*tempo);
+            let text = &input_text(text, *stdin || text == "-", *input_format, *code_blocks);
+            // A morph is named after both voices in file names, tags, and the sidecar
+            let voice = &morph.as_ref().map(MorphSpec::label).unwrap_or_else(|| voice.clone());
+            let tags = AudioTags {
+                title: title.clone(),
+                artist: artist.clone(),
+                comment: comment.clone(),
+                ..AudioTags::for_synthesis(voice, pitch.as_factor(), *tempo)
+            };
+            let options = ExportOptions {
+                tags,
+                sidecar: *sidecar,
+                force: *force,
+                karaoke: *karaoke,
+                subtitles: subtitles.clone(),
+                subtitle_line_length: *subtitle_line_length,
+                lipsync_format: *lipsync_format,
+                preview: *preview,
+                stream: *stream,
+                sprite_layout: sprite_layout.clone(),
+                sprite_track: sprite_track.clone(),
+                blender_keys: blender_keys.clone(),
+                live2d_motion: live2d_motion.clone(),
+                gltf: gltf.clone(),
+                unity_anim: unity_anim.clone(),
+                unity_mesh_path: unity_mesh_path.clone(),
+                blendshapes: *blendshapes,
+                markers: *markers,
+                morph: morph.clone(),
+                verify: *verify,
+                prosody: *prosody,
+                viseme_scheme: *viseme_scheme,
+                music: music.clone(),
+                duck: DuckSettings { music_gain_db: *music_gain, duck_db: *duck, ..DuckSettings::default() },
+                fps: *fps,
+                takes: *takes as usize,
+                trim: TrimRange { start: *start_at, max_duration: *max_duration },
+                // A threshold is below full scale whichever way it's written
+                trim_silence: trim_silence.map(|db| -db.abs()),
+                encoding: EncodeSettings {
+                    format: format.or_else(|| output.as_deref().and_then(|o| AudioFormat::from_path(Path::new(o)))).unwrap_or(AudioFormat::Wav),
+                    bitrate_kbps: *bitrate,
+                    quality: *quality,
+                    opus_frame_ms: *frame_size,
+                },
+            };
+            handle_export(voice, output.as_deref(), text, pitch, *tempo, *lipsync, json_output, lipsync_with_llm.clone(), options)
+        }
+        Some(Commands::Audiobook { input, voice, output, chapter_files, pitch, tempo, title, author, narrator, year, bitrate, sidecar, force, lipsync, lipsync_with_llm, align_jobs, verify }) => {
+            let tempo = &rated_tempo(*tempo);
+            if *verify && text_to_face::alignment::transcript_alignment().is_some() {
+                note!("--verify needs WhisperX's speech recognition; ignoring --align-only");
+                set_transcript_alignment(None);
+            }
+            let metadata = text_to_face::audiobook::AudiobookMetadata {
+                title: title.clone(),
+                author: author.clone(),
+                narrator: narrator.clone().or_else(|| Some(voice.clone())),
+                year: year.clone(),
+                comment: Some(AudioTags::for_synthesis(voice, pitch.as_factor(), *tempo).params_string()),
+            };
+            let options = text_to_face::audiobook::AudiobookOptions {
+                bitrate_kbps: *bitrate,
+                force: *force,
+                // Verification needs the alignment pass
+                lipsync: if *verify { LipsyncLevel::High.max(*lipsync) } else { *lipsync },
+                lipsync_with_llm: lipsync_with_llm.clone(),
+                align_jobs: *align_jobs,
+                verify: *verify,
+                chapter_files: *chapter_files,
+            };
+            let output = output.as_deref().unwrap_or(if *chapter_files { "audiobook" } else { "audiobook.m4b" });
+            handle_audiobook(input, voice, output, pitch, *tempo, metadata, options, *sidecar)
+        }
+        Some(Commands::Batch { input, voice, tempo, output_dir, jobs, force, report }) => {
+            let options = text_to_face::batch::BatchOptions {
+                default_voice: voice.clone(),
+                default_tempo: *tempo,
+                output_dir: PathBuf::from(output_dir),
+                jobs: *jobs,
+                force: *force,
+            };
+            handle_batch(input, options, report.as_deref())
+        }
+        Some(Commands::Coverage { script, voice }) => handle_coverage(script, voice, cli.json),
+        Some(Commands::Gallery { dir, title }) => match text_to_face::gallery::write_gallery(Path::new(dir), title) {
+            Ok((path, lines)) => note!("Review gallery of {} lines written to {}", lines, path.display()),
+            Err(e) => fail!("Failed to write gallery: {}", e),
+        },
+        Some(Commands::Enrich { input, text, output, lipsync_with_llm }) => handle_enrich(input, text.as_deref(), output.as_deref(), lipsync_with_llm.as_deref()),
+        Some(Commands::Inspect { files }) => handle_inspect(files, cli.json),
+        Some(Commands::Voicechanger { pitch, device, output, list_devices }) => handle_voicechanger(pitch, device.as_deref(), output.as_deref(), *list_devices),
+        Some(Commands::Voices { action: VoicesAction::Install { id, language, all, quantized, jobs } }) => handle_voices_install(id.as_deref(), language.as_deref(), *all, *quantized, *jobs),
+        Some(Commands::Voices { action: VoicesAction::Remove { id, language, all } }) => handle_voices_remove(id.as_deref(), language.as_deref(), *all),
+        Some(Commands::Voices { action: VoicesAction::Pin { id } }) => handle_voices_pin(id),
+        Some(Commands::Voices { action: VoicesAction::Upgrade { id, language } }) => handle_voices_upgrade(id.as_deref(), language.as_deref()),
+        Some(Commands::Voices { action: VoicesAction::Speakers { id } }) => handle_voices_speakers(id, cli.json),
+        Some(Commands::Voices { action: VoicesAction::AddLocal { id, model, config } }) => handle_voices_add_local(id, model, config.as_deref()),
+        Some(Commands::Read { voice, pitch, tempo, mode, .. }) => handle_read(voice, pitch, rated_tempo(*tempo), *mode),
+        Some(Commands::Interactive { voice, pitch, tempo, mode, .. }) => handle_interactive(voice, pitch, *tempo, *mode),
+        Some(Commands::Sing { text, melody, midi, bpm, voice, output, .. }) => handle_sing(text, melody.as_deref(), midi.as_deref(), *bpm, voice, output.as_deref()),
+        Some(Commands::Dialogue { script, output, gap, pitch, tempo }) => handle_dialogue(script, output, *gap, pitch.as_factor(), rated_tempo(*tempo)),
+        Some(Commands::Replay { log, entry }) => handle_replay(log, *entry),
+        Some(Commands::Serve { bind, voice, allow_origin }) => {
+            let options = ServerOptions { bind: bind.clone(), default_voice: voice.clone(), allow_origin: allow_origin.clone() };
+            if let Err(e) = text_to_face::server::serve(&options) {
+                fail!("Error: {}", e);
+            }
+        }
+        Some(Commands::Daemon { socket, voice, stop }) => handle_daemon(socket.as_deref(), voice, *stop),
+        Some(Commands::Enqueue { text, output, voice, pitch, tempo, lipsync, wait, socket }) => {
+            handle_enqueue(text, output, voice.as_deref(), pitch, *tempo, *lipsync, *wait, socket.as_deref())
+        }
+        Some(Commands::Status { job, socket }) => handle_status(*job, socket.as_deref()),
+        Some(Commands::Cancel { job, socket }) => handle_cancel(*job, socket.as_deref()),
+        Some(Commands::Devices) => handle_devices(cli.json),
+        Some(Commands::Config { action: ConfigAction::Get { key } }) => handle_config_get(key.as_deref()),
+        Some(Commands::Config { action: ConfigAction::Set { key, value } }) => handle_config_set(key, Some(value)),
+        Some(Commands::Config { action: ConfigAction::Unset { key } }) => handle_config_set(key, None),
+        Some(Commands::Config { action: ConfigAction::Path }) => note!("{}", config::config_path().display()),
         Some(Commands::Info) => print_info(),
         None => {
             // Show help by default instead of playing audio
@@ -139,36 +1308,56 @@ fn main() {
                 // If voice or text is provided, play audio (legacy behavior)
                 let voice_id = cli.voice.as_deref().unwrap_or("en_GB-alba-medium");
                 let text = cli.text.as_deref().unwrap_or("Hello! I'm playing audio from memory directly with piper-rs.");
-                println!("Using voice: {}", voice_id);
+                note!("Using voice: {}", voice_id);
                 let _pitch_factor = cli.pitch.as_ref().map(|p| p.as_factor()).unwrap_or(1.0);
                 match synth_with_voice_config(text.to_string(), voice_id) {
                     Ok(samples) => {
-                        let (_stream, handle) = rodio::OutputStream::try_default().unwrap();
+                        let (_stream, handle) = text_to_face::devices::open_output_stream().unwrap();
                         let sink = rodio::Sink::try_new(&handle).unwrap();
-                        let buf = SamplesBuffer::new(1, 22050, samples);
+                        let buf = text_to_face::stereo::playback_buffer(&samples, voice_sample_rate(voice_id));
                         sink.append(buf);
                         sink.sleep_until_end();
                     }
-                    Err(e) => eprintln!("Error: {}", e),
+                    Err(e) => fail!("Error: {}", e),
                 }
             } else {
                 // Show help by default
-                println!("text-to-face - A text-to-speech tool with pitch shifting, voice selection, and lipsync generation");
-                println!();
-                println!("USAGE:");
-                println!("    text-to-face <SUBCOMMAND>");
-                println!();
-                println!("SUBCOMMANDS:");
-                println!("    list     List all available voices");
-                println!("    say      Synthesize speech and play it");
-                println!("    export   Export speech to WAV file");
-                println!("    help     Print this message or the help of the given subcommand(s)");
-                println!();
-                println!("OPTIONS:");
-                println!("    -h, --help       Print help");
-                println!("    -V, --version    Print version");
-                println!();
-                println!("For more information on a specific command, try 'text-to-face <COMMAND> --help'");
+                note!("text-to-face - A text-to-speech tool with pitch shifting, voice selection, and lipsync generation");
+                note!();
+                note!("USAGE:");
+                note!("    text-to-face <SUBCOMMAND>");
+                note!();
+                note!("SUBCOMMANDS:");
+                note!("    list     List all available voices");
+                note!("    say      Synthesize speech and play it");
+                note!("    export   Export speech to WAV file");
+                note!("    audiobook  Render a text project into a chaptered M4B audiobook");
+                note!("    batch    Render every row of a JSONL or CSV file, resuming where it left off");
+                note!("    cancel   Cancel a queued daemon job, or stop a running one");
+                note!("    config   Show or change defaults in the config file (config get|set|unset|path)");
+                note!("    coverage  Count the phonemes and visemes a script uses");
+                note!("    daemon   Keep voice models loaded and run jobs sent with enqueue");
+                note!("    devices  List audio output devices for --device");
+                note!("    dialogue  Perform a dialogue script into one WAV, each character in their own voice");
+                note!("    enqueue  Queue a synthesis job on the running daemon");
+                note!("    enrich   Add phonemes and visemes to an alignment JSON made elsewhere");
+                note!("    gallery  Write an index.html review page for a directory of exports");
+                note!("    inspect  Show stats for WAV and lipsync JSON outputs");
+                note!("    interactive  Type lines to hear them spoken, changing voice, pitch, and tempo as you go");
+                note!("    read     Read stdin aloud continuously, interrupting on new input");
+                note!("    replay   Re-run the commands in a --session-log and compare the audio");
+                note!("    serve    Run an HTTP API for synthesis, voices, and lipsync");
+                note!("    sing     Sing lyrics to a melody (experimental)");
+                note!("    status   Show the daemon's queued, running, and recent jobs");
+                note!("    voicechanger  Pitch-shift your microphone live");
+                note!("    voices   Install, remove, pin, upgrade, and list speakers of voice models (voices install|remove|pin|upgrade|speakers)");
+                note!("    help     Print this message or the help of the given subcommand(s)");
+                note!();
+                note!("OPTIONS:");
+                note!("    -h, --help       Print help");
+                note!("    -V, --version    Print version");
+                note!();
+                note!("For more information on a specific command, try 'text-to-face <COMMAND> --help'");
             }
         }
     }
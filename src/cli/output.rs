@@ -0,0 +1,116 @@
+//! `--json`: one JSON object on stdout describing what a command did (files written, durations,
+//! timings, warnings, errors), with the usual human-readable messages moved to stderr so scripts
+//! can parse stdout as is.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Human-readable output: stdout normally, stderr under `--json` so stdout holds only the result
+macro_rules! note {
+    ($($arg:tt)*) => {
+        if $crate::output::is_json() { eprintln!($($arg)*) } else { println!($($arg)*) }
+    };
+}
+
+/// A failure message on stderr, also recorded under `errors` in the `--json` result
+macro_rules! fail {
+    ($($arg:tt)*) => {{
+        let message = format!($($arg)*);
+        eprintln!("{}", message);
+        $crate::output::record_error(message);
+    }};
+}
+
+static JSON: AtomicBool = AtomicBool::new(false);
+/// The subcommand being run, named in the result
+static COMMAND: Mutex<String> = Mutex::new(String::new());
+/// Set once a command has printed its own JSON document, which then stands in for the result
+static DOCUMENT_PRINTED: AtomicBool = AtomicBool::new(false);
+static RESULT: Mutex<Option<Map<String, Value>>> = Mutex::new(None);
+
+/// Start collecting the result of `command` when `json` is set
+pub fn start(command: &str, json: bool) {
+    JSON.store(json, Ordering::Relaxed);
+    *COMMAND.lock().unwrap() = command.to_string();
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+fn with_result(update: impl FnOnce(&mut Map<String, Value>)) {
+    if is_json() {
+        update(RESULT.lock().unwrap().get_or_insert_with(Map::new));
+    }
+}
+
+/// Set a field of the result, e.g. `duration` or `lipsync`
+pub fn record(key: &str, value: impl Serialize) {
+    if let Ok(value) = serde_json::to_value(value) {
+        with_result(|result| {
+            result.insert(key.to_string(), value);
+        });
+    }
+}
+
+/// Add an entry to a list field of the result, e.g. one per take under `exports`
+pub fn append(key: &str, value: impl Serialize) {
+    if let Ok(value) = serde_json::to_value(value) {
+        with_result(|result| {
+            if let Some(list) = result.entry(key).or_insert_with(|| Value::Array(Vec::new())).as_array_mut() {
+                list.push(value);
+            }
+        });
+    }
+}
+
+/// Note a file the command wrote, under `files` with what it holds (`audio`, `lipsync`, `subtitles`, ...)
+pub fn record_file(kind: &str, path: impl AsRef<Path>) {
+    append("files", serde_json::json!({ "kind": kind, "path": path.as_ref().to_string_lossy() }));
+}
+
+pub fn record_error(message: impl Into<String>) {
+    append("errors", message.into());
+}
+
+pub fn record_warnings(warnings: &[text_to_face::warnings::Warning]) {
+    for warning in warnings {
+        append("warnings", serde_json::json!({ "message": warning.to_string(), "warning": warning }));
+    }
+}
+
+/// Print a command's own JSON document (`list`, `devices`, `inspect`, ...) on stdout. Under `--json`
+/// it is the whole output, in the same shape these commands have always printed.
+pub fn print_document(value: &impl Serialize) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => {
+            println!("{}", json);
+            DOCUMENT_PRINTED.store(true, Ordering::Relaxed);
+        }
+        Err(e) => fail!("Failed to serialize JSON: {}", e),
+    }
+}
+
+/// Print the command's result under `--json` (unless it printed its own document); returns whether
+/// it finished without errors
+pub fn finish() -> bool {
+    let mut result = RESULT.lock().unwrap().take().unwrap_or_default();
+    let ok = result.get("errors").is_none_or(|errors| errors.as_array().is_none_or(|e| e.is_empty()));
+    if is_json() && !DOCUMENT_PRINTED.load(Ordering::Relaxed) {
+        let mut object = Map::new();
+        object.insert("command".to_string(), Value::from(COMMAND.lock().unwrap().as_str()));
+        object.insert("ok".to_string(), Value::from(ok));
+        object.append(&mut result);
+        println!("{}", serde_json::to_string_pretty(&Value::Object(object)).unwrap_or_default());
+    }
+    ok
+}
+
+/// Exit with `code`, printing the `--json` result first
+pub fn exit(code: i32) -> ! {
+    finish();
+    std::process::exit(code)
+}
@@ -0,0 +1,261 @@
+//! Abbreviation and acronym expansion, run as part of [`crate::normalize::normalize_for_voice`] before
+//! numbers are spelled out: `Dr.` becomes "Doctor", `5 km` "5 kilometres", and acronyms are either
+//! spelled out letter by letter (`FBI`) or read as a word (`NASA`).
+//!
+//! The built-in expansions are English and apply to English voices. User abbreviations, from the
+//! `[abbreviations]` table of the config file, apply to every voice and win over the built-ins:
+//!
+//! ```toml
+//! acronyms = "auto"
+//!
+//! [abbreviations]
+//! "Rd." = "Road"
+//! ISBN = "I S B N"
+//! GIF = "jif"
+//! ```
+
+use crate::normalize::{Language, Locale};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// How all-caps words such as `FBI` and `NASA` are read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AcronymMode {
+    /// Read the known word acronyms (`NASA`, `NATO`) as words and spell out known initialisms (`FBI`)
+    /// and acronyms without vowels (`BBC`); leave other capitals, such as shouted words, as written
+    #[default]
+    Auto,
+    /// Spell out every all-caps word of two to five letters except the known word acronyms
+    Spell,
+    /// Read every acronym as a word
+    Word,
+}
+
+impl AcronymMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AcronymMode::Auto => "auto",
+            AcronymMode::Spell => "spell",
+            AcronymMode::Word => "word",
+        }
+    }
+}
+
+impl FromStr for AcronymMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "auto" => Ok(AcronymMode::Auto),
+            "spell" => Ok(AcronymMode::Spell),
+            "word" => Ok(AcronymMode::Word),
+            _ => Err(format!("Invalid acronym mode '{}': expected auto, spell, or word", s)),
+        }
+    }
+}
+
+/// The acronym mode and the user's own abbreviations
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AbbreviationSettings {
+    pub acronyms: AcronymMode,
+    /// Word as written (with its full stop, if it has one) to what is said, e.g. `"Rd." = "Road"`
+    pub user: BTreeMap<String, String>,
+}
+
+/// Set from `--acronyms` and the config file
+static SETTINGS: Mutex<AbbreviationSettings> = Mutex::new(AbbreviationSettings { acronyms: AcronymMode::Auto, user: BTreeMap::new() });
+
+pub fn set_settings(settings: AbbreviationSettings) {
+    *SETTINGS.lock().unwrap() = settings;
+}
+
+pub fn settings() -> AbbreviationSettings {
+    SETTINGS.lock().unwrap().clone()
+}
+
+#[cfg(feature = "full")]
+/// Sidecar effect labels for non-default settings, so changing them invalidates up-to-date checks
+pub fn effects() -> Vec<String> {
+    use sha2::{Digest, Sha256};
+    let settings = settings();
+    let mut effects = Vec::new();
+    if settings.acronyms != AcronymMode::Auto {
+        effects.push(format!("acronyms={}", settings.acronyms.name()));
+    }
+    if !settings.user.is_empty() {
+        let mut hasher = Sha256::new();
+        for (written, said) in &settings.user {
+            hasher.update(written.as_bytes());
+            hasher.update([0u8]);
+            hasher.update(said.as_bytes());
+            hasher.update([0u8]);
+        }
+        effects.push(format!("abbreviations={}", hasher.finalize().iter().take(6).map(|b| format!("{:02x}", b)).collect::<String>()));
+    }
+    effects
+}
+
+/// English abbreviations
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("Dr.", "Doctor"),
+    ("Mr.", "Mister"),
+    ("Mrs.", "Missus"),
+    ("Ms.", "Miz"),
+    ("Prof.", "Professor"),
+    ("Mt.", "Mount"),
+    ("Capt.", "Captain"),
+    ("Gen.", "General"),
+    ("Sgt.", "Sergeant"),
+    ("Jr.", "Junior"),
+    ("Sr.", "Senior"),
+    ("vs.", "versus"),
+    ("etc.", "et cetera"),
+    ("e.g.", "for example"),
+    ("i.e.", "that is"),
+    ("approx.", "approximately"),
+];
+
+/// Units read after a number: (written, singular, plural), in British spelling
+const UNITS: &[(&str, &str, &str)] = &[
+    ("km", "kilometre", "kilometres"),
+    ("cm", "centimetre", "centimetres"),
+    ("mm", "millimetre", "millimetres"),
+    ("kg", "kilogram", "kilograms"),
+    ("mg", "milligram", "milligrams"),
+    ("ml", "millilitre", "millilitres"),
+    ("lb", "pound", "pounds"),
+    ("lbs", "pound", "pounds"),
+    ("oz", "ounce", "ounces"),
+    ("ft", "foot", "feet"),
+    ("mi", "mile", "miles"),
+    ("mph", "mile per hour", "miles per hour"),
+    ("km/h", "kilometre per hour", "kilometres per hour"),
+    ("kWh", "kilowatt hour", "kilowatt hours"),
+    ("GB", "gigabyte", "gigabytes"),
+    ("MB", "megabyte", "megabytes"),
+    ("hrs", "hour", "hours"),
+    ("mins", "minute", "minutes"),
+    ("secs", "second", "seconds"),
+];
+
+/// Acronyms read as words
+const WORD_ACRONYMS: &[&str] = &["NASA", "NATO", "UNESCO", "UNICEF", "FIFA", "OPEC", "LASER", "RADAR", "SONAR", "SCUBA", "NASDAQ", "POTUS", "COVID", "ASCII", "JPEG"];
+
+/// Initialisms with vowels, which [`AcronymMode::Auto`] couldn't tell from words otherwise
+const SPELLED_ACRONYMS: &[&str] = &["FBI", "CIA", "USA", "UK", "EU", "UN", "IBM", "CEO", "AI", "API", "URL", "USB", "CPU", "GPU", "UFO", "ETA", "IOU", "ID", "IT", "OS", "UI", "VIP"];
+
+/// English letter names; `z` is "zee" in American English and "zed" elsewhere
+fn letter_name(letter: char, american: bool) -> &'static str {
+    const NAMES: [&str; 26] = [
+        "ay", "bee", "see", "dee", "ee", "ef", "gee", "aitch", "eye", "jay", "kay", "el", "em", "en", "oh", "pee", "cue", "ar", "ess", "tee", "you", "vee", "double you", "ex", "why", "zed",
+    ];
+    match letter.to_ascii_lowercase() {
+        'z' if american => "zee",
+        c @ 'a'..='z' => NAMES[(c as u8 - b'a') as usize],
+        _ => "",
+    }
+}
+
+/// American spelling of a unit name
+fn american(unit: &str) -> String {
+    unit.replace("metre", "meter").replace("litre", "liter")
+}
+
+/// How an all-caps word is read in `mode`, or `None` to leave it as written
+fn expand_acronym(word: &str, mode: AcronymMode, american: bool) -> Option<String> {
+    if word.len() < 2 || !word.bytes().all(|b| b.is_ascii_uppercase()) {
+        return None;
+    }
+    // Capitalized, so the phonemizer reads it as a word rather than letters
+    let as_word = || Some(word[..1].to_string() + &word[1..].to_lowercase());
+    let spelled = || Some(word.chars().map(|c| letter_name(c, american)).collect::<Vec<_>>().join(" "));
+    match mode {
+        _ if WORD_ACRONYMS.contains(&word) => as_word(),
+        _ if word.len() > 5 => None,
+        AcronymMode::Word => as_word(),
+        AcronymMode::Spell => spelled(),
+        AcronymMode::Auto if SPELLED_ACRONYMS.contains(&word) || !word.contains(['A', 'E', 'I', 'O', 'U', 'Y']) => spelled(),
+        AcronymMode::Auto => None,
+    }
+}
+
+/// Whether `word` is a number, so a unit after it is read out
+fn is_number(word: &str) -> bool {
+    word.trim_start_matches(|c: char| !c.is_ascii_digit()).ends_with(|c: char| c.is_ascii_digit())
+}
+
+/// What `table` says for the word `core`, tried with its full stop first, and the punctuation left after it
+fn lookup<'a>(core: &str, trailing: &'a str, table: impl Fn(&str) -> Option<String>) -> Option<(String, &'a str)> {
+    let with_stop = trailing.strip_prefix('.').and_then(|after| table(&format!("{}.", core)).map(|said| (said, after)));
+    with_stop.or_else(|| table(core).map(|said| (said, trailing)))
+}
+
+/// Expand one whitespace-separated word. `previous` is the word before it, for units; `last` is set
+/// for the text's final word, whose full stop is kept to end the text. `english` holds the acronym
+/// mode and whether to use American spelling, for English voices.
+fn expand_word(word: &str, previous: Option<&str>, last: bool, user: &BTreeMap<String, String>, english: Option<(AcronymMode, bool)>) -> Option<String> {
+    let (prefix, rest) = word.split_at(word.find(|c: char| c.is_alphanumeric()).unwrap_or(word.len()));
+    let core = rest.trim_end_matches(|c: char| !c.is_alphanumeric());
+    let trailing = &rest[core.len()..];
+
+    let built_in = |written: &str| english.and(ABBREVIATIONS.iter().find(|(abbr, _)| *abbr == written)).map(|(_, said)| said.to_string());
+    if let Some((said, after)) = lookup(core, trailing, |written| user.get(written).cloned()).or_else(|| lookup(core, trailing, built_in)) {
+        let after = if last { trailing } else { after };
+        return Some(format!("{}{}{}", prefix, said, after));
+    }
+
+    let (mode, american_spelling) = english?;
+    // Units: "5 km" or "5km"
+    let (number, unit) = match core.find(|c: char| c.is_alphabetic()) {
+        Some(0) => (previous.filter(|p| is_number(p)).map(|_| ""), core),
+        Some(start) if core[..start].bytes().all(|b| b.is_ascii_digit() || b == b'.' || b == b',') => (Some(&core[..start]), &core[start..]),
+        _ => (None, core),
+    };
+    if let Some(number) = number
+        && let Some((_, singular, plural)) = UNITS.iter().find(|(abbr, _, _)| *abbr == unit)
+    {
+        let one = if number.is_empty() { previous.is_some_and(|p| p.trim_start_matches(|c: char| !c.is_ascii_digit()) == "1") } else { number == "1" };
+        let name = if one { singular } else { plural };
+        let name = if american_spelling { american(name) } else { name.to_string() };
+        let number = if number.is_empty() { String::new() } else { format!("{} ", number) };
+        return Some(format!("{}{}{}{}", prefix, number, name, trailing));
+    }
+    expand_acronym(core, mode, american_spelling).map(|said| format!("{}{}{}", prefix, said, trailing))
+}
+
+/// Expand abbreviations, units, and acronyms in `text` with the current [`settings`]
+pub fn expand(text: &str, locale: Option<Locale>) -> String {
+    expand_with(text, locale, &settings())
+}
+
+/// Expand abbreviations, units, and acronyms in `text`. The built-in expansions only apply to English
+/// (`locale`); user abbreviations apply to every language.
+pub fn expand_with(text: &str, locale: Option<Locale>, settings: &AbbreviationSettings) -> String {
+    let english = locale.filter(|l| l.language == Language::English).map(|l| (settings.acronyms, l.month_first));
+    if english.is_none() && settings.user.is_empty() {
+        return text.to_string();
+    }
+    // Words with the whitespace that follows them, so spacing is preserved
+    let mut words: Vec<(&str, &str)> = Vec::new();
+    let mut rest = text.trim_start();
+    let leading = &text[..text.len() - rest.len()];
+    while !rest.is_empty() {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let (word, after) = rest.split_at(end);
+        let next = after.trim_start();
+        words.push((word, &after[..after.len() - next.len()]));
+        rest = next;
+    }
+
+    let mut output = String::from(leading);
+    for (i, (word, space)) in words.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|p| words[p].0);
+        match expand_word(word, previous, i + 1 == words.len(), &settings.user, english) {
+            Some(said) => output.push_str(&said),
+            None => output.push_str(word),
+        }
+        output.push_str(space);
+    }
+    output
+}
@@ -0,0 +1,96 @@
+//! Hardware acceleration for voice model inference. ONNX Runtime runs on the CPU unless another
+//! execution provider is chosen; the choice applies to every model loaded afterwards, both through
+//! piper-rs and for [`crate::piper_timings`].
+//!
+//! GPU providers need an ONNX Runtime build that includes them: build this crate with the `cuda`,
+//! `coreml`, or `directml` feature. A provider that's compiled in but can't start (no GPU, missing
+//! drivers) falls back to the CPU with a warning.
+
+use ort::execution_providers::{CPUExecutionProvider, CUDAExecutionProvider, CoreMLExecutionProvider, DirectMLExecutionProvider, ExecutionProvider, ExecutionProviderDispatch};
+use std::sync::Mutex;
+
+/// Where voice models run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "full", derive(clap::ValueEnum))]
+pub enum Accelerator {
+    #[default]
+    Cpu,
+    /// NVIDIA GPUs (Linux and Windows)
+    Cuda,
+    /// Apple's Core ML: the GPU and Neural Engine on macOS and iOS
+    #[cfg_attr(feature = "full", value(name = "coreml"))]
+    CoreMl,
+    /// DirectX 12 GPUs on Windows
+    #[cfg_attr(feature = "full", value(name = "directml"))]
+    DirectMl,
+}
+
+impl Accelerator {
+    pub fn name(self) -> &'static str {
+        match self {
+            Accelerator::Cpu => "cpu",
+            Accelerator::Cuda => "cuda",
+            Accelerator::CoreMl => "coreml",
+            Accelerator::DirectMl => "directml",
+        }
+    }
+
+    /// Whether this build's ONNX Runtime was compiled with the provider (the CPU always is)
+    pub fn is_compiled(self) -> bool {
+        match self {
+            Accelerator::Cpu => true,
+            Accelerator::Cuda => cfg!(feature = "cuda"),
+            Accelerator::CoreMl => cfg!(feature = "coreml"),
+            Accelerator::DirectMl => cfg!(feature = "directml"),
+        }
+    }
+
+    fn provider(self) -> ExecutionProviderDispatch {
+        match self {
+            Accelerator::Cpu => CPUExecutionProvider::default().build(),
+            Accelerator::Cuda => CUDAExecutionProvider::default().build(),
+            Accelerator::CoreMl => CoreMLExecutionProvider::default().build(),
+            Accelerator::DirectMl => DirectMLExecutionProvider::default().build(),
+        }
+    }
+
+    /// Whether the provider can start on this machine
+    fn is_available(self) -> bool {
+        let available = match self {
+            Accelerator::Cpu => return true,
+            Accelerator::Cuda => CUDAExecutionProvider::default().is_available(),
+            Accelerator::CoreMl => CoreMLExecutionProvider::default().is_available(),
+            Accelerator::DirectMl => DirectMLExecutionProvider::default().is_available(),
+        };
+        available.unwrap_or(false)
+    }
+}
+
+static ACCELERATOR: Mutex<Accelerator> = Mutex::new(Accelerator::Cpu);
+
+/// The execution provider voice models are loaded with
+pub fn accelerator() -> Accelerator {
+    *ACCELERATOR.lock().unwrap()
+}
+
+/// Run voice models loaded from now on with `accelerator`, falling back to the CPU for operations it
+/// doesn't support. Call before loading a voice; models already loaded keep their provider. Fails if
+/// this build doesn't include the provider.
+pub fn set_accelerator(accelerator: Accelerator) -> crate::error::Result<()> {
+    if !accelerator.is_compiled() {
+        // The provider's name is also the cargo feature that compiles it in
+        return Err(format!("This build has no {} support; rebuild with `--features {}`", accelerator.name(), accelerator.name()).into());
+    }
+    let mut current = ACCELERATOR.lock().unwrap();
+    if *current == accelerator {
+        return Ok(());
+    }
+    if !accelerator.is_available() {
+        log::warn!("The {} execution provider isn't available on this machine; running voice models on the CPU", accelerator.name());
+    }
+    // Sessions pick up the environment's providers when they're created, so piper-rs needs no changes
+    let providers = if accelerator == Accelerator::Cpu { Vec::new() } else { vec![accelerator.provider(), Accelerator::Cpu.provider()] };
+    ort::init().with_name("text-to-face").with_execution_providers(providers).commit().map_err(|e| format!("Failed to set up ONNX Runtime: {}", e))?;
+    *current = accelerator;
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use crate::run_whisperx_on_wav;
+use colored::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// One WhisperX alignment to run after synthesis
+#[derive(Debug, Clone)]
+pub struct AlignmentJob {
+    pub wav_path: String,
+    pub json_path: String,
+    pub text: String,
+    pub hi_fidelity: bool,
+    pub lipsync_with_llm: Option<String>,
+}
+
+/// A sensible default number of concurrent alignments.
+/// Each WhisperX process loads its own model (several GB on GPU, several cores on CPU),
+/// so this stays well below the core count, and at one under `--nice`.
+pub fn default_alignment_jobs() -> usize {
+    if crate::nice::is_enabled() {
+        return 1;
+    }
+    let cores = std::thread::available_parallelism().map(usize::from).unwrap_or(1);
+    (cores / 4).clamp(1, 4)
+}
+
+/// Run alignment jobs on a bounded pool of worker threads.
+/// `max_jobs` of 0 picks [`default_alignment_jobs`]. Jobs are started in order, and every job runs
+/// even if an earlier one fails; the error lists each failed file.
+pub fn run_alignment_jobs(jobs: &[AlignmentJob], max_jobs: usize) -> Result<(), Box<dyn std::error::Error>> {
+    if jobs.is_empty() {
+        return Ok(());
+    }
+    let workers = if max_jobs == 0 { default_alignment_jobs() } else { max_jobs }.min(jobs.len());
+    log::info!("{} Aligning {} files with {} concurrent job(s)", "[WhisperX]".cyan(), jobs.len(), workers);
+
+    let next = AtomicUsize::new(0);
+    let failures = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(job) = jobs.get(index) else {
+                    break;
+                };
+                if let Err(e) = run_whisperx_on_wav(&job.wav_path, Some(&job.json_path), job.hi_fidelity, &job.text, job.lipsync_with_llm.as_deref()) {
+                    log::error!("{} {}: {}", "[WhisperX]".red(), job.wav_path, e);
+                    failures.lock().unwrap().push(job.wav_path.clone());
+                }
+            });
+        }
+    });
+    let failures = failures.into_inner().unwrap();
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Alignment failed for {}", failures.join(", ")).into())
+    }
+}
+
+/// Language of the text to align when speech recognition is skipped; `None` runs WhisperX's usual
+/// recognize-then-align pass
+static TRANSCRIPT_LANGUAGE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Align the known input text instead of recognizing the speech first (`--align-only`). Synthesized
+/// speech always says the text it was given, so recognition only costs time and adds errors.
+pub fn set_transcript_alignment(language: Option<String>) {
+    *TRANSCRIPT_LANGUAGE.lock().unwrap() = language;
+}
+
+pub fn transcript_alignment() -> Option<String> {
+    TRANSCRIPT_LANGUAGE.lock().unwrap().clone()
+}
+
+/// Runs WhisperX's phoneme aligner on one segment spanning the whole audio, with the text on stdin
+const ALIGN_SCRIPT: &str = r#"
+import json, sys
+import torch, whisperx
+wav_path, language, json_path = sys.argv[1:4]
+device = "cuda" if torch.cuda.is_available() else "cpu"
+audio = whisperx.load_audio(wav_path)
+segments = [{"text": sys.stdin.read(), "start": 0.0, "end": len(audio) / whisperx.audio.SAMPLE_RATE}]
+model, metadata = whisperx.load_align_model(language_code=language, device=device)
+result = whisperx.align(segments, model, metadata, audio, device, return_char_alignments=False)
+result["language"] = language
+with open(json_path, "w", encoding="utf-8") as f:
+    json.dump(result, f, ensure_ascii=False, indent=2)
+"#;
+
+/// Force-align `text` to the speech in `wav_path` with WhisperX's alignment model, skipping
+/// recognition, and write WhisperX's usual JSON (`segments` and `word_segments`) to `json_path`.
+/// Runs the Python that has WhisperX installed: `WHISPERX_PYTHON`, or `python3`.
+pub fn align_transcript(wav_path: &str, text: &str, language: &str, json_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+    let python = std::env::var("WHISPERX_PYTHON").unwrap_or_else(|_| "python3".to_string());
+    log::info!("{} Aligning the known text to {} ({}, no recognition)...", "[WhisperX]".cyan(), wav_path, language);
+    let mut child = Command::new(&python)
+        .envs(crate::nice::child_env())
+        .arg("-c")
+        .arg(ALIGN_SCRIPT)
+        .arg(wav_path)
+        .arg(language)
+        .arg(json_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", python, e))?;
+    child.stdin.take().ok_or("no stdin for the aligner")?.write_all(text.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!("Transcript alignment failed with status {}:\n{}", output.status, String::from_utf8_lossy(&output.stderr)).into());
+    }
+    log::info!("{} Lipsync JSON written to {}", "[WhisperX]".cyan(), json_path);
+    Ok(())
+}
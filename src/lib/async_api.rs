@@ -0,0 +1,33 @@
+//! Async versions of the main entry points, for embedding in tokio servers and bots (build with
+//! the `tokio` feature).
+//!
+//! Synthesis and downloads are blocking work (ONNX inference, file I/O, HTTP), so each call runs on
+//! tokio's blocking thread pool and the executor stays free while it does. The global settings
+//! (limits, pauses, rules, models directory, ...) apply exactly as they do to the blocking calls.
+
+use crate::error::{Error, Result};
+use crate::PitchArg;
+
+/// Run `work` on the blocking pool. A panic in it is reported as an error rather than propagated.
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(work).await.map_err(|e| Error::from(format!("Background task failed: {}", e)))?
+}
+
+/// [`crate::synthesize_processed`] without blocking the executor: synthesize `text` with `voice`, pitch
+/// shifted and tempo stretched, returning the samples and their sample rate. Downloads the voice first
+/// if it isn't installed.
+pub async fn synth_async(text: &str, voice: &str, pitch: &PitchArg, tempo: f32) -> Result<(Vec<f32>, u32)> {
+    let (text, voice, pitch) = (text.to_string(), voice.to_string(), pitch.clone());
+    blocking(move || crate::synthesize_processed(&text, &voice, &pitch, tempo)).await
+}
+
+/// Look a voice up in the catalog and download it (see [`crate::download_voice_files`]) without blocking
+/// the executor. Returns the model and config paths.
+pub async fn download_voice_async(voice_id: &str) -> Result<(String, String)> {
+    let voice_id = voice_id.to_string();
+    blocking(move || {
+        let voice = crate::get_available_voices().into_iter().find(|v| v.id == voice_id).ok_or(Error::VoiceNotFound(voice_id))?;
+        crate::download_voice_files(&voice)
+    })
+    .await
+}
@@ -0,0 +1,478 @@
+use crate::alignment::{run_alignment_jobs, AlignmentJob};
+use crate::directives::strip_directives;
+use crate::verify::write_transcript_report;
+use crate::sidecar::{is_up_to_date, write_sidecar, ExportSidecar};
+use crate::warnings::{clipping, take_warnings, warn};
+use crate::{read_wav, synthesize_processed, write_aligned_lipsync, write_estimated_lipsync, write_wav, LipsyncLevel, PitchArg, SynthesisReport};
+use colored::*;
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::process::Command;
+
+/// A single chapter of a text project
+#[derive(Debug, Clone)]
+pub struct Chapter {
+    pub title: String,
+    pub text: String,
+}
+
+/// Book-level metadata embedded in the M4B container
+#[derive(Debug, Clone, Default)]
+pub struct AudiobookMetadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub narrator: Option<String>,
+    pub year: Option<String>,
+    pub comment: Option<String>,
+}
+
+/// Rendering options for [`synthesize_audiobook`]
+#[derive(Debug, Clone)]
+pub struct AudiobookOptions {
+    /// AAC bitrate in kbps
+    pub bitrate_kbps: u32,
+    /// Re-render chapters even if an up-to-date part exists
+    pub force: bool,
+    /// Write per-chapter lipsync JSON next to the audiobook when not `Low` (aligned by WhisperX for `High`)
+    pub lipsync: LipsyncLevel,
+    pub lipsync_with_llm: Option<String>,
+    /// Concurrent WhisperX jobs for chapter alignment (0 = automatic)
+    pub align_jobs: usize,
+    /// After alignment, compare each chapter's transcript with its text (`<stem>.chapter_NNN.verify.json`)
+    pub verify: bool,
+    /// Treat the output path as a directory and write each chapter to `chapter_NNN.wav` in it, with a
+    /// [`MANIFEST_FILE`], instead of one M4B
+    pub chapter_files: bool,
+}
+
+impl Default for AudiobookOptions {
+    fn default() -> Self {
+        AudiobookOptions {
+            bitrate_kbps: 64,
+            force: false,
+            lipsync: LipsyncLevel::Low,
+            lipsync_with_llm: None,
+            align_jobs: 0,
+            verify: false,
+            chapter_files: false,
+        }
+    }
+}
+
+impl AudiobookMetadata {
+    /// Sidecar descriptions of the set fields, so retagging a book re-renders it
+    pub fn describe(&self) -> Vec<String> {
+        [("title", &self.title), ("author", &self.author), ("narrator", &self.narrator), ("year", &self.year), ("comment", &self.comment)]
+            .into_iter()
+            .filter_map(|(key, value)| value.as_ref().map(|value| format!("{}={}", key, value)))
+            .collect()
+    }
+}
+
+impl AudiobookOptions {
+    /// Sidecar descriptions of the options that change what a render writes
+    pub fn describe(&self) -> Vec<String> {
+        let mut effects = vec![format!("aac {}k", self.bitrate_kbps)];
+        if self.lipsync != LipsyncLevel::Low {
+            effects.push(format!("lipsync={:?}", self.lipsync).to_lowercase());
+        }
+        effects.extend(self.lipsync_with_llm.as_ref().map(|model| format!("llm={}", model)));
+        if self.verify {
+            effects.push("verify".to_string());
+        }
+        effects
+    }
+}
+
+/// Longest `Chapter 2: Title` line, in words; a longer line starting with "Chapter" is prose
+const MAX_CHAPTER_LINE_WORDS: usize = 12;
+
+/// Words that number a chapter, alone or hyphenated (`twenty-one`)
+const NUMBER_WORDS: &[&str] = &[
+    "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten", "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen", "twenty",
+    "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety", "hundred",
+];
+
+/// The level of a `# Title` (1) or `## Title` (2) markdown heading. `#1 fan`, hashtags, and deeper
+/// headings aren't chapter headings.
+fn heading_level(line: &str) -> Option<usize> {
+    let line = line.trim_start();
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let title = &line[level..];
+    ((1..=2).contains(&level) && title.starts_with(' ') && !title.trim().is_empty()).then_some(level)
+}
+
+/// A Roman numeral in its usual form, all upper or all lower case: `IV`, `xii`
+fn is_roman_numeral(word: &str) -> bool {
+    const NUMERALS: [&str; 13] = ["M", "CM", "D", "CD", "C", "XC", "L", "XL", "X", "IX", "V", "IV", "I"];
+    if word.is_empty() || !(word == word.to_uppercase() || word == word.to_lowercase()) {
+        return false;
+    }
+    // Read greedily and require every digit to be used, which rejects `IIII` and `VX`
+    let mut rest = word.to_uppercase();
+    for numeral in NUMERALS {
+        let repeats = if numeral.len() == 1 && !matches!(numeral, "V" | "L" | "D") { 3 } else { 1 };
+        for _ in 0..repeats {
+            match rest.strip_prefix(numeral) {
+                Some(stripped) => rest = stripped.to_string(),
+                None => break,
+            }
+        }
+    }
+    rest.is_empty()
+}
+
+/// A chapter number: digits, a Roman numeral, or number words
+fn is_chapter_number(word: &str) -> bool {
+    (!word.is_empty() && word.chars().all(|c| c.is_ascii_digit()))
+        || is_roman_numeral(word)
+        || word.split('-').all(|part| NUMBER_WORDS.contains(&part.to_lowercase().as_str()))
+}
+
+/// A short standalone line such as `Chapter 3`, `CHAPTER IV.`, or `Chapter Twelve: The Storm`. A title
+/// after the number needs a separator, so "Chapter one was the hardest of my life." is prose.
+fn is_chapter_line(line: &str) -> bool {
+    let line = line.trim();
+    let Some((first, rest)) = line.split_once(char::is_whitespace) else {
+        return false;
+    };
+    if !first.eq_ignore_ascii_case("chapter") || line.split_whitespace().count() > MAX_CHAPTER_LINE_WORDS {
+        return false;
+    }
+    let rest = rest.trim_start();
+    let (number, title) = rest.split_at(rest.find(|c: char| c.is_whitespace() || matches!(c, ':' | '.' | '—' | '–')).unwrap_or(rest.len()));
+    let title = title.trim_start();
+    is_chapter_number(number) && (title.is_empty() || title.starts_with([':', '.', '-', '—', '–']))
+}
+
+/// Returns true if a line starts a new chapter: a markdown heading at `level` (`# Title` for 1), or a
+/// `Chapter 3: Title` line
+fn is_chapter_heading(line: &str, level: usize) -> bool {
+    heading_level(line) == Some(level) || is_chapter_line(line)
+}
+
+/// Split a long text into chapters on markdown headings or "Chapter N" lines. Chapters start at the
+/// top heading level used: `#` headings if there are any, so `##` ones stay inside their chapter, else
+/// `##` headings. Text before the first heading becomes its own chapter; if there are no headings the
+/// whole text is returned as "Chapter 1".
+pub fn split_chapters(text: &str) -> Vec<Chapter> {
+    let level = if text.lines().any(|line| heading_level(line) == Some(1)) { 1 } else { 2 };
+    let mut chapters: Vec<Chapter> = Vec::new();
+    let mut current_title: Option<String> = None;
+    let mut current_text = String::new();
+
+    let flush = |title: Option<String>, body: &mut String, chapters: &mut Vec<Chapter>| {
+        let trimmed = body.trim();
+        if !trimmed.is_empty() {
+            let title = title.unwrap_or_else(|| format!("Chapter {}", chapters.len() + 1));
+            chapters.push(Chapter { title, text: trimmed.to_string() });
+        }
+        body.clear();
+    };
+
+    for line in text.lines() {
+        if is_chapter_heading(line, level) {
+            flush(current_title.take(), &mut current_text, &mut chapters);
+            current_title = Some(line.trim().trim_start_matches('#').trim().to_string());
+        } else {
+            current_text.push_str(line);
+            current_text.push('\n');
+        }
+    }
+    flush(current_title.take(), &mut current_text, &mut chapters);
+    chapters
+}
+
+/// Chapters from a directory of text files (`.txt` or `.md`), one chapter per file in file name
+/// order, so number them with leading zeros (`01.txt`, `02.txt`, ...). A chapter heading on a file's
+/// first line is its title; otherwise the file name is.
+pub fn read_chapter_files(dir: &Path) -> Result<Vec<Chapter>, Box<dyn std::error::Error>> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("txt") || e.eq_ignore_ascii_case("md")))
+        .collect();
+    paths.sort();
+    let mut chapters = Vec::new();
+    for path in paths {
+        let text = std::fs::read_to_string(&path)?;
+        let mut lines = text.trim_start().lines();
+        let (title, body) = match lines.next() {
+            Some(first) if heading_level(first).is_some() || is_chapter_line(first) => {
+                let title = first.trim().trim_start_matches('#').trim().to_string();
+                (title, lines.collect::<Vec<_>>().join("\n"))
+            }
+            _ => (path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string(), text.clone()),
+        };
+        if !body.trim().is_empty() {
+            chapters.push(Chapter { title, text: body.trim().to_string() });
+        }
+    }
+    Ok(chapters)
+}
+
+/// Escape a value for the ffmetadata format (`=`, `;`, `#`, `\` and newlines must be escaped)
+fn escape_ffmetadata(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Build an ffmetadata document with book tags and one `[CHAPTER]` block per chapter.
+/// `chapters` pairs each chapter title with its duration in seconds.
+pub fn build_ffmetadata(metadata: &AudiobookMetadata, chapters: &[(String, f32)]) -> String {
+    let mut out = String::from(";FFMETADATA1\n");
+    let tags = [
+        ("title", &metadata.title),
+        ("album", &metadata.title),
+        ("artist", &metadata.author),
+        ("album_artist", &metadata.author),
+        ("composer", &metadata.narrator),
+        ("date", &metadata.year),
+        ("comment", &metadata.comment),
+    ];
+    for (key, value) in tags {
+        if let Some(value) = value {
+            let _ = writeln!(out, "{}={}", key, escape_ffmetadata(value));
+        }
+    }
+    let _ = writeln!(out, "genre=Audiobook");
+
+    let mut start_ms: u64 = 0;
+    for (title, duration) in chapters {
+        let end_ms = start_ms + (duration * 1000.0).round() as u64;
+        let _ = writeln!(out, "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}", start_ms, end_ms, escape_ffmetadata(title));
+        start_ms = end_ms;
+    }
+    out
+}
+
+/// Encode already-synthesized chapters into a single chaptered M4B/M4A file using ffmpeg
+pub fn write_m4b(
+    chapters: &[(String, Vec<f32>)],
+    sample_rate: u32,
+    output_path: &str,
+    metadata: &AudiobookMetadata,
+    bitrate_kbps: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let ffmpeg_available = Command::new("ffmpeg").arg("-version").output().is_ok();
+    if !ffmpeg_available {
+        return Err(crate::Error::tool_missing("ffmpeg", "Install ffmpeg to write M4B audiobooks.").into());
+    }
+
+    let temp_wav = format!("{}.temp.wav", output_path);
+    let temp_meta = format!("{}.ffmetadata", output_path);
+
+    let all_samples: Vec<f32> = chapters.iter().flat_map(|(_, samples)| samples.iter().copied()).collect();
+    write_wav(&temp_wav, &all_samples, sample_rate)?;
+    let durations: Vec<(String, f32)> = chapters
+        .iter()
+        .map(|(title, samples)| (title.clone(), samples.len() as f32 / sample_rate as f32))
+        .collect();
+    std::fs::write(&temp_meta, build_ffmetadata(metadata, &durations))?;
+
+    let output = Command::new("ffmpeg")
+        .arg("-y")
+        .arg("-i").arg(&temp_wav)
+        .arg("-i").arg(&temp_meta)
+        .arg("-map_metadata").arg("1")
+        .arg("-map_chapters").arg("1")
+        .arg("-c:a").arg("aac")
+        .arg("-b:a").arg(format!("{}k", bitrate_kbps))
+        .args(crate::nice::thread_args("-threads"))
+        .arg("-f").arg("mp4")
+        .arg(output_path)
+        .output();
+
+    let _ = std::fs::remove_file(&temp_wav);
+    let _ = std::fs::remove_file(&temp_meta);
+
+    let output = output?;
+    if !output.status.success() {
+        return Err(format!("ffmpeg failed: {}", String::from_utf8_lossy(&output.stderr)).into());
+    }
+    Ok(())
+}
+
+/// Written into the output directory with [`AudiobookOptions::chapter_files`]
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// `manifest.json`: the book and its chapter files, for packaging tools
+#[derive(Debug, Clone, Serialize)]
+pub struct AudiobookManifest {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub narrator: Option<String>,
+    pub year: Option<String>,
+    pub voice: String,
+    /// Seconds, all chapters together
+    pub duration: f32,
+    pub chapters: Vec<ManifestChapter>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestChapter {
+    /// From 1
+    pub number: usize,
+    pub title: String,
+    /// File name in the output directory
+    pub file: String,
+    pub sample_rate: u32,
+    /// Seconds from the start of the book, as if the chapters were played back to back
+    pub start: f32,
+    pub duration: f32,
+    /// Lipsync JSON file name, when lipsync was written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lipsync: Option<String>,
+}
+
+/// Synthesize every chapter of a text project and package the result as a chaptered M4B (see
+/// [`synthesize_chapters`]).
+pub fn synthesize_audiobook(
+    text: &str,
+    voice: &str,
+    pitch: &PitchArg,
+    tempo: f32,
+    output_path: &str,
+    metadata: &AudiobookMetadata,
+    options: &AudiobookOptions,
+) -> Result<SynthesisReport, Box<dyn std::error::Error>> {
+    synthesize_chapters(&split_chapters(text), voice, pitch, tempo, output_path, metadata, options)
+}
+
+/// Synthesize chapters into a chaptered M4B, or with `options.chapter_files` into numbered WAVs and a
+/// manifest in the `output_path` directory.
+///
+/// Each rendered chapter is kept in `<output>.parts/` with a sidecar until the M4B is encoded,
+/// so an interrupted render resumes with the chapters that are already done. Set `options.force`
+/// to re-render every chapter regardless. With lipsync enabled, chapters are aligned on a bounded
+/// pool of WhisperX jobs into `<output stem>.chapter_NNN.json`, and `options.verify` checks each
+/// chapter's recognized transcript against its text. The report holds the warnings from every chapter.
+///
+/// Chapter files are rendered the same way, but straight into the output directory, where they stay:
+/// rendering again only redoes the chapters whose text or settings changed. Their lipsync JSON is
+/// `chapter_NNN.json` alongside.
+pub fn synthesize_chapters(
+    chapters: &[Chapter],
+    voice: &str,
+    pitch: &PitchArg,
+    tempo: f32,
+    output_path: &str,
+    metadata: &AudiobookMetadata,
+    options: &AudiobookOptions,
+) -> Result<SynthesisReport, Box<dyn std::error::Error>> {
+    if chapters.is_empty() {
+        return Err("No text to synthesize".into());
+    }
+    let parts_dir = if options.chapter_files { output_path.to_string() } else { format!("{}.parts", output_path) };
+    std::fs::create_dir_all(&parts_dir)?;
+
+    let mut rendered: Vec<(String, Vec<f32>, u32)> = Vec::with_capacity(chapters.len());
+    let mut alignment_jobs: Vec<AlignmentJob> = Vec::new();
+    let output = Path::new(output_path);
+    let stem = output.file_stem().and_then(|s| s.to_str()).unwrap_or("audiobook");
+    let chapter_json = |i: usize| match options.chapter_files {
+        true => output.join(format!("chapter_{:03}.json", i + 1)).to_string_lossy().to_string(),
+        false => output.with_file_name(format!("{}.chapter_{:03}.json", stem, i + 1)).to_string_lossy().to_string(),
+    };
+    for (i, chapter) in chapters.iter().enumerate() {
+        let part_name = if options.chapter_files { format!("chapter_{:03}.wav", i + 1) } else { format!("{:03}.wav", i + 1) };
+        let part_path = Path::new(&parts_dir).join(part_name);
+        let part_str = part_path.to_string_lossy().to_string();
+        let mut meta = ExportSidecar::new(&chapter.text, voice, pitch.as_factor(), tempo, "low", &part_str);
+        if options.lipsync == LipsyncLevel::High {
+            alignment_jobs.push(AlignmentJob {
+                wav_path: part_str.clone(),
+                json_path: chapter_json(i),
+                text: strip_directives(&chapter.text),
+                hi_fidelity: true,
+                lipsync_with_llm: options.lipsync_with_llm.clone(),
+            });
+        }
+
+        if !options.force && is_up_to_date(&part_path, &meta) {
+            log::info!("{} [{}/{}] {} (unchanged, skipping)", "[Audiobook]".cyan(), i + 1, chapters.len(), chapter.title);
+            let (samples, rate) = read_wav(&part_str)?;
+            rendered.push((chapter.title.clone(), samples, rate));
+            continue;
+        }
+
+        log::info!("{} [{}/{}] {}", "[Audiobook]".cyan(), i + 1, chapters.len(), chapter.title);
+        let (samples, rate) = synthesize_processed(&chapter.text, voice, pitch, tempo)?;
+        if let Some(clipped) = clipping(&samples) {
+            warn(clipped);
+        }
+        write_wav(&part_str, &samples, rate)?;
+        meta.finalize(None);
+        write_sidecar(&part_path, &meta)?;
+        rendered.push((chapter.title.clone(), samples, rate));
+    }
+    let file_rates: Vec<u32> = rendered.iter().map(|(_, _, rate)| *rate).collect();
+    // Chapters are joined at the voice's rate; one starting with another voice may differ
+    let sample_rate = crate::voice_sample_rate(voice);
+    let rendered: Vec<(String, Vec<f32>)> = rendered
+        .into_iter()
+        .map(|(title, samples, rate)| (title, if rate == sample_rate { samples } else { crate::dsp::resample(&samples, rate as usize, sample_rate as usize) }))
+        .collect();
+    if options.chapter_files {
+        let mut start = 0.0;
+        let manifest_chapters = rendered
+            .iter()
+            .zip(file_rates)
+            .enumerate()
+            .map(|(i, ((title, samples), file_rate))| {
+                let duration = samples.len() as f32 / sample_rate as f32;
+                let lipsync = (options.lipsync != LipsyncLevel::Low).then(|| format!("chapter_{:03}.json", i + 1));
+                let chapter = ManifestChapter { number: i + 1, title: title.clone(), file: format!("chapter_{:03}.wav", i + 1), sample_rate: file_rate, start, duration, lipsync };
+                start += duration;
+                chapter
+            })
+            .collect();
+        let manifest = AudiobookManifest {
+            title: metadata.title.clone(),
+            author: metadata.author.clone(),
+            narrator: metadata.narrator.clone(),
+            year: metadata.year.clone(),
+            voice: voice.to_string(),
+            duration: start,
+            chapters: manifest_chapters,
+        };
+        std::fs::write(output.join(MANIFEST_FILE), serde_json::to_string_pretty(&manifest)?)?;
+        log::info!("{} written to {} ({} chapters, {})", "Chapter files".green(), output_path, rendered.len(), MANIFEST_FILE);
+    } else {
+        write_m4b(&rendered, sample_rate, output_path, metadata, options.bitrate_kbps)?;
+        log::info!("{} file written to {} ({} chapters)", "M4B".green(), output_path, rendered.len());
+    }
+    if matches!(options.lipsync, LipsyncLevel::Estimate | LipsyncLevel::Align | LipsyncLevel::Piper) {
+        let write_lipsync = if options.lipsync == LipsyncLevel::Align { write_aligned_lipsync } else { write_estimated_lipsync };
+        for (i, (chapter, (_, samples))) in chapters.iter().zip(&rendered).enumerate() {
+            write_lipsync(&strip_directives(&chapter.text), samples, sample_rate, Some(&chapter_json(i)), options.lipsync_with_llm.as_deref())?;
+        }
+    }
+    run_alignment_jobs(&alignment_jobs, options.align_jobs)?;
+    if options.verify {
+        for job in &alignment_jobs {
+            let report_path = Path::new(&job.json_path).with_extension("verify.json");
+            match write_transcript_report(&job.text, &job.json_path, &report_path.to_string_lossy()) {
+                Ok(report) => log::info!(
+                    "{} {}: {} mismatched of {} words (WER {:.1}%)",
+                    "[Verify]".cyan(),
+                    report_path.display(),
+                    report.mismatches.len(),
+                    report.input_words,
+                    report.word_error_rate * 100.0
+                ),
+                Err(e) => log::error!("{} {}: {}", "[Verify]".red(), job.json_path, e),
+            }
+        }
+    }
+    if !options.chapter_files {
+        let _ = std::fs::remove_dir_all(&parts_dir);
+    }
+    Ok(SynthesisReport { warnings: take_warnings(), lipsync_json: None })
+}
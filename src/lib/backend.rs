@@ -0,0 +1,177 @@
+use crate::WordTiming;
+use std::sync::{Arc, Mutex};
+
+/// Something that turns text into mono samples
+pub trait TtsBackend {
+    /// Sample rate of the samples returned by [`TtsBackend::synthesize`]
+    fn sample_rate(&self) -> u32;
+
+    fn synthesize(&self, text: &str) -> Result<Vec<f32>, Box<dyn std::error::Error>>;
+
+    /// Word timings for `text` when the backend knows them without alignment
+    fn word_timings(&self, _text: &str) -> Option<Vec<WordTiming>> {
+        None
+    }
+
+    /// Load whatever synthesis needs up front, so the first request doesn't wait for it
+    fn load(&self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    /// Lipsync JSON in WhisperX's `word_segments` shape from [`TtsBackend::word_timings`], with times
+    /// multiplied by `time_scale` (the ratio of processed to raw length when pitch/tempo processing
+    /// changed the duration). `None` when the backend doesn't know its timings.
+    fn lipsync_json(&self, text: &str, time_scale: f32) -> Option<serde_json::Value> {
+        let words: Vec<serde_json::Value> = self
+            .word_timings(text)?
+            .into_iter()
+            .map(|w| serde_json::json!({ "word": w.word, "start": w.start * time_scale, "end": w.end * time_scale, "score": 1.0 }))
+            .collect();
+        Some(serde_json::json!({ "segments": [{ "text": text, "words": words }], "word_segments": words }))
+    }
+}
+
+/// A backend shared by every synthesis with its voice ID
+pub type SharedBackend = Arc<dyn TtsBackend + Send + Sync>;
+
+/// Voice ID that selects [`MockBackend`] wherever a voice is expected
+pub const MOCK_VOICE: &str = "mock";
+
+static BACKENDS: Mutex<Vec<(String, SharedBackend)>> = Mutex::new(Vec::new());
+
+/// Synthesize `voice` with `backend` wherever a voice ID is accepted, instead of a Piper model.
+/// Registering an ID again replaces its backend.
+pub fn register_backend(voice: &str, backend: SharedBackend) {
+    let mut backends = BACKENDS.lock().unwrap();
+    backends.retain(|(id, _)| id != voice);
+    backends.push((voice.to_string(), backend));
+}
+
+/// The backend registered for `voice` with [`register_backend`], or [`MockBackend`] for [`MOCK_VOICE`].
+/// `None` means `voice` names a Piper voice.
+pub fn registered_backend(voice: &str) -> Option<SharedBackend> {
+    let registered = BACKENDS.lock().unwrap().iter().find(|(id, _)| id == voice).map(|(_, backend)| backend.clone());
+    registered.or_else(|| (voice == MOCK_VOICE).then(|| Arc::new(MockBackend::default()) as SharedBackend))
+}
+
+#[cfg(feature = "full")]
+/// The backend that synthesizes `voice`: a registered one (see [`registered_backend`]), else the Piper voice
+pub fn backend(voice: &str) -> SharedBackend {
+    registered_backend(voice).unwrap_or_else(|| Arc::new(PiperBackend::new(voice)))
+}
+
+/// Deterministic backend for tests and CI: no model, no downloads.
+/// Each word becomes a sine tone whose pitch is derived from the word and whose length grows with
+/// the word, separated by short silences; [`TtsBackend::word_timings`] reports exactly where they are.
+#[derive(Debug, Clone)]
+pub struct MockBackend {
+    pub sample_rate: u32,
+    /// Seconds of tone per character of a word
+    pub seconds_per_char: f32,
+    /// Silence between words, in seconds
+    pub gap: f32,
+}
+
+impl Default for MockBackend {
+    fn default() -> Self {
+        MockBackend { sample_rate: 22050, seconds_per_char: 0.06, gap: 0.08 }
+    }
+}
+
+impl MockBackend {
+    /// A stable frequency between 150 and 400 Hz for a word
+    fn frequency(word: &str) -> f32 {
+        let hash = word.bytes().fold(2166136261u32, |h, b| (h ^ b as u32).wrapping_mul(16777619));
+        150.0 + (hash % 250) as f32
+    }
+
+    fn words(text: &str) -> impl Iterator<Item = &str> {
+        text.split_whitespace()
+    }
+}
+
+impl TtsBackend for MockBackend {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn synthesize(&self, text: &str) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+        let rate = self.sample_rate as f32;
+        let gap = vec![0.0; (self.gap * rate) as usize];
+        let mut samples = gap.clone();
+        for word in Self::words(text) {
+            let len = (word.chars().count() as f32 * self.seconds_per_char * rate) as usize;
+            let frequency = Self::frequency(word);
+            let fade = len / 10;
+            samples.extend((0..len).map(|i| {
+                // Short ramps keep the tones click-free
+                let envelope = if fade == 0 { 1.0 } else { (i.min(len - 1 - i) as f32 / fade as f32).min(1.0) };
+                0.3 * envelope * (2.0 * std::f32::consts::PI * frequency * i as f32 / rate).sin()
+            }));
+            samples.extend_from_slice(&gap);
+        }
+        Ok(samples)
+    }
+
+    fn word_timings(&self, text: &str) -> Option<Vec<WordTiming>> {
+        let rate = self.sample_rate as f32;
+        // Mirror the sample counts in `synthesize` so timings line up exactly with the audio
+        let gap = (self.gap * rate) as usize;
+        let mut cursor = gap;
+        let timings = Self::words(text)
+            .map(|word| {
+                let len = (word.chars().count() as f32 * self.seconds_per_char * rate) as usize;
+                let timing = WordTiming {
+                    word: word.to_string(),
+                    start: cursor as f32 / rate,
+                    end: (cursor + len) as f32 / rate,
+                    phonemes: Vec::new(),
+                };
+                cursor += len + gap;
+                timing
+            })
+            .collect();
+        Some(timings)
+    }
+}
+
+impl TtsBackend for crate::runtime::RuntimeVoice {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn synthesize(&self, text: &str) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+        crate::runtime::RuntimeVoice::synthesize(self, text)
+    }
+}
+
+#[cfg(feature = "full")]
+/// A Piper voice by ID, downloaded on first use, with the text rules, normalization, respellings, and
+/// pauses applied as in [`crate::synth_with_voice_config`]
+#[derive(Debug, Clone)]
+pub struct PiperBackend {
+    pub voice: String,
+}
+
+#[cfg(feature = "full")]
+impl PiperBackend {
+    pub fn new(voice: &str) -> Self {
+        PiperBackend { voice: voice.to_string() }
+    }
+}
+
+#[cfg(feature = "full")]
+impl TtsBackend for PiperBackend {
+    fn sample_rate(&self) -> u32 {
+        crate::voice_sample_rate(&self.voice)
+    }
+
+    fn synthesize(&self, text: &str) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+        Ok(crate::synth_with_piper(text.to_string(), &self.voice)?)
+    }
+
+    fn load(&self) -> Result<(), Box<dyn std::error::Error>> {
+        crate::load_synthesizer(&self.voice)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,249 @@
+//! Batch synthesis: render every row of a JSONL or CSV file of `{text, voice, pitch, tempo, output}`
+//! on a bounded pool of worker threads. Each output gets a sidecar, so re-running a batch after an
+//! interruption (or after editing a few rows) only renders what is missing or changed.
+
+use crate::encode::{encode_wav, AudioFormat, EncodeSettings};
+use crate::sidecar::{is_up_to_date, write_sidecar, ExportSidecar};
+use crate::stereo::{pan_wav, stereo_output};
+use crate::tags::{tag_wav, AudioTags};
+use crate::{synthesize_processed, write_wav, PitchArg};
+use colored::*;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// One line to render; unset fields fall back to the [`BatchOptions`]
+#[derive(Debug, Clone)]
+pub struct BatchRow {
+    /// 1-based row number in the input, for reports (the header of a CSV is not counted)
+    pub row: usize,
+    pub text: String,
+    pub voice: Option<String>,
+    pub pitch: Option<PitchArg>,
+    pub tempo: Option<f32>,
+    pub output: Option<String>,
+}
+
+/// Rendering options for [`run_batch`]
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    pub default_voice: String,
+    /// Tempo for rows without one; like row tempos, the `--rate` preset is applied on top
+    pub default_tempo: f32,
+    /// Directory that relative `output` paths (and the default `NNNN.wav` names) are resolved against
+    pub output_dir: PathBuf,
+    /// Concurrent syntheses; 0 picks [`default_batch_jobs`]
+    pub jobs: usize,
+    /// Re-render rows even if their sidecar shows the output is up to date
+    pub force: bool,
+}
+
+/// A row that failed, and why
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchFailure {
+    pub row: usize,
+    pub output: String,
+    pub error: String,
+}
+
+/// What a batch run did
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BatchReport {
+    pub rendered: usize,
+    /// Rows whose output was already up to date
+    pub skipped: usize,
+    pub failures: Vec<BatchFailure>,
+}
+
+/// Concurrent syntheses when `--jobs` isn't given: half the CPU cores, or one under `--nice`
+pub fn default_batch_jobs() -> usize {
+    if crate::nice::is_enabled() {
+        return 1;
+    }
+    let cores = std::thread::available_parallelism().map(usize::from).unwrap_or(1);
+    (cores / 2).max(1)
+}
+
+/// Read a batch file: CSV for a `.csv` extension, JSON Lines otherwise
+pub fn read_batch(path: &Path) -> Result<Vec<BatchRow>, Box<dyn std::error::Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    let csv = path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("csv"));
+    if csv { parse_batch_csv(&contents) } else { parse_batch_jsonl(&contents) }
+}
+
+/// Parse JSON Lines, one object per line. `pitch` may be a number or a preset name; blank lines
+/// and lines starting with `#` are skipped.
+pub fn parse_batch_jsonl(contents: &str) -> Result<Vec<BatchRow>, Box<dyn std::error::Error>> {
+    let mut rows = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let value: serde_json::Value = serde_json::from_str(line).map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        let field = |name: &str| match value.get(name) {
+            Some(serde_json::Value::String(s)) => Some(s.clone()),
+            Some(serde_json::Value::Number(n)) => Some(n.to_string()),
+            _ => None,
+        };
+        let row = batch_row(rows.len() + 1, field("text"), field("voice"), field("pitch"), field("tempo"), field("output")).map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Parse CSV with a header row naming the columns (`text` is required; `voice`, `pitch`, `tempo`,
+/// and `output` are optional, and other columns are ignored). Fields may be quoted, with `""` for a
+/// literal quote and line breaks allowed inside quotes.
+pub fn parse_batch_csv(contents: &str) -> Result<Vec<BatchRow>, Box<dyn std::error::Error>> {
+    let mut records = csv_records(contents)?.into_iter();
+    let header: Vec<String> = records.next().ok_or("Empty CSV file")?.into_iter().map(|h| h.trim().to_lowercase()).collect();
+    let column = |name: &str| header.iter().position(|h| h == name);
+    if column("text").is_none() {
+        return Err("CSV header has no `text` column".into());
+    }
+    let mut rows = Vec::new();
+    for record in records {
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        let field = |name: &str| column(name).and_then(|c| record.get(c)).map(|f| f.trim().to_string()).filter(|f| !f.is_empty());
+        let row = rows.len() + 1;
+        rows.push(batch_row(row, field("text"), field("voice"), field("pitch"), field("tempo"), field("output")).map_err(|e| format!("Row {}: {}", row, e))?);
+    }
+    Ok(rows)
+}
+
+fn batch_row(row: usize, text: Option<String>, voice: Option<String>, pitch: Option<String>, tempo: Option<String>, output: Option<String>) -> Result<BatchRow, String> {
+    let text = text.filter(|t| !t.trim().is_empty()).ok_or("missing `text`")?;
+    let pitch = pitch.map(|p| PitchArg::from_str(&p)).transpose()?;
+    let tempo = tempo.map(|t| t.parse::<f32>().map_err(|_| format!("invalid tempo '{}'", t))).transpose()?;
+    Ok(BatchRow { row, text, voice, pitch, tempo, output })
+}
+
+/// Split CSV into records of fields
+fn csv_records(contents: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("Unterminated quoted field in CSV".into());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Where a row's audio goes: its `output` (relative to `output_dir`) or `NNNN.wav` by row number
+pub fn row_output(row: &BatchRow, output_dir: &Path) -> PathBuf {
+    match &row.output {
+        Some(output) => output_dir.join(output),
+        None => output_dir.join(format!("{:04}.wav", row.row)),
+    }
+}
+
+/// Render every row on a pool of `options.jobs` workers. A failing row is logged and recorded in the
+/// report; the other rows still run. Rows whose sidecar shows an up-to-date output are skipped.
+pub fn run_batch(rows: &[BatchRow], options: &BatchOptions) -> BatchReport {
+    if rows.is_empty() {
+        return BatchReport::default();
+    }
+    let workers = if options.jobs == 0 { default_batch_jobs() } else { options.jobs }.min(rows.len());
+    log::info!("{} Rendering {} rows with {} concurrent job(s)", "[Batch]".cyan(), rows.len(), workers);
+
+    let next = AtomicUsize::new(0);
+    let report = Mutex::new(BatchReport::default());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(row) = rows.get(index) else {
+                    break;
+                };
+                let output = row_output(row, &options.output_dir);
+                match render_row(row, &output, options) {
+                    Ok(true) => {
+                        log::info!("{} [{}/{}] {}", "[Batch]".cyan(), row.row, rows.len(), output.display());
+                        report.lock().unwrap().rendered += 1;
+                    }
+                    Ok(false) => {
+                        log::info!("{} [{}/{}] {} (unchanged, skipping)", "[Batch]".cyan(), row.row, rows.len(), output.display());
+                        report.lock().unwrap().skipped += 1;
+                    }
+                    Err(e) => {
+                        log::error!("{} Row {} ({}): {}", "[Batch]".red(), row.row, output.display(), e);
+                        report.lock().unwrap().failures.push(BatchFailure { row: row.row, output: output.display().to_string(), error: e.to_string() });
+                    }
+                }
+            });
+        }
+    });
+    // Warnings from concurrent rows can't be told apart, so they aren't reported per row
+    crate::warnings::take_warnings();
+    let mut report = report.into_inner().unwrap();
+    report.failures.sort_by_key(|failure| failure.row);
+    report
+}
+
+/// Render one row to `output`; false if it was already up to date
+fn render_row(row: &BatchRow, output: &Path, options: &BatchOptions) -> Result<bool, Box<dyn std::error::Error>> {
+    let voice = row.voice.as_deref().unwrap_or(&options.default_voice);
+    let pitch = row.pitch.clone().unwrap_or(PitchArg::Value(1.0));
+    let tempo = crate::rate::rated_tempo(row.tempo.unwrap_or(options.default_tempo));
+    let format = AudioFormat::from_path(output).ok_or_else(|| format!("unsupported output format: {}", output.display()))?;
+    let output_str = output.to_string_lossy().to_string();
+    let mut meta = ExportSidecar::new(&row.text, voice, pitch.as_factor(), tempo, "low", &output_str);
+    if stereo_output().is_stereo() {
+        meta.effects.push(stereo_output().describe());
+    }
+    if !options.force && is_up_to_date(output, &meta) {
+        return Ok(false);
+    }
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let (samples, sample_rate) = synthesize_processed(&row.text, voice, &pitch, tempo)?;
+    let tags = AudioTags::for_synthesis(voice, pitch.as_factor(), tempo);
+    if format == AudioFormat::Wav {
+        write_wav(&output_str, &samples, sample_rate)?;
+        pan_wav(&output_str)?;
+        tag_wav(&output_str, &tags)?;
+    } else {
+        // Other formats are encoded from a WAV rendered beside the output
+        let wav_path = output.with_extension(format!("{}.wav", format.extension()));
+        write_wav(&wav_path.to_string_lossy(), &samples, sample_rate)?;
+        pan_wav(&wav_path.to_string_lossy())?;
+        let settings = EncodeSettings { format, ..EncodeSettings::default() };
+        let encoded = encode_wav(&wav_path, output, &settings, &tags);
+        let _ = std::fs::remove_file(&wav_path);
+        encoded?;
+    }
+    meta.finalize(None);
+    write_sidecar(output, &meta)?;
+    crate::session::record_output(output);
+    Ok(true)
+}
@@ -0,0 +1,85 @@
+use crate::visemes::{VisemeEvent, SILENCE};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+/// Shape key prefix used by the bundled Blender add-on (`viseme_AA`, `viseme_BM`, ...)
+pub const DEFAULT_SHAPE_PREFIX: &str = "viseme_";
+
+/// One shape-key keyframe
+#[derive(Debug, Clone, Serialize)]
+pub struct ShapeKeyframe {
+    pub frame: i64,
+    pub shape: String,
+    pub value: f32,
+}
+
+/// Convert a viseme track into shape-key keyframes.
+/// Each viseme's shape key rises to 1.0 at its start frame and falls back to 0.0 at its end frame;
+/// silence keys nothing, so all shapes rest at 0.0. Frames are offset by `frame_start`.
+pub fn shape_key_keyframes(visemes: &[VisemeEvent], fps: f32, frame_start: i64, prefix: &str) -> Vec<ShapeKeyframe> {
+    // (frame, shape) -> value; a shape reaching 1.0 wins over one returning to 0.0 on the same frame
+    let mut keys: BTreeMap<(i64, String), f32> = BTreeMap::new();
+    let to_frame = |t: f32| frame_start + (t * fps).round() as i64;
+    for event in visemes.iter().filter(|e| e.viseme != SILENCE) {
+        let shape = format!("{}{}", prefix, event.viseme);
+        let start = to_frame(event.start);
+        let end = to_frame(event.end).max(start + 1);
+        // Rest one frame before, so the shape ramps in rather than holding from the previous key
+        keys.entry((start - 1, shape.clone())).or_insert(0.0);
+        keys.insert((start, shape.clone()), 1.0);
+        keys.entry((end, shape)).or_insert(0.0);
+    }
+    keys.into_iter()
+        .map(|((frame, shape), value)| ShapeKeyframe { frame, shape, value })
+        .collect()
+}
+
+/// A Python script that keys the active object's shape keys when run in Blender's text editor
+pub fn blender_script(keyframes: &[ShapeKeyframe], fps: f32, audio_path: Option<&str>) -> String {
+    let mut py = String::from("# Generated by text-to-face: run in Blender with the character mesh selected\nimport bpy\n\n");
+    let _ = writeln!(py, "FPS = {}", fps.round() as i64);
+    let _ = writeln!(py, "AUDIO = {}", audio_path.map(|p| format!("{:?}", p)).unwrap_or_else(|| "None".to_string()));
+    py.push_str("KEYFRAMES = [\n");
+    for k in keyframes {
+        let _ = writeln!(py, "    ({}, {:?}, {}),", k.frame, k.shape, k.value);
+    }
+    py.push_str("]\n\n");
+    py.push_str(
+        r#"scene = bpy.context.scene
+scene.render.fps = FPS
+obj = bpy.context.active_object
+if obj is None or obj.data.shape_keys is None:
+    raise RuntimeError("Select a mesh with shape keys before running this script")
+blocks = obj.data.shape_keys.key_blocks
+missing = set()
+for frame, shape, value in KEYFRAMES:
+    block = blocks.get(shape)
+    if block is None:
+        missing.add(shape)
+        continue
+    block.value = value
+    block.keyframe_insert("value", frame=frame)
+if KEYFRAMES:
+    scene.frame_end = max(scene.frame_end, max(k[0] for k in KEYFRAMES))
+if AUDIO:
+    if not scene.sequence_editor:
+        scene.sequence_editor_create()
+    scene.sequence_editor.sequences.new_sound("text-to-face", AUDIO, 1, scene.frame_start)
+if missing:
+    print("text-to-face: shape keys not found on", obj.name, ":", ", ".join(sorted(missing)))
+"#,
+    );
+    py
+}
+
+/// Write shape-key keyframes as a Blender Python script (`.py`) or JSON (anything else)
+pub fn write_blender_keyframes(path: &str, keyframes: &[ShapeKeyframe], fps: f32, audio_path: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    if path.to_lowercase().ends_with(".py") {
+        std::fs::write(path, blender_script(keyframes, fps, audio_path))?;
+    } else {
+        let json = serde_json::json!({ "fps": fps, "audio": audio_path, "keyframes": keyframes });
+        std::fs::write(path, serde_json::to_string_pretty(&json)?)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+//! Cancelling long-running work: synthesis stops between sentences, pitch shifting and time stretching
+//! between frames, and downloads between reads, failing with [`crate::Error::Cancelled`].
+//!
+//! ```no_run
+//! use text_to_face::cancel::{self, CancelToken};
+//!
+//! let token = CancelToken::new();
+//! let stop = token.clone();
+//! // e.g. from a Cancel button on the UI thread
+//! std::thread::spawn(move || stop.cancel());
+//! let result = cancel::with_token(&token, || {
+//!     text_to_face::synthesize_processed("A long chapter...", "en_GB-alba-medium", &text_to_face::PitchArg::Value(1.0), 1.0)
+//! });
+//! ```
+//!
+//! The token applies to the thread running `with_token`, so other threads' work carries on.
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A handle for cancelling work. Clones share the flag, so keep one to call [`CancelToken::cancel`]
+/// from another thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the work to stop at its next check
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+thread_local! {
+    /// The token the work on this thread checks
+    static TOKEN: RefCell<Option<CancelToken>> = const { RefCell::new(None) };
+}
+
+/// Run `work` on this thread with `token` checked, then put back whichever token was there before
+pub fn with_token<T>(token: &CancelToken, work: impl FnOnce() -> T) -> T {
+    let previous = TOKEN.with(|current| current.replace(Some(token.clone())));
+    let result = work();
+    TOKEN.with(|current| *current.borrow_mut() = previous);
+    result
+}
+
+/// Whether the work on this thread has been cancelled
+pub fn is_cancelled() -> bool {
+    TOKEN.with(|current| current.borrow().as_ref().is_some_and(CancelToken::is_cancelled))
+}
+
+/// [`crate::Error::Cancelled`] if the work on this thread has been cancelled
+pub fn check() -> crate::error::Result<()> {
+    if is_cancelled() { Err(crate::Error::Cancelled) } else { Ok(()) }
+}
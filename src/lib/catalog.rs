@@ -0,0 +1,159 @@
+//! A user manifest merged with the built-in voice catalog: `voices.toml` (or `voices.json`) in the app
+//! data directory, or the file given with [`set_manifest_path`] (`--catalog`). It can hide voices,
+//! point downloads at a mirror, and add or re-point single voices:
+//!
+//! ```toml
+//! # Download every catalog voice from an internal mirror of rhasspy/piper-voices
+//! base_url = "https://mirror.example.com/piper-voices/resolve/main"
+//! hide = ["en_US-ryan-high"]
+//!
+//! [[voice]]
+//! id = "en_GB-alba-medium"
+//! model = "https://cdn.example.com/alba/en_GB-alba-medium.onnx"
+//! config = "https://cdn.example.com/alba/en_GB-alba-medium.onnx.json"
+//!
+//! [[voice]]
+//! id = "en_US-studio-high"
+//! language = "American English"
+//! model = "https://cdn.example.com/studio/en_US-studio-high.onnx"
+//! config = "https://cdn.example.com/studio/en_US-studio-high.onnx.json"
+//! ```
+
+use crate::Voice;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A voice the manifest adds, or changes when the ID is already in the catalog. Unset fields keep the
+/// catalog's values; a new voice needs `model` and `config`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestVoice {
+    pub id: String,
+    pub language: Option<String>,
+    /// Defaults to the last part of the ID for new voices, e.g. `medium`
+    pub quality: Option<String>,
+    /// URL of the `.onnx` model
+    pub model: Option<String>,
+    /// URL of the `.onnx.json` config
+    pub config: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CatalogManifest {
+    /// Replaces the upstream `https://huggingface.co/rhasspy/piper-voices/resolve/main` in catalog URLs
+    pub base_url: Option<String>,
+    /// Voice IDs to leave out
+    #[serde(default)]
+    pub hide: Vec<String>,
+    #[serde(default, rename = "voice")]
+    pub voices: Vec<ManifestVoice>,
+}
+
+impl CatalogManifest {
+    /// Read a manifest, as JSON for `.json` files and TOML otherwise
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        let parsed = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&contents).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(&contents).map_err(|e| e.to_string())
+        };
+        let manifest: Self = parsed.map_err(|e| format!("Invalid voice manifest {}: {}", path.display(), e))?;
+        if let Some(voice) = manifest.voices.iter().find(|voice| voice.id.trim().is_empty()) {
+            return Err(format!("Invalid voice manifest {}: a voice has no id ({:?})", path.display(), voice).into());
+        }
+        Ok(manifest)
+    }
+
+    /// The catalog with this manifest applied
+    pub fn apply(&self, mut voices: Vec<Voice>) -> Result<Vec<Voice>, String> {
+        if let Some(base_url) = &self.base_url {
+            let base_url = base_url.trim_end_matches('/');
+            for voice in &mut voices {
+                for url in [&mut voice.model_path, &mut voice.config_path] {
+                    if let Some(rest) = url.strip_prefix(crate::HF_BASE) {
+                        *url = format!("{}{}", base_url, rest);
+                    }
+                }
+            }
+        }
+        voices.retain(|voice| !self.hide.contains(&voice.id));
+        for entry in &self.voices {
+            if self.hide.contains(&entry.id) {
+                continue;
+            }
+            match voices.iter_mut().find(|voice| voice.id == entry.id) {
+                Some(voice) => {
+                    if let Some(language) = &entry.language {
+                        voice.language = language.clone();
+                    }
+                    if let Some(quality) = &entry.quality {
+                        voice.quality = quality.clone();
+                        voice.display_name = format!("{} ({})", voice.id.replace('_', " "), quality);
+                    }
+                    if let Some(model) = &entry.model {
+                        voice.model_path = model.clone();
+                    }
+                    if let Some(config) = &entry.config {
+                        voice.config_path = config.clone();
+                    }
+                }
+                None => {
+                    let (Some(model), Some(config)) = (&entry.model, &entry.config) else {
+                        return Err(format!("{} isn't in the catalog, so it needs both a model and a config URL", entry.id));
+                    };
+                    let quality = entry.quality.clone().unwrap_or_else(|| entry.id.rsplit('-').next().unwrap_or("medium").to_string());
+                    voices.push(Voice {
+                        id: entry.id.clone(),
+                        display_name: format!("{} ({})", entry.id.replace('_', " "), quality),
+                        language: entry.language.clone().unwrap_or_else(|| "Custom".to_string()),
+                        quality,
+                        model_path: model.clone(),
+                        config_path: config.clone(),
+                    });
+                }
+            }
+        }
+        Ok(voices)
+    }
+}
+
+/// Set from `--catalog`; `None` looks for `voices.toml` or `voices.json` in the app data directory
+static MANIFEST_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+pub fn set_manifest_path(path: Option<PathBuf>) {
+    *MANIFEST_PATH.lock().unwrap() = path;
+}
+
+/// The manifest in use, if there is one
+pub fn manifest_path() -> Option<PathBuf> {
+    if let Some(path) = MANIFEST_PATH.lock().unwrap().clone() {
+        return Some(path);
+    }
+    let data_dir = crate::get_app_data_dir();
+    ["voices.toml", "voices.json"].into_iter().map(|name| data_dir.join(name)).find(|path| path.exists())
+}
+
+/// The built-in catalog with the manifest applied. A manifest that can't be read or applied is
+/// reported and ignored, so the built-in voices still work.
+pub fn merged(voices: Vec<Voice>) -> Vec<Voice> {
+    let Some(path) = manifest_path() else {
+        return voices;
+    };
+    let manifest = match CatalogManifest::load(&path) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            log::warn!("[Catalog] Ignoring {}", e);
+            return voices;
+        }
+    };
+    match manifest.apply(voices.clone()) {
+        Ok(merged) => merged,
+        Err(e) => {
+            log::warn!("[Catalog] Ignoring {}: {}", path.display(), e);
+            voices
+        }
+    }
+}
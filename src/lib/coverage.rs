@@ -0,0 +1,100 @@
+use crate::visemes::{arpabet_to_viseme, ipa_to_viseme, ARPABET_PHONEMES, SILENCE, VISEMES};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Which phonemes and visemes a script exercises, and how often
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CoverageReport {
+    /// `arpabet` (CMUdict, English) or `ipa` (the voice's own phonemizer)
+    pub alphabet: &'static str,
+    pub words: usize,
+    /// Total phonemes counted
+    pub phoneme_count: usize,
+    pub phonemes: BTreeMap<String, usize>,
+    pub visemes: BTreeMap<String, usize>,
+    /// Phonemes of the inventory that never occur
+    pub missing_phonemes: Vec<String>,
+    /// Visemes that never occur, i.e. mouth shapes the script doesn't need
+    pub missing_visemes: Vec<String>,
+    /// Distinct words with no known pronunciation, which aren't counted
+    pub unknown_words: Vec<String>,
+}
+
+impl CoverageReport {
+    fn finish(mut self, inventory: &[String]) -> Self {
+        self.phoneme_count = self.phonemes.values().sum();
+        self.missing_phonemes = inventory.iter().filter(|p| !self.phonemes.contains_key(*p)).cloned().collect();
+        self.missing_visemes = VISEMES.iter().filter(|v| !self.visemes.contains_key(**v)).map(|v| v.to_string()).collect();
+        self
+    }
+
+    /// Share of all counted phonemes, from 0.0 to 1.0
+    pub fn share(&self, count: usize) -> f32 {
+        if self.phoneme_count == 0 { 0.0 } else { count as f32 / self.phoneme_count as f32 }
+    }
+}
+
+/// Coverage of ARPAbet pronunciations, one entry per word (`None` for words without one).
+/// Stress markers are dropped, so `AH0` and `AH1` both count as `AH`.
+pub fn arpabet_coverage(words: &[(String, Option<Vec<String>>)]) -> CoverageReport {
+    let mut report = CoverageReport { alphabet: "arpabet", words: words.len(), ..Default::default() };
+    for (word, phonemes) in words {
+        let Some(phonemes) = phonemes.as_ref().filter(|p| !p.is_empty()) else {
+            if !report.unknown_words.contains(word) {
+                report.unknown_words.push(word.clone());
+            }
+            continue;
+        };
+        for phoneme in phonemes {
+            let base = phoneme.trim_end_matches(|c: char| c.is_ascii_digit());
+            *report.phonemes.entry(base.to_string()).or_default() += 1;
+            let viseme = arpabet_to_viseme(base);
+            if viseme != SILENCE {
+                *report.visemes.entry(viseme.to_string()).or_default() += 1;
+            }
+        }
+    }
+    let inventory: Vec<String> = ARPABET_PHONEMES.iter().map(|p| p.to_string()).collect();
+    report.finish(&inventory)
+}
+
+/// Coverage of IPA phoneme strings (one per sentence). Stress and length marks, spaces, and punctuation
+/// aren't phonemes and aren't counted. Piper voices share one symbol table across all languages, so there's
+/// no per-language inventory to report missing phonemes against; missing visemes are still reported.
+pub fn ipa_coverage(sentences: &[String]) -> CoverageReport {
+    let mut report = CoverageReport { alphabet: "ipa", ..Default::default() };
+    for sentence in sentences {
+        report.words += sentence.split_whitespace().count();
+        // Stress and length marks are letters to Unicode, but live in the spacing modifier block
+        for symbol in sentence.chars().filter(|c| c.is_alphabetic() && !('\u{02B0}'..='\u{02FF}').contains(c)) {
+            *report.phonemes.entry(symbol.to_string()).or_default() += 1;
+            if let Some(viseme) = ipa_to_viseme(symbol) {
+                *report.visemes.entry(viseme.to_string()).or_default() += 1;
+            }
+        }
+    }
+    report.finish(&[])
+}
+
+#[cfg(feature = "full")]
+/// Phoneme and viseme coverage of a script as read by `voice`. English voices use CMUdict, the same
+/// source as the lipsync phonemes; other languages use the voice's own phonemizer (installing the voice
+/// if needed).
+pub fn script_coverage(text: &str, voice: &str) -> Result<CoverageReport, Box<dyn std::error::Error>> {
+    // Count what the voice will actually say
+    let text = &crate::normalize::normalize_for_voice(&crate::rules::apply_rules(text), voice);
+    if voice.starts_with("en") {
+        let words: Vec<(String, Option<Vec<String>>)> = text
+            .split_whitespace()
+            .filter(|w| w.chars().any(char::is_alphanumeric))
+            .map(|w| (w.trim_matches(|c: char| !c.is_alphanumeric()).to_string(), crate::cmudict_phonemes(w)))
+            .collect();
+        return Ok(arpabet_coverage(&words));
+    }
+    let voices = crate::get_available_voices();
+    let entry = voices.iter().find(|v| v.id == voice).ok_or_else(|| format!("Voice '{}' not found", voice))?;
+    let (_, config_path) = crate::download_voice_files(entry)?;
+    let config_path = crate::overrides::config_for_voice(voice, std::path::Path::new(&config_path))?;
+    let model = piper_rs::from_config_path(&config_path)?;
+    Ok(ipa_coverage(&model.phonemize_text(text)?.to_vec()))
+}
@@ -0,0 +1,404 @@
+//! A long-running process that keeps voice models loaded and works through a queue of synthesis jobs,
+//! so interactive apps get audio in well under a second instead of waiting on a cold start. Clients
+//! talk to it over a local socket: a Unix socket, or a loopback TCP port on Windows.
+//!
+//! Each connection sends one JSON [`Request`] line and reads one JSON [`Response`] line back:
+//!
+//! - `{"command":"enqueue","text":"Hello.","output":"/abs/hello.wav"}` queues a job: `{"job":{"id":1,...}}`
+//! - `{"command":"status"}` lists recent jobs; with `"job":1`, just that one
+//! - `{"command":"wait","job":1}` answers once the job has finished
+//! - `{"command":"cancel","job":1}` drops a queued job, or stops a running one between sentences
+//! - `{"command":"shutdown"}` stops the daemon after the running job
+//!
+//! Jobs run one at a time in the order they were queued: synthesis already keeps the CPU busy, and the
+//! global settings and warnings are process-wide. The daemon's own global options (rules, pauses,
+//! `--model-cache`, ...) apply to every job.
+
+use crate::{synthesize_and_handle, LipsyncLevel, PitchArg};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Finished jobs remembered for `status`, oldest dropped first
+const JOB_HISTORY: usize = 100;
+
+#[cfg(unix)]
+type Stream = std::os::unix::net::UnixStream;
+#[cfg(not(unix))]
+type Stream = std::net::TcpStream;
+
+/// Where the daemon listens unless told otherwise: `pitch-tts.sock` in the runtime directory (or the
+/// temp directory), or a loopback port on Windows
+pub fn default_socket() -> String {
+    if cfg!(unix) {
+        let dir = std::env::var_os("XDG_RUNTIME_DIR").map(std::path::PathBuf::from).unwrap_or_else(std::env::temp_dir);
+        dir.join("pitch-tts.sock").to_string_lossy().into_owned()
+    } else {
+        "127.0.0.1:7583".to_string()
+    }
+}
+
+fn connect(socket: &str) -> std::io::Result<Stream> {
+    Stream::connect(socket)
+}
+
+/// What a job synthesizes and where it goes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobSpec {
+    pub text: String,
+    /// The daemon's default voice when left out
+    #[serde(default)]
+    pub voice: Option<String>,
+    /// Pitch factor (1.0 = unchanged)
+    #[serde(default = "unchanged")]
+    pub pitch: f32,
+    #[serde(default = "unchanged")]
+    pub tempo: f32,
+    /// WAV file to write. Resolved by the daemon, so clients send absolute paths.
+    pub output: String,
+    /// `estimate`, `align`, `piper`, or `high` for lipsync JSON next to the WAV; none when left out
+    #[serde(default)]
+    pub lipsync: Option<String>,
+}
+
+fn unchanged() -> f32 {
+    1.0
+}
+
+impl JobSpec {
+    fn lipsync_level(&self) -> Result<LipsyncLevel, String> {
+        match &self.lipsync {
+            None => Ok(LipsyncLevel::Low),
+            Some(name) => LipsyncLevel::from_str(name, true).map_err(|_| format!("Unknown lipsync level '{}' (use estimate, align, piper, or high)", name)),
+        }
+    }
+
+    /// The lipsync JSON written next to the WAV, e.g. `hello.json` for `hello.wav`
+    fn lipsync_path(&self) -> String {
+        Path::new(&self.output).with_extension("json").to_string_lossy().into_owned()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_finished(self) -> bool {
+        matches!(self, JobState::Done | JobState::Failed | JobState::Cancelled)
+    }
+}
+
+/// A job as `status` reports it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobStatus {
+    pub id: u64,
+    pub state: JobState,
+    #[serde(flatten)]
+    pub spec: JobSpec,
+    /// Lipsync JSON written, if the job asked for lipsync and finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lipsync_json: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Seconds from starting the job to finishing it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seconds: Option<f32>,
+}
+
+/// One request line from a client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub enum Request {
+    Enqueue(JobSpec),
+    Status {
+        #[serde(default)]
+        job: Option<u64>,
+    },
+    Wait {
+        job: u64,
+    },
+    Cancel {
+        job: u64,
+    },
+    Shutdown,
+}
+
+/// The daemon's answer to a [`Request`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Response {
+    Job(JobStatus),
+    Jobs(Vec<JobStatus>),
+    Ok,
+    Error(String),
+}
+
+/// Send `request` to the daemon at `socket` and read its response
+pub fn send(socket: &str, request: &Request) -> Result<Response, Box<dyn std::error::Error>> {
+    let mut stream = connect(socket).map_err(|e| format!("Can't reach the daemon at {} ({}); start it with `daemon`", socket, e))?;
+    writeln!(stream, "{}", serde_json::to_string(request)?)?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    if line.trim().is_empty() {
+        return Err("The daemon closed the connection without answering".into());
+    }
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// How the daemon starts
+#[derive(Debug, Clone)]
+pub struct DaemonOptions {
+    /// Unix socket path, or `host:port` on Windows
+    pub socket: String,
+    /// Voice for jobs that don't name one, loaded before the daemon starts listening
+    pub default_voice: String,
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: VecDeque<JobStatus>,
+    next_id: u64,
+    shutting_down: bool,
+    /// Stops the running job
+    running: crate::cancel::CancelToken,
+}
+
+impl Queue {
+    fn find(&mut self, id: u64) -> Option<&mut JobStatus> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    /// Forget the oldest finished jobs beyond [`JOB_HISTORY`]
+    fn trim(&mut self) {
+        let mut finished = self.jobs.iter().filter(|job| job.state.is_finished()).count();
+        self.jobs.retain(|job| {
+            if finished > JOB_HISTORY && job.state.is_finished() {
+                finished -= 1;
+                return false;
+            }
+            true
+        });
+    }
+}
+
+/// The queue and the condition variable that announces every change to it
+type Shared = Arc<(Mutex<Queue>, Condvar)>;
+
+fn not_found(id: u64) -> Response {
+    Response::Error(format!("No job {}", id))
+}
+
+fn handle_request(request: Request, shared: &Shared) -> Response {
+    let (queue, changed) = &**shared;
+    let mut queue = queue.lock().unwrap();
+    match request {
+        Request::Enqueue(spec) => {
+            if queue.shutting_down {
+                return Response::Error("The daemon is shutting down".to_string());
+            }
+            if let Err(e) = spec.lipsync_level().and(crate::limits::check_pitch(spec.pitch)).and(crate::limits::check_tempo(spec.tempo)) {
+                return Response::Error(e);
+            }
+            if !Path::new(&spec.output).is_absolute() {
+                return Response::Error(format!("Output path {} isn't absolute", spec.output));
+            }
+            queue.next_id += 1;
+            let job = JobStatus { id: queue.next_id, state: JobState::Queued, spec, lipsync_json: None, warnings: Vec::new(), error: None, seconds: None };
+            queue.jobs.push_back(job.clone());
+            changed.notify_all();
+            Response::Job(job)
+        }
+        Request::Status { job: Some(id) } => queue.find(id).map_or_else(|| not_found(id), |job| Response::Job(job.clone())),
+        Request::Status { job: None } => Response::Jobs(queue.jobs.iter().cloned().collect()),
+        Request::Wait { job: id } => loop {
+            match queue.find(id) {
+                None => return not_found(id),
+                Some(job) if job.state.is_finished() => return Response::Job(job.clone()),
+                Some(_) => queue = changed.wait(queue).unwrap(),
+            }
+        },
+        Request::Cancel { job: id } => {
+            let Some(job) = queue.find(id) else {
+                return not_found(id);
+            };
+            match job.state {
+                JobState::Queued => {
+                    job.state = JobState::Cancelled;
+                    let job = job.clone();
+                    changed.notify_all();
+                    Response::Job(job)
+                }
+                JobState::Running => {
+                    // The worker marks it cancelled once it stops
+                    queue.running.cancel();
+                    Response::Job(queue.find(id).unwrap().clone())
+                }
+                _ => Response::Error(format!("Job {} has already finished", id)),
+            }
+        }
+        Request::Shutdown => {
+            queue.shutting_down = true;
+            for job in queue.jobs.iter_mut().filter(|job| job.state == JobState::Queued) {
+                job.state = JobState::Cancelled;
+            }
+            changed.notify_all();
+            Response::Ok
+        }
+    }
+}
+
+/// Synthesize one job into its output files, returning the lipsync JSON path and the warnings
+fn run_job(spec: &JobSpec, default_voice: &str) -> Result<(Option<String>, Vec<String>), Box<dyn std::error::Error>> {
+    let voice = spec.voice.as_deref().unwrap_or(default_voice);
+    let level = spec.lipsync_level()?;
+    let lipsync_json = (level != LipsyncLevel::Low).then(|| spec.lipsync_path());
+    let report = synthesize_and_handle(&spec.text, voice, &PitchArg::Value(spec.pitch), spec.tempo, Some(&spec.output), false, level, lipsync_json.as_deref(), None)?;
+    Ok((lipsync_json, report.warnings.iter().map(ToString::to_string).collect()))
+}
+
+/// The message a panic was raised with
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+    panic.downcast_ref::<&str>().copied().or_else(|| panic.downcast_ref::<String>().map(String::as_str)).unwrap_or("unknown error")
+}
+
+/// Take queued jobs one at a time until shutdown
+fn work(shared: &Shared, default_voice: &str) {
+    let (queue, changed) = &**shared;
+    loop {
+        let (id, spec, token) = {
+            let mut queue = queue.lock().unwrap();
+            loop {
+                if let Some(job) = queue.jobs.iter_mut().find(|job| job.state == JobState::Queued) {
+                    job.state = JobState::Running;
+                    let (id, spec) = (job.id, job.spec.clone());
+                    queue.running = crate::cancel::CancelToken::new();
+                    break (id, spec, queue.running.clone());
+                }
+                if queue.shutting_down {
+                    return;
+                }
+                queue = changed.wait(queue).unwrap();
+            }
+        };
+        let started = std::time::Instant::now();
+        log::info!("[Daemon] Job {} started ({} chars)", id, spec.text.chars().count());
+        // A panic fails its job rather than the worker, which would leave every later job queued
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| crate::cancel::with_token(&token, || run_job(&spec, default_voice))))
+            .unwrap_or_else(|panic| Err(format!("Synthesis panicked: {}", panic_message(&*panic)).into()));
+        let mut queue = queue.lock().unwrap();
+        if let Some(job) = queue.find(id) {
+            job.seconds = Some(started.elapsed().as_secs_f32());
+            match result {
+                Ok((lipsync_json, warnings)) => {
+                    log::info!("[Daemon] Job {} done in {:.2}s -> {}", id, started.elapsed().as_secs_f32(), spec.output);
+                    (job.state, job.lipsync_json, job.warnings) = (JobState::Done, lipsync_json, warnings);
+                }
+                Err(e) if matches!(crate::Error::find(&*e), Some(crate::Error::Cancelled)) => {
+                    log::info!("[Daemon] Job {} cancelled", id);
+                    job.state = JobState::Cancelled;
+                }
+                Err(e) => {
+                    log::error!("[Daemon] Job {} failed: {}", id, e);
+                    (job.state, job.error) = (JobState::Failed, Some(e.to_string()));
+                }
+            }
+        }
+        queue.trim();
+        changed.notify_all();
+    }
+}
+
+/// Read a connection's request line. Clients send it straight away, so a silent one is dropped
+/// rather than holding up the others.
+fn read_request(stream: &Stream) -> Result<Request, String> {
+    let _ = stream.set_read_timeout(Some(std::time::Duration::from_secs(5)));
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).map_err(|e| format!("Failed to read the request: {}", e))?;
+    serde_json::from_str(&line).map_err(|e| format!("Invalid request JSON: {}", e))
+}
+
+fn respond(mut stream: Stream, response: &Response) {
+    if let Ok(json) = serde_json::to_string(response) {
+        let _ = writeln!(stream, "{}", json);
+    }
+}
+
+#[cfg(unix)]
+fn listen(socket: &str) -> Result<std::os::unix::net::UnixListener, Box<dyn std::error::Error>> {
+    if Path::new(socket).exists() {
+        // A socket nobody answers on is left over from a daemon that didn't shut down cleanly
+        if connect(socket).is_ok() {
+            return Err(format!("A daemon is already listening on {}", socket).into());
+        }
+        std::fs::remove_file(socket)?;
+    }
+    Ok(std::os::unix::net::UnixListener::bind(socket).map_err(|e| format!("Failed to listen on {}: {}", socket, e))?)
+}
+
+#[cfg(not(unix))]
+fn listen(socket: &str) -> Result<std::net::TcpListener, Box<dyn std::error::Error>> {
+    Ok(std::net::TcpListener::bind(socket).map_err(|e| format!("Failed to listen on {}: {}", socket, e))?)
+}
+
+/// Load the default voice, then take requests on `options.socket` until a client asks for shutdown.
+/// The running job finishes first; queued ones are cancelled.
+pub fn run(options: &DaemonOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let started = std::time::Instant::now();
+    crate::backend::backend(&options.default_voice).load()?;
+    log::info!("[Daemon] Loaded {} in {:.2}s", options.default_voice, started.elapsed().as_secs_f32());
+    let listener = listen(&options.socket)?;
+    log::info!("[Daemon] Listening on {}", options.socket);
+
+    let shared: Shared = Arc::default();
+    let worker = {
+        let (shared, default_voice) = (shared.clone(), options.default_voice.clone());
+        std::thread::spawn(move || work(&shared, &default_voice))
+    };
+    let mut waiting: Vec<std::thread::JoinHandle<()>> = Vec::new();
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::error!("[Daemon] Failed to accept a connection: {}", e);
+                continue;
+            }
+        };
+        match read_request(&stream) {
+            // `wait` holds its connection until the job finishes, so it gets a thread of its own
+            Ok(request @ Request::Wait { .. }) => {
+                let shared = shared.clone();
+                waiting.retain(|waiter| !waiter.is_finished());
+                waiting.push(std::thread::spawn(move || respond(stream, &handle_request(request, &shared))));
+            }
+            Ok(request) => {
+                let shutdown = matches!(request, Request::Shutdown);
+                respond(stream, &handle_request(request, &shared));
+                if shutdown {
+                    break;
+                }
+            }
+            Err(e) => respond(stream, &Response::Error(e)),
+        }
+    }
+    let _ = worker.join();
+    for waiter in waiting {
+        let _ = waiter.join();
+    }
+    if cfg!(unix) {
+        let _ = std::fs::remove_file(&options.socket);
+    }
+    log::info!("[Daemon] Stopped");
+    Ok(())
+}
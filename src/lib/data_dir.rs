@@ -0,0 +1,101 @@
+//! Where downloaded voices, CMUdict, and the user's own files (rules, lexicon, overrides) live: the
+//! platform's data directory, e.g. `~/.local/share/pitch-tts` on Linux, so every working directory
+//! shares one copy of the models.
+//!
+//! [`set_data_dir`] (the CLI's `--data-dir`) or the `PITCH_TTS_DATA_DIR` environment variable moves it.
+//! [`migrate`], which the CLI runs at startup, brings files from older layouts into the default
+//! directory: the previous `text-to-face` app data directory is moved, and catalog voices in `./models`
+//! and CMUdict in `./extra` are copied.
+
+use directories::ProjectDirs;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, Once};
+
+/// Environment variable that moves the data directory, when [`set_data_dir`] hasn't
+pub const DATA_DIR_ENV: &str = "PITCH_TTS_DATA_DIR";
+
+static DATA_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+static MIGRATED: Once = Once::new();
+
+/// Keep data in `dir` instead of the default, or go back to the default (or `PITCH_TTS_DATA_DIR`) for `None`
+pub fn set_data_dir(dir: Option<PathBuf>) {
+    *DATA_DIR.lock().unwrap() = dir;
+}
+
+/// The platform's data directory for pitch-tts, whatever the overrides say
+pub fn default_data_dir() -> PathBuf {
+    ProjectDirs::from("", "", "pitch-tts").map_or_else(|| PathBuf::from(".pitch-tts"), |dirs| dirs.data_dir().to_path_buf())
+}
+
+/// The data directory in use: [`set_data_dir`]'s, then `PITCH_TTS_DATA_DIR`, then the default
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = DATA_DIR.lock().unwrap().clone() {
+        return dir;
+    }
+    if let Some(dir) = std::env::var_os(DATA_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    default_data_dir()
+}
+
+/// Copy the files in `from` that `wanted` picks into `to`, leaving any that are already there
+fn copy_files(from: &Path, to: &Path, wanted: impl Fn(&str) -> bool) -> usize {
+    let Ok(entries) = fs::read_dir(from) else {
+        return 0;
+    };
+    let mut copied = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let target = to.join(&name);
+        if !entry.path().is_file() || !wanted(&name) || target.exists() {
+            continue;
+        }
+        match fs::create_dir_all(to).and_then(|()| fs::copy(entry.path(), &target)) {
+            Ok(_) => copied += 1,
+            Err(e) => log::warn!("[Data] Couldn't copy {} to {}: {}", entry.path().display(), to.display(), e),
+        }
+    }
+    copied
+}
+
+/// Whether `name` is a file of a catalog voice, e.g. `en_GB-alba-medium.onnx.json`
+fn is_catalog_voice_file(name: &str, ids: &[String]) -> bool {
+    crate::VOICE_FILE_SUFFIXES.iter().any(|suffix| name.strip_suffix(&format!(".{}", suffix)).is_some_and(|id| ids.iter().any(|known| known == id)))
+}
+
+/// Bring files from older layouts into the default data directory, once per process. Does nothing when
+/// [`set_data_dir`] or `PITCH_TTS_DATA_DIR` chose another directory. Only files named after catalog voices
+/// are taken from `./models`, and they're copied, so other projects' ONNX models are left alone.
+pub fn migrate() {
+    if DATA_DIR.lock().unwrap().is_some() || std::env::var_os(DATA_DIR_ENV).is_some_and(|dir| !dir.is_empty()) {
+        return;
+    }
+    MIGRATED.call_once(|| migrate_into(&default_data_dir()));
+}
+
+fn migrate_into(dir: &Path) {
+    // Before the pitch-tts name, the app data directory was text-to-face's
+    if let Some(old) = ProjectDirs::from("com", "yourorg", "text-to-face").map(|dirs| dirs.data_dir().to_path_buf())
+        && old != dir
+        && old.is_dir()
+        && !dir.exists()
+    {
+        let moved = dir.parent().map_or(Ok(()), fs::create_dir_all).and_then(|()| fs::rename(&old, dir));
+        match moved {
+            Ok(()) => log::info!("[Data] Moved {} to {}", old.display(), dir.display()),
+            Err(e) => log::warn!("[Data] Couldn't move {} to {}: {}; move it by hand to keep its voices", old.display(), dir.display(), e),
+        }
+    }
+    // Older builds downloaded into the working directory
+    let models = crate::MODELS_DIR.lock().unwrap().clone().unwrap_or_else(|| dir.join("models"));
+    let ids: Vec<String> = crate::builtin_voices().into_iter().map(|voice| voice.id).collect();
+    let voices = copy_files(Path::new("models"), &models, |name| is_catalog_voice_file(name, &ids));
+    if voices > 0 {
+        log::info!("[Data] Copied {} voice files from ./models to {}", voices, models.display());
+    }
+    if copy_files(Path::new("extra"), dir, |name| name == "cmudict-0.7b.txt") > 0 {
+        log::info!("[Data] Copied ./extra/cmudict-0.7b.txt to {}", dir.display());
+    }
+}
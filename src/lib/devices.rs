@@ -0,0 +1,59 @@
+//! Audio output device selection. Playback goes to the system's default output unless a device is
+//! chosen with `--device`, e.g. a second sound card or a virtual cable feeding another app.
+
+use rodio::cpal::traits::{DeviceTrait, HostTrait};
+use rodio::cpal;
+use std::sync::Mutex;
+
+/// Set by `--device`; read wherever audio is played
+static OUTPUT_DEVICE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Play through the output device with this name (or part of it), or the system default for `None`
+pub fn set_output_device(name: Option<String>) {
+    *OUTPUT_DEVICE.lock().unwrap() = name;
+}
+
+pub fn output_device() -> Option<String> {
+    OUTPUT_DEVICE.lock().unwrap().clone()
+}
+
+/// An output device and whether it is the system default
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct OutputDevice {
+    pub name: String,
+    pub default: bool,
+}
+
+/// The output devices of the system's audio host
+pub fn output_devices() -> Result<Vec<OutputDevice>, Box<dyn std::error::Error>> {
+    let host = cpal::default_host();
+    let default = host.default_output_device().and_then(|d| d.name().ok());
+    Ok(host
+        .output_devices()?
+        .filter_map(|d| d.name().ok())
+        .map(|name| OutputDevice { default: default.as_deref() == Some(name.as_str()), name })
+        .collect())
+}
+
+/// Find a device by exact name, falling back to a case-insensitive substring match
+pub(crate) fn find_device(devices: impl Iterator<Item = cpal::Device>, name: &str, kind: &str) -> Result<cpal::Device, Box<dyn std::error::Error>> {
+    let devices: Vec<(String, cpal::Device)> = devices.filter_map(|d| Some((d.name().ok()?, d))).collect();
+    let needle = name.to_lowercase();
+    let position = devices
+        .iter()
+        .position(|(n, _)| n == name)
+        .or_else(|| devices.iter().position(|(n, _)| n.to_lowercase().contains(&needle)))
+        .ok_or_else(|| format!("No {} device matching '{}'", kind, name))?;
+    Ok(devices.into_iter().nth(position).unwrap().1)
+}
+
+/// Open a playback stream on the selected output device (see [`set_output_device`])
+pub fn open_output_stream() -> Result<(rodio::OutputStream, rodio::OutputStreamHandle), Box<dyn std::error::Error>> {
+    match output_device() {
+        Some(name) => {
+            let device = find_device(cpal::default_host().output_devices()?, &name, "output").map_err(|e| format!("{} (see `text-to-face devices`)", e))?;
+            Ok(rodio::OutputStream::try_from_device(&device)?)
+        }
+        None => Ok(rodio::OutputStream::try_default()?),
+    }
+}
@@ -0,0 +1,156 @@
+//! Dialogue scripts: a conversation between characters, each with their own voice, rendered into one
+//! track with a gap between lines.
+//!
+//! ```text
+//! # The cast: a voice for each character, optionally with a pitch (factor or preset) and tempo
+//! ALBA = en_GB-alba-medium
+//! RYAN = en_US-ryan-high pitch=deep tempo=1.1
+//!
+//! ALBA: Hello there.
+//! RYAN: Hi! Have we met?
+//!   Lines that don't name a character carry on the previous speaker's line.
+//! ```
+//!
+//! The cast comes before the first line of dialogue. Character names are matched case-insensitively.
+//! Lines may use `@pitch(...)` and the other voice directives, which apply on top of the character's
+//! settings.
+
+use crate::directives::VoiceSettings;
+use crate::PitchArg;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// One character's line, as written in the script
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptLine {
+    /// As the cast names them
+    pub character: String,
+    pub text: String,
+    /// Line number in the script where it starts, for errors
+    pub line_number: usize,
+}
+
+/// A parsed dialogue script
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Script {
+    /// Voice settings by character name (uppercased)
+    pub cast: BTreeMap<String, VoiceSettings>,
+    pub lines: Vec<ScriptLine>,
+}
+
+impl Script {
+    /// The settings a character speaks with
+    pub fn settings(&self, character: &str) -> Option<&VoiceSettings> {
+        self.cast.get(&character.to_uppercase())
+    }
+}
+
+/// Whether `name` can be a character name: a letter first, then letters, digits, spaces, `_`, `-`, `.`, or `'`
+fn is_character_name(name: &str) -> bool {
+    name.chars().next().is_some_and(char::is_alphabetic)
+        && name.len() <= 40
+        && name.chars().all(|c| c.is_alphanumeric() || matches!(c, ' ' | '_' | '-' | '.' | '\''))
+}
+
+/// A cast line's settings: `en_US-ryan-high pitch=deep tempo=1.1`
+fn parse_cast(definition: &str) -> Result<VoiceSettings, String> {
+    let mut words = definition.split_whitespace();
+    let voice = words.next().ok_or("needs a voice ID")?;
+    let mut settings = VoiceSettings { voice: voice.to_string(), pitch: 1.0, tempo: 1.0 };
+    for word in words {
+        match word.split_once('=') {
+            Some(("pitch", pitch)) => settings.pitch = PitchArg::from_str(pitch)?.as_factor(),
+            Some(("tempo", tempo)) => settings.tempo = tempo.parse::<f32>().ok().filter(|t| *t > 0.0).ok_or_else(|| format!("invalid tempo '{}'", tempo))?,
+            _ => return Err(format!("unknown setting '{}' (use pitch=X or tempo=X)", word)),
+        }
+    }
+    Ok(settings)
+}
+
+/// Parse a script: cast lines (`NAME = voice ...`) before the first line of dialogue, dialogue lines
+/// (`NAME: text`), continuation lines, blank lines, and `#` comments. Every character who speaks needs
+/// a cast line.
+pub fn parse_script(script: &str) -> Result<Script, String> {
+    let mut parsed = Script::default();
+    for (index, line) in script.lines().enumerate() {
+        let (number, trimmed) = (index + 1, line.trim());
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        // The cast comes first, so later lines can say `x = y` without being taken for cast entries
+        if parsed.lines.is_empty()
+            && let Some((name, definition)) = trimmed.split_once('=')
+            && is_character_name(name.trim())
+        {
+            let settings = parse_cast(definition).map_err(|e| format!("Line {}: cast entry for {} {}", number, name.trim(), e))?;
+            parsed.cast.insert(name.trim().to_uppercase(), settings);
+            continue;
+        }
+        match trimmed.split_once(':') {
+            Some((name, text)) if is_character_name(name.trim()) => {
+                parsed.lines.push(ScriptLine { character: name.trim().to_string(), text: text.trim().to_string(), line_number: number });
+            }
+            _ => match parsed.lines.last_mut() {
+                Some(previous) => {
+                    previous.text.push(' ');
+                    previous.text.push_str(trimmed);
+                }
+                None => return Err(format!("Line {}: expected `NAME: text` or `NAME = voice`", number)),
+            },
+        }
+    }
+    parsed.lines.retain(|line| !line.text.is_empty());
+    if let Some(line) = parsed.lines.iter().find(|line| parsed.settings(&line.character).is_none()) {
+        return Err(format!("Line {}: {} has no voice; add `{} = <voice ID>` to the script", line.line_number, line.character, line.character));
+    }
+    if parsed.lines.is_empty() {
+        return Err("The script has no dialogue lines".to_string());
+    }
+    Ok(parsed)
+}
+
+/// Where a line landed in the rendered track
+#[cfg(feature = "full")]
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SpokenLine {
+    pub character: String,
+    pub voice: String,
+    pub text: String,
+    /// Seconds from the start of the track
+    pub start: f32,
+    pub end: f32,
+}
+
+/// Synthesize every line with its character's voice, `pitch` and `tempo` applying on top of each
+/// character's own, and join them with `gap` of silence between lines (the voices' own leading and
+/// trailing silence is trimmed, so the gap is exact). Voices with other sample rates are resampled to
+/// the first line's. Returns the samples, their sample rate, and where each line landed.
+#[cfg(feature = "full")]
+pub fn synthesize_dialogue(script: &Script, gap: std::time::Duration, pitch: f32, tempo: f32) -> crate::error::Result<(Vec<f32>, u32, Vec<SpokenLine>)> {
+    let limits = crate::limits::limits();
+    limits.check_text(&script.lines.iter().map(|line| line.text.as_str()).collect::<Vec<_>>().join("\n"))?;
+    let mut samples = Vec::new();
+    let mut track_rate = None;
+    let mut spoken = Vec::new();
+    for line in &script.lines {
+        let settings = script.settings(&line.character).ok_or_else(|| format!("{} has no voice", line.character))?;
+        log::info!("[Dialogue] {} ({}): {}", line.character, settings.voice, line.text);
+        let (line_samples, rate) = crate::synthesize_processed(&line.text, &settings.voice, &PitchArg::Value(settings.pitch * pitch), settings.tempo * tempo)?;
+        let track_rate = *track_rate.get_or_insert(rate);
+        let line_samples = if rate == track_rate { line_samples } else { crate::dsp::resample(&line_samples, rate as usize, track_rate as usize) };
+        if !samples.is_empty() {
+            samples.extend(std::iter::repeat_n(0.0, (gap.as_secs_f64() * track_rate as f64) as usize));
+        }
+        let start = samples.len() as f32 / track_rate as f32;
+        samples.extend_from_slice(crate::pauses::trim_silence(&line_samples, track_rate, true, true));
+        limits.check_samples(samples.len(), track_rate)?;
+        spoken.push(SpokenLine {
+            character: line.character.clone(),
+            voice: settings.voice.clone(),
+            text: crate::directives::strip_directives(&line.text),
+            start,
+            end: samples.len() as f32 / track_rate as f32,
+        });
+    }
+    Ok((samples, track_rate.unwrap_or(22050), spoken))
+}
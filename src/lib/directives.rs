@@ -0,0 +1,103 @@
+use crate::PitchArg;
+use std::str::FromStr;
+
+/// Voice settings in effect for part of a text
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoiceSettings {
+    pub voice: String,
+    pub pitch: f32,
+    pub tempo: f32,
+}
+
+/// A run of text spoken with one set of settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectedPart {
+    pub settings: VoiceSettings,
+    pub text: String,
+}
+
+/// An inline directive: `@voice(en_US-ryan-high)`, `@pitch(0.9)` (or a preset like `@pitch(deep)`),
+/// or `@tempo(1.1)`. Each one lasts until the next directive of the same kind.
+#[derive(Debug, Clone, PartialEq)]
+enum Directive {
+    Voice(String),
+    Pitch(f32),
+    Tempo(f32),
+}
+
+/// Find the next directive at or after `from`: its byte range, and the directive or an error for a bad
+/// argument. `@name(...)` with any other name, or not at the start of a word, is ordinary text.
+fn next_directive(text: &str, mut from: usize) -> Option<(usize, usize, Result<Directive, String>)> {
+    while let Some(offset) = text[from..].find('@') {
+        let at = from + offset;
+        from = at + 1;
+        if text[..at].chars().next_back().is_some_and(|c| !c.is_whitespace()) {
+            continue;
+        }
+        let rest = &text[at + 1..];
+        let name_len = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+        let (name, rest) = rest.split_at(name_len);
+        let Some(argument) = rest.strip_prefix('(').and_then(|r| r.find(')').map(|close| &r[..close])) else {
+            continue;
+        };
+        let end = at + 1 + name_len + 1 + argument.len() + 1;
+        let argument = argument.trim();
+        let directive = match name {
+            "voice" if !argument.is_empty() => Ok(Directive::Voice(argument.to_string())),
+            "voice" => Err("@voice() needs a voice ID".to_string()),
+            "pitch" => PitchArg::from_str(argument).map(|p| Directive::Pitch(p.as_factor())),
+            "tempo" => argument.parse::<f32>().ok().filter(|t| *t > 0.0).map(Directive::Tempo).ok_or_else(|| format!("Invalid tempo in @tempo({})", argument)),
+            _ => continue,
+        };
+        return Some((at, end, directive));
+    }
+    None
+}
+
+/// Whether the text has any `@voice`, `@pitch`, or `@tempo` directives
+pub fn has_directives(text: &str) -> bool {
+    next_directive(text, 0).is_some()
+}
+
+/// Split text at its directives into parts, each with the settings in effect, starting from `initial`.
+/// Parts with only whitespace are dropped, and neighbouring parts with the same settings are joined.
+pub fn split_directives(text: &str, initial: &VoiceSettings) -> Result<Vec<DirectedPart>, String> {
+    let mut parts: Vec<DirectedPart> = Vec::new();
+    let mut settings = initial.clone();
+    let mut push = |settings: &VoiceSettings, piece: &str| {
+        if piece.trim().is_empty() {
+            return;
+        }
+        match parts.last_mut() {
+            Some(last) if last.settings == *settings => last.text.push_str(piece),
+            _ => parts.push(DirectedPart { settings: settings.clone(), text: piece.to_string() }),
+        }
+    };
+    let mut cursor = 0;
+    while let Some((start, end, directive)) = next_directive(text, cursor) {
+        push(&settings, &text[cursor..start]);
+        match directive? {
+            Directive::Voice(voice) => settings.voice = voice,
+            Directive::Pitch(pitch) => settings.pitch = pitch,
+            Directive::Tempo(tempo) => settings.tempo = tempo,
+        }
+        cursor = end;
+    }
+    push(&settings, &text[cursor..]);
+    for part in &mut parts {
+        part.text = part.text.trim().to_string();
+    }
+    Ok(parts)
+}
+
+/// The text as spoken, for display and alignment: directives are removed along with the spaces after them
+pub fn strip_directives(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut cursor = 0;
+    while let Some((start, end, _)) = next_directive(text, cursor) {
+        stripped.push_str(&text[cursor..start]);
+        cursor = text.len() - text[end..].trim_start_matches([' ', '\t']).len();
+    }
+    stripped.push_str(&text[cursor..]);
+    stripped
+}
@@ -0,0 +1,269 @@
+//! Native signal processing, so pitch changes work without external tools.
+
+use realfft::num_complex::Complex;
+use realfft::RealFftPlanner;
+use rubato::{FftFixedIn, Resampler};
+use std::f32::consts::PI;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+const FFT_SIZE: usize = 2048;
+/// Synthesis hop: frames overlap by three quarters
+const HOP: usize = FFT_SIZE / 4;
+
+fn hann(n: usize) -> Vec<f32> {
+    (0..n).map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / n as f32).cos()).collect()
+}
+
+/// Wrap a phase to [-π, π]
+fn principal(phase: f32) -> f32 {
+    phase - 2.0 * PI * ((phase + PI) / (2.0 * PI)).floor()
+}
+
+/// Bins louder than their two neighbours on each side: the partials whose phases the other bins follow
+fn peaks(magnitudes: &[f32]) -> Vec<usize> {
+    (0..magnitudes.len())
+        .filter(|&b| {
+            let m = magnitudes[b];
+            m > 1e-6 && (b.saturating_sub(2)..(b + 3).min(magnitudes.len())).all(|n| n == b || magnitudes[n] <= m)
+        })
+        .collect()
+}
+
+/// Stretch `samples` to `factor` times their length without changing their pitch.
+///
+/// A phase vocoder with identity phase locking (Laroche and Dolson): each spectral peak's phase
+/// advances at its measured frequency, and the bins around it keep their phase relative to the peak,
+/// which keeps voices from sounding phasey or distant.
+pub fn time_stretch(samples: &[f32], factor: f32) -> Vec<f32> {
+    let target = (samples.len() as f32 * factor).round() as usize;
+    if samples.is_empty() || target == 0 {
+        return Vec::new();
+    }
+    let window = hann(FFT_SIZE);
+    let mut planner = RealFftPlanner::<f32>::new();
+    let forward = planner.plan_fft_forward(FFT_SIZE);
+    let inverse = planner.plan_fft_inverse(FFT_SIZE);
+    let bins = FFT_SIZE / 2 + 1;
+    // Frames are centred on their hop positions, so the input is read as if padded by half a frame
+    let half = FFT_SIZE / 2;
+    let input_at = |i: isize| if i >= 0 { samples.get(i as usize).copied().unwrap_or(0.0) } else { 0.0 };
+    let analysis_hop = HOP as f32 / factor;
+
+    let frames = target.div_ceil(HOP) + 1;
+    let mut output = vec![0.0f32; frames * HOP + FFT_SIZE];
+    let mut window_sum = vec![0.0f32; output.len()];
+    let mut previous_phase = vec![0.0f32; bins];
+    let mut synthesis_phase = vec![0.0f32; bins];
+    let mut previous_position = 0isize;
+    let mut input = forward.make_input_vec();
+    let mut spectrum = forward.make_output_vec();
+    let mut frame = inverse.make_output_vec();
+    let mut progress = crate::progress::SampleProgress::new(samples.len());
+
+    for k in 0..frames {
+        let position = (k as f32 * analysis_hop).round() as isize;
+        if crate::cancel::is_cancelled() {
+            break;
+        }
+        progress.advance(position as usize);
+        for (i, x) in input.iter_mut().enumerate() {
+            *x = input_at(position + i as isize - half as isize) * window[i];
+        }
+        let _ = forward.process(&mut input, &mut spectrum);
+        let magnitudes: Vec<f32> = spectrum.iter().map(|c| c.norm()).collect();
+        let phases: Vec<f32> = spectrum.iter().map(|c| c.arg()).collect();
+
+        if k == 0 {
+            synthesis_phase.copy_from_slice(&phases);
+        } else {
+            let hop = (position - previous_position).max(1) as f32;
+            let advance = |b: usize| {
+                let expected = 2.0 * PI * b as f32 * hop / FFT_SIZE as f32;
+                let frequency = (expected + principal(phases[b] - previous_phase[b] - expected)) / hop;
+                frequency * HOP as f32
+            };
+            let peaks = peaks(&magnitudes);
+            if peaks.is_empty() {
+                for (b, phase) in synthesis_phase.iter_mut().enumerate() {
+                    *phase += advance(b);
+                }
+            } else {
+                let peak_phase: Vec<f32> = peaks.iter().map(|&p| synthesis_phase[p] + advance(p)).collect();
+                // Every bin follows the nearest peak, boundaries halfway between peaks
+                let mut nearest = 0;
+                for b in 0..bins {
+                    while nearest + 1 < peaks.len() && peaks[nearest + 1].abs_diff(b) < peaks[nearest].abs_diff(b) {
+                        nearest += 1;
+                    }
+                    let p = peaks[nearest];
+                    synthesis_phase[b] = peak_phase[nearest] + phases[b] - phases[p];
+                }
+            }
+        }
+        previous_phase.copy_from_slice(&phases);
+        previous_position = position;
+
+        for (b, c) in spectrum.iter_mut().enumerate() {
+            *c = Complex::from_polar(magnitudes[b], synthesis_phase[b]);
+        }
+        // The inverse transform needs real DC and Nyquist bins
+        spectrum[0].im = 0.0;
+        spectrum[bins - 1].im = 0.0;
+        let _ = inverse.process(&mut spectrum, &mut frame);
+        let start = k * HOP;
+        for i in 0..FFT_SIZE {
+            output[start + i] += frame[i] * window[i] / FFT_SIZE as f32;
+            window_sum[start + i] += window[i] * window[i];
+        }
+    }
+    progress.finish();
+
+    output
+        .iter()
+        .zip(&window_sum)
+        .skip(half)
+        .take(target)
+        .map(|(x, w)| if *w > 1e-3 { x / w } else { 0.0 })
+        .collect()
+}
+
+/// How `--tempo` stretches audio (see [`crate::time_stretch`])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StretchQuality {
+    /// Resample, which is fast but moves the pitch with the tempo
+    #[default]
+    Fast,
+    /// [`wsola_stretch`], which keeps the pitch and has no glitches at chunk edges
+    High,
+}
+
+impl StretchQuality {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StretchQuality::Fast => "fast",
+            StretchQuality::High => "high",
+        }
+    }
+}
+
+impl FromStr for StretchQuality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "fast" => Ok(StretchQuality::Fast),
+            "high" => Ok(StretchQuality::High),
+            _ => Err(format!("Invalid stretch quality '{}': expected fast or high", s)),
+        }
+    }
+}
+
+/// Set from `--stretch-quality`
+static STRETCH_QUALITY: Mutex<StretchQuality> = Mutex::new(StretchQuality::Fast);
+
+pub fn set_stretch_quality(quality: StretchQuality) {
+    *STRETCH_QUALITY.lock().unwrap() = quality;
+}
+
+pub fn stretch_quality() -> StretchQuality {
+    *STRETCH_QUALITY.lock().unwrap()
+}
+
+/// `stretch=high`, for sidecars, or `None` for the default
+pub fn describe_stretch() -> Option<String> {
+    let quality = stretch_quality();
+    (quality != StretchQuality::Fast).then(|| format!("stretch={}", quality.name()))
+}
+
+/// Stretch `samples` to `factor` times their length without changing their pitch, by WSOLA (waveform
+/// similarity overlap-add): 30ms windows of the input are overlap-added at a fixed hop, and each one
+/// is taken from within a few milliseconds of where it nominally falls, at the offset that lines its
+/// waveform up best with the natural continuation of the window before. Unlike the default resampling
+/// in [`crate::time_stretch`] it keeps the pitch, and it works on the waveform itself, so speech keeps its
+/// transients and stays dry.
+pub fn wsola_stretch(samples: &[f32], sample_rate: usize, factor: f32) -> Vec<f32> {
+    let target = (samples.len() as f32 * factor).round() as usize;
+    if samples.is_empty() || target == 0 {
+        return Vec::new();
+    }
+    let frame = (sample_rate * 3 / 100).max(64) & !1;
+    let hop = frame / 2;
+    let tolerance = (frame / 4) as isize;
+    let window = hann(frame);
+    let input_at = |i: isize| if i >= 0 { samples.get(i as usize).copied().unwrap_or(0.0) } else { 0.0 };
+
+    let frames = target.div_ceil(hop) + 1;
+    let mut output = vec![0.0f32; frames * hop + frame];
+    let mut window_sum = vec![0.0f32; output.len()];
+    let mut previous = 0isize;
+    let mut progress = crate::progress::SampleProgress::new(samples.len());
+    for k in 0..frames {
+        if crate::cancel::is_cancelled() {
+            break;
+        }
+        let nominal = (k as f32 * hop as f32 / factor).round() as isize;
+        let position = if k == 0 {
+            0
+        } else {
+            // The window that would follow the previous one in the input, compared over the half that overlaps
+            let natural = previous + hop as isize;
+            let similarity = |candidate: isize| -> f32 {
+                let (mut dot, mut energy) = (0.0f32, 1e-9f32);
+                for i in 0..hop as isize {
+                    let x = input_at(candidate + i);
+                    dot += input_at(natural + i) * x;
+                    energy += x * x;
+                }
+                dot / energy.sqrt()
+            };
+            (-tolerance..=tolerance)
+                .map(|delta| (nominal + delta).max(0))
+                .map(|candidate| (candidate, similarity(candidate)))
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map_or(nominal, |(best, _)| best)
+        };
+        progress.advance(position.max(0) as usize);
+        let start = k * hop;
+        for i in 0..frame {
+            output[start + i] += input_at(position + i as isize) * window[i];
+            window_sum[start + i] += window[i];
+        }
+        previous = position;
+    }
+    progress.finish();
+
+    output.iter().zip(&window_sum).take(target).map(|(x, w)| if *w > 1e-3 { x / w } else { 0.0 }).collect()
+}
+
+/// Resample from `from` Hz to `to` Hz, keeping the audio aligned with the input
+pub fn resample(samples: &[f32], from: usize, to: usize) -> Vec<f32> {
+    let chunk = 1024;
+    let mut resampler = FftFixedIn::<f32>::new(from, to, chunk, 2, 1).expect("Failed to create resampler");
+    let delay = resampler.output_delay();
+    let wanted = (samples.len() as f64 * to as f64 / from as f64).round() as usize;
+    let mut output = Vec::with_capacity(wanted + delay + chunk);
+    let mut pos = 0;
+    while output.len() < wanted + delay {
+        let mut input: Vec<f32> = samples.iter().skip(pos).take(chunk).copied().collect();
+        input.resize(chunk, 0.0);
+        output.extend_from_slice(&resampler.process(&[input], None).expect("Resample failed")[0]);
+        pos += chunk;
+    }
+    output.drain(..delay);
+    output.truncate(wanted);
+    output
+}
+
+/// Shift the pitch of `samples` by `factor` (2.0 = an octave up) without changing their length:
+/// stretch by `factor` with [`time_stretch`], then resample back to the original length.
+pub fn pitch_shift(samples: &[f32], sample_rate: usize, factor: f32) -> Vec<f32> {
+    if (factor - 1.0).abs() < 0.01 || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let stretched = time_stretch(samples, factor);
+    let rate = (sample_rate as f32 / factor).round() as usize;
+    let mut shifted = resample(&stretched, sample_rate, rate.max(1));
+    shifted.resize(samples.len(), 0.0);
+    shifted
+}
@@ -0,0 +1,207 @@
+//! Emoji and pictographic symbols in input text, which phonemizers otherwise read as garbage. They're
+//! stripped by default; `--emoji speak` reads the common ones by name ("🙂" → "smiley face") and
+//! `--emoji ignore` leaves them for the phonemizer.
+
+use crate::normalize::{Language, Locale};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmojiMode {
+    /// Remove emoji and symbols
+    #[default]
+    Strip,
+    /// Read emoji and symbols by name, in English voices; those without a name, and all of them in
+    /// other languages, are removed
+    Speak,
+    /// Leave them in the text
+    Ignore,
+}
+
+impl EmojiMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmojiMode::Strip => "strip",
+            EmojiMode::Speak => "speak",
+            EmojiMode::Ignore => "ignore",
+        }
+    }
+}
+
+impl FromStr for EmojiMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "strip" => Ok(EmojiMode::Strip),
+            "speak" => Ok(EmojiMode::Speak),
+            "ignore" => Ok(EmojiMode::Ignore),
+            _ => Err(format!("Invalid emoji mode '{}': expected strip, speak, or ignore", s)),
+        }
+    }
+}
+
+/// Set from `--emoji`
+static MODE: Mutex<EmojiMode> = Mutex::new(EmojiMode::Strip);
+
+pub fn set_mode(mode: EmojiMode) {
+    *MODE.lock().unwrap() = mode;
+}
+
+pub fn mode() -> EmojiMode {
+    *MODE.lock().unwrap()
+}
+
+/// `emoji=speak`, for sidecars, or `None` for the default
+pub fn describe() -> Option<String> {
+    let mode = mode();
+    (mode != EmojiMode::Strip).then(|| format!("emoji={}", mode.name()))
+}
+
+/// Names read by [`EmojiMode::Speak`]
+const NAMES: &[(char, &str)] = &[
+    ('🙂', "smiley face"),
+    ('😀', "grinning face"),
+    ('😃', "grinning face"),
+    ('😄', "grinning face"),
+    ('😁', "beaming face"),
+    ('😆', "laughing face"),
+    ('😅', "nervous laugh"),
+    ('😂', "face with tears of joy"),
+    ('🤣', "rolling on the floor laughing"),
+    ('😊', "smiling face"),
+    ('😉', "winking face"),
+    ('😍', "heart eyes"),
+    ('😘', "blowing a kiss"),
+    ('😎', "smiling face with sunglasses"),
+    ('🤔', "thinking face"),
+    ('🙄', "eye roll"),
+    ('😐', "neutral face"),
+    ('😴', "sleeping face"),
+    ('😢', "crying face"),
+    ('😭', "sobbing face"),
+    ('😱', "screaming face"),
+    ('😡', "angry face"),
+    ('😠', "angry face"),
+    ('🙃', "upside-down face"),
+    ('🤯', "mind blown"),
+    ('🥳', "party face"),
+    ('🤷', "shrug"),
+    ('🤦', "facepalm"),
+    ('👍', "thumbs up"),
+    ('👎', "thumbs down"),
+    ('👏', "clapping hands"),
+    ('🙌', "raised hands"),
+    ('🙏', "folded hands"),
+    ('👋', "waving hand"),
+    ('💪', "flexed biceps"),
+    ('👀', "eyes"),
+    ('💀', "skull"),
+    ('❤', "red heart"),
+    ('💔', "broken heart"),
+    ('💯', "hundred points"),
+    ('🔥', "fire"),
+    ('✨', "sparkles"),
+    ('⭐', "star"),
+    ('🎉', "party popper"),
+    ('🎂', "birthday cake"),
+    ('🚀', "rocket"),
+    ('💡', "light bulb"),
+    ('⚠', "warning"),
+    ('✅', "check mark"),
+    ('✔', "check mark"),
+    ('❌', "cross mark"),
+    ('☕', "coffee"),
+    ('🍕', "pizza"),
+    ('🍺', "beer"),
+    ('🐶', "dog"),
+    ('🐱', "cat"),
+    ('☀', "sun"),
+    ('🌧', "rain"),
+    ('📞', "telephone"),
+    ('📧', "email"),
+    ('➡', "right arrow"),
+    ('→', "right arrow"),
+    ('←', "left arrow"),
+    ('©', "copyright"),
+    ('®', "registered"),
+    ('™', "trademark"),
+];
+
+/// Whether `c` is an emoji or pictographic symbol
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32,
+        0x1F000..=0x1FAFF // emoticons, pictographs, transport, flags, supplemental symbols
+        | 0x2600..=0x27BF // miscellaneous symbols and dingbats
+        | 0x2B00..=0x2BFF // stars and arrows
+        | 0x2190..=0x21FF // arrows
+        | 0x2300..=0x23FF // watches, hourglasses, media controls
+        | 0x00A9 | 0x00AE | 0x2122 // ©, ®, ™
+    )
+}
+
+/// Characters that only modify the emoji before them: variation selectors, skin tones, keycaps, and tags
+fn is_modifier(c: char) -> bool {
+    matches!(c as u32, 0xFE0E | 0xFE0F | 0x1F3FB..=0x1F3FF | 0x20E3 | 0xE0020..=0xE007F)
+}
+
+/// Regional indicator letters, two of which make a flag
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c as u32, 0x1F1E6..=0x1F1FF)
+}
+
+/// Handle the emoji in `text` with the current [`mode`]
+pub fn apply(text: &str, locale: Option<Locale>) -> String {
+    apply_with(text, locale, mode())
+}
+
+/// Strip or name the emoji in `text`. An emoji with its modifiers, a joined sequence (👨‍👩‍👧), or a flag
+/// counts as one, read by its first emoji's name; a run of the same emoji is read once.
+pub fn apply_with(text: &str, locale: Option<Locale>, mode: EmojiMode) -> String {
+    if mode == EmojiMode::Ignore || !text.chars().any(is_emoji) {
+        return text.to_string();
+    }
+    let speak = mode == EmojiMode::Speak && locale.is_some_and(|l| l.language == Language::English);
+    let mut output = String::with_capacity(text.len());
+    let mut last_name: Option<&str> = None;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if !is_emoji(c) {
+            if !c.is_whitespace() {
+                last_name = None;
+            }
+            output.push(c);
+            continue;
+        }
+        // The rest of the sequence
+        let flag = is_regional_indicator(c) && chars.next_if(|&next| is_regional_indicator(next)).is_some();
+        loop {
+            while chars.next_if(|&next| is_modifier(next)).is_some() {}
+            if chars.next_if_eq(&'\u{200D}').is_none() {
+                break;
+            }
+            chars.next_if(|&next| is_emoji(next));
+        }
+        let name = if flag { Some("flag") } else { NAMES.iter().find(|(emoji, _)| *emoji == c).map(|(_, name)| *name) };
+        match name.filter(|_| speak) {
+            Some(name) if last_name != Some(name) => {
+                if output.ends_with(|c: char| !c.is_whitespace()) {
+                    output.push(' ');
+                }
+                output.push_str(name);
+                if chars.peek().is_some_and(|next| next.is_alphanumeric()) {
+                    output.push(' ');
+                }
+                last_name = Some(name);
+            }
+            // Removed, or a repeat of the emoji just read
+            _ => {
+                // Don't leave a double space, or a space before punctuation, where it was
+                if output.ends_with(' ') && chars.peek().is_none_or(|next| next.is_whitespace() || next.is_ascii_punctuation()) {
+                    output.pop();
+                }
+            }
+        }
+    }
+    output
+}
@@ -0,0 +1,183 @@
+//! Inline per-word emphasis and pitch/volume markup. `*really*` stresses a word (a little higher and
+//! louder), and `{pitch=1.3}these words{/pitch}` or `{volume=0.6}these{/volume}` change the words between
+//! the tags; tags nest, and an unclosed one lasts to the end of the text. The text is synthesized without
+//! the markup, then each marked word's stretch of audio, found from the word timings, is processed in place.
+
+use crate::{PitchArg, WordTiming};
+use std::str::FromStr;
+
+/// Pitch and volume multipliers for one word
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WordStyle {
+    pub pitch: f32,
+    pub volume: f32,
+}
+
+impl WordStyle {
+    pub const NONE: WordStyle = WordStyle { pitch: 1.0, volume: 1.0 };
+
+    pub fn is_none(&self) -> bool {
+        (self.pitch - 1.0).abs() < 0.01 && (self.volume - 1.0).abs() < 0.01
+    }
+}
+
+/// What `*word*` does: a little higher and about 3 dB louder
+pub const EMPHASIS: WordStyle = WordStyle { pitch: 1.12, volume: 1.4 };
+
+/// Text with its markup removed, and the style of each of its whitespace-separated words
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmphasizedText {
+    pub text: String,
+    pub styles: Vec<WordStyle>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tag {
+    Pitch(f32),
+    Volume(f32),
+    EndPitch,
+    EndVolume,
+}
+
+/// The tag starting at `chars[open]` (a `{`) and the index just past it, or `None` if it isn't one,
+/// e.g. a respelling such as `Hermione{her-MY-oh-nee}`
+fn tag_at(chars: &[char], open: usize) -> Option<(usize, Result<Tag, String>)> {
+    let close = open + chars[open..].iter().position(|&c| c == '}')?;
+    let inner: String = chars[open + 1..close].iter().collect();
+    let tag = match inner.trim() {
+        "/pitch" => Ok(Tag::EndPitch),
+        "/volume" => Ok(Tag::EndVolume),
+        inner => match inner.split_once('=').map(|(name, value)| (name.trim(), value.trim())) {
+            Some(("pitch", value)) => PitchArg::from_str(value).map(|p| p.as_factor()).ok().filter(|f| *f > 0.0).map(Tag::Pitch).ok_or_else(|| format!("Invalid pitch in {{{}}}", inner)),
+            Some(("volume", value)) => value.parse::<f32>().ok().filter(|v| (0.0..=4.0).contains(v)).map(Tag::Volume).ok_or_else(|| format!("Invalid volume in {{{}}}: use 0 to 4", inner)),
+            _ => return None,
+        },
+    };
+    Some((close + 1, tag))
+}
+
+/// Index of the `*` that closes emphasis opened before `from`: one after a non-space, not followed by
+/// a letter or digit
+fn closing_star(chars: &[char], from: usize) -> Option<usize> {
+    (from..chars.len()).find(|&i| {
+        chars[i] == '*' && chars[i - 1] != '*' && !chars[i - 1].is_whitespace() && {
+            let run = chars[i..].iter().take_while(|&&c| c == '*').count();
+            chars.get(i + run).is_none_or(|next| !next.is_alphanumeric())
+        }
+    })
+}
+
+/// Remove the markup from `text`, noting each word's style. A `*` that doesn't pair up, and a closing tag
+/// without an opening one, are left as they are and dropped respectively.
+pub fn parse_emphasis(text: &str) -> Result<EmphasizedText, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut output = String::with_capacity(text.len());
+    let mut styles = Vec::new();
+    let (mut pitches, mut volumes): (Vec<f32>, Vec<f32>) = (Vec::new(), Vec::new());
+    // Where the current `*...*` ends
+    let mut emphasis_end: Option<usize> = None;
+    let mut word_start = true;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '*' {
+            let run = chars[i..].iter().take_while(|&&c| c == '*').count();
+            if emphasis_end == Some(i) {
+                emphasis_end = None;
+                i += run;
+                continue;
+            }
+            let opens = i.checked_sub(1).is_none_or(|p| chars[p].is_whitespace() || matches!(chars[p], '(' | '"' | '\'' | '“'));
+            if emphasis_end.is_none()
+                && opens
+                && chars.get(i + run).is_some_and(|next| !next.is_whitespace())
+                && let Some(close) = closing_star(&chars, i + run + 1)
+            {
+                emphasis_end = Some(close);
+                i += run;
+                continue;
+            }
+        }
+        if c == '{'
+            && let Some((end, tag)) = tag_at(&chars, i)
+        {
+            match tag? {
+                Tag::Pitch(factor) => pitches.push(factor),
+                Tag::Volume(gain) => volumes.push(gain),
+                Tag::EndPitch => {
+                    pitches.pop();
+                }
+                Tag::EndVolume => {
+                    volumes.pop();
+                }
+            }
+            i = end;
+            continue;
+        }
+        if c.is_whitespace() {
+            word_start = true;
+        } else if word_start {
+            word_start = false;
+            let emphasis = if emphasis_end.is_some() { EMPHASIS } else { WordStyle::NONE };
+            styles.push(WordStyle { pitch: emphasis.pitch * pitches.iter().product::<f32>(), volume: emphasis.volume * volumes.iter().product::<f32>() });
+        }
+        output.push(c);
+        i += 1;
+    }
+    Ok(EmphasizedText { text: output, styles })
+}
+
+/// Whether `text` has any emphasis or pitch/volume markup (or a bad tag, which synthesis reports)
+pub fn has_emphasis(text: &str) -> bool {
+    parse_emphasis(text).map_or(true, |marked| marked.text != text)
+}
+
+/// The text without its markup, for lipsync and subtitles
+pub fn strip_emphasis(text: &str) -> String {
+    parse_emphasis(text).map_or_else(|_| text.to_string(), |marked| marked.text)
+}
+
+/// The marked text as it's spoken, with `speak` applied (rules and normalization), and a style for each
+/// of its words: a written word that `speak` turns into several, such as `1,234`, gives each of them its
+/// style. Fails when the words spoken from the whole text aren't those spoken word by word, since the
+/// styles would then land on the wrong words.
+pub fn spoken_styles(marked: &EmphasizedText, speak: impl Fn(&str) -> String) -> Result<(String, Vec<WordStyle>), String> {
+    let spoken = speak(&marked.text);
+    let styles: Vec<WordStyle> = marked.text.split_whitespace().zip(&marked.styles).flat_map(|(word, style)| std::iter::repeat_n(*style, speak(word).split_whitespace().count())).collect();
+    if styles.len() != spoken.split_whitespace().count() {
+        return Err(format!("Can't place the emphasis markup in \"{}\": it's spoken as \"{}\"; write the marked words out as they're spoken", marked.text, spoken));
+    }
+    Ok((spoken, styles))
+}
+
+/// Apply each word's style to its stretch of `samples`, with `timings` holding one entry per word of the
+/// text. The changed audio is crossfaded in over 10ms at each edge, so it doesn't click.
+pub fn apply_styles(samples: &mut [f32], sample_rate: u32, timings: &[WordTiming], styles: &[WordStyle]) {
+    let rate = sample_rate as f32;
+    let fade = (sample_rate / 100) as usize;
+    for (timing, style) in timings.iter().zip(styles) {
+        if style.is_none() {
+            continue;
+        }
+        let start = ((timing.start * rate) as usize).saturating_sub(fade).min(samples.len());
+        let end = ((timing.end * rate) as usize + fade).min(samples.len());
+        if end <= start {
+            continue;
+        }
+        let original = &samples[start..end];
+        let mut styled = crate::true_pitch_shift(original, sample_rate as usize, style.pitch);
+        styled.resize(original.len(), 0.0);
+        let len = styled.len();
+        let mixed: Vec<f32> = original
+            .iter()
+            .zip(styled)
+            .enumerate()
+            .map(|(k, (dry, wet))| {
+                let edge = k.min(len - 1 - k);
+                let mix = if fade == 0 { 1.0 } else { (edge as f32 / fade as f32).min(1.0) };
+                dry * (1.0 - mix) + wet * style.volume * mix
+            })
+            .collect();
+        samples[start..end].copy_from_slice(&mixed);
+    }
+}
@@ -0,0 +1,454 @@
+//! Audio containers for exports: WAV as rendered, FLAC encoded natively, Ogg Opus encoded with
+//! libopus straight from the samples, and MP3 and Ogg Vorbis encoded with ffmpeg (there are no
+//! pure-Rust encoders for those codecs).
+
+use crate::tags::AudioTags;
+use std::path::Path;
+use std::process::Command;
+
+/// Container and codec of an exported file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "full", derive(clap::ValueEnum))]
+pub enum AudioFormat {
+    /// 16-bit PCM WAV
+    Wav,
+    /// Lossless FLAC, encoded natively
+    Flac,
+    /// MP3 (LAME, through ffmpeg)
+    Mp3,
+    /// Ogg Vorbis (through ffmpeg)
+    Ogg,
+    /// Ogg Opus (libopus), the usual voice format for Unity and Godot
+    Opus,
+}
+
+impl AudioFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "wav",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Ogg => "ogg",
+            AudioFormat::Opus => "opus",
+        }
+    }
+
+    /// MIME type of the format, e.g. for an HTTP response
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "audio/wav",
+            AudioFormat::Flac => "audio/flac",
+            AudioFormat::Mp3 => "audio/mpeg",
+            AudioFormat::Ogg | AudioFormat::Opus => "audio/ogg",
+        }
+    }
+
+    /// The format named by an extension such as `mp3` (any case)
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        let extension = extension.to_lowercase();
+        [AudioFormat::Wav, AudioFormat::Flac, AudioFormat::Mp3, AudioFormat::Ogg, AudioFormat::Opus].into_iter().find(|f| f.extension() == extension)
+    }
+
+    /// The format a file name asks for, from its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::from_extension(path.extension()?.to_str()?)
+    }
+}
+
+/// Default quality for lossy formats, on the 0-10 scale of [`EncodeSettings::quality`]
+pub const DEFAULT_QUALITY: f32 = 5.0;
+/// Opus bitrate when none is given: clear speech at a fraction of Vorbis's size
+pub const DEFAULT_OPUS_KBPS: u32 = 32;
+/// Opus frame duration when none is given
+pub const DEFAULT_OPUS_FRAME_MS: f32 = 20.0;
+/// The frame durations Opus can encode, in milliseconds
+pub const OPUS_FRAME_SIZES_MS: [f32; 6] = [2.5, 5.0, 10.0, 20.0, 40.0, 60.0];
+
+/// Parse an Opus frame duration in milliseconds, e.g. for `--frame-size`
+pub fn parse_opus_frame_ms(value: &str) -> Result<f32, String> {
+    match value.trim_end_matches("ms").parse::<f32>() {
+        Ok(ms) if OPUS_FRAME_SIZES_MS.contains(&ms) => Ok(ms),
+        _ => Err(format!("Invalid Opus frame size '{}' (expected 2.5, 5, 10, 20, 40, or 60 ms)", value)),
+    }
+}
+
+/// How exported audio is encoded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EncodeSettings {
+    pub format: AudioFormat,
+    /// Constant bitrate in kbps for MP3 and Ogg; variable bitrate at `quality` when unset
+    pub bitrate_kbps: Option<u32>,
+    /// Variable-bitrate quality for MP3 and Ogg, 0 (smallest) to 10 (best)
+    pub quality: f32,
+    /// Opus frame duration in milliseconds, one of [`OPUS_FRAME_SIZES_MS`]; longer frames compress
+    /// better, shorter ones cut latency when streaming
+    pub opus_frame_ms: f32,
+}
+
+impl Default for EncodeSettings {
+    fn default() -> Self {
+        EncodeSettings { format: AudioFormat::Wav, bitrate_kbps: None, quality: DEFAULT_QUALITY, opus_frame_ms: DEFAULT_OPUS_FRAME_MS }
+    }
+}
+
+impl EncodeSettings {
+    /// Short description for sidecar effects, e.g. `mp3 128k`, `ogg q5`, or `opus 32k 20ms`
+    pub fn describe(&self) -> String {
+        match (self.format, self.bitrate_kbps) {
+            (AudioFormat::Wav | AudioFormat::Flac, _) => self.format.extension().to_string(),
+            (AudioFormat::Opus, kbps) => format!("opus {}k {}ms", kbps.unwrap_or(DEFAULT_OPUS_KBPS), self.opus_frame_ms),
+            (format, Some(kbps)) => format!("{} {}k", format.extension(), kbps),
+            (format, None) => format!("{} q{}", format.extension(), self.quality),
+        }
+    }
+
+    /// ffmpeg codec arguments for the lossy formats
+    fn ffmpeg_codec_args(&self) -> Vec<String> {
+        let codec = match self.format {
+            AudioFormat::Mp3 => "libmp3lame",
+            _ => "libvorbis",
+        };
+        let rate = match self.bitrate_kbps {
+            Some(kbps) => vec!["-b:a".to_string(), format!("{}k", kbps)],
+            // LAME's VBR scale runs the other way: V0 is the best, V9 the smallest
+            None if self.format == AudioFormat::Mp3 => vec!["-q:a".to_string(), format!("{}", ((10.0 - self.quality.clamp(0.0, 10.0)) * 0.9).round())],
+            None => vec!["-q:a".to_string(), format!("{}", self.quality.clamp(0.0, 10.0))],
+        };
+        [vec!["-c:a".to_string(), codec.to_string()], rate].concat()
+    }
+}
+
+/// Encode a rendered WAV into `output` in the settings' format, with `tags` embedded
+pub fn encode_wav(wav_path: &Path, output: &Path, settings: &EncodeSettings, tags: &AudioTags) -> Result<(), Box<dyn std::error::Error>> {
+    match settings.format {
+        AudioFormat::Wav => {
+            if wav_path != output {
+                std::fs::copy(wav_path, output)?;
+            }
+            Ok(())
+        }
+        AudioFormat::Flac => {
+            let reader = hound::WavReader::open(wav_path)?;
+            let spec = reader.spec();
+            if !(1..=2).contains(&spec.channels) || spec.bits_per_sample != 16 {
+                return Err(format!("{} is not 16-bit mono or stereo", wav_path.display()).into());
+            }
+            let samples = reader.into_samples::<i16>().collect::<Result<Vec<i16>, _>>()?;
+            std::fs::write(output, encode_flac_interleaved(&samples, spec.channels, spec.sample_rate, tags))?;
+            Ok(())
+        }
+        #[cfg(feature = "full")]
+        AudioFormat::Opus => {
+            let reader = hound::WavReader::open(wav_path)?;
+            let spec = reader.spec();
+            let samples = reader.into_samples::<i16>().map(|s| s.map(|s| s as f32 / 32767.0)).collect::<Result<Vec<f32>, _>>()?;
+            std::fs::write(output, crate::opus::encode_opus(&samples, spec.channels, spec.sample_rate, settings, tags)?)?;
+            Ok(())
+        }
+        #[cfg(not(feature = "full"))]
+        AudioFormat::Opus => Err("Opus export needs the `full` feature".into()),
+        AudioFormat::Mp3 | AudioFormat::Ogg => {
+            if Command::new("ffmpeg").arg("-version").output().is_err() {
+                return Err(crate::Error::tool_missing("ffmpeg", format!("Install ffmpeg to export {}, or use --format wav or flac.", settings.format.extension())).into());
+            }
+            let result = Command::new("ffmpeg")
+                .arg("-y")
+                .args(["-loglevel", "error"])
+                .arg("-i")
+                .arg(wav_path)
+                .args(settings.ffmpeg_codec_args())
+                .args(tags.ffmpeg_args())
+                .args(crate::nice::thread_args("-threads"))
+                .arg(output)
+                .output()?;
+            if !result.status.success() {
+                return Err(format!("ffmpeg failed: {}", String::from_utf8_lossy(&result.stderr)).into());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Samples per FLAC frame
+const FLAC_BLOCK_SIZE: usize = 4096;
+/// Largest Rice parameter written; 15 is the escape code
+const MAX_RICE_PARAMETER: u32 = 14;
+const MAX_PARTITION_ORDER: u32 = 6;
+
+/// Big-endian bit packer
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        BitWriter { bytes: Vec::new(), acc: 0, bits: 0 }
+    }
+
+    /// Write the low `n` bits of `value` (n <= 32)
+    fn write(&mut self, value: u64, n: u32) {
+        if n == 0 {
+            return;
+        }
+        self.acc = (self.acc << n) | (value & ((1u64 << n) - 1));
+        self.bits += n;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.bytes.push((self.acc >> self.bits) as u8);
+        }
+    }
+
+    fn write_signed(&mut self, value: i32, n: u32) {
+        self.write(value as u32 as u64, n);
+    }
+
+    fn write_zeros(&mut self, mut n: u32) {
+        while n > 0 {
+            let chunk = n.min(32);
+            self.write(0, chunk);
+            n -= chunk;
+        }
+    }
+
+    /// Zero-pad to a byte boundary
+    fn align(&mut self) {
+        if self.bits > 0 {
+            self.write(0, 8 - self.bits);
+        }
+    }
+}
+
+fn crc8(bytes: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &byte in bytes {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in bytes {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// Frame number in FLAC's UTF-8-like variable-length coding
+fn utf8_number(n: u64) -> Vec<u8> {
+    if n < 0x80 {
+        return vec![n as u8];
+    }
+    let count = match n {
+        0..0x800 => 2,
+        0x800..0x10000 => 3,
+        0x10000..0x200000 => 4,
+        0x200000..0x4000000 => 5,
+        0x4000000..0x80000000 => 6,
+        _ => 7,
+    };
+    let mut bytes = vec![((0xFF00u16 >> count) as u8) | (n >> (6 * (count - 1))) as u8];
+    for i in (0..count - 1).rev() {
+        bytes.push(0x80 | ((n >> (6 * i)) & 0x3F) as u8);
+    }
+    bytes
+}
+
+/// Sample rate code for the frame header, or 0 to take it from STREAMINFO
+fn sample_rate_code(sample_rate: u32) -> u8 {
+    match sample_rate {
+        88200 => 0b0001,
+        176400 => 0b0010,
+        192000 => 0b0011,
+        8000 => 0b0100,
+        16000 => 0b0101,
+        22050 => 0b0110,
+        24000 => 0b0111,
+        32000 => 0b1000,
+        44100 => 0b1001,
+        48000 => 0b1010,
+        96000 => 0b1011,
+        _ => 0,
+    }
+}
+
+/// Residual of FLAC's fixed polynomial predictor of `order` (0-4), from sample `order` on
+fn fixed_residual(samples: &[i32], order: usize) -> Vec<i32> {
+    (order..samples.len())
+        .map(|i| {
+            let s = |k: usize| samples[i - k] as i64;
+            let prediction = match order {
+                0 => 0,
+                1 => s(1),
+                2 => 2 * s(1) - s(2),
+                3 => 3 * s(1) - 3 * s(2) + s(3),
+                _ => 4 * s(1) - 6 * s(2) + 4 * s(3) - s(4),
+            };
+            (s(0) - prediction) as i32
+        })
+        .collect()
+}
+
+fn zigzag(r: i32) -> u32 {
+    ((r << 1) ^ (r >> 31)) as u32
+}
+
+/// Best Rice parameter for a partition and its cost in bits (excluding the parameter itself)
+fn rice_parameter(residual: &[i32]) -> (u32, u64) {
+    (0..=MAX_RICE_PARAMETER)
+        .map(|k| (k, residual.iter().map(|&r| (zigzag(r) >> k) as u64 + 1 + k as u64).sum::<u64>()))
+        .min_by_key(|&(_, bits)| bits)
+        .unwrap_or((0, 0))
+}
+
+/// Partitions of a block's residual: sample ranges within `residual` for a partition order
+fn partitions(block_size: usize, predictor_order: usize, partition_order: u32) -> Vec<std::ops::Range<usize>> {
+    let size = block_size >> partition_order;
+    (0..1usize << partition_order)
+        .map(|p| {
+            let start = (p * size).saturating_sub(predictor_order);
+            start..(p + 1) * size - predictor_order
+        })
+        .collect()
+}
+
+/// The cheapest partition order and each partition's Rice parameter, with the total cost in bits
+fn plan_residual(residual: &[i32], block_size: usize, predictor_order: usize) -> (u32, Vec<u32>, u64) {
+    let mut best: Option<(u32, Vec<u32>, u64)> = None;
+    for order in 0..=MAX_PARTITION_ORDER {
+        if !block_size.is_multiple_of(1 << order) || (block_size >> order) <= predictor_order {
+            break;
+        }
+        let mut parameters = Vec::new();
+        let mut bits = 6u64;
+        for range in partitions(block_size, predictor_order, order) {
+            let (k, cost) = rice_parameter(&residual[range]);
+            parameters.push(k);
+            bits += 4 + cost;
+        }
+        if best.as_ref().is_none_or(|(_, _, b)| bits < *b) {
+            best = Some((order, parameters, bits));
+        }
+    }
+    best.unwrap_or((0, vec![0], u64::MAX))
+}
+
+/// Write one block as a subframe: constant, fixed-predictor, or verbatim, whichever is smallest
+fn write_subframe(out: &mut BitWriter, block: &[i32]) {
+    const BITS: u32 = 16;
+    if block.iter().all(|&s| s == block[0]) {
+        out.write(0, 8);
+        out.write_signed(block[0], BITS);
+        return;
+    }
+    let max_order = 4.min(block.len() - 1);
+    let (order, residual) = (0..=max_order)
+        .map(|order| (order, fixed_residual(block, order)))
+        .min_by_key(|(_, residual)| residual.iter().map(|r| r.unsigned_abs() as u64).sum::<u64>())
+        .unwrap();
+    let (partition_order, parameters, bits) = plan_residual(&residual, block.len(), order);
+    if bits.saturating_add(order as u64 * BITS as u64) >= block.len() as u64 * BITS as u64 {
+        out.write(0b0000_0010, 8);
+        for &s in block {
+            out.write_signed(s, BITS);
+        }
+        return;
+    }
+    out.write(0b0001_0000 | ((order as u64) << 1), 8);
+    for &s in &block[..order] {
+        out.write_signed(s, BITS);
+    }
+    out.write(0, 2);
+    out.write(partition_order as u64, 4);
+    for (range, k) in partitions(block.len(), order, partition_order).into_iter().zip(parameters) {
+        out.write(k as u64, 4);
+        for &r in &residual[range] {
+            let u = zigzag(r);
+            out.write_zeros(u >> k);
+            out.write(1, 1);
+            out.write(u as u64, k);
+        }
+    }
+}
+
+fn metadata_block(out: &mut Vec<u8>, kind: u8, last: bool, data: &[u8]) {
+    out.push(kind | if last { 0x80 } else { 0 });
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+    out.extend_from_slice(data);
+}
+
+/// Encode 16-bit mono samples as a FLAC file, with `tags` as Vorbis comments
+pub fn encode_flac(samples: &[i16], sample_rate: u32, tags: &AudioTags) -> Vec<u8> {
+    encode_flac_interleaved(samples, 1, sample_rate, tags)
+}
+
+/// Encode interleaved 16-bit samples of 1 or 2 channels as a FLAC file; channels are coded independently
+pub fn encode_flac_interleaved(samples: &[i16], channels: u16, sample_rate: u32, tags: &AudioTags) -> Vec<u8> {
+    let channels = channels.clamp(1, 2) as usize;
+    let channel_samples: Vec<Vec<i32>> = (0..channels).map(|c| samples.iter().skip(c).step_by(channels).map(|&s| s as i32).collect()).collect();
+    let frame_count = channel_samples[0].len();
+    let mut frames = Vec::new();
+    let (mut min_frame, mut max_frame) = (u32::MAX, 0u32);
+    for (number, start) in (0..frame_count).step_by(FLAC_BLOCK_SIZE).enumerate() {
+        let end = (start + FLAC_BLOCK_SIZE).min(frame_count);
+        // Channel assignment 0 or 1 (independent mono or left/right), then 16 bits per sample
+        let mut header = vec![0xFF, 0xF8, 0b0111_0000 | sample_rate_code(sample_rate), ((channels as u8 - 1) << 4) | 0b0000_1000];
+        header.extend(utf8_number(number as u64));
+        header.extend_from_slice(&((end - start - 1) as u16).to_be_bytes());
+        header.push(crc8(&header));
+        let mut frame = BitWriter::new();
+        frame.bytes = header;
+        for channel in &channel_samples {
+            write_subframe(&mut frame, &channel[start..end]);
+        }
+        frame.align();
+        let crc = crc16(&frame.bytes);
+        frame.bytes.extend_from_slice(&crc.to_be_bytes());
+        min_frame = min_frame.min(frame.bytes.len() as u32);
+        max_frame = max_frame.max(frame.bytes.len() as u32);
+        frames.extend(frame.bytes);
+    }
+
+    let mut info = BitWriter::new();
+    let block_size = FLAC_BLOCK_SIZE.min(frame_count.max(16)) as u64;
+    info.write(block_size, 16);
+    info.write(block_size, 16);
+    info.write(if max_frame == 0 { 0 } else { min_frame as u64 }, 24);
+    info.write(max_frame as u64, 24);
+    info.write(sample_rate as u64, 20);
+    info.write(channels as u64 - 1, 3);
+    info.write(15, 5); // 16 bits per sample
+    info.write((frame_count as u64) >> 32, 4);
+    info.write(frame_count as u64 & 0xFFFF_FFFF, 32);
+    // No MD5 signature: all zeros means "not computed"
+    info.write_zeros(128);
+
+    let mut out = b"fLaC".to_vec();
+    metadata_block(&mut out, 0, false, &info.bytes);
+    metadata_block(&mut out, 4, true, &vorbis_comments(tags));
+    out.extend(frames);
+    out
+}
+
+/// `tags` as a Vorbis comment block, shared by FLAC and Opus: the vendor string, then `KEY=value` fields
+pub(crate) fn vorbis_comments(tags: &AudioTags) -> Vec<u8> {
+    let mut comments = Vec::new();
+    let vendor = AudioTags::software();
+    let fields: Vec<String> = tags.as_key_values().into_iter().filter(|(key, _)| key != "encoder").map(|(key, value)| format!("{}={}", key.to_uppercase(), value)).collect();
+    comments.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    comments.extend_from_slice(vendor.as_bytes());
+    comments.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+    for field in &fields {
+        comments.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        comments.extend_from_slice(field.as_bytes());
+    }
+    comments
+}
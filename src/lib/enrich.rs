@@ -0,0 +1,117 @@
+use serde_json::{json, Map, Value};
+
+/// First of `keys` present on a word entry, as a number of seconds; `*_ms` keys are milliseconds
+fn seconds(entry: &Value, keys: &[&str]) -> Option<f64> {
+    keys.iter().find_map(|key| {
+        let value = entry.get(*key)?.as_f64()?;
+        Some(if key.ends_with("_ms") { value / 1000.0 } else { value })
+    })
+}
+
+/// One word entry in WhisperX's shape (`word`, `start`, `end`, `score`), from any entry with a word
+/// (`word`, `text`, or `token`) and optionally its times and confidence under common names
+fn import_word(entry: &Value) -> Option<Value> {
+    let word = ["word", "text", "token"].iter().find_map(|key| entry.get(*key)?.as_str())?.trim();
+    if word.is_empty() {
+        return None;
+    }
+    let mut imported = Map::new();
+    imported.insert("word".to_string(), Value::from(word));
+    if let Some(start) = seconds(entry, &["start", "start_time", "begin", "start_ms"]) {
+        imported.insert("start".to_string(), Value::from(start));
+    }
+    if let Some(end) = seconds(entry, &["end", "end_time", "stop", "end_ms"]) {
+        imported.insert("end".to_string(), Value::from(end));
+    }
+    if let Some(score) = ["score", "confidence", "probability"].iter().find_map(|key| entry.get(*key)?.as_f64()) {
+        imported.insert("score".to_string(), Value::from(score));
+    }
+    Some(Value::Object(imported))
+}
+
+fn import_words(entries: &[Value]) -> Vec<Value> {
+    entries.iter().filter_map(import_word).collect()
+}
+
+/// A segment in WhisperX's shape around `words`, with its text and span taken from them
+fn segment(words: Vec<Value>) -> Value {
+    let text = words.iter().filter_map(|w| w["word"].as_str()).collect::<Vec<_>>().join(" ");
+    let start = words.iter().find_map(|w| w.get("start")?.as_f64());
+    let end = words.iter().rev().find_map(|w| w.get("end")?.as_f64());
+    json!({ "text": text, "start": start, "end": end, "words": words })
+}
+
+/// Convert an alignment made elsewhere to WhisperX's JSON shape (`segments` and `word_segments`).
+///
+/// The input is duck-typed: WhisperX's own output, an object of `segments` each with `words`, an
+/// object with a `words` list (as from Gentle or many cloud speech APIs), or a bare list of words.
+/// Words may be named `word`, `text`, or `token`, with times under `start`/`end`,
+/// `start_time`/`end_time`, `begin`/`stop`, or `start_ms`/`end_ms` (milliseconds).
+pub fn import_alignment(value: &Value) -> Result<Value, String> {
+    let segments: Vec<Value> = if let Some(segments) = value.get("segments").and_then(|s| s.as_array()).filter(|s| s.iter().any(|s| s.get("words").is_some())) {
+        segments.iter().filter_map(|s| s.get("words")?.as_array()).map(|words| segment(import_words(words))).collect()
+    } else if let Some(words) = value.get("word_segments").or_else(|| value.get("words")).and_then(|w| w.as_array()) {
+        vec![segment(import_words(words))]
+    } else if let Some(words) = value.as_array() {
+        vec![segment(import_words(words))]
+    } else {
+        return Err("no words found: expected `word_segments`, `segments` with `words`, a `words` list, or a list of words".to_string());
+    };
+    let word_segments: Vec<Value> = segments.iter().flat_map(|s| s["words"].as_array().cloned().unwrap_or_default()).collect();
+    if word_segments.is_empty() {
+        return Err("no words found in the alignment".to_string());
+    }
+    Ok(json!({ "segments": segments, "word_segments": word_segments }))
+}
+
+/// Add each word's ARPAbet phonemes and the source they came from (`phonemes`, `phoneme_method`) to a
+/// lipsync JSON's `word_segments`, in order
+pub fn add_phonemes(json: &mut Value, arpabet: &[(Vec<String>, &str)]) {
+    if let Some(word_segments) = json.get_mut("word_segments").and_then(|w| w.as_array_mut()) {
+        for (word_segment, (phonemes, method)) in word_segments.iter_mut().zip(arpabet) {
+            if let Some(word) = word_segment.as_object_mut()
+                && word.get("word").and_then(|w| w.as_str()).is_some()
+            {
+                word.insert("phonemes".to_string(), json!(phonemes));
+                word.insert("phoneme_method".to_string(), json!(method));
+            }
+        }
+    }
+}
+
+/// Add the viseme track of a lipsync JSON's words (see [`crate::visemes::viseme_track`]) as `visemes`
+pub fn add_visemes(json: &mut Value) {
+    let visemes = crate::word_timings(json).map(|words| crate::visemes::viseme_track(&words)).unwrap_or_default();
+    if let Some(object) = json.as_object_mut() {
+        object.insert("visemes".to_string(), json!(visemes));
+    }
+}
+
+#[cfg(feature = "full")]
+/// Enrich an alignment made elsewhere: import it (see [`import_alignment`]), then add ARPAbet phonemes
+/// and visemes as a `--lipsync high` export would. Phonemes are looked up for the words of `text`
+/// when given, as the export pipeline does, and otherwise for the aligned words themselves.
+pub fn enrich_alignment(value: &Value, text: Option<&str>, lipsync_with_llm: Option<&str>) -> Result<(Value, crate::SynthesisReport), Box<dyn std::error::Error>> {
+    let mut json = import_alignment(value)?;
+    for warning in crate::warnings::low_confidence_words(&json) {
+        crate::warnings::warn(warning);
+    }
+    let aligned = json["word_segments"].as_array().map(|w| w.iter().filter_map(|w| w["word"].as_str()).collect::<Vec<_>>().join(" ")).unwrap_or_default();
+    let text = match text {
+        Some(text) => {
+            let text = crate::directives::strip_directives(text);
+            let spoken = crate::respelling::strip_respellings(&text).split_whitespace().count();
+            let aligned_count = aligned.split_whitespace().count();
+            if spoken != aligned_count {
+                log::warn!("[Enrich] The text has {} words but the alignment has {}; phonemes may land on the wrong words", spoken, aligned_count);
+            }
+            text
+        }
+        None => aligned,
+    };
+    let arpabet = crate::text_to_arpabet_with_method(&text, lipsync_with_llm);
+    crate::check_strict(&text, &arpabet)?;
+    add_phonemes(&mut json, &arpabet);
+    add_visemes(&mut json);
+    Ok((json, crate::SynthesisReport { warnings: crate::warnings::take_warnings(), lipsync_json: None }))
+}
@@ -0,0 +1,93 @@
+//! The library's error type, so callers can tell failures apart without parsing messages.
+
+use crate::limits::LimitError;
+
+/// Why a library call failed.
+///
+/// Voice loading, synthesis, and downloads return it directly. Functions that still return
+/// `Box<dyn std::error::Error>` box these same values where they apply, so
+/// `error.downcast_ref::<text_to_face::Error>()` (or [`Error::find`]) recovers them.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// No voice in the catalog has this ID
+    #[error("Voice '{0}' not found. Run `text-to-face list` to see available voices.")]
+    VoiceNotFound(String),
+    /// A voice model or other file couldn't be fetched
+    #[error("Failed to download {url}: {message}")]
+    DownloadFailed { url: String, message: String },
+    /// The voice model failed to load, phonemize, or synthesize
+    #[error("Synthesis failed: {0}")]
+    SynthesisFailed(#[from] piper_rs::PiperError),
+    /// An external program the operation needs (WhisperX, ffmpeg, SoX, ...) isn't installed
+    #[error("'{tool}' executable not found in your PATH. {hint}")]
+    ExternalToolMissing { tool: String, hint: String },
+    /// A WAV file couldn't be read or written
+    #[error("Audio I/O failed: {0}")]
+    AudioIo(#[from] hound::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// The request was refused or stopped for exceeding the configured [`crate::limits::ResourceLimits`]
+    #[error(transparent)]
+    LimitExceeded(#[from] LimitError),
+    /// The work was stopped through its [`crate::cancel::CancelToken`]
+    #[error("Cancelled")]
+    Cancelled,
+    /// Any other failure, from parts of the library without a variant of their own. Held as `Send +
+    /// Sync` so errors can cross threads (and async tasks); other boxed errors keep their message.
+    #[error(transparent)]
+    Other(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl Error {
+    pub fn tool_missing(tool: &str, hint: impl Into<String>) -> Self {
+        Error::ExternalToolMissing { tool: tool.to_string(), hint: hint.into() }
+    }
+
+    /// A failure to start `tool`: [`Error::ExternalToolMissing`] if it isn't installed, else [`Error::Io`]
+    pub fn spawn_failed(tool: &str, hint: impl Into<String>, error: std::io::Error) -> Self {
+        if error.kind() == std::io::ErrorKind::NotFound { Error::tool_missing(tool, hint) } else { Error::Io(error) }
+    }
+
+    /// The [`Error`] inside a boxed error, if it is one
+    pub fn find<'a>(error: &'a (dyn std::error::Error + 'static)) -> Option<&'a Error> {
+        error.downcast_ref::<Error>()
+    }
+
+    /// The limit exceeded, if that's why this failed
+    pub fn limit(&self) -> Option<&LimitError> {
+        match self {
+            Error::LimitExceeded(limit) => Some(limit),
+            Error::Other(inner) => inner.downcast_ref::<LimitError>(),
+            _ => None,
+        }
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for Error {
+    /// Boxed errors that are already an [`Error`] are unwrapped rather than nested in [`Error::Other`]
+    fn from(error: Box<dyn std::error::Error>) -> Self {
+        match error.downcast::<Error>() {
+            Ok(error) => *error,
+            Err(error) => match error.downcast::<LimitError>() {
+                Ok(limit) => Error::LimitExceeded(*limit),
+                Err(error) => Error::Other(error.to_string().into()),
+            },
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Other(message.into())
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::Other(message.into())
+    }
+}
+
+/// Result of the library calls that return [`Error`]
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,138 @@
+use crate::vmc::amplitude_envelope;
+use crate::WordTiming;
+use serde_json::{json, Value};
+
+/// Loudness (relative to the loudest 10ms) above which audio counts as speech
+const VOICED_THRESHOLD: f32 = 0.05;
+/// Nominal length per letter for words without phonemes
+const SECONDS_PER_LETTER: f32 = 0.065;
+/// Nominal pause after a comma, semicolon, or colon
+const CLAUSE_PAUSE: f32 = 0.15;
+/// Nominal pause after the end of a sentence
+const SENTENCE_PAUSE: f32 = 0.35;
+
+/// Typical length of an ARPAbet phoneme in read speech, in seconds. Stress markers are ignored,
+/// except that unstressed `AH0` (schwa) is shorter.
+pub fn average_phone_seconds(phoneme: &str) -> f32 {
+    if phoneme == "AH0" {
+        return 0.05;
+    }
+    match phoneme.trim_end_matches(|c: char| c.is_ascii_digit()) {
+        "AW" | "AY" | "OY" => 0.15,
+        "EY" | "OW" => 0.13,
+        "AA" | "AE" | "AO" => 0.12,
+        "ER" | "IY" | "UW" => 0.10,
+        "EH" => 0.09,
+        "AH" | "IH" | "UH" => 0.07,
+        "S" | "SH" => 0.10,
+        "CH" | "F" => 0.09,
+        "JH" | "Z" | "ZH" | "TH" => 0.08,
+        "K" | "P" | "M" | "NG" => 0.07,
+        "B" | "G" | "T" | "V" | "HH" | "N" | "L" | "R" | "W" => 0.06,
+        "D" | "Y" => 0.05,
+        "DH" => 0.04,
+        _ => 0.08,
+    }
+}
+
+pub(crate) fn nominal_word_seconds(word: &str, phonemes: &[String]) -> f32 {
+    if phonemes.is_empty() {
+        return word.chars().filter(|c| c.is_alphanumeric()).count().max(1) as f32 * SECONDS_PER_LETTER;
+    }
+    phonemes.iter().map(|p| average_phone_seconds(p)).sum()
+}
+
+pub(crate) fn pause_after(word: &str) -> f32 {
+    match word.trim_end_matches(['"', '\'', ')', '’', '”']).chars().last() {
+        Some('.' | '!' | '?' | '…') => SENTENCE_PAUSE,
+        Some(',' | ';' | ':' | '—') => CLAUSE_PAUSE,
+        _ => 0.0,
+    }
+}
+
+/// Start and end in seconds of the audio louder than [`VOICED_THRESHOLD`], or `None` if it's all silent
+pub(crate) fn voiced_span(samples: &[f32], sample_rate: u32) -> Option<(f32, f32)> {
+    let hop = (sample_rate / 100).max(1) as usize;
+    let envelope = amplitude_envelope(samples, hop);
+    let voiced = |v: &f32| *v > VOICED_THRESHOLD;
+    let (first, last) = (envelope.iter().position(voiced)?, envelope.iter().rposition(voiced)?);
+    Some((first as f32 * hop as f32 / sample_rate as f32, (last + 1) as f32 * hop as f32 / sample_rate as f32))
+}
+
+/// Estimate word timings without alignment: each word gets its phonemes' average lengths, punctuation
+/// gets a pause, and the result is stretched to fit the voiced part of the audio. No recognition model is
+/// involved, so it takes milliseconds, at the cost of accuracy on long or unevenly paced audio.
+pub fn estimate_word_timings(words: &[(String, Vec<String>)], samples: &[f32], sample_rate: u32) -> Vec<WordTiming> {
+    let Some((start, end)) = voiced_span(samples, sample_rate) else {
+        return Vec::new();
+    };
+
+    let nominal: Vec<(f32, f32)> = words
+        .iter()
+        .enumerate()
+        // The last word's pause is past the end of the speech
+        .map(|(i, (word, phonemes))| (nominal_word_seconds(word, phonemes), if i + 1 < words.len() { pause_after(word) } else { 0.0 }))
+        .collect();
+    let total: f32 = nominal.iter().map(|(word, pause)| word + pause).sum();
+    if total <= 0.0 {
+        return Vec::new();
+    }
+    let scale = (end - start) / total;
+    let mut cursor = start;
+    words
+        .iter()
+        .zip(nominal)
+        .map(|((word, phonemes), (length, pause))| {
+            let timing = WordTiming { word: word.clone(), start: cursor, end: cursor + length * scale, phonemes: phonemes.clone() };
+            cursor = timing.end + pause * scale;
+            timing
+        })
+        .collect()
+}
+
+/// Split a word's time span between its phonemes in proportion to their average lengths
+pub fn phone_spans(word: &WordTiming) -> Vec<(String, f32, f32)> {
+    let total: f32 = word.phonemes.iter().map(|p| average_phone_seconds(p)).sum();
+    let mut cursor = word.start;
+    word.phonemes
+        .iter()
+        .map(|phoneme| {
+            let start = cursor;
+            cursor += (word.end - word.start) * average_phone_seconds(phoneme) / total;
+            (phoneme.clone(), start, cursor)
+        })
+        .collect()
+}
+
+/// Lipsync JSON in WhisperX's shape (`segments` and `word_segments`) for estimated timings, with each
+/// word's phonemes and their estimated spans under `phones`. `methods` gives each word's `phoneme_method`.
+pub fn estimated_lipsync_json(text: &str, words: &[WordTiming], methods: &[&str]) -> Value {
+    let phones: Vec<Vec<(String, f32, f32)>> = words.iter().map(phone_spans).collect();
+    timed_lipsync_json("estimate", text, words, &phones, methods)
+}
+
+/// Lipsync JSON in WhisperX's shape for timings found without WhisperX, marked `"lipsync": lipsync`,
+/// with each word's phoneme spans (`phones[i]` for `words[i]`) under `phones`
+pub fn timed_lipsync_json(lipsync: &str, text: &str, words: &[WordTiming], phones: &[Vec<(String, f32, f32)>], methods: &[&str]) -> Value {
+    let segments: Vec<Value> = words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| {
+            let phones: Vec<Value> = phones.get(i).into_iter().flatten().map(|(phoneme, start, end)| json!({ "phoneme": phoneme, "start": start, "end": end })).collect();
+            json!({
+                "word": word.word,
+                "start": word.start,
+                "end": word.end,
+                "phonemes": word.phonemes,
+                "phoneme_method": methods.get(i),
+                "phones": phones,
+            })
+        })
+        .collect();
+    let (start, end) = (words.first().map_or(0.0, |w| w.start), words.last().map_or(0.0, |w| w.end));
+    json!({
+        "lipsync": lipsync,
+        "segments": [{ "text": text, "start": start, "end": end, "words": segments }],
+        "word_segments": segments,
+    })
+}
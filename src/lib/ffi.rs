@@ -0,0 +1,177 @@
+//! C ABI for engine plugins (Unreal, Godot, ...) and other languages (build with the `ffi` feature).
+//! The declarations are in `include/pitch_tts.h`.
+//!
+//! Functions return 0 on success and -1 on failure, with the message available from
+//! [`pitch_tts_last_error`] on the same thread. Strings are UTF-8 and NUL-terminated. Audio and
+//! strings the library returns are owned by the caller and freed with [`pitch_tts_free_audio`] and
+//! [`pitch_tts_free_string`].
+
+use crate::PitchArg;
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+/// Synthesized audio: `len` mono samples at `sample_rate`, pitch and tempo applied
+#[repr(C)]
+pub struct PitchTtsAudio {
+    pub samples: *mut f32,
+    pub len: usize,
+    pub sample_rate: u32,
+}
+
+thread_local! {
+    /// The last failure on this thread, for `pitch_tts_last_error`
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    // Interior NULs would cut the message short in C, so they're dropped
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Run `call`, turning an error or a panic into -1 and the thread's last error
+fn guarded(call: impl FnOnce() -> Result<(), Box<dyn std::error::Error>>) -> c_int {
+    match catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(())) => 0,
+        Ok(Err(e)) => {
+            set_last_error(e.to_string());
+            -1
+        }
+        Err(_) => {
+            set_last_error("pitch-tts panicked".to_string());
+            -1
+        }
+    }
+}
+
+/// # Safety
+/// `s` must be null or a valid NUL-terminated string.
+unsafe fn string_arg<'a>(s: *const c_char, name: &str) -> Result<&'a str, Box<dyn std::error::Error>> {
+    if s.is_null() {
+        return Err(format!("{} is null", name).into());
+    }
+    // SAFETY: non-null, and the caller promises a NUL-terminated string
+    Ok(unsafe { CStr::from_ptr(s) }.to_str().map_err(|_| format!("{} isn't valid UTF-8", name))?)
+}
+
+/// Write `json` to `*out` as a string the caller frees with `pitch_tts_free_string`
+///
+/// # Safety
+/// `out` must be null or valid for writes.
+unsafe fn return_string(json: String, out: *mut *mut c_char) -> Result<(), Box<dyn std::error::Error>> {
+    if out.is_null() {
+        return Err("out is null".into());
+    }
+    let json = CString::new(json)?;
+    // SAFETY: non-null, and the caller promises it's writable
+    unsafe { *out = json.into_raw() };
+    Ok(())
+}
+
+/// Synthesize `text` with `voice` (downloading it if needed), pitch shifted by `pitch` (1.0 = unchanged)
+/// and stretched by `tempo`, into `*out`.
+///
+/// # Safety
+/// `text` and `voice` must be valid NUL-terminated strings and `out` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pitch_tts_synthesize(text: *const c_char, voice: *const c_char, pitch: f32, tempo: f32, out: *mut PitchTtsAudio) -> c_int {
+    guarded(|| {
+        // SAFETY: the caller's promises above
+        let (text, voice) = unsafe { (string_arg(text, "text")?, string_arg(voice, "voice")?) };
+        if out.is_null() {
+            return Err("out is null".into());
+        }
+        let (samples, sample_rate) = crate::synthesize_processed(text, voice, &PitchArg::Value(pitch), tempo)?;
+        let samples = Box::into_raw(samples.into_boxed_slice());
+        // SAFETY: checked non-null; the caller promises it's writable
+        unsafe { *out = PitchTtsAudio { len: samples.len(), samples: samples.cast(), sample_rate } };
+        Ok(())
+    })
+}
+
+/// Free audio from `pitch_tts_synthesize` and reset it to empty. Null and empty audio are ignored.
+///
+/// # Safety
+/// `audio` must be null or point to audio filled in by `pitch_tts_synthesize`, not already freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pitch_tts_free_audio(audio: *mut PitchTtsAudio) {
+    // SAFETY: the caller's promise above
+    let Some(audio) = (unsafe { audio.as_mut() }) else {
+        return;
+    };
+    if !audio.samples.is_null() {
+        // SAFETY: allocated by `pitch_tts_synthesize` as a boxed slice of `len` samples
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(audio.samples, audio.len)) });
+    }
+    *audio = PitchTtsAudio { samples: ptr::null_mut(), len: 0, sample_rate: 0 };
+}
+
+/// The voice catalog as a JSON array into `*out`, as `list --json` prints it (ID, language, quality,
+/// sample rate, whether installed, ...). Only installed voices when `installed_only` is nonzero.
+///
+/// # Safety
+/// `out` must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pitch_tts_list_voices(installed_only: c_int, out: *mut *mut c_char) -> c_int {
+    guarded(|| {
+        let voices: Vec<serde_json::Value> = crate::get_available_voices()
+            .into_iter()
+            .filter_map(|voice| {
+                let capabilities = crate::voice_capabilities(&voice);
+                if installed_only != 0 && !capabilities.installed {
+                    return None;
+                }
+                let mut listing = serde_json::to_value(&voice).ok()?;
+                if let (Some(listing), serde_json::Value::Object(capabilities)) = (listing.as_object_mut(), serde_json::to_value(capabilities).ok()?) {
+                    listing.extend(capabilities);
+                }
+                Some(listing)
+            })
+            .collect();
+        // SAFETY: the caller's promise above
+        unsafe { return_string(serde_json::to_string(&voices)?, out) }
+    })
+}
+
+/// Lipsync JSON for `text` spoken in `len` samples at `sample_rate` (as from `pitch_tts_synthesize`)
+/// into `*out`: word timings with ARPAbet phonemes, fitted to the audio with the built-in aligner when
+/// `aligned` is nonzero and estimated from the text otherwise. Neither needs WhisperX.
+///
+/// # Safety
+/// `text` must be a valid NUL-terminated string, `samples` valid for `len` reads, and `out` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pitch_tts_lipsync(text: *const c_char, samples: *const f32, len: usize, sample_rate: u32, aligned: c_int, out: *mut *mut c_char) -> c_int {
+    guarded(|| {
+        // SAFETY: the caller's promise above
+        let text = unsafe { string_arg(text, "text")? };
+        if samples.is_null() && len > 0 {
+            return Err("samples is null".into());
+        }
+        // SAFETY: the caller promises `len` readable samples
+        let samples = if len == 0 { &[][..] } else { unsafe { std::slice::from_raw_parts(samples, len) } };
+        let json = crate::lipsync_without_whisperx(text, samples, sample_rate, aligned != 0, None)?;
+        // SAFETY: the caller's promise above
+        unsafe { return_string(serde_json::to_string(&json)?, out) }
+    })
+}
+
+/// Free a string from `pitch_tts_list_voices` or `pitch_tts_lipsync`. Null is ignored.
+///
+/// # Safety
+/// `s` must be null or a string returned by this library, not already freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pitch_tts_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: created by `CString::into_raw` in `return_string`
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+/// The message of the last failure on this thread, or null if nothing has failed. Valid until the
+/// next failing call on the thread; don't free it.
+#[unsafe(no_mangle)]
+pub extern "C" fn pitch_tts_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
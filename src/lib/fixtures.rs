@@ -0,0 +1,216 @@
+//! Fixture voice and mock aligner for end-to-end tests (`--features fixtures`).
+//!
+//! The fixture voice is a real ONNX model in Piper's format, a few hundred bytes, generated on the fly:
+//! it takes the same `input`, `input_lengths`, and `scales` inputs as a Piper VITS model and turns the
+//! phoneme IDs into a deterministic buzz, [`SAMPLES_PER_PHONEME_ID`] samples per ID. Everything around
+//! the model (voice lookup, config loading, phonemization with espeak-ng, pitch/tempo, export, tags)
+//! runs for real. The mock aligner stands in for the `whisperx` executable.
+
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// Sample rate of the fixture voice
+pub const FIXTURE_SAMPLE_RATE: u32 = 22050;
+
+/// Samples of audio the fixture model produces per phoneme ID (Piper pads between phonemes, so about
+/// two IDs per phoneme)
+pub const SAMPLES_PER_PHONEME_ID: i64 = 1024;
+
+/// Symbols the fixture voice knows: Piper's pad, start, and end markers, then the characters espeak-ng
+/// uses for English and most other languages. Phonemes outside it are skipped, as Piper does.
+const SYMBOLS: &str = "_^$ !'(),-.:;?abcdefghijklmnopqrstuvwxyzæçðøħŋœɐɑɒɓɔɕɖɗɘəɚɛɜɞɟɠɡɢɣɤɥɦɧɨɪɫɬɭɮɯɰɱɲɳɴɵɶɸɹɺɻɽɾʀʁʂʃʄʈʉʊʋʌʍʎʏʐʑʒʔʕʘʙʛʜʝʟʡʢʲˈˌːˑ̩̃θχ";
+
+// Protobuf encoding, just enough of it to write an ONNX ModelProto
+
+fn varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn int_field(buf: &mut Vec<u8>, field: u64, value: u64) {
+    varint(buf, field << 3);
+    varint(buf, value);
+}
+
+fn bytes_field(buf: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    varint(buf, field << 3 | 2);
+    varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+// ONNX TensorProto data types
+const FLOAT: u64 = 1;
+const INT64: u64 = 7;
+
+/// ValueInfoProto for a tensor; `dims` are fixed sizes or named dynamic ones
+fn value_info(name: &str, elem_type: u64, dims: &[Result<u64, &str>]) -> Vec<u8> {
+    let mut shape = Vec::new();
+    for dim in dims {
+        let mut dimension = Vec::new();
+        match dim {
+            Ok(size) => int_field(&mut dimension, 1, *size),
+            Err(param) => bytes_field(&mut dimension, 2, param.as_bytes()),
+        }
+        bytes_field(&mut shape, 1, &dimension);
+    }
+    let mut tensor = Vec::new();
+    int_field(&mut tensor, 1, elem_type);
+    bytes_field(&mut tensor, 2, &shape);
+    let mut type_proto = Vec::new();
+    bytes_field(&mut type_proto, 1, &tensor);
+    let mut info = Vec::new();
+    bytes_field(&mut info, 1, name.as_bytes());
+    bytes_field(&mut info, 2, &type_proto);
+    info
+}
+
+/// TensorProto holding constant data
+fn initializer(name: &str, data_type: u64, dims: &[u64], raw: &[u8]) -> Vec<u8> {
+    let mut tensor = Vec::new();
+    for dim in dims {
+        int_field(&mut tensor, 1, *dim);
+    }
+    int_field(&mut tensor, 2, data_type);
+    bytes_field(&mut tensor, 8, name.as_bytes());
+    bytes_field(&mut tensor, 9, raw);
+    tensor
+}
+
+/// NodeProto, with integer attributes
+fn node(op_type: &str, inputs: &[&str], output: &str, attributes: &[(&str, u64)]) -> Vec<u8> {
+    let mut node = Vec::new();
+    for input in inputs {
+        bytes_field(&mut node, 1, input.as_bytes());
+    }
+    bytes_field(&mut node, 2, output.as_bytes());
+    bytes_field(&mut node, 3, output.as_bytes());
+    bytes_field(&mut node, 4, op_type.as_bytes());
+    for (name, value) in attributes {
+        let mut attribute = Vec::new();
+        bytes_field(&mut attribute, 1, name.as_bytes());
+        int_field(&mut attribute, 3, *value);
+        int_field(&mut attribute, 20, 2); // AttributeProto.INT
+        bytes_field(&mut node, 5, &attribute);
+    }
+    node
+}
+
+/// The fixture model as ONNX bytes: `output = 0.3 * sin(0.37 * input)`, tiled
+/// [`SAMPLES_PER_PHONEME_ID`] times. `input_lengths` and `scales` are accepted and ignored, so
+/// `length_scale` (and with it `--rate`) doesn't change the length of fixture audio; `--tempo` does.
+pub fn fixture_model() -> Vec<u8> {
+    let mut graph = Vec::new();
+    for node in [
+        node("Cast", &["input"], "ids", &[("to", FLOAT)]),
+        node("Mul", &["ids", "step"], "phase", &[]),
+        node("Sin", &["phase"], "wave", &[]),
+        node("Mul", &["wave", "gain"], "quiet", &[]),
+        node("Tile", &["quiet", "repeats"], "output", &[]),
+    ] {
+        bytes_field(&mut graph, 1, &node);
+    }
+    bytes_field(&mut graph, 2, b"fixture");
+    let repeats: Vec<u8> = [1i64, SAMPLES_PER_PHONEME_ID].iter().flat_map(|r| r.to_le_bytes()).collect();
+    for tensor in [
+        initializer("step", FLOAT, &[], &0.37f32.to_le_bytes()),
+        initializer("gain", FLOAT, &[], &0.3f32.to_le_bytes()),
+        initializer("repeats", INT64, &[2], &repeats),
+    ] {
+        bytes_field(&mut graph, 5, &tensor);
+    }
+    for input in [
+        value_info("input", INT64, &[Ok(1), Err("phonemes")]),
+        value_info("input_lengths", INT64, &[Ok(1)]),
+        value_info("scales", FLOAT, &[Ok(3)]),
+    ] {
+        bytes_field(&mut graph, 11, &input);
+    }
+    bytes_field(&mut graph, 12, &value_info("output", FLOAT, &[Ok(1), Err("samples")]));
+
+    let mut opset = Vec::new();
+    bytes_field(&mut opset, 1, b"");
+    int_field(&mut opset, 2, 13);
+    let mut model = Vec::new();
+    int_field(&mut model, 1, 7); // IR version
+    bytes_field(&mut model, 2, b"text-to-face fixtures");
+    bytes_field(&mut model, 7, &graph);
+    bytes_field(&mut model, 8, &opset);
+    model
+}
+
+/// Piper voice config for the fixture model, phonemized by espeak-ng as `espeak_voice` (e.g. `en-us`)
+pub fn fixture_config(espeak_voice: &str) -> Value {
+    let phoneme_id_map: serde_json::Map<String, Value> = SYMBOLS.chars().enumerate().map(|(id, symbol)| (symbol.to_string(), json!([id]))).collect();
+    json!({
+        "dataset": "fixture",
+        "audio": { "sample_rate": FIXTURE_SAMPLE_RATE, "quality": "x_low" },
+        "espeak": { "voice": espeak_voice },
+        "language": { "code": espeak_voice },
+        "inference": { "noise_scale": 0.667, "length_scale": 1.0, "noise_w": 0.8 },
+        "num_symbols": phoneme_id_map.len(),
+        "num_speakers": 1,
+        "speaker_id_map": {},
+        "phoneme_map": {},
+        "phoneme_id_map": phoneme_id_map,
+    })
+}
+
+/// Write the fixture voice into `dir` as `<voice_id>.onnx` and `<voice_id>.onnx.json`, where any
+/// voice is looked for: a [`crate::runtime::VoiceRuntime`] directory, or the models directory to
+/// stand in for a catalog voice without downloading it. Returns the config's path.
+pub fn write_fixture_voice(dir: &Path, voice_id: &str) -> std::io::Result<PathBuf> {
+    // Voice IDs start with their language, e.g. en_GB-alba-medium, which espeak-ng calls en-gb
+    let language = voice_id.split('-').next().unwrap_or("en").replace('_', "-").to_lowercase();
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(dir.join(format!("{}.onnx", voice_id)), fixture_model())?;
+    let config_path = dir.join(format!("{}.onnx.json", voice_id));
+    std::fs::write(&config_path, serde_json::to_string_pretty(&fixture_config(&language))?)?;
+    Ok(config_path)
+}
+
+/// Words of `transcript` as JSON string literals, one per line
+fn transcript_lines(transcript: &str) -> String {
+    transcript.split_whitespace().map(|word| Value::from(word).to_string() + "\n").collect()
+}
+
+#[cfg(unix)]
+/// Write a `whisperx` executable into `dir` that "recognizes" `transcript` in whatever WAV it's given:
+/// it writes WhisperX's JSON (to `--output_dir`, named after the WAV) with the words spread evenly over
+/// the audio. Put `dir` first on `PATH` to run lipsync exports without WhisperX or its models.
+pub fn write_mock_aligner(dir: &Path, transcript: &str) -> std::io::Result<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+    // The WAV's length comes from its size: exports are 16-bit mono with a 44-byte header
+    let script = format!(
+        r#"#!/bin/sh
+# Mock WhisperX from text_to_face::fixtures
+[ "$1" = "--help" ] && exit 0
+wav="$1"
+out=.
+while [ $# -gt 0 ]; do
+  [ "$1" = "--output_dir" ] && out="$2"
+  shift
+done
+bytes=$(wc -c < "$wav")
+awk -v bytes="$bytes" -v rate={rate} '
+  {{ words[NR] = $0 }}
+  END {{
+    step = (bytes - 44) / (2 * rate) / (NR > 0 ? NR : 1)
+    list = ""
+    for (i = 1; i <= NR; i++)
+      list = list (i > 1 ? ", " : "") sprintf("{{\"word\": %s, \"start\": %.3f, \"end\": %.3f, \"score\": 1.0}}", words[i], (i - 1) * step, (i - 0.1) * step)
+    printf "{{\"segments\": [{{\"words\": [%s]}}], \"word_segments\": [%s]}}\n", list, list
+  }}' > "$out/$(basename "$wav" .wav).json" <<'WORDS'
+{words}WORDS
+"#,
+        rate = FIXTURE_SAMPLE_RATE,
+        words = transcript_lines(transcript),
+    );
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join("whisperx");
+    std::fs::write(&path, script)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    Ok(path)
+}
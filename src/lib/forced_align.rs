@@ -0,0 +1,249 @@
+//! Forced alignment without WhisperX (`--lipsync align`): the text's phonemes are fitted to the audio
+//! by dynamic programming over 10ms frames of loudness and zero-crossing rate, with each phoneme's
+//! average length as a prior. It needs no Python or recognition model, and follows the audio where
+//! [`crate::estimate`] only stretches the text over it.
+
+use crate::estimate::{average_phone_seconds, estimate_word_timings, nominal_word_seconds, pause_after, phone_spans, voiced_span};
+use crate::WordTiming;
+
+/// Frames per second of the acoustic features
+const FRAME_RATE: usize = 100;
+/// Loudness floor in dB below the loudest frame; quieter frames all count as silence
+const FLOOR_DB: f32 = 60.0;
+/// Zero-crossing rate (crossings per sample) treated as fully noisy, as in a strong `S`
+const NOISY_ZCR: f32 = 0.3;
+/// Weight of the log-duration prior against the per-frame acoustic cost
+const DURATION_WEIGHT: f32 = 1.5;
+/// Cost of a pause between two words, so short dips in loudness (like stop closures) stay in the word
+const PAUSE_COST: f32 = 2.0;
+/// How far (in frames) a unit may end from where the average phoneme lengths would put it
+const BAND: usize = 300;
+/// Longest a phoneme may be held, as a multiple of its expected length
+const MAX_STRETCH: f32 = 4.0;
+
+/// Broad acoustic class of a phoneme, which is all the features can tell apart
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Class {
+    Silence,
+    Vowel,
+    Sonorant,
+    VoicedFricative,
+    Fricative,
+    Stop,
+}
+
+impl Class {
+    const ALL: [Class; 6] = [Class::Silence, Class::Vowel, Class::Sonorant, Class::VoicedFricative, Class::Fricative, Class::Stop];
+
+    fn of(phoneme: &str) -> Class {
+        match phoneme.trim_end_matches(|c: char| c.is_ascii_digit()) {
+            "M" | "N" | "NG" | "L" | "R" | "W" | "Y" => Class::Sonorant,
+            "Z" | "ZH" | "V" | "DH" | "JH" => Class::VoicedFricative,
+            "S" | "SH" | "F" | "TH" | "HH" | "CH" => Class::Fricative,
+            "P" | "B" | "T" | "D" | "K" | "G" => Class::Stop,
+            _ => Class::Vowel,
+        }
+    }
+
+    /// Typical (loudness, noisiness) of the class, both 0–1, and how much noisiness counts
+    fn target(self) -> (f32, f32, f32) {
+        match self {
+            Class::Silence => (0.1, 0.0, 0.0),
+            Class::Vowel => (0.9, 0.1, 1.0),
+            Class::Sonorant => (0.75, 0.1, 1.0),
+            Class::VoicedFricative => (0.6, 0.5, 1.0),
+            Class::Fricative => (0.5, 0.9, 1.0),
+            Class::Stop => (0.4, 0.4, 0.3),
+        }
+    }
+
+    /// How far a frame with `loudness` and `noise` is from this class
+    fn cost(self, loudness: f32, noise: f32) -> f32 {
+        let (target_loudness, target_noise, noise_weight) = self.target();
+        (loudness - target_loudness).powi(2) + noise_weight * 0.5 * (noise - target_noise).powi(2)
+    }
+
+    fn index(self) -> usize {
+        Class::ALL.iter().position(|c| *c == self).unwrap_or(0)
+    }
+}
+
+/// Per-frame loudness (0 at [`FLOOR_DB`] below the loudest frame, 1 at the loudest) and noisiness
+/// (zero-crossing rate relative to [`NOISY_ZCR`])
+fn frame_features(samples: &[f32], sample_rate: u32) -> Vec<(f32, f32)> {
+    let hop = (sample_rate as usize / FRAME_RATE).max(1);
+    let frames: Vec<(f32, f32)> = samples
+        .chunks(hop)
+        .map(|chunk| {
+            let rms = (chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32).sqrt();
+            let crossings = chunk.windows(2).filter(|w| (w[0] >= 0.0) != (w[1] >= 0.0)).count();
+            (rms, crossings as f32 / chunk.len() as f32)
+        })
+        .collect();
+    let loudest = frames.iter().map(|(rms, _)| *rms).fold(0.0f32, f32::max);
+    if loudest <= 0.0 {
+        return frames.into_iter().map(|_| (0.0, 0.0)).collect();
+    }
+    frames
+        .into_iter()
+        .map(|(rms, zcr)| {
+            let db = 20.0 * (rms.max(1e-9) / loudest).log10();
+            (((db + FLOOR_DB) / FLOOR_DB).clamp(0.0, 1.0), (zcr / NOISY_ZCR).min(1.0))
+        })
+        .collect()
+}
+
+/// A word's phonemes with their start and end in seconds
+pub type PhoneSpans = Vec<(String, f32, f32)>;
+
+/// One step of the alignment: a phoneme of a word, or an optional pause
+struct Unit {
+    class: Class,
+    /// Expected length (in seconds, then frames once scaled to the audio); pauses have none and may be skipped
+    expected: Option<f32>,
+    /// Nominal pause in seconds, for pauses between words
+    pause: f32,
+    /// The word and phoneme index this unit belongs to
+    phone: Option<(usize, usize)>,
+    /// Longest the unit may last, in frames
+    max_frames: usize,
+}
+
+/// Word timings and each word's phoneme spans, fitted to the audio. Falls back to
+/// [`estimate_word_timings`] when the audio is too short for the phonemes or is silent.
+pub fn align_word_timings(words: &[(String, Vec<String>)], samples: &[f32], sample_rate: u32) -> (Vec<WordTiming>, Vec<PhoneSpans>) {
+    let estimated = || {
+        let timings = estimate_word_timings(words, samples, sample_rate);
+        let phones = timings.iter().map(phone_spans).collect();
+        (timings, phones)
+    };
+    let features = frame_features(samples, sample_rate);
+    let frames = features.len();
+    let Some((voiced_start, voiced_end)) = voiced_span(samples, sample_rate) else {
+        return estimated();
+    };
+
+    // The text as a chain of units: pause, phonemes of word 1, pause, phonemes of word 2, ..., pause
+    let pause = |seconds: f32, max_frames: usize| Unit { class: Class::Silence, expected: None, pause: seconds, phone: None, max_frames };
+    let mut units = vec![pause(0.0, frames)];
+    let mut nominal_total = 0.0f32;
+    for (w, (word, phonemes)) in words.iter().enumerate() {
+        if phonemes.is_empty() {
+            // Without phonemes the word is one voiced stretch of its nominal length
+            let seconds = nominal_word_seconds(word, phonemes);
+            nominal_total += seconds;
+            units.push(Unit { class: Class::Vowel, expected: Some(seconds), pause: 0.0, phone: Some((w, 0)), max_frames: 0 });
+        } else {
+            for (p, phoneme) in phonemes.iter().enumerate() {
+                let seconds = average_phone_seconds(phoneme);
+                nominal_total += seconds;
+                units.push(Unit { class: Class::of(phoneme), expected: Some(seconds), pause: 0.0, phone: Some((w, p)), max_frames: 0 });
+            }
+        }
+        if w + 1 < words.len() {
+            nominal_total += pause_after(word);
+            units.push(pause(pause_after(word), FRAME_RATE * 2));
+        }
+    }
+    units.push(pause(0.0, frames));
+    if nominal_total <= 0.0 || units.iter().filter(|u| u.expected.is_some()).count() > frames {
+        return estimated();
+    }
+
+    // Expected lengths in frames, stretched like the estimate so the phonemes fill the voiced audio
+    let scale = (voiced_end - voiced_start) / nominal_total * FRAME_RATE as f32;
+    // The first pause ends where the speech starts, and the last at the end of the audio
+    let mut expected_end = Vec::with_capacity(units.len());
+    let mut cursor = voiced_start * FRAME_RATE as f32;
+    for unit in units.iter_mut() {
+        match unit.expected.as_mut() {
+            Some(length) => {
+                *length *= scale;
+                unit.max_frames = (*length * MAX_STRETCH).ceil() as usize + 3;
+                cursor += *length;
+            }
+            None => cursor += unit.pause * scale,
+        }
+        expected_end.push((cursor.round() as usize).min(frames));
+    }
+    let last = units.len() - 1;
+    expected_end[last] = frames;
+
+    // Prefix sums of each class's frame cost, so any segment's cost is two lookups
+    let mut prefix = vec![vec![0.0f32; frames + 1]; Class::ALL.len()];
+    for (c, class) in Class::ALL.iter().enumerate() {
+        for (f, (loudness, noise)) in features.iter().enumerate() {
+            prefix[c][f + 1] = prefix[c][f] + class.cost(*loudness, *noise);
+        }
+    }
+    let segment_cost = |class: Class, start: usize, end: usize| prefix[class.index()][end] - prefix[class.index()][start];
+
+    // best[u][e - lo[u]]: cheapest fit of units 0..=u to frames 0..e, with the length of unit u
+    // Only ends within BAND of the expected position are considered; the last unit ends the audio
+    let mut lo: Vec<usize> = expected_end.iter().map(|e| e.saturating_sub(BAND)).collect();
+    let hi: Vec<usize> = expected_end.iter().map(|e| (e + BAND).min(frames)).collect();
+    lo[last] = frames;
+    let mut best: Vec<Vec<(f32, usize)>> = Vec::with_capacity(units.len());
+    for (u, unit) in units.iter().enumerate() {
+        let mut row = vec![(f32::INFINITY, 0usize); hi[u] - lo[u] + 1];
+        for (slot, end) in row.iter_mut().zip(lo[u]..=hi[u]) {
+            let min_frames = if unit.expected.is_some() { 1 } else { 0 };
+            for length in min_frames..=unit.max_frames.min(end) {
+                let start = end - length;
+                let before = if u == 0 {
+                    if start == 0 { 0.0 } else { continue }
+                } else if start < lo[u - 1] || start > hi[u - 1] {
+                    continue;
+                } else {
+                    best[u - 1][start - lo[u - 1]].0
+                };
+                if !before.is_finite() {
+                    continue;
+                }
+                let prior = match unit.expected {
+                    Some(expected) => DURATION_WEIGHT * (length as f32 / expected.max(1.0)).ln().powi(2),
+                    None if length > 0 && u != 0 && u != last => PAUSE_COST,
+                    None => 0.0,
+                };
+                let cost = before + segment_cost(unit.class, start, end) + prior;
+                if cost < slot.0 {
+                    *slot = (cost, length);
+                }
+            }
+        }
+        best.push(row);
+    }
+    if !best[last][0].0.is_finite() {
+        return estimated();
+    }
+
+    // Walk back from the end to find each unit's frames
+    let mut spans = vec![(0usize, 0usize); units.len()];
+    let mut end = frames;
+    for u in (0..units.len()).rev() {
+        let length = best[u][end - lo[u]].1;
+        spans[u] = (end - length, end);
+        end -= length;
+    }
+
+    let seconds = |frame: usize| frame as f32 / FRAME_RATE as f32;
+    let mut phones: Vec<PhoneSpans> = vec![Vec::new(); words.len()];
+    let mut bounds: Vec<Option<(usize, usize)>> = vec![None; words.len()];
+    for (unit, (start, end)) in units.iter().zip(&spans) {
+        let Some((w, p)) = unit.phone else { continue };
+        if let Some(phoneme) = words[w].1.get(p) {
+            phones[w].push((phoneme.clone(), seconds(*start), seconds(*end)));
+        }
+        let bound = bounds[w].get_or_insert((*start, *end));
+        bound.1 = *end;
+    }
+    let timings = words
+        .iter()
+        .zip(&bounds)
+        .map(|((word, phonemes), bound)| {
+            let (start, end) = bound.unwrap_or_default();
+            WordTiming { word: word.clone(), start: seconds(start), end: seconds(end), phonemes: phonemes.clone() }
+        })
+        .collect();
+    (timings, phones)
+}
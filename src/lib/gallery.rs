@@ -0,0 +1,196 @@
+use crate::sidecar::read_sidecar;
+use crate::visemes::{mouth_form, mouth_openness, viseme_track};
+use serde::Serialize;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+/// One rendered line in a review gallery. Paths are relative to the gallery page.
+#[derive(Debug, Clone, Serialize)]
+pub struct GalleryEntry {
+    pub audio: String,
+    pub duration: f32,
+    pub voice: Option<String>,
+    pub text: String,
+    pub warnings: Vec<String>,
+    /// Lipsync JSON, if the line has one
+    pub lipsync: Option<String>,
+    /// `<name>.preview.html`, if one was written
+    pub preview: Option<String>,
+    /// Viseme track with mouth openness and form, for the gallery's inline mouth
+    pub visemes: Vec<serde_json::Value>,
+}
+
+/// Every WAV under `dir`, in path order. Music mixes (`.mix.wav`) are left out; they belong to the line beside them.
+fn find_wavs(dir: &Path, wavs: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)?.filter_map(|e| e.ok().map(|e| e.path())).collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            find_wavs(&path, wavs)?;
+        } else if path.extension().is_some_and(|e| e == "wav") && !path.to_string_lossy().ends_with(".mix.wav") {
+            wavs.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn relative(path: &Path, dir: &Path) -> String {
+    path.strip_prefix(dir).unwrap_or(path).to_string_lossy().replace('\\', "/")
+}
+
+/// Gather the lines rendered under `dir`: each WAV with what its sidecar, lipsync JSON, and preview
+/// page say about it. Lines without a sidecar are listed by file name.
+pub fn collect_entries(dir: &Path) -> Result<Vec<GalleryEntry>, Box<dyn std::error::Error>> {
+    let mut wavs = Vec::new();
+    find_wavs(dir, &mut wavs)?;
+    let mut entries = Vec::with_capacity(wavs.len());
+    for wav in wavs {
+        let duration = match hound::WavReader::open(&wav) {
+            Ok(reader) => reader.duration() as f32 / reader.spec().sample_rate as f32,
+            Err(e) => {
+                log::warn!("[Gallery] Skipping {}: {}", wav.display(), e);
+                continue;
+            }
+        };
+        let sidecar = read_sidecar(&wav);
+        let lipsync = sidecar
+            .as_ref()
+            .and_then(|s| s.lipsync_path.as_ref().map(PathBuf::from))
+            .filter(|p| p.exists())
+            .or_else(|| Some(wav.with_extension("json")).filter(|p| p.exists()));
+        let timings = lipsync.as_ref().and_then(|p| crate::read_word_timings(p.to_str()?).ok());
+        let visemes = viseme_track(timings.as_deref().unwrap_or_default())
+            .into_iter()
+            .map(|v| json!({ "start": v.start, "end": v.end, "open": mouth_openness(&v.viseme), "form": mouth_form(&v.viseme) }))
+            .collect();
+        let text = match (&sidecar, &timings) {
+            (Some(sidecar), _) => sidecar.text.clone(),
+            (None, Some(words)) => words.iter().map(|w| w.word.as_str()).collect::<Vec<_>>().join(" "),
+            (None, None) => wav.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default(),
+        };
+        let preview = Some(wav.with_extension("preview.html")).filter(|p| p.exists());
+        entries.push(GalleryEntry {
+            audio: relative(&wav, dir),
+            duration,
+            voice: sidecar.as_ref().map(|s| s.voice.clone()),
+            text,
+            warnings: sidecar.as_ref().map(|s| s.warnings.iter().map(|w| w.to_string()).collect()).unwrap_or_default(),
+            lipsync: lipsync.map(|p| relative(&p, dir)),
+            preview: preview.map(|p| relative(&p, dir)),
+            visemes,
+        });
+    }
+    Ok(entries)
+}
+
+const TEMPLATE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>__TITLE__</title>
+<style>
+  body { font-family: system-ui, sans-serif; background: #1e1f24; color: #e6e6e6; max-width: 1200px; margin: 2em auto; }
+  #filters { display: flex; gap: 1.5em; align-items: center; margin-bottom: 1em; }
+  #filters input[type=search] { flex: 1; padding: 0.4em; background: #2a2c33; color: inherit; border: 1px solid #444; border-radius: 4px; }
+  table { width: 100%; border-collapse: collapse; }
+  th, td { text-align: left; padding: 0.5em; border-bottom: 1px solid #33353d; vertical-align: middle; }
+  th { color: #9aa0aa; font-weight: normal; }
+  td.text { max-width: 420px; }
+  td.file, td.voice { font-family: monospace; font-size: 0.85em; color: #9aa0aa; }
+  ul.warnings { margin: 0; padding-left: 1.1em; color: #f5a142; font-size: 0.9em; }
+  audio { width: 240px; }
+  a { color: #7fb2f0; }
+  svg.face { background: #f2d4b7; border-radius: 50%; }
+</style>
+</head>
+<body>
+<h1>__TITLE__</h1>
+<div id="filters">
+  <input type="search" id="search" placeholder="Filter by text, voice, or file">
+  <label><input type="checkbox" id="only-warnings"> Only lines with warnings</label>
+  <span id="count"></span>
+</div>
+<table>
+  <thead><tr><th>Line</th><th>Audio</th><th>Duration</th><th>Voice</th><th>Warnings</th><th>Lipsync</th></tr></thead>
+  <tbody id="rows"></tbody>
+</table>
+<script>
+const ENTRIES = __DATA__;
+const rows = document.getElementById('rows');
+const search = document.getElementById('search');
+const onlyWarnings = document.getElementById('only-warnings');
+
+function escapeHtml(s) {
+  return String(s).replace(/[&<>"]/g, c => ({'&': '&amp;', '<': '&lt;', '>': '&gt;', '"': '&quot;'}[c]));
+}
+
+ENTRIES.forEach((e, i) => {
+  const tr = document.createElement('tr');
+  const lipsync = [];
+  if (e.visemes.length) lipsync.push('<svg class="face" width="40" height="40" viewBox="0 0 160 160"><circle cx="55" cy="60" r="10" fill="#333"/><circle cx="105" cy="60" r="10" fill="#333"/><ellipse cx="80" cy="110" rx="24" ry="2" fill="#7a2630"/></svg>');
+  if (e.preview) lipsync.push('<a href="' + encodeURI(e.preview) + '">preview</a>');
+  if (e.lipsync) lipsync.push('<a href="' + encodeURI(e.lipsync) + '">json</a>');
+  tr.innerHTML =
+    '<td class="text">' + escapeHtml(e.text) + '<br><span class="file">' + escapeHtml(e.audio) + '</span></td>' +
+    '<td><audio controls preload="none" src="' + encodeURI(e.audio) + '"></audio></td>' +
+    '<td>' + e.duration.toFixed(2) + 's</td>' +
+    '<td class="voice">' + escapeHtml(e.voice || '') + '</td>' +
+    '<td>' + (e.warnings.length ? '<ul class="warnings">' + e.warnings.map(w => '<li>' + escapeHtml(w) + '</li>').join('') + '</ul>' : '') + '</td>' +
+    '<td>' + (lipsync.join(' ') || '-') + '</td>';
+  tr.dataset.search = (e.text + ' ' + (e.voice || '') + ' ' + e.audio).toLowerCase();
+  const audio = tr.querySelector('audio');
+  const mouth = tr.querySelector('ellipse');
+  // Only the playing line animates its mouth
+  audio.addEventListener('play', () => {
+    document.querySelectorAll('audio').forEach(other => { if (other !== audio) other.pause(); });
+    if (!mouth) return;
+    const frame = () => {
+      const t = audio.currentTime;
+      const v = e.visemes.find(v => t >= v.start && t < v.end) || { open: 0, form: 0 };
+      mouth.setAttribute('ry', 2 + v.open * 22);
+      mouth.setAttribute('rx', 24 * (1 + v.form * 0.35));
+      if (!audio.paused) requestAnimationFrame(frame);
+      else { mouth.setAttribute('ry', 2); mouth.setAttribute('rx', 24); }
+    };
+    requestAnimationFrame(frame);
+  });
+  e.row = tr;
+  rows.appendChild(tr);
+});
+
+function filter() {
+  const q = search.value.toLowerCase();
+  let shown = 0;
+  ENTRIES.forEach(e => {
+    const show = e.row.dataset.search.includes(q) && (!onlyWarnings.checked || e.warnings.length);
+    e.row.style.display = show ? '' : 'none';
+    if (show) shown++;
+  });
+  const warned = ENTRIES.filter(e => e.warnings.length).length;
+  document.getElementById('count').textContent = shown + ' of ' + ENTRIES.length + ' lines, ' + warned + ' with warnings';
+}
+search.addEventListener('input', filter);
+onlyWarnings.addEventListener('change', filter);
+filter();
+</script>
+</body>
+</html>
+"##;
+
+/// The gallery page for `entries`
+pub fn gallery_html(title: &str, entries: &[GalleryEntry]) -> Result<String, Box<dyn std::error::Error>> {
+    let title = title.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    // Keep `</script>` in user text from terminating the inline script
+    let data = serde_json::to_string(entries)?.replace("</", "<\\/");
+    Ok(TEMPLATE.replace("__TITLE__", &title).replacen("__DATA__", &data, 1))
+}
+
+/// Write `index.html` in `dir`: a review page listing every rendered line under it with an audio
+/// player, duration, voice, warnings, and lipsync. Audio is linked rather than embedded, so the page
+/// stays small for hundreds of lines. Returns the page's path and the number of lines.
+pub fn write_gallery(dir: &Path, title: &str) -> Result<(PathBuf, usize), Box<dyn std::error::Error>> {
+    let entries = collect_entries(dir)?;
+    let path = dir.join("index.html");
+    std::fs::write(&path, gallery_html(title, &entries)?)?;
+    Ok((path, entries.len()))
+}
@@ -0,0 +1,275 @@
+use crate::blender::DEFAULT_SHAPE_PREFIX;
+use crate::visemes::{mouth_form, mouth_openness, VisemeEvent, SILENCE, VISEMES};
+use serde_json::{json, Value};
+
+/// Time to blend from one mouth shape into the next, in seconds
+const BLEND_SECONDS: f32 = 0.04;
+/// Points around the rim of the preview mouth
+const RIM_POINTS: usize = 16;
+
+/// Named set of morph targets (blendshapes) the animation drives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "full", derive(clap::ValueEnum))]
+pub enum BlendshapeSet {
+    /// One target per viseme, named like the Blender add-on's shape keys (`viseme_AA`, `viseme_BM`, ...)
+    Visemes,
+    /// The 15 Oculus/Meta visemes (`viseme_sil`, `viseme_PP`, `viseme_aa`, ...), as used by Ready Player Me avatars
+    Oculus,
+    /// ARKit face blendshapes for the mouth (`jawOpen`, `mouthFunnel`, `mouthPucker`, ...)
+    Arkit,
+}
+
+/// Oculus viseme targets, each with the viseme whose mouth shape it previews as
+const OCULUS: &[(&str, &str)] = &[
+    ("viseme_sil", SILENCE),
+    ("viseme_PP", "BM"),
+    ("viseme_FF", "FV"),
+    ("viseme_TH", "TH"),
+    ("viseme_DD", "D"),
+    ("viseme_kk", "GK"),
+    ("viseme_CH", "CH"),
+    ("viseme_SS", "S"),
+    ("viseme_nn", "N"),
+    ("viseme_RR", "R"),
+    ("viseme_aa", "AA"),
+    ("viseme_E", "E"),
+    ("viseme_I", "I"),
+    ("viseme_O", "O"),
+    ("viseme_U", "W"),
+];
+
+/// ARKit mouth targets, each with the openness and form it previews as
+const ARKIT: &[(&str, f32, f32)] = &[
+    ("jawOpen", 1.0, 0.0),
+    ("mouthClose", -0.15, 0.0),
+    ("mouthFunnel", 0.4, -0.6),
+    ("mouthPucker", 0.05, -1.0),
+    ("mouthSmileLeft", 0.0, 0.5),
+    ("mouthSmileRight", 0.0, 0.5),
+    ("mouthRollLower", -0.1, 0.1),
+];
+
+fn oculus_target(viseme: &str) -> &'static str {
+    match viseme {
+        "BM" => "viseme_PP",
+        "FV" => "viseme_FF",
+        "TH" => "viseme_TH",
+        "D" => "viseme_DD",
+        "GK" => "viseme_kk",
+        "CH" | "SH" => "viseme_CH",
+        "S" => "viseme_SS",
+        "N" | "L" => "viseme_nn",
+        "R" => "viseme_RR",
+        "AA" => "viseme_aa",
+        "E" => "viseme_E",
+        "I" | "Y" => "viseme_I",
+        "O" => "viseme_O",
+        "W" => "viseme_U",
+        _ => "viseme_sil",
+    }
+}
+
+impl BlendshapeSet {
+    /// Target names, in the order of the mesh's morph targets and of each keyframe's weights
+    pub fn target_names(&self) -> Vec<String> {
+        match self {
+            BlendshapeSet::Visemes => VISEMES.iter().map(|v| format!("{}{}", DEFAULT_SHAPE_PREFIX, v)).collect(),
+            BlendshapeSet::Oculus => OCULUS.iter().map(|(name, _)| name.to_string()).collect(),
+            BlendshapeSet::Arkit => ARKIT.iter().map(|(name, _, _)| name.to_string()).collect(),
+        }
+    }
+
+    /// Weight of each target while `viseme` is held
+    pub fn weights(&self, viseme: &str) -> Vec<f32> {
+        match self {
+            BlendshapeSet::Visemes => VISEMES.iter().map(|v| if *v == viseme { 1.0 } else { 0.0 }).collect(),
+            BlendshapeSet::Oculus => {
+                let target = oculus_target(viseme);
+                OCULUS.iter().map(|(name, _)| if *name == target { 1.0 } else { 0.0 }).collect()
+            }
+            BlendshapeSet::Arkit => {
+                let smile = mouth_form(viseme).max(0.0) * 0.6;
+                ARKIT
+                    .iter()
+                    .map(|(name, _, _)| match *name {
+                        "jawOpen" => mouth_openness(viseme),
+                        "mouthClose" if viseme == "BM" => 1.0,
+                        "mouthFunnel" if viseme == "O" => 0.7,
+                        "mouthFunnel" if viseme == "SH" || viseme == "CH" => 0.5,
+                        "mouthPucker" if viseme == "W" => 0.9,
+                        "mouthPucker" if viseme == "O" => 0.3,
+                        "mouthSmileLeft" | "mouthSmileRight" => smile,
+                        "mouthRollLower" if viseme == "FV" => 0.7,
+                        _ => 0.0,
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Openness and form of each target, for shaping the preview mesh
+    fn preview_shapes(&self) -> Vec<(f32, f32)> {
+        match self {
+            BlendshapeSet::Visemes => VISEMES.iter().map(|v| (mouth_openness(v), mouth_form(v))).collect(),
+            BlendshapeSet::Oculus => OCULUS.iter().map(|(_, v)| (mouth_openness(v), mouth_form(v))).collect(),
+            BlendshapeSet::Arkit => ARKIT.iter().map(|(_, open, form)| (*open, *form)).collect(),
+        }
+    }
+}
+
+/// Weight keyframes for a viseme track: each viseme's weights are held over its event, blending briefly
+/// into the next, and every target rests at 0.0 at the start and at `duration`. Times strictly increase.
+pub fn weight_keyframes(visemes: &[VisemeEvent], set: BlendshapeSet, duration: f32) -> Vec<(f32, Vec<f32>)> {
+    let rest = vec![0.0; set.target_names().len()];
+    let mut keys: Vec<(f32, Vec<f32>)> = vec![(0.0, rest.clone())];
+    let push = |keys: &mut Vec<(f32, Vec<f32>)>, time: f32, weights: Vec<f32>| {
+        if time > keys.last().unwrap().0 + 1e-4 {
+            keys.push((time, weights));
+        }
+    };
+    for event in visemes {
+        let weights = set.weights(&event.viseme);
+        let blend = BLEND_SECONDS.min((event.end - event.start) / 2.0);
+        push(&mut keys, event.start + blend, weights.clone());
+        push(&mut keys, event.end - blend, weights);
+    }
+    let end = duration.max(keys.last().unwrap().0 + BLEND_SECONDS);
+    push(&mut keys, end, rest);
+    keys
+}
+
+/// Rim of the preview mouth for an openness and form: a slit when closed, wider when smiling,
+/// narrower when rounded, taller when open
+fn mouth_rim(open: f32, form: f32) -> Vec<[f32; 3]> {
+    let half_width = 0.025 * (1.0 + 0.3 * form);
+    let half_height = 0.02 * (0.15 + 0.85 * open).max(0.0);
+    (0..RIM_POINTS)
+        .map(|i| {
+            let angle = i as f32 / RIM_POINTS as f32 * std::f32::consts::TAU;
+            [angle.cos() * half_width, angle.sin() * half_height, 0.0]
+        })
+        .collect()
+}
+
+fn push_f32s(buffer: &mut Vec<u8>, values: impl IntoIterator<Item = f32>) {
+    for value in values {
+        buffer.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn bounds(points: &[[f32; 3]]) -> (Vec<f32>, Vec<f32>) {
+    let mut min = vec![f32::INFINITY; 3];
+    let mut max = vec![f32::NEG_INFINITY; 3];
+    for point in points {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(point[axis]);
+            max[axis] = max[axis].max(point[axis]);
+        }
+    }
+    (min, max)
+}
+
+/// Build a binary glTF (`.glb`): a placeholder mouth mesh with one morph target per blendshape in `set`
+/// (named in `extras.targetNames`, which Blender, three.js, and Unity read), and a `lipsync` animation
+/// keying its weights. Import the animation onto your own mesh with matching target names, or open the
+/// file as is in any glTF viewer to preview the timing.
+pub fn lipsync_glb(visemes: &[VisemeEvent], set: BlendshapeSet, duration: f32, audio_path: Option<&str>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let names = set.target_names();
+    let keys = weight_keyframes(visemes, set, duration);
+
+    // Fan of triangles around the centre of the mouth
+    let base_rim = mouth_rim(0.0, 0.0);
+    let mut positions = vec![[0.0f32; 3]];
+    positions.extend(base_rim.iter().copied());
+    let indices: Vec<u16> = (0..RIM_POINTS as u16).flat_map(|i| [0, 1 + i, 1 + (i + 1) % RIM_POINTS as u16]).collect();
+
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut views: Vec<Value> = Vec::new();
+    let mut accessors: Vec<Value> = Vec::new();
+    // Append a float view and its accessor, returning the accessor index
+    let mut add = |buffer: &mut Vec<u8>, data: Vec<f32>, kind: &str, count: usize, min: Value, max: Value, target: Option<u32>| {
+        let offset = buffer.len();
+        push_f32s(buffer, data);
+        let mut view = json!({ "buffer": 0, "byteOffset": offset, "byteLength": buffer.len() - offset });
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+        views.push(view);
+        accessors.push(json!({ "bufferView": views.len() - 1, "componentType": 5126, "count": count, "type": kind, "min": min, "max": max }));
+        accessors.len() - 1
+    };
+    const ARRAY_BUFFER: u32 = 34962;
+
+    let (min, max) = bounds(&positions);
+    let position = add(&mut buffer, positions.iter().flatten().copied().collect(), "VEC3", positions.len(), json!(min), json!(max), Some(ARRAY_BUFFER));
+    let mut targets = Vec::new();
+    for (open, form) in set.preview_shapes() {
+        let offsets: Vec<[f32; 3]> = std::iter::once([0.0; 3])
+            .chain(mouth_rim(open, form).iter().zip(&base_rim).map(|(p, b)| [p[0] - b[0], p[1] - b[1], 0.0]))
+            .collect();
+        let (min, max) = bounds(&offsets);
+        let accessor = add(&mut buffer, offsets.iter().flatten().copied().collect(), "VEC3", offsets.len(), json!(min), json!(max), Some(ARRAY_BUFFER));
+        targets.push(json!({ "POSITION": accessor }));
+    }
+    let (first, last) = (keys.first().map_or(0.0, |k| k.0), keys.last().map_or(0.0, |k| k.0));
+    let times = add(&mut buffer, keys.iter().map(|k| k.0).collect(), "SCALAR", keys.len(), json!([first]), json!([last]), None);
+    let weights: Vec<f32> = keys.iter().flat_map(|k| k.1.iter().copied()).collect();
+    let weight_count = weights.len();
+    let weights = add(&mut buffer, weights, "SCALAR", weight_count, json!([0.0]), json!([1.0]), None);
+
+    let index_offset = buffer.len();
+    for index in &indices {
+        buffer.extend_from_slice(&index.to_le_bytes());
+    }
+    views.push(json!({ "buffer": 0, "byteOffset": index_offset, "byteLength": buffer.len() - index_offset, "target": 34963 }));
+    accessors.push(json!({ "bufferView": views.len() - 1, "componentType": 5123, "count": indices.len(), "type": "SCALAR" }));
+    let index_accessor = accessors.len() - 1;
+    while !buffer.len().is_multiple_of(4) {
+        buffer.push(0);
+    }
+
+    let gltf = json!({
+        "asset": { "version": "2.0", "generator": "text-to-face" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "name": "Mouth", "mesh": 0 }],
+        "meshes": [{
+            "name": "Mouth",
+            "primitives": [{ "attributes": { "POSITION": position }, "indices": index_accessor, "targets": targets }],
+            "weights": vec![0.0; names.len()],
+            "extras": { "targetNames": names },
+        }],
+        "animations": [{
+            "name": "lipsync",
+            "samplers": [{ "input": times, "output": weights, "interpolation": "LINEAR" }],
+            "channels": [{ "sampler": 0, "target": { "node": 0, "path": "weights" } }],
+            "extras": { "audio": audio_path },
+        }],
+        "buffers": [{ "byteLength": buffer.len() }],
+        "bufferViews": views,
+        "accessors": accessors,
+    });
+
+    let mut json_chunk = serde_json::to_vec(&gltf)?;
+    while !json_chunk.len().is_multiple_of(4) {
+        json_chunk.push(b' ');
+    }
+    let total = 12 + 8 + json_chunk.len() + 8 + buffer.len();
+    let mut glb = Vec::with_capacity(total);
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(total as u32).to_le_bytes());
+    glb.extend_from_slice(&(json_chunk.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(&json_chunk);
+    glb.extend_from_slice(&(buffer.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"BIN\0");
+    glb.extend_from_slice(&buffer);
+    Ok(glb)
+}
+
+/// Write a viseme track lasting `duration` seconds as a binary glTF morph-target animation
+pub fn write_gltf_animation(path: &str, visemes: &[VisemeEvent], set: BlendshapeSet, duration: f32, audio_path: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::write(path, lipsync_glb(visemes, set, duration, audio_path)?)?;
+    Ok(())
+}
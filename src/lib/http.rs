@@ -0,0 +1,152 @@
+//! Built-in HTTP client for voice, lockfile, and CMUdict downloads and the Ollama API.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Tries per download before giving up; connection failures and server errors are retried
+const ATTEMPTS: u32 = 3;
+
+type HttpResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+fn client() -> HttpResult<reqwest::blocking::Client> {
+    Ok(reqwest::blocking::Client::builder()
+        .user_agent(format!("text-to-face/{}", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_secs(30))
+        // Voice models are tens of megabytes and Ollama can take a while to answer, so no overall limit
+        .timeout(None)
+        .build()?)
+}
+
+/// Whether a failed request is worth repeating: the server or the network, not the request itself
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.status().is_none_or(|status| status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS)
+}
+
+/// Run `request` up to [`ATTEMPTS`] times, waiting a little longer after each transient failure
+fn with_retries<T>(what: &str, mut request: impl FnMut() -> reqwest::Result<T>) -> HttpResult<T> {
+    let mut attempt = 1;
+    loop {
+        match request() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < ATTEMPTS && is_transient(&e) => {
+                log::warn!("[Download] {} failed ({}), retrying ({}/{})...", what, e, attempt + 1, ATTEMPTS);
+                std::thread::sleep(Duration::from_secs(attempt as u64));
+                attempt += 1;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// GET `url` as text. HTTP errors (404 and the like) are errors, not response bodies.
+pub fn get_text(url: &str) -> HttpResult<String> {
+    let client = client()?;
+    with_retries(url, || client.get(url).send()?.error_for_status()?.text())
+}
+
+/// POST `body` as JSON to `url` and parse the JSON response. Tried once: a server that isn't
+/// running fails fast.
+pub fn post_json(url: &str, body: &serde_json::Value) -> HttpResult<serde_json::Value> {
+    Ok(client()?.post(url).json(body).send()?.error_for_status()?.json()?)
+}
+
+pub use crate::progress::DownloadProgress;
+
+/// Called as downloads progress, at most a few times a second; `None` (the default) reports nothing.
+/// Downloads are also reported to the [`crate::progress`] handler.
+static PROGRESS_HANDLER: Mutex<Option<fn(&DownloadProgress)>> = Mutex::new(None);
+
+pub fn set_progress_handler(handler: Option<fn(&DownloadProgress)>) {
+    *PROGRESS_HANDLER.lock().unwrap() = handler;
+}
+
+/// Reports a download's progress to the [`set_progress_handler`] callback and the
+/// [`crate::progress`] handler, throttled
+struct Progress<'a> {
+    label: &'a str,
+    total: Option<u64>,
+    done: u64,
+    reported: Option<Instant>,
+    handler: Option<fn(&DownloadProgress)>,
+}
+
+impl Progress<'_> {
+    fn report(&mut self, finished: bool) {
+        if self.handler.is_none() && !crate::progress::is_reporting() {
+            return;
+        }
+        if !finished && self.reported.is_some_and(|t| t.elapsed() < Duration::from_millis(200)) {
+            return;
+        }
+        let progress = DownloadProgress { label: self.label, done: self.done, total: self.total, finished };
+        if let Some(handler) = self.handler {
+            handler(&progress);
+        }
+        crate::progress::download(&progress);
+        self.reported = Some(Instant::now());
+    }
+}
+
+/// Partial file a download is written to before it's complete
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+/// Stream `response` into `path`, reporting progress
+fn save(response: reqwest::blocking::Response, path: &Path, label: &str) -> HttpResult<()> {
+    let handler = *PROGRESS_HANDLER.lock().unwrap();
+    let mut progress = Progress { label, total: response.content_length(), done: 0, reported: None, handler };
+    let mut response = response;
+    let mut file = fs::File::create(path)?;
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = response.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        file.write_all(&buffer[..read])?;
+        crate::cancel::check()?;
+        progress.done += read as u64;
+        progress.report(false);
+    }
+    progress.report(true);
+    if let Some(total) = progress.total
+        && progress.done != total
+    {
+        return Err(format!("connection closed after {} of {} bytes", progress.done, total).into());
+    }
+    Ok(())
+}
+
+/// Download `url` to `path`, reporting progress as `label`. Connection failures, server errors, and
+/// cut-off transfers are retried. The file is written beside `path` and moved into place once
+/// complete, so a failed or cancelled download never leaves a truncated model behind.
+pub fn download(url: &str, path: &Path, label: &str) -> crate::error::Result<()> {
+    let failed = |message: String| crate::Error::DownloadFailed { url: url.to_string(), message };
+    let client = client().map_err(|e| failed(e.to_string()))?;
+    let partial = partial_path(path);
+    let mut attempt = 1;
+    loop {
+        let saved = with_retries(url, || client.get(url).send()?.error_for_status()).and_then(|response| save(response, &partial, label));
+        match saved {
+            Ok(()) => return Ok(fs::rename(&partial, path)?),
+            Err(e) => {
+                let _ = fs::remove_file(&partial);
+                if matches!(crate::Error::find(&*e), Some(crate::Error::Cancelled)) {
+                    return Err(crate::Error::Cancelled);
+                }
+                // A transfer cut off part way is worth another go too
+                if attempt >= ATTEMPTS || e.downcast_ref::<reqwest::Error>().is_some() {
+                    return Err(failed(e.to_string()));
+                }
+                log::warn!("[Download] {}: {}, retrying ({}/{})...", url, e, attempt + 1, ATTEMPTS);
+                attempt += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Sentence segmentation and per-sentence prosody variation (`--humanize`): each sentence gets a slightly
+//! different pitch and tempo, so long narrations sound less monotone. The variation for sentence N is
+//! always the same, so re-rendering a text gives the same audio.
+
+use std::sync::Mutex;
+
+/// Seed of the sentence variation; sentence N draws from `SEED + N`
+pub const SEED: u64 = 0x6875_6d61_6e69_7a65;
+
+/// Largest `--humanize` amount; beyond this the voice starts to wobble rather than vary
+pub const MAX_AMOUNT: f32 = 0.25;
+
+/// Words that end in a full stop without ending the sentence
+const ABBREVIATIONS: &[&str] = &["mr", "mrs", "ms", "dr", "prof", "st", "sr", "jr", "mt", "vs", "etc", "e.g", "i.e", "a.m", "p.m", "no", "fig", "approx"];
+
+/// Pitch and tempo multipliers for one sentence, on top of the text's own
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SentenceProsody {
+    pub pitch: f32,
+    pub tempo: f32,
+}
+
+impl SentenceProsody {
+    pub const NONE: SentenceProsody = SentenceProsody { pitch: 1.0, tempo: 1.0 };
+}
+
+/// Set from `--humanize`; 0 leaves every sentence as the voice reads it
+static AMOUNT: Mutex<f32> = Mutex::new(0.0);
+
+pub fn set_amount(amount: f32) {
+    *AMOUNT.lock().unwrap() = amount.clamp(0.0, MAX_AMOUNT);
+}
+
+pub fn amount() -> f32 {
+    *AMOUNT.lock().unwrap()
+}
+
+pub fn is_enabled() -> bool {
+    amount() > 0.0
+}
+
+/// `humanize=0.05`, for sidecars, or `None` when off
+pub fn describe() -> Option<String> {
+    is_enabled().then(|| format!("humanize={}", amount()))
+}
+
+/// Parse a `--humanize` amount, e.g. `0.05` for up to ±5% pitch
+pub fn parse_amount(s: &str) -> Result<f32, String> {
+    let amount: f32 = s.trim().parse().map_err(|_| format!("Invalid humanize amount '{}': use e.g. 0.05", s))?;
+    if !(0.0..=MAX_AMOUNT).contains(&amount) {
+        return Err(format!("Humanize amount '{}' must be between 0 and {}", s, MAX_AMOUNT));
+    }
+    Ok(amount)
+}
+
+/// Whether the full stop ending `before` is part of an abbreviation or an initial (`J. R. R.`)
+fn is_abbreviation(before: &str) -> bool {
+    let word = before.rsplit(|c: char| c.is_whitespace() || c == '(' || c == '"').next().unwrap_or("");
+    let mut letters = word.chars();
+    if let (Some(first), None) = (letters.next(), letters.next())
+        && first.is_uppercase()
+    {
+        return true;
+    }
+    ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+}
+
+/// Split text into sentences at `.`, `!`, `?`, and `…` followed by whitespace, keeping closing quotes and
+/// brackets with their sentence. Abbreviations such as `Dr.` and initials don't end a sentence.
+pub fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if !matches!(c, '.' | '!' | '?' | '…') {
+            continue;
+        }
+        let mut end = i + c.len_utf8();
+        while let Some(&(j, next)) = chars.peek() {
+            if !matches!(next, '.' | '!' | '?' | '…' | '"' | '\'' | '”' | '’' | ')' | ']') {
+                break;
+            }
+            end = j + next.len_utf8();
+            chars.next();
+        }
+        let at_break = chars.peek().is_none_or(|&(_, next)| next.is_whitespace());
+        if !at_break || (c == '.' && end == i + 1 && is_abbreviation(&text[start..i])) {
+            continue;
+        }
+        let sentence = text[start..end].trim();
+        if !sentence.is_empty() {
+            sentences.push(sentence.to_string());
+        }
+        start = end;
+    }
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest.to_string());
+    }
+    sentences
+}
+
+/// The variation for sentence `index` (0-based) at `amount`: pitch within ±amount and tempo within
+/// half that, so the pace stays steady while the intonation moves
+pub fn sentence_prosody(index: usize, amount: f32) -> SentenceProsody {
+    if amount <= 0.0 {
+        return SentenceProsody::NONE;
+    }
+    let mut state = (index as u64).wrapping_add(SEED);
+    let mut jitter = |amount: f32| 1.0 + (crate::takes::split_mix(&mut state) * 2.0 - 1.0) * amount;
+    SentenceProsody { pitch: jitter(amount), tempo: jitter(amount / 2.0) }
+}
+
+/// Apply sentence `index`'s variation at the current [`amount`] to its samples
+pub fn vary(samples: Vec<f32>, sample_rate: u32, index: usize) -> Vec<f32> {
+    let prosody = sentence_prosody(index, amount());
+    let rate = sample_rate as usize;
+    let samples = if (prosody.pitch - 1.0).abs() < 0.005 { samples } else { crate::true_pitch_shift(&samples, rate, prosody.pitch) };
+    if (prosody.tempo - 1.0).abs() < 0.005 { samples } else { crate::time_stretch(&samples, rate, prosody.tempo) }
+}
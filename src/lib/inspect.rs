@@ -0,0 +1,122 @@
+use serde::Serialize;
+use std::path::Path;
+
+/// Format and level statistics of a WAV file
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioStats {
+    pub duration: f32,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    /// Highest absolute sample level in dBFS
+    pub peak_dbfs: f32,
+    /// RMS level in dBFS
+    pub rms_dbfs: f32,
+}
+
+/// What a pitch-tts JSON artifact contains
+#[derive(Debug, Clone, Serialize)]
+pub struct JsonStats {
+    /// `lipsync`, `karaoke`, `sidecar`, `live2d-motion`, `transcript-report`, or `unknown`
+    pub kind: &'static str,
+    /// Schema version, when the format carries one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub word_count: Option<usize>,
+    /// End of the last timed word, or the motion length
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f32>,
+    /// Fraction of words that have ARPAbet phonemes (lipsync only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phoneme_coverage: Option<f32>,
+}
+
+/// Summary of an audio or JSON artifact
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Inspection {
+    Audio(AudioStats),
+    Json(JsonStats),
+}
+
+fn to_dbfs(level: f32) -> f32 {
+    if level > 0.0 { 20.0 * level.log10() } else { f32::NEG_INFINITY }
+}
+
+/// Read a WAV file of any sample format and measure its levels
+pub fn inspect_wav(path: &str) -> Result<AudioStats, Box<dyn std::error::Error>> {
+    let reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.into_samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader.into_samples::<i32>().map(|s| s.map(|s| s as f32 / scale)).collect::<Result<_, _>>()?
+        }
+    };
+    let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    let rms = if samples.is_empty() {
+        0.0
+    } else {
+        (samples.iter().map(|s| (*s as f64).powi(2)).sum::<f64>() / samples.len() as f64).sqrt() as f32
+    };
+    let frames = samples.len() / spec.channels.max(1) as usize;
+    Ok(AudioStats {
+        duration: frames as f32 / spec.sample_rate as f32,
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+        bits_per_sample: spec.bits_per_sample,
+        peak_dbfs: to_dbfs(peak),
+        rms_dbfs: to_dbfs(rms),
+    })
+}
+
+/// Work out which artifact a JSON document is and summarize it
+pub fn inspect_json_value(value: &serde_json::Value) -> JsonStats {
+    let mut stats = JsonStats { kind: "unknown", schema_version: None, word_count: None, duration: None, phoneme_coverage: None };
+    let array_len = |key: &str| value.get(key).and_then(|v| v.as_array()).map(|a| a.len());
+    let last_end = |key: &str| {
+        value.get(key).and_then(|v| v.as_array()).and_then(|a| a.iter().rev().find_map(|w| w.get("end")?.as_f64())).map(|e| e as f32)
+    };
+
+    if let Some(words) = value.get("word_segments").and_then(|w| w.as_array()) {
+        // WhisperX output carries no version of its own
+        stats.kind = "lipsync";
+        stats.word_count = Some(words.len());
+        stats.duration = last_end("word_segments");
+        let with_phonemes = words
+            .iter()
+            .filter(|w| w.get("phonemes").and_then(|p| p.as_array()).is_some_and(|p| !p.is_empty()))
+            .count();
+        stats.phoneme_coverage = Some(if words.is_empty() { 0.0 } else { with_phonemes as f32 / words.len() as f32 });
+    } else if let Some(version) = value.get("schema_version") {
+        stats.kind = "sidecar";
+        stats.schema_version = Some(version.to_string());
+        stats.word_count = value.get("text").and_then(|t| t.as_str()).map(|t| t.split_whitespace().count());
+    } else if value.get("words").is_some() && value.get("sentences").is_some() {
+        stats.kind = "karaoke";
+        stats.word_count = array_len("words");
+        stats.duration = last_end("words");
+    } else if let (Some(version), Some(meta)) = (value.get("Version"), value.get("Meta")) {
+        stats.kind = "live2d-motion";
+        stats.schema_version = Some(version.to_string());
+        stats.duration = meta.get("Duration").and_then(|d| d.as_f64()).map(|d| d as f32);
+    } else if value.get("mismatches").is_some() {
+        stats.kind = "transcript-report";
+        stats.word_count = value.get("input_words").and_then(|w| w.as_u64()).map(|w| w as usize);
+    }
+    stats
+}
+
+/// Inspect a `.wav` or `.json` file
+pub fn inspect_file(path: &str) -> Result<Inspection, Box<dyn std::error::Error>> {
+    match Path::new(path).extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+        Some("wav") => Ok(Inspection::Audio(inspect_wav(path)?)),
+        Some("json") => {
+            let value: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+            Ok(Inspection::Json(inspect_json_value(&value)))
+        }
+        _ => Err(format!("Don't know how to inspect {} (expected .wav or .json)", path).into()),
+    }
+}
@@ -0,0 +1,163 @@
+use crate::WordTiming;
+use serde::Serialize;
+
+/// A word for read-along highlighting.
+/// `charOffsets` are `[start, end)` offsets into the original text in UTF-16 code units,
+/// so they can be passed straight to JavaScript's `String.prototype.slice`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KaraokeWord {
+    pub word: String,
+    pub start: f32,
+    pub end: f32,
+    pub char_offsets: [usize; 2],
+    /// Index into `sentences`
+    pub sentence: usize,
+}
+
+/// A sentence grouping consecutive words
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KaraokeSentence {
+    pub text: String,
+    pub start: f32,
+    pub end: f32,
+    pub char_offsets: [usize; 2],
+}
+
+/// Word highlight data for web and e-reader players
+#[derive(Debug, Clone, Serialize)]
+pub struct KaraokeDocument {
+    pub text: String,
+    pub words: Vec<KaraokeWord>,
+    pub sentences: Vec<KaraokeSentence>,
+}
+
+/// A whitespace-separated token of the source text with its UTF-16 offsets
+struct Token {
+    normalized: String,
+    start: usize,
+    end: usize,
+    ends_sentence: bool,
+}
+
+fn normalize(word: &str) -> String {
+    word.chars().filter(|c| c.is_alphanumeric()).flat_map(|c| c.to_lowercase()).collect()
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut utf16_pos = 0;
+    let mut current: Option<(usize, String)> = None;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if let Some((start, raw)) = current.take() {
+                tokens.push(make_token(&raw, start, utf16_pos));
+            }
+        } else {
+            current.get_or_insert_with(|| (utf16_pos, String::new())).1.push(c);
+        }
+        utf16_pos += c.len_utf16();
+    }
+    if let Some((start, raw)) = current.take() {
+        tokens.push(make_token(&raw, start, utf16_pos));
+    }
+    tokens
+}
+
+fn make_token(raw: &str, start: usize, end: usize) -> Token {
+    let trimmed = raw.trim_end_matches(['"', '\'', ')', ']', '”', '’']);
+    Token {
+        normalized: normalize(raw),
+        start,
+        end,
+        ends_sentence: trimmed.ends_with(['.', '!', '?', '…']),
+    }
+}
+
+/// Slice `text` by UTF-16 offsets
+pub(crate) fn slice_utf16(text: &str, start: usize, end: usize) -> String {
+    let units: Vec<u16> = text.encode_utf16().collect();
+    String::from_utf16_lossy(&units[start.min(units.len())..end.min(units.len())])
+}
+
+/// Match aligned words back onto the source text and group them into sentences
+pub fn build_karaoke(text: &str, timings: &[WordTiming]) -> KaraokeDocument {
+    let tokens = tokenize(text);
+    let mut words = Vec::with_capacity(timings.len());
+    let mut sentences: Vec<KaraokeSentence> = Vec::new();
+    let mut cursor = 0;
+    let mut sentence_start: Option<(usize, f32)> = None;
+
+    for timing in timings {
+        let target = normalize(&timing.word);
+        // Look a few tokens ahead so a skipped or split word doesn't derail the rest of the text
+        let matched = tokens
+            .iter()
+            .enumerate()
+            .skip(cursor)
+            .take(5)
+            .find(|(_, t)| t.normalized == target)
+            .map(|(i, _)| i)
+            .or(if cursor < tokens.len() { Some(cursor) } else { None });
+
+        let Some(index) = matched else {
+            // Ran out of source text; keep the word with an empty range at the end, in the sentence that's
+            // still open or else the last one
+            let end = text.encode_utf16().count();
+            let sentence = match sentences.last_mut() {
+                Some(last) if sentence_start.is_none() => {
+                    last.end = last.end.max(timing.end);
+                    sentences.len() - 1
+                }
+                _ => {
+                    sentence_start.get_or_insert((end, timing.start));
+                    sentences.len()
+                }
+            };
+            words.push(KaraokeWord { word: timing.word.clone(), start: timing.start, end: timing.end, char_offsets: [end, end], sentence });
+            continue;
+        };
+        let token = &tokens[index];
+        cursor = index + 1;
+
+        let (sentence_char_start, sentence_time_start) = *sentence_start.get_or_insert((token.start, timing.start));
+        words.push(KaraokeWord {
+            word: timing.word.clone(),
+            start: timing.start,
+            end: timing.end,
+            char_offsets: [token.start, token.end],
+            sentence: sentences.len(),
+        });
+
+        if token.ends_sentence {
+            sentences.push(KaraokeSentence {
+                text: slice_utf16(text, sentence_char_start, token.end),
+                start: sentence_time_start,
+                end: timing.end,
+                char_offsets: [sentence_char_start, token.end],
+            });
+            sentence_start = None;
+        }
+    }
+
+    // Close a trailing sentence without terminal punctuation
+    if let (Some((char_start, time_start)), Some(last)) = (sentence_start, words.last()) {
+        sentences.push(KaraokeSentence {
+            text: slice_utf16(text, char_start, last.char_offsets[1]),
+            start: time_start,
+            end: last.end,
+            char_offsets: [char_start, last.char_offsets[1]],
+        });
+    }
+
+    KaraokeDocument { text: text.to_string(), words, sentences }
+}
+
+/// Write karaoke highlight JSON built from a lipsync JSON file
+pub fn write_karaoke_json(text: &str, lipsync_json: &str, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let timings = crate::read_word_timings(lipsync_json)?;
+    let document = build_karaoke(text, &timings);
+    std::fs::write(output_path, serde_json::to_string_pretty(&document)?)?;
+    Ok(())
+}
@@ -1,24 +1,174 @@
+#[cfg(feature = "full")]
 use piper_rs::synth::PiperSpeechSynthesizer;
 use std::fs;
-use std::process::Command;
+#[cfg(feature = "full")]
 use std::path::Path;
+#[cfg(feature = "full")]
 use std::collections::HashMap;
+#[cfg(feature = "full")]
 use once_cell::sync::Lazy;
 use serde::{Serialize, Deserialize};
 use rubato::{FftFixedIn, Resampler};
 
+#[cfg(feature = "full")]
 use clap::ValueEnum;
+#[cfg(feature = "full")]
 use colored::*;
-use directories::ProjectDirs;
+#[cfg(feature = "full")]
 use std::fs::OpenOptions;
+#[cfg(feature = "full")]
 use std::io::Write;
+pub use error::Error;
+#[cfg(feature = "tokio")]
+pub use async_api::{download_voice_async, synth_async};
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub mod abbreviations;
+pub mod accelerator;
+#[cfg(feature = "full")]
+pub mod alignment;
+#[cfg(feature = "tokio")]
+pub mod async_api;
+#[cfg(feature = "full")]
+pub mod audiobook;
+pub mod backend;
+#[cfg(feature = "full")]
+pub mod batch;
+pub mod blender;
+pub mod cancel;
+#[cfg(feature = "full")]
+pub mod catalog;
+pub mod coverage;
+#[cfg(feature = "full")]
+pub mod daemon;
+#[cfg(feature = "full")]
+pub mod data_dir;
+#[cfg(feature = "full")]
+pub mod devices;
+pub mod dialogue;
+pub mod directives;
+pub mod effects;
+pub mod emoji;
+pub mod emphasis;
+pub mod dsp;
+pub mod encode;
+pub mod enrich;
+pub mod error;
+pub mod estimate;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+pub mod forced_align;
+#[cfg(feature = "full")]
+pub mod gallery;
+pub mod gltf;
+#[cfg(feature = "full")]
+pub mod http;
+pub mod humanize;
+pub mod inspect;
+pub mod karaoke;
+pub mod limits;
+#[cfg(feature = "full")]
+pub mod lipsync_stream;
+pub mod live2d;
+pub mod llm_fallback;
+#[cfg(feature = "full")]
+pub mod local_voices;
+#[cfg(feature = "full")]
+pub mod low_memory;
+pub mod markdown;
+pub mod markers;
+pub mod mixing;
+#[cfg(feature = "full")]
+pub mod model_cache;
+pub mod nice;
+#[cfg(feature = "full")]
+pub mod morph;
+pub mod normalize;
+#[cfg(feature = "full")]
+pub mod opus;
+pub mod overrides;
+pub mod pause_markers;
+pub mod pauses;
+pub mod phoneme_sources;
+#[cfg(feature = "full")]
+pub mod pinning;
+pub mod piper_timings;
+#[cfg(feature = "full")]
+pub mod playback;
+#[cfg(feature = "full")]
+pub mod preview;
+pub mod progress;
+pub mod prosody;
+#[cfg(feature = "python")]
+mod python;
+pub mod rate;
+pub mod respelling;
+pub mod rhubarb;
+#[cfg(feature = "full")]
+pub mod rules;
+pub mod runtime;
+#[cfg(feature = "full")]
+pub mod server;
+#[cfg(feature = "full")]
+pub mod session;
+#[cfg(feature = "full")]
+pub mod sidecar;
+#[cfg(feature = "full")]
+pub mod singing;
+pub mod speakers;
+pub mod sprites;
+pub mod ssml;
+pub mod subtitles;
+pub mod stereo;
+#[cfg(feature = "full")]
+pub mod streaming;
+pub mod takes;
+pub mod tags;
+pub mod trim;
+pub mod unity;
+pub mod verify;
+pub mod visemes;
+#[cfg(feature = "full")]
+pub mod voicechanger;
+pub mod vmc;
+pub mod warnings;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "full", derive(ValueEnum))]
 pub enum LipsyncLevel {
     Low,
+    /// Word and phoneme timings estimated from average phoneme lengths, without WhisperX
+    Estimate,
+    /// Word and phoneme timings from the built-in forced aligner, without WhisperX
+    Align,
+    /// Exact word and phoneme timings from the voice's own duration predictor, without WhisperX
+    /// (voices exported with phoneme durations; others fall back to estimates)
+    Piper,
     High,
 }
 
+/// Lipsync files an export writes besides its own lipsync JSON
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "full", derive(ValueEnum))]
+pub enum LipsyncFormat {
+    /// Only the lipsync JSON
+    #[default]
+    Native,
+    /// Also Rhubarb Lip Sync mouth cues, as `<name>.rhubarb.tsv` and `<name>.rhubarb.json`
+    Rhubarb,
+}
+
+/// What a synthesis call produced besides its audio and files
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SynthesisReport {
+    /// Non-fatal problems, e.g. words without phonemes or clipped samples
+    pub warnings: Vec<warnings::Warning>,
+    /// The lipsync JSON, when lipsync ran without a file to write it to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lipsync_json: Option<serde_json::Value>,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct Voice {
     pub id: String,
@@ -43,6 +193,54 @@ pub struct LipSyncData {
     pub sample_rate: u32,
 }
 
+impl LipSyncData {
+    /// The same timings with every time multiplied by `factor`, e.g. after a time stretch
+    pub fn scaled(&self, factor: f32) -> LipSyncData {
+        LipSyncData {
+            phonemes: self.phonemes.iter().map(|p| Phoneme { phoneme: p.phoneme.clone(), start_time: p.start_time * factor, end_time: p.end_time * factor }).collect(),
+            duration: self.duration * factor,
+            sample_rate: self.sample_rate,
+        }
+    }
+}
+
+/// A single aligned word from a lipsync JSON (`word_segments` in WhisperX output)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WordTiming {
+    pub word: String,
+    pub start: f32,
+    pub end: f32,
+    /// ARPAbet phonemes, present in high-fidelity lipsync output
+    #[serde(default)]
+    pub phonemes: Vec<String>,
+}
+
+/// Read the word timings from a lipsync JSON file.
+/// Words WhisperX could not align (no `start`/`end`, e.g. numerals) are given the gap after the previous word.
+pub fn read_word_timings(json_path: &str) -> Result<Vec<WordTiming>, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(json_path)?;
+    let value: serde_json::Value = serde_json::from_str(&contents)?;
+    Ok(word_timings(&value).ok_or_else(|| format!("{} has no word_segments", json_path))?)
+}
+
+/// The word timings of a lipsync JSON value, as [`read_word_timings`] reads them, or `None` without `word_segments`
+pub fn word_timings(value: &serde_json::Value) -> Option<Vec<WordTiming>> {
+    let segments = value.get("word_segments")?.as_array()?;
+    let mut words: Vec<WordTiming> = Vec::with_capacity(segments.len());
+    for segment in segments {
+        let word = segment.get("word").and_then(|w| w.as_str()).unwrap_or_default().to_string();
+        let previous_end = words.last().map(|w| w.end).unwrap_or(0.0);
+        let start = segment.get("start").and_then(|v| v.as_f64()).map(|v| v as f32).unwrap_or(previous_end);
+        let end = segment.get("end").and_then(|v| v.as_f64()).map(|v| v as f32).unwrap_or(start);
+        let phonemes = segment
+            .get("phonemes")
+            .and_then(|p| serde_json::from_value::<Vec<String>>(p.clone()).ok())
+            .unwrap_or_default();
+        words.push(WordTiming { word, start, end, phonemes });
+    }
+    Some(words)
+}
+
 #[derive(Clone, Debug)]
 pub enum PitchArg {
     Value(f32),
@@ -94,6 +292,7 @@ impl PitchArg {
     }
 }
 
+#[cfg(feature = "full")]
 const HF_BASE: &str = "https://huggingface.co/rhasspy/piper-voices/resolve/main";
 
 /// Pitch shift function using simple resampling
@@ -125,155 +324,83 @@ pub fn pitch_shift(samples: &[f32], pitch_factor: f32) -> Vec<f32> {
     shifted
 }
 
-/// High-quality pitch shift without speed change using SoX executable
+/// High-quality pitch shift without speed change (a phase vocoder, see [`dsp::pitch_shift`])
 pub fn true_pitch_shift(samples: &[f32], sample_rate: usize, pitch_factor: f32) -> Vec<f32> {
-    if (pitch_factor - 1.0).abs() < 0.01 {
-        return samples.to_vec();
-    }
-    
-    // Create temporary input and output files
-    let temp_input = "temp_input.wav";
-    let temp_output = "temp_output.wav";
-    
-    // Write input samples to WAV file
-    let spec = hound::WavSpec {
-        channels: 1,
-        sample_rate: sample_rate as u32,
-        bits_per_sample: 16,
-        sample_format: hound::SampleFormat::Int,
-    };
-    let mut writer = hound::WavWriter::create(temp_input, spec).expect("Failed to create temp WAV");
-    for sample in samples {
-        let sample_i16 = (sample * 32767.0).clamp(-32768.0, 32767.0) as i16;
-        writer.write_sample(sample_i16).expect("Failed to write sample");
-    }
-    writer.finalize().expect("Failed to finalize WAV");
-    
-    // Calculate pitch shift in cents (1200 cents per octave)
-    let cents = 1200.0 * pitch_factor.log2();
-    
-    // Use sox executable to pitch shift while keeping tempo the same
-    let output = Command::new("sox")
-        .arg(temp_input)
-        .arg(temp_output)
-        .arg("pitch")
-        .arg(&format!("{}", cents))
-        .output()
-        .expect("Failed to execute sox");
-    
-    if !output.status.success() {
-        eprintln!("SoX error: {}", String::from_utf8_lossy(&output.stderr));
-        // Clean up temp files
-        let _ = std::fs::remove_file(temp_input);
-        return samples.to_vec(); // Return original samples on error
-    }
-    
-    // Read the processed audio back
-    let reader = hound::WavReader::open(temp_output).expect("Failed to open output WAV");
-    let samples: Vec<f32> = reader.into_samples::<i16>()
-        .map(|s| s.expect("Failed to read sample") as f32 / 32767.0)
-        .collect();
-    
-    // Clean up temp files
-    let _ = std::fs::remove_file(temp_input);
-    let _ = std::fs::remove_file(temp_output);
-    
-    samples
+    dsp::pitch_shift(samples, sample_rate, pitch_factor)
 }
 
-/// Time-stretch function using rubato (tempo_factor > 1.0 = slower, < 1.0 = faster)
+/// Stretch audio to `tempo_factor` times its length (> 1.0 = slower, < 1.0 = faster). By default it's
+/// resampled with rubato, so the pitch moves with the speed like a tape; `--stretch-quality high` uses
+/// WSOLA instead, which keeps the pitch (see [`dsp::wsola_stretch`]).
 pub fn time_stretch(samples: &[f32], sample_rate: usize, tempo_factor: f32) -> Vec<f32> {
     if (tempo_factor - 1.0).abs() < 0.01 {
         return samples.to_vec(); // No stretch needed
     }
+    if dsp::stretch_quality() == dsp::StretchQuality::High {
+        return dsp::wsola_stretch(samples, sample_rate, tempo_factor);
+    }
     let channels = 1;
     let input_frame_length = 1024;
-    let _output_frame_length = (input_frame_length as f32 * tempo_factor) as usize;
-    let _fft_size = input_frame_length * 2;
+    // Resampled to more samples and played at the original rate, the audio lasts longer
+    let output_rate = ((sample_rate as f32 * tempo_factor) as usize).max(1);
     let mut resampler = FftFixedIn::<f32>::new(
         sample_rate, // sample_rate_input
-        (sample_rate as f32 / tempo_factor) as usize, // sample_rate_output
+        output_rate, // sample_rate_output
         input_frame_length, // chunk_size_in
         1, // sub_chunks
         channels, // nbr_channels
     ).expect("Failed to create resampler");
+    let delay = resampler.output_delay();
+    let wanted = (samples.len() as f64 * output_rate as f64 / sample_rate as f64).round() as usize;
     let mut output = Vec::new();
+    let mut progress = progress::SampleProgress::new(samples.len());
     let mut pos = 0;
-    while pos < samples.len() {
-        let end = (pos + input_frame_length).min(samples.len());
-        let mut chunk = samples[pos..end].to_vec();
-        if chunk.len() < input_frame_length {
-            chunk.resize(input_frame_length, 0.0);
-        }
-        let input = vec![chunk];
-        let result = resampler.process(&input, None).expect("Resample failed");
+    // Zeros are fed past the end of the input until the resampler's delay is flushed out
+    while output.len() < wanted + delay && !cancel::is_cancelled() {
+        progress.advance(pos.min(samples.len()));
+        let mut chunk: Vec<f32> = samples.iter().skip(pos).take(input_frame_length).copied().collect();
+        chunk.resize(input_frame_length, 0.0);
+        let result = resampler.process(&[chunk], None).expect("Resample failed");
         output.extend_from_slice(&result[0]);
         pos += input_frame_length;
     }
+    output.drain(..delay.min(output.len()));
+    output.truncate(wanted);
+    progress.finish();
     output
 }
 
+#[cfg(feature = "full")]
 /// Return type for ARPAbet lookup: (phonemes, method)
 type ArpabetResult = (Vec<String>, &'static str);
 
-/// Use Ollama to get ARPAbet phonemes for a word not found in CMUdict or g2p-en
-fn get_arpabet_from_ollama(word: &str, model: &str) -> Option<Vec<String>> {
-    // List of valid ARPAbet phonemes (no stress markers)
-    const ARPABET: &[&str] = &[
-        "AA", "AE", "AH", "AO", "AW", "AY", "B", "CH", "D", "DH", "EH", "ER", "EY", "F", "G", "HH", "IH", "IY", "JH", "K", "L", "M", "N", "NG", "OW", "OY", "P", "R", "S", "SH", "T", "TH", "UH", "UW", "V", "W", "Y", "Z", "ZH",
-        // With stress markers
-        "AA0", "AA1", "AA2", "AE0", "AE1", "AE2", "AH0", "AH1", "AH2", "AO0", "AO1", "AO2", "AW0", "AW1", "AW2", "AY0", "AY1", "AY2", "EH0", "EH1", "EH2", "ER0", "ER1", "ER2", "EY0", "EY1", "EY2", "IH0", "IH1", "IH2", "IY0", "IY1", "IY2", "OW0", "OW1", "OW2", "OY0", "OY1", "OY2", "UH0", "UH1", "UH2", "UW0", "UW1", "UW2"
-    ];
-    let valid: std::collections::HashSet<&str> = ARPABET.iter().copied().collect();
-
-    let prompt = format!(
-        "Give only the ARPAbet phonemes for the word '{}'. Respond ONLY with the ARPAbet phonemes, space-separated, no explanation, no punctuation, no extra words.\nExample: hello => HH AH0 L OW1\nNow, {} =>",
-        word, word
-    );
-    
-    match std::process::Command::new("ollama")
-        .arg("run")
-        .arg(model)
-        .arg(&prompt)
-        .output() {
-        Ok(output) if output.status.success() => {
-            let response = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if !response.is_empty() && !response.contains("error") && !response.contains("not found") {
-                let all_tokens: Vec<String> = response.split_whitespace().map(|s| s.to_string()).collect();
-                let filtered: Vec<String> = all_tokens.iter().filter(|s| valid.contains(s.as_str())).cloned().collect();
-                if filtered.is_empty() {
-                    println!("[ARPAbet] {} => {:?} (from Ollama/{}, but no valid ARPAbet tokens)", word, all_tokens, model);
-                } else if filtered.len() != all_tokens.len() {
-                    println!("[ARPAbet] {} => {:?} (filtered from {:?}, Ollama/{})", word, filtered, all_tokens, model);
-                } else {
-                    println!("[ARPAbet] {} => {:?} (from Ollama/{})", word, filtered, model);
-                }
-                if !filtered.is_empty() {
-                    return Some(filtered);
-                }
-            }
-        }
-        _ => {}
-    }
-    None
+#[cfg(feature = "full")]
+/// Where voices, CMUdict, and the user's rules, lexicon, and overrides are kept (see [`data_dir`])
+pub fn get_app_data_dir() -> std::path::PathBuf {
+    data_dir::data_dir()
 }
 
-pub fn get_app_data_dir() -> std::path::PathBuf {
-    if let Some(proj_dirs) = ProjectDirs::from("com", "yourorg", "text-to-face") {
-        proj_dirs.data_dir().to_path_buf()
-    } else {
-        std::path::PathBuf::from("models")
-    }
+/// Set from the config file's `models_dir`; voices are otherwise kept in the app data directory
+#[cfg(feature = "full")]
+static MODELS_DIR: std::sync::Mutex<Option<std::path::PathBuf>> = std::sync::Mutex::new(None);
+
+/// Keep voice models in `dir` instead of `<app data>/models`, or go back to the default for `None`
+#[cfg(feature = "full")]
+pub fn set_models_dir(dir: Option<std::path::PathBuf>) {
+    *MODELS_DIR.lock().unwrap() = dir;
 }
 
+#[cfg(feature = "full")]
 pub fn get_models_dir() -> std::path::PathBuf {
-    get_app_data_dir().join("models")
+    MODELS_DIR.lock().unwrap().clone().unwrap_or_else(|| get_app_data_dir().join("models"))
 }
 
+#[cfg(feature = "full")]
 pub fn get_cmudict_path() -> std::path::PathBuf {
     get_app_data_dir().join("cmudict-0.7b.txt")
 }
 
+#[cfg(feature = "full")]
 fn log_to_file(msg: &str) {
     let log_path = get_app_data_dir().join("text_to_face.log");
     if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(log_path) {
@@ -281,18 +408,19 @@ fn log_to_file(msg: &str) {
     }
 }
 
+#[cfg(feature = "full")]
 // Global cache for CMUdict - loaded once and reused
 static CMUDICT_CACHE: Lazy<HashMap<String, Vec<Vec<String>>>> = Lazy::new(|| {
-    println!("[ARPAbet] Loading CMUdict into memory...");
+    log::info!("[ARPAbet] Loading CMUdict into memory...");
     let dict_path = get_cmudict_path();
 
     if !dict_path.exists() {
-        println!("[ARPAbet] cmudict-0.7b.txt not found, downloading to extra/...");
+        log::info!("[ARPAbet] cmudict-0.7b.txt not found, downloading to {}...", dict_path.display());
         let url = "https://raw.githubusercontent.com/Alexir/CMUdict/master/cmudict-0.7b";
-        match std::process::Command::new("curl").arg("-L").arg("-o").arg(&dict_path).arg(url).status() {
-            Ok(status) if status.success() => println!("[ARPAbet] Downloaded cmudict-0.7b.txt to extra/"),
-            _ => {
-                eprintln!("[ARPAbet] Failed to download cmudict-0.7b.txt. Please download it manually.");
+        match http::download(url, &dict_path, "cmudict-0.7b") {
+            Ok(()) => log::info!("[ARPAbet] Downloaded cmudict-0.7b.txt to {}", dict_path.display()),
+            Err(e) => {
+                log::error!("[ARPAbet] {}. Please download it manually.", e);
                 return HashMap::new();
             }
         }
@@ -302,7 +430,7 @@ static CMUDICT_CACHE: Lazy<HashMap<String, Vec<Vec<String>>>> = Lazy::new(|| {
     let bytes = match std::fs::read(&dict_path) {
         Ok(bytes) => bytes,
         Err(e) => {
-            eprintln!("[ARPAbet] Failed to read cmudict file: {}", e);
+            log::error!("[ARPAbet] Failed to read cmudict file: {}", e);
             return HashMap::new();
         }
     };
@@ -312,7 +440,7 @@ static CMUDICT_CACHE: Lazy<HashMap<String, Vec<Vec<String>>>> = Lazy::new(|| {
     
     // Validate that this looks like a CMUdict file
     if !content.contains(";;; # CMUdict") {
-        eprintln!("[ARPAbet] File does not appear to be a valid CMUdict file");
+        log::error!("[ARPAbet] File does not appear to be a valid CMUdict file");
         return HashMap::new();
     }
     
@@ -344,57 +472,133 @@ static CMUDICT_CACHE: Lazy<HashMap<String, Vec<Vec<String>>>> = Lazy::new(|| {
         dict.entry(base_word.to_string()).or_insert_with(Vec::new).push(phonemes);
     }
     
-    println!("[ARPAbet] Loaded {} words from CMUdict (cached)", dict.len());
+    log::info!("[ARPAbet] Loaded {} words from CMUdict (cached)", dict.len());
     dict
 });
 
+#[cfg(feature = "full")]
+/// First CMUdict pronunciation of a word (case and surrounding punctuation are ignored), without logging
+pub fn cmudict_phonemes(word: &str) -> Option<Vec<String>> {
+    let word_upper = word.trim_matches(|c: char| !c.is_alphanumeric()).to_uppercase();
+    CMUDICT_CACHE.get(&word_upper)?.first().cloned()
+}
+
+#[cfg(feature = "full")]
 /// Given a text, return a Vec<(Vec<String>, &str)> of ARPAbet phonemes and method for each word.
-/// Uses CMUdict for known words, falls back to g2p-en, then Ollama for unknown words.
+/// Words with an inline respelling (`Hermione{her-MY-oh-nee}`) use the respelling; other words go
+/// through the sources in [`phoneme_sources::settings`] (by default the user lexicon, CMUdict, g2p-en,
+/// then Ollama) until one has them. The method is the name of the source that answered.
+/// The Ollama fallback follows [`llm_fallback::settings`].
 pub fn text_to_arpabet_with_method(text: &str, lipsync_with_llm: Option<&str>) -> Vec<ArpabetResult> {
-    let dict = &*CMUDICT_CACHE;
-    text.split_whitespace()
-        .map(|word| {
-            let word_upper = word.trim_matches(|c: char| !c.is_alphanumeric()).to_uppercase();
-            if let Some(pronunciations) = dict.get(&word_upper) {
-                if let Some(first_pronunciation) = pronunciations.first() {
-                    println!("{} {} => {:?} (from {})", "[ARPAbet]".cyan(), word_upper, first_pronunciation, "cmudict".bold().green());
-                    (first_pronunciation.clone(), "cmudict")
-                } else {
-                    println!("{} {} => [] (no pronunciations)", "[ARPAbet]".yellow(), word_upper);
-                    (vec![], "cmudict")
+    use phoneme_sources::PhonemeSource;
+    let settings = llm_fallback::settings();
+    let sources = phoneme_sources::settings().sources();
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut results: Vec<Option<ArpabetResult>> = vec![None; words.len()];
+    let mut plain_words = Vec::with_capacity(words.len());
+    for (i, word) in words.iter().enumerate() {
+        if let Some(respelled) = respelling::parse_word(word) {
+            match respelling::respelling_to_arpabet(&respelled.respelling) {
+                Ok(phonemes) => {
+                    log::info!("{} {} => {:?} (from {})", "[ARPAbet]".cyan(), respelled.word.to_uppercase(), phonemes, "respelling".bold().blue());
+                    results[i] = Some((phonemes, "respelling"));
                 }
-            } else if let Some(model) = lipsync_with_llm {
-                if !model.trim().is_empty() {
-                    if let Some(llm_phonemes) = get_arpabet_from_ollama(&word_upper, model) {
-                        println!("{} {} => {:?} (from {})", "[ARPAbet]".cyan(), word_upper, llm_phonemes, "llm".bold().magenta());
-                        (llm_phonemes, "llm")
-                    } else {
-                        println!("{} {} => [] (not found in CMUdict or Ollama/{})", "[ARPAbet]".red(), word_upper, model);
-                        eprintln!("{} All fallbacks failed for '{}'. Make sure Ollama is installed and the '{}' model is available:", "[ARPAbet]".red(), word_upper, model);
-                        eprintln!("  brew install ollama");
-                        eprintln!("  ollama pull {} (first-time download may take several minutes)", model);
-                        eprintln!("  We recommend using 'llama3.2' for best ARPAbet accuracy.");
-                        (vec![], "user_manual")
+                Err(reason) => warnings::warn(warnings::Warning::UnreadableRespelling { word: respelled.word, respelling: respelled.respelling, reason }),
+            }
+        }
+        plain_words.push(respelling::strip_respellings(word));
+    }
+    let upper = |word: &str| word.trim_matches(|c: char| !c.is_alphanumeric()).to_uppercase();
+
+    let mut llm_words = 0;
+    let mut llm_reason = None;
+    for source in &sources {
+        let pending: Vec<usize> = (0..words.len()).filter(|&i| results[i].is_none()).collect();
+        if pending.is_empty() {
+            break;
+        }
+        let found: Vec<(usize, Vec<String>)> = match source {
+            PhonemeSource::Lexicon => pending.iter().filter_map(|&i| Some((i, phoneme_sources::lexicon_phonemes(&plain_words[i])?))).collect(),
+            PhonemeSource::Cmudict => pending.iter().filter_map(|&i| Some((i, cmudict_phonemes(&plain_words[i])?))).collect(),
+            PhonemeSource::G2p => {
+                let mut lookup: Vec<String> = pending.iter().map(|&i| upper(&plain_words[i])).filter(|w| !w.is_empty()).collect();
+                lookup.dedup();
+                let g2p = phoneme_sources::g2p_phonemes(&lookup);
+                pending.iter().filter_map(|&i| Some((i, g2p.get(&upper(&plain_words[i]))?.clone()))).collect()
+            }
+            PhonemeSource::Llm => {
+                let Some(model) = lipsync_with_llm.filter(|m| !m.trim().is_empty()) else {
+                    continue;
+                };
+                let mut found = Vec::new();
+                for &i in &pending {
+                    if settings.max_words.is_some_and(|max| llm_words >= max) {
+                        llm_reason = Some(format!("LLM word limit ({}) reached for this run", llm_words));
+                        break;
+                    }
+                    llm_words += 1;
+                    match llm_fallback::arpabet_from_ollama(&upper(&plain_words[i]), model, &settings) {
+                        Some(phonemes) => found.push((i, phonemes)),
+                        None => llm_reason = Some(format!("no answer from Ollama/{} (is `ollama serve` running, with `ollama pull {}` done?)", model, model)),
                     }
-                } else {
-                    eprintln!("{} No phoneme data for '{}'. Rerun with --lipsync-with-llm <model> to enable LLM fallback.", "[ARPAbet]".red(), word);
-                    (vec![], "user_manual")
                 }
-            } else {
-                eprintln!("{} No phoneme data for '{}'. Rerun with --lipsync-with-llm <model> to enable LLM fallback.", "[ARPAbet]".red(), word);
-                (vec![], "user_manual")
+                found
             }
+        };
+        for (i, phonemes) in found {
+            let name = match source {
+                PhonemeSource::Lexicon => source.name().bold().yellow(),
+                PhonemeSource::Cmudict => source.name().bold().green(),
+                PhonemeSource::G2p => source.name().bold().cyan(),
+                PhonemeSource::Llm => source.name().bold().magenta(),
+            };
+            log::info!("{} {} => {:?} (from {})", "[ARPAbet]".cyan(), upper(&plain_words[i]), phonemes, name);
+            results[i] = Some((phonemes, source.name()));
+        }
+    }
+
+    let tried: Vec<&str> = sources.iter().map(|s| s.name()).collect();
+    results
+        .into_iter()
+        .zip(plain_words)
+        .map(|(result, word)| {
+            result.unwrap_or_else(|| {
+                log::info!("{} {} => [] (not found in {})", "[ARPAbet]".red(), upper(&word), tried.join(", "));
+                let reason = match (&llm_reason, lipsync_with_llm.filter(|m| !m.trim().is_empty())) {
+                    (Some(reason), _) => format!("not found in {}; {}", tried.join(", "), reason),
+                    (None, None) if sources.contains(&PhonemeSource::Llm) => format!("not found in {}; rerun with --lipsync-with-llm <model> to enable LLM fallback", tried.join(", ")),
+                    _ => format!("not found in {}", tried.join(", ")),
+                };
+                warnings::warn(warnings::Warning::OutOfVocabulary { word, reason });
+                (vec![], "user_manual")
+            })
         })
         .collect()
 }
 
+#[cfg(feature = "full")]
 /// For backward compatibility: just return the phonemes (no method)
 pub fn text_to_arpabet(text: &str, lipsync_with_llm: Option<&str>) -> Vec<Vec<String>> {
     text_to_arpabet_with_method(text, lipsync_with_llm).into_iter().map(|(p, _)| p).collect()
 }
 
-/// Get all available voices
+#[cfg(feature = "full")]
+/// Get all available voices: the catalog, then the registered local voices (see [`local_voices`])
 pub fn get_available_voices() -> Vec<Voice> {
+    let mut voices = catalog_voices();
+    voices.extend(local_voices::local_voices().iter().map(|(id, local)| local.voice(id)));
+    voices
+}
+
+#[cfg(feature = "full")]
+/// The built-in catalog with the user's manifest, if any, applied (see [`catalog`])
+pub fn catalog_voices() -> Vec<Voice> {
+    catalog::merged(builtin_voices())
+}
+
+#[cfg(feature = "full")]
+/// The upstream Piper voices
+pub fn builtin_voices() -> Vec<Voice> {
     let mut voices = Vec::new();
     
     // Helper function to add a voice
@@ -509,6 +713,7 @@ pub fn get_available_voices() -> Vec<Voice> {
     voices
 }
 
+#[cfg(feature = "full")]
 /// Get voices grouped by language
 pub fn get_voices_by_language() -> HashMap<String, Vec<Voice>> {
     let voices = get_available_voices();
@@ -521,11 +726,92 @@ pub fn get_voices_by_language() -> HashMap<String, Vec<Voice>> {
     by_language
 }
 
+#[cfg(feature = "full")]
+/// Files a voice can have in the models directory: its model and config, and any int8 variant
+const VOICE_FILE_SUFFIXES: [&str; 4] = ["onnx", "onnx.json", "int8.onnx", "int8.onnx.json"];
+
+/// Whether a voice ID is for `language`, given as a language (`en`) or a locale (`en_GB` or `en-GB`)
+pub fn voice_matches_language(voice_id: &str, language: &str) -> bool {
+    let locale = voice_id.split('-').next().unwrap_or_default();
+    let language = language.trim().replace('-', "_");
+    !language.is_empty() && (locale.eq_ignore_ascii_case(&language) || locale.split('_').next().is_some_and(|l| l.eq_ignore_ascii_case(&language)))
+}
+
+/// Narrows the voice catalog, e.g. for `list`. Unset fields match every voice.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VoiceFilter {
+    /// Language (`en`) or locale (`en_GB`), as for [`voice_matches_language`]
+    pub language: Option<String>,
+    /// `x_low`, `low`, `medium`, or `high`
+    pub quality: Option<String>,
+    /// Country code, e.g. `GB`
+    pub country: Option<String>,
+    /// Text anywhere in the ID, display name, or language name, ignoring case
+    pub search: Option<String>,
+}
+
+impl VoiceFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == VoiceFilter::default()
+    }
+
+    pub fn matches(&self, voice: &Voice) -> bool {
+        let country = voice.id.split('-').next().and_then(|locale| locale.split_once('_')).map(|(_, country)| country);
+        let search = self.search.as_deref().map(str::to_lowercase);
+        self.language.as_deref().is_none_or(|language| voice_matches_language(&voice.id, language))
+            && self.quality.as_deref().is_none_or(|quality| voice.quality.eq_ignore_ascii_case(&quality.replace('-', "_")))
+            && self.country.as_deref().is_none_or(|wanted| country.is_some_and(|c| c.eq_ignore_ascii_case(wanted.trim())))
+            && search.is_none_or(|search| [&voice.id, &voice.display_name, &voice.language].iter().any(|field| field.to_lowercase().contains(&search)))
+    }
+}
+
+#[cfg(feature = "full")]
+/// What a voice offers, for planning deployments. The speaker count is only known once the voice is installed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoiceCapabilities {
+    pub installed: bool,
+    /// Output sample rate, from the installed config or else the usual rate for the voice's quality
+    pub sample_rate: u32,
+    pub speakers: Option<u64>,
+    /// Bytes on disk for the model, its config, and any int8 variant
+    pub size_bytes: Option<u64>,
+    /// Lipsync detail for the voice's language: `phonemes` (aligned words with ARPAbet phonemes) for English, `words` (aligned words) otherwise
+    pub lipsync: String,
+}
+
+#[cfg(feature = "full")]
+/// Look up a voice's capabilities from its installed files, without downloading anything
+pub fn voice_capabilities(voice: &Voice) -> VoiceCapabilities {
+    let models_dir = get_models_dir();
+    let files: Vec<std::path::PathBuf> = VOICE_FILE_SUFFIXES.iter().map(|ext| models_dir.join(format!("{}.{}", voice.id, ext))).collect();
+    let installed = files[0].exists() && files[1].exists();
+    let config: Option<serde_json::Value> = installed.then(|| fs::read_to_string(&files[1]).ok().and_then(|c| serde_json::from_str(&c).ok())).flatten();
+    let default_rate = if voice.quality.ends_with("low") { 16000 } else { DEFAULT_SAMPLE_RATE };
+    VoiceCapabilities {
+        installed,
+        sample_rate: config.as_ref().and_then(config_sample_rate).unwrap_or(default_rate),
+        speakers: config.as_ref().and_then(|c| c["num_speakers"].as_u64()),
+        size_bytes: installed.then(|| files.iter().filter_map(|f| fs::metadata(f).ok()).map(|m| m.len()).sum()),
+        lipsync: if voice.id.starts_with("en") { "phonemes" } else { "words" }.to_string(),
+    }
+}
+
+#[cfg(feature = "full")]
+/// Output sample rate of a voice, from its installed config (see [`voice_capabilities`]). Audio from
+/// [`synth_with_voice_config`] is at this rate.
+pub fn voice_sample_rate(voice_id: &str) -> u32 {
+    if let Some(backend) = backend::registered_backend(voice_id) {
+        return backend.sample_rate();
+    }
+    get_available_voices().iter().find(|v| v.id == voice_id).map_or(DEFAULT_SAMPLE_RATE, |voice| voice_capabilities(voice).sample_rate)
+}
+
+#[cfg(feature = "full")]
 /// Download voice model and config files
-pub fn download_voice_files(voice: &Voice) -> Result<(String, String), Box<dyn std::error::Error>> {
+pub fn download_voice_files(voice: &Voice) -> error::Result<(String, String)> {
     let models_dir = get_models_dir();
     let log_msg = format!("[text-to-face] Using models directory: {}", models_dir.display());
-    println!("{}", log_msg);
+    log::info!("{}", log_msg);
     log_to_file(&log_msg);
     if !models_dir.exists() {
         fs::create_dir_all(&models_dir)?;
@@ -536,66 +822,469 @@ pub fn download_voice_files(voice: &Voice) -> Result<(String, String), Box<dyn s
     let model_path = models_dir.join(&model_filename);
     let config_path = models_dir.join(&config_filename);
 
+    let missing = !model_path.exists() || !config_path.exists();
+    if let Some(local) = local_voices::find_local_voice(&voice.id) {
+        local_voices::install(&voice.id, &local, &model_path, &config_path)?;
+        return Ok((model_path.to_string_lossy().to_string(), config_path.to_string_lossy().to_string()));
+    }
+    if missing && let Some(lock) = pinning::voice_lock(&voice.id).filter(|l| l.pinned) {
+        pinning::install_pinned(voice, &lock)?;
+        log::info!("{}", "Successfully downloaded".green());
+        return Ok((model_path.to_string_lossy().to_string(), config_path.to_string_lossy().to_string()));
+    }
+    // Download at the current upstream revision so it can be recorded; fall back to main if the lookup fails
+    let revision = if missing { pinning::upstream_revision().ok() } else { None };
+    let model_url = revision.as_deref().map(|r| pinning::url_at_revision(&voice.model_path, r)).unwrap_or_else(|| voice.model_path.clone());
+    let config_url = revision.as_deref().map(|r| pinning::url_at_revision(&voice.config_path, r)).unwrap_or_else(|| voice.config_path.clone());
+
     if !model_path.exists() {
-        println!("{} voice model...", voice.display_name.yellow());
-        let output = Command::new("curl")
-            .arg("-L").arg("-o").arg(&model_path).arg(&voice.model_path)
-            .output()?;
-        if !output.status.success() {
-            return Err(format!("Failed to download {}: {}", voice.display_name, String::from_utf8_lossy(&output.stderr)).into());
-        }
-        println!("{}", "Successfully downloaded".green());
+        log::info!("{} voice model...", voice.display_name.yellow());
+        http::download(&model_url, &model_path, &model_filename)?;
+        log::info!("{}", "Successfully downloaded".green());
     }
     
     if !config_path.exists() {
-        println!("{} config...", voice.display_name.yellow());
-        let output = Command::new("curl")
-            .arg("-L").arg("-o").arg(&config_path).arg(&voice.config_path)
-            .output()?;
-        if !output.status.success() {
-            return Err(format!("Failed to download config for {}: {}", voice.display_name, String::from_utf8_lossy(&output.stderr)).into());
-        }
-        println!("{}", "Successfully downloaded config for".green());
+        log::info!("{} config...", voice.display_name.yellow());
+        http::download(&config_url, &config_path, &config_filename)?;
+        log::info!("{}", "Successfully downloaded config for".green());
+    }
+    if let Some(revision) = &revision
+        && let Err(e) = pinning::record_voice(&voice.id, revision)
+    {
+        log::warn!("Failed to record the revision of {}: {}", voice.id, e);
     }
     
     Ok((model_path.to_string_lossy().to_string(), config_path.to_string_lossy().to_string()))
 }
 
-/// Synthesize speech with a specific voice
-pub fn synth_with_voice_config(text: String, voice_id: &str) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+/// Set by `--quantized`: load int8 models when they're installed
+#[cfg(feature = "full")]
+static PREFER_QUANTIZED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+#[cfg(feature = "full")]
+pub fn set_prefer_quantized(enabled: bool) {
+    PREFER_QUANTIZED.store(enabled, std::sync::atomic::Ordering::Relaxed);
+}
+
+#[cfg(feature = "full")]
+/// Whether an installed int8 model should be used (`--quantized`, or implied by `--low-memory`)
+pub fn prefers_quantized() -> bool {
+    PREFER_QUANTIZED.load(std::sync::atomic::Ordering::Relaxed) || low_memory::is_enabled()
+}
+
+#[cfg(feature = "full")]
+/// The int8 variant of a voice config (`<id>.int8.onnx.json` next to `<id>.int8.onnx`), if installed
+pub fn quantized_config_path(config_path: &Path) -> Option<std::path::PathBuf> {
+    let name = config_path.file_name()?.to_str()?;
+    let id = name.strip_suffix(".onnx.json")?;
+    let config = config_path.with_file_name(format!("{}.int8.onnx.json", id));
+    let model = config_path.with_file_name(format!("{}.int8.onnx", id));
+    (config.exists() && model.exists()).then_some(config)
+}
+
+#[cfg(feature = "full")]
+/// Download the int8 model for a voice where upstream publishes one (`<id>.int8.onnx` beside the
+/// full model). The voice's regular config is reused for it. Returns the int8 model and config paths.
+pub fn download_quantized_voice_files(voice: &Voice) -> error::Result<(String, String)> {
+    let (_model_path, config_path) = download_voice_files(voice)?;
+    let models_dir = get_models_dir();
+    let model_path = models_dir.join(format!("{}.int8.onnx", voice.id));
+    let quantized_config = models_dir.join(format!("{}.int8.onnx.json", voice.id));
+    if !model_path.exists() {
+        let url = voice.model_path.replace(".onnx", ".int8.onnx");
+        log::info!("{} int8 voice model...", voice.display_name.yellow());
+        if let Err(e) = http::download(&url, &model_path, &format!("{}.int8.onnx", voice.id)) {
+            return Err(Error::DownloadFailed { url, message: format!("no int8 model is published for {} ({})", voice.id, e) });
+        }
+        log::info!("{}", "Successfully downloaded".green());
+    }
+    if !quantized_config.exists() {
+        fs::copy(&config_path, &quantized_config)?;
+    }
+    Ok((model_path.to_string_lossy().to_string(), quantized_config.to_string_lossy().to_string()))
+}
+
+#[cfg(feature = "full")]
+/// Catalog voices whose model and config are installed
+pub fn installed_voices() -> Vec<Voice> {
+    get_available_voices().into_iter().filter(|voice| voice_capabilities(voice).installed).collect()
+}
+
+#[cfg(feature = "full")]
+/// Delete a voice's installed files, including any int8 variant, returning the bytes freed. Its
+/// lockfile entry goes too, unless it's pinned, so a reinstall still gets the pinned files.
+pub fn remove_voice_files(voice_id: &str) -> error::Result<u64> {
+    let models_dir = get_models_dir();
+    let mut freed = 0;
+    for suffix in VOICE_FILE_SUFFIXES {
+        let path = models_dir.join(format!("{}.{}", voice_id, suffix));
+        if let Ok(metadata) = fs::metadata(&path) {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+            freed += metadata.len();
+        }
+    }
+    pinning::forget_voice(voice_id)?;
+    model_cache::evict(voice_id);
+    Ok(freed)
+}
+
+#[cfg(feature = "full")]
+/// Load (downloading if needed) the synthesizer for a voice.
+/// With `--quantized` or `--low-memory`, an installed int8 variant of the voice is preferred.
+/// The voice's override file, if any, is applied to its config (see [`overrides::config_for_voice`]).
+/// The current take variation (see [`takes::set_variation`]) and rate (see [`rate::set_rate`]) are
+/// applied to its inference settings, and the selected speaker (see [`speakers::set_speaker`]) to
+/// multi-speaker voices. Models stay loaded between calls (see [`model_cache`]).
+pub fn load_synthesizer(voice_id: &str) -> error::Result<PiperSpeechSynthesizer> {
+    let config_path = voice_config_path(voice_id)?;
+    let synth = model_cache::synthesizer(voice_id, &config_path)?;
+    takes::apply_variation(&synth)?;
+    rate::apply_rate(&synth)?;
+    speakers::apply_speaker(&synth, voice_id, &config_path)?;
+    Ok(synth)
+}
+
+#[cfg(feature = "full")]
+/// The config a voice is loaded from, downloading it first if needed: the int8 model's when
+/// [`prefers_quantized`], with any overrides applied
+fn voice_config_path(voice_id: &str) -> error::Result<std::path::PathBuf> {
     let voices = get_available_voices();
     let voice = voices.iter()
         .find(|v| v.id == voice_id)
-        .ok_or_else(|| {
-            let available = voices.iter().map(|v| v.id.as_str()).collect::<Vec<_>>().join(", ");
-            format!("Voice '{}' not found. Available voices: {}", voice_id, available)
-        })?;
+        .ok_or_else(|| Error::VoiceNotFound(voice_id.to_string()))?;
     
     let (_model_path, config_path) = download_voice_files(voice)?;
-    let model = piper_rs::from_config_path(config_path.as_ref())?;
-    let synth = PiperSpeechSynthesizer::new(model)?;
-    
-    let mut samples: Vec<f32> = Vec::new();
-    let audio = synth.synthesize_parallel(text, None)?;
-    for result in audio {
-        samples.append(&mut result?.into_vec());
+    let mut config_path = std::path::PathBuf::from(config_path);
+    if prefers_quantized() {
+        if let Some(quantized) = quantized_config_path(&config_path) {
+            log::info!("{} Using int8 model {}", "[Quantized]".cyan(), quantized.display());
+            config_path = quantized;
+        }
     }
-    
+    Ok(overrides::config_for_voice(voice_id, &config_path)?)
+}
+
+#[cfg(feature = "full")]
+/// Synthesize speech with a specific voice, within the configured [`limits::limits`]. The samples are at
+/// the voice's [`voice_sample_rate`].
+pub fn synth_with_voice_config(text: String, voice_id: &str) -> error::Result<Vec<f32>> {
+    let limits = limits::limits();
+    limits.check_text(&text)?;
+    cancel::check()?;
+    if pause_markers::has_pauses(&text) {
+        return synth_with_pause_markers(&text, voice_id);
+    }
+    if emphasis::has_emphasis(&text) {
+        return synth_with_emphasis(&text, voice_id);
+    }
+    let Some(backend) = backend::registered_backend(voice_id) else {
+        return synth_with_piper(text, voice_id);
+    };
+    // Registered backends speak the text as written
+    let plain = respelling::strip_respellings(&text);
+    let sample_rate = backend.sample_rate();
+    let samples = if humanize::is_enabled() {
+        // Each sentence on its own, so each can be varied
+        let sentences = humanize::split_sentences(&plain);
+        let mut samples = Vec::new();
+        for (index, sentence) in sentences.iter().enumerate() {
+            samples.extend(humanize::vary(backend.synthesize(sentence)?, sample_rate, index));
+            progress::sentences(index + 1, sentences.len());
+        }
+        samples
+    } else {
+        let samples = backend.synthesize(&plain)?;
+        progress::sentences(1, 1);
+        samples
+    };
+    limits.check_samples(samples.len(), sample_rate)?;
+    session::record_synthesis(&text, voice_id, &samples, sample_rate);
     Ok(samples)
 }
 
+#[cfg(feature = "full")]
+/// The Piper half of [`synth_with_voice_config`], behind [`backend::PiperBackend`]: text rules,
+/// normalization, respellings, and pauses applied, a sentence at a time
+pub(crate) fn synth_with_piper(text: String, voice_id: &str) -> error::Result<Vec<f32>> {
+    let limits = limits::limits();
+    let respelled = respelling::has_respellings(&text);
+    // Respelled words must reach the phonemizer untouched, so that text is preprocessed piece by piece instead
+    let normalized = if respelled { text.clone() } else { normalize::normalize_for_voice(&rules::apply_rules(&text), voice_id) };
+    let synth = load_synthesizer(voice_id)?;
+    let sample_rate = voice_sample_rate(voice_id);
+    let pause_settings = pauses::pauses();
+    // Piper yields one chunk per sentence; paragraphs are synthesized separately so their breaks can be controlled too
+    let paragraphs = if pause_settings.is_default() { vec![normalized] } else { pauses::split_paragraphs(&normalized) };
+    // Respelled text, and text whose progress is reported, is phonemized up front (which gives the
+    // sentence count) and synthesized a sentence at a time
+    let model = synth.clone_model();
+    let phonemized = if respelled || progress::is_reporting() {
+        let mut phonemized = Vec::with_capacity(paragraphs.len());
+        for paragraph in &paragraphs {
+            phonemized.push(if respelled { respelling::phonemize_with_respellings(&synth, paragraph, voice_id)? } else { model.phonemize_text(paragraph)?.to_vec() });
+        }
+        Some(phonemized)
+    } else {
+        None
+    };
+    let sentence_count: usize = phonemized.iter().flatten().map(Vec::len).sum();
+    let mut chunks: Vec<Vec<Vec<f32>>> = Vec::with_capacity(paragraphs.len());
+    // Checked after every sentence, so an over-long request stops early
+    let (mut total, mut done) = (0, 0);
+    let mut keep = |sentences: &mut Vec<Vec<f32>>, samples: Vec<f32>| -> error::Result<()> {
+        let samples = humanize::vary(samples, sample_rate, done);
+        total += samples.len();
+        done += 1;
+        sentences.push(samples);
+        progress::sentences(done, sentence_count.max(done));
+        cancel::check()?;
+        Ok(limits.check_samples(total, sample_rate)?)
+    };
+    if let Some(phonemized) = phonemized {
+        for paragraph in phonemized {
+            let mut sentences: Vec<Vec<f32>> = Vec::new();
+            for phonemes in paragraph {
+                keep(&mut sentences, model.speak_one_sentence(phonemes)?.into_vec())?;
+            }
+            chunks.push(sentences);
+        }
+    } else {
+        for paragraph in paragraphs {
+            let mut sentences: Vec<Vec<f32>> = Vec::new();
+            if low_memory::is_enabled() || nice::is_enabled() {
+                // One sentence at a time on this thread instead of a worker per sentence
+                for result in synth.synthesize_lazy(paragraph, None)? {
+                    keep(&mut sentences, result?.into_vec())?;
+                }
+            } else {
+                for result in synth.synthesize_parallel(paragraph, None)? {
+                    keep(&mut sentences, result?.into_vec())?;
+                }
+            }
+            chunks.push(sentences);
+        }
+    }
+    let samples = if pause_settings.is_default() {
+        chunks.into_iter().flatten().flatten().collect()
+    } else {
+        pauses::join_with_pauses(chunks, &pause_settings, sample_rate)
+    };
+    // Configured pauses add silence
+    limits.check_samples(samples.len(), sample_rate)?;
+    session::record_synthesis(&text, voice_id, &samples, sample_rate);
+    Ok(samples)
+}
+
+#[cfg(feature = "full")]
+/// [`synth_with_voice_config`] for text with pause markers (see [`pause_markers`]): the text between them
+/// is synthesized piece by piece, and the model's own silence next to each pause is replaced by exactly
+/// that pause
+fn synth_with_pause_markers(text: &str, voice_id: &str) -> error::Result<Vec<f32>> {
+    let limits = limits::limits();
+    let parts = pause_markers::split_pauses(text)?;
+    let sample_rate = voice_sample_rate(voice_id);
+    let mut samples = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        match part {
+            // Text and pauses alternate, so only the first and last pieces have an end without a pause
+            pause_markers::PausePart::Text(piece) => {
+                let piece_samples = synth_with_voice_config(piece.clone(), voice_id)?;
+                samples.extend_from_slice(pauses::trim_silence(&piece_samples, sample_rate, i > 0, i + 1 < parts.len()));
+            }
+            pause_markers::PausePart::Pause(pause) => samples.extend(pauses::silence(*pause, sample_rate)),
+        }
+        limits.check_samples(samples.len(), sample_rate)?;
+    }
+    Ok(samples)
+}
+
+#[cfg(feature = "full")]
+/// [`synth_with_voice_config`] for text with emphasis or pitch/volume markup (see [`emphasis`]): the text
+/// is synthesized without it, then the marked words are found by forced alignment (or the mock voice's
+/// own timings) and processed in place
+fn synth_with_emphasis(text: &str, voice_id: &str) -> error::Result<Vec<f32>> {
+    let marked = emphasis::parse_emphasis(text)?;
+    let mut samples = synth_with_voice_config(marked.text.clone(), voice_id)?;
+    if marked.styles.iter().all(emphasis::WordStyle::is_none) {
+        return Ok(samples);
+    }
+    let sample_rate = voice_sample_rate(voice_id);
+    // Registered backends and respelled text are spoken as written; anything else goes through the rules
+    // and normalization first, as in `synth_with_voice_config`
+    let registered = backend::registered_backend(voice_id);
+    let (timings, styles) = if registered.is_some() || respelling::has_respellings(&marked.text) {
+        let spoken = respelling::strip_respellings(&marked.text);
+        // Backends that know their own timings save the alignment
+        let timings = registered.and_then(|backend| backend.word_timings(&spoken)).unwrap_or_else(|| {
+            let words: Vec<(String, Vec<String>)> = spoken.split_whitespace().map(str::to_string).zip(text_to_arpabet(&marked.text, None)).collect();
+            forced_align::align_word_timings(&words, &samples, sample_rate).0
+        });
+        (timings, marked.styles)
+    } else {
+        let (spoken, styles) = emphasis::spoken_styles(&marked, |text| normalize::normalize_for_voice(&rules::apply_rules(text), voice_id))?;
+        let words: Vec<(String, Vec<String>)> = spoken.split_whitespace().map(str::to_string).zip(text_to_arpabet(&spoken, None)).collect();
+        (forced_align::align_word_timings(&words, &samples, sample_rate).0, styles)
+    };
+    emphasis::apply_styles(&mut samples, sample_rate, &timings, &styles);
+    Ok(samples)
+}
+
+#[cfg(feature = "full")]
+/// Synthesize speech with a specific voice along with Piper's own phoneme timings (see [`piper_timings`]),
+/// which are `None` when the voice model doesn't report durations. Also returns the text as spoken after
+/// normalization, for labelling words. Sentences are joined without configured pauses, so the timings
+/// stay those of the audio.
+pub fn synth_with_phoneme_timings(text: &str, voice_id: &str) -> error::Result<(Vec<f32>, Option<LipSyncData>, String)> {
+    let limits = limits::limits();
+    limits.check_text(text)?;
+    let respelled = respelling::has_respellings(text);
+    let spoken = if respelled { respelling::strip_respellings(text) } else { normalize::normalize_for_voice(&rules::apply_rules(text), voice_id) };
+    let synth = load_synthesizer(voice_id)?;
+    let model = synth.clone_model();
+    let sentences = if respelled { respelling::phonemize_with_respellings(&synth, text, voice_id)? } else { model.phonemize_text(&spoken)?.to_vec() };
+    let settings = model.get_fallback_synthesis_config()?;
+    let settings = settings.downcast_ref::<piper_rs::PiperSynthesisConfig>().ok_or("Voice has no Piper synthesis settings")?;
+    let (samples, timings) = piper_timings::synthesize_with_timings(&voice_config_path(voice_id)?, &sentences, settings)?;
+    let sample_rate = timings.as_ref().map_or_else(|| voice_sample_rate(voice_id), |t| t.sample_rate);
+    limits.check_samples(samples.len(), sample_rate)?;
+    session::record_synthesis(text, voice_id, &samples, sample_rate);
+    Ok((samples, timings, spoken))
+}
+
+#[cfg(feature = "full")]
+/// Synthesize speech and apply the high-quality pitch shift and tempo stretch, returning the processed samples
+/// and their sample rate. `@voice(...)`, `@pitch(...)`, and `@tempo(...)` directives in the text switch settings
+/// partway through.
+pub fn synthesize_processed(text: &str, voice: &str, pitch: &PitchArg, tempo: f32) -> error::Result<(Vec<f32>, u32)> {
+    if directives::has_directives(text) {
+        return synthesize_directed(text, voice, pitch.as_factor(), tempo);
+    }
+    let samples = synth_with_voice_config(text.to_string(), voice)?;
+    let sample_rate = voice_sample_rate(voice);
+    let processed_samples = true_pitch_shift(&samples, sample_rate as usize, pitch.as_factor());
+    let processed_samples = time_stretch(&processed_samples, sample_rate as usize, tempo);
+    // A cancelled pitch shift or stretch stops part way
+    cancel::check()?;
+    // A slow tempo can stretch audio that was within the limits past them
+    limits::limits().check_samples(processed_samples.len(), sample_rate)?;
+    Ok((effects::apply_effect(processed_samples, sample_rate), sample_rate))
+}
+
+#[cfg(feature = "full")]
+/// Synthesize text with inline directives (see [`directives`]): each part with its own voice, pitch, and
+/// tempo, starting from the given ones, joined into one processed track at the first part's sample rate
+fn synthesize_directed(text: &str, voice: &str, pitch_factor: f32, tempo: f32) -> error::Result<(Vec<f32>, u32)> {
+    let limits = limits::limits();
+    limits.check_text(&directives::strip_directives(text))?;
+    let initial = directives::VoiceSettings { voice: voice.to_string(), pitch: pitch_factor, tempo };
+    let mut samples = Vec::new();
+    let mut track_rate = None;
+    for part in directives::split_directives(text, &initial)? {
+        let settings = &part.settings;
+        log::info!("{} {} (pitch: {}, tempo: {})", "[Directive]".cyan(), settings.voice, settings.pitch, settings.tempo);
+        let raw = synth_with_voice_config(part.text, &settings.voice)?;
+        let rate = voice_sample_rate(&settings.voice);
+        let processed = effects::apply_effect(time_stretch(&true_pitch_shift(&raw, rate as usize, settings.pitch), rate as usize, settings.tempo), rate);
+        cancel::check()?;
+        // Voices of different qualities can have different rates
+        let track_rate = *track_rate.get_or_insert(rate);
+        if rate == track_rate {
+            samples.extend(processed);
+        } else {
+            samples.extend(dsp::resample(&processed, rate as usize, track_rate as usize));
+        }
+        limits.check_samples(samples.len(), track_rate)?;
+    }
+    Ok((samples, track_rate.unwrap_or_else(|| voice_sample_rate(voice))))
+}
+
+#[cfg(feature = "full")]
+/// Synthesize an SSML document (see [`ssml::parse_ssml`]): each part with its prosody on top of the
+/// given pitch and tempo, and `<break>`s as silence, joined into one processed track. Returns the
+/// samples, their sample rate, and the text as spoken, for lipsync.
+pub fn synthesize_ssml(ssml: &str, voice: &str, pitch: &PitchArg, tempo: f32) -> error::Result<(Vec<f32>, u32, String)> {
+    let parts = ssml::parse_ssml(ssml).map_err(|e| format!("Invalid SSML: {}", e))?;
+    let text = ssml::spoken_text(&parts);
+    let limits = limits::limits();
+    limits.check_text(&text)?;
+    let mut speech = Vec::new();
+    let mut total = 0;
+    for part in &parts {
+        if let ssml::SsmlPart::Speech(part) = part {
+            let (pitch, tempo) = (pitch.as_factor() * part.pitch, tempo * part.tempo);
+            log::info!("{} {} (pitch: {:.2}, tempo: {:.2})", "[SSML]".cyan(), part.text, pitch, tempo);
+            let raw = synth_with_voice_config(part.text.clone(), voice)?;
+            let rate = voice_sample_rate(voice) as usize;
+            let processed = effects::apply_effect(time_stretch(&true_pitch_shift(&raw, rate, pitch), rate, tempo), rate as u32);
+            cancel::check()?;
+            total += processed.len();
+            limits.check_samples(total, rate as u32)?;
+            speech.push(processed);
+        }
+    }
+    // Breaks are laid down once the voice is installed and its rate known
+    let sample_rate = voice_sample_rate(voice);
+    let mut speech = speech.into_iter();
+    let mut samples = Vec::new();
+    for part in parts {
+        match part {
+            ssml::SsmlPart::Speech(_) => samples.extend(speech.next().unwrap_or_default()),
+            ssml::SsmlPart::Break(duration) => samples.extend(std::iter::repeat_n(0.0, (duration.as_secs_f32() * sample_rate as f32) as usize)),
+        }
+    }
+    limits.check_samples(samples.len(), sample_rate)?;
+    Ok((samples, sample_rate, text))
+}
+
+/// Sample rate used when a voice config doesn't specify one
+pub const DEFAULT_SAMPLE_RATE: u32 = 22050;
+
+/// Output sample rate from a parsed voice config (`audio.sample_rate`)
+pub fn config_sample_rate(config: &serde_json::Value) -> Option<u32> {
+    config.pointer("/audio/sample_rate").and_then(|v| v.as_u64()).map(|v| v as u32)
+}
+
+/// Write mono f32 samples to a 16-bit PCM WAV file
+pub fn write_wav(path: &str, samples: &[f32], sample_rate: u32) -> Result<(), hound::Error> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for sample in samples {
+        let sample_i16 = (*sample * 32767.0).clamp(-32768.0, 32767.0) as i16;
+        writer.write_sample(sample_i16)?;
+    }
+    writer.finalize()
+}
+
+/// Read a 16-bit PCM WAV file as mono f32 samples (stereo is averaged down), returning the samples and the sample rate
+pub fn read_wav(path: &str) -> Result<(Vec<f32>, u32), hound::Error> {
+    let reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let samples = reader.into_samples::<i16>()
+        .map(|s| s.map(|s| s as f32 / 32767.0))
+        .collect::<Result<Vec<f32>, _>>()?;
+    Ok((stereo::downmix(&samples, spec.channels), spec.sample_rate))
+}
+
+#[cfg(feature = "full")]
 /// Synthesize speech to WAV file with pitch shifting and tempo adjustment
 pub fn synth_to_wav_with_pitch(text: String, voice_id: &str, output_path: &str, pitch_factor: f32, tempo: f32) -> Result<(), Box<dyn std::error::Error>> {
     // Get the raw audio samples
     let samples = synth_with_voice_config(text, voice_id)?;
+    let sample_rate = voice_sample_rate(voice_id);
     // Apply pitch shift if needed
     let processed_samples = pitch_shift(&samples, pitch_factor);
     // Apply time stretch if needed
-    let processed_samples = time_stretch(&processed_samples, 22050, tempo);
+    let processed_samples = time_stretch(&processed_samples, sample_rate as usize, tempo);
+    cancel::check()?;
     // Write to WAV file
     let spec = hound::WavSpec {
         channels: 1,
-        sample_rate: 22050,
+        sample_rate,
         bits_per_sample: 16,
         sample_format: hound::SampleFormat::Int,
     };
@@ -606,14 +1295,17 @@ pub fn synth_to_wav_with_pitch(text: String, voice_id: &str, output_path: &str,
         writer.write_sample(sample_i16)?;
     }
     writer.finalize()?;
-    println!("{} file written to {} with pitch factor {} and tempo {}", "WAV".green(), output_path, pitch_factor, tempo);
+    log::info!("{} file written to {} with pitch factor {} and tempo {}", "WAV".green(), output_path, pitch_factor, tempo);
     Ok(())
 } 
 
+#[cfg(feature = "full")]
 /// Synthesize, process, and optionally export/play and lipsync.
 /// - If `output_wav` is Some(path), writes to WAV.
 /// - If `play_audio` is true, plays the audio.
 /// - If `lipsync_json` is Some(path), runs WhisperX and saves JSON there; if None and lipsync is true, prints JSON.
+///
+/// The report holds the warnings recorded along the way.
 pub fn synthesize_and_handle(
     text: &str,
     voice: &str,
@@ -624,48 +1316,106 @@ pub fn synthesize_and_handle(
     lipsync: LipsyncLevel,
     lipsync_json: Option<&str>,
     lipsync_with_llm: Option<&str>,
-) {
+) -> Result<SynthesisReport, Box<dyn std::error::Error>> {
     let pitch_factor = pitch.as_factor();
-    let samples = match synth_with_voice_config(text.to_string(), voice) {
-        Ok(samples) => samples,
-        Err(e) => {
-            eprintln!("{}", "Error:".red());
-            eprintln!("{}", e);
-            return;
-        }
-    };
+    if directives::has_directives(text) {
+        let (processed_samples, sample_rate) = synthesize_directed(text, voice, pitch_factor, tempo)?;
+        return handle_processed_samples(text, &processed_samples, sample_rate, pitch_factor, tempo, output_wav, play_audio, lipsync, lipsync_json, lipsync_with_llm);
+    }
+    // Pause markers split the text into separate runs, and markup changes words after synthesis, so Piper's
+    // timings would miss both
+    let registered = backend::registered_backend(voice);
+    if lipsync == LipsyncLevel::Piper && registered.is_none() && !pause_markers::has_pauses(text) && !emphasis::has_emphasis(text) {
+        // The lipsync JSON comes from the same inference run as the audio
+        let (samples, timings, spoken) = synth_with_phoneme_timings(text, voice)?;
+        let sample_rate = timings.as_ref().map_or_else(|| voice_sample_rate(voice), |t| t.sample_rate);
+        let processed_samples = true_pitch_shift(&samples, sample_rate as usize, pitch_factor);
+        let processed_samples = effects::apply_effect(time_stretch(&processed_samples, sample_rate as usize, tempo), sample_rate);
+        limits::limits().check_samples(processed_samples.len(), sample_rate)?;
+        let Some(timings) = timings else {
+            log::warn!("{} {} has no phoneme duration output; estimating timings instead", "[Piper]".cyan(), voice);
+            return handle_processed_samples(text, &processed_samples, sample_rate, pitch_factor, tempo, output_wav, play_audio, LipsyncLevel::Estimate, lipsync_json, lipsync_with_llm);
+        };
+        let report = handle_processed_samples(text, &processed_samples, sample_rate, pitch_factor, tempo, output_wav, play_audio, LipsyncLevel::Low, None, None)?;
+        let scale = processed_samples.len() as f32 / samples.len().max(1) as f32;
+        let lipsync_json = write_piper_lipsync(text, &spoken, &timings.scaled(scale), lipsync_json, lipsync_with_llm)?;
+        return Ok(SynthesisReport { lipsync_json, ..report });
+    }
+    let samples = synth_with_voice_config(text.to_string(), voice)?;
+    let sample_rate = voice_sample_rate(voice);
     // Use high-quality pitch shift
-    let processed_samples = true_pitch_shift(&samples, 22050, pitch_factor);
-    let processed_samples = time_stretch(&processed_samples, 22050, tempo);
+    let processed_samples = true_pitch_shift(&samples, sample_rate as usize, pitch_factor);
+    let processed_samples = effects::apply_effect(time_stretch(&processed_samples, sample_rate as usize, tempo), sample_rate);
+    // A slow tempo can stretch audio that was within the limits past them
+    limits::limits().check_samples(processed_samples.len(), sample_rate)?;
+    // A backend that knows its own word timings skips WhisperX
+    let scale = processed_samples.len() as f32 / samples.len().max(1) as f32;
+    let plain = emphasis::strip_emphasis(&pause_markers::strip_markers(text));
+    if let Some(json) = registered.filter(|_| lipsync != LipsyncLevel::Low).and_then(|backend| backend.lipsync_json(&plain, scale)) {
+        let report = handle_processed_samples(text, &processed_samples, sample_rate, pitch_factor, tempo, output_wav, play_audio, LipsyncLevel::Low, None, None)?;
+        let lipsync_json = write_lipsync_json("[Backend]", json, lipsync_json)?;
+        return Ok(SynthesisReport { lipsync_json, ..report });
+    }
+    handle_processed_samples(text, &processed_samples, sample_rate, pitch_factor, tempo, output_wav, play_audio, lipsync, lipsync_json, lipsync_with_llm)
+}
 
+#[cfg(feature = "full")]
+/// The output half of [`synthesize_and_handle`], for samples that were already synthesized and
+/// processed (e.g. a voice morph) at `sample_rate`: writes the WAV, plays it, and runs lipsync as
+/// requested. The report holds every warning recorded since the last report, including during synthesis.
+pub fn handle_processed_samples(
+    text: &str,
+    processed_samples: &[f32],
+    sample_rate: u32,
+    pitch_factor: f32,
+    tempo: f32,
+    output_wav: Option<&str>,
+    play_audio: bool,
+    lipsync: LipsyncLevel,
+    lipsync_json: Option<&str>,
+    lipsync_with_llm: Option<&str>,
+) -> Result<SynthesisReport, Box<dyn std::error::Error>> {
+    // Samples from a cancelled pitch shift or stretch are cut short, so nothing is written
+    cancel::check()?;
+    // Lipsync follows the words as spoken
+    let text = &emphasis::strip_emphasis(&pause_markers::strip_markers(&directives::strip_directives(text)));
+    if let Some(clipped) = warnings::clipping(processed_samples) {
+        warnings::warn(clipped);
+    }
     // Write to WAV if requested
     if let Some(wav_path) = output_wav {
         let spec = hound::WavSpec {
             channels: 1,
-            sample_rate: 22050,
+            sample_rate,
             bits_per_sample: 16,
             sample_format: hound::SampleFormat::Int,
         };
-        let mut writer = hound::WavWriter::create(wav_path, spec).unwrap();
-        for sample in &processed_samples {
+        let mut writer = hound::WavWriter::create(wav_path, spec)?;
+        for sample in processed_samples {
             let sample_i16 = (*sample * 32767.0).clamp(-32768.0, 32767.0) as i16;
-            writer.write_sample(sample_i16).unwrap();
+            writer.write_sample(sample_i16)?;
         }
-        writer.finalize().unwrap();
-        println!("{} file written to {} with pitch factor {} and tempo {}", "WAV".green(), wav_path, pitch_factor, tempo);
+        writer.finalize()?;
+        log::info!("{} file written to {} with pitch factor {} and tempo {}", "WAV".green(), wav_path, pitch_factor, tempo);
     }
 
     // Play audio if requested
-    if play_audio {
-        if let Ok((_stream, handle)) = rodio::OutputStream::try_default() {
+    if play_audio && !(low_memory::is_enabled() && low_memory::play_alsa(processed_samples, sample_rate)) {
+        if let Ok((_stream, handle)) = devices::open_output_stream() {
             if let Ok(sink) = rodio::Sink::try_new(&handle) {
-                let buf = rodio::buffer::SamplesBuffer::new(1, 22050, processed_samples.as_slice());
+                let buf = stereo::playback_buffer(processed_samples, sample_rate);
                 sink.append(buf);
                 sink.sleep_until_end();
             }
         }
     }
 
+    // Piper's own timings need the inference run; audio that was already synthesized gets estimates
+    if matches!(lipsync, LipsyncLevel::Estimate | LipsyncLevel::Align | LipsyncLevel::Piper) {
+        let write_lipsync = if lipsync == LipsyncLevel::Align { write_aligned_lipsync } else { write_estimated_lipsync };
+        let lipsync_json = write_lipsync(text, processed_samples, sample_rate, lipsync_json, lipsync_with_llm)?;
+        return Ok(SynthesisReport { warnings: warnings::take_warnings(), lipsync_json });
+    }
     // Lipsync (WhisperX) if requested
     if lipsync != LipsyncLevel::Low {
         // Use the WAV file if it was just written, otherwise write a temp WAV
@@ -675,180 +1425,257 @@ pub fn synthesize_and_handle(
             let temp_wav = "temp_lipsync.wav";
             let spec = hound::WavSpec {
                 channels: 1,
-                sample_rate: 22050,
+                sample_rate,
                 bits_per_sample: 16,
                 sample_format: hound::SampleFormat::Int,
             };
-            let mut writer = hound::WavWriter::create(temp_wav, spec).unwrap();
-            for sample in &processed_samples {
+            let mut writer = hound::WavWriter::create(temp_wav, spec)?;
+            for sample in processed_samples {
                 let sample_i16 = (*sample * 32767.0).clamp(-32768.0, 32767.0) as i16;
-                writer.write_sample(sample_i16).unwrap();
+                writer.write_sample(sample_i16)?;
             }
-            writer.finalize().unwrap();
+            writer.finalize()?;
             temp_wav
         };
-        run_whisperx_on_wav(wav_path, lipsync_json, lipsync == LipsyncLevel::High, text, lipsync_with_llm);
+        let result = run_whisperx_on_wav(wav_path, lipsync_json, lipsync == LipsyncLevel::High, text, lipsync_with_llm);
         if output_wav.is_none() {
             let _ = std::fs::remove_file(wav_path);
         }
+        result?;
     }
-} 
+    Ok(SynthesisReport { warnings: warnings::take_warnings(), lipsync_json: None })
+}
 
+#[cfg(feature = "full")]
 /// Run WhisperX on a WAV file, optionally saving output JSON to a file or printing it.
-pub fn run_whisperx_on_wav(wav_path: &str, output_json: Option<&str>, hi_fidelity: bool, text: &str, lipsync_with_llm: Option<&str>) {
-    use std::env;
+///
+/// With [`alignment::set_transcript_alignment`], `text` itself is aligned and recognition is skipped.
+pub fn run_whisperx_on_wav(wav_path: &str, output_json: Option<&str>, hi_fidelity: bool, text: &str, lipsync_with_llm: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
     use serde_json::Value;
+    match alignment::transcript_alignment() {
+        Some(language) => {
+            // Same place WhisperX itself would write it
+            let stem = Path::new(wav_path).file_stem().and_then(|s| s.to_str()).unwrap_or("lipsync");
+            let default_json = format!("{}.json", stem);
+            alignment::align_transcript(wav_path, &respelling::strip_respellings(text), &language, output_json.unwrap_or(&default_json))?;
+        }
+        None => recognize_and_align(wav_path, output_json)?,
+    }
+    if let Some(json_path) = output_json {
+        let json_value: Value = serde_json::from_str(&std::fs::read_to_string(json_path)?)?;
+        for warning in warnings::low_confidence_words(&json_value) {
+            warnings::warn(warning);
+        }
+    }
+    // Hi-fidelity: add ARPAbet if requested
+    if hi_fidelity && let Some(json_path) = output_json {
+        let mut json_value: Value = serde_json::from_str(&std::fs::read_to_string(json_path)?)?;
+        // Get ARPAbet for each word
+        let arpabet_dict = text_to_arpabet_with_method(text, lipsync_with_llm);
+        if let Err(e) = check_strict(text, &arpabet_dict) {
+            // Don't leave a lipsync file behind with holes in it
+            let _ = std::fs::remove_file(json_path);
+            return Err(e);
+        }
+
+        enrich::add_phonemes(&mut json_value, &arpabet_dict);
+
+        std::fs::write(json_path, serde_json::to_string_pretty(&json_value)?)?;
+        log::info!("{} Added ARPAbet phonemes to word segments in {}", "[HiFidelity]".cyan(), json_path);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "full")]
+/// Run the `whisperx` command on a WAV file: recognize the speech, then align the recognized words.
+///
+/// WhisperX is run with an explicit output directory instead of changing the process working
+/// directory, so several alignments can run concurrently (see [`alignment::run_alignment_jobs`]).
+fn recognize_and_align(wav_path: &str, output_json: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    use std::path::PathBuf;
     // Check for whisperx
     let whisperx_available = std::process::Command::new("whisperx")
         .arg("--help")
         .output()
         .is_ok();
     if !whisperx_available {
-        eprintln!("\n{} Error: 'whisperx' executable not found in your PATH.", "[WhisperX]".red());
-        eprintln!("{} To use the --lipsync flag, you must install WhisperX:", "[WhisperX]".red());
-        eprintln!("  python3 -m pip install git+https://github.com/m-bain/whisperx.git");
-        eprintln!("{} See: https://github.com/m-bain/whisperX\n", "[WhisperX]".red());
-        return;
-    }
-
-    // If output_json is provided, change to its directory
-    let (run_dir, wav_filename, json_filename, restore_dir): (Option<String>, String, Option<String>, Option<String>) =
-        if let Some(json_path) = output_json {
-            let json_path = std::path::Path::new(json_path);
-            let dir = json_path.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| std::path::PathBuf::from("."));
-            let json_filename = json_path.file_name().and_then(|n| n.to_str()).map(|s| s.to_string());
-            let wav_pathbuf = std::path::Path::new(wav_path);
-            let wav_filename = wav_pathbuf.file_name().and_then(|n| n.to_str()).unwrap_or(wav_path).to_string();
-            let orig_dir = env::current_dir().ok().map(|d| d.to_string_lossy().to_string());
-            (Some(dir.to_string_lossy().to_string()), wav_filename, json_filename, orig_dir)
-        } else {
-            (None, wav_path.to_string(), None, None)
-        };
-
-    // Change directory if needed
-    if let Some(ref dir) = run_dir {
-        if let Err(e) = env::set_current_dir(dir) {
-            eprintln!("{} Failed to change directory to {}: {}", "[WhisperX]".red(), dir, e);
-            return;
-        }
+        log::warn!("{} To use the --lipsync flag, you must install WhisperX:", "[WhisperX]".red());
+        log::warn!("  python3 -m pip install git+https://github.com/m-bain/whisperx.git");
+        log::warn!("{} See: https://github.com/m-bain/whisperX\n", "[WhisperX]".red());
+        return Err(Error::tool_missing("whisperx", "Install WhisperX for --lipsync high, or use --lipsync estimate or align.").into());
     }
 
-    println!("{} Running whisperx on {}...", "[WhisperX]".cyan(), wav_filename);
-    println!("{} Current working directory: {:?}", "[WhisperX]".cyan(), env::current_dir().unwrap_or_default());
-    let whisperx_result = std::process::Command::new("whisperx")
-        .arg(&wav_filename)
+    // WhisperX writes <wav stem>.json into its output directory; use the requested JSON's directory
+    let output_dir: PathBuf = output_json
+        .and_then(|json_path| Path::new(json_path).parent())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(|dir| dir.to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."));
+    let wav_filename = Path::new(wav_path).file_name().and_then(|n| n.to_str()).unwrap_or(wav_path).to_string();
+
+    log::info!("{} Running whisperx on {}...", "[WhisperX]".cyan(), wav_filename);
+    log::debug!("{} Output directory: {:?}", "[WhisperX]".cyan(), output_dir);
+    let result = std::process::Command::new("whisperx")
+        .arg(wav_path)
         .arg("--output_dir")
-        .arg(".")
+        .arg(&output_dir)
         .arg("--output_format")
         .arg("json")
         .arg("--compute_type")
         .arg("float32")
-        .output();
-    match whisperx_result {
-        Ok(result) => {
-            println!("{} Command stdout: {}", "[WhisperX]".cyan(), String::from_utf8_lossy(&result.stdout));
-            println!("{} Command stderr: {}", "[WhisperX]".red(), String::from_utf8_lossy(&result.stderr));
-            if result.status.success() {
-                // WhisperX writes to <filename>.json (e.g., lipsync_test_phrase.json)
-                let base = if let Some(stripped) = wav_filename.strip_suffix(".wav") {
-                    stripped
-                } else {
-                    &wav_filename
-                };
-                println!("{} Base filename: {}", "[WhisperX]".cyan(), base);
-                let whisperx_json_path = format!("{}.json", base);
-                println!("{} Looking for output file: {}", "[WhisperX]".cyan(), whisperx_json_path);
-                if !std::path::Path::new(&whisperx_json_path).exists() {
-                    eprintln!("{} Output JSON not found: {}", "[WhisperX]".red(), whisperx_json_path);
-                    // List files in current directory to see what WhisperX actually created
-                    if let Ok(entries) = std::fs::read_dir(".") {
-                        println!("{} Files in current directory:", "[WhisperX]".cyan());
-                        for entry in entries {
-                            if let Ok(entry) = entry {
-                                if let Some(name) = entry.file_name().to_str() {
-                                    if name.ends_with(".json") {
-                                        println!("  - {}", name);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    // Restore directory before returning
-                    if let Some(ref orig) = restore_dir {
-                        let _ = env::set_current_dir(orig);
-                    }
-                    return;
-                }
-                match json_filename {
-                    Some(ref json_path) => {
-                        // If the output file is not the expected name, rename it
-                        if json_path != &whisperx_json_path {
-                            match std::fs::rename(&whisperx_json_path, json_path) {
-                                Ok(_) => {
-                                    println!("{} Lipsync JSON renamed to {}", "[WhisperX]".cyan(), json_path);
-                                },
-                                Err(_e) => {
-                                    if let Err(copy_err) = std::fs::copy(&whisperx_json_path, json_path) {
-                                        eprintln!("{} Failed to copy WhisperX output: {}", "[WhisperX]".red(), copy_err);
-                                    } else if let Err(remove_err) = std::fs::remove_file(&whisperx_json_path) {
-                                        eprintln!("{} Failed to remove original WhisperX output: {}", "[WhisperX]".red(), remove_err);
-                                    } else {
-                                        println!("{} Lipsync JSON copied to {}", "[WhisperX]".cyan(), json_path);
-                                    }
-                                }
-                            }
-                        } else {
-                            println!("{} Lipsync JSON written to {}", "[WhisperX]".cyan(), json_path);
-                        }
-                    }
-                    None => {
-                        println!("{} Lipsync JSON written to {}", "[WhisperX]".cyan(), whisperx_json_path);
-                    }
-                }
-                // Hi-fidelity: add ARPAbet if requested
-                if hi_fidelity {
-                    if let Some(ref json_path) = json_filename {
-                        if let Ok(mut json_value) = std::fs::read_to_string(json_path).and_then(|s| serde_json::from_str::<Value>(&s).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))) {
-                            // Get ARPAbet for each word
-                            let arpabet_dict = text_to_arpabet_with_method(text, lipsync_with_llm);
-                            
-                            // Add phonemes to each word segment
-                            if let Some(word_segments) = json_value.get_mut("word_segments") {
-                                if let Some(word_segments_array) = word_segments.as_array_mut() {
-                                    for (i, word_segment) in word_segments_array.iter_mut().enumerate() {
-                                        if let Some(word_obj) = word_segment.as_object_mut() {
-                                            if let Some(_word) = word_obj.get("word").and_then(|w| w.as_str()) {
-                                                if let Some((phonemes, method)) = arpabet_dict.get(i) {
-                                                    word_obj.insert("phonemes".to_string(), serde_json::to_value(phonemes).unwrap_or(Value::Null));
-                                                    word_obj.insert("phoneme_method".to_string(), serde_json::to_value(method).unwrap_or(Value::Null));
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            
-                            let _ = std::fs::write(json_path, serde_json::to_string_pretty(&json_value).unwrap());
-                            println!("{} Added ARPAbet phonemes to word segments in {}", "[HiFidelity]".cyan(), json_path);
-                        }
-                    }
+        .args(nice::thread_args("--threads"))
+        .envs(nice::child_env())
+        .output()
+        .map_err(|e| format!("Failed to run WhisperX: {}", e))?;
+    log::debug!("{} Command stdout: {}", "[WhisperX]".cyan(), String::from_utf8_lossy(&result.stdout));
+    log::debug!("{} Command stderr: {}", "[WhisperX]".red(), String::from_utf8_lossy(&result.stderr));
+    if !result.status.success() {
+        return Err(format!("WhisperX failed with status {}:\n{}", result.status, String::from_utf8_lossy(&result.stderr)).into());
+    }
+    // WhisperX writes to <filename>.json (e.g., lipsync_test_phrase.json)
+    let base = if let Some(stripped) = wav_filename.strip_suffix(".wav") {
+        stripped
+    } else {
+        &wav_filename
+    };
+    log::debug!("{} Base filename: {}", "[WhisperX]".cyan(), base);
+    let whisperx_json_path = output_dir.join(format!("{}.json", base));
+    log::debug!("{} Looking for output file: {}", "[WhisperX]".cyan(), whisperx_json_path.display());
+    if !whisperx_json_path.exists() {
+        // List files in the output directory to see what WhisperX actually created
+        if let Ok(entries) = std::fs::read_dir(&output_dir) {
+            log::debug!("{} Files in output directory:", "[WhisperX]".cyan());
+            for entry in entries.flatten() {
+                if let Some(name) = entry.file_name().to_str()
+                    && name.ends_with(".json")
+                {
+                    log::debug!("  - {}", name);
                 }
             }
-            else {
-                eprintln!(
-                    "{} Failed with status {}:\n{}",
-                    "[WhisperX]".red(),
-                    result.status,
-                    String::from_utf8_lossy(&result.stderr)
-                );
+        }
+        return Err(format!("WhisperX output JSON not found: {}", whisperx_json_path.display()).into());
+    }
+    match output_json {
+        Some(json_path) => {
+            // If the output file is not the expected name, rename it
+            if Path::new(json_path) != whisperx_json_path {
+                if std::fs::rename(&whisperx_json_path, json_path).is_ok() {
+                    log::info!("{} Lipsync JSON renamed to {}", "[WhisperX]".cyan(), json_path);
+                } else {
+                    std::fs::copy(&whisperx_json_path, json_path).map_err(|e| format!("Failed to copy WhisperX output: {}", e))?;
+                    std::fs::remove_file(&whisperx_json_path).map_err(|e| format!("Failed to remove original WhisperX output: {}", e))?;
+                    log::info!("{} Lipsync JSON copied to {}", "[WhisperX]".cyan(), json_path);
+                }
+            } else {
+                log::info!("{} Lipsync JSON written to {}", "[WhisperX]".cyan(), json_path);
             }
         }
-        Err(e) => {
-            eprintln!("{} Failed to run WhisperX: {}", "[WhisperX]".red(), e);
+        None => {
+            log::info!("{} Lipsync JSON written to {}", "[WhisperX]".cyan(), whisperx_json_path.display());
         }
     }
-    // Restore original directory if changed
-    if let Some(ref orig) = restore_dir {
-        let _ = env::set_current_dir(orig);
+    Ok(())
+}
+
+#[cfg(feature = "full")]
+/// With `--strict`, fail when any word of `text` got no phonemes
+pub(crate) fn check_strict(text: &str, arpabet: &[ArpabetResult]) -> Result<(), Box<dyn std::error::Error>> {
+    if !llm_fallback::settings().strict {
+        return Ok(());
+    }
+    let missing: Vec<&str> = text.split_whitespace().zip(arpabet).filter(|(_, (phonemes, _))| phonemes.is_empty()).map(|(word, _)| word).collect();
+    if missing.is_empty() {
+        return Ok(());
     }
-} 
\ No newline at end of file
+    Err(format!("No phonemes for {} word(s) (--strict): {}", missing.len(), missing.join(", ")).into())
+}
+
+#[cfg(feature = "full")]
+/// Lipsync without WhisperX (`--lipsync estimate`): look up each word's phonemes and spread them over
+/// the audio by their average lengths (see [`estimate::estimate_word_timings`]). Writes the JSON to
+/// `output_json`, or returns it.
+pub fn write_estimated_lipsync(text: &str, samples: &[f32], sample_rate: u32, output_json: Option<&str>, lipsync_with_llm: Option<&str>) -> Result<Option<serde_json::Value>, Box<dyn std::error::Error>> {
+    write_lipsync_without_whisperx(text, samples, sample_rate, false, output_json, lipsync_with_llm)
+}
+
+#[cfg(feature = "full")]
+/// Lipsync without WhisperX (`--lipsync align`): look up each word's phonemes and fit them to the
+/// audio with the built-in forced aligner (see [`forced_align::align_word_timings`]). Writes the JSON
+/// to `output_json`, or returns it.
+pub fn write_aligned_lipsync(text: &str, samples: &[f32], sample_rate: u32, output_json: Option<&str>, lipsync_with_llm: Option<&str>) -> Result<Option<serde_json::Value>, Box<dyn std::error::Error>> {
+    write_lipsync_without_whisperx(text, samples, sample_rate, true, output_json, lipsync_with_llm)
+}
+
+#[cfg(feature = "full")]
+/// Lipsync JSON for `text` spoken in `samples` without WhisperX: word timings fitted to the audio by
+/// the built-in forced aligner when `aligned` (`--lipsync align`), else estimated (`--lipsync estimate`)
+pub fn lipsync_without_whisperx(text: &str, samples: &[f32], sample_rate: u32, aligned: bool, lipsync_with_llm: Option<&str>) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let arpabet = text_to_arpabet_with_method(text, lipsync_with_llm);
+    check_strict(text, &arpabet)?;
+    let plain = respelling::strip_respellings(text);
+    let words: Vec<(String, Vec<String>)> = plain.split_whitespace().map(str::to_string).zip(arpabet.iter().map(|(p, _)| p.clone())).collect();
+    let methods: Vec<&str> = arpabet.iter().map(|(_, method)| *method).collect();
+    Ok(if aligned {
+        let (timings, phones) = forced_align::align_word_timings(&words, samples, sample_rate);
+        estimate::timed_lipsync_json("align", &plain, &timings, &phones, &methods)
+    } else {
+        let timings = estimate::estimate_word_timings(&words, samples, sample_rate);
+        estimate::estimated_lipsync_json(&plain, &timings, &methods)
+    })
+}
+
+#[cfg(feature = "full")]
+fn write_lipsync_without_whisperx(text: &str, samples: &[f32], sample_rate: u32, aligned: bool, output_json: Option<&str>, lipsync_with_llm: Option<&str>) -> Result<Option<serde_json::Value>, Box<dyn std::error::Error>> {
+    let label = if aligned { "[Align]" } else { "[Estimate]" };
+    write_lipsync_json(label, lipsync_without_whisperx(text, samples, sample_rate, aligned, lipsync_with_llm)?, output_json)
+}
+
+#[cfg(feature = "full")]
+/// Write lipsync JSON to `output_json`, or hand it back when there's no file for it
+fn write_lipsync_json(label: &str, json: serde_json::Value, output_json: Option<&str>) -> Result<Option<serde_json::Value>, Box<dyn std::error::Error>> {
+    let Some(path) = output_json else {
+        return Ok(Some(json));
+    };
+    fs::write(path, serde_json::to_string_pretty(&json)?).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    log::info!("{} Lipsync JSON written to {}", label.cyan(), path);
+    Ok(None)
+}
+
+#[cfg(feature = "full")]
+/// Write lipsync JSON from Piper's own phoneme timings (see [`synth_with_phoneme_timings`]). Words are
+/// labelled from `text`, or from `spoken` when normalization expanded numbers and the like; if neither
+/// has one word per timed word, each word is labelled with its IPA. Without `output_json` the JSON is
+/// returned instead.
+pub fn write_piper_lipsync(text: &str, spoken: &str, timings: &LipSyncData, output_json: Option<&str>, lipsync_with_llm: Option<&str>) -> Result<Option<serde_json::Value>, Box<dyn std::error::Error>> {
+    let spans = piper_timings::word_spans(timings);
+    let plain = respelling::strip_respellings(text);
+    let labels = [plain.as_str(), spoken].into_iter().find(|t| t.split_whitespace().count() == spans.len());
+    let arpabet = match labels {
+        Some(labels) => {
+            let arpabet = text_to_arpabet_with_method(labels, lipsync_with_llm);
+            check_strict(labels, &arpabet)?;
+            arpabet
+        }
+        None => {
+            log::debug!("Piper timed {} words; labelling them with IPA", spans.len());
+            Vec::new()
+        }
+    };
+    let mut labels = labels.map(|l| l.split_whitespace());
+    let words: Vec<WordTiming> = spans
+        .iter()
+        .enumerate()
+        .map(|(i, span)| {
+            let ipa: String = span.iter().map(|p| p.phoneme.as_str()).collect();
+            WordTiming {
+                word: labels.as_mut().and_then(|l| l.next()).map_or(ipa, str::to_string),
+                start: span[0].start_time,
+                end: span[span.len() - 1].end_time,
+                phonemes: arpabet.get(i).map(|(p, _)| p.clone()).unwrap_or_default(),
+            }
+        })
+        .collect();
+    let phones: Vec<Vec<(String, f32, f32)>> = spans.iter().map(|span| span.iter().map(|p| (p.phoneme.clone(), p.start_time, p.end_time)).collect()).collect();
+    let methods: Vec<&str> = (0..words.len()).map(|i| arpabet.get(i).map_or("piper", |(_, method)| *method)).collect();
+    write_lipsync_json("[Piper]", estimate::timed_lipsync_json("piper", &plain, &words, &phones, &methods), output_json)
+}
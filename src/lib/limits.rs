@@ -0,0 +1,103 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::Mutex;
+
+/// Bytes held per synthesized sample
+const BYTES_PER_SAMPLE: usize = std::mem::size_of::<f32>();
+
+/// Limits on a single synthesis request, so a server embedding the crate can't be tied up by one
+/// enormous request. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResourceLimits {
+    /// Longest text accepted, in characters
+    pub max_text_chars: Option<usize>,
+    /// Longest audio synthesized, in seconds; synthesis stops as soon as it's exceeded
+    pub max_audio_seconds: Option<f32>,
+    /// Most memory the synthesized audio may take, in bytes (4 per sample)
+    pub max_memory_bytes: Option<usize>,
+}
+
+/// A request refused or stopped for exceeding its [`ResourceLimits`]. Synthesis functions return it
+/// as [`crate::Error::LimitExceeded`]; others return it boxed, for `error.downcast_ref::<LimitError>()`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LimitError {
+    TextTooLong { chars: usize, max: usize },
+    AudioTooLong { seconds: f32, max: f32 },
+    MemoryExceeded { bytes: usize, max: usize },
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::TextTooLong { chars, max } => write!(f, "Text is {} characters, over the limit of {}", chars, max),
+            LimitError::AudioTooLong { seconds, max } => write!(f, "Audio reached {:.1}s, over the limit of {:.1}s", seconds, max),
+            LimitError::MemoryExceeded { bytes, max } => write!(f, "Audio needs {} bytes, over the memory limit of {}", bytes, max),
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+impl ResourceLimits {
+    pub fn is_unlimited(&self) -> bool {
+        *self == ResourceLimits::default()
+    }
+
+    /// Check a request's text before any synthesis happens
+    pub fn check_text(&self, text: &str) -> Result<(), LimitError> {
+        let chars = text.chars().count();
+        match self.max_text_chars {
+            Some(max) if chars > max => Err(LimitError::TextTooLong { chars, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the audio synthesized so far, so a request can be stopped partway
+    pub fn check_samples(&self, samples: usize, sample_rate: u32) -> Result<(), LimitError> {
+        let seconds = samples as f32 / sample_rate as f32;
+        if let Some(max) = self.max_audio_seconds
+            && seconds > max
+        {
+            return Err(LimitError::AudioTooLong { seconds, max });
+        }
+        let bytes = samples * BYTES_PER_SAMPLE;
+        match self.max_memory_bytes {
+            Some(max) if bytes > max => Err(LimitError::MemoryExceeded { bytes, max }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Pitch factors a request may ask for. Far outside this, the resampling behind a shift needs buffers
+/// too large to allocate.
+pub const PITCH_RANGE: RangeInclusive<f32> = 0.25..=4.0;
+
+/// Tempo factors a request may ask for, for the same reason as [`PITCH_RANGE`]
+pub const TEMPO_RANGE: RangeInclusive<f32> = 0.1..=10.0;
+
+/// Check a requested pitch factor against [`PITCH_RANGE`]; NaN and infinities are refused too
+pub fn check_pitch(pitch: f32) -> Result<(), String> {
+    match PITCH_RANGE.contains(&pitch) {
+        true => Ok(()),
+        false => Err(format!("Pitch must be between {} and {}, got {}", PITCH_RANGE.start(), PITCH_RANGE.end(), pitch)),
+    }
+}
+
+/// Check a requested tempo factor against [`TEMPO_RANGE`]; NaN and infinities are refused too
+pub fn check_tempo(tempo: f32) -> Result<(), String> {
+    match TEMPO_RANGE.contains(&tempo) {
+        true => Ok(()),
+        false => Err(format!("Tempo must be between {} and {}, got {}", TEMPO_RANGE.start(), TEMPO_RANGE.end(), tempo)),
+    }
+}
+
+static LIMITS: Mutex<ResourceLimits> = Mutex::new(ResourceLimits { max_text_chars: None, max_audio_seconds: None, max_memory_bytes: None });
+
+/// Set the limits every synthesis request is held to
+pub fn set_limits(limits: ResourceLimits) {
+    *LIMITS.lock().unwrap() = limits;
+}
+
+pub fn limits() -> ResourceLimits {
+    *LIMITS.lock().unwrap()
+}
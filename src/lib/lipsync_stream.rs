@@ -0,0 +1,89 @@
+use crate::visemes::viseme_track;
+use crate::WordTiming;
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// Default length of each audio frame
+pub const DEFAULT_CHUNK_SECONDS: f32 = 0.1;
+
+/// One frame of a multiplexed lipsync stream: audio and the word and viseme events that go with it, in
+/// one newline-delimited JSON stream. Events are sent just before the audio frame they start in, so a
+/// client can schedule them as the audio arrives instead of running its own alignment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamFrame {
+    /// First frame: how to decode the audio frames
+    Header { sample_rate: u32, channels: u16, encoding: String, duration: f32 },
+    Word { word: String, start: f32, end: f32, phonemes: Vec<String> },
+    Viseme { viseme: String, start: f32, end: f32 },
+    /// Base64 little-endian 16-bit PCM starting at `start` seconds
+    Audio { start: f32, samples: usize, data: String },
+    /// Last frame
+    End { duration: f32 },
+}
+
+fn pcm16_base64(samples: &[f32]) -> String {
+    let bytes: Vec<u8> = samples.iter().flat_map(|s| ((s * 32767.0).clamp(-32768.0, 32767.0) as i16).to_le_bytes()).collect();
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+/// Multiplex mono audio with its word timings (and the visemes derived from them) into stream frames,
+/// with audio frames of `chunk_seconds`
+pub fn lipsync_frames(samples: &[f32], sample_rate: u32, words: &[WordTiming], chunk_seconds: f32) -> Vec<StreamFrame> {
+    let duration = samples.len() as f32 / sample_rate as f32;
+    let mut events: Vec<(f32, StreamFrame)> = words
+        .iter()
+        .map(|w| (w.start, StreamFrame::Word { word: w.word.clone(), start: w.start, end: w.end, phonemes: w.phonemes.clone() }))
+        .chain(viseme_track(words).into_iter().map(|v| (v.start, StreamFrame::Viseme { viseme: v.viseme, start: v.start, end: v.end })))
+        .collect();
+    // Stable, so a word comes before the visemes that start with it
+    events.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut events = events.into_iter().peekable();
+
+    let mut frames = vec![StreamFrame::Header { sample_rate, channels: 1, encoding: "pcm_s16le".to_string(), duration }];
+    let chunk = ((chunk_seconds * sample_rate as f32) as usize).max(1);
+    for (i, audio) in samples.chunks(chunk).enumerate() {
+        let start = (i * chunk) as f32 / sample_rate as f32;
+        let end = start + audio.len() as f32 / sample_rate as f32;
+        while let Some((_, event)) = events.next_if(|(time, _)| *time < end) {
+            frames.push(event);
+        }
+        frames.push(StreamFrame::Audio { start, samples: audio.len(), data: pcm16_base64(audio) });
+    }
+    // Events past the end of the audio (e.g. trailing silence) still reach the client
+    frames.extend(events.map(|(_, event)| event));
+    frames.push(StreamFrame::End { duration });
+    frames
+}
+
+/// Write frames as newline-delimited JSON, flushing after each frame
+pub fn write_ndjson<W: Write>(writer: &mut W, frames: &[StreamFrame]) -> Result<(), Box<dyn std::error::Error>> {
+    for frame in frames {
+        serde_json::to_writer(&mut *writer, frame)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Word timings for `samples` estimated from the phonemes of `text`, the plain text that was spoken
+pub fn estimated_words(text: &str, samples: &[f32], sample_rate: u32) -> Vec<WordTiming> {
+    let phonemes = crate::text_to_arpabet(text, None);
+    let words: Vec<(String, Vec<String>)> = crate::respelling::strip_respellings(text).split_whitespace().map(str::to_string).zip(phonemes).collect();
+    crate::estimate::estimate_word_timings(&words, samples, sample_rate)
+}
+
+/// Write `<name>.stream.ndjson` for a rendered WAV. Word timings come from the lipsync JSON when there is
+/// one, and are otherwise estimated from the text's phonemes (see [`crate::estimate`]).
+pub fn write_lipsync_stream(stream_path: &str, wav_path: &str, lipsync_json: Option<&str>, text: &str, chunk_seconds: f32) -> Result<usize, Box<dyn std::error::Error>> {
+    let (samples, sample_rate) = crate::read_wav(wav_path)?;
+    let words = match lipsync_json {
+        Some(json_path) => crate::read_word_timings(json_path)?,
+        None => estimated_words(text, &samples, sample_rate),
+    };
+    let frames = lipsync_frames(&samples, sample_rate, &words, chunk_seconds);
+    let mut file = std::io::BufWriter::new(std::fs::File::create(stream_path)?);
+    write_ndjson(&mut file, &frames)?;
+    Ok(frames.len())
+}
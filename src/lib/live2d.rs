@@ -0,0 +1,106 @@
+use crate::visemes::{mouth_form, mouth_openness, VisemeEvent};
+use serde_json::json;
+
+/// Live2D standard parameter for jaw opening (0.0 closed .. 1.0 open)
+pub const PARAM_MOUTH_OPEN_Y: &str = "ParamMouthOpenY";
+/// Live2D standard parameter for lip shape (-1.0 rounded .. 1.0 smile)
+pub const PARAM_MOUTH_FORM: &str = "ParamMouthForm";
+
+/// Time to blend from one mouth shape into the next, in seconds
+const BLEND_SECONDS: f32 = 0.04;
+
+/// A parameter curve as `(time, value)` points joined by linear segments
+#[derive(Debug, Clone)]
+pub struct ParameterCurve {
+    pub id: String,
+    pub points: Vec<(f32, f32)>,
+}
+
+impl ParameterCurve {
+    /// Flatten into motion3.json `Segments`: the first point, then `0, time, value` per linear segment
+    fn segments(&self) -> Vec<f32> {
+        let mut segments = Vec::with_capacity(self.points.len() * 3);
+        for (i, (time, value)) in self.points.iter().enumerate() {
+            if i > 0 {
+                segments.push(0.0);
+            }
+            segments.push(round3(*time));
+            segments.push(round3(*value));
+        }
+        segments
+    }
+}
+
+fn round3(v: f32) -> f32 {
+    (v * 1000.0).round() / 1000.0
+}
+
+/// Build a curve that holds `value_of(viseme)` over each event, blending briefly into each new shape
+fn curve_for(id: &str, visemes: &[VisemeEvent], duration: f32, value_of: fn(&str) -> f32) -> ParameterCurve {
+    let mut points: Vec<(f32, f32)> = vec![(0.0, 0.0)];
+    for event in visemes {
+        let value = value_of(&event.viseme);
+        let (last_time, last_value) = *points.last().unwrap();
+        if (value - last_value).abs() < 1e-4 {
+            continue;
+        }
+        // Hold the previous shape until just before this one starts, then blend in
+        let blend_start = (event.start - BLEND_SECONDS).max(last_time);
+        if blend_start > last_time {
+            points.push((blend_start, last_value));
+        }
+        points.push((event.start.max(blend_start + 1e-3), value));
+    }
+    let (last_time, last_value) = *points.last().unwrap();
+    let duration = duration.max(last_time);
+    if last_value.abs() > 1e-4 {
+        points.push((duration.max(last_time + BLEND_SECONDS), 0.0));
+    } else if duration > last_time {
+        points.push((duration, 0.0));
+    }
+    ParameterCurve { id: id.to_string(), points }
+}
+
+/// Map a viseme track onto `ParamMouthOpenY` and `ParamMouthForm` curves
+pub fn mouth_curves(visemes: &[VisemeEvent], duration: f32) -> Vec<ParameterCurve> {
+    vec![
+        curve_for(PARAM_MOUTH_OPEN_Y, visemes, duration, mouth_openness),
+        curve_for(PARAM_MOUTH_FORM, visemes, duration, mouth_form),
+    ]
+}
+
+/// Build a Cubism `motion3.json` document from parameter curves
+pub fn motion3_json(curves: &[ParameterCurve], fps: f32) -> serde_json::Value {
+    let duration = curves
+        .iter()
+        .filter_map(|c| c.points.last().map(|(t, _)| *t))
+        .fold(0.0f32, f32::max);
+    let segment_count: usize = curves.iter().map(|c| c.points.len().saturating_sub(1)).sum();
+    let point_count: usize = curves.iter().map(|c| c.points.len()).sum();
+    let curves_json: Vec<serde_json::Value> = curves
+        .iter()
+        .map(|c| json!({ "Target": "Parameter", "Id": c.id, "Segments": c.segments() }))
+        .collect();
+    json!({
+        "Version": 3,
+        "Meta": {
+            "Duration": round3(duration),
+            "Fps": fps,
+            "Loop": false,
+            "AreBeziersRestricted": true,
+            "CurveCount": curves.len(),
+            "TotalSegmentCount": segment_count,
+            "TotalPointCount": point_count,
+            "UserDataCount": 0,
+            "TotalUserDataSize": 0
+        },
+        "Curves": curves_json
+    })
+}
+
+/// Write a Live2D `motion3.json` mouth animation for a viseme track lasting `duration` seconds
+pub fn write_motion3_json(path: &str, visemes: &[VisemeEvent], duration: f32, fps: f32) -> Result<(), Box<dyn std::error::Error>> {
+    let motion = motion3_json(&mouth_curves(visemes, duration), fps);
+    std::fs::write(path, serde_json::to_string_pretty(&motion)?)?;
+    Ok(())
+}
@@ -0,0 +1,88 @@
+#[cfg(feature = "full")]
+use serde_json::json;
+use crate::visemes::ARPABET_PHONEMES;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Placeholder replaced with the word in prompt templates
+pub const WORD_PLACEHOLDER: &str = "{word}";
+
+const DEFAULT_PROMPT: &str = "Give only the ARPAbet phonemes for the word '{word}'. Respond ONLY with the ARPAbet phonemes, space-separated, no explanation, no punctuation, no extra words.\nExample: hello => HH AH0 L OW1\nNow, {word} =>";
+
+/// How unknown words fall back to an Ollama model for phonemes
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LlmFallbackSettings {
+    /// Most words sent to the model per lipsync run; the rest get empty phonemes
+    pub max_words: Option<usize>,
+    /// Sampling temperature; `None` uses the model's default
+    pub temperature: Option<f32>,
+    /// Prompt with a `{word}` placeholder; `None` uses the built-in prompt
+    pub prompt_template: Option<String>,
+    /// Fail lipsync instead of writing empty phonemes for words no source could pronounce
+    pub strict: bool,
+}
+
+impl LlmFallbackSettings {
+    /// Read a prompt template file, which must contain the `{word}` placeholder
+    pub fn load_prompt_template(path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+        let template = std::fs::read_to_string(path).map_err(|e| format!("Failed to read prompt template {}: {}", path.display(), e))?;
+        if !template.contains(WORD_PLACEHOLDER) {
+            return Err(format!("Prompt template {} has no {} placeholder", path.display(), WORD_PLACEHOLDER).into());
+        }
+        Ok(template)
+    }
+
+    pub fn prompt(&self, word: &str) -> String {
+        self.prompt_template.as_deref().unwrap_or(DEFAULT_PROMPT).replace(WORD_PLACEHOLDER, word)
+    }
+}
+
+static SETTINGS: Mutex<Option<LlmFallbackSettings>> = Mutex::new(None);
+
+pub fn set_settings(settings: LlmFallbackSettings) {
+    *SETTINGS.lock().unwrap() = Some(settings);
+}
+
+pub fn settings() -> LlmFallbackSettings {
+    SETTINGS.lock().unwrap().clone().unwrap_or_default()
+}
+
+/// Keep only valid ARPAbet tokens from a model response
+pub fn parse_arpabet_response(response: &str) -> Vec<String> {
+    let valid = |token: &str| {
+        let (base, stress) = token.split_at(token.trim_end_matches(|c: char| c.is_ascii_digit()).len());
+        // Only vowels carry a 0/1/2 stress marker
+        ARPABET_PHONEMES.contains(&base) && (stress.is_empty() || (stress.len() == 1 && stress <= "2" && base.starts_with(['A', 'E', 'I', 'O', 'U'])))
+    };
+    response.split_whitespace().filter(|s| valid(s)).map(str::to_string).collect()
+}
+
+#[cfg(feature = "full")]
+/// Base URL of the Ollama server (`OLLAMA_HOST`, default localhost)
+fn ollama_url() -> String {
+    let host = std::env::var("OLLAMA_HOST").unwrap_or_else(|_| "127.0.0.1:11434".to_string());
+    if host.starts_with("http://") || host.starts_with("https://") { host } else { format!("http://{}", host) }
+}
+
+#[cfg(feature = "full")]
+/// Ask an Ollama model for the ARPAbet phonemes of a word not found in CMUdict
+pub fn arpabet_from_ollama(word: &str, model: &str, settings: &LlmFallbackSettings) -> Option<Vec<String>> {
+    let mut request = json!({ "model": model, "prompt": settings.prompt(word), "stream": false });
+    if let Some(temperature) = settings.temperature {
+        request["options"] = json!({ "temperature": temperature });
+    }
+    let body = crate::http::post_json(&format!("{}/api/generate", ollama_url()), &request).ok()?;
+    let response = body.get("response")?.as_str()?.trim();
+    let all_tokens: Vec<&str> = response.split_whitespace().collect();
+    let filtered = parse_arpabet_response(response);
+    if filtered.is_empty() {
+        log::info!("[ARPAbet] {} => {:?} (from Ollama/{}, but no valid ARPAbet tokens)", word, all_tokens, model);
+        return None;
+    }
+    if filtered.len() != all_tokens.len() {
+        log::info!("[ARPAbet] {} => {:?} (filtered from {:?}, Ollama/{})", word, filtered, all_tokens, model);
+    } else {
+        log::info!("[ARPAbet] {} => {:?} (from Ollama/{})", word, filtered, model);
+    }
+    Some(filtered)
+}
@@ -0,0 +1,142 @@
+use crate::{get_app_data_dir, Voice};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A user-trained Piper voice added with `voices add-local`. Its files are copied into the models
+/// directory as `<id>.onnx` and `<id>.onnx.json`, like a downloaded voice, so everything that takes a
+/// voice ID can use it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocalVoice {
+    /// Language name from the voice's config (`language.name_english`), or its code
+    pub language: String,
+    /// `audio.quality` from the config, e.g. `medium`
+    pub quality: String,
+    /// Where the model was added from; reinstalls copy it again from here
+    pub model: PathBuf,
+    pub config: PathBuf,
+}
+
+impl LocalVoice {
+    /// The voice as a catalog entry, with the source files as its model and config paths
+    pub fn voice(&self, id: &str) -> Voice {
+        Voice {
+            id: id.to_string(),
+            display_name: format!("{} ({}, local)", id.replace('_', " "), self.quality),
+            language: self.language.clone(),
+            quality: self.quality.clone(),
+            model_path: self.model.to_string_lossy().into_owned(),
+            config_path: self.config.to_string_lossy().into_owned(),
+        }
+    }
+}
+
+/// `local_voices.json` in the app data directory: local voices keyed by voice ID
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LocalVoiceRegistry {
+    pub voices: BTreeMap<String, LocalVoice>,
+}
+
+impl LocalVoiceRegistry {
+    /// Read the registry; a missing file is an empty registry
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(path)?;
+        serde_json::from_str(&contents).map_err(|e| format!("Invalid local voice registry {}: {}", path.display(), e).into())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+pub fn registry_path() -> PathBuf {
+    get_app_data_dir().join("local_voices.json")
+}
+
+/// Every registered local voice. A registry that can't be read is reported and treated as empty, so
+/// the catalog voices still work.
+pub fn local_voices() -> BTreeMap<String, LocalVoice> {
+    match LocalVoiceRegistry::load(&registry_path()) {
+        Ok(registry) => registry.voices,
+        Err(e) => {
+            log::warn!("[Local voices] {}", e);
+            BTreeMap::new()
+        }
+    }
+}
+
+pub fn find_local_voice(id: &str) -> Option<LocalVoice> {
+    local_voices().remove(id)
+}
+
+/// Whether `id` can name a local voice: letters, digits, `_`, `-`, and `.`, not starting with `.`
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && !id.starts_with('.') && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// Register the Piper voice at `model` and `config` as `id` and install it into the models directory.
+/// Adding an ID again replaces it, e.g. after retraining. Catalog voice IDs can't be reused.
+pub fn add_local_voice(id: &str, model: &Path, config: &Path) -> crate::error::Result<Voice> {
+    if !is_valid_id(id) {
+        return Err(format!("Invalid voice ID '{}': use letters, digits, '_', '-', and '.'", id).into());
+    }
+    if crate::backend::registered_backend(id).is_some() || crate::catalog_voices().iter().any(|voice| voice.id == id) {
+        return Err(format!("{} is a catalog voice; choose another ID", id).into());
+    }
+    for path in [model, config] {
+        if !path.is_file() {
+            return Err(format!("{} not found", path.display()).into());
+        }
+    }
+    let parsed: serde_json::Value = serde_json::from_str(&fs::read_to_string(config)?).map_err(|e| format!("{} isn't a Piper voice config: {}", config.display(), e))?;
+    if crate::config_sample_rate(&parsed).is_none() {
+        return Err(format!("{} isn't a Piper voice config: it has no audio.sample_rate", config.display()).into());
+    }
+    let language = ["/language/name_english", "/language/code", "/espeak/voice"].iter().find_map(|pointer| parsed.pointer(pointer).and_then(|v| v.as_str()));
+    let local = LocalVoice {
+        language: language.unwrap_or("Custom").to_string(),
+        quality: parsed.pointer("/audio/quality").and_then(|v| v.as_str()).unwrap_or("medium").to_string(),
+        model: std::path::absolute(model)?,
+        config: std::path::absolute(config)?,
+    };
+
+    let path = registry_path();
+    let mut registry = LocalVoiceRegistry::load(&path)?;
+    registry.voices.insert(id.to_string(), local.clone());
+    registry.save(&path)?;
+    // The previous files, if the ID was added before, are replaced by the new ones
+    crate::remove_voice_files(id)?;
+    let voice = local.voice(id);
+    crate::download_voice_files(&voice)?;
+    Ok(voice)
+}
+
+/// Drop a voice from the registry, leaving any installed files to [`crate::remove_voice_files`].
+/// Returns whether it was registered.
+pub fn forget_local_voice(id: &str) -> crate::error::Result<bool> {
+    let path = registry_path();
+    let mut registry = LocalVoiceRegistry::load(&path)?;
+    if registry.voices.remove(id).is_none() {
+        return Ok(false);
+    }
+    registry.save(&path)?;
+    Ok(true)
+}
+
+/// Copy a local voice's files into place, as its download
+pub(crate) fn install(id: &str, local: &LocalVoice, model_path: &Path, config_path: &Path) -> crate::error::Result<()> {
+    for (source, target) in [(&local.model, model_path), (&local.config, config_path)] {
+        if !target.exists() {
+            fs::copy(source, target).map_err(|e| format!("Failed to copy {} for local voice {}: {}", source.display(), id, e))?;
+        }
+    }
+    Ok(())
+}
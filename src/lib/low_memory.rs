@@ -0,0 +1,72 @@
+use crate::streaming::SynthStream;
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by `--low-memory`; read wherever synthesis or playback picks a strategy
+static LOW_MEMORY: AtomicBool = AtomicBool::new(false);
+
+/// Enable the low-memory profile for Pi-class hardware:
+/// sentence-by-sentence synthesis on one thread, int8 models when installed, and playback through `aplay`
+pub fn set_enabled(enabled: bool) {
+    LOW_MEMORY.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    LOW_MEMORY.load(Ordering::Relaxed)
+}
+
+/// Start `aplay` reading raw 16-bit PCM (mono, or stereo under `--channels 2`) from stdin, writing straight to the ALSA device
+fn spawn_aplay(sample_rate: u32) -> Option<Child> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let mut command = Command::new("aplay");
+    let channels = crate::stereo::stereo_output().channels.to_string();
+    command.args(["-q", "-t", "raw", "-f", "S16_LE", "-c", &channels, "-r", &sample_rate.to_string()]);
+    // ALSA device names are what `devices` lists on Linux, so `--device` works here too
+    if let Some(device) = crate::devices::output_device() {
+        command.args(["-D", &device]);
+    }
+    command
+        .stdin(Stdio::piped())
+        .spawn()
+        .ok()
+}
+
+/// Mono samples as 16-bit PCM in the current channel layout
+fn to_pcm16(samples: &[f32]) -> Vec<u8> {
+    crate::stereo::stereo_output()
+        .interleave(samples)
+        .iter()
+        .flat_map(|s| ((s * 32767.0).clamp(-32768.0, 32767.0) as i16).to_le_bytes())
+        .collect()
+}
+
+/// Play samples through `aplay`. Returns `false` when `aplay` isn't available so the caller can fall back to rodio.
+pub fn play_alsa(samples: &[f32], sample_rate: u32) -> bool {
+    let Some(mut child) = spawn_aplay(sample_rate) else {
+        return false;
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(&to_pcm16(samples));
+    }
+    let _ = child.wait();
+    true
+}
+
+/// Play a [`SynthStream`] one sentence at a time as it's synthesized, so only the current sentence is held in memory
+pub fn say_streamed(stream: SynthStream) -> Result<(), Box<dyn std::error::Error>> {
+    let sample_rate = stream.sample_rate;
+    if let Some(mut child) = spawn_aplay(sample_rate) {
+        let mut stdin = child.stdin.take().ok_or("aplay has no stdin")?;
+        for samples in stream {
+            stdin.write_all(&to_pcm16(&samples?))?;
+        }
+        drop(stdin);
+        child.wait()?;
+        return Ok(());
+    }
+    // No aplay: queue each sentence on a rodio sink as soon as it's ready
+    crate::playback::play_streamed(stream)
+}
@@ -0,0 +1,263 @@
+//! Markdown as input text (`--input-format markdown`): formatting is stripped, code blocks are skipped
+//! or announced, and headings, list items, and table rows become sentences of their own, so READMEs
+//! and notes read sensibly. Headings and paragraphs come out separated by blank lines, which the
+//! paragraph pause (see [`crate::pauses`]) turns into longer pauses.
+
+#[cfg(feature = "full")]
+use clap::ValueEnum;
+use std::time::Duration;
+
+/// How the input text is read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "full", derive(ValueEnum))]
+pub enum InputFormat {
+    /// As written
+    #[default]
+    Plain,
+    /// As Markdown, see [`markdown_to_text`]
+    Markdown,
+}
+
+/// What happens to fenced code blocks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "full", derive(ValueEnum))]
+pub enum CodeBlocks {
+    /// Leave them out
+    #[default]
+    Skip,
+    /// Say that one was left out, with its language if the fence names one ("Rust code block omitted.")
+    Announce,
+}
+
+/// Pause at headings and between paragraphs when no `--paragraph-pause` is given
+pub const PARAGRAPH_PAUSE: Duration = Duration::from_millis(800);
+
+/// End a heading, list item, or table row with a full stop, so it isn't run into the next line
+fn as_sentence(text: &str) -> String {
+    let text = text.trim();
+    if text.is_empty() || text.ends_with(['.', '!', '?', ':', ';', '…']) { text.to_string() } else { format!("{}.", text) }
+}
+
+/// A code fence (three or more backticks or tildes), with the language after it
+fn code_fence(line: &str) -> Option<(&str, &str)> {
+    let marker = if line.starts_with("```") { '`' } else if line.starts_with("~~~") { '~' } else { return None };
+    let len = line.find(|c| c != marker).unwrap_or(line.len());
+    Some((&line[..len], line[len..].trim()))
+}
+
+/// A line of only `-`, `*`, or `_` (three or more, spaces allowed): a thematic break
+fn is_rule(line: &str) -> bool {
+    let marks: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3 && ['-', '*', '_'].iter().any(|&mark| marks.chars().all(|c| c == mark))
+}
+
+/// The text of a list item, without its bullet or number and any task box, or `None` if `line` isn't one
+fn list_item(line: &str) -> Option<&str> {
+    let rest = ["- ", "* ", "+ "].iter().find_map(|bullet| line.strip_prefix(bullet)).or_else(|| {
+        let digits = line.find(|c: char| !c.is_ascii_digit()).filter(|&n| n > 0)?;
+        line[digits..].strip_prefix(". ").or_else(|| line[digits..].strip_prefix(") "))
+    })?;
+    let rest = rest.trim_start();
+    Some(["[ ] ", "[x] ", "[X] "].iter().find_map(|task| rest.strip_prefix(task)).unwrap_or(rest))
+}
+
+/// Index just past the `]` closing the `[` at `open`, allowing nested brackets
+fn closing_bracket(chars: &[char], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, &c) in chars.iter().enumerate().skip(open) {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Index just past the `)` of a link destination starting at `open`
+fn closing_paren(chars: &[char], open: usize) -> Option<usize> {
+    chars.get(open).filter(|&&c| c == '(')?;
+    chars[open..].iter().position(|&c| c == ')').map(|close| open + close + 1)
+}
+
+/// Strip inline formatting: emphasis, code spans, links (keeping their text), images (keeping their alt
+/// text), footnote references, HTML tags, and backslash escapes
+pub fn strip_inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut output = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let previous = i.checked_sub(1).map(|p| chars[p]);
+        match c {
+            '\\' if chars.get(i + 1).is_some_and(|c| c.is_ascii_punctuation()) => {
+                output.push(chars[i + 1]);
+                i += 2;
+            }
+            '`' => {
+                let run = chars[i..].iter().take_while(|&&c| c == '`').count();
+                let fence: String = "`".repeat(run);
+                let rest: String = chars[i + run..].iter().collect();
+                match rest.find(&fence) {
+                    Some(end) => {
+                        output.push_str(rest[..end].trim());
+                        i += run + rest[..end].chars().count() + run;
+                    }
+                    None => i += run,
+                }
+            }
+            // An image, read as its alt text
+            '!' if chars.get(i + 1) == Some(&'[') && closing_bracket(&chars, i + 1).and_then(|close| closing_paren(&chars, close)).is_some() => i += 1,
+            '[' => {
+                let Some(close) = closing_bracket(&chars, i) else {
+                    output.push(c);
+                    i += 1;
+                    continue;
+                };
+                let label: String = chars[i + 1..close - 1].iter().collect();
+                if label.starts_with('^') {
+                    // A footnote reference
+                    i = close;
+                    continue;
+                }
+                let after = closing_paren(&chars, close).or_else(|| (chars.get(close) == Some(&'[')).then(|| closing_bracket(&chars, close)).flatten());
+                match after {
+                    Some(end) => {
+                        output.push_str(&strip_inline(&label));
+                        i = end;
+                    }
+                    None => {
+                        output.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            '<' if chars.get(i + 1).is_some_and(|c| c.is_ascii_alphabetic() || *c == '/' || *c == '!') => match chars[i..].iter().position(|&c| c == '>') {
+                Some(end) => i += end + 1,
+                None => {
+                    output.push(c);
+                    i += 1;
+                }
+            },
+            '*' | '~' => {
+                let run = chars[i..].iter().take_while(|&&m| m == c).count();
+                let next = chars.get(i + run);
+                // A lone `*` between spaces is multiplication, a lone `~` means "about"
+                let spaced = previous.is_none_or(|p| p.is_whitespace()) && next.is_none_or(|n| n.is_whitespace());
+                if spaced || (c == '~' && run == 1) {
+                    output.extend(&chars[i..i + run]);
+                }
+                i += run;
+            }
+            '_' => {
+                let run = chars[i..].iter().take_while(|&&m| m == '_').count();
+                let next = chars.get(i + run);
+                // Underscores inside a word, as in snake_case, are kept
+                if previous.is_some_and(|p| p.is_alphanumeric()) && next.is_some_and(|n| n.is_alphanumeric()) {
+                    output.extend(&chars[i..i + run]);
+                }
+                i += run;
+            }
+            _ => {
+                output.push(c);
+                i += 1;
+            }
+        }
+    }
+    output
+}
+
+/// Turn Markdown into text to read. Paragraphs, headings, block quotes, list items, and table rows are
+/// kept; front matter, HTML comments, and thematic breaks are dropped; code blocks follow `code`.
+/// Headings and paragraphs are separated by blank lines, other blocks by line breaks.
+pub fn markdown_to_text(markdown: &str, code: CodeBlocks) -> String {
+    let mut blocks: Vec<String> = Vec::new();
+    let mut paragraph: Vec<String> = Vec::new();
+    let flush = |paragraph: &mut Vec<String>, blocks: &mut Vec<String>| {
+        if !paragraph.is_empty() {
+            blocks.push(paragraph.join("\n"));
+            paragraph.clear();
+        }
+    };
+    let mut lines = markdown.lines().peekable();
+    // YAML front matter
+    if lines.peek().is_some_and(|line| line.trim_end() == "---") {
+        lines.next();
+        for line in lines.by_ref() {
+            if matches!(line.trim_end(), "---" | "...") {
+                break;
+            }
+        }
+    }
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        if let Some((fence, language)) = code_fence(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            for line in lines.by_ref() {
+                if line.trim().starts_with(fence) && line.trim().trim_start_matches(fence.chars().next().unwrap()).trim().is_empty() {
+                    break;
+                }
+            }
+            if code == CodeBlocks::Announce {
+                let mut language = language.split(|c: char| c.is_whitespace() || c == '{' || c == ',').next().unwrap_or("").chars();
+                blocks.push(match language.next() {
+                    Some(first) => format!("{}{} code block omitted.", first.to_uppercase(), language.as_str()),
+                    None => "Code block omitted.".to_string(),
+                });
+            }
+            continue;
+        }
+        if trimmed.starts_with("<!--") {
+            flush(&mut paragraph, &mut blocks);
+            if !trimmed.contains("-->") {
+                for line in lines.by_ref() {
+                    if line.contains("-->") {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        if trimmed.is_empty() || is_rule(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            continue;
+        }
+        let hashes = trimmed.find(|c| c != '#').unwrap_or(trimmed.len());
+        if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(as_sentence(&strip_inline(trimmed[hashes..].trim_end_matches('#'))));
+            continue;
+        }
+        // A setext heading: the paragraph so far, underlined with `===` or `---`
+        if let Some(next) = lines.peek().map(|next| next.trim())
+            && !next.is_empty()
+            && (next.chars().all(|c| c == '=') || next.chars().all(|c| c == '-'))
+            && paragraph.is_empty()
+            && list_item(trimmed).is_none()
+        {
+            lines.next();
+            blocks.push(as_sentence(&strip_inline(trimmed)));
+            continue;
+        }
+        let quoted = trimmed.trim_start_matches(['>', ' ']);
+        if let Some(item) = list_item(quoted) {
+            paragraph.push(as_sentence(&strip_inline(item)));
+        } else if quoted.starts_with('|') || quoted.ends_with('|') {
+            // Table rows are read cell by cell; the row under the header is dropped
+            let cells: Vec<String> = quoted.trim_matches('|').split('|').map(|cell| strip_inline(cell.trim())).filter(|cell| !cell.is_empty()).collect();
+            if !cells.iter().all(|cell| cell.chars().all(|c| matches!(c, '-' | ':' | ' '))) {
+                paragraph.push(as_sentence(&cells.join(", ")));
+            }
+        } else if !quoted.is_empty() {
+            paragraph.push(strip_inline(quoted));
+        }
+    }
+    flush(&mut paragraph, &mut blocks);
+    blocks.retain(|block| !block.trim().is_empty());
+    blocks.join("\n\n")
+}
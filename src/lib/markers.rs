@@ -0,0 +1,119 @@
+use crate::tags::{append_chunks, push_chunk};
+use crate::WordTiming;
+
+/// Which boundaries to mark in an exported WAV
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "full", derive(clap::ValueEnum))]
+pub enum MarkerSet {
+    /// A marker at the start of every word, labelled with the word
+    Words,
+    /// A marker at the start of every sentence, labelled with the sentence
+    Sentences,
+    /// Both, sentences first where they coincide
+    All,
+}
+
+/// A labelled position in the audio, in sample frames
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marker {
+    pub position: u32,
+    pub label: String,
+}
+
+fn frame(seconds: f32, sample_rate: u32) -> u32 {
+    (seconds.max(0.0) as f64 * sample_rate as f64).round() as u32
+}
+
+/// Markers for aligned words at `sample_rate`, in time order
+pub fn markers(words: &[WordTiming], sample_rate: u32, set: MarkerSet) -> Vec<Marker> {
+    let mut markers = Vec::new();
+    let mut sentence: Vec<&WordTiming> = Vec::new();
+    for (i, word) in words.iter().enumerate() {
+        sentence.push(word);
+        let ends_sentence = word.word.trim_end_matches(['"', '\'', ')', '”', '’']).ends_with(['.', '!', '?', '…']) || i + 1 == words.len();
+        if ends_sentence && set != MarkerSet::Words {
+            let label = sentence.iter().map(|w| w.word.as_str()).collect::<Vec<_>>().join(" ");
+            markers.push(Marker { position: frame(sentence[0].start, sample_rate), label });
+        }
+        if ends_sentence {
+            sentence.clear();
+        }
+    }
+    if set != MarkerSet::Sentences {
+        markers.extend(words.iter().map(|w| Marker { position: frame(w.start, sample_rate), label: w.word.clone() }));
+    }
+    // Stable, so a sentence marker stays ahead of its first word
+    markers.sort_by_key(|m| m.position);
+    markers
+}
+
+/// The `cue ` chunk and `LIST/adtl` chunk of `labl` names for markers, as DAWs (Reaper, Pro Tools,
+/// Audition) read them
+fn build_marker_chunks(markers: &[Marker]) -> Vec<u8> {
+    let mut cue = (markers.len() as u32).to_le_bytes().to_vec();
+    let mut adtl = b"adtl".to_vec();
+    for (i, marker) in markers.iter().enumerate() {
+        let id = i as u32 + 1;
+        cue.extend_from_slice(&id.to_le_bytes());
+        cue.extend_from_slice(&marker.position.to_le_bytes());
+        cue.extend_from_slice(b"data");
+        cue.extend_from_slice(&0u32.to_le_bytes()); // chunk start
+        cue.extend_from_slice(&0u32.to_le_bytes()); // block start
+        cue.extend_from_slice(&marker.position.to_le_bytes()); // sample offset
+        let mut label = id.to_le_bytes().to_vec();
+        label.extend_from_slice(marker.label.as_bytes());
+        label.push(0);
+        push_chunk(&mut adtl, b"labl", &label);
+    }
+    let mut chunks = Vec::new();
+    push_chunk(&mut chunks, b"cue ", &cue);
+    push_chunk(&mut chunks, b"LIST", &adtl);
+    chunks
+}
+
+/// Append markers to an existing WAV file as `cue ` and `LIST/adtl` chunks
+pub fn write_wav_markers(path: &str, markers: &[Marker]) -> std::io::Result<()> {
+    if markers.is_empty() {
+        return Ok(());
+    }
+    append_chunks(path, &build_marker_chunks(markers))
+}
+
+/// Read back the markers of a WAV file, in cue order
+pub fn read_wav_markers(path: &str) -> std::io::Result<Vec<Marker>> {
+    let bytes = std::fs::read(path)?;
+    let u32_at = |at: usize| bytes.get(at..at + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+    let mut cues: Vec<(u32, u32)> = Vec::new();
+    let mut labels: Vec<(u32, String)> = Vec::new();
+    let mut visit = |id: &[u8], data: &[u8]| match id {
+        b"cue " => {
+            let count = data.get(0..4).map_or(0, |b| u32::from_le_bytes(b.try_into().unwrap())) as usize;
+            cues.extend(data.get(4..).unwrap_or_default().chunks_exact(24).take(count).map(|c| (u32::from_le_bytes(c[0..4].try_into().unwrap()), u32::from_le_bytes(c[20..24].try_into().unwrap()))));
+        }
+        b"labl" if data.len() >= 4 => {
+            let text = String::from_utf8_lossy(&data[4..]).trim_end_matches('\0').to_string();
+            labels.push((u32::from_le_bytes(data[0..4].try_into().unwrap()), text));
+        }
+        _ => {}
+    };
+    // Walk the top-level chunks, and one level into LIST chunks
+    let mut at = 12;
+    while let (Some(size), Some(id)) = (u32_at(at + 4), bytes.get(at..at + 4)) {
+        let data = bytes.get(at + 8..(at + 8 + size as usize).min(bytes.len())).unwrap_or_default();
+        if id == b"LIST" && data.len() >= 4 {
+            let mut inner = 4;
+            while let (Some(inner_id), Some(inner_size)) = (data.get(inner..inner + 4), data.get(inner + 4..inner + 8)) {
+                let inner_size = u32::from_le_bytes(inner_size.try_into().unwrap()) as usize;
+                visit(inner_id, data.get(inner + 8..(inner + 8 + inner_size).min(data.len())).unwrap_or_default());
+                inner += 8 + inner_size + inner_size % 2;
+            }
+        } else {
+            visit(id, data);
+        }
+        at += 8 + size as usize + size as usize % 2;
+    }
+    Ok(cues
+        .into_iter()
+        .map(|(id, position)| Marker { position, label: labels.iter().find(|(l, _)| *l == id).map(|(_, t)| t.clone()).unwrap_or_default() })
+        .collect())
+}
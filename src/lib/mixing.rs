@@ -0,0 +1,98 @@
+use crate::WordTiming;
+
+/// How background music is mixed under speech
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DuckSettings {
+    /// Music level relative to its original level, in dB
+    pub music_gain_db: f32,
+    /// How far the music drops while speech is present, in dB
+    pub duck_db: f32,
+    /// Seconds the music takes to fade down before a word
+    pub attack: f32,
+    /// Seconds the music takes to come back up after a word
+    pub release: f32,
+}
+
+impl Default for DuckSettings {
+    fn default() -> Self {
+        DuckSettings { music_gain_db: -6.0, duck_db: 12.0, attack: 0.15, release: 0.5 }
+    }
+}
+
+impl DuckSettings {
+    /// Sidecar description, so changing the mix re-renders an export
+    pub fn describe(&self) -> String {
+        format!("music_gain={}dB,duck={}dB,attack={}s,release={}s", self.music_gain_db, self.duck_db, self.attack, self.release)
+    }
+}
+
+fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// Per-sample ducking amount (0 = full music level, 1 = fully ducked) for `len` samples.
+/// The music is fully ducked during each word and ramps down/up around it, so short gaps
+/// between words stay ducked instead of pumping.
+pub fn duck_amounts(timings: &[WordTiming], len: usize, sample_rate: u32, settings: &DuckSettings) -> Vec<f32> {
+    let rate = sample_rate as f32;
+    let attack = (settings.attack * rate) as usize;
+    let release = (settings.release * rate) as usize;
+    let mut amounts = vec![0.0f32; len];
+    for word in timings {
+        let start = ((word.start * rate) as usize).min(len);
+        let end = ((word.end * rate) as usize).clamp(start, len);
+        amounts[start..end].fill(1.0);
+        let ramp_start = start.saturating_sub(attack);
+        for (i, amount) in amounts[ramp_start..start].iter_mut().enumerate() {
+            *amount = amount.max(1.0 - (start - ramp_start - i) as f32 / attack as f32);
+        }
+        let ramp_end = (end + release).min(len);
+        for (i, amount) in amounts[end..ramp_end].iter_mut().enumerate() {
+            *amount = amount.max(1.0 - i as f32 / release as f32);
+        }
+    }
+    amounts
+}
+
+/// Mix `music` under `speech`, ducking it wherever the word timings say someone is talking.
+/// The music is looped to cover the speech, faded out over the final second, and the result has the
+/// speech's length. Both inputs must be mono at `sample_rate`.
+pub fn mix_with_ducking(speech: &[f32], music: &[f32], sample_rate: u32, timings: &[WordTiming], settings: &DuckSettings) -> Vec<f32> {
+    if music.is_empty() {
+        return speech.to_vec();
+    }
+    let amounts = duck_amounts(timings, speech.len(), sample_rate, settings);
+    let base = db_to_gain(settings.music_gain_db);
+    let ducked = db_to_gain(settings.music_gain_db - settings.duck_db);
+    let fade = (sample_rate as usize).min(speech.len());
+    speech
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let gain = base + (ducked - base) * amounts[i];
+            let remaining = speech.len() - i;
+            let fade_out = if remaining < fade { remaining as f32 / fade as f32 } else { 1.0 };
+            (s + music[i % music.len()] * gain * fade_out).clamp(-1.0, 1.0)
+        })
+        .collect()
+}
+
+#[cfg(feature = "full")]
+/// Read any audio file SoX understands as mono f32 samples at `sample_rate`
+pub fn load_music(path: &str, sample_rate: u32) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+    let temp_path = std::env::temp_dir().join(format!("text_to_face_music_{}.wav", std::process::id()));
+    let output = std::process::Command::new("sox")
+        .arg(path)
+        .args(["-b", "16", "-c", "1", "-r"])
+        .arg(sample_rate.to_string())
+        .arg(&temp_path)
+        .output()
+        .map_err(|e| crate::Error::spawn_failed("sox", "Install SoX to mix in background music.", e))?;
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(format!("SoX error: {}", String::from_utf8_lossy(&output.stderr)).into());
+    }
+    let result = crate::read_wav(temp_path.to_str().unwrap());
+    let _ = std::fs::remove_file(&temp_path);
+    Ok(result?.0)
+}
@@ -0,0 +1,108 @@
+//! Loaded voice models, kept between calls so batch jobs, the server, and other long-running callers
+//! don't pay the multi-second model load for every piece of text.
+//!
+//! Entries are keyed by the config a voice loads from (so the int8 model and voice overrides get their
+//! own) and its modification time, so an edited or re-downloaded voice is loaded afresh. The least
+//! recently used model is dropped once the cache is full. Under `--low-memory` nothing is kept.
+
+use piper_rs::synth::PiperSpeechSynthesizer;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Models kept loaded unless [`set_capacity`] says otherwise; a medium voice is about 60 MB
+pub const DEFAULT_CAPACITY: usize = 4;
+
+static CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_CAPACITY);
+
+struct Entry {
+    voice_id: String,
+    config_path: PathBuf,
+    modified: Option<SystemTime>,
+    synth: PiperSpeechSynthesizer,
+}
+
+/// Least recently used first
+static CACHE: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+
+/// Keep up to `capacity` models loaded (0 turns the cache off), dropping the least recently used ones
+/// beyond it
+pub fn set_capacity(capacity: usize) {
+    CAPACITY.store(capacity, Ordering::Relaxed);
+    trim(&mut CACHE.lock().unwrap(), capacity);
+}
+
+/// How many models are kept loaded: none under `--low-memory`
+pub fn capacity() -> usize {
+    if crate::low_memory::is_enabled() {
+        0
+    } else {
+        CAPACITY.load(Ordering::Relaxed)
+    }
+}
+
+/// Drop every loaded model
+pub fn clear() {
+    CACHE.lock().unwrap().clear();
+}
+
+/// Drop a voice's loaded models, so its next load reads the files again
+pub fn evict(voice_id: &str) {
+    CACHE.lock().unwrap().retain(|entry| entry.voice_id != voice_id);
+}
+
+/// Voices with a model loaded, least recently used first
+pub fn cached_voices() -> Vec<String> {
+    CACHE.lock().unwrap().iter().map(|entry| entry.voice_id.clone()).collect()
+}
+
+fn trim(cache: &mut Vec<Entry>, capacity: usize) {
+    let excess = cache.len().saturating_sub(capacity);
+    cache.drain(..excess);
+}
+
+/// A synthesizer for the model at `config_path`, loaded or taken from the cache. Cached models share
+/// their synthesis settings, so those are reset to the config's defaults before it's returned.
+pub(crate) fn synthesizer(voice_id: &str, config_path: &Path) -> crate::error::Result<PiperSpeechSynthesizer> {
+    let modified = std::fs::metadata(config_path).and_then(|metadata| metadata.modified()).ok();
+    let capacity = capacity();
+    if capacity > 0 {
+        let mut cache = CACHE.lock().unwrap();
+        if let Some(index) = cache.iter().position(|entry| entry.config_path == config_path && entry.modified == modified) {
+            let entry = cache.remove(index);
+            let synth = PiperSpeechSynthesizer::new(entry.synth.clone_model())?;
+            cache.push(entry);
+            drop(cache);
+            reset_synthesis_config(&synth)?;
+            return Ok(synth);
+        }
+    }
+
+    // Loaded without holding the lock, so other voices aren't held up behind it
+    let synth = PiperSpeechSynthesizer::new(piper_rs::from_config_path(config_path)?)?;
+    if capacity > 0 {
+        let mut cache = CACHE.lock().unwrap();
+        cache.retain(|entry| entry.config_path != config_path);
+        cache.push(Entry {
+            voice_id: voice_id.to_string(),
+            config_path: config_path.to_path_buf(),
+            modified,
+            synth: PiperSpeechSynthesizer::new(synth.clone_model())?,
+        });
+        trim(&mut cache, capacity);
+    }
+    Ok(synth)
+}
+
+/// Put back the speaker, noise, and length scales from the voice's config, undoing whatever the last
+/// caller applied (variation, rate, speaker)
+fn reset_synthesis_config(synth: &PiperSpeechSynthesizer) -> crate::error::Result<()> {
+    let model = synth.clone_model();
+    let defaults = model.get_default_synthesis_config()?;
+    let Some(defaults) = defaults.downcast_ref::<piper_rs::PiperSynthesisConfig>() else {
+        return Err("Voice doesn't support synthesis settings".into());
+    };
+    model.set_fallback_synthesis_config(defaults)?;
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use crate::{synthesize_processed, PitchArg};
+use realfft::num_complex::Complex;
+use realfft::RealFftPlanner;
+
+const FFT_SIZE: usize = 1024;
+const HOP: usize = FFT_SIZE / 4;
+
+/// A blend of two voices: `amount` 0.0 is all `voice_a`, 1.0 is all `voice_b`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MorphSpec {
+    pub voice_a: String,
+    pub voice_b: String,
+    pub amount: f32,
+}
+
+impl MorphSpec {
+    /// Label used where a single voice ID is expected (file names, tags)
+    pub fn label(&self) -> String {
+        format!("{}+{}@{}", self.voice_a, self.voice_b, self.amount)
+    }
+}
+
+impl std::str::FromStr for MorphSpec {
+    type Err = String;
+
+    /// Parse `voiceA:voiceB:amount`, e.g. `en_GB-alba-medium:en_US-joe-medium:0.4`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let [voice_a, voice_b, amount] = parts.as_slice() else {
+            return Err(format!("Invalid morph '{}': expected voiceA:voiceB:amount", s));
+        };
+        let amount: f32 = amount.parse().map_err(|_| format!("Invalid morph amount '{}'", amount))?;
+        if !(0.0..=1.0).contains(&amount) {
+            return Err(format!("Morph amount must be between 0.0 and 1.0, got {}", amount));
+        }
+        Ok(MorphSpec { voice_a: voice_a.to_string(), voice_b: voice_b.to_string(), amount })
+    }
+}
+
+fn hann(n: usize) -> Vec<f32> {
+    (0..n).map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / n as f32).cos()).collect()
+}
+
+/// Short-time spectra of `samples`, one every `HOP` samples
+fn stft(samples: &[f32], window: &[f32], planner: &mut RealFftPlanner<f32>) -> Vec<Vec<Complex<f32>>> {
+    let fft = planner.plan_fft_forward(FFT_SIZE);
+    let frames = samples.len().div_ceil(HOP).max(1);
+    (0..frames)
+        .map(|f| {
+            let mut input: Vec<f32> = (0..FFT_SIZE)
+                .map(|i| samples.get(f * HOP + i).copied().unwrap_or(0.0) * window[i])
+                .collect();
+            let mut spectrum = fft.make_output_vec();
+            let _ = fft.process(&mut input, &mut spectrum);
+            spectrum
+        })
+        .collect()
+}
+
+/// Spectrally crossfade two renderings of the same line.
+/// Both are time-aligned by stretching onto a common length (interpolated between the two), each
+/// frame's magnitude is blended geometrically so formants move between the voices rather than
+/// layering them, and phase comes from whichever voice dominates.
+pub fn spectral_morph(a: &[f32], b: &[f32], amount: f32) -> Vec<f32> {
+    let amount = amount.clamp(0.0, 1.0);
+    if a.is_empty() || amount >= 1.0 {
+        return b.to_vec();
+    }
+    if b.is_empty() || amount <= 0.0 {
+        return a.to_vec();
+    }
+    let window = hann(FFT_SIZE);
+    let mut planner = RealFftPlanner::<f32>::new();
+    let spectra_a = stft(a, &window, &mut planner);
+    let spectra_b = stft(b, &window, &mut planner);
+    let inverse = planner.plan_fft_inverse(FFT_SIZE);
+
+    let out_len = (a.len() as f32 * (1.0 - amount) + b.len() as f32 * amount) as usize;
+    let frames = out_len.div_ceil(HOP).max(1);
+    let mut output = vec![0.0f32; out_len + FFT_SIZE];
+    let mut norm = vec![0.0f32; out_len + FFT_SIZE];
+    for f in 0..frames {
+        let pos = f as f32 / frames as f32;
+        let fa = &spectra_a[((pos * spectra_a.len() as f32) as usize).min(spectra_a.len() - 1)];
+        let fb = &spectra_b[((pos * spectra_b.len() as f32) as usize).min(spectra_b.len() - 1)];
+        let mut spectrum: Vec<Complex<f32>> = fa
+            .iter()
+            .zip(fb)
+            .map(|(ca, cb)| {
+                let magnitude = (ca.norm() + 1e-9).powf(1.0 - amount) * (cb.norm() + 1e-9).powf(amount);
+                let phase = if amount < 0.5 { ca.arg() } else { cb.arg() };
+                Complex::from_polar(magnitude, phase)
+            })
+            .collect();
+        // The DC and Nyquist bins of a real signal have no imaginary part
+        spectrum[0].im = 0.0;
+        if let Some(last) = spectrum.last_mut() {
+            last.im = 0.0;
+        }
+        let mut frame = inverse.make_output_vec();
+        let _ = inverse.process(&mut spectrum, &mut frame);
+        for (i, sample) in frame.iter().enumerate() {
+            output[f * HOP + i] += sample / FFT_SIZE as f32 * window[i];
+            norm[f * HOP + i] += window[i] * window[i];
+        }
+    }
+    output.truncate(out_len);
+    output.iter().zip(&norm).map(|(s, n)| if *n > 1e-6 { s / n } else { 0.0 }).collect()
+}
+
+/// Synthesize `text` with both voices and morph between them.
+/// Piper's multi-speaker embeddings aren't exposed through its ONNX interface, so voices are
+/// always blended spectrally, even when both are speakers of the same model. Returns the samples at
+/// the first voice's sample rate, and that rate.
+pub fn synthesize_morph(text: &str, spec: &MorphSpec, pitch: &PitchArg, tempo: f32) -> Result<(Vec<f32>, u32), Box<dyn std::error::Error>> {
+    let (a, sample_rate) = synthesize_processed(text, &spec.voice_a, pitch, tempo)?;
+    let (b, rate_b) = synthesize_processed(text, &spec.voice_b, pitch, tempo)?;
+    let b = if rate_b == sample_rate { b } else { crate::dsp::resample(&b, rate_b as usize, sample_rate as usize) };
+    Ok((spectral_morph(&a, &b, spec.amount), sample_rate))
+}
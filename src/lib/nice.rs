@@ -0,0 +1,80 @@
+use std::process::Command;
+use std::sync::Mutex;
+
+/// Scheduling priority increment for `--nice` (as for the `nice` command; 19 is the lowest priority)
+pub const NICE_INCREMENT: i32 = 10;
+
+/// Resource limits for `--nice`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NiceSettings {
+    /// CPU cores the process (and everything it starts) may use
+    pub threads: usize,
+}
+
+impl NiceSettings {
+    /// A quarter of the machine's cores, or `threads` if given
+    pub fn new(threads: Option<usize>) -> Self {
+        let cores = std::thread::available_parallelism().map(usize::from).unwrap_or(1);
+        NiceSettings { threads: threads.unwrap_or(cores / 4).clamp(1, cores.max(1)) }
+    }
+}
+
+static NICE: Mutex<Option<NiceSettings>> = Mutex::new(None);
+
+/// Whether `--nice` is on
+pub fn settings() -> Option<NiceSettings> {
+    *NICE.lock().unwrap()
+}
+
+pub fn is_enabled() -> bool {
+    settings().is_some()
+}
+
+/// Run `program args...`, reporting why it couldn't be used instead of failing
+fn run_tool(program: &str, args: &[String]) -> Result<(), String> {
+    match Command::new(program).args(args).output() {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(format!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim())),
+        Err(e) => Err(format!("{} unavailable: {}", program, e)),
+    }
+}
+
+/// Turn on `--nice` for this process: lower its priority with `renice`, and pin it (every thread,
+/// including ONNX Runtime's and the synthesis pool's) to the first `threads` cores with `taskset`.
+/// Tools started later inherit both. Call before loading any voice, so thread pools are sized to
+/// the pinned cores. Returns what couldn't be applied on this system; the rest still is.
+/// Priority and pinning can't be undone, so only the first call changes them.
+pub fn enable(settings: NiceSettings) -> Vec<String> {
+    if NICE.lock().unwrap().replace(settings).is_some() {
+        return Vec::new();
+    }
+    let pid = std::process::id().to_string();
+    let mut problems = Vec::new();
+    if let Err(e) = run_tool("renice", &["-n".to_string(), NICE_INCREMENT.to_string(), "-p".to_string(), pid.clone()]) {
+        problems.push(e);
+    }
+    if cfg!(target_os = "linux") {
+        let cores = format!("0-{}", settings.threads - 1);
+        if let Err(e) = run_tool("taskset", &["-a".to_string(), "-p".to_string(), "-c".to_string(), cores, pid]) {
+            problems.push(e);
+        }
+    } else {
+        problems.push("CPU pinning needs taskset (Linux only); synthesis runs one sentence at a time instead".to_string());
+    }
+    problems
+}
+
+/// Environment limiting the thread pools of a tool started under `--nice` (PyTorch, BLAS), for
+/// `Command::envs`. Empty when `--nice` is off.
+pub fn child_env() -> Vec<(&'static str, String)> {
+    let Some(settings) = settings() else {
+        return Vec::new();
+    };
+    ["OMP_NUM_THREADS", "MKL_NUM_THREADS", "OPENBLAS_NUM_THREADS"].into_iter().map(|var| (var, settings.threads.to_string())).collect()
+}
+
+/// Extra arguments asking a tool that takes a thread count flag (`--threads` for WhisperX,
+/// `-threads` for ffmpeg) to stay within `--nice`. Empty when `--nice` is off.
+pub fn thread_args(flag: &str) -> Vec<String> {
+    settings().map(|s| vec![flag.to_string(), s.threads.to_string()]).unwrap_or_default()
+}
@@ -0,0 +1,868 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Languages whose numbers, ordinals, and dates are spelled out before synthesis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    German,
+    French,
+    Spanish,
+    Italian,
+    Russian,
+}
+
+/// Language plus the regional conventions that change how numbers are read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locale {
+    pub language: Language,
+    /// Numeric dates are month/day/year (en_US) rather than day/month/year
+    pub month_first: bool,
+}
+
+impl Locale {
+    /// Locale of a Piper voice ID such as `de_DE-thorsten-medium`
+    pub fn from_voice_id(voice_id: &str) -> Option<Locale> {
+        let tag = voice_id.split('-').next()?;
+        let (language, region) = tag.split_once('_').unwrap_or((tag, ""));
+        let language = match language {
+            "en" => Language::English,
+            "de" => Language::German,
+            "fr" => Language::French,
+            "es" => Language::Spanish,
+            "it" => Language::Italian,
+            "ru" => Language::Russian,
+            _ => return None,
+        };
+        Some(Locale { language, month_first: language == Language::English && region == "US" })
+    }
+
+    fn decimal_separator(&self) -> char {
+        if self.language == Language::English { '.' } else { ',' }
+    }
+
+    /// Digit grouping separator; French and Russian group with spaces, which split words anyway
+    fn group_separator(&self) -> Option<char> {
+        match self.language {
+            Language::English => Some(','),
+            Language::German | Language::Spanish | Language::Italian => Some('.'),
+            Language::French | Language::Russian => None,
+        }
+    }
+}
+
+/// Grammatical form of an ordinal. Only the languages that inflect ordinals distinguish them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Form {
+    Masculine,
+    Feminine,
+    Neuter,
+    /// Russian masculine/neuter genitive (years in dates)
+    Genitive,
+    /// Russian masculine/neuter prepositional ("в 2024 году")
+    Prepositional,
+}
+
+/// Set from `--no-normalize`; normalization is on by default
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Handle emoji (see [`crate::emoji`]), then expand abbreviations (see [`crate::abbreviations`]) and
+/// normalize `text` for the voice's locale unless normalization is disabled. Numbers are left as
+/// written in languages that aren't supported.
+pub fn normalize_for_voice(text: &str, voice_id: &str) -> String {
+    let locale = Locale::from_voice_id(voice_id);
+    let text = crate::emoji::apply(text, locale);
+    if !is_enabled() {
+        return text;
+    }
+    let text = crate::abbreviations::expand(&text, locale);
+    match locale {
+        Some(locale) => normalize_text(&text, locale),
+        None => text,
+    }
+}
+
+const MONTHS_EN: [&str; 12] = ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"];
+const MONTHS_DE: [&str; 12] = ["Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August", "September", "Oktober", "November", "Dezember"];
+const MONTHS_FR: [&str; 12] = ["janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre", "octobre", "novembre", "décembre"];
+const MONTHS_ES: [&str; 12] = ["enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto", "septiembre", "octubre", "noviembre", "diciembre"];
+const MONTHS_IT: [&str; 12] = ["gennaio", "febbraio", "marzo", "aprile", "maggio", "giugno", "luglio", "agosto", "settembre", "ottobre", "novembre", "dicembre"];
+/// Genitive, as used after a day
+const MONTHS_RU: [&str; 12] = ["января", "февраля", "марта", "апреля", "мая", "июня", "июля", "августа", "сентября", "октября", "ноября", "декабря"];
+
+fn months(language: Language) -> &'static [&'static str; 12] {
+    match language {
+        Language::English => &MONTHS_EN,
+        Language::German => &MONTHS_DE,
+        Language::French => &MONTHS_FR,
+        Language::Spanish => &MONTHS_ES,
+        Language::Italian => &MONTHS_IT,
+        Language::Russian => &MONTHS_RU,
+    }
+}
+
+fn is_month(word: &str, language: Language) -> bool {
+    let word = word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+    !word.is_empty() && months(language).iter().any(|m| m.to_lowercase() == word)
+}
+
+/// Largest number spelled out; longer digit runs are left to the phonemizer
+const MAX_NUMBER: u64 = 999_999_999_999;
+
+/// Spell out numbers, ordinals, decimals, and dates in `text` the way a speaker of the locale reads them
+pub fn normalize_text(text: &str, locale: Locale) -> String {
+    // Words with the whitespace that follows them, so spacing is preserved
+    let mut words: Vec<(&str, &str)> = Vec::new();
+    let mut rest = text.trim_start();
+    let leading = &text[..text.len() - rest.len()];
+    while !rest.is_empty() {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let (word, after) = rest.split_at(end);
+        let next = after.trim_start();
+        words.push((word, &after[..after.len() - next.len()]));
+        rest = next;
+    }
+
+    let mut output = String::from(leading);
+    for (i, (word, space)) in words.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|p| words[p].0);
+        let next = words.get(i + 1).map(|w| w.0);
+        match normalize_word(word, previous, next, locale) {
+            Some(spoken) => output.push_str(&spoken),
+            None => output.push_str(word),
+        }
+        output.push_str(space);
+    }
+    output
+}
+
+/// Spell out the number in one whitespace-separated word, or `None` to leave it as written
+fn normalize_word(word: &str, previous: Option<&str>, next: Option<&str>, locale: Locale) -> Option<String> {
+    // Digits must start the word or follow punctuation ("(12", "l'11º"), so "A4" and "mp3" are left alone
+    let start = word.find(|c: char| c.is_ascii_digit())?;
+    let (prefix, core) = word.split_at(start);
+    if prefix.ends_with(|c: char| c.is_alphanumeric()) {
+        return None;
+    }
+    // The numeric part runs over digits and the separators between them
+    let mut end = core.find(|c: char| !(c.is_ascii_digit() || ".,/-".contains(c))).unwrap_or(core.len());
+    while end > 0 && !core[..end].ends_with(|c: char| c.is_ascii_digit()) {
+        end -= 1;
+    }
+    let (number, suffix) = core.split_at(end);
+    let language = locale.language;
+
+    let (spoken, suffix) = if let Some(date) = parse_date(number, suffix, locale) {
+        let suffix = if date.2.is_none() && language == Language::German { &suffix[1..] } else { suffix };
+        (say_date(date, locale), suffix)
+    } else if number.bytes().all(|b| b.is_ascii_digit()) {
+        if number.len() > 1 && number.starts_with('0') {
+            return None;
+        }
+        let n: u64 = number.parse().ok().filter(|n| *n <= MAX_NUMBER)?;
+        let month_follows = next.is_some_and(|w| is_month(w, language));
+        let month_precedes = previous.is_some_and(|w| is_month(w, language));
+        if let Some((form, len)) = ordinal_suffix(suffix, language) {
+            (ordinal(n, form, language), &suffix[len..])
+        } else if (1..=31).contains(&n) && month_follows {
+            // A day before a month name is read as a date
+            match language {
+                Language::English => (format!("the {} of", ordinal(n, Form::Masculine, language)), suffix),
+                Language::German if suffix.starts_with('.') => (ordinal(n, Form::Masculine, language), &suffix[1..]),
+                Language::French | Language::Italian if n == 1 => (ordinal(n, Form::Masculine, language), suffix),
+                Language::Russian => (ordinal(n, Form::Neuter, language), suffix),
+                _ => (cardinal(n, language), suffix),
+            }
+        } else if (1..=31).contains(&n) && month_precedes && language == Language::English {
+            (ordinal(n, Form::Masculine, language), suffix)
+        } else if language == Language::Russian && next.is_some_and(|w| w.starts_with("года")) {
+            // "2024 года": the year is a genitive ordinal
+            (ordinal(n, Form::Genitive, language), suffix)
+        } else if language == Language::Russian && next.is_some_and(|w| w.starts_with("году")) {
+            (ordinal(n, Form::Prepositional, language), suffix)
+        } else {
+            (cardinal(n, language), suffix)
+        }
+    } else if let Some(n) = parse_grouped(number, locale) {
+        (cardinal(n, language), suffix)
+    } else if let Some((whole, fraction)) = number.split_once(locale.decimal_separator()) {
+        if fraction.is_empty() || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let plain = || whole.bytes().all(|b| b.is_ascii_digit()).then(|| whole.parse().ok()).flatten().filter(|n| *n <= MAX_NUMBER);
+        let whole = parse_grouped(whole, locale).or_else(plain)?;
+        let digits: Vec<String> = fraction.bytes().map(|b| cardinal((b - b'0') as u64, language)).collect();
+        (format!("{} {} {}", cardinal(whole, language), decimal_word(language), digits.join(" ")), suffix)
+    } else {
+        return None;
+    };
+    Some(format!("{}{}{}", prefix, spoken, suffix))
+}
+
+/// Parse `1,234,567` (or `1.234.567`) using the locale's grouping separator
+fn parse_grouped(number: &str, locale: Locale) -> Option<u64> {
+    let separator = locale.group_separator()?;
+    let groups: Vec<&str> = number.split(separator).collect();
+    let valid = groups.len() > 1
+        && (1..=3).contains(&groups[0].len())
+        && groups[1..].iter().all(|g| g.len() == 3)
+        && groups.iter().all(|g| g.bytes().all(|b| b.is_ascii_digit()));
+    if !valid {
+        return None;
+    }
+    groups.concat().parse().ok().filter(|n| *n <= MAX_NUMBER)
+}
+
+/// Ordinal markers written after a number: the form they ask for and their length in bytes
+fn ordinal_suffix(suffix: &str, language: Language) -> Option<(Form, usize)> {
+    let candidates: &[(&str, Form)] = match language {
+        Language::English => &[("st", Form::Masculine), ("nd", Form::Masculine), ("rd", Form::Masculine), ("th", Form::Masculine)],
+        Language::German => &[],
+        Language::French => &[("ère", Form::Feminine), ("re", Form::Feminine), ("er", Form::Masculine), ("ème", Form::Masculine), ("eme", Form::Masculine), ("e", Form::Masculine)],
+        Language::Spanish | Language::Italian => &[
+            (".º", Form::Masculine),
+            (".ª", Form::Feminine),
+            ("º", Form::Masculine),
+            ("°", Form::Masculine),
+            ("ª", Form::Feminine),
+        ],
+        Language::Russian => &[
+            ("-ого", Form::Genitive),
+            ("-го", Form::Genitive),
+            ("-ый", Form::Masculine),
+            ("-ой", Form::Masculine),
+            ("-ий", Form::Masculine),
+            ("-й", Form::Masculine),
+            ("-ая", Form::Feminine),
+            ("-я", Form::Feminine),
+            ("-ое", Form::Neuter),
+            ("-е", Form::Neuter),
+        ],
+    };
+    candidates.iter().find_map(|(marker, form)| {
+        let rest = suffix.strip_prefix(marker)?;
+        // The marker must end the word (apart from punctuation), so "3rdparty" or "2e5" aren't ordinals
+        (!rest.starts_with(|c: char| c.is_alphanumeric())).then_some((*form, marker.len()))
+    })
+}
+
+/// Day, month, and optional year
+type Date = (u32, u32, Option<u64>);
+
+/// Recognize `2024-03-12`, `12.03.2024`, `12/03/2024` (`03/12/2024` in en_US), and German `12.03.`
+fn parse_date(number: &str, suffix: &str, locale: Locale) -> Option<Date> {
+    let valid = |day: u32, month: u32| (1..=31).contains(&day) && (1..=12).contains(&month);
+    let digits = |s: &str, lens: &[usize]| lens.contains(&s.len()) && s.bytes().all(|b| b.is_ascii_digit());
+
+    let parts: Vec<&str> = number.split('-').collect();
+    if parts.len() == 3 && digits(parts[0], &[4]) && digits(parts[1], &[1, 2]) && digits(parts[2], &[1, 2]) {
+        let (day, month) = (parts[2].parse().ok()?, parts[1].parse().ok()?);
+        return valid(day, month).then_some((day, month, parts[0].parse().ok()));
+    }
+    let separator = if number.contains('/') { '/' } else { '.' };
+    let parts: Vec<&str> = number.split(separator).collect();
+    if parts.len() == 3 && digits(parts[0], &[1, 2]) && digits(parts[1], &[1, 2]) && digits(parts[2], &[2, 4]) {
+        let (first, second) = (parts[0].parse().ok()?, parts[1].parse().ok()?);
+        let (day, month) = if locale.month_first { (second, first) } else { (first, second) };
+        return valid(day, month).then_some((day, month, parts[2].parse().ok()));
+    }
+    // German dates without a year keep their final dot: "am 12.03."
+    if locale.language == Language::German && parts.len() == 2 && separator == '.' && suffix.starts_with('.') && digits(parts[0], &[1, 2]) && digits(parts[1], &[1, 2]) {
+        let (day, month) = (parts[0].parse().ok()?, parts[1].parse().ok()?);
+        return valid(day, month).then_some((day, month, None));
+    }
+    None
+}
+
+fn say_date((day, month, year): Date, locale: Locale) -> String {
+    let language = locale.language;
+    let month_name = months(language)[month as usize - 1];
+    let day = day as u64;
+    let date = match language {
+        Language::English if locale.month_first => format!("{} {}", month_name, ordinal(day, Form::Masculine, language)),
+        Language::English => format!("the {} of {}", ordinal(day, Form::Masculine, language), month_name),
+        Language::German => format!("{} {}", ordinal(day, Form::Masculine, language), month_name),
+        Language::French | Language::Italian if day == 1 => format!("{} {}", ordinal(day, Form::Masculine, language), month_name),
+        Language::French | Language::Italian => format!("{} {}", cardinal(day, language), month_name),
+        Language::Spanish => format!("{} de {}", cardinal(day, language), month_name),
+        Language::Russian => format!("{} {}", ordinal(day, Form::Neuter, language), month_name),
+    };
+    let Some(year) = year else { return date };
+    match language {
+        Language::English if locale.month_first => format!("{}, {}", date, say_year(year, language)),
+        Language::Spanish => format!("{} de {}", date, say_year(year, language)),
+        _ => format!("{} {}", date, say_year(year, language)),
+    }
+}
+
+fn say_year(year: u64, language: Language) -> String {
+    match language {
+        Language::English if (1100..=9999).contains(&year) && !(2000..=2009).contains(&year) && !year.is_multiple_of(1000) => {
+            let (century, rest) = (year / 100, year % 100);
+            match rest {
+                0 => format!("{} hundred", cardinal(century, language)),
+                1..=9 => format!("{} oh {}", cardinal(century, language), cardinal(rest, language)),
+                _ => format!("{} {}", cardinal(century, language), cardinal(rest, language)),
+            }
+        }
+        Language::German if (1100..=1999).contains(&year) => {
+            let rest = year % 100;
+            let rest = if rest == 0 { String::new() } else { cardinal(rest, language) };
+            format!("{}hundert{}", cardinal(year / 100, language), rest)
+        }
+        Language::Russian => format!("{} года", ordinal(year, Form::Genitive, language)),
+        _ => cardinal(year, language),
+    }
+}
+
+fn decimal_word(language: Language) -> &'static str {
+    match language {
+        Language::English => "point",
+        Language::German => "Komma",
+        Language::French => "virgule",
+        Language::Spanish => "coma",
+        Language::Italian => "virgola",
+        Language::Russian => "запятая",
+    }
+}
+
+fn cardinal(n: u64, language: Language) -> String {
+    match language {
+        Language::English => english::cardinal(n),
+        Language::German => german::cardinal(n),
+        Language::French => french::cardinal(n),
+        Language::Spanish => spanish::cardinal(n),
+        Language::Italian => italian::cardinal(n),
+        Language::Russian => russian::cardinal(n),
+    }
+}
+
+fn ordinal(n: u64, form: Form, language: Language) -> String {
+    match language {
+        Language::English => english::ordinal(n),
+        Language::German => german::ordinal(n),
+        Language::French => french::ordinal(n, form),
+        Language::Spanish => spanish::ordinal(n, form),
+        Language::Italian => italian::ordinal(n, form),
+        Language::Russian => russian::ordinal(n, form),
+    }
+}
+
+/// Split off the last word of a spelled-out number (after a space or hyphen)
+fn split_last_word(words: &str) -> (&str, &str) {
+    let at = words.rfind([' ', '-']).map(|i| i + 1).unwrap_or(0);
+    words.split_at(at)
+}
+
+mod english {
+    const ONES: [&str; 20] = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten", "eleven", "twelve", "thirteen", "fourteen",
+        "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+    ];
+    const TENS: [&str; 10] = ["", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety"];
+
+    fn below_thousand(n: u64) -> String {
+        let (hundreds, rest) = (n / 100, n % 100);
+        let rest = match rest {
+            0 => String::new(),
+            1..=19 => ONES[rest as usize].to_string(),
+            _ if rest.is_multiple_of(10) => TENS[(rest / 10) as usize].to_string(),
+            _ => format!("{}-{}", TENS[(rest / 10) as usize], ONES[(rest % 10) as usize]),
+        };
+        match (hundreds, rest.is_empty()) {
+            (0, _) => rest,
+            (_, true) => format!("{} hundred", ONES[hundreds as usize]),
+            _ => format!("{} hundred {}", ONES[hundreds as usize], rest),
+        }
+    }
+
+    pub fn cardinal(n: u64) -> String {
+        if n == 0 {
+            return ONES[0].to_string();
+        }
+        let mut parts = Vec::new();
+        for (scale, name) in [(1_000_000_000, "billion"), (1_000_000, "million"), (1_000, "thousand")] {
+            if !(n / scale).is_multiple_of(1000) {
+                parts.push(format!("{} {}", below_thousand(n / scale % 1000), name));
+            }
+        }
+        if !n.is_multiple_of(1000) {
+            parts.push(below_thousand(n % 1000));
+        }
+        parts.join(" ")
+    }
+
+    pub fn ordinal(n: u64) -> String {
+        let words = cardinal(n);
+        let (head, last) = super::split_last_word(&words);
+        let last = match last {
+            "one" => "first".to_string(),
+            "two" => "second".to_string(),
+            "three" => "third".to_string(),
+            "five" => "fifth".to_string(),
+            "eight" => "eighth".to_string(),
+            "nine" => "ninth".to_string(),
+            "twelve" => "twelfth".to_string(),
+            _ if last.ends_with('y') => format!("{}ieth", &last[..last.len() - 1]),
+            _ => format!("{}th", last),
+        };
+        format!("{}{}", head, last)
+    }
+}
+
+mod german {
+    const ONES: [&str; 20] = [
+        "null", "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun", "zehn", "elf", "zwölf", "dreizehn", "vierzehn",
+        "fünfzehn", "sechzehn", "siebzehn", "achtzehn", "neunzehn",
+    ];
+    const TENS: [&str; 10] = ["", "", "zwanzig", "dreißig", "vierzig", "fünfzig", "sechzig", "siebzig", "achtzig", "neunzig"];
+
+    /// "eins" becomes "ein" inside compounds (einhundert, einundzwanzig, einstausend is wrong)
+    fn combining(n: u64) -> String {
+        let words = below_thousand(n);
+        words.strip_suffix("eins").map(|w| format!("{}ein", w)).unwrap_or(words)
+    }
+
+    fn below_hundred(n: u64) -> String {
+        match n {
+            0..=19 => ONES[n as usize].to_string(),
+            _ if n.is_multiple_of(10) => TENS[(n / 10) as usize].to_string(),
+            _ => format!("{}und{}", combining(n % 10), TENS[(n / 10) as usize]),
+        }
+    }
+
+    fn below_thousand(n: u64) -> String {
+        let (hundreds, rest) = (n / 100, n % 100);
+        let rest = if rest == 0 && hundreds > 0 { String::new() } else { below_hundred(rest) };
+        if hundreds == 0 { rest } else { format!("{}hundert{}", combining(hundreds), rest) }
+    }
+
+    pub fn cardinal(n: u64) -> String {
+        let mut parts = Vec::new();
+        for (scale, one, many) in [(1_000_000_000, "eine Milliarde", "Milliarden"), (1_000_000, "eine Million", "Millionen")] {
+            match n / scale % 1000 {
+                0 => {}
+                1 => parts.push(one.to_string()),
+                count => parts.push(format!("{} {}", combining(count), many)),
+            }
+        }
+        // Thousands and below form a single word
+        let thousands = n / 1000 % 1000;
+        let mut word = if thousands > 0 { format!("{}tausend", combining(thousands)) } else { String::new() };
+        if !n.is_multiple_of(1000) || n == 0 {
+            word.push_str(&below_thousand(n % 1000));
+        }
+        if !word.is_empty() {
+            parts.push(word);
+        }
+        parts.join(" ")
+    }
+
+    /// Ordinal in the -ten form used in dates ("am dritten März")
+    pub fn ordinal(n: u64) -> String {
+        let small = |n: u64| match n {
+            1 => "ersten".to_string(),
+            3 => "dritten".to_string(),
+            7 => "siebten".to_string(),
+            8 => "achten".to_string(),
+            _ => format!("{}ten", ONES[n as usize]),
+        };
+        let rest = n % 100;
+        if (1..=19).contains(&rest) {
+            let head = if n > rest { cardinal(n - rest) } else { String::new() };
+            format!("{}{}", head, small(rest))
+        } else {
+            format!("{}sten", cardinal(n))
+        }
+    }
+}
+
+mod french {
+    use super::Form;
+
+    const ONES: [&str; 17] = [
+        "zéro", "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf", "dix", "onze", "douze", "treize", "quatorze", "quinze",
+        "seize",
+    ];
+    const TENS: [&str; 7] = ["", "", "vingt", "trente", "quarante", "cinquante", "soixante"];
+
+    fn below_hundred(n: u64) -> String {
+        match n {
+            0..=16 => ONES[n as usize].to_string(),
+            17..=19 => format!("dix-{}", ONES[(n - 10) as usize]),
+            20..=69 => match n % 10 {
+                0 => TENS[(n / 10) as usize].to_string(),
+                1 => format!("{} et un", TENS[(n / 10) as usize]),
+                unit => format!("{}-{}", TENS[(n / 10) as usize], ONES[unit as usize]),
+            },
+            71 => "soixante et onze".to_string(),
+            70..=79 => format!("soixante-{}", below_hundred(n - 60)),
+            80 => "quatre-vingts".to_string(),
+            _ => format!("quatre-vingt-{}", below_hundred(n - 80)),
+        }
+    }
+
+    fn below_thousand(n: u64) -> String {
+        let (hundreds, rest) = (n / 100, n % 100);
+        let head = match hundreds {
+            0 => return below_hundred(rest),
+            1 => "cent".to_string(),
+            _ => format!("{} cent", ONES[hundreds as usize]),
+        };
+        match rest {
+            0 if hundreds > 1 => format!("{}s", head),
+            0 => head,
+            _ => format!("{} {}", head, below_hundred(rest)),
+        }
+    }
+
+    /// "cents" and "quatre-vingts" lose their s when another number word follows
+    fn multiplier(n: u64) -> String {
+        let words = below_thousand(n);
+        match words.strip_suffix("cents").or_else(|| words.strip_suffix("vingts")) {
+            Some(_) => words[..words.len() - 1].to_string(),
+            None => words,
+        }
+    }
+
+    pub fn cardinal(n: u64) -> String {
+        if n == 0 {
+            return ONES[0].to_string();
+        }
+        let mut parts = Vec::new();
+        for (scale, one, many) in [(1_000_000_000, "un milliard", "milliards"), (1_000_000, "un million", "millions")] {
+            match n / scale % 1000 {
+                0 => {}
+                1 => parts.push(one.to_string()),
+                count => parts.push(format!("{} {}", multiplier(count), many)),
+            }
+        }
+        match n / 1000 % 1000 {
+            0 => {}
+            1 => parts.push("mille".to_string()),
+            count => parts.push(format!("{} mille", multiplier(count))),
+        }
+        if !n.is_multiple_of(1000) {
+            parts.push(below_thousand(n % 1000));
+        }
+        parts.join(" ")
+    }
+
+    pub fn ordinal(n: u64, form: Form) -> String {
+        if n == 1 {
+            return if form == Form::Feminine { "première" } else { "premier" }.to_string();
+        }
+        let words = cardinal(n);
+        let (head, last) = super::split_last_word(&words);
+        let last = match last {
+            "vingts" | "cents" | "millions" | "milliards" => &last[..last.len() - 1],
+            _ => last,
+        };
+        let stem = match last {
+            "cinq" => "cinqu",
+            "neuf" => "neuv",
+            _ => last.strip_suffix('e').unwrap_or(last),
+        };
+        format!("{}{}ième", head, stem)
+    }
+}
+
+mod spanish {
+    use super::Form;
+
+    const ONES: [&str; 30] = [
+        "cero", "uno", "dos", "tres", "cuatro", "cinco", "seis", "siete", "ocho", "nueve", "diez", "once", "doce", "trece", "catorce", "quince",
+        "dieciséis", "diecisiete", "dieciocho", "diecinueve", "veinte", "veintiuno", "veintidós", "veintitrés", "veinticuatro", "veinticinco",
+        "veintiséis", "veintisiete", "veintiocho", "veintinueve",
+    ];
+    const TENS: [&str; 10] = ["", "", "veinte", "treinta", "cuarenta", "cincuenta", "sesenta", "setenta", "ochenta", "noventa"];
+    const HUNDREDS: [&str; 10] = [
+        "", "ciento", "doscientos", "trescientos", "cuatrocientos", "quinientos", "seiscientos", "setecientos", "ochocientos", "novecientos",
+    ];
+
+    fn below_thousand(n: u64) -> String {
+        let (hundreds, rest) = (n / 100, n % 100);
+        let rest_words = match rest {
+            0..=29 => ONES[rest as usize].to_string(),
+            _ if rest.is_multiple_of(10) => TENS[(rest / 10) as usize].to_string(),
+            _ => format!("{} y {}", TENS[(rest / 10) as usize], ONES[(rest % 10) as usize]),
+        };
+        match (hundreds, rest) {
+            (0, _) => rest_words,
+            (1, 0) => "cien".to_string(),
+            (_, 0) => HUNDREDS[hundreds as usize].to_string(),
+            _ => format!("{} {}", HUNDREDS[hundreds as usize], rest_words),
+        }
+    }
+
+    /// "uno" shortens before "mil" and "millones": veintiún mil, treinta y un millones
+    fn multiplier(n: u64) -> String {
+        let words = below_thousand(n);
+        if let Some(stem) = words.strip_suffix("veintiuno") {
+            format!("{}veintiún", stem)
+        } else if let Some(stem) = words.strip_suffix("uno") {
+            format!("{}un", stem)
+        } else {
+            words
+        }
+    }
+
+    pub fn cardinal(n: u64) -> String {
+        if n == 0 {
+            return ONES[0].to_string();
+        }
+        let mut parts = Vec::new();
+        let millions = n / 1_000_000;
+        match millions {
+            0 => {}
+            1 => parts.push("un millón".to_string()),
+            _ if millions >= 1000 => parts.push(format!("{} millones", cardinal(millions).replace("uno", "un"))),
+            _ => parts.push(format!("{} millones", multiplier(millions))),
+        }
+        match n / 1000 % 1000 {
+            0 => {}
+            1 => parts.push("mil".to_string()),
+            count => parts.push(format!("{} mil", multiplier(count))),
+        }
+        if !n.is_multiple_of(1000) {
+            parts.push(below_thousand(n % 1000));
+        }
+        parts.join(" ")
+    }
+
+    const ORDINAL_ONES: [&str; 10] = ["", "primero", "segundo", "tercero", "cuarto", "quinto", "sexto", "séptimo", "octavo", "noveno"];
+    const ORDINAL_TENS: [&str; 10] = [
+        "", "décimo", "vigésimo", "trigésimo", "cuadragésimo", "quincuagésimo", "sexagésimo", "septuagésimo", "octogésimo", "nonagésimo",
+    ];
+
+    /// Ordinals up to 100; larger numbers are read as cardinals, as is usual in speech
+    pub fn ordinal(n: u64, form: Form) -> String {
+        let words = match n {
+            1..=9 => ORDINAL_ONES[n as usize].to_string(),
+            11 => "undécimo".to_string(),
+            12 => "duodécimo".to_string(),
+            13..=19 => format!("decimo{}", ORDINAL_ONES[(n - 10) as usize]),
+            10..=99 if n.is_multiple_of(10) => ORDINAL_TENS[(n / 10) as usize].to_string(),
+            21..=99 => format!("{} {}", ORDINAL_TENS[(n / 10) as usize], ORDINAL_ONES[(n % 10) as usize]),
+            100 => "centésimo".to_string(),
+            _ => return cardinal(n),
+        };
+        if form == Form::Feminine {
+            words.split(' ').map(|w| format!("{}a", &w[..w.len() - 1])).collect::<Vec<_>>().join(" ")
+        } else {
+            words
+        }
+    }
+}
+
+mod italian {
+    use super::Form;
+
+    const ONES: [&str; 20] = [
+        "zero", "uno", "due", "tre", "quattro", "cinque", "sei", "sette", "otto", "nove", "dieci", "undici", "dodici", "tredici", "quattordici",
+        "quindici", "sedici", "diciassette", "diciotto", "diciannove",
+    ];
+    const TENS: [&str; 10] = ["", "", "venti", "trenta", "quaranta", "cinquanta", "sessanta", "settanta", "ottanta", "novanta"];
+
+    fn below_hundred(n: u64) -> String {
+        let tens = TENS[(n / 10) as usize];
+        match n {
+            0..=19 => ONES[n as usize].to_string(),
+            _ if n.is_multiple_of(10) => tens.to_string(),
+            // The tens drop their vowel before uno and otto: ventuno, trentotto
+            _ if n % 10 == 1 || n % 10 == 8 => format!("{}{}", &tens[..tens.len() - 1], ONES[(n % 10) as usize]),
+            _ => format!("{}{}", tens, ONES[(n % 10) as usize]),
+        }
+    }
+
+    fn below_thousand(n: u64) -> String {
+        let (hundreds, rest) = (n / 100, n % 100);
+        let head = match hundreds {
+            0 => return below_hundred(rest),
+            1 => "cento".to_string(),
+            _ => format!("{}cento", ONES[hundreds as usize]),
+        };
+        match rest {
+            0 => head,
+            // cento drops its vowel before otto and ottanta
+            8 | 80..=89 => format!("{}{}", &head[..head.len() - 1], below_hundred(rest)),
+            _ => format!("{}{}", head, below_hundred(rest)),
+        }
+    }
+
+    pub fn cardinal(n: u64) -> String {
+        if n == 0 {
+            return ONES[0].to_string();
+        }
+        let mut parts = Vec::new();
+        for (scale, one, many) in [(1_000_000_000, "un miliardo", "miliardi"), (1_000_000, "un milione", "milioni")] {
+            match n / scale % 1000 {
+                0 => {}
+                1 => parts.push(one.to_string()),
+                count => parts.push(format!("{} {}", below_thousand(count), many)),
+            }
+        }
+        // Thousands and below form a single word
+        let mut word = match n / 1000 % 1000 {
+            0 => String::new(),
+            1 => "mille".to_string(),
+            count => format!("{}mila", below_thousand(count)),
+        };
+        if !n.is_multiple_of(1000) {
+            word.push_str(&below_thousand(n % 1000));
+        }
+        // A final "tre" in a compound is stressed: ventitré, duemilatré
+        if word.len() > 3 && n % 10 == 3 && n % 100 != 13 {
+            word = format!("{}é", &word[..word.len() - 1]);
+        }
+        if !word.is_empty() {
+            parts.push(word);
+        }
+        parts.join(" ")
+    }
+
+    const ORDINALS: [&str; 11] = ["", "primo", "secondo", "terzo", "quarto", "quinto", "sesto", "settimo", "ottavo", "nono", "decimo"];
+
+    pub fn ordinal(n: u64, form: Form) -> String {
+        let word = if (1..=10).contains(&n) {
+            ORDINALS[n as usize].to_string()
+        } else {
+            let words = cardinal(n).replace('é', "e");
+            if words.ends_with("tre") || words.ends_with("sei") {
+                format!("{}esimo", words)
+            } else {
+                let mut stem = words;
+                stem.pop();
+                format!("{}esimo", stem)
+            }
+        };
+        if form == Form::Feminine { format!("{}a", &word[..word.len() - 1]) } else { word }
+    }
+}
+
+mod russian {
+    use super::Form;
+
+    const ONES: [&str; 20] = [
+        "ноль", "один", "два", "три", "четыре", "пять", "шесть", "семь", "восемь", "девять", "десять", "одиннадцать", "двенадцать", "тринадцать",
+        "четырнадцать", "пятнадцать", "шестнадцать", "семнадцать", "восемнадцать", "девятнадцать",
+    ];
+    const TENS: [&str; 10] = ["", "", "двадцать", "тридцать", "сорок", "пятьдесят", "шестьдесят", "семьдесят", "восемьдесят", "девяносто"];
+    const HUNDREDS: [&str; 10] = ["", "сто", "двести", "триста", "четыреста", "пятьсот", "шестьсот", "семьсот", "восемьсот", "девятьсот"];
+
+    fn below_thousand(n: u64, feminine: bool) -> Vec<&'static str> {
+        let mut words = Vec::new();
+        if n / 100 > 0 {
+            words.push(HUNDREDS[(n / 100) as usize]);
+        }
+        let rest = n % 100;
+        let unit = if rest < 20 { rest } else { rest % 10 };
+        if rest >= 20 {
+            words.push(TENS[(rest / 10) as usize]);
+        }
+        match unit {
+            0 => {}
+            1 if feminine => words.push("одна"),
+            2 if feminine => words.push("две"),
+            _ => words.push(ONES[unit as usize]),
+        }
+        words
+    }
+
+    /// Noun form after a count: 1 тысяча, 2 тысячи, 5 тысяч
+    fn plural(count: u64, forms: [&str; 3]) -> &str {
+        match (count % 10, count % 100) {
+            (_, 11..=14) => forms[2],
+            (1, _) => forms[0],
+            (2..=4, _) => forms[1],
+            _ => forms[2],
+        }
+    }
+
+    pub fn cardinal(n: u64) -> String {
+        if n == 0 {
+            return ONES[0].to_string();
+        }
+        let mut words = Vec::new();
+        for (scale, forms, feminine) in [
+            (1_000_000_000, ["миллиард", "миллиарда", "миллиардов"], false),
+            (1_000_000, ["миллион", "миллиона", "миллионов"], false),
+            (1_000, ["тысяча", "тысячи", "тысяч"], true),
+        ] {
+            let count = n / scale % 1000;
+            if count > 0 {
+                words.extend(below_thousand(count, feminine));
+                words.push(plural(count, forms));
+            }
+        }
+        words.extend(below_thousand(n % 1000, false));
+        words.join(" ")
+    }
+
+    const ORDINAL_ONES: [&str; 20] = [
+        "", "первый", "второй", "третий", "четвёртый", "пятый", "шестой", "седьмой", "восьмой", "девятый", "десятый", "одиннадцатый",
+        "двенадцатый", "тринадцатый", "четырнадцатый", "пятнадцатый", "шестнадцатый", "семнадцатый", "восемнадцатый", "девятнадцатый",
+    ];
+    const ORDINAL_TENS: [&str; 10] = [
+        "", "", "двадцатый", "тридцатый", "сороковой", "пятидесятый", "шестидесятый", "семидесятый", "восьмидесятый", "девяностый",
+    ];
+    const ORDINAL_HUNDREDS: [&str; 10] = [
+        "", "сотый", "двухсотый", "трёхсотый", "четырёхсотый", "пятисотый", "шестисотый", "семисотый", "восьмисотый", "девятисотый",
+    ];
+    /// Genitive stems that prefix "тысячный": двухтысячный, пятитысячный
+    const THOUSAND_PREFIXES: [&str; 10] = ["", "", "двух", "трёх", "четырёх", "пяти", "шести", "семи", "восьми", "девяти"];
+
+    /// Only the last word of a Russian ordinal is an ordinal; the rest stays cardinal
+    fn masculine(n: u64) -> String {
+        let (head, last) = if !n.is_multiple_of(100) {
+            let rest = n % 100;
+            if rest < 20 {
+                (n - rest, ORDINAL_ONES[rest as usize].to_string())
+            } else if rest.is_multiple_of(10) {
+                (n - rest, ORDINAL_TENS[(rest / 10) as usize].to_string())
+            } else {
+                (n - rest % 10, ORDINAL_ONES[(rest % 10) as usize].to_string())
+            }
+        } else if !n.is_multiple_of(1000) {
+            (n - n % 1000, ORDINAL_HUNDREDS[(n % 1000 / 100) as usize].to_string())
+        } else if n < 10_000 {
+            (0, format!("{}тысячный", THOUSAND_PREFIXES[(n / 1000) as usize]))
+        } else {
+            return format!("{}-й", cardinal(n));
+        };
+        if head == 0 { last } else { format!("{} {}", cardinal(head), last) }
+    }
+
+    pub fn ordinal(n: u64, form: Form) -> String {
+        let words = masculine(n);
+        let (head, last) = super::split_last_word(&words);
+        let last = if let Some(stem) = last.strip_suffix("ий") {
+            // третий: третья, третье, третьего
+            match form {
+                Form::Masculine => last.to_string(),
+                Form::Feminine => format!("{}ья", stem),
+                Form::Neuter => format!("{}ье", stem),
+                Form::Genitive => format!("{}ьего", stem),
+                Form::Prepositional => format!("{}ьем", stem),
+            }
+        } else if let Some(stem) = last.strip_suffix("ый").or_else(|| last.strip_suffix("ой")) {
+            match form {
+                Form::Masculine => last.to_string(),
+                Form::Feminine => format!("{}ая", stem),
+                Form::Neuter => format!("{}ое", stem),
+                Form::Genitive => format!("{}ого", stem),
+                Form::Prepositional => format!("{}ом", stem),
+            }
+        } else {
+            last.to_string()
+        };
+        format!("{}{}", head, last)
+    }
+}
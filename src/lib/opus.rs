@@ -0,0 +1,124 @@
+//! Ogg Opus encoding with libopus, written straight from the samples: no ffmpeg and no temp files.
+//! Opus is what game engines such as Unity and Godot expect for voice banks.
+
+use crate::encode::{vorbis_comments, EncodeSettings, DEFAULT_OPUS_KBPS, OPUS_FRAME_SIZES_MS};
+use crate::tags::AudioTags;
+use audiopus::coder::Encoder;
+use audiopus::{Application, Bitrate, Channels, SampleRate, Signal};
+
+/// Opus always codes at 48 kHz; other rates are resampled to it
+const OPUS_SAMPLE_RATE: u32 = 48000;
+/// Largest Opus packet libopus is asked to write
+const MAX_OPUS_PACKET: usize = 4000;
+/// Ogg stream serial number, fixed so the same audio always encodes to the same file
+const OGG_SERIAL: u32 = 0x7074_7473;
+
+/// Encode interleaved samples of 1 or 2 channels as an Ogg Opus file at the settings' bitrate and
+/// frame size, with `tags` as Vorbis comments. Samples are resampled to 48 kHz, and the original rate
+/// is recorded in the header.
+pub fn encode_opus(samples: &[f32], channels: u16, sample_rate: u32, settings: &EncodeSettings, tags: &AudioTags) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if !OPUS_FRAME_SIZES_MS.contains(&settings.opus_frame_ms) {
+        return Err(format!("Invalid Opus frame size {}ms (expected 2.5, 5, 10, 20, 40, or 60)", settings.opus_frame_ms).into());
+    }
+    let channels = channels.clamp(1, 2) as usize;
+    let mut encoder = Encoder::new(SampleRate::Hz48000, if channels == 2 { Channels::Stereo } else { Channels::Mono }, Application::Audio)?;
+    encoder.set_signal(Signal::Voice)?;
+    encoder.set_bitrate(Bitrate::BitsPerSecond(settings.bitrate_kbps.unwrap_or(DEFAULT_OPUS_KBPS) as i32 * 1000))?;
+    let pre_skip = encoder.lookahead()? as usize;
+
+    let resampled: Vec<Vec<f32>> = (0..channels)
+        .map(|c| {
+            let channel: Vec<f32> = samples.iter().skip(c).step_by(channels).copied().collect();
+            if sample_rate == OPUS_SAMPLE_RATE { channel } else { crate::dsp::resample(&channel, sample_rate as usize, OPUS_SAMPLE_RATE as usize) }
+        })
+        .collect();
+    let length = resampled[0].len();
+    let frame = (settings.opus_frame_ms * OPUS_SAMPLE_RATE as f32 / 1000.0) as usize;
+    // Run on past the end by the encoder's lookahead so the tail is coded, then to a whole frame
+    let padded = (length + pre_skip).div_ceil(frame).max(1) * frame;
+    let mut pcm = vec![0.0f32; padded * channels];
+    for (c, channel) in resampled.iter().enumerate() {
+        for (i, sample) in channel.iter().enumerate() {
+            pcm[i * channels + c] = sample.clamp(-1.0, 1.0);
+        }
+    }
+
+    let mut head = b"OpusHead".to_vec();
+    head.push(1); // version
+    head.push(channels as u8);
+    head.extend_from_slice(&(pre_skip as u16).to_le_bytes());
+    head.extend_from_slice(&sample_rate.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes()); // output gain
+    head.push(0); // mono/stereo channel mapping
+    let mut comments = b"OpusTags".to_vec();
+    comments.extend(vorbis_comments(tags));
+
+    let mut ogg = OggWriter::default();
+    ogg.page(&[head], 0, false);
+    ogg.page(&[comments], 0, false);
+    let mut buffer = [0u8; MAX_OPUS_PACKET];
+    let mut page: Vec<Vec<u8>> = Vec::new();
+    let frames = padded / frame;
+    for (index, chunk) in pcm.chunks(frame * channels).enumerate() {
+        let size = encoder.encode_float(chunk, &mut buffer)?;
+        let packet = buffer[..size].to_vec();
+        // Pages are cut at the 255-segment limit or after about a second of audio
+        let segments: usize = page.iter().chain([&packet]).map(|p| p.len() / 255 + 1).sum();
+        if segments > 255 || page.len() * frame >= OPUS_SAMPLE_RATE as usize {
+            ogg.page(&page, (index * frame) as u64, false);
+            page.clear();
+        }
+        page.push(packet);
+        if index + 1 == frames {
+            // The last page's granule position marks where the real audio ends, so players drop the padding
+            ogg.page(&page, (pre_skip + length) as u64, true);
+        }
+    }
+    Ok(ogg.bytes)
+}
+
+/// Ogg pages of a single logical stream
+#[derive(Default)]
+struct OggWriter {
+    bytes: Vec<u8>,
+    sequence: u32,
+}
+
+impl OggWriter {
+    /// Write `packets` as one page ending at `granule` (samples at 48 kHz, including pre-skip)
+    fn page(&mut self, packets: &[Vec<u8>], granule: u64, last: bool) {
+        let mut lacing = Vec::new();
+        for packet in packets {
+            lacing.extend(std::iter::repeat_n(255u8, packet.len() / 255));
+            lacing.push((packet.len() % 255) as u8);
+        }
+        let flags = if self.sequence == 0 { 0x02 } else { 0 } | if last { 0x04 } else { 0 };
+        let mut page = b"OggS".to_vec();
+        page.push(0); // version
+        page.push(flags);
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&OGG_SERIAL.to_le_bytes());
+        page.extend_from_slice(&self.sequence.to_le_bytes());
+        page.extend_from_slice(&[0; 4]); // CRC, filled in below
+        page.push(lacing.len() as u8);
+        page.extend(lacing);
+        for packet in packets {
+            page.extend_from_slice(packet);
+        }
+        let crc = ogg_crc(&page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        self.bytes.extend(page);
+        self.sequence += 1;
+    }
+}
+
+fn ogg_crc(bytes: &[u8]) -> u32 {
+    let mut crc = 0u32;
+    for &byte in bytes {
+        crc ^= (byte as u32) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04C1_1DB7 } else { crc << 1 };
+        }
+    }
+    crc
+}
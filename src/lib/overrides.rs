@@ -0,0 +1,83 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Directory, beside a voice's files, holding its configs with overrides applied
+const OVERLAID_DIR: &str = ".overlaid";
+
+/// Apply a JSON Merge Patch (RFC 7396) to `target`: objects are merged key by key, `null` removes a
+/// key, and anything else replaces what was there
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let target = target.as_object_mut().expect("just made an object");
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Link `link` to `original`, or copy it where hard links aren't supported
+fn link_model(original: &Path, link: &Path) -> std::io::Result<()> {
+    // Replaced every time, so an upgraded model is never shadowed by a stale link
+    let _ = std::fs::remove_file(link);
+    std::fs::hard_link(original, link).or_else(|_| std::fs::copy(original, link).map(|_| ()))
+}
+
+/// A copy of the voice config at `config_path` with the overlay file applied, for loading with
+/// `piper_rs::from_config_path`. The downloaded files are left untouched: the patched config is
+/// written to a `.overlaid` directory beside them, with the model files linked next to it.
+pub fn overlaid_config(config_path: &Path, overlay_path: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let mut config: Value = serde_json::from_str(&std::fs::read_to_string(config_path)?)?;
+    let overlay: Value = serde_json::from_str(&std::fs::read_to_string(overlay_path)?).map_err(|e| format!("{}: {}", overlay_path.display(), e))?;
+    if !overlay.is_object() {
+        return Err(format!("{}: expected a JSON object of config fields to override", overlay_path.display()).into());
+    }
+    merge_patch(&mut config, &overlay);
+
+    let voice_dir = config_path.parent().unwrap_or(Path::new("."));
+    let overlaid_dir = voice_dir.join(OVERLAID_DIR);
+    std::fs::create_dir_all(&overlaid_dir)?;
+    let file_name = config_path.file_name().ok_or("Invalid voice config path")?;
+    let overlaid_path = overlaid_dir.join(file_name);
+    let contents = serde_json::to_string_pretty(&config)?;
+    if std::fs::read_to_string(&overlaid_path).ok().as_deref() != Some(contents.as_str()) {
+        std::fs::write(&overlaid_path, contents)?;
+    }
+    // piper-rs finds the model beside its config: `<name>.onnx` for `<name>.onnx.json`, or the
+    // encoder and decoder of a streaming model
+    let models: Vec<PathBuf> = if config["streaming"].as_bool().unwrap_or_default() {
+        vec![PathBuf::from("encoder.onnx"), PathBuf::from("decoder.onnx")]
+    } else {
+        vec![PathBuf::from(config_path.file_stem().ok_or("Invalid voice config path")?)]
+    };
+    for model in models {
+        link_model(&voice_dir.join(&model), &overlaid_dir.join(&model))?;
+    }
+    Ok(overlaid_path)
+}
+
+#[cfg(feature = "full")]
+/// Where a voice's override file lives: `overrides/<voice id>.json` in the app data directory
+pub fn override_path(voice_id: &str) -> PathBuf {
+    crate::get_app_data_dir().join("overrides").join(format!("{}.json", voice_id))
+}
+
+#[cfg(feature = "full")]
+/// The config to load a voice from: `config_path`, or the overlaid copy if the voice has an override file
+pub fn config_for_voice(voice_id: &str, config_path: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let overlay = override_path(voice_id);
+    if !overlay.exists() {
+        return Ok(config_path.to_path_buf());
+    }
+    use colored::Colorize;
+    log::info!("{} Applying {}", "[Override]".cyan(), overlay.display());
+    overlaid_config(config_path, &overlay)
+}
@@ -0,0 +1,138 @@
+//! Inline pause markers, for pacing without SSML: `[pause]` (half a second), `[pause 1.5s]`, or
+//! `[pause 300ms]` puts exactly that much silence into the audio at that point. With `--ellipsis-pause`,
+//! an ellipsis (`...` or `…`) between words does the same, with the configured length.
+
+use crate::pauses::parse_pause;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Length of a `[pause]` marker without one of its own
+pub const DEFAULT_PAUSE: Duration = Duration::from_millis(500);
+
+/// A piece of marked-up text: text to speak, or silence
+#[derive(Debug, Clone, PartialEq)]
+pub enum PausePart {
+    Text(String),
+    Pause(Duration),
+}
+
+/// Set from `--ellipsis-pause`; `None` leaves ellipses to the model
+static ELLIPSIS_PAUSE: Mutex<Option<Duration>> = Mutex::new(None);
+
+pub fn set_ellipsis_pause(pause: Option<Duration>) {
+    *ELLIPSIS_PAUSE.lock().unwrap() = pause;
+}
+
+pub fn ellipsis_pause() -> Option<Duration> {
+    *ELLIPSIS_PAUSE.lock().unwrap()
+}
+
+/// `ellipsis_pause=600ms`, for sidecars, or `None` when ellipses aren't pauses
+pub fn describe() -> Option<String> {
+    ellipsis_pause().map(|pause| format!("ellipsis_pause={}ms", pause.as_millis()))
+}
+
+/// Find the next `[pause ...]` marker at or after `from`: its byte range, and its length or an error for
+/// a bad one. Other bracketed text is ordinary text.
+fn next_marker(text: &str, mut from: usize) -> Option<(usize, usize, Result<Duration, String>)> {
+    while let Some(offset) = text[from..].find('[') {
+        let open = from + offset;
+        from = open + 1;
+        let close = open + text[open..].find(']')?;
+        let inner = text[open + 1..close].trim();
+        let Some(argument) = inner.get(..5).filter(|word| word.eq_ignore_ascii_case("pause")).map(|_| inner[5..].trim()) else {
+            continue;
+        };
+        // `[pauses]` or `[paused]` is a word, not a marker
+        if !argument.is_empty() && !inner[5..].starts_with(char::is_whitespace) {
+            continue;
+        }
+        let pause = if argument.is_empty() { Ok(DEFAULT_PAUSE) } else { parse_pause(argument).map_err(|e| format!("{} in [{}]", e, inner)) };
+        return Some((open, close + 1, pause));
+    }
+    None
+}
+
+/// Find the next ellipsis at or after `from` that has words on both sides: its byte range
+fn next_ellipsis(text: &str, from: usize) -> Option<(usize, usize)> {
+    let mut chars = text[from..].char_indices().map(|(i, c)| (from + i, c)).peekable();
+    while let Some((i, c)) = chars.next() {
+        let end = match c {
+            '…' => i + c.len_utf8(),
+            '.' => {
+                let mut end = i + 1;
+                while chars.next_if(|&(_, next)| next == '.').is_some() {
+                    end += 1;
+                }
+                if end - i < 3 {
+                    continue;
+                }
+                end
+            }
+            _ => continue,
+        };
+        let before = text[..i].trim_end();
+        let after = &text[end..];
+        if before.chars().next_back().is_some_and(char::is_alphanumeric) && after.starts_with(char::is_whitespace) && after.trim_start().starts_with(char::is_alphanumeric) {
+            return Some((i, end));
+        }
+    }
+    None
+}
+
+/// Split `text` at its pause markers and, with the current [`ellipsis_pause`], its ellipses
+pub fn split_pauses(text: &str) -> Result<Vec<PausePart>, String> {
+    split_pauses_with(text, ellipsis_pause())
+}
+
+/// Split `text` into text and pauses. An ellipsis stays at the end of the text before its pause, so the
+/// voice still trails off; pauses next to each other add up.
+pub fn split_pauses_with(text: &str, ellipsis: Option<Duration>) -> Result<Vec<PausePart>, String> {
+    let mut parts = Vec::new();
+    let push = |parts: &mut Vec<PausePart>, part: PausePart| match (parts.last_mut(), part) {
+        (_, PausePart::Text(text)) if text.trim().is_empty() => {}
+        (Some(PausePart::Pause(last)), PausePart::Pause(pause)) => *last += pause,
+        (_, part) => parts.push(part),
+    };
+    let mut start = 0;
+    loop {
+        let marker = next_marker(text, start);
+        let ellipsis_at = ellipsis.and_then(|pause| next_ellipsis(text, start).map(|(from, to)| (from, to, pause)));
+        match (marker, ellipsis_at) {
+            (Some((from, to, pause)), ellipsis_at) if ellipsis_at.is_none_or(|(at, _, _)| from < at) => {
+                push(&mut parts, PausePart::Text(text[start..from].trim().to_string()));
+                push(&mut parts, PausePart::Pause(pause?));
+                start = to;
+            }
+            (_, Some((_, to, pause))) => {
+                push(&mut parts, PausePart::Text(text[start..to].trim().to_string()));
+                push(&mut parts, PausePart::Pause(pause));
+                start = to;
+            }
+            (_, None) => break,
+        }
+    }
+    push(&mut parts, PausePart::Text(text[start..].trim().to_string()));
+    Ok(parts)
+}
+
+/// Whether the text has pause markers or, with the current [`ellipsis_pause`], ellipses that pause
+pub fn has_pauses(text: &str) -> bool {
+    next_marker(text, 0).is_some() || ellipsis_pause().is_some_and(|_| next_ellipsis(text, 0).is_some())
+}
+
+/// The text without its pause markers, for lipsync and subtitles
+pub fn strip_markers(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut start = 0;
+    while let Some((from, to, _)) = next_marker(text, start) {
+        output.push_str(text[start..from].trim_end());
+        let rest = text[to..].trim_start();
+        if !output.is_empty() && rest.starts_with(|c: char| !c.is_ascii_punctuation()) {
+            output.push(' ');
+        }
+        start = text.len() - rest.len();
+    }
+    output.push_str(&text[start..]);
+    output
+}
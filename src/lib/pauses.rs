@@ -0,0 +1,120 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Silence placed between sentences and paragraphs in place of whatever the model produces.
+/// `None` keeps the model's own pauses.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PauseSettings {
+    pub sentence: Option<Duration>,
+    pub paragraph: Option<Duration>,
+}
+
+impl PauseSettings {
+    pub fn is_default(&self) -> bool {
+        self.sentence.is_none() && self.paragraph.is_none()
+    }
+
+    /// Settings as sidecar effect labels, so changing a pause invalidates up-to-date checks
+    pub fn effects(&self) -> Vec<String> {
+        let mut effects = Vec::new();
+        if let Some(pause) = self.sentence {
+            effects.push(format!("sentence_pause={}ms", pause.as_millis()));
+        }
+        if let Some(pause) = self.paragraph {
+            effects.push(format!("paragraph_pause={}ms", pause.as_millis()));
+        }
+        effects
+    }
+}
+
+/// Set from `--sentence-pause` / `--paragraph-pause`
+static PAUSES: Mutex<PauseSettings> = Mutex::new(PauseSettings { sentence: None, paragraph: None });
+
+pub fn set_pauses(settings: PauseSettings) {
+    *PAUSES.lock().unwrap() = settings;
+}
+
+pub fn pauses() -> PauseSettings {
+    *PAUSES.lock().unwrap()
+}
+
+/// Parse a pause length such as `300ms`, `0.8s`, or `250` (milliseconds)
+pub fn parse_pause(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (number, scale) = if let Some(ms) = s.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(secs) = s.strip_suffix('s') {
+        (secs, 1.0)
+    } else {
+        (s, 0.001)
+    };
+    let value: f64 = number.trim().parse().map_err(|_| format!("Invalid pause '{}': use e.g. 300ms or 0.5s", s))?;
+    if !(0.0..=60.0).contains(&(value * scale)) {
+        return Err(format!("Pause '{}' must be between 0 and 60 seconds", s));
+    }
+    Ok(Duration::from_secs_f64(value * scale))
+}
+
+/// Split text into paragraphs at blank lines
+pub fn split_paragraphs(text: &str) -> Vec<String> {
+    let mut paragraphs = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                paragraphs.push(current.join(" "));
+                current.clear();
+            }
+        } else {
+            current.push(line.trim());
+        }
+    }
+    if !current.is_empty() {
+        paragraphs.push(current.join(" "));
+    }
+    paragraphs
+}
+
+/// Strip near-silence from the start and/or end of a chunk, keeping a few milliseconds so word onsets aren't clipped
+pub fn trim_silence(samples: &[f32], sample_rate: u32, start: bool, end: bool) -> &[f32] {
+    let margin = sample_rate as usize / 100;
+    let loud = |s: &f32| s.abs() > 0.01;
+    let (Some(first), Some(last)) = (samples.iter().position(loud), samples.iter().rposition(loud)) else {
+        return if start || end { &samples[0..0] } else { samples };
+    };
+    let from = if start { first.saturating_sub(margin) } else { 0 };
+    let to = if end { (last + 1 + margin).min(samples.len()) } else { samples.len() };
+    &samples[from..to]
+}
+
+pub(crate) fn silence(duration: Duration, sample_rate: u32) -> impl Iterator<Item = f32> {
+    std::iter::repeat_n(0.0, (duration.as_secs_f64() * sample_rate as f64) as usize)
+}
+
+/// Join per-sentence chunks of each paragraph. At each boundary with a configured pause, the
+/// silence the model produced on either side is trimmed and replaced by exactly that pause;
+/// boundaries without one keep the model's own silence.
+pub fn join_with_pauses(paragraphs: Vec<Vec<Vec<f32>>>, settings: &PauseSettings, sample_rate: u32) -> Vec<f32> {
+    // Flatten to chunks, each with the pause that follows it (None after the last chunk)
+    let mut chunks: Vec<(Vec<f32>, Option<Duration>)> = Vec::new();
+    for sentences in paragraphs {
+        let count = sentences.len();
+        for (i, chunk) in sentences.into_iter().enumerate() {
+            chunks.push((chunk, if i + 1 == count { settings.paragraph } else { settings.sentence }));
+        }
+    }
+    if let Some(last) = chunks.last_mut() {
+        last.1 = None;
+    }
+
+    let mut output = Vec::new();
+    let mut previous_pause = None;
+    for (chunk, pause) in &chunks {
+        output.extend_from_slice(trim_silence(chunk, sample_rate, previous_pause.is_some(), pause.is_some()));
+        if let Some(pause) = pause {
+            output.extend(silence(*pause, sample_rate));
+        }
+        previous_pause = *pause;
+    }
+    output
+}
@@ -0,0 +1,258 @@
+use crate::llm_fallback::parse_arpabet_response;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// Where a word's ARPAbet phonemes can come from. The name is what lipsync JSON records as the word's
+/// `phoneme_method`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhonemeSource {
+    /// The user's own pronunciations (see [`Lexicon`])
+    Lexicon,
+    /// The CMU Pronouncing Dictionary
+    Cmudict,
+    /// Rule- and model-based guessing with the `g2p-en` Python package
+    G2p,
+    /// An Ollama model, when `--lipsync-with-llm` names one
+    Llm,
+}
+
+impl PhonemeSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PhonemeSource::Lexicon => "lexicon",
+            PhonemeSource::Cmudict => "cmudict",
+            PhonemeSource::G2p => "g2p",
+            PhonemeSource::Llm => "llm",
+        }
+    }
+}
+
+impl FromStr for PhonemeSource {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "lexicon" => Ok(PhonemeSource::Lexicon),
+            "cmudict" => Ok(PhonemeSource::Cmudict),
+            "g2p" => Ok(PhonemeSource::G2p),
+            "llm" => Ok(PhonemeSource::Llm),
+            other => Err(format!("Unknown phoneme source '{}' (expected lexicon, cmudict, g2p, or llm)", other)),
+        }
+    }
+}
+
+/// The order sources are tried in when nothing else is configured
+pub const DEFAULT_SOURCES: &[PhonemeSource] = &[PhonemeSource::Lexicon, PhonemeSource::Cmudict, PhonemeSource::G2p, PhonemeSource::Llm];
+
+/// A source order, optionally for one language only: `lexicon,cmudict,llm` or `de=lexicon,llm`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceOrder {
+    pub language: Option<String>,
+    pub sources: Vec<PhonemeSource>,
+}
+
+impl FromStr for SourceOrder {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (language, list) = match s.split_once('=') {
+            Some((language, list)) => (Some(language.trim().to_string()), list),
+            None => (None, s),
+        };
+        let sources = list.split(',').filter(|s| !s.trim().is_empty()).map(PhonemeSource::from_str).collect::<Result<Vec<_>, _>>()?;
+        if sources.is_empty() {
+            return Err(format!("No phoneme sources in '{}'", s));
+        }
+        Ok(SourceOrder { language, sources })
+    }
+}
+
+/// Which sources are tried, in which order, for each language
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhonemeSourceSettings {
+    /// Orders given; the last one without a language applies to every language without its own
+    pub orders: Vec<SourceOrder>,
+    /// Language of the text being looked up (`en`, `de`, ...), usually the voice's
+    pub language: Option<String>,
+}
+
+impl PhonemeSourceSettings {
+    /// The sources to try for the current language
+    pub fn sources(&self) -> Vec<PhonemeSource> {
+        let for_language = self.language.as_deref().and_then(|language| self.orders.iter().rev().find(|o| o.language.as_deref() == Some(language)));
+        let general = || self.orders.iter().rev().find(|o| o.language.is_none());
+        for_language.or_else(general).map(|o| o.sources.clone()).unwrap_or_else(|| DEFAULT_SOURCES.to_vec())
+    }
+}
+
+static SETTINGS: Mutex<Option<PhonemeSourceSettings>> = Mutex::new(None);
+
+pub fn set_settings(settings: PhonemeSourceSettings) {
+    *SETTINGS.lock().unwrap() = Some(settings);
+}
+
+pub fn settings() -> PhonemeSourceSettings {
+    SETTINGS.lock().unwrap().clone().unwrap_or_default()
+}
+
+/// The user's own pronunciations, checked before (or after) the dictionary as configured.
+///
+/// Lexicon files use CMUdict's layout, one word per line followed by its phonemes, or by a
+/// respelling in braces. Case doesn't matter; blank lines, lines starting with `#` or `;;;`, and
+/// trailing ` # comments` are ignored. CMUdict's alternate pronunciations (`WORD(2)`) are skipped
+/// in favour of the first, so a `.dict` file in CMUdict's own format can be used as is.
+///
+/// ```text
+/// # Character names
+/// HERMIONE  HH ER0 M AY1 AH0 N IY0
+/// Siobhan   {shiv-AWN}
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Lexicon {
+    pub entries: HashMap<String, Vec<String>>,
+}
+
+impl Lexicon {
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut entries = HashMap::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.split_once(" #").map_or(line, |(line, _)| line).trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(";;;") {
+                continue;
+            }
+            let (word, pronunciation) = line.split_once(char::is_whitespace).ok_or_else(|| format!("line {}: expected a word and its phonemes", number + 1))?;
+            let pronunciation = pronunciation.trim();
+            if word.ends_with(')') && word.contains('(') {
+                continue;
+            }
+            let phonemes = match pronunciation.strip_prefix('{').and_then(|p| p.strip_suffix('}')) {
+                Some(respelling) => crate::respelling::respelling_to_arpabet(respelling).map_err(|e| format!("line {}: {}", number + 1, e))?,
+                None => {
+                    let phonemes = parse_arpabet_response(&pronunciation.to_uppercase());
+                    if phonemes.len() != pronunciation.split_whitespace().count() {
+                        return Err(format!("line {}: '{}' isn't all ARPAbet phonemes", number + 1, pronunciation));
+                    }
+                    phonemes
+                }
+            };
+            entries.insert(word.to_uppercase(), phonemes);
+        }
+        Ok(Lexicon { entries })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(Self::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))?)
+    }
+
+    /// Phonemes for a word, ignoring case and surrounding punctuation
+    pub fn get(&self, word: &str) -> Option<&Vec<String>> {
+        self.entries.get(&word.trim_matches(|c: char| !c.is_alphanumeric()).to_uppercase())
+    }
+}
+
+/// Active lexicons, in the order they were registered; later ones win
+static LEXICONS: Mutex<Vec<Lexicon>> = Mutex::new(Vec::new());
+
+/// Replace every active lexicon with `lexicon` (or none)
+pub fn set_lexicon(lexicon: Option<Lexicon>) {
+    *LEXICONS.lock().unwrap() = lexicon.into_iter().collect();
+}
+
+/// Add a lexicon on top of the active ones, so its pronunciations win over theirs
+pub fn register_lexicon(lexicon: Lexicon) {
+    LEXICONS.lock().unwrap().push(lexicon);
+}
+
+/// A word's phonemes from the most recently registered lexicon that has it
+pub fn lexicon_phonemes(word: &str) -> Option<Vec<String>> {
+    LEXICONS.lock().unwrap().iter().rev().find_map(|lexicon| lexicon.get(word).cloned())
+}
+
+#[cfg(feature = "full")]
+/// Lexicon file picked up automatically when `--lexicon` isn't given
+pub fn default_lexicon_path() -> std::path::PathBuf {
+    crate::get_app_data_dir().join("lexicon.txt")
+}
+
+#[cfg(feature = "full")]
+/// Load and activate the lexicons in `paths`, later files winning over earlier ones, or the default
+/// lexicon file if none are given and it exists. Returns the number of words now in the lexicons.
+pub fn load_lexicons(paths: &[&Path]) -> Result<usize, Box<dyn std::error::Error>> {
+    *LEXICON_PATHS.lock().unwrap() = paths.iter().map(|path| path.to_path_buf()).collect();
+    let lexicons = if paths.is_empty() {
+        // A missing default file just means no lexicon; a missing explicit one is an error
+        let path = default_lexicon_path();
+        if path.exists() { vec![Lexicon::from_file(&path)?] } else { Vec::new() }
+    } else {
+        paths.iter().map(|path| Lexicon::from_file(path)).collect::<Result<Vec<_>, _>>()?
+    };
+    let count = lexicons.iter().map(|l| l.entries.len()).sum();
+    *LEXICONS.lock().unwrap() = lexicons;
+    Ok(count)
+}
+
+#[cfg(feature = "full")]
+/// Lexicon files given to [`load_lexicons`], remembered for [`reload_lexicons`]
+static LEXICON_PATHS: Mutex<Vec<std::path::PathBuf>> = Mutex::new(Vec::new());
+
+#[cfg(feature = "full")]
+/// Re-read the lexicon files last passed to [`load_lexicons`] (or the default one), so long-running
+/// processes pick up edits without a restart. On error the current lexicons stay active.
+pub fn reload_lexicons() -> Result<usize, Box<dyn std::error::Error>> {
+    let paths = LEXICON_PATHS.lock().unwrap().clone();
+    load_lexicons(&paths.iter().map(|path| path.as_path()).collect::<Vec<_>>())
+}
+
+/// Looks up every word on stdin with g2p-en, printing `word<TAB>phonemes` per line
+const G2P_SCRIPT: &str = r#"
+import sys
+from g2p_en import G2p
+g2p = G2p()
+for word in sys.stdin.read().split():
+    print(word + "\t" + " ".join(g2p(word)))
+"#;
+
+/// Set once g2p-en has failed to run, so it's reported once rather than on every lookup
+static G2P_UNAVAILABLE: Mutex<bool> = Mutex::new(false);
+
+/// Phonemes for each of `words` from the `g2p-en` Python package, in one run. Uses the Python in
+/// `G2P_PYTHON`, or `python3`. Returns nothing if g2p-en isn't installed.
+pub fn g2p_phonemes(words: &[String]) -> HashMap<String, Vec<String>> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+    let mut unavailable = G2P_UNAVAILABLE.lock().unwrap();
+    if words.is_empty() || *unavailable {
+        return HashMap::new();
+    }
+    let python = std::env::var("G2P_PYTHON").unwrap_or_else(|_| "python3".to_string());
+    let output = Command::new(&python)
+        .envs(crate::nice::child_env())
+        .arg("-c")
+        .arg(G2P_SCRIPT)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.take().expect("piped stdin").write_all(words.join("\n").as_bytes())?;
+            child.wait_with_output()
+        });
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(word, phonemes)| (word.to_string(), parse_arpabet_response(phonemes)))
+            .filter(|(_, phonemes)| !phonemes.is_empty())
+            .collect(),
+        failed => {
+            let reason = match failed {
+                Ok(output) => String::from_utf8_lossy(&output.stderr).lines().last().unwrap_or_default().to_string(),
+                Err(e) => e.to_string(),
+            };
+            log::warn!("[ARPAbet] g2p-en unavailable ({}); install it with `pip install g2p-en` or leave g2p out of --phoneme-sources", reason);
+            *unavailable = true;
+            HashMap::new()
+        }
+    }
+}
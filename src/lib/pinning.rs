@@ -0,0 +1,208 @@
+use crate::http::download;
+use crate::sidecar::sha256_file;
+use crate::{get_models_dir, Voice};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Upstream repository the voice catalog downloads from
+const VOICES_REPO: &str = "rhasspy/piper-voices";
+
+/// The exact upstream files an installed voice came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoiceLock {
+    /// Upstream git revision the files were downloaded at
+    pub revision: String,
+    pub model_sha256: String,
+    pub config_sha256: String,
+    /// Pinned voices are always downloaded at `revision` and checked against the hashes
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// `voices.lock.json`: recorded revisions of installed voices, keyed by voice ID.
+/// Commit it to a project and pass it with `--voice-lock` to get identical voices on every machine.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VoiceLockfile {
+    pub voices: BTreeMap<String, VoiceLock>,
+}
+
+impl VoiceLockfile {
+    /// Read a lockfile; a missing file is an empty lockfile
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(path)?;
+        serde_json::from_str(&contents).map_err(|e| format!("Invalid voice lockfile {}: {}", path.display(), e).into())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Set from `--voice-lock`; `None` uses the lockfile in the models directory
+static LOCKFILE_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Held while the lockfile is read, changed, and written back, so voices installed in parallel are
+/// all recorded
+static LOCKFILE_UPDATE: Mutex<()> = Mutex::new(());
+
+pub fn set_lockfile_path(path: Option<PathBuf>) {
+    *LOCKFILE_PATH.lock().unwrap() = path;
+}
+
+pub fn lockfile_path() -> PathBuf {
+    LOCKFILE_PATH.lock().unwrap().clone().unwrap_or_else(|| get_models_dir().join("voices.lock.json"))
+}
+
+/// The recorded lock for a voice, if any
+pub fn voice_lock(voice_id: &str) -> Option<VoiceLock> {
+    VoiceLockfile::load(&lockfile_path()).ok()?.voices.get(voice_id).cloned()
+}
+
+/// Current revision of the upstream voice repository's main branch
+pub fn upstream_revision() -> Result<String, Box<dyn std::error::Error>> {
+    let url = format!("https://huggingface.co/api/models/{}/revision/main", VOICES_REPO);
+    let body = crate::http::get_text(&url).map_err(|e| format!("Failed to look up the latest voice revision: {}", e))?;
+    let info: serde_json::Value = serde_json::from_str(&body)?;
+    info.get("sha").and_then(|s| s.as_str()).map(str::to_string).ok_or_else(|| "No revision in upstream response".into())
+}
+
+/// A catalog URL (which tracks `main`) rewritten to fetch the file at a fixed revision
+pub fn url_at_revision(url: &str, revision: &str) -> String {
+    url.replacen("/resolve/main/", &format!("/resolve/{}/", revision), 1)
+}
+
+fn voice_paths(voice_id: &str) -> (PathBuf, PathBuf) {
+    let models_dir = get_models_dir();
+    (models_dir.join(format!("{}.onnx", voice_id)), models_dir.join(format!("{}.onnx.json", voice_id)))
+}
+
+/// Check installed files against a lock, naming the first file that differs
+pub fn verify_voice(voice_id: &str, lock: &VoiceLock) -> Result<(), Box<dyn std::error::Error>> {
+    let (model_path, config_path) = voice_paths(voice_id);
+    for (path, expected) in [(&model_path, &lock.model_sha256), (&config_path, &lock.config_sha256)] {
+        let actual = sha256_file(path)?;
+        if &actual != expected {
+            return Err(format!("{} doesn't match the locked hash (expected {}, found {})", path.display(), expected, actual).into());
+        }
+    }
+    Ok(())
+}
+
+/// Record the installed files of a voice in the lockfile, keeping its pinned state
+pub fn record_voice(voice_id: &str, revision: &str) -> Result<VoiceLock, Box<dyn std::error::Error>> {
+    let path = lockfile_path();
+    let (model_path, config_path) = voice_paths(voice_id);
+    let (model_sha256, config_sha256) = (sha256_file(&model_path)?, sha256_file(&config_path)?);
+    let _updating = LOCKFILE_UPDATE.lock().unwrap();
+    let mut lockfile = VoiceLockfile::load(&path)?;
+    let lock = VoiceLock {
+        revision: revision.to_string(),
+        model_sha256,
+        config_sha256,
+        pinned: lockfile.voices.get(voice_id).is_some_and(|l| l.pinned),
+    };
+    lockfile.voices.insert(voice_id.to_string(), lock.clone());
+    lockfile.save(&path)?;
+    Ok(lock)
+}
+
+/// Drop a removed voice from the lockfile, unless it's pinned
+pub fn forget_voice(voice_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let path = lockfile_path();
+    let _updating = LOCKFILE_UPDATE.lock().unwrap();
+    let mut lockfile = VoiceLockfile::load(&path)?;
+    if lockfile.voices.get(voice_id).is_some_and(|lock| !lock.pinned) {
+        lockfile.voices.remove(voice_id);
+        lockfile.save(&path)?;
+    }
+    Ok(())
+}
+
+/// Download a voice's model and config at `revision` into the models directory, replacing what's there
+fn download_at_revision(voice: &Voice, revision: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let (model_path, config_path) = voice_paths(&voice.id);
+    fs::create_dir_all(get_models_dir())?;
+    // Download beside the real files first so a failure leaves the installed voice intact
+    let model_tmp = model_path.with_extension("onnx.download");
+    let config_tmp = config_path.with_extension("json.download");
+    download(&url_at_revision(&voice.model_path, revision), &model_tmp, &format!("{}.onnx", voice.id))?;
+    if let Err(e) = download(&url_at_revision(&voice.config_path, revision), &config_tmp, &format!("{}.onnx.json", voice.id)) {
+        let _ = fs::remove_file(&model_tmp);
+        return Err(e.into());
+    }
+    fs::rename(&model_tmp, &model_path)?;
+    fs::rename(&config_tmp, &config_path)?;
+    Ok(())
+}
+
+/// Fetch a pinned voice at its locked revision and verify it, removing the files if they don't match
+pub fn install_pinned(voice: &Voice, lock: &VoiceLock) -> Result<(), Box<dyn std::error::Error>> {
+    log::info!("{} at pinned revision {}...", voice.display_name.yellow(), short(&lock.revision));
+    download_at_revision(voice, &lock.revision)?;
+    if let Err(e) = verify_voice(&voice.id, lock) {
+        let (model_path, config_path) = voice_paths(&voice.id);
+        let _ = fs::remove_file(model_path);
+        let _ = fs::remove_file(config_path);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Pin an installed voice at its recorded revision. Voices installed before revisions were recorded are
+/// pinned at the current upstream revision.
+pub fn pin_voice(voice_id: &str) -> Result<VoiceLock, Box<dyn std::error::Error>> {
+    let (model_path, _) = voice_paths(voice_id);
+    if !model_path.exists() {
+        return Err(format!("{} isn't installed; run `voices install {}` first", voice_id, voice_id).into());
+    }
+    let path = lockfile_path();
+    let mut lockfile = VoiceLockfile::load(&path)?;
+    let mut lock = match lockfile.voices.get(voice_id) {
+        Some(lock) => lock.clone(),
+        None => record_voice(voice_id, &upstream_revision()?)?,
+    };
+    lock.pinned = true;
+    lockfile.voices.insert(voice_id.to_string(), lock.clone());
+    lockfile.save(&path)?;
+    Ok(lock)
+}
+
+/// Outcome of [`upgrade_voice`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Upgrade {
+    /// Already at the latest revision, or upstream files are identical and only the recorded revision moved
+    UpToDate(VoiceLock),
+    Upgraded { from: Option<VoiceLock>, to: VoiceLock },
+}
+
+/// Move a voice to the latest upstream revision, re-downloading it if the files changed.
+/// A pinned voice stays pinned, at the new revision.
+pub fn upgrade_voice(voice: &Voice) -> Result<Upgrade, Box<dyn std::error::Error>> {
+    let revision = upstream_revision()?;
+    let previous = voice_lock(&voice.id);
+    if let Some(lock) = previous.as_ref().filter(|l| l.revision == revision && verify_voice(&voice.id, l).is_ok()) {
+        return Ok(Upgrade::UpToDate(lock.clone()));
+    }
+    download_at_revision(voice, &revision)?;
+    let lock = record_voice(&voice.id, &revision)?;
+    match previous {
+        Some(old) if old.model_sha256 == lock.model_sha256 && old.config_sha256 == lock.config_sha256 => Ok(Upgrade::UpToDate(lock)),
+        from => Ok(Upgrade::Upgraded { from, to: lock }),
+    }
+}
+
+/// First 12 characters of a revision, for display
+pub fn short(revision: &str) -> &str {
+    &revision[..revision.len().min(12)]
+}
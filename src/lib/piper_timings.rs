@@ -0,0 +1,168 @@
+//! Phoneme timings from Piper's own synthesis. A VITS voice predicts how many audio frames each
+//! phoneme lasts before rendering them, so those durations are exact where estimates and
+//! alignment are approximations. piper-rs keeps them to itself, so this runs the voice model
+//! directly with the same inputs piper-rs gives it.
+//!
+//! Stock Piper exports only return audio. Voices exported with a second output holding each
+//! phoneme ID's duration (as Piper's alignment export adds) report timings; for others the audio
+//! is still synthesized, without them.
+
+use crate::error::{Error, Result};
+use crate::{LipSyncData, Phoneme};
+use ndarray::{Array1, Array2};
+use ort::session::{Session, SessionInputValue, SessionInputs};
+use ort::value::Value;
+use piper_rs::PiperSynthesisConfig;
+use std::collections::HashMap;
+use std::path::Path;
+
+const BOS: char = '^';
+const EOS: char = '$';
+const PAD: char = '_';
+
+/// The parts of a voice config the model inputs need
+struct VoiceConfig {
+    sample_rate: u32,
+    num_speakers: u64,
+    phoneme_id_map: HashMap<char, Vec<i64>>,
+}
+
+fn read_voice_config(config_path: &Path) -> Result<VoiceConfig> {
+    let config: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(config_path)?)
+        .map_err(|e| format!("Invalid voice config {}: {}", config_path.display(), e))?;
+    if config.get("streaming").and_then(|v| v.as_bool()) == Some(true) {
+        return Err(format!("{} is a streaming (encoder/decoder) voice; phoneme timings need a single-model voice", config_path.display()).into());
+    }
+    let phoneme_id_map = config
+        .get("phoneme_id_map")
+        .and_then(|map| map.as_object())
+        .map(|map| {
+            map.iter()
+                .filter_map(|(symbol, ids)| Some((symbol.chars().next()?, ids.as_array()?.iter().filter_map(|id| id.as_i64()).collect())))
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(VoiceConfig {
+        sample_rate: crate::config_sample_rate(&config).unwrap_or(crate::DEFAULT_SAMPLE_RATE),
+        num_speakers: config.get("num_speakers").and_then(|v| v.as_u64()).unwrap_or(1),
+        phoneme_id_map,
+    })
+}
+
+/// Phoneme IDs for one sentence of IPA, laid out as piper-rs lays them out: BOS, each known phoneme
+/// followed by PAD, then EOS. Alongside each ID is the phoneme it voices (`None` for BOS, PAD, and EOS).
+pub fn phoneme_ids(phonemes: &str, phoneme_id_map: &HashMap<char, Vec<i64>>) -> Result<(Vec<i64>, Vec<Option<char>>)> {
+    let id = |symbol: char| {
+        phoneme_id_map.get(&symbol).and_then(|ids| ids.first().copied()).ok_or_else(|| Error::from(format!("Voice config has no phoneme ID for '{}'", symbol)))
+    };
+    let (pad, bos, eos) = (id(PAD)?, id(BOS)?, id(EOS)?);
+    let mut ids = vec![bos];
+    let mut symbols = vec![None];
+    for symbol in phonemes.chars() {
+        if let Ok(phoneme) = id(symbol) {
+            ids.extend([phoneme, pad]);
+            symbols.extend([Some(symbol), None]);
+        }
+    }
+    ids.push(eos);
+    symbols.push(None);
+    Ok((ids, symbols))
+}
+
+/// Timed phonemes for one sentence from the model's per-ID `durations` (in any unit; they are scaled
+/// to fill `seconds`), starting `offset` seconds into the audio. Each PAD belongs to the phoneme before
+/// it; BOS and EOS are the silence either side.
+pub fn phoneme_spans(symbols: &[Option<char>], durations: &[f32], seconds: f32, offset: f32) -> Vec<Phoneme> {
+    let total: f32 = durations.iter().sum();
+    let scale = if total > 0.0 { seconds / total } else { 0.0 };
+    let mut spans: Vec<Phoneme> = Vec::new();
+    let mut cursor = offset;
+    for (i, (symbol, duration)) in symbols.iter().zip(durations).enumerate() {
+        let end = cursor + duration * scale;
+        match (symbol, spans.last_mut()) {
+            (Some(symbol), _) => spans.push(Phoneme { phoneme: symbol.to_string(), start_time: cursor, end_time: end }),
+            (None, Some(previous)) if i + 1 < symbols.len() => previous.end_time = end,
+            _ => {}
+        }
+        cursor = end;
+    }
+    spans
+}
+
+/// Whether a phoneme separates words: spaces and punctuation
+fn is_boundary(phoneme: &str) -> bool {
+    phoneme.chars().all(|c| c.is_whitespace() || c.is_ascii_punctuation() || "…—–¡¿«»“”„‘’".contains(c))
+}
+
+/// The phonemes of `data` grouped into words, split at spaces and punctuation
+pub fn word_spans(data: &LipSyncData) -> Vec<Vec<&Phoneme>> {
+    let mut words: Vec<Vec<&Phoneme>> = Vec::new();
+    let mut current: Vec<&Phoneme> = Vec::new();
+    for phoneme in &data.phonemes {
+        if is_boundary(&phoneme.phoneme) {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(phoneme);
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn inference_failed(error: ort::Error) -> Error {
+    format!("Voice model inference failed: {}", error).into()
+}
+
+/// Synthesize `sentences` of IPA (from the voice's phonemizer) with the model beside `config_path`,
+/// using `synth`'s scales and speaker. Returns the samples, at the voice's sample rate, and the
+/// phoneme timings if the model reports durations.
+pub fn synthesize_with_timings(config_path: &Path, sentences: &[String], synth: &PiperSynthesisConfig) -> Result<(Vec<f32>, Option<LipSyncData>)> {
+    let config = read_voice_config(config_path)?;
+    // piper-rs's naming: `voice.onnx.json` sits beside `voice.onnx`
+    let model_path = config_path.with_file_name(config_path.file_stem().ok_or_else(|| format!("Invalid voice config path {}", config_path.display()))?);
+    let session = Session::builder().and_then(|builder| builder.commit_from_file(&model_path)).map_err(inference_failed)?;
+    let mut timed = session.outputs.len() > 1;
+    if !timed {
+        log::debug!("{} has no phoneme duration output", model_path.display());
+    }
+    let mut samples: Vec<f32> = Vec::new();
+    let mut phonemes: Vec<Phoneme> = Vec::new();
+    for sentence in sentences {
+        let (ids, symbols) = phoneme_ids(sentence, &config.phoneme_id_map)?;
+        let length = ids.len();
+        let mut inputs = vec![
+            SessionInputValue::from(Value::from_array(Array2::from_shape_vec((1, length), ids).map_err(|e| e.to_string())?).map_err(inference_failed)?),
+            SessionInputValue::from(Value::from_array(Array1::from_iter([length as i64])).map_err(inference_failed)?),
+            SessionInputValue::from(Value::from_array(Array1::from_iter([synth.noise_scale, synth.length_scale, synth.noise_w])).map_err(inference_failed)?),
+        ];
+        if config.num_speakers > 1 {
+            inputs.push(SessionInputValue::from(Value::from_array(Array1::from_iter([synth.speaker.unwrap_or(0)])).map_err(inference_failed)?));
+        }
+        let outputs = session.run(SessionInputs::from(inputs.as_slice())).map_err(inference_failed)?;
+        let audio: Vec<f32> = outputs[0].try_extract_tensor::<f32>().map_err(inference_failed)?.iter().copied().collect();
+        if timed {
+            // Durations come out as frame counts, float or integer depending on the export
+            let durations: Option<Vec<f32>> = match outputs[1].try_extract_tensor::<f32>() {
+                Ok(durations) => Some(durations.iter().copied().collect()),
+                Err(_) => outputs[1].try_extract_tensor::<i64>().ok().map(|durations| durations.iter().map(|&d| d as f32).collect()),
+            };
+            match durations.filter(|durations| durations.len() == symbols.len()) {
+                Some(durations) => {
+                    let offset = samples.len() as f32 / config.sample_rate as f32;
+                    phonemes.extend(phoneme_spans(&symbols, &durations, audio.len() as f32 / config.sample_rate as f32, offset));
+                }
+                None => {
+                    log::warn!("{}'s second output isn't one duration per phoneme ID; ignoring it", model_path.display());
+                    timed = false;
+                }
+            }
+        }
+        samples.extend(audio);
+    }
+    let lipsync = timed.then(|| LipSyncData { phonemes, duration: samples.len() as f32 / config.sample_rate as f32, sample_rate: config.sample_rate });
+    Ok((samples, lipsync))
+}
@@ -0,0 +1,196 @@
+use crate::streaming::SynthStream;
+use crate::{load_synthesizer, normalize, rules, time_stretch, true_pitch_shift, voice_sample_rate, PitchArg};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// What happens to speech already playing or queued when new text arrives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SpeakMode {
+    /// Stop immediately and speak the new text (screen-reader style)
+    Interrupt,
+    /// Speak the new text after everything already queued
+    Queue,
+}
+
+enum Request {
+    Speak { generation: u64, text: String },
+    /// Reload the voice model from disk, replying with the outcome
+    Reload(Sender<Result<(), String>>),
+    /// Switch to another voice, replying with the outcome
+    Voice(String, Sender<Result<(), String>>),
+    Pitch(f32),
+    Tempo(f32),
+    Shutdown,
+}
+
+/// Play a [`SynthStream`] on the output device, queueing each sentence as soon as it's ready
+pub fn play_streamed(stream: SynthStream) -> Result<(), Box<dyn std::error::Error>> {
+    let sample_rate = stream.sample_rate;
+    let (_stream, handle) = crate::devices::open_output_stream()?;
+    let sink = rodio::Sink::try_new(&handle)?;
+    for samples in stream {
+        sink.append(crate::stereo::playback_buffer(&samples?, sample_rate));
+    }
+    sink.sleep_until_end();
+    Ok(())
+}
+
+/// Continuous speech output: text is synthesized and played sentence by sentence on a background
+/// thread, so long passages start quickly and an interruption takes effect at once.
+pub struct Speaker {
+    requests: Sender<Request>,
+    sink: Arc<rodio::Sink>,
+    /// Bumped on every interruption; sentences from older generations are dropped
+    generation: Arc<AtomicU64>,
+    /// Texts sent but not yet fully synthesized
+    pending: Arc<AtomicUsize>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Speaker {
+    /// Start the playback thread and load the voice
+    pub fn new(voice: &str, pitch: PitchArg, tempo: f32) -> Result<Self, Box<dyn std::error::Error>> {
+        let (requests, inbox) = mpsc::channel::<Request>();
+        let (ready_tx, ready_rx) = mpsc::channel::<Result<Arc<rodio::Sink>, String>>();
+        let generation = Arc::new(AtomicU64::new(0));
+        let pending = Arc::new(AtomicUsize::new(0));
+        let mut voice = voice.to_string();
+        let (mut pitch, mut tempo) = (pitch.as_factor(), tempo);
+        let (worker_generation, worker_pending) = (generation.clone(), pending.clone());
+
+        let worker = std::thread::spawn(move || {
+            // The output stream isn't Send, so it lives on this thread for the speaker's lifetime
+            let setup = crate::devices::open_output_stream()
+                .map_err(|e| e.to_string())
+                .and_then(|(stream, handle)| Ok((stream, Arc::new(rodio::Sink::try_new(&handle).map_err(|e| e.to_string())?))))
+                .and_then(|(stream, sink)| Ok((stream, sink, load_synthesizer(&voice).map_err(|e| e.to_string())?)));
+            let (_stream, sink, mut synth) = match setup {
+                Ok(setup) => setup,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            let _ = ready_tx.send(Ok(sink.clone()));
+            let mut sample_rate = voice_sample_rate(&voice);
+
+            loop {
+                let (generation, text) = match inbox.recv() {
+                    Ok(Request::Speak { generation, text }) => (generation, text),
+                    Ok(Request::Reload(reply)) => {
+                        // Speech queued before the reload finishes with the old model
+                        crate::model_cache::evict(&voice);
+                        let result = load_synthesizer(&voice).map(|new_synth| synth = new_synth).map_err(|e| e.to_string());
+                        let _ = reply.send(result);
+                        continue;
+                    }
+                    Ok(Request::Voice(new_voice, reply)) => {
+                        let result = load_synthesizer(&new_voice)
+                            .map(|new_synth| {
+                                (synth, sample_rate) = (new_synth, voice_sample_rate(&new_voice));
+                                voice = new_voice;
+                            })
+                            .map_err(|e| e.to_string());
+                        let _ = reply.send(result);
+                        continue;
+                    }
+                    Ok(Request::Pitch(new_pitch)) => {
+                        pitch = new_pitch;
+                        continue;
+                    }
+                    Ok(Request::Tempo(new_tempo)) => {
+                        tempo = new_tempo;
+                        continue;
+                    }
+                    Ok(Request::Shutdown) | Err(_) => break,
+                };
+                let current = || worker_generation.load(Ordering::SeqCst) == generation;
+                if current()
+                    && let Ok(sentences) = synth.synthesize_lazy(normalize::normalize_for_voice(&rules::apply_rules(&text), &voice), None)
+                {
+                    for sentence in sentences {
+                        let Ok(sentence) = sentence else { break };
+                        let samples = true_pitch_shift(&sentence.into_vec(), sample_rate as usize, pitch);
+                        let samples = time_stretch(&samples, sample_rate as usize, tempo);
+                        // Checked after synthesizing too, in case an interruption arrived meanwhile
+                        if !current() {
+                            break;
+                        }
+                        sink.append(crate::stereo::playback_buffer(&samples, sample_rate));
+                    }
+                }
+                worker_pending.fetch_sub(1, Ordering::SeqCst);
+            }
+        });
+
+        let sink = ready_rx.recv().map_err(|_| "Playback thread exited")??;
+        Ok(Speaker { requests, sink, generation, pending, worker: Some(worker) })
+    }
+
+    /// Speak `text`, interrupting or queueing behind current speech according to `mode`
+    pub fn speak(&self, text: &str, mode: SpeakMode) {
+        if mode == SpeakMode::Interrupt {
+            self.stop();
+        }
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        let generation = self.generation.load(Ordering::SeqCst);
+        let _ = self.requests.send(Request::Speak { generation, text: text.to_string() });
+    }
+
+    /// Stop speaking and drop everything queued
+    pub fn stop(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.sink.clear();
+        self.sink.play();
+    }
+
+    /// Reload the voice model from disk (e.g. after `voices install --quantized` or an update) without
+    /// stopping playback. On failure the previous model stays in use.
+    pub fn reload(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let (reply, outcome) = mpsc::channel();
+        self.requests.send(Request::Reload(reply)).map_err(|_| "Playback thread exited")?;
+        Ok(outcome.recv().map_err(|_| "Playback thread exited")??)
+    }
+
+    /// Speak with `voice` from now on; text already sent keeps the previous one. On failure the previous
+    /// voice stays in use.
+    pub fn set_voice(&self, voice: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let (reply, outcome) = mpsc::channel();
+        self.requests.send(Request::Voice(voice.to_string(), reply)).map_err(|_| "Playback thread exited")?;
+        Ok(outcome.recv().map_err(|_| "Playback thread exited")??)
+    }
+
+    /// Pitch shift text sent from now on
+    pub fn set_pitch(&self, pitch: &PitchArg) {
+        let _ = self.requests.send(Request::Pitch(pitch.as_factor()));
+    }
+
+    /// Tempo for text sent from now on
+    pub fn set_tempo(&self, tempo: f32) {
+        let _ = self.requests.send(Request::Tempo(tempo));
+    }
+
+    /// Whether anything is playing or waiting to be spoken
+    pub fn is_speaking(&self) -> bool {
+        self.pending.load(Ordering::SeqCst) > 0 || !self.sink.empty()
+    }
+
+    /// Block until everything queued has been spoken
+    pub fn wait(&self) {
+        while self.is_speaking() {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
+
+impl Drop for Speaker {
+    fn drop(&mut self) {
+        let _ = self.requests.send(Request::Shutdown);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
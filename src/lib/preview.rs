@@ -0,0 +1,160 @@
+use crate::karaoke::build_karaoke;
+use crate::visemes::{mouth_form, mouth_openness, viseme_track};
+use crate::{read_wav, read_word_timings};
+use base64::Engine as _;
+use serde_json::json;
+
+/// Number of peaks drawn in the waveform
+const WAVEFORM_BUCKETS: usize = 600;
+
+/// Absolute peak of each bucket, for drawing a waveform
+fn waveform_peaks(samples: &[f32], buckets: usize) -> Vec<f32> {
+    if samples.is_empty() {
+        return vec![0.0; buckets];
+    }
+    let bucket_len = samples.len().div_ceil(buckets);
+    samples
+        .chunks(bucket_len)
+        .map(|chunk| chunk.iter().fold(0.0f32, |peak, s| peak.max(s.abs())))
+        .map(|peak| (peak * 1000.0).round() / 1000.0)
+        .collect()
+}
+
+const TEMPLATE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>text-to-face preview</title>
+<style>
+  body { font-family: system-ui, sans-serif; background: #1e1f24; color: #e6e6e6; max-width: 900px; margin: 2em auto; }
+  #stage { display: flex; gap: 2em; align-items: center; }
+  #face { background: #f2d4b7; border-radius: 50%; }
+  canvas { width: 100%; height: 90px; background: #2a2c33; border-radius: 6px; cursor: pointer; }
+  #transcript { font-size: 1.4em; line-height: 1.6; }
+  #transcript span.active { background: #f5c542; color: #1e1f24; border-radius: 3px; }
+  #viseme { font-family: monospace; color: #9aa0aa; }
+  audio { width: 100%; margin: 1em 0; }
+</style>
+</head>
+<body>
+<div id="stage">
+  <svg id="face" width="160" height="160" viewBox="0 0 160 160">
+    <circle cx="55" cy="60" r="8" fill="#333"/>
+    <circle cx="105" cy="60" r="8" fill="#333"/>
+    <ellipse id="mouth" cx="80" cy="110" rx="24" ry="2" fill="#7a2630"/>
+  </svg>
+  <div>
+    <div id="transcript"></div>
+    <div id="viseme">sil</div>
+  </div>
+</div>
+<audio id="audio" controls src="data:audio/wav;base64,__AUDIO__"></audio>
+<canvas id="waveform" width="1200" height="180"></canvas>
+<script>
+const DATA = __DATA__;
+const audio = document.getElementById('audio');
+const canvas = document.getElementById('waveform');
+const ctx = canvas.getContext('2d');
+const mouth = document.getElementById('mouth');
+const visemeLabel = document.getElementById('viseme');
+const transcript = document.getElementById('transcript');
+
+// Transcript: original text with each aligned word wrapped in a span
+let html = '', pos = 0;
+const spans = [];
+DATA.words.forEach((w, i) => {
+  const [s, e] = w.charOffsets;
+  if (s < pos) return;
+  html += escapeHtml(DATA.text.slice(pos, s)) + '<span data-i="' + i + '">' + escapeHtml(DATA.text.slice(s, e)) + '</span>';
+  pos = e;
+});
+html += escapeHtml(DATA.text.slice(pos));
+transcript.innerHTML = html;
+transcript.querySelectorAll('span').forEach(el => spans[+el.dataset.i] = el);
+transcript.addEventListener('click', ev => {
+  const i = ev.target.dataset && ev.target.dataset.i;
+  if (i !== undefined) { audio.currentTime = DATA.words[+i].start; audio.play(); }
+});
+
+function escapeHtml(s) {
+  return s.replace(/[&<>"]/g, c => ({'&': '&amp;', '<': '&lt;', '>': '&gt;', '"': '&quot;'}[c]));
+}
+
+function drawWaveform(progress) {
+  const w = canvas.width, h = canvas.height, n = DATA.peaks.length;
+  ctx.clearRect(0, 0, w, h);
+  for (let i = 0; i < n; i++) {
+    const x = i / n * w, amp = DATA.peaks[i] * h / 2;
+    ctx.fillStyle = i / n < progress ? '#f5c542' : '#5b606b';
+    ctx.fillRect(x, h / 2 - amp, Math.max(1, w / n - 1), amp * 2 || 1);
+  }
+}
+canvas.addEventListener('click', ev => {
+  const rect = canvas.getBoundingClientRect();
+  audio.currentTime = (ev.clientX - rect.left) / rect.width * DATA.duration;
+});
+
+function find(list, t) {
+  for (let i = 0; i < list.length; i++) if (t >= list[i].start && t < list[i].end) return i;
+  return -1;
+}
+
+let activeWord = -1;
+function frame() {
+  const t = audio.currentTime;
+  drawWaveform(DATA.duration ? t / DATA.duration : 0);
+  const v = find(DATA.visemes, t);
+  const shape = v >= 0 ? DATA.visemes[v] : { viseme: 'sil', open: 0, form: 0 };
+  mouth.setAttribute('ry', 2 + shape.open * 22);
+  mouth.setAttribute('rx', 24 * (1 + shape.form * 0.35));
+  visemeLabel.textContent = shape.viseme;
+  const w = find(DATA.words, t);
+  if (w !== activeWord) {
+    if (spans[activeWord]) spans[activeWord].classList.remove('active');
+    if (spans[w]) spans[w].classList.add('active');
+    activeWord = w;
+  }
+  requestAnimationFrame(frame);
+}
+requestAnimationFrame(frame);
+</script>
+</body>
+</html>
+"##;
+
+/// Write a self-contained HTML page embedding the audio, a waveform, a word-highlighting
+/// transcript, and a mouth animated from the viseme track, for reviewing lipsync in a browser.
+/// Without a lipsync JSON the page still plays the audio and shows the waveform.
+pub fn write_preview_html(output_path: &str, wav_path: &str, text: &str, lipsync_json: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let (samples, sample_rate) = read_wav(wav_path)?;
+    let audio_b64 = base64::engine::general_purpose::STANDARD.encode(std::fs::read(wav_path)?);
+
+    let timings = match lipsync_json {
+        Some(path) => read_word_timings(path)?,
+        None => Vec::new(),
+    };
+    let karaoke = build_karaoke(text, &timings);
+    let visemes: Vec<serde_json::Value> = viseme_track(&timings)
+        .into_iter()
+        .map(|v| json!({
+            "viseme": v.viseme,
+            "start": v.start,
+            "end": v.end,
+            "open": mouth_openness(&v.viseme),
+            "form": mouth_form(&v.viseme),
+        }))
+        .collect();
+
+    let data = json!({
+        "text": text,
+        "duration": samples.len() as f32 / sample_rate as f32,
+        "peaks": waveform_peaks(&samples, WAVEFORM_BUCKETS),
+        "words": karaoke.words,
+        "visemes": visemes,
+    });
+    // Keep `</script>` in user text from terminating the inline script
+    let data = serde_json::to_string(&data)?.replace("</", "<\\/");
+    let html = TEMPLATE.replacen("__AUDIO__", &audio_b64, 1).replacen("__DATA__", &data, 1);
+    std::fs::write(output_path, html)?;
+    Ok(())
+}
@@ -0,0 +1,125 @@
+//! Progress reports for front-ends: voice downloads, sentences synthesized, and samples through pitch
+//! shifting and time stretching, so a GUI can show a progress bar instead of freezing.
+//!
+//! Install a [`ProgressHandler`] with [`set_handler`] to hear about everything, or run a single call
+//! under one with [`with_handler`]:
+//!
+//! ```no_run
+//! use text_to_face::progress::{self, ProgressHandler};
+//! use std::sync::Arc;
+//!
+//! struct Bar;
+//!
+//! impl ProgressHandler for Bar {
+//!     fn sentences(&self, done: usize, total: usize) {
+//!         println!("{}/{} sentences", done, total);
+//!     }
+//! }
+//!
+//! progress::with_handler(Arc::new(Bar), || {
+//!     // text_to_face::synthesize_and_handle(...) and friends report to Bar here
+//! });
+//! ```
+//!
+//! Handlers are called on the thread doing the work, so they should hand the numbers to the UI rather
+//! than draw themselves.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How far a download has got, reported to [`ProgressHandler::download`]
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadProgress<'a> {
+    /// File being downloaded, e.g. `en_GB-alba-medium.onnx`
+    pub label: &'a str,
+    pub done: u64,
+    /// Size from the server, when it sent one
+    pub total: Option<u64>,
+    /// Set on the last call for a download
+    pub finished: bool,
+}
+
+/// Receives progress reports. Every method does nothing by default, so implement only the ones you show.
+pub trait ProgressHandler: Send + Sync {
+    /// A voice, lockfile, or dictionary download moved on; at most a few times a second
+    fn download(&self, _progress: &DownloadProgress) {}
+
+    /// `done` of the text's `total` sentences are synthesized. Called after each sentence.
+    fn sentences(&self, _done: usize, _total: usize) {}
+
+    /// `done` of `total` samples are through a pitch shift or time stretch; at most a few times a second,
+    /// and always once with `done == total`. Each pass starts again from 0.
+    fn samples(&self, _done: usize, _total: usize) {}
+}
+
+/// Minimum time between throttled reports
+const INTERVAL: Duration = Duration::from_millis(200);
+
+/// Where reports go; `None` (the default) reports nothing
+static HANDLER: Mutex<Option<Arc<dyn ProgressHandler>>> = Mutex::new(None);
+
+pub fn set_handler(handler: Option<Arc<dyn ProgressHandler>>) {
+    *HANDLER.lock().unwrap() = handler;
+}
+
+/// The installed handler
+pub fn handler() -> Option<Arc<dyn ProgressHandler>> {
+    HANDLER.lock().unwrap().clone()
+}
+
+/// Whether a handler is installed, for work that costs something to count
+pub fn is_reporting() -> bool {
+    HANDLER.lock().unwrap().is_some()
+}
+
+/// Run `work` with `handler` installed, then put back whichever handler was there before
+pub fn with_handler<T>(handler: Arc<dyn ProgressHandler>, work: impl FnOnce() -> T) -> T {
+    let previous = HANDLER.lock().unwrap().replace(handler);
+    let result = work();
+    set_handler(previous);
+    result
+}
+
+#[cfg(feature = "full")]
+pub(crate) fn download(progress: &DownloadProgress) {
+    if let Some(handler) = handler() {
+        handler.download(progress);
+    }
+}
+
+#[cfg(feature = "full")]
+pub(crate) fn sentences(done: usize, total: usize) {
+    if let Some(handler) = handler() {
+        handler.sentences(done, total);
+    }
+}
+
+/// Reports one pass over `total` samples, throttled
+pub(crate) struct SampleProgress {
+    total: usize,
+    reported: Option<Instant>,
+    handler: Option<Arc<dyn ProgressHandler>>,
+}
+
+impl SampleProgress {
+    pub(crate) fn new(total: usize) -> Self {
+        SampleProgress { total, reported: None, handler: handler() }
+    }
+
+    pub(crate) fn advance(&mut self, done: usize) {
+        let Some(handler) = &self.handler else {
+            return;
+        };
+        if self.reported.is_some_and(|t| t.elapsed() < INTERVAL) {
+            return;
+        }
+        handler.samples(done.min(self.total), self.total);
+        self.reported = Some(Instant::now());
+    }
+
+    pub(crate) fn finish(self) {
+        if let Some(handler) = &self.handler {
+            handler.samples(self.total, self.total);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+
+/// Default number of contour frames per second
+pub const DEFAULT_FRAME_RATE: f32 = 100.0;
+/// Analysis window for F0; long enough to hold two periods of the lowest pitch
+const F0_WINDOW_SECONDS: f32 = 0.04;
+/// Analysis window for energy
+const ENERGY_WINDOW_SECONDS: f32 = 0.025;
+const MIN_F0: f32 = 60.0;
+const MAX_F0: f32 = 500.0;
+/// Normalized autocorrelation a frame needs at its best lag to count as voiced
+const VOICING_THRESHOLD: f32 = 0.5;
+/// Frames quieter than this (RMS) are treated as silence
+const SILENCE_RMS: f32 = 0.01;
+
+/// Prosody at one point in time
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ProsodyFrame {
+    /// Seconds from the start of the audio
+    pub time: f32,
+    /// Fundamental frequency in Hz; `None` for unvoiced frames and silence
+    pub f0: Option<f32>,
+    /// RMS energy, normalized so the loudest frame is 1.0
+    pub energy: f32,
+}
+
+/// F0 and energy contours sampled at a fixed frame rate, for driving eyebrows, head nods, and other
+/// motion that follows the voice rather than the mouth shapes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProsodyContour {
+    pub frame_rate: f32,
+    pub frames: Vec<ProsodyFrame>,
+}
+
+fn frame_count(len: usize, sample_rate: u32, frame_rate: f32) -> usize {
+    (len as f32 / sample_rate as f32 * frame_rate).ceil() as usize
+}
+
+/// The `window`-sample slice centered on frame `index`, clipped to the audio
+fn window_at(samples: &[f32], index: usize, hop: f32, window: usize) -> &[f32] {
+    let center = (index as f32 * hop) as usize;
+    let start = center.saturating_sub(window / 2).min(samples.len());
+    let end = (start + window).min(samples.len());
+    &samples[start..end]
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+/// RMS energy per frame, normalized so the loudest frame is 1.0
+pub fn energy_contour(samples: &[f32], sample_rate: u32, frame_rate: f32) -> Vec<f32> {
+    let hop = sample_rate as f32 / frame_rate;
+    let window = (sample_rate as f32 * ENERGY_WINDOW_SECONDS) as usize;
+    let levels: Vec<f32> = (0..frame_count(samples.len(), sample_rate, frame_rate)).map(|i| rms(window_at(samples, i, hop, window))).collect();
+    let max = levels.iter().cloned().fold(0.0f32, f32::max);
+    if max <= 0.0 {
+        return levels;
+    }
+    levels.into_iter().map(|l| l / max).collect()
+}
+
+/// Normalized autocorrelation of a window at `lag`
+fn correlation(window: &[f32], lag: usize) -> f32 {
+    let (head, tail) = (&window[..window.len() - lag], &window[lag..]);
+    let cross: f32 = head.iter().zip(tail).map(|(a, b)| a * b).sum();
+    let energy = head.iter().map(|s| s * s).sum::<f32>() * tail.iter().map(|s| s * s).sum::<f32>();
+    if energy > 0.0 { cross / energy.sqrt() } else { 0.0 }
+}
+
+/// F0 of one window by normalized autocorrelation, refined between lags with a parabola
+fn window_f0(window: &[f32], sample_rate: u32) -> Option<f32> {
+    let min_lag = (sample_rate as f32 / MAX_F0) as usize;
+    let max_lag = ((sample_rate as f32 / MIN_F0) as usize).min(window.len() / 2);
+    if min_lag < 1 || max_lag <= min_lag + 1 || rms(window) < SILENCE_RMS {
+        return None;
+    }
+    let correlations: Vec<f32> = (min_lag..=max_lag).map(|lag| correlation(window, lag)).collect();
+    let best = correlations.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).map(|(i, _)| i)?;
+    if correlations[best] < VOICING_THRESHOLD {
+        return None;
+    }
+    // Prefer the shortest lag that is nearly as good, so a doubled period doesn't halve the pitch
+    let threshold = correlations[best] * 0.9;
+    let is_peak = |i: usize| correlations[i] >= threshold && (i == 0 || correlations[i - 1] <= correlations[i]) && correlations.get(i + 1).is_none_or(|next| *next <= correlations[i]);
+    let best = (0..correlations.len()).find(|i| is_peak(*i)).unwrap_or(best);
+    let offset = match (best.checked_sub(1).map(|i| correlations[i]), correlations.get(best + 1)) {
+        (Some(before), Some(after)) => {
+            let curvature = before - 2.0 * correlations[best] + after;
+            if curvature < 0.0 { 0.5 * (before - after) / curvature } else { 0.0 }
+        }
+        _ => 0.0,
+    };
+    Some(sample_rate as f32 / ((min_lag + best) as f32 + offset))
+}
+
+/// Fundamental frequency per frame; `None` where the audio is silent or unvoiced
+pub fn f0_contour(samples: &[f32], sample_rate: u32, frame_rate: f32) -> Vec<Option<f32>> {
+    let hop = sample_rate as f32 / frame_rate;
+    let window = (sample_rate as f32 * F0_WINDOW_SECONDS) as usize;
+    (0..frame_count(samples.len(), sample_rate, frame_rate)).map(|i| window_f0(window_at(samples, i, hop, window), sample_rate)).collect()
+}
+
+/// F0 and energy contours of mono audio
+pub fn prosody_contour(samples: &[f32], sample_rate: u32, frame_rate: f32) -> ProsodyContour {
+    let energy = energy_contour(samples, sample_rate, frame_rate);
+    let f0 = f0_contour(samples, sample_rate, frame_rate);
+    let frames = f0
+        .into_iter()
+        .zip(energy)
+        .enumerate()
+        .map(|(i, (f0, energy))| ProsodyFrame { time: i as f32 / frame_rate, f0, energy })
+        .collect();
+    ProsodyContour { frame_rate, frames }
+}
+
+/// Analyze a WAV file and add its contour to a lipsync JSON file under `prosody`
+pub fn add_prosody_to_json(wav_path: &str, json_path: &str, frame_rate: f32) -> Result<ProsodyContour, Box<dyn std::error::Error>> {
+    let (samples, sample_rate) = crate::read_wav(wav_path)?;
+    let contour = prosody_contour(&samples, sample_rate, frame_rate);
+    let mut value: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(json_path)?)?;
+    let object = value.as_object_mut().ok_or_else(|| format!("{} isn't a JSON object", json_path))?;
+    object.insert("prosody".to_string(), serde_json::to_value(&contour)?);
+    std::fs::write(json_path, serde_json::to_string_pretty(&value)?)?;
+    Ok(contour)
+}
@@ -0,0 +1,93 @@
+//! The `pitch_tts` Python module (build with the `python` feature, e.g. `maturin develop`): synthesis,
+//! pitch and tempo processing, and lipsync JSON for pipelines written in Python.
+//!
+//! Audio crosses as lists of floats (mono, -1.0 to 1.0) with their sample rate; lipsync comes back as
+//! the same JSON text the CLI writes, for `json.loads`. Synthesis and lipsync release the GIL while
+//! they run.
+
+use crate::PitchArg;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use std::str::FromStr;
+
+fn runtime_error(error: impl std::fmt::Display) -> PyErr {
+    PyRuntimeError::new_err(error.to_string())
+}
+
+/// A pitch as a factor (`1.2`) or a preset name (`"deep"`), as `--pitch` accepts
+#[derive(FromPyObject)]
+enum Pitch {
+    Factor(f32),
+    Preset(String),
+}
+
+impl Pitch {
+    fn factor(&self) -> PyResult<f32> {
+        match self {
+            Pitch::Factor(factor) => Ok(*factor),
+            Pitch::Preset(preset) => PitchArg::from_str(preset).map(|pitch| pitch.as_factor()).map_err(PyValueError::new_err),
+        }
+    }
+}
+
+/// synthesize(text, voice, pitch=1.0, tempo=1.0) -> (samples, sample_rate)
+///
+/// Synthesize `text` with `voice` (downloaded if it isn't installed), pitch shifted and tempo stretched.
+#[pyfunction]
+#[pyo3(signature = (text, voice, pitch = Pitch::Factor(1.0), tempo = 1.0))]
+fn synthesize(py: Python<'_>, text: &str, voice: &str, pitch: Pitch, tempo: f32) -> PyResult<(Vec<f32>, u32)> {
+    let pitch = PitchArg::Value(pitch.factor()?);
+    py.allow_threads(|| crate::synthesize_processed(text, voice, &pitch, tempo)).map_err(runtime_error)
+}
+
+/// pitch_shift(samples, sample_rate, pitch) -> samples
+///
+/// Shift the pitch without changing the length (0.5 = an octave down, 2.0 = an octave up).
+#[pyfunction]
+fn pitch_shift(py: Python<'_>, samples: Vec<f32>, sample_rate: usize, pitch: Pitch) -> PyResult<Vec<f32>> {
+    let factor = pitch.factor()?;
+    Ok(py.allow_threads(|| crate::true_pitch_shift(&samples, sample_rate, factor)))
+}
+
+/// time_stretch(samples, sample_rate, tempo) -> samples
+///
+/// Stretch to `tempo` times the length (2.0 = twice as slow, 0.5 = twice as fast). By default this
+/// resamples, so the pitch moves with the speed; with the high stretch quality (`--stretch-quality high`
+/// in the CLI) the pitch is kept.
+#[pyfunction]
+fn time_stretch(py: Python<'_>, samples: Vec<f32>, sample_rate: usize, tempo: f32) -> Vec<f32> {
+    py.allow_threads(|| crate::time_stretch(&samples, sample_rate, tempo))
+}
+
+/// lipsync_json(text, samples, sample_rate, aligned=True) -> str
+///
+/// Lipsync JSON (word timings with ARPAbet phonemes) for `text` spoken in `samples`: fitted to the
+/// audio with the built-in aligner, or estimated from the text with `aligned=False`. No WhisperX needed.
+#[pyfunction]
+#[pyo3(signature = (text, samples, sample_rate, aligned = true))]
+fn lipsync_json(py: Python<'_>, text: &str, samples: Vec<f32>, sample_rate: u32, aligned: bool) -> PyResult<String> {
+    py.allow_threads(|| {
+        let json = crate::lipsync_without_whisperx(text, &samples, sample_rate, aligned, None).map_err(|e| e.to_string())?;
+        serde_json::to_string_pretty(&json).map_err(|e| e.to_string())
+    })
+    .map_err(runtime_error)
+}
+
+/// write_wav(path, samples, sample_rate)
+///
+/// Write mono samples to a 16-bit WAV file.
+#[pyfunction]
+fn write_wav(path: &str, samples: Vec<f32>, sample_rate: u32) -> PyResult<()> {
+    crate::write_wav(path, &samples, sample_rate).map_err(runtime_error)
+}
+
+#[pymodule]
+fn pitch_tts(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add_function(wrap_pyfunction!(synthesize, m)?)?;
+    m.add_function(wrap_pyfunction!(pitch_shift, m)?)?;
+    m.add_function(wrap_pyfunction!(time_stretch, m)?)?;
+    m.add_function(wrap_pyfunction!(lipsync_json, m)?)?;
+    m.add_function(wrap_pyfunction!(write_wav, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// A named speaking rate, for people who'd rather not think in tempo factors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RatePreset {
+    Slow,
+    Normal,
+    Fast,
+    VeryFast,
+}
+
+impl FromStr for RatePreset {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "slow" => Ok(RatePreset::Slow),
+            "normal" => Ok(RatePreset::Normal),
+            "fast" => Ok(RatePreset::Fast),
+            "very-fast" | "veryfast" => Ok(RatePreset::VeryFast),
+            _ => Err(format!("Invalid rate preset: {} (expected slow, normal, fast, or very-fast)", s)),
+        }
+    }
+}
+
+/// Languages whose voices already speak quickly, as (language, extra slowdown): their `slow` is a
+/// little slower and their `fast` a little less fast, so the presets stay easy to follow
+const BRISK_LANGUAGES: &[(&str, f32)] = &[("es", 1.08), ("it", 1.06), ("fr", 1.05), ("ja", 1.08), ("pt", 1.05)];
+
+/// How a rate is made: the model's `length_scale` (phoneme durations, so speech still sounds natural)
+/// and a tempo factor for the time stretch on top (`> 1.0` slower, as for `--tempo`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateSettings {
+    pub length_scale: f32,
+    pub tempo: f32,
+}
+
+impl RateSettings {
+    pub const NORMAL: RateSettings = RateSettings { length_scale: 1.0, tempo: 1.0 };
+
+    /// `rate=length_scale=x0.85,tempo=x1.00`, for sidecars
+    pub fn describe(&self) -> String {
+        format!("rate=length_scale=x{:.2},tempo=x{:.2}", self.length_scale, self.tempo)
+    }
+}
+
+impl RatePreset {
+    /// The rate for a voice language (`en`, `es`, ...). The model's length scale does most of the work,
+    /// since it keeps speech natural; beyond about 20% faster it starts slurring, so `very-fast` adds a
+    /// time stretch instead of pushing it further.
+    pub fn settings(&self, language: Option<&str>) -> RateSettings {
+        let slowdown = language.and_then(|language| BRISK_LANGUAGES.iter().find(|(l, _)| *l == language)).map_or(1.0, |(_, s)| *s);
+        match self {
+            RatePreset::Slow => RateSettings { length_scale: 1.25 * slowdown, tempo: 1.0 },
+            RatePreset::Normal => RateSettings::NORMAL,
+            RatePreset::Fast => RateSettings { length_scale: 0.85 * slowdown, tempo: 1.0 },
+            RatePreset::VeryFast => RateSettings { length_scale: 0.8 * slowdown, tempo: 0.85 },
+        }
+    }
+}
+
+static RATE: Mutex<RateSettings> = Mutex::new(RateSettings::NORMAL);
+
+pub fn set_rate(rate: RateSettings) {
+    *RATE.lock().unwrap() = rate;
+}
+
+pub fn rate() -> RateSettings {
+    *RATE.lock().unwrap()
+}
+
+/// A `--tempo` factor with the current rate's time stretch applied
+pub fn rated_tempo(tempo: f32) -> f32 {
+    tempo * rate().tempo
+}
+
+#[cfg(feature = "full")]
+/// Scale a freshly loaded voice's `length_scale` by the current [`rate`]. Runs after
+/// [`crate::takes::apply_variation`], so a take's variation and the rate combine.
+pub fn apply_rate(synth: &piper_rs::synth::PiperSpeechSynthesizer) -> Result<(), Box<dyn std::error::Error>> {
+    let rate = rate();
+    if rate.length_scale == 1.0 {
+        return Ok(());
+    }
+    let model = synth.clone_model();
+    let current = model.get_fallback_synthesis_config()?;
+    let Some(current) = current.downcast_ref::<piper_rs::PiperSynthesisConfig>() else {
+        return Err("Voice doesn't support synthesis settings".into());
+    };
+    model.set_fallback_synthesis_config(&piper_rs::PiperSynthesisConfig { length_scale: current.length_scale * rate.length_scale, ..current.clone() })?;
+    Ok(())
+}
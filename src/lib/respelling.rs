@@ -0,0 +1,300 @@
+use crate::visemes::ARPABET_PHONEMES;
+
+/// A word written with an inline pronunciation respelling, e.g. `Hermione{her-MY-oh-nee}`.
+///
+/// Syllables are separated by hyphens (or spaces), and the stressed syllable is written in capitals,
+/// following the usual dictionary respelling key: `ay` as in *day*, `ee` as in *see*, `y`/`eye` as in
+/// *my*, `oh` as in *go*, `oo` as in *too*, `ow` as in *now*, `ah` as in *father*, `aw` as in *law*,
+/// `er` as in *her*, `uh` as in *cup*, and `sh`, `ch`, `th`, `dh`, `zh`, `ng` for consonants.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Respelling {
+    pub word: String,
+    pub respelling: String,
+}
+
+/// A piece of text: plain text, or a respelled word
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Text(String),
+    Respelled(Respelling),
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '\'' || c == '’'
+}
+
+/// Split text into plain text and respelled words. Braces that don't follow a word or aren't closed are left as text.
+pub fn split_respellings(text: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+    let mut pending = String::new();
+    while let Some(open) = rest.find('{') {
+        let word_start = rest[..open].char_indices().rev().take_while(|(_, c)| is_word_char(*c)).last().map(|(i, _)| i);
+        let close = rest[open..].find('}').map(|i| open + i);
+        match (word_start, close) {
+            (Some(start), Some(close)) if !rest[open + 1..close].contains('{') => {
+                pending.push_str(&rest[..start]);
+                if !pending.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut pending)));
+                }
+                segments.push(Segment::Respelled(Respelling { word: rest[start..open].to_string(), respelling: rest[open + 1..close].trim().to_string() }));
+                rest = &rest[close + 1..];
+            }
+            _ => {
+                pending.push_str(&rest[..=open]);
+                rest = &rest[open + 1..];
+            }
+        }
+    }
+    pending.push_str(rest);
+    if !pending.is_empty() {
+        segments.push(Segment::Text(pending));
+    }
+    segments
+}
+
+pub fn has_respellings(text: &str) -> bool {
+    split_respellings(text).iter().any(|s| matches!(s, Segment::Respelled(_)))
+}
+
+/// The text as it should be displayed and transcribed: `Hermione{her-MY-oh-nee}` becomes `Hermione`
+pub fn strip_respellings(text: &str) -> String {
+    split_respellings(text)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => text,
+            Segment::Respelled(respelled) => respelled.word,
+        })
+        .collect()
+}
+
+/// The respelling in one whitespace-separated word, which may carry punctuation (`Hermione{her-MY-oh-nee},`)
+pub fn parse_word(word: &str) -> Option<Respelling> {
+    split_respellings(word).into_iter().find_map(|segment| match segment {
+        Segment::Respelled(respelled) => Some(respelled),
+        Segment::Text(_) => None,
+    })
+}
+
+/// Respelling spellings and their phonemes, longest first so `igh` wins over `i`
+const VOWELS: &[(&str, &[&str])] = &[
+    ("eye", &["AY"]),
+    ("igh", &["AY"]),
+    ("ay", &["EY"]),
+    ("ai", &["EY"]),
+    ("ee", &["IY"]),
+    ("ea", &["IY"]),
+    ("oh", &["OW"]),
+    ("oa", &["OW"]),
+    ("oo", &["UW"]),
+    ("ew", &["Y", "UW"]),
+    ("ow", &["AW"]),
+    ("ou", &["AW"]),
+    ("oy", &["OY"]),
+    ("oi", &["OY"]),
+    ("ah", &["AA"]),
+    ("aw", &["AO"]),
+    ("au", &["AO"]),
+    ("er", &["ER"]),
+    ("ur", &["ER"]),
+    ("ir", &["ER"]),
+    ("uh", &["AH"]),
+    ("ih", &["IH"]),
+    ("eh", &["EH"]),
+    ("uu", &["UH"]),
+    ("a", &["AE"]),
+    ("e", &["EH"]),
+    ("i", &["IH"]),
+    ("o", &["AA"]),
+    ("u", &["AH"]),
+];
+
+const CONSONANTS: &[(&str, &[&str])] = &[
+    ("tch", &["CH"]),
+    ("ch", &["CH"]),
+    ("sh", &["SH"]),
+    ("th", &["TH"]),
+    ("dh", &["DH"]),
+    ("zh", &["ZH"]),
+    ("ng", &["NG"]),
+    ("nk", &["NG", "K"]),
+    ("kh", &["K"]),
+    ("ph", &["F"]),
+    ("wh", &["W"]),
+    ("ck", &["K"]),
+    ("b", &["B"]),
+    ("c", &["K"]),
+    ("d", &["D"]),
+    ("f", &["F"]),
+    ("g", &["G"]),
+    ("h", &["HH"]),
+    ("j", &["JH"]),
+    ("k", &["K"]),
+    ("l", &["L"]),
+    ("m", &["M"]),
+    ("n", &["N"]),
+    ("p", &["P"]),
+    ("q", &["K"]),
+    ("r", &["R"]),
+    ("s", &["S"]),
+    ("t", &["T"]),
+    ("v", &["V"]),
+    ("w", &["W"]),
+    ("x", &["K", "S"]),
+    ("z", &["Z"]),
+];
+
+fn is_vowel(phoneme: &str) -> bool {
+    phoneme.starts_with(['A', 'E', 'I', 'O', 'U'])
+}
+
+/// Phonemes of one syllable, with vowels marked for `stress`
+fn syllable_to_arpabet(syllable: &str, stress: u8) -> Result<Vec<String>, String> {
+    let letters: String = syllable.to_lowercase().chars().filter(|c| *c != '\'' && *c != '’').collect();
+    let mut phonemes: Vec<String> = Vec::new();
+    let mut rest = letters.as_str();
+    while !rest.is_empty() {
+        let matched = VOWELS.iter().chain(CONSONANTS).find(|(spelling, _)| rest.starts_with(spelling));
+        let (length, sounds): (usize, &[&str]) = match matched {
+            Some((spelling, sounds)) => (spelling.len(), sounds),
+            // `y` is a consonant before a vowel (`yoo`) and the vowel of *my* otherwise
+            None if rest.starts_with('y') => (1, if rest[1..].starts_with(['a', 'e', 'i', 'o', 'u']) { &["Y"] } else { &["AY"] }),
+            None => return Err(format!("can't read '{}' in respelling syllable '{}'", rest.chars().next().unwrap_or_default(), syllable)),
+        };
+        for sound in sounds {
+            // Doubled letters (`ss`, `ll`) are one consonant
+            if !is_vowel(sound) && phonemes.last().is_some_and(|last| last == sound) {
+                continue;
+            }
+            phonemes.push(if is_vowel(sound) { format!("{}{}", sound, stress) } else { sound.to_string() });
+        }
+        rest = &rest[length..];
+    }
+    if !phonemes.iter().any(|p| is_vowel(p)) {
+        return Err(format!("respelling syllable '{}' has no vowel", syllable));
+    }
+    Ok(phonemes)
+}
+
+/// ARPAbet phonemes for a respelling like `her-MY-oh-nee`. Capitalized syllables get primary stress;
+/// a single syllable is always stressed.
+pub fn respelling_to_arpabet(respelling: &str) -> Result<Vec<String>, String> {
+    let syllables: Vec<&str> = respelling.split(['-', ' ']).filter(|s| !s.is_empty()).collect();
+    if syllables.is_empty() {
+        return Err("empty respelling".to_string());
+    }
+    let mut phonemes = Vec::new();
+    for syllable in &syllables {
+        let stressed = syllables.len() == 1 || (syllable.chars().any(char::is_alphabetic) && !syllable.chars().any(char::is_lowercase));
+        phonemes.extend(syllable_to_arpabet(syllable, if stressed { 1 } else { 0 })?);
+    }
+    debug_assert!(phonemes.iter().all(|p| ARPABET_PHONEMES.contains(&p.trim_end_matches(|c: char| c.is_ascii_digit()))));
+    Ok(phonemes)
+}
+
+/// IPA for one ARPAbet phoneme, as espeak writes it for English voices
+fn phoneme_to_ipa(phoneme: &str, stressed: bool) -> &'static str {
+    match phoneme {
+        "AA" => "ɑː",
+        "AE" => "æ",
+        "AH" if stressed => "ʌ",
+        "AH" => "ə",
+        "AO" => "ɔː",
+        "AW" => "aʊ",
+        "AY" => "aɪ",
+        "EH" => "ɛ",
+        "ER" if stressed => "ɜː",
+        "ER" => "ɚ",
+        "EY" => "eɪ",
+        "IH" => "ɪ",
+        "IY" => "iː",
+        "OW" => "oʊ",
+        "OY" => "ɔɪ",
+        "UH" => "ʊ",
+        "UW" => "uː",
+        "B" => "b",
+        "CH" => "tʃ",
+        "D" => "d",
+        "DH" => "ð",
+        "F" => "f",
+        "G" => "ɡ",
+        "HH" => "h",
+        "JH" => "dʒ",
+        "K" => "k",
+        "L" => "l",
+        "M" => "m",
+        "N" => "n",
+        "NG" => "ŋ",
+        "P" => "p",
+        "R" => "ɹ",
+        "S" => "s",
+        "SH" => "ʃ",
+        "T" => "t",
+        "TH" => "θ",
+        "V" => "v",
+        "W" => "w",
+        "Y" => "j",
+        "Z" => "z",
+        "ZH" => "ʒ",
+        _ => "",
+    }
+}
+
+/// IPA for ARPAbet phonemes, with espeak's stress marks before stressed vowels
+pub fn arpabet_to_ipa(phonemes: &[String]) -> String {
+    let mut ipa = String::new();
+    for phoneme in phonemes {
+        let (base, stress) = phoneme.split_at(phoneme.trim_end_matches(|c: char| c.is_ascii_digit()).len());
+        match stress {
+            "1" => ipa.push('ˈ'),
+            "2" => ipa.push('ˌ'),
+            _ => {}
+        }
+        ipa.push_str(phoneme_to_ipa(base, stress == "1" || stress == "2"));
+    }
+    ipa
+}
+
+#[cfg(feature = "full")]
+/// Phonemes for each sentence of `text` as `synth` will speak it: respelled words use their respelling,
+/// and the text between them is preprocessed and phonemized by the voice as usual. Respellings that can't
+/// be read are reported and the word is read normally.
+pub fn phonemize_with_respellings(synth: &piper_rs::synth::PiperSpeechSynthesizer, text: &str, voice: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let model = synth.clone_model();
+    let mut sentences: Vec<String> = Vec::new();
+    // Whether the last sentence is still open, i.e. the next piece continues it
+    let mut open = false;
+    let add = |sentences: &mut Vec<String>, phonemes: String, continues: bool| match sentences.last_mut() {
+        Some(last) if continues => {
+            last.push(' ');
+            last.push_str(&phonemes);
+        }
+        _ => sentences.push(phonemes),
+    };
+    for segment in split_respellings(text) {
+        let piece = match segment {
+            Segment::Respelled(respelled) => match respelling_to_arpabet(&respelled.respelling) {
+                Ok(phonemes) => {
+                    add(&mut sentences, arpabet_to_ipa(&phonemes), open);
+                    open = true;
+                    continue;
+                }
+                Err(reason) => {
+                    let word = respelled.word.clone();
+                    crate::warnings::warn(crate::warnings::Warning::UnreadableRespelling { word: respelled.word, respelling: respelled.respelling, reason });
+                    word
+                }
+            },
+            Segment::Text(text) => text,
+        };
+        let prepared = crate::normalize::normalize_for_voice(&crate::rules::apply_rules(&piece), voice);
+        if prepared.trim().is_empty() {
+            continue;
+        }
+        for (i, phonemes) in model.phonemize_text(&prepared)?.to_vec().into_iter().enumerate() {
+            add(&mut sentences, phonemes, i == 0 && open);
+        }
+        open = !prepared.trim_end().ends_with(['.', '!', '?', ';', ':']);
+    }
+    Ok(sentences)
+}
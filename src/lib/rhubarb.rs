@@ -0,0 +1,124 @@
+//! Lipsync in the output formats of [Rhubarb Lip Sync](https://github.com/DanielSWolf/rhubarb-lip-sync),
+//! which many game and animation pipelines already read: mouth shapes A–F, the extended shapes G, H,
+//! and X, as TSV or JSON mouth cues.
+
+use crate::WordTiming;
+use serde::Serialize;
+use std::fmt::Write as _;
+
+/// Rhubarb's rest shape, for silence between words
+pub const REST: char = 'X';
+
+/// A Rhubarb mouth shape held over a time range
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MouthCue {
+    pub start: f32,
+    pub end: f32,
+    pub value: char,
+}
+
+/// Map an ARPAbet phoneme (with or without stress marker) onto a Rhubarb mouth shape:
+///
+/// - `A` closed (P, B, M)
+/// - `B` slightly open, teeth together (most consonants, EE)
+/// - `C` open (EH, AE, AH)
+/// - `D` wide open (AA)
+/// - `E` slightly rounded (AO, ER)
+/// - `F` puckered (UW, OW, W)
+/// - `G` teeth on lower lip (F, V)
+/// - `H` tongue raised (L)
+pub fn arpabet_to_rhubarb(phoneme: &str) -> char {
+    let base = phoneme.trim_end_matches(|c: char| c.is_ascii_digit());
+    match base {
+        "P" | "B" | "M" => 'A',
+        "AA" | "AW" | "AY" => 'D',
+        "AE" | "AH" | "EH" | "EY" | "HH" => 'C',
+        "AO" | "ER" | "OY" | "UH" | "R" => 'E',
+        "UW" | "OW" | "W" => 'F',
+        "F" | "V" => 'G',
+        "L" => 'H',
+        "IH" | "IY" | "Y" | "CH" | "JH" | "SH" | "ZH" | "D" | "DH" | "T" | "TH" | "N" | "NG" | "S" | "Z" | "K" | "G" => 'B',
+        _ => REST,
+    }
+}
+
+/// Build Rhubarb mouth cues from aligned words, covering `0..duration`.
+/// Each word's ARPAbet phonemes share the word's duration evenly, as in [`crate::visemes::viseme_track`];
+/// gaps between words rest on `X`, and words without phoneme data get the open `C`.
+pub fn mouth_cues(words: &[WordTiming], duration: f32) -> Vec<MouthCue> {
+    let mut cues: Vec<MouthCue> = Vec::new();
+    let push = |value: char, start: f32, end: f32, cues: &mut Vec<MouthCue>| {
+        if end <= start {
+            return;
+        }
+        // Merge with the previous cue when the shape doesn't change
+        if let Some(last) = cues.last_mut()
+            && last.value == value
+            && (last.end - start).abs() < 1e-4
+        {
+            last.end = end;
+            return;
+        }
+        cues.push(MouthCue { start, end, value });
+    };
+
+    let mut cursor = 0.0f32;
+    for word in words {
+        if word.start > cursor {
+            push(REST, cursor, word.start, &mut cues);
+        }
+        if word.phonemes.is_empty() {
+            push('C', word.start, word.end, &mut cues);
+        } else {
+            let step = (word.end - word.start) / word.phonemes.len() as f32;
+            for (i, phoneme) in word.phonemes.iter().enumerate() {
+                let start = word.start + step * i as f32;
+                push(arpabet_to_rhubarb(phoneme), start, start + step, &mut cues);
+            }
+        }
+        cursor = cursor.max(word.end);
+    }
+    if duration > cursor {
+        push(REST, cursor, duration, &mut cues);
+    }
+    cues
+}
+
+/// Centiseconds, as Rhubarb writes them; rounded as f64 so the JSON shows `1.2`, not `1.2000000476837158`
+fn round2(v: f32) -> f64 {
+    (v as f64 * 100.0).round() / 100.0
+}
+
+/// Rhubarb's TSV output (`-f tsv`): the start of each cue and its shape, ending at rest
+pub fn rhubarb_tsv(cues: &[MouthCue]) -> String {
+    let mut tsv = String::new();
+    for cue in cues {
+        let _ = writeln!(tsv, "{:.2}\t{}", cue.start, cue.value);
+    }
+    if let Some(last) = cues.last().filter(|cue| cue.value != REST) {
+        let _ = writeln!(tsv, "{:.2}\t{}", last.end, REST);
+    }
+    tsv
+}
+
+/// Rhubarb's JSON output (`-f json`): `metadata` with the sound file and duration, and `mouthCues`
+pub fn rhubarb_json(cues: &[MouthCue], sound_file: &str, duration: f32) -> serde_json::Value {
+    let cues: Vec<serde_json::Value> = cues
+        .iter()
+        .map(|cue| serde_json::json!({ "start": round2(cue.start), "end": round2(cue.end), "value": cue.value.to_string() }))
+        .collect();
+    serde_json::json!({
+        "metadata": { "soundFile": sound_file, "duration": round2(duration) },
+        "mouthCues": cues,
+    })
+}
+
+/// Write Rhubarb cues to `path`: JSON for a `.json` extension, TSV otherwise
+pub fn write_rhubarb(path: &str, cues: &[MouthCue], sound_file: &str, duration: f32) -> Result<(), Box<dyn std::error::Error>> {
+    if path.to_lowercase().ends_with(".json") {
+        std::fs::write(path, serde_json::to_string_pretty(&rhubarb_json(cues, sound_file, duration))?)?;
+    } else {
+        std::fs::write(path, rhubarb_tsv(cues))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use regex::{Regex, RegexBuilder};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// One `pattern => replacement` rewrite
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub pattern: Regex,
+    pub replacement: String,
+}
+
+/// Text preprocessing rules, applied in order to every text before synthesis.
+///
+/// Rules files have one rule per line, `pattern => replacement`, where the pattern is a regular
+/// expression and the replacement may use `$1`/`${name}` capture groups. Blank lines and lines
+/// starting with `#` are ignored. Prefix a pattern with `(?i)` for case-insensitive matching.
+///
+/// ```text
+/// # Ticker symbols
+/// \$([A-Z]{2,5})\b => ticker $1
+/// \bk8s\b => kubernetes
+/// (?i)\bgonna\b => going to
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TextRules {
+    pub rules: Vec<Rule>,
+}
+
+impl TextRules {
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (pattern, replacement) = line
+                .split_once(" => ")
+                .ok_or_else(|| format!("line {}: expected `pattern => replacement`", number + 1))?;
+            let pattern = RegexBuilder::new(pattern.trim())
+                .build()
+                .map_err(|e| format!("line {}: invalid pattern: {}", number + 1, e))?;
+            rules.push(Rule { pattern, replacement: replacement.trim().to_string() });
+        }
+        Ok(TextRules { rules })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(Self::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))?)
+    }
+
+    /// Apply every rule in order
+    pub fn apply(&self, text: &str) -> String {
+        self.rules.iter().fold(text.to_string(), |text, rule| {
+            rule.pattern.replace_all(&text, rule.replacement.as_str()).into_owned()
+        })
+    }
+}
+
+/// Rules used for all synthesis, set from `--rules` or the default rules file
+static RULES: Mutex<Option<TextRules>> = Mutex::new(None);
+
+pub fn set_rules(rules: Option<TextRules>) {
+    *RULES.lock().unwrap() = rules;
+}
+
+/// Apply the active rules, if any
+pub fn apply_rules(text: &str) -> String {
+    match RULES.lock().unwrap().as_ref() {
+        Some(rules) => rules.apply(text),
+        None => text.to_string(),
+    }
+}
+
+/// Short hash of the active rules for sidecars, so editing the rules invalidates up-to-date checks
+pub fn rules_fingerprint() -> Option<String> {
+    use sha2::{Digest, Sha256};
+    let rules = RULES.lock().unwrap();
+    let rules = rules.as_ref().filter(|r| !r.rules.is_empty())?;
+    let mut hasher = Sha256::new();
+    for rule in &rules.rules {
+        hasher.update(rule.pattern.as_str().as_bytes());
+        hasher.update([0u8]);
+        hasher.update(rule.replacement.as_bytes());
+        hasher.update([0u8]);
+    }
+    Some(hasher.finalize().iter().take(6).map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Rules file picked up automatically when `--rules` isn't given
+pub fn default_rules_path() -> PathBuf {
+    crate::get_app_data_dir().join("rules.txt")
+}
+
+/// Explicit rules file given to [`load_rules`], remembered for [`reload_rules`]
+static RULES_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Load and activate the rules from `path`, or from the default rules file if it exists.
+/// Returns the number of rules now active.
+pub fn load_rules(path: Option<&Path>) -> Result<usize, Box<dyn std::error::Error>> {
+    *RULES_PATH.lock().unwrap() = path.map(Path::to_path_buf);
+    let explicit = path.is_some();
+    let path = path.map(Path::to_path_buf).unwrap_or_else(default_rules_path);
+    // A missing default file just means no rules; a missing explicit one is an error
+    let rules = if explicit || path.exists() { Some(TextRules::from_file(&path)?) } else { None };
+    let count = rules.as_ref().map(|r| r.rules.len()).unwrap_or(0);
+    set_rules(rules);
+    Ok(count)
+}
+
+/// Re-read the rules file last passed to [`load_rules`] (or the default one), so long-running
+/// processes pick up edits without a restart. On error the current rules stay active.
+pub fn reload_rules() -> Result<usize, Box<dyn std::error::Error>> {
+    let path = RULES_PATH.lock().unwrap().clone();
+    load_rules(path.as_deref())
+}
@@ -0,0 +1,91 @@
+//! Minimal runtime for shipping voices inside a game or other application.
+//!
+//! Voices are loaded only from a directory you provide (`<id>.onnx` next to `<id>.onnx.json`, with
+//! optional config overrides in `overrides/<id>.json`); there is no voice catalog, no downloading, and no WhisperX alignment. Build with
+//! `default-features = false, features = ["runtime"]` for the smallest dependency footprint.
+
+use crate::speakers::{config_speakers, select_speaker, SpeakerSelection};
+use crate::{config_sample_rate, time_stretch, true_pitch_shift, DEFAULT_SAMPLE_RATE};
+use piper_rs::synth::PiperSpeechSynthesizer;
+use std::path::{Path, PathBuf};
+
+/// Bundled voices in a directory
+pub struct VoiceRuntime {
+    voices_dir: PathBuf,
+}
+
+/// A loaded voice, ready to synthesize
+pub struct RuntimeVoice {
+    pub id: String,
+    pub sample_rate: u32,
+    /// `(id, name)` of each speaker of a multi-speaker voice; empty for single-speaker voices
+    pub speakers: Vec<(i64, String)>,
+    synth: PiperSpeechSynthesizer,
+}
+
+impl VoiceRuntime {
+    pub fn new(voices_dir: impl AsRef<Path>) -> Self {
+        Self { voices_dir: voices_dir.as_ref().to_path_buf() }
+    }
+
+    /// IDs of the voices bundled in the directory (those with both model and config files)
+    pub fn voices(&self) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(&self.voices_dir) else {
+            return Vec::new();
+        };
+        let mut ids: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().to_str()?.strip_suffix(".onnx.json").map(str::to_string))
+            .filter(|id| self.voices_dir.join(format!("{}.onnx", id)).exists())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Load a bundled voice by ID
+    pub fn load(&self, voice_id: &str) -> Result<RuntimeVoice, Box<dyn std::error::Error>> {
+        let config_path = self.voices_dir.join(format!("{}.onnx.json", voice_id));
+        if !config_path.exists() || !self.voices_dir.join(format!("{}.onnx", voice_id)).exists() {
+            return Err(format!("Voice '{}' not found in {}", voice_id, self.voices_dir.display()).into());
+        }
+        let overlay = self.voices_dir.join("overrides").join(format!("{}.json", voice_id));
+        let config_path = if overlay.exists() { crate::overrides::overlaid_config(&config_path, &overlay)? } else { config_path };
+        let config: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&config_path)?)?;
+        let sample_rate = config_sample_rate(&config).unwrap_or(DEFAULT_SAMPLE_RATE);
+        let model = piper_rs::from_config_path(&config_path)?;
+        Ok(RuntimeVoice {
+            id: voice_id.to_string(),
+            sample_rate,
+            speakers: config_speakers(&config),
+            synth: PiperSpeechSynthesizer::new(model)?,
+        })
+    }
+}
+
+impl RuntimeVoice {
+    /// Speak as one of [`RuntimeVoice::speakers`] from now on, by ID or name; returns the speaker's ID
+    pub fn set_speaker(&self, selection: &SpeakerSelection) -> Result<i64, Box<dyn std::error::Error>> {
+        select_speaker(&self.synth, &self.speakers, selection).map_err(|e| format!("Speaker {} of {}: {}", selection, self.id, e).into())
+    }
+
+    /// Synthesize mono samples at [`RuntimeVoice::sample_rate`], within the configured [`crate::limits::limits`]
+    pub fn synthesize(&self, text: &str) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+        let limits = crate::limits::limits();
+        limits.check_text(text)?;
+        let mut samples: Vec<f32> = Vec::new();
+        for result in self.synth.synthesize_parallel(text.to_string(), None)? {
+            samples.append(&mut result?.into_vec());
+            limits.check_samples(samples.len(), self.sample_rate)?;
+        }
+        Ok(samples)
+    }
+
+    /// Synthesize with a pitch factor (0.5 = octave down, 2.0 = octave up) and tempo factor (2.0 = twice as slow)
+    pub fn synthesize_with(&self, text: &str, pitch: f32, tempo: f32) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+        let samples = self.synthesize(text)?;
+        let shifted = true_pitch_shift(&samples, self.sample_rate as usize, pitch);
+        let stretched = time_stretch(&shifted, self.sample_rate as usize, tempo);
+        crate::limits::limits().check_samples(stretched.len(), self.sample_rate)?;
+        Ok(stretched)
+    }
+}
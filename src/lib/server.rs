@@ -0,0 +1,414 @@
+//! HTTP API for game engines and web apps: synthesis, voice listing, and lipsync without spawning the
+//! CLI per request.
+//!
+//! - `GET /voices`: the voice catalog with capabilities, as `list --json` prints it
+//! - `POST /synthesize`: audio in the requested format (WAV by default); with `?stream=ndjson`, the
+//!   audio and its word and viseme timings as newline-delimited frames (see [`crate::lipsync_stream`]),
+//!   sent chunked so a client can act on each frame as it arrives
+//! - `POST /lipsync`: lipsync JSON, optionally with the audio as base64 WAV
+//! - `POST /reload`: re-read the rules, lexicons, and voice manifest, and drop the loaded voice models,
+//!   so edits and re-downloaded voices take effect without a restart
+//!
+//! Requests are handled one at a time: synthesis already keeps the CPU busy, and warnings are
+//! collected process-wide.
+
+use crate::lipsync_stream::{self, DEFAULT_CHUNK_SECONDS};
+use crate::encode::{self, AudioFormat, EncodeSettings, DEFAULT_OPUS_FRAME_MS, DEFAULT_QUALITY};
+use crate::speakers::{self, SpeakerSelection};
+use crate::tags::AudioTags;
+use crate::{directives, limits, emphasis, pause_markers, get_available_voices, ssml, synthesize_and_handle, synthesize_processed, synthesize_ssml, voice_capabilities, warnings, LipsyncLevel, PitchArg, Voice, VoiceCapabilities};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Largest request body read, so a runaway client can't exhaust memory before the text limits apply
+const MAX_BODY_BYTES: u64 = 1_000_000;
+
+/// How the server listens and what it assumes when a request leaves something out
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    /// Address to listen on, e.g. `127.0.0.1:8080`
+    pub bind: String,
+    /// Voice for requests that don't name one
+    pub default_voice: String,
+    /// `Access-Control-Allow-Origin` sent with every response, for browser clients on another origin
+    pub allow_origin: Option<String>,
+}
+
+/// Body of `POST /synthesize` and `POST /lipsync`
+#[derive(Debug, Deserialize)]
+struct SynthesisRequest {
+    /// Text to speak; `/synthesize` also takes an SSML document
+    text: String,
+    voice: Option<String>,
+    /// Speaker of a multi-speaker voice, by ID or name
+    speaker: Option<serde_json::Value>,
+    /// Pitch factor or preset name, e.g. `1.2` or `"deep"`
+    pitch: Option<serde_json::Value>,
+    tempo: Option<f32>,
+    /// `wav`, `flac`, `mp3`, `ogg`, or `opus` (`/synthesize` only)
+    format: Option<String>,
+    bitrate: Option<u32>,
+    quality: Option<f32>,
+    /// Opus frame duration in milliseconds
+    frame_size: Option<f32>,
+    /// `estimate` (default), `align`, or `high` (`/lipsync` only)
+    lipsync: Option<String>,
+    /// Add the audio to the lipsync response as base64 WAV (`/lipsync` only)
+    #[serde(default)]
+    include_audio: bool,
+}
+
+/// A voice in `GET /voices`
+#[derive(Serialize)]
+struct VoiceListing {
+    #[serde(flatten)]
+    voice: Voice,
+    #[serde(flatten)]
+    capabilities: VoiceCapabilities,
+}
+
+/// A failed request: the HTTP status and a message for the `error` field
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: impl ToString) -> Self {
+        ApiError { status: 400, message: message.to_string() }
+    }
+}
+
+impl From<crate::Error> for ApiError {
+    fn from(error: crate::Error) -> Self {
+        let status = match error {
+            // Over-long requests are the client's to fix
+            _ if error.limit().is_some() => 413,
+            crate::Error::VoiceNotFound(_) => 404,
+            _ => 500,
+        };
+        ApiError { status, message: error.to_string() }
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for ApiError {
+    fn from(error: Box<dyn std::error::Error>) -> Self {
+        crate::Error::from(error).into()
+    }
+}
+
+/// A successful response body with its content type
+struct Reply {
+    body: Vec<u8>,
+    content_type: &'static str,
+    /// Send with chunked transfer encoding instead of a `Content-Length`
+    chunked: bool,
+}
+
+impl Reply {
+    fn new(body: Vec<u8>, content_type: &'static str) -> Self {
+        Reply { body, content_type, chunked: false }
+    }
+
+    fn json(value: &impl Serialize) -> Self {
+        Reply::new(serde_json::to_vec_pretty(value).unwrap_or_default(), "application/json")
+    }
+}
+
+/// Scratch file for one request's intermediate audio or JSON
+fn temp_path(extension: &str) -> PathBuf {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    std::env::temp_dir().join(format!("text_to_face_serve_{}_{}.{}", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed), extension))
+}
+
+/// Mono 16-bit WAV file contents
+fn wav_bytes(samples: &[f32], sample_rate: u32) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let spec = hound::WavSpec { channels: 1, sample_rate, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+    let mut cursor = std::io::Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut cursor, spec)?;
+    for sample in samples {
+        writer.write_sample((*sample * 32767.0).clamp(-32768.0, 32767.0) as i16)?;
+    }
+    writer.finalize()?;
+    Ok(cursor.into_inner())
+}
+
+/// Only catalog and local Piper voices are served; the mock voice and other registered backends are
+/// for code embedding the crate
+fn is_known_voice(voice: &str) -> bool {
+    get_available_voices().iter().any(|v| v.id == voice)
+}
+
+impl SynthesisRequest {
+    fn voice<'a>(&'a self, options: &'a ServerOptions) -> Result<&'a str, ApiError> {
+        let voice = self.voice.as_deref().unwrap_or(&options.default_voice);
+        if !is_known_voice(voice) {
+            return Err(ApiError { status: 404, message: format!("Unknown voice '{}'", voice) });
+        }
+        Ok(voice)
+    }
+
+    fn speaker(&self) -> Result<Option<SpeakerSelection>, ApiError> {
+        match &self.speaker {
+            None => Ok(None),
+            Some(serde_json::Value::Number(n)) => n.as_i64().map(|id| Some(SpeakerSelection::Id(id))).ok_or_else(|| ApiError::bad_request(format!("Invalid speaker {}", n))),
+            Some(serde_json::Value::String(s)) => SpeakerSelection::from_str(s).map(Some).map_err(ApiError::bad_request),
+            Some(other) => Err(ApiError::bad_request(format!("Invalid speaker {}", other))),
+        }
+    }
+
+    /// Run `synthesize` with the request's speaker selected, if it names one, instead of `--speaker-id`
+    fn with_speaker<T>(&self, voice: &str, synthesize: impl FnOnce() -> Result<T, ApiError>) -> Result<T, ApiError> {
+        let Some(selection) = self.speaker()? else {
+            return synthesize();
+        };
+        let available = speakers::voice_speakers(voice)?;
+        speakers::resolve_speaker(&available, &selection).map_err(|e| ApiError::bad_request(format!("Speaker {} of {}: {}", selection, voice, e)))?;
+        let previous = speakers::speaker();
+        speakers::set_speaker(Some(selection));
+        let result = synthesize();
+        speakers::set_speaker(previous);
+        result
+    }
+
+    fn pitch(&self) -> Result<PitchArg, ApiError> {
+        let pitch = match &self.pitch {
+            None => PitchArg::Value(1.0),
+            Some(serde_json::Value::Number(n)) => PitchArg::Value(n.as_f64().unwrap_or(f64::NAN) as f32),
+            Some(serde_json::Value::String(s)) => PitchArg::from_str(s).map_err(ApiError::bad_request)?,
+            Some(other) => return Err(ApiError::bad_request(format!("Invalid pitch {}", other))),
+        };
+        limits::check_pitch(pitch.as_factor()).map_err(ApiError::bad_request)?;
+        Ok(pitch)
+    }
+
+    fn tempo(&self) -> Result<f32, ApiError> {
+        let tempo = self.tempo.unwrap_or(1.0);
+        limits::check_tempo(tempo).map_err(ApiError::bad_request)?;
+        Ok(tempo)
+    }
+
+    fn encoding(&self) -> Result<EncodeSettings, ApiError> {
+        let format = match &self.format {
+            Some(name) => AudioFormat::from_extension(name).ok_or_else(|| ApiError::bad_request(format!("Unknown format '{}' (use wav, flac, mp3, ogg, or opus)", name)))?,
+            None => AudioFormat::Wav,
+        };
+        let opus_frame_ms = match self.frame_size {
+            Some(ms) => encode::parse_opus_frame_ms(&ms.to_string()).map_err(ApiError::bad_request)?,
+            None => DEFAULT_OPUS_FRAME_MS,
+        };
+        Ok(EncodeSettings { format, bitrate_kbps: self.bitrate, quality: self.quality.unwrap_or(DEFAULT_QUALITY), opus_frame_ms })
+    }
+
+    fn lipsync_level(&self) -> Result<LipsyncLevel, ApiError> {
+        match self.lipsync.as_deref().unwrap_or("estimate") {
+            "estimate" => Ok(LipsyncLevel::Estimate),
+            "align" => Ok(LipsyncLevel::Align),
+            "piper" => Ok(LipsyncLevel::Piper),
+            "high" => Ok(LipsyncLevel::High),
+            other => Err(ApiError::bad_request(format!("Unknown lipsync level '{}' (use estimate, align, piper, or high)", other))),
+        }
+    }
+}
+
+/// `POST /synthesize`
+fn synthesize(request: &SynthesisRequest, options: &ServerOptions) -> Result<Reply, ApiError> {
+    let pitch = request.pitch()?;
+    let tempo = request.tempo()?;
+    let voice = request.voice(options)?;
+    let encoding = request.encoding()?;
+    let (samples, sample_rate) = request.with_speaker(voice, || {
+        if ssml::is_ssml(&request.text) {
+            let (samples, sample_rate, _) = synthesize_ssml(&request.text, voice, &pitch, tempo)?;
+            Ok((samples, sample_rate))
+        } else {
+            Ok(synthesize_processed(&request.text, voice, &pitch, tempo)?)
+        }
+    })?;
+    let tags = AudioTags::for_synthesis(voice, pitch.as_factor(), tempo);
+    let body = match encoding.format {
+        AudioFormat::Wav => wav_bytes(&samples, sample_rate)?,
+        AudioFormat::Flac => {
+            let pcm: Vec<i16> = samples.iter().map(|s| (s * 32767.0).clamp(-32768.0, 32767.0) as i16).collect();
+            encode::encode_flac(&pcm, sample_rate, &tags)
+        }
+        AudioFormat::Opus => crate::opus::encode_opus(&samples, 1, sample_rate, &encoding, &tags)?,
+        AudioFormat::Mp3 | AudioFormat::Ogg => {
+            let (wav, output) = (temp_path("wav"), temp_path(encoding.format.extension()));
+            let encoded = crate::write_wav(&wav.to_string_lossy(), &samples, sample_rate)
+                .map_err(|e| e.into())
+                .and_then(|()| encode::encode_wav(&wav, &output, &encoding, &tags))
+                .and_then(|()| Ok(std::fs::read(&output)?));
+            let _ = std::fs::remove_file(&wav);
+            let _ = std::fs::remove_file(&output);
+            encoded?
+        }
+    };
+    Ok(Reply::new(body, encoding.format.mime_type()))
+}
+
+/// `POST /synthesize?stream=ndjson`: the audio in WAV chunks between word and viseme frames, with word
+/// timings estimated from the text's phonemes
+fn synthesize_stream(request: &SynthesisRequest, options: &ServerOptions) -> Result<Reply, ApiError> {
+    let pitch = request.pitch()?;
+    let tempo = request.tempo()?;
+    let voice = request.voice(options)?;
+    let (samples, sample_rate, spoken) = request.with_speaker(voice, || {
+        if ssml::is_ssml(&request.text) {
+            Ok(synthesize_ssml(&request.text, voice, &pitch, tempo)?)
+        } else {
+            let (samples, sample_rate) = synthesize_processed(&request.text, voice, &pitch, tempo)?;
+            Ok((samples, sample_rate, emphasis::strip_emphasis(&pause_markers::strip_markers(&directives::strip_directives(&request.text)))))
+        }
+    })?;
+    let words = lipsync_stream::estimated_words(&spoken, &samples, sample_rate);
+    let frames = lipsync_stream::lipsync_frames(&samples, sample_rate, &words, DEFAULT_CHUNK_SECONDS);
+    let mut body = Vec::new();
+    lipsync_stream::write_ndjson(&mut body, &frames)?;
+    Ok(Reply { body, content_type: "application/x-ndjson", chunked: true })
+}
+
+/// Synthesize with lipsync into the request's scratch files and read back the lipsync JSON
+fn render_lipsync(request: &SynthesisRequest, voice: &str, pitch: &PitchArg, tempo: f32, level: LipsyncLevel, wav: &Path, json: &Path) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    synthesize_and_handle(&request.text, voice, pitch, tempo, Some(&wav.to_string_lossy()), false, level, Some(&json.to_string_lossy()), None)?;
+    let mut lipsync: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(json)?)?;
+    if request.include_audio
+        && let Some(object) = lipsync.as_object_mut()
+    {
+        object.insert("audio".to_string(), base64::engine::general_purpose::STANDARD.encode(std::fs::read(wav)?).into());
+    }
+    Ok(lipsync)
+}
+
+/// `POST /lipsync`
+fn lipsync(request: &SynthesisRequest, options: &ServerOptions) -> Result<Reply, ApiError> {
+    if ssml::is_ssml(&request.text) {
+        return Err(ApiError::bad_request("SSML isn't supported for lipsync; send plain text"));
+    }
+    let pitch = request.pitch()?;
+    let tempo = request.tempo()?;
+    let voice = request.voice(options)?;
+    let level = request.lipsync_level()?;
+    let (wav, json) = (temp_path("wav"), temp_path("json"));
+    let result = request.with_speaker(voice, || Ok(render_lipsync(request, voice, &pitch, tempo, level, &wav, &json)?));
+    let _ = std::fs::remove_file(&wav);
+    let _ = std::fs::remove_file(&json);
+    Ok(Reply::json(&result?))
+}
+
+/// What `POST /reload` reloaded
+#[derive(Serialize)]
+struct ReloadReport {
+    rules: usize,
+    lexicon_words: usize,
+    voices: usize,
+}
+
+/// `POST /reload`. Rules and lexicons that fail to load are reported and the previous ones stay active.
+fn reload() -> Result<Reply, ApiError> {
+    let rules = crate::rules::reload_rules().map_err(|e| ApiError { status: 500, message: format!("Failed to reload rules: {}", e) })?;
+    let lexicon_words = crate::phoneme_sources::reload_lexicons().map_err(|e| ApiError { status: 500, message: format!("Failed to reload lexicon: {}", e) })?;
+    // The manifest is read on every lookup, so this only checks it, since a broken one is otherwise just logged
+    if let Some(path) = crate::catalog::manifest_path() {
+        crate::catalog::CatalogManifest::load(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|manifest| manifest.apply(crate::builtin_voices()).map_err(|e| format!("{}: {}", path.display(), e)))
+            .map_err(|e| ApiError { status: 500, message: format!("Failed to reload voice manifest: {}", e) })?;
+    }
+    crate::model_cache::clear();
+    Ok(Reply::json(&ReloadReport { rules, lexicon_words, voices: get_available_voices().len() }))
+}
+
+/// `GET /voices`
+fn voices() -> Reply {
+    let listings: Vec<VoiceListing> = get_available_voices().into_iter().map(|voice| VoiceListing { capabilities: voice_capabilities(&voice), voice }).collect();
+    Reply::json(&listings)
+}
+
+fn read_request(request: &mut tiny_http::Request) -> Result<SynthesisRequest, ApiError> {
+    let mut body = String::new();
+    request.as_reader().take(MAX_BODY_BYTES + 1).read_to_string(&mut body).map_err(ApiError::bad_request)?;
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return Err(ApiError { status: 413, message: format!("Request body is over {} bytes", MAX_BODY_BYTES) });
+    }
+    serde_json::from_str(&body).map_err(|e| ApiError::bad_request(format!("Invalid request JSON: {}", e)))
+}
+
+/// The value of `name` in a URL's query string
+fn query_param<'a>(url: &'a str, name: &str) -> Option<&'a str> {
+    let (_, query) = url.split_once('?')?;
+    query.split('&').find_map(|pair| match pair.split_once('=') {
+        Some((key, value)) if key == name => Some(value),
+        None if pair == name => Some(""),
+        _ => None,
+    })
+}
+
+fn route(request: &mut tiny_http::Request, options: &ServerOptions) -> Result<Reply, ApiError> {
+    use tiny_http::Method;
+    let url = request.url().to_string();
+    let path = url.split('?').next().unwrap_or_default();
+    match (request.method(), path) {
+        (Method::Get, "/voices") => Ok(voices()),
+        (Method::Post, "/synthesize") => match query_param(&url, "stream") {
+            None => synthesize(&read_request(request)?, options),
+            Some("ndjson") => synthesize_stream(&read_request(request)?, options),
+            Some(other) => Err(ApiError::bad_request(format!("Unknown stream format '{}' (use ndjson)", other))),
+        },
+        (Method::Post, "/lipsync") => lipsync(&read_request(request)?, options),
+        (Method::Post, "/reload") => reload(),
+        (_, "/voices" | "/synthesize" | "/lipsync" | "/reload") => Err(ApiError { status: 405, message: format!("{} isn't allowed on {}", request.method(), path) }),
+        _ => Err(ApiError { status: 404, message: format!("No endpoint {}", path) }),
+    }
+}
+
+fn header(name: &str, value: &str) -> tiny_http::Header {
+    tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+fn respond(mut request: tiny_http::Request, options: &ServerOptions) {
+    let started = std::time::Instant::now();
+    let (method, url) = (request.method().to_string(), request.url().to_string());
+    let mut response = if request.method() == &tiny_http::Method::Options {
+        // CORS preflight
+        tiny_http::Response::from_data(Vec::new()).with_status_code(204)
+    } else {
+        match route(&mut request, options) {
+            Ok(Reply { body, content_type, chunked: true }) => {
+                // No length, so tiny_http sends the body chunked and it reaches the client frame by frame
+                tiny_http::Response::new(200.into(), vec![header("Content-Type", content_type)], std::io::Cursor::new(body), None, None)
+            }
+            Ok(reply) => tiny_http::Response::from_data(reply.body).with_header(header("Content-Type", reply.content_type)),
+            Err(error) => tiny_http::Response::from_data(serde_json::to_vec(&serde_json::json!({ "error": error.message })).unwrap_or_default())
+                .with_status_code(error.status)
+                .with_header(header("Content-Type", "application/json")),
+        }
+    };
+    if let Some(origin) = &options.allow_origin {
+        response.add_header(header("Access-Control-Allow-Origin", origin));
+        response.add_header(header("Access-Control-Allow-Methods", "GET, POST, OPTIONS"));
+        response.add_header(header("Access-Control-Allow-Headers", "Content-Type"));
+    }
+    let status = response.status_code().0;
+    if let Err(e) = request.respond(response) {
+        log::error!("[Serve] Failed to send response: {}", e);
+    }
+    log::info!("[Serve] {} {} -> {} ({:.2}s)", method, url, status, started.elapsed().as_secs_f32());
+    for warning in warnings::take_warnings() {
+        log::warn!("[Serve]   warning: {}", warning);
+    }
+}
+
+/// Listen on `options.bind` and answer requests until the process is stopped
+pub fn serve(options: &ServerOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let server = tiny_http::Server::http(&options.bind).map_err(|e| format!("Failed to listen on {}: {}", options.bind, e))?;
+    log::info!("[Serve] Listening on http://{} (default voice {})", options.bind, options.default_voice);
+    for request in server.incoming_requests() {
+        respond(request, options);
+    }
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Schema version of session log entries
+pub const SESSION_VERSION: u32 = 1;
+
+/// One synthesis performed during a command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SynthesisRecord {
+    pub text: String,
+    pub voice: String,
+    pub sample_rate: u32,
+    pub samples: usize,
+    /// SHA-256 of the synthesized samples (little-endian f32)
+    pub sha256: String,
+}
+
+impl SynthesisRecord {
+    pub fn duration(&self) -> f32 {
+        self.samples as f32 / self.sample_rate.max(1) as f32
+    }
+}
+
+/// A file a command wrote
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputRecord {
+    pub path: String,
+    pub sha256: Option<String>,
+}
+
+/// One line of a session log: a command invocation and everything it synthesized
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionEntry {
+    pub schema_version: u32,
+    pub crate_version: String,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Full command line, program name first; `replay` re-parses it
+    pub args: Vec<String>,
+    /// Settings in effect, as in the export sidecar (see [`crate::sidecar::render_effects`])
+    pub effects: Vec<String>,
+    pub syntheses: Vec<SynthesisRecord>,
+    pub outputs: Vec<OutputRecord>,
+}
+
+struct Recording {
+    log: Option<PathBuf>,
+    entry: SessionEntry,
+}
+
+/// The command currently being recorded, if any
+static RECORDING: Mutex<Option<Recording>> = Mutex::new(None);
+
+/// Start recording a command. With a `log`, [`finish`] appends the entry to it; without one the entry is
+/// only kept in memory, which is how `replay` captures what a re-run produced.
+pub fn start(log: Option<PathBuf>, args: Vec<String>) {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let entry = SessionEntry {
+        schema_version: SESSION_VERSION,
+        crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        timestamp,
+        args,
+        effects: crate::sidecar::render_effects(None),
+        syntheses: Vec::new(),
+        outputs: Vec::new(),
+    };
+    *RECORDING.lock().unwrap() = Some(Recording { log, entry });
+}
+
+pub fn is_recording() -> bool {
+    RECORDING.lock().unwrap().is_some()
+}
+
+/// Incremental hash of audio that's synthesized in pieces, such as sentence-by-sentence playback
+pub struct SynthesisHasher {
+    hasher: Sha256,
+    samples: usize,
+}
+
+impl Default for SynthesisHasher {
+    fn default() -> Self {
+        SynthesisHasher { hasher: Sha256::new(), samples: 0 }
+    }
+}
+
+impl SynthesisHasher {
+    pub fn update(&mut self, samples: &[f32]) {
+        for sample in samples {
+            self.hasher.update(sample.to_le_bytes());
+        }
+        self.samples += samples.len();
+    }
+
+    /// Add the hashed audio to the current recording
+    pub fn record(self, text: &str, voice: &str, sample_rate: u32) {
+        if let Some(recording) = RECORDING.lock().unwrap().as_mut() {
+            recording.entry.syntheses.push(SynthesisRecord {
+                text: text.to_string(),
+                voice: voice.to_string(),
+                sample_rate,
+                samples: self.samples,
+                sha256: self.hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect(),
+            });
+        }
+    }
+}
+
+/// Add a synthesis to the current recording; does nothing when no session is being recorded
+pub fn record_synthesis(text: &str, voice: &str, samples: &[f32], sample_rate: u32) {
+    if !is_recording() {
+        return;
+    }
+    let mut hasher = SynthesisHasher::default();
+    hasher.update(samples);
+    hasher.record(text, voice, sample_rate);
+}
+
+/// Add a written file to the current recording
+pub fn record_output(path: &Path) {
+    if let Some(recording) = RECORDING.lock().unwrap().as_mut() {
+        recording.entry.outputs.push(OutputRecord {
+            path: path.display().to_string(),
+            sha256: crate::sidecar::sha256_file(path).ok(),
+        });
+    }
+}
+
+/// Stop recording and return the entry. Entries for commands that synthesized something are appended to the
+/// session log as one JSON line.
+pub fn finish() -> Result<Option<SessionEntry>, Box<dyn std::error::Error>> {
+    let Some(recording) = RECORDING.lock().unwrap().take() else {
+        return Ok(None);
+    };
+    if let Some(log) = &recording.log
+        && !recording.entry.syntheses.is_empty()
+    {
+        if let Some(parent) = log.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(log)?;
+        writeln!(file, "{}", serde_json::to_string(&recording.entry)?)?;
+    }
+    Ok(Some(recording.entry))
+}
+
+/// Read every entry of a session log, skipping blank lines
+pub fn read_session(path: &Path) -> Result<Vec<SessionEntry>, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)?;
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line).map_err(|e| format!("{} line {}: {}", path.display(), i + 1, e).into()))
+        .collect()
+}
+
+/// Differences between a logged entry and its replay; empty when every synthesis produced identical audio.
+/// Piper samples noise while synthesizing, so a replay of a real voice usually differs slightly in its
+/// hashes; the durations show whether anything substantial changed.
+pub fn compare_entries(logged: &SessionEntry, replayed: &SessionEntry) -> Vec<String> {
+    let mut differences = Vec::new();
+    if logged.syntheses.len() != replayed.syntheses.len() {
+        differences.push(format!("{} syntheses logged, {} replayed", logged.syntheses.len(), replayed.syntheses.len()));
+    }
+    for (i, (old, new)) in logged.syntheses.iter().zip(&replayed.syntheses).enumerate() {
+        if old.text != new.text || old.voice != new.voice {
+            differences.push(format!("synthesis {}: input changed", i + 1));
+        } else if old.sha256 != new.sha256 {
+            differences.push(format!("synthesis {}: audio differs ({:.2}s logged, {:.2}s replayed)", i + 1, old.duration(), new.duration()));
+        }
+    }
+    if logged.effects != replayed.effects {
+        differences.push(format!("effects changed: [{}] -> [{}]", logged.effects.join(", "), replayed.effects.join(", ")));
+    }
+    differences
+}
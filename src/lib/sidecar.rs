@@ -0,0 +1,160 @@
+use crate::warnings::Warning;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Schema version of the `.meta.json` sidecar
+pub const SIDECAR_VERSION: u32 = 1;
+
+/// Everything needed to tell whether an exported asset is stale or mismatched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSidecar {
+    pub schema_version: u32,
+    pub crate_version: String,
+    pub text: String,
+    pub voice: String,
+    pub speaker: Option<i64>,
+    pub pitch: f32,
+    pub tempo: f32,
+    pub lipsync: String,
+    pub effects: Vec<String>,
+    /// Seed of the render's random variation: the take's (see [`crate::takes`]), else `--humanize`'s
+    pub seed: Option<u64>,
+    /// SHA-256 over the text and every synthesis parameter above
+    pub input_hash: String,
+    /// SHA-256 of the exported audio file
+    pub audio_sha256: Option<String>,
+    /// SHA-256 of the lipsync JSON, if one was written
+    pub lipsync_sha256: Option<String>,
+    pub audio_path: String,
+    pub lipsync_path: Option<String>,
+    /// Non-fatal problems reported while rendering
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+}
+
+impl ExportSidecar {
+    /// Describe an export before it is rendered; hashes of the outputs are filled in by [`ExportSidecar::finalize`]
+    pub fn new(text: &str, voice: &str, pitch: f32, tempo: f32, lipsync: &str, audio_path: &str) -> Self {
+        let mut sidecar = ExportSidecar {
+            schema_version: SIDECAR_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            text: text.to_string(),
+            voice: voice.to_string(),
+            speaker: crate::speakers::selected_speaker_id(voice),
+            pitch,
+            tempo,
+            lipsync: lipsync.to_string(),
+            effects: render_effects(Some(voice)),
+            seed: crate::takes::variation().map(|v| v.seed()).or(crate::humanize::is_enabled().then_some(crate::humanize::SEED)),
+            input_hash: String::new(),
+            audio_sha256: None,
+            lipsync_sha256: None,
+            audio_path: audio_path.to_string(),
+            lipsync_path: None,
+            warnings: Vec::new(),
+        };
+        sidecar.input_hash = sidecar.compute_input_hash();
+        sidecar
+    }
+
+    /// Hash of everything that influences the rendered audio
+    pub fn compute_input_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        for part in [
+            self.crate_version.as_str(),
+            self.text.as_str(),
+            self.voice.as_str(),
+            &format!("{:?}", self.speaker),
+            &self.pitch.to_string(),
+            &self.tempo.to_string(),
+            self.lipsync.as_str(),
+            &self.effects.join(","),
+            &format!("{:?}", self.seed),
+        ] {
+            hasher.update(part.as_bytes());
+            hasher.update([0u8]);
+        }
+        to_hex(&hasher.finalize())
+    }
+
+    /// Hash the written audio (and lipsync JSON, if present) into the sidecar
+    pub fn finalize(&mut self, lipsync_path: Option<&str>) {
+        self.input_hash = self.compute_input_hash();
+        self.audio_sha256 = sha256_file(Path::new(&self.audio_path)).ok();
+        self.lipsync_path = lipsync_path.filter(|p| Path::new(p).exists()).map(|p| p.to_string());
+        self.lipsync_sha256 = self.lipsync_path.as_ref().and_then(|p| sha256_file(Path::new(p)).ok());
+    }
+}
+
+/// Every setting besides the text, voice, pitch, and tempo that changes a render, as labels for sidecars
+/// and session logs. The voice's override file and locked revision are included when `voice` is given.
+pub fn render_effects(voice: Option<&str>) -> Vec<String> {
+    crate::pauses::pauses()
+        .effects()
+        .into_iter()
+        .chain(crate::rules::rules_fingerprint().map(|f| format!("rules={}", f)))
+        .chain(crate::effects::effect().map(|e| format!("effect={}", e.name())))
+        .chain(crate::takes::variation().map(|v| v.describe()))
+        .chain(crate::humanize::describe())
+        .chain(crate::abbreviations::effects())
+        .chain(crate::emoji::describe())
+        .chain(crate::pause_markers::describe())
+        .chain(crate::dsp::describe_stretch())
+        .chain(Some(crate::rate::rate()).filter(|r| *r != crate::rate::RateSettings::NORMAL).map(|r| r.describe()))
+        .chain((!crate::normalize::is_enabled()).then(|| "normalize=off".to_string()))
+        .chain(crate::prefers_quantized().then(|| "quantized".to_string()))
+        .chain(voice.and_then(overrides_fingerprint).map(|f| format!("overrides={}", f)))
+        .chain(voice.and_then(crate::pinning::voice_lock).map(|lock| format!("voice_revision={}", lock.revision)))
+        .collect()
+}
+
+/// Short hash of the voice's override file, if it has one, so editing it invalidates up-to-date checks
+fn overrides_fingerprint(voice: &str) -> Option<String> {
+    sha256_file(&crate::overrides::override_path(voice)).ok().map(|hash| hash[..12].to_string())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// SHA-256 of a file's contents as lowercase hex
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let bytes = std::fs::read(path)?;
+    Ok(to_hex(&Sha256::digest(&bytes)))
+}
+
+/// The sidecar path for an exported file (`dialogue.wav` → `dialogue.meta.json`)
+pub fn sidecar_path(output: &Path) -> PathBuf {
+    output.with_extension("meta.json")
+}
+
+/// Write a sidecar next to its exported file
+pub fn write_sidecar(output: &Path, sidecar: &ExportSidecar) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = sidecar_path(output);
+    std::fs::write(&path, serde_json::to_string_pretty(sidecar)?)?;
+    Ok(path)
+}
+
+/// Read the sidecar for an exported file, if there is one
+pub fn read_sidecar(output: &Path) -> Option<ExportSidecar> {
+    let contents = std::fs::read_to_string(sidecar_path(output)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// True if `output` exists and its sidecar shows it was rendered from the same inputs and has not been modified since
+pub fn is_up_to_date(output: &Path, expected: &ExportSidecar) -> bool {
+    if !output.exists() {
+        return false;
+    }
+    let Some(existing) = read_sidecar(output) else {
+        return false;
+    };
+    if existing.input_hash != expected.compute_input_hash() {
+        return false;
+    }
+    match existing.audio_sha256 {
+        Some(hash) => sha256_file(output).map(|h| h == hash).unwrap_or(false),
+        None => false,
+    }
+}
@@ -0,0 +1,314 @@
+use crate::load_synthesizer;
+
+/// A note of the melody; `midi` is `None` for a rest
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Note {
+    pub midi: Option<u8>,
+    pub beats: f32,
+}
+
+impl Note {
+    pub fn frequency(&self) -> Option<f32> {
+        self.midi.map(|m| 440.0 * 2f32.powf((m as f32 - 69.0) / 12.0))
+    }
+}
+
+/// Parse a note name such as `C4`, `F#3`, or `Bb5` into a MIDI note number
+fn parse_pitch(name: &str) -> Result<u8, String> {
+    let mut chars = name.chars();
+    let letter = chars.next().ok_or("empty note")?.to_ascii_uppercase();
+    let base: i32 = match letter {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return Err(format!("Invalid note '{}'", name)),
+    };
+    let rest: String = chars.collect();
+    let (accidental, octave) = if let Some(octave) = rest.strip_prefix('#') {
+        (1, octave)
+    } else if let Some(octave) = rest.strip_prefix('b') {
+        (-1, octave)
+    } else {
+        (0, rest.as_str())
+    };
+    let octave: i32 = octave.parse().map_err(|_| format!("Invalid octave in note '{}'", name))?;
+    let midi = (octave + 1) * 12 + base + accidental;
+    u8::try_from(midi).ok().filter(|m| *m <= 127).ok_or_else(|| format!("Note '{}' is out of range", name))
+}
+
+/// Parse a note list such as `"C4 D4:0.5 E4:0.5 R G4:2"`.
+/// Each note is a pitch (`C4`, `F#3`, `Bb2`) or `R` for a rest, optionally followed by `:beats` (default 1).
+pub fn parse_note_list(notes: &str) -> Result<Vec<Note>, String> {
+    notes
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|t| !t.is_empty())
+        .map(|token| {
+            let (name, beats) = match token.split_once(':') {
+                Some((name, beats)) => (name, beats.parse::<f32>().map_err(|_| format!("Invalid duration in '{}'", token))?),
+                None => (token, 1.0),
+            };
+            if beats <= 0.0 {
+                return Err(format!("Duration must be positive in '{}'", token));
+            }
+            let midi = if name.eq_ignore_ascii_case("r") { None } else { Some(parse_pitch(name)?) };
+            Ok(Note { midi, beats })
+        })
+        .collect()
+}
+
+fn read_vlq(data: &[u8], pos: &mut usize) -> Result<u32, String> {
+    let mut value = 0u32;
+    loop {
+        let byte = *data.get(*pos).ok_or("Unexpected end of MIDI track")?;
+        *pos += 1;
+        value = (value << 7) | (byte & 0x7f) as u32;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+/// Read a monophonic melody from a standard MIDI file: the first track with notes is used,
+/// overlapping notes are cut at the next note-on, and gaps become rests.
+/// Returns the notes and the tempo in BPM, if the file sets one.
+pub fn read_midi_melody(path: &str) -> Result<(Vec<Note>, Option<f32>), Box<dyn std::error::Error>> {
+    let data = std::fs::read(path)?;
+    if data.len() < 14 || &data[0..4] != b"MThd" {
+        return Err("Not a standard MIDI file".into());
+    }
+    let division = u16::from_be_bytes([data[12], data[13]]);
+    if division & 0x8000 != 0 {
+        return Err("SMPTE-timed MIDI files are not supported".into());
+    }
+    let ticks_per_beat = division.max(1) as f32;
+    let mut bpm = None;
+    let mut pos = 8 + u32::from_be_bytes([data[4], data[5], data[6], data[7]]) as usize;
+
+    while pos + 8 <= data.len() {
+        let chunk_len = u32::from_be_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]) as usize;
+        let start = pos + 8;
+        let end = (start + chunk_len).min(data.len());
+        pos = end;
+        if &data[start - 8..start - 4] != b"MTrk" {
+            continue;
+        }
+        // (start tick, end tick, note)
+        let mut notes: Vec<(u32, Option<u32>, u8)> = Vec::new();
+        let (mut i, mut tick, mut status) = (start, 0u32, 0u8);
+        while i < end {
+            tick += read_vlq(&data, &mut i)?;
+            let mut byte = *data.get(i).ok_or("Unexpected end of MIDI track")?;
+            if byte & 0x80 != 0 {
+                status = byte;
+                i += 1;
+                byte = *data.get(i).unwrap_or(&0);
+            }
+            match status {
+                0xff => {
+                    let kind = byte;
+                    i += 1;
+                    let len = read_vlq(&data, &mut i)? as usize;
+                    if kind == 0x51 && len == 3 && i + 3 <= end {
+                        let micros = u32::from_be_bytes([0, data[i], data[i + 1], data[i + 2]]);
+                        bpm.get_or_insert(60_000_000.0 / micros.max(1) as f32);
+                    }
+                    i += len;
+                }
+                0xf0 | 0xf7 => {
+                    let len = read_vlq(&data, &mut i)? as usize;
+                    i += len;
+                }
+                _ => {
+                    let velocity = *data.get(i + 1).unwrap_or(&0);
+                    match status & 0xf0 {
+                        0x90 if velocity > 0 => {
+                            if let Some(last) = notes.last_mut() {
+                                last.1.get_or_insert(tick);
+                            }
+                            notes.push((tick, None, byte));
+                        }
+                        0x80 | 0x90 => {
+                            if let Some(note) = notes.iter_mut().rev().find(|n| n.2 == byte && n.1.is_none()) {
+                                note.1 = Some(tick);
+                            }
+                        }
+                        _ => {}
+                    }
+                    // Program change and channel pressure have one data byte, the rest two
+                    i += if matches!(status & 0xf0, 0xc0 | 0xd0) { 1 } else { 2 };
+                }
+            }
+        }
+        if notes.is_empty() {
+            continue;
+        }
+        let mut melody = Vec::new();
+        let mut cursor = 0u32;
+        for (note_start, note_end, midi) in notes {
+            if note_start > cursor {
+                melody.push(Note { midi: None, beats: (note_start - cursor) as f32 / ticks_per_beat });
+            }
+            let note_end = note_end.unwrap_or(note_start + ticks_per_beat as u32).max(note_start + 1);
+            melody.push(Note { midi: Some(midi), beats: (note_end - note_start) as f32 / ticks_per_beat });
+            cursor = note_end;
+        }
+        return Ok((melody, bpm));
+    }
+    Err("MIDI file contains no notes".into())
+}
+
+/// Split lyrics into syllables. Hyphens mark syllable breaks explicitly (`won-der-ful`);
+/// otherwise each word is split before every vowel group after the first.
+pub fn split_syllables(lyrics: &str) -> Vec<Vec<String>> {
+    lyrics
+        .split_whitespace()
+        .map(|word| {
+            if word.contains('-') {
+                return word.split('-').filter(|s| !s.is_empty()).map(str::to_string).collect();
+            }
+            let chars: Vec<char> = word.chars().collect();
+            let is_vowel = |c: char| "aeiouyAEIOUY".contains(c);
+            let mut syllables: Vec<String> = Vec::new();
+            let mut current = String::new();
+            let mut seen_vowel = false;
+            for (i, &c) in chars.iter().enumerate() {
+                let starts_group = is_vowel(c) && (i == 0 || !is_vowel(chars[i - 1]));
+                // A trailing silent "e" doesn't start a syllable
+                let silent_e = (c == 'e' || c == 'E') && i == chars.len() - 1 && i > 0 && !is_vowel(chars[i - 1]) && seen_vowel;
+                if starts_group && seen_vowel && !silent_e {
+                    // Keep one consonant with the new syllable ("won-der")
+                    let split_at = current.char_indices().last().map(|(i, _)| i).unwrap_or(0);
+                    let carried = current.split_off(split_at);
+                    syllables.push(std::mem::take(&mut current));
+                    current = carried;
+                }
+                if is_vowel(c) {
+                    seen_vowel = true;
+                }
+                current.push(c);
+            }
+            if !current.is_empty() {
+                syllables.push(current);
+            }
+            syllables.retain(|s| !s.is_empty());
+            syllables
+        })
+        .filter(|s: &Vec<String>| !s.is_empty())
+        .collect()
+}
+
+/// Estimate the fundamental frequency of a voiced segment by autocorrelation (70–400 Hz)
+pub fn estimate_pitch(samples: &[f32], sample_rate: u32) -> Option<f32> {
+    let frame = (sample_rate as f32 * 0.04) as usize;
+    let min_lag = (sample_rate as f32 / 400.0) as usize;
+    let max_lag = (sample_rate as f32 / 70.0) as usize;
+    let mut estimates: Vec<f32> = samples
+        .chunks(frame)
+        .filter(|chunk| chunk.len() > max_lag * 2)
+        .filter_map(|chunk| {
+            let energy: f32 = chunk.iter().map(|s| s * s).sum();
+            if energy / (chunk.len() as f32) < 1e-4 {
+                return None;
+            }
+            let (lag, corr) = (min_lag..max_lag)
+                .map(|lag| (lag, chunk.iter().zip(&chunk[lag..]).map(|(a, b)| a * b).sum::<f32>()))
+                .fold((0, f32::MIN), |best, c| if c.1 > best.1 { c } else { best });
+            (lag > 0 && corr > 0.3 * energy).then(|| sample_rate as f32 / lag as f32)
+        })
+        .collect();
+    if estimates.is_empty() {
+        return None;
+    }
+    estimates.sort_by(|a, b| a.total_cmp(b));
+    Some(estimates[estimates.len() / 2])
+}
+
+/// Trim leading and trailing near-silence
+fn trim_silence(samples: &[f32]) -> &[f32] {
+    let loud = |s: &f32| s.abs() > 0.02;
+    match (samples.iter().position(loud), samples.iter().rposition(loud)) {
+        (Some(start), Some(end)) => &samples[start..=end],
+        _ => &samples[0..0],
+    }
+}
+
+/// Shift a segment by `cents` and fit it to `target_len` samples, with the same phase vocoder and WSOLA
+/// stretch used for synthesis
+fn fit_segment(samples: &[f32], sample_rate: u32, cents: f32, target_len: usize) -> Vec<f32> {
+    if samples.is_empty() {
+        return vec![0.0; target_len];
+    }
+    let shifted = crate::dsp::pitch_shift(samples, sample_rate as usize, 2f32.powf(cents / 1200.0));
+    // Above 1.0 lengthens, as everywhere else
+    let stretch = (target_len as f32 / samples.len() as f32).clamp(0.1, 10.0);
+    let mut fitted = crate::dsp::wsola_stretch(&shifted, sample_rate as usize, stretch);
+    fitted.resize(target_len, 0.0);
+    // Short fades so notes don't click at the joins
+    let fade = (sample_rate as usize / 200).min(target_len / 2);
+    for i in 0..fade {
+        let gain = i as f32 / fade as f32;
+        fitted[i] *= gain;
+        fitted[target_len - 1 - i] *= gain;
+    }
+    fitted
+}
+
+/// Sing lyrics to a melody: each syllable takes the next note, is pitch-shifted from its spoken
+/// pitch to the note, and stretched to the note's length. Rests are silence; syllables beyond the
+/// melody repeat the last note. Returns the samples and their sample rate (the voice's).
+pub fn sing(lyrics: &str, voice: &str, melody: &[Note], bpm: f32) -> Result<(Vec<f32>, u32), Box<dyn std::error::Error>> {
+    let words = split_syllables(lyrics);
+    let syllable_count: usize = words.iter().map(|w| w.len()).sum();
+    let sung_notes = melody.iter().filter(|n| n.midi.is_some()).count();
+    if sung_notes == 0 {
+        return Err("Melody has no notes".into());
+    }
+    if syllable_count != sung_notes {
+        log::warn!("Warning: {} syllables for {} notes", syllable_count, sung_notes);
+    }
+    let seconds_per_beat = 60.0 / bpm.max(1.0);
+    let synth = load_synthesizer(voice)?;
+    let sample_rate = crate::voice_sample_rate(voice);
+
+    let mut output = Vec::new();
+    let mut notes = melody.iter();
+    let mut last_note = None;
+    for syllables in &words {
+        // Synthesize the whole word, then cut it into equal parts per syllable
+        let mut spoken: Vec<f32> = Vec::new();
+        for chunk in synth.synthesize_lazy(syllables.concat(), None)? {
+            spoken.append(&mut chunk?.into_vec());
+        }
+        let spoken = trim_silence(&spoken);
+        let part_len = spoken.len().div_ceil(syllables.len().max(1)).max(1);
+        for part in spoken.chunks(part_len).chain(std::iter::repeat(&[][..])).take(syllables.len()) {
+            // Emit rests until the next sung note
+            let note = loop {
+                match notes.next() {
+                    Some(n) if n.midi.is_none() => {
+                        output.extend(std::iter::repeat_n(0.0, (n.beats * seconds_per_beat * sample_rate as f32) as usize));
+                    }
+                    Some(n) => break *n,
+                    None => break last_note.unwrap_or(Note { midi: Some(60), beats: 1.0 }),
+                }
+            };
+            last_note = Some(note);
+            let target_len = (note.beats * seconds_per_beat * sample_rate as f32) as usize;
+            let cents = match (estimate_pitch(part, sample_rate), note.frequency()) {
+                (Some(from), Some(to)) => (1200.0 * (to / from).log2()).clamp(-2400.0, 2400.0),
+                _ => 0.0,
+            };
+            output.extend(fit_segment(part, sample_rate, cents, target_len));
+        }
+    }
+    // Trailing rests
+    for n in notes.filter(|n| n.midi.is_none()) {
+        output.extend(std::iter::repeat_n(0.0, (n.beats * seconds_per_beat * sample_rate as f32) as usize));
+    }
+    crate::session::record_synthesis(lyrics, voice, &output, sample_rate);
+    Ok((output, sample_rate))
+}
@@ -0,0 +1,110 @@
+//! Speaker selection for multi-speaker voices such as `en_GB-vctk-medium` and `en_US-libritts-high`.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// A speaker of a multi-speaker voice: its numeric ID, or its name in the voice's `speaker_id_map`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpeakerSelection {
+    Id(i64),
+    Name(String),
+}
+
+impl FromStr for SpeakerSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("Speaker can't be empty".to_string());
+        }
+        Ok(s.parse::<i64>().map(SpeakerSelection::Id).unwrap_or_else(|_| SpeakerSelection::Name(s.to_string())))
+    }
+}
+
+impl fmt::Display for SpeakerSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpeakerSelection::Id(id) => write!(f, "{}", id),
+            SpeakerSelection::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+/// The speakers in a voice config, as `(id, name)` sorted by ID. Single-speaker voices have none.
+pub fn config_speakers(config: &serde_json::Value) -> Vec<(i64, String)> {
+    if config["num_speakers"].as_u64().unwrap_or(1) <= 1 {
+        return Vec::new();
+    }
+    let mut speakers: Vec<(i64, String)> = config["speaker_id_map"]
+        .as_object()
+        .map(|map| map.iter().filter_map(|(name, id)| Some((id.as_i64()?, name.clone()))).collect())
+        .unwrap_or_default();
+    speakers.sort();
+    speakers
+}
+
+/// The ID `selection` names among `speakers` (from [`config_speakers`])
+pub fn resolve_speaker(speakers: &[(i64, String)], selection: &SpeakerSelection) -> Result<i64, String> {
+    let (Some(first), Some(last)) = (speakers.first(), speakers.last()) else {
+        return Err("the voice has a single speaker".to_string());
+    };
+    match selection {
+        SpeakerSelection::Id(id) if speakers.iter().any(|(i, _)| i == id) => Ok(*id),
+        SpeakerSelection::Id(id) => Err(format!("no speaker {} (IDs run from {} to {})", id, first.0, last.0)),
+        SpeakerSelection::Name(name) => speakers.iter().find(|(_, n)| n == name).map(|(id, _)| *id).ok_or_else(|| format!("no speaker named '{}'", name)),
+    }
+}
+
+/// Select a speaker in a freshly loaded voice, returning its ID
+pub fn select_speaker(synth: &piper_rs::synth::PiperSpeechSynthesizer, speakers: &[(i64, String)], selection: &SpeakerSelection) -> Result<i64, Box<dyn std::error::Error>> {
+    let id = resolve_speaker(speakers, selection)?;
+    match synth.clone_model().set_speaker(id) {
+        Some(e) => Err(e.into()),
+        None => Ok(id),
+    }
+}
+
+/// Speaker selected for voices as they're loaded (`--speaker-id`); `None` uses each voice's first speaker
+static SPEAKER: Mutex<Option<SpeakerSelection>> = Mutex::new(None);
+
+pub fn set_speaker(selection: Option<SpeakerSelection>) {
+    *SPEAKER.lock().unwrap() = selection;
+}
+
+pub fn speaker() -> Option<SpeakerSelection> {
+    SPEAKER.lock().unwrap().clone()
+}
+
+#[cfg(feature = "full")]
+/// The speakers of a catalog voice, from its installed config, or else its published config (no model download)
+pub fn voice_speakers(voice_id: &str) -> Result<Vec<(i64, String)>, Box<dyn std::error::Error>> {
+    let voices = crate::get_available_voices();
+    let voice = voices.iter().find(|v| v.id == voice_id).ok_or_else(|| crate::Error::VoiceNotFound(voice_id.to_string()))?;
+    let installed = crate::get_models_dir().join(format!("{}.onnx.json", voice_id));
+    let config = match std::fs::read_to_string(&installed) {
+        Ok(config) => config,
+        Err(_) => crate::http::get_text(&voice.config_path)?,
+    };
+    Ok(config_speakers(&serde_json::from_str(&config)?))
+}
+
+#[cfg(feature = "full")]
+/// The ID of the selected [`speaker`] in an installed voice, e.g. for export sidecars
+pub fn selected_speaker_id(voice_id: &str) -> Option<i64> {
+    let selection = speaker()?;
+    let config = std::fs::read_to_string(crate::get_models_dir().join(format!("{}.onnx.json", voice_id))).ok()?;
+    resolve_speaker(&config_speakers(&serde_json::from_str(&config).ok()?), &selection).ok()
+}
+
+#[cfg(feature = "full")]
+/// Select the current [`speaker`], if any, in a freshly loaded voice with config `config_path`
+pub fn apply_speaker(synth: &piper_rs::synth::PiperSpeechSynthesizer, voice_id: &str, config_path: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(selection) = speaker() else {
+        return Ok(());
+    };
+    let config: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(config_path)?)?;
+    select_speaker(synth, &config_speakers(&config), &selection).map_err(|e| format!("Speaker {} of {}: {}", selection, voice_id, e))?;
+    Ok(())
+}
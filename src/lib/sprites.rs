@@ -0,0 +1,109 @@
+use crate::visemes::{VisemeEvent, SILENCE};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+/// Sprite sheet layout: which frame of the sheet shows each viseme.
+///
+/// ```json
+/// { "columns": 4, "frame_width": 128, "frame_height": 128,
+///   "frames": { "sil": 0, "AA": 1, "BM": 2, "E": 3, "O": 4 } }
+/// ```
+/// `columns` and the frame size are optional; when present the pixel offset of each frame is included.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpriteLayout {
+    pub columns: Option<u32>,
+    pub frame_width: Option<u32>,
+    pub frame_height: Option<u32>,
+    pub frames: HashMap<String, u32>,
+    /// Frame for visemes missing from `frames` (defaults to the `sil` frame, then 0)
+    pub default: Option<u32>,
+}
+
+impl SpriteLayout {
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// The sheet frame showing a viseme
+    pub fn frame_for(&self, viseme: &str) -> u32 {
+        self.frames
+            .get(viseme)
+            .or(self.default.as_ref())
+            .or_else(|| self.frames.get(SILENCE))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Pixel offset of a sheet frame, if the layout describes the grid
+    pub fn offset_of(&self, frame: u32) -> Option<(u32, u32)> {
+        let columns = self.columns.filter(|c| *c > 0)?;
+        Some(((frame % columns) * self.frame_width?, (frame / columns) * self.frame_height?))
+    }
+}
+
+/// The mouth sprite to show on one video frame
+#[derive(Debug, Clone, Serialize)]
+pub struct SpriteFrame {
+    pub frame: u32,
+    pub time: f32,
+    pub viseme: String,
+    pub sprite: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<u32>,
+}
+
+/// Sample the viseme track once per video frame for `duration` seconds at `fps`
+pub fn sprite_track(visemes: &[VisemeEvent], layout: &SpriteLayout, fps: f32, duration: f32) -> Vec<SpriteFrame> {
+    let frame_count = (duration * fps).ceil().max(0.0) as u32;
+    let mut index = 0;
+    (0..frame_count)
+        .map(|frame| {
+            let time = frame as f32 / fps;
+            while index < visemes.len() && visemes[index].end <= time {
+                index += 1;
+            }
+            let viseme = match visemes.get(index) {
+                Some(event) if event.start <= time => event.viseme.as_str(),
+                _ => SILENCE,
+            };
+            let sprite = layout.frame_for(viseme);
+            let offset = layout.offset_of(sprite);
+            SpriteFrame {
+                frame,
+                time: (time * 1000.0).round() / 1000.0,
+                viseme: viseme.to_string(),
+                sprite,
+                x: offset.map(|(x, _)| x),
+                y: offset.map(|(_, y)| y),
+            }
+        })
+        .collect()
+}
+
+/// Write a sprite track as CSV (`.csv` extension) or JSON (anything else)
+pub fn write_sprite_track(path: &str, frames: &[SpriteFrame], fps: f32) -> Result<(), Box<dyn std::error::Error>> {
+    if path.to_lowercase().ends_with(".csv") {
+        let mut csv = String::from("frame,time,viseme,sprite,x,y\n");
+        for f in frames {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{}",
+                f.frame,
+                f.time,
+                f.viseme,
+                f.sprite,
+                f.x.map(|x| x.to_string()).unwrap_or_default(),
+                f.y.map(|y| y.to_string()).unwrap_or_default()
+            );
+        }
+        std::fs::write(path, csv)?;
+    } else {
+        let json = serde_json::json!({ "fps": fps, "frames": frames });
+        std::fs::write(path, serde_json::to_string_pretty(&json)?)?;
+    }
+    Ok(())
+}
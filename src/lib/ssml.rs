@@ -0,0 +1,270 @@
+use crate::pauses::parse_pause;
+use std::time::Duration;
+
+/// Text spoken with one pitch and tempo. Both are relative to the voice's own settings and multiply
+/// them: pitch as a factor (2.0 = an octave up), tempo as elsewhere in the crate (2.0 = twice as slow).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpokenPart {
+    pub text: String,
+    pub pitch: f32,
+    pub tempo: f32,
+}
+
+/// A piece of an SSML document: speech, or silence from `<break>`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SsmlPart {
+    Speech(SpokenPart),
+    Break(Duration),
+}
+
+/// Pitch and tempo of an element, multiplied into those around it
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Prosody {
+    pitch: f32,
+    tempo: f32,
+}
+
+const NEUTRAL: Prosody = Prosody { pitch: 1.0, tempo: 1.0 };
+
+/// Whether the text looks like an SSML document (starts with `<speak`)
+pub fn is_ssml(text: &str) -> bool {
+    text.trim_start().starts_with("<speak")
+}
+
+/// Attributes of a tag, `name="value"` or `name='value'`
+fn attributes(mut rest: &str) -> Result<Vec<(String, String)>, String> {
+    let mut attributes = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Ok(attributes);
+        }
+        let (name, value) = rest.split_once('=').ok_or_else(|| format!("Expected name=\"value\" in tag, got '{}'", rest))?;
+        let value = value.trim_start();
+        let quote = value.chars().next().filter(|q| *q == '"' || *q == '\'').ok_or_else(|| format!("Unquoted value for '{}'", name.trim()))?;
+        let end = value[1..].find(quote).ok_or_else(|| format!("Unterminated value for '{}'", name.trim()))?;
+        attributes.push((name.trim().to_string(), decode_entities(&value[1..1 + end])));
+        rest = &value[end + 2..];
+    }
+}
+
+fn attribute<'a>(attributes: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&")
+}
+
+/// `+10%`, `-5%`, `150%`, or a bare factor, as a factor
+fn relative_factor(value: &str) -> Option<f32> {
+    let factor = match value.strip_suffix('%') {
+        Some(percent) if percent.starts_with(['+', '-']) => 1.0 + percent.parse::<f32>().ok()? / 100.0,
+        Some(percent) => percent.parse::<f32>().ok()? / 100.0,
+        None => value.parse::<f32>().ok()?,
+    };
+    (factor > 0.0).then_some(factor)
+}
+
+/// `<prosody rate>` as a tempo: SSML rates are speeds, so `slow` (or `80%`) is a tempo above 1
+fn parse_rate(value: &str) -> Result<f32, String> {
+    let speed = match value {
+        "x-slow" => 0.65,
+        "slow" => 0.8,
+        "medium" | "default" => 1.0,
+        "fast" => 1.25,
+        "x-fast" => 1.5,
+        _ => relative_factor(value).ok_or_else(|| format!("Invalid prosody rate '{}'", value))?,
+    };
+    Ok(1.0 / speed)
+}
+
+/// `<prosody pitch>` as a factor: a level, a percentage, or semitones (`+2st`)
+fn parse_pitch(value: &str) -> Result<f32, String> {
+    let factor = match value {
+        "x-low" => Some(0.75),
+        "low" => Some(0.88),
+        "medium" | "default" => Some(1.0),
+        "high" => Some(1.12),
+        "x-high" => Some(1.25),
+        _ => match value.strip_suffix("st") {
+            Some(semitones) => semitones.parse::<f32>().ok().map(|st| 2f32.powf(st / 12.0)),
+            None => relative_factor(value),
+        },
+    };
+    factor.ok_or_else(|| format!("Invalid prosody pitch '{}' (use a level, a percentage, or semitones like +2st)", value))
+}
+
+/// `<emphasis level>`: stressed words come out a little higher and slower, reduced ones lower and quicker
+fn emphasis(level: Option<&str>) -> Result<Prosody, String> {
+    match level.unwrap_or("moderate") {
+        "strong" => Ok(Prosody { pitch: 1.1, tempo: 1.15 }),
+        "moderate" => Ok(Prosody { pitch: 1.05, tempo: 1.08 }),
+        "none" => Ok(NEUTRAL),
+        "reduced" => Ok(Prosody { pitch: 0.95, tempo: 0.92 }),
+        other => Err(format!("Invalid emphasis level '{}'", other)),
+    }
+}
+
+/// `<break>`: an explicit `time`, or a `strength` (medium when neither is given)
+fn break_duration(attributes: &[(String, String)]) -> Result<Duration, String> {
+    if let Some(time) = attribute(attributes, "time") {
+        return parse_pause(time);
+    }
+    let ms = match attribute(attributes, "strength").unwrap_or("medium") {
+        "none" => 0,
+        "x-weak" => 100,
+        "weak" => 250,
+        "medium" => 400,
+        "strong" => 700,
+        "x-strong" => 1000,
+        other => return Err(format!("Invalid break strength '{}'", other)),
+    };
+    Ok(Duration::from_millis(ms))
+}
+
+/// `<say-as>` content rewritten so the voice reads it the requested way. Numbers are otherwise left for
+/// the number normalization that runs before synthesis.
+fn say_as(interpret_as: &str, text: &str) -> String {
+    let text = text.trim();
+    match interpret_as {
+        // Separate letters are read by name
+        "characters" | "spell-out" | "letters" => text.chars().filter(|c| !c.is_whitespace()).map(|c| c.to_uppercase().to_string()).collect::<Vec<_>>().join(" "),
+        "digits" | "telephone" => text.chars().filter(|c| c.is_ascii_digit()).map(|c| c.to_string()).collect::<Vec<_>>().join(" "),
+        "ordinal" if !text.is_empty() && text.chars().all(|c| c.is_ascii_digit()) => {
+            let n: u64 = text.parse().unwrap_or(0);
+            let suffix = match (n % 10, n % 100) {
+                (_, 11..=13) => "th",
+                (1, _) => "st",
+                (2, _) => "nd",
+                (3, _) => "rd",
+                _ => "th",
+            };
+            format!("{}{}", text, suffix)
+        }
+        _ => text.to_string(),
+    }
+}
+
+/// Parse the SSML subset this crate speaks: `<speak>`, `<break time|strength>`, `<prosody rate pitch>`,
+/// `<emphasis level>`, and `<say-as interpret-as>` (characters, spell-out, digits, telephone, ordinal,
+/// cardinal). Other elements (`<p>`, `<s>`, ...) are read for their text. Whitespace is collapsed;
+/// neighbouring speech with the same prosody is joined, and empty speech is dropped.
+pub fn parse_ssml(ssml: &str) -> Result<Vec<SsmlPart>, String> {
+    let mut parts: Vec<SsmlPart> = Vec::new();
+    // Open elements with the prosody in effect inside them
+    let mut open: Vec<(String, Prosody)> = Vec::new();
+    let mut prosody = NEUTRAL;
+    // `<say-as>` collects its text to rewrite at the closing tag
+    let mut say_as_text: Option<(String, String)> = None;
+
+    let push_text = |parts: &mut Vec<SsmlPart>, prosody: Prosody, text: &str| {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if words.is_empty() {
+            return;
+        }
+        let text = words.join(" ");
+        match parts.last_mut() {
+            Some(SsmlPart::Speech(last)) if last.pitch == prosody.pitch && last.tempo == prosody.tempo => {
+                last.text.push(' ');
+                last.text.push_str(&text);
+            }
+            _ => parts.push(SsmlPart::Speech(SpokenPart { text, pitch: prosody.pitch, tempo: prosody.tempo })),
+        }
+    };
+
+    let mut rest = ssml;
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            let text = decode_entities(rest);
+            match &mut say_as_text {
+                Some((_, buffer)) => buffer.push_str(&text),
+                None => push_text(&mut parts, prosody, &text),
+            }
+            break;
+        };
+        let text = decode_entities(&rest[..lt]);
+        match &mut say_as_text {
+            Some((_, buffer)) => buffer.push_str(&text),
+            None => push_text(&mut parts, prosody, &text),
+        }
+        rest = &rest[lt..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").ok_or("Unterminated comment")?;
+            rest = &comment[end + 3..];
+            continue;
+        }
+        let gt = rest.find('>').ok_or("Unterminated tag")?;
+        let tag = &rest[1..gt];
+        rest = &rest[gt + 1..];
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            let (expected, outer) = open.pop().ok_or_else(|| format!("Unexpected </{}>", name))?;
+            if expected != name {
+                return Err(format!("Expected </{}>, found </{}>", expected, name));
+            }
+            if name == "say-as"
+                && let Some((interpret_as, buffer)) = say_as_text.take()
+            {
+                push_text(&mut parts, prosody, &say_as(&interpret_as, &buffer));
+            }
+            prosody = outer;
+            continue;
+        }
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let (name, attrs) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let attrs = attributes(attrs)?;
+        let inner = match name {
+            "break" => {
+                parts.push(SsmlPart::Break(break_duration(&attrs)?));
+                continue;
+            }
+            "prosody" => {
+                let rate = attribute(&attrs, "rate").map(parse_rate).transpose()?.unwrap_or(1.0);
+                let pitch = attribute(&attrs, "pitch").map(parse_pitch).transpose()?.unwrap_or(1.0);
+                Prosody { pitch: prosody.pitch * pitch, tempo: prosody.tempo * rate }
+            }
+            "emphasis" => {
+                let emphasis = emphasis(attribute(&attrs, "level"))?;
+                Prosody { pitch: prosody.pitch * emphasis.pitch, tempo: prosody.tempo * emphasis.tempo }
+            }
+            "say-as" => {
+                if say_as_text.is_some() {
+                    return Err("<say-as> can't be nested".to_string());
+                }
+                let interpret_as = attribute(&attrs, "interpret-as").ok_or("<say-as> needs interpret-as")?;
+                say_as_text = Some((interpret_as.to_string(), String::new()));
+                prosody
+            }
+            _ => prosody,
+        };
+        if self_closing {
+            if name == "say-as" {
+                say_as_text = None;
+            }
+            continue;
+        }
+        open.push((name.to_string(), prosody));
+        prosody = inner;
+    }
+    if let Some((name, _)) = open.last() {
+        return Err(format!("<{}> is never closed", name));
+    }
+    Ok(parts)
+}
+
+/// The words of an SSML document as spoken, for display, limits, and alignment
+pub fn spoken_text(parts: &[SsmlPart]) -> String {
+    parts
+        .iter()
+        .filter_map(|part| match part {
+            SsmlPart::Speech(speech) => Some(speech.text.as_str()),
+            SsmlPart::Break(_) => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
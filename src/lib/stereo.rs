@@ -0,0 +1,114 @@
+//! Stereo output: speech is synthesized mono, then placed in a stereo field with a constant-power
+//! pan as it is exported or played, so characters in a multi-voice scene can stand apart.
+
+use std::f32::consts::FRAC_PI_4;
+use std::sync::Mutex;
+
+/// Channel layout of exported and played audio
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StereoOutput {
+    /// 1 for mono, 2 for stereo
+    pub channels: u16,
+    /// Position from -1.0 (hard left) through 0.0 (center) to 1.0 (hard right); ignored for mono
+    pub pan: f32,
+}
+
+impl Default for StereoOutput {
+    fn default() -> Self {
+        StereoOutput { channels: 1, pan: 0.0 }
+    }
+}
+
+impl StereoOutput {
+    /// A layout for `--channels` and `--pan`; a pan implies stereo, and is clamped to -1.0..=1.0
+    pub fn new(channels: Option<u16>, pan: Option<f32>) -> Result<StereoOutput, String> {
+        let channels = match (channels, pan) {
+            (Some(1), Some(_)) => return Err("--pan needs stereo output (--channels 2)".to_string()),
+            (Some(channels @ (1 | 2)), _) => channels,
+            (Some(channels), _) => return Err(format!("Invalid channel count: {} (expected 1 or 2)", channels)),
+            (None, Some(_)) => 2,
+            (None, None) => 1,
+        };
+        Ok(StereoOutput { channels, pan: pan.unwrap_or(0.0).clamp(-1.0, 1.0) })
+    }
+
+    pub fn is_stereo(&self) -> bool {
+        self.channels == 2
+    }
+
+    /// Mono `samples` in this layout: unchanged for mono, interleaved left/right pairs for stereo
+    pub fn interleave(&self, samples: &[f32]) -> Vec<f32> {
+        if !self.is_stereo() {
+            return samples.to_vec();
+        }
+        let (left, right) = pan_gains(self.pan);
+        samples.iter().flat_map(|s| [s * left, s * right]).collect()
+    }
+
+    /// Sidecar description, so changing the layout re-renders an export
+    pub fn describe(&self) -> String {
+        format!("channels={},pan={}", self.channels, self.pan)
+    }
+}
+
+/// Left and right gains for `pan` (-1.0..=1.0) under the constant-power law: both are cos(45°)
+/// at center, so a voice is equally loud wherever it is placed
+pub fn pan_gains(pan: f32) -> (f32, f32) {
+    let angle = (pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+    (angle.cos(), angle.sin())
+}
+
+/// Average interleaved frames of `channels` samples down to mono
+pub fn downmix(samples: &[f32], channels: u16) -> Vec<f32> {
+    if channels <= 1 {
+        return samples.to_vec();
+    }
+    samples.chunks(channels as usize).map(|frame| frame.iter().sum::<f32>() / frame.len() as f32).collect()
+}
+
+/// Set by `--channels` and `--pan`; read wherever audio is exported or played
+static STEREO_OUTPUT: Mutex<StereoOutput> = Mutex::new(StereoOutput { channels: 1, pan: 0.0 });
+
+pub fn set_stereo_output(output: StereoOutput) {
+    *STEREO_OUTPUT.lock().unwrap() = output;
+}
+
+pub fn stereo_output() -> StereoOutput {
+    *STEREO_OUTPUT.lock().unwrap()
+}
+
+/// Rewrite a mono WAV in the current layout (see [`set_stereo_output`]). Run before tags or
+/// markers are appended, since the file is written afresh. A no-op for mono output.
+pub fn pan_wav(path: &str) -> Result<(), hound::Error> {
+    let output = stereo_output();
+    if !output.is_stereo() || hound::WavReader::open(path)?.spec().channels != 1 {
+        return Ok(());
+    }
+    let (samples, sample_rate) = crate::read_wav(path)?;
+    let spec = hound::WavSpec { channels: 2, sample_rate, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for sample in output.interleave(&samples) {
+        writer.write_sample((sample * 32767.0).clamp(-32768.0, 32767.0) as i16)?;
+    }
+    writer.finalize()
+}
+
+/// Read back a WAV written by [`pan_wav`] as the mono speech it was panned from. Projecting each
+/// frame onto the pan gains recovers the original level exactly, where a plain downmix would not.
+pub fn read_unpanned_wav(path: &str) -> Result<(Vec<f32>, u32), hound::Error> {
+    let reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let samples = reader.into_samples::<i16>().map(|s| s.map(|s| s as f32 / 32767.0)).collect::<Result<Vec<f32>, _>>()?;
+    if spec.channels != 2 {
+        return Ok((downmix(&samples, spec.channels), spec.sample_rate));
+    }
+    let (left, right) = pan_gains(stereo_output().pan);
+    Ok((samples.chunks(2).map(|frame| frame[0] * left + frame.get(1).unwrap_or(&0.0) * right).collect(), spec.sample_rate))
+}
+
+#[cfg(feature = "full")]
+/// Mono `samples` as a rodio source in the current layout
+pub fn playback_buffer(samples: &[f32], sample_rate: u32) -> rodio::buffer::SamplesBuffer<f32> {
+    let output = stereo_output();
+    rodio::buffer::SamplesBuffer::new(output.channels, sample_rate, output.interleave(samples))
+}
@@ -0,0 +1,125 @@
+//! Synthesis that yields audio a sentence at a time, so playback or network streaming can start
+//! before the whole text is synthesized.
+
+use crate::backend;
+use crate::limits::{self, ResourceLimits};
+use crate::session::SynthesisHasher;
+use crate::{effects, emphasis, humanize, load_synthesizer, normalize, pause_markers, pauses, respelling, rules, time_stretch, true_pitch_shift, voice_sample_rate, PitchArg};
+
+type Chunk = Result<Vec<f32>, Box<dyn std::error::Error>>;
+
+/// Processed audio from [`synth_streaming`], one sentence per item, at [`SynthStream::sample_rate`].
+/// Resource limits are checked as the audio grows; the first error ends the stream.
+pub struct SynthStream {
+    pub sample_rate: u32,
+    chunks: Box<dyn Iterator<Item = Chunk>>,
+    pitch: f32,
+    tempo: f32,
+    limits: ResourceLimits,
+    total: usize,
+    /// Hash of the raw audio for the session log, recorded once the stream is exhausted
+    hasher: Option<SynthesisHasher>,
+    /// Sentences yielded so far, for `--humanize`
+    sentences: usize,
+    text: String,
+    voice: String,
+    done: bool,
+}
+
+impl SynthStream {
+    /// Pitch, tempo, `--humanize` variation, the voice effect, and the configured sentence pause for one sentence
+    fn process(&self, samples: Vec<f32>, index: usize) -> Vec<f32> {
+        let rate = self.sample_rate as usize;
+        let samples = humanize::vary(samples, self.sample_rate, index);
+        let samples = if (self.pitch - 1.0).abs() < 0.01 { samples } else { true_pitch_shift(&samples, rate, self.pitch) };
+        let samples = if (self.tempo - 1.0).abs() < 0.01 { samples } else { time_stretch(&samples, rate, self.tempo) };
+        let samples = effects::apply_effect(samples, self.sample_rate);
+        match pauses::pauses().sentence {
+            // The sentence's own trailing silence is replaced by the configured pause
+            Some(pause) => {
+                let mut trimmed = pauses::trim_silence(&samples, self.sample_rate, true, true).to_vec();
+                trimmed.resize(trimmed.len() + (pause.as_secs_f64() * self.sample_rate as f64) as usize, 0.0);
+                trimmed
+            }
+            None => samples,
+        }
+    }
+}
+
+impl Iterator for SynthStream {
+    type Item = Chunk;
+
+    fn next(&mut self) -> Option<Chunk> {
+        if self.done {
+            return None;
+        }
+        let raw = match self.chunks.next() {
+            Some(Ok(raw)) => raw,
+            Some(Err(e)) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+            None => {
+                self.done = true;
+                if let Some(hasher) = self.hasher.take() {
+                    hasher.record(&self.text, &self.voice, self.sample_rate);
+                }
+                return None;
+            }
+        };
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&raw);
+        }
+        self.sentences += 1;
+        let processed = self.process(raw, self.sentences - 1);
+        // Counted after the stretch, which a slow tempo makes longer than what was synthesized
+        self.total += processed.len();
+        if let Err(e) = self.limits.check_samples(self.total, self.sample_rate) {
+            self.done = true;
+            return Some(Err(e.into()));
+        }
+        Some(Ok(processed))
+    }
+}
+
+/// Synthesize `text` a sentence at a time, with `pitch` and `tempo` applied to each sentence as it's
+/// ready. Text rules, number normalization, respellings, and `--sentence-pause` apply as in
+/// [`crate::synth_with_voice_config`]; paragraph pauses and `@` directives don't, and pause markers and
+/// emphasis markup are left out.
+///
+/// Loading the voice (and downloading it if needed) happens here, so the first chunk only waits for
+/// its own sentence.
+pub fn synth_streaming(text: &str, voice_id: &str, pitch: &PitchArg, tempo: f32) -> Result<SynthStream, Box<dyn std::error::Error>> {
+    let limits = limits::limits();
+    limits.check_text(text)?;
+    let text = &emphasis::strip_emphasis(&pause_markers::strip_markers(text));
+    let chunks: Box<dyn Iterator<Item = Chunk>> = if let Some(backend) = backend::registered_backend(voice_id) {
+        // Registered backends synthesize the text in one piece
+        let samples = backend.synthesize(&respelling::strip_respellings(text))?;
+        Box::new(std::iter::once(Ok(samples)))
+    } else {
+        let synth = load_synthesizer(voice_id)?;
+        if respelling::has_respellings(text) {
+            // Respelled words are phonemized up front; the audio still comes a sentence at a time
+            let model = synth.clone_model();
+            let sentences = respelling::phonemize_with_respellings(&synth, text, voice_id)?;
+            Box::new(sentences.into_iter().map(move |phonemes| Ok(model.speak_one_sentence(phonemes)?.into_vec())))
+        } else {
+            let normalized = normalize::normalize_for_voice(&rules::apply_rules(text), voice_id);
+            Box::new(synth.synthesize_lazy(normalized, None)?.map(|chunk| Ok(chunk?.into_vec())))
+        }
+    };
+    Ok(SynthStream {
+        sample_rate: voice_sample_rate(voice_id),
+        chunks,
+        pitch: pitch.as_factor(),
+        tempo,
+        limits,
+        total: 0,
+        hasher: Some(SynthesisHasher::default()),
+        sentences: 0,
+        text: text.to_string(),
+        voice: voice_id.to_string(),
+        done: false,
+    })
+}
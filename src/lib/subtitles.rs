@@ -0,0 +1,125 @@
+//! SRT and WebVTT captions from word timings, so narrated videos get subtitle files for free.
+//! Words keep the punctuation and capitalization of the source text; cues break at sentence ends
+//! and are wrapped to at most two lines of a configurable length.
+
+use crate::karaoke::{build_karaoke, slice_utf16, KaraokeWord};
+use crate::WordTiming;
+use std::path::Path;
+
+/// Default longest caption line, the usual broadcast limit
+pub const DEFAULT_MAX_LINE_CHARS: usize = 42;
+/// Lines shown at once; a cue that would need more is split
+const MAX_CUE_LINES: usize = 2;
+/// A pause this long (in seconds) between words starts a new cue even mid-sentence
+const CUE_GAP: f32 = 1.0;
+
+/// Caption file format, picked by extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    WebVtt,
+}
+
+impl SubtitleFormat {
+    /// `.srt` or `.vtt` (any case)
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "srt" => Some(SubtitleFormat::Srt),
+            "vtt" => Some(SubtitleFormat::WebVtt),
+            _ => None,
+        }
+    }
+}
+
+/// Check that a `--subtitles` path names a format, e.g. `captions.srt`
+pub fn parse_subtitles_path(path: &str) -> Result<String, String> {
+    match SubtitleFormat::from_path(Path::new(path)) {
+        Some(_) => Ok(path.to_string()),
+        None => Err(format!("Subtitle file '{}' needs a .srt or .vtt extension", path)),
+    }
+}
+
+/// One caption: up to two lines shown from `start` to `end` seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub start: f32,
+    pub end: f32,
+    pub lines: Vec<String>,
+}
+
+/// Group timed words into cues of at most two lines of `max_line_chars`. A word longer than a line
+/// gets a line to itself.
+pub fn build_cues(text: &str, timings: &[WordTiming], max_line_chars: usize) -> Vec<Cue> {
+    let document = build_karaoke(text, timings);
+    let mut cues = Vec::new();
+    let mut lines: Vec<String> = Vec::new();
+    let mut span: Option<(f32, f32)> = None;
+    let mut previous: Option<&KaraokeWord> = None;
+    for word in &document.words {
+        // The source text's spelling, with its punctuation; the recognized word if it didn't match
+        let source = slice_utf16(text, word.char_offsets[0], word.char_offsets[1]);
+        let display = if source.trim().is_empty() { word.word.trim().to_string() } else { source };
+        let breaks_cue = previous.is_some_and(|p| p.sentence != word.sentence || word.start - p.end >= CUE_GAP);
+        let fits = lines.last().is_some_and(|line| line.chars().count() + 1 + display.chars().count() <= max_line_chars);
+        if breaks_cue || (!fits && lines.len() == MAX_CUE_LINES) {
+            flush(&mut cues, &mut lines, &mut span);
+        }
+        match lines.last_mut() {
+            Some(line) if fits && !breaks_cue => {
+                line.push(' ');
+                line.push_str(&display);
+            }
+            _ => lines.push(display),
+        }
+        let (start, _) = span.unwrap_or((word.start, word.end));
+        span = Some((start, word.end));
+        previous = Some(word);
+    }
+    flush(&mut cues, &mut lines, &mut span);
+    cues
+}
+
+fn flush(cues: &mut Vec<Cue>, lines: &mut Vec<String>, span: &mut Option<(f32, f32)>) {
+    if let Some((start, end)) = span.take().filter(|_| !lines.is_empty()) {
+        cues.push(Cue { start, end: end.max(start), lines: std::mem::take(lines) });
+    }
+    lines.clear();
+}
+
+/// `HH:MM:SS,mmm` for SRT, `HH:MM:SS.mmm` for WebVTT
+fn timestamp(seconds: f32, separator: char) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!("{:02}:{:02}:{:02}{}{:03}", millis / 3_600_000, millis / 60_000 % 60, millis / 1000 % 60, separator, millis % 1000)
+}
+
+/// Numbered SubRip cues
+pub fn format_srt(cues: &[Cue]) -> String {
+    cues.iter()
+        .enumerate()
+        .map(|(i, cue)| format!("{}\n{} --> {}\n{}\n", i + 1, timestamp(cue.start, ','), timestamp(cue.end, ','), cue.lines.join("\n")))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A WebVTT file with one cue per caption
+pub fn format_webvtt(cues: &[Cue]) -> String {
+    let mut out = "WEBVTT\n".to_string();
+    for cue in cues {
+        out.push_str(&format!("\n{} --> {}\n{}\n", timestamp(cue.start, '.'), timestamp(cue.end, '.'), cue.lines.join("\n")));
+    }
+    out
+}
+
+/// Write captions for `text` from a lipsync JSON file to `output_path`, in the format its extension
+/// names; returns the number of cues
+pub fn write_subtitles(text: &str, lipsync_json: &str, output_path: &str, max_line_chars: usize) -> Result<usize, Box<dyn std::error::Error>> {
+    let format = SubtitleFormat::from_path(Path::new(output_path)).ok_or_else(|| format!("{} is not a .srt or .vtt file", output_path))?;
+    let timings = crate::read_word_timings(lipsync_json)?;
+    let cues = build_cues(text, &timings, max_line_chars);
+    let contents = match format {
+        SubtitleFormat::Srt => format_srt(&cues),
+        SubtitleFormat::WebVtt => format_webvtt(&cues),
+    };
+    std::fs::write(output_path, contents)?;
+    Ok(cues.len())
+}
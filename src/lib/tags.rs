@@ -0,0 +1,168 @@
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Descriptive tags plus the synthesis parameters that produced an exported file
+#[derive(Debug, Clone, Default)]
+pub struct AudioTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub comment: Option<String>,
+    /// Synthesis parameters as ordered key/value pairs (voice, pitch, tempo, ...)
+    pub params: Vec<(String, String)>,
+}
+
+impl AudioTags {
+    /// Tags recording the core synthesis parameters of an export
+    pub fn for_synthesis(voice: &str, pitch: f32, tempo: f32) -> Self {
+        AudioTags {
+            params: vec![
+                ("voice".to_string(), voice.to_string()),
+                ("pitch".to_string(), pitch.to_string()),
+                ("tempo".to_string(), tempo.to_string()),
+            ],
+            ..Default::default()
+        }
+    }
+
+    /// Add a synthesis parameter
+    pub fn with_param(mut self, key: &str, value: impl ToString) -> Self {
+        self.params.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// The synthesis parameters rendered as `key=value; key=value`
+    pub fn params_string(&self) -> String {
+        self.params.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("; ")
+    }
+
+    /// The comment to embed: the user comment (if any) followed by the synthesis parameters
+    pub fn full_comment(&self) -> String {
+        match &self.comment {
+            Some(comment) if !self.params.is_empty() => format!("{} ({})", comment, self.params_string()),
+            Some(comment) => comment.clone(),
+            None => self.params_string(),
+        }
+    }
+
+    /// Sidecar descriptions of the descriptive tags, so retagging an export re-renders it. The
+    /// synthesis parameters are left out; the sidecar records those itself.
+    pub fn describe(&self) -> Vec<String> {
+        [("title", &self.title), ("artist", &self.artist), ("comment", &self.comment)]
+            .into_iter()
+            .filter_map(|(key, value)| value.as_ref().map(|value| format!("{}={}", key, value)))
+            .collect()
+    }
+
+    /// Software identifier embedded alongside the tags
+    pub fn software() -> String {
+        format!("text-to-face {}", env!("CARGO_PKG_VERSION"))
+    }
+
+    /// Tags as generic `key=value` pairs (ID3/Vorbis comment/MP4 naming as understood by ffmpeg)
+    pub fn as_key_values(&self) -> Vec<(String, String)> {
+        let mut out = Vec::new();
+        if let Some(title) = &self.title {
+            out.push(("title".to_string(), title.clone()));
+        }
+        if let Some(artist) = &self.artist {
+            out.push(("artist".to_string(), artist.clone()));
+        }
+        out.push(("comment".to_string(), self.full_comment()));
+        out.push(("encoder".to_string(), Self::software()));
+        out
+    }
+
+    /// `-metadata key=value` arguments for ffmpeg-based encoders
+    pub fn ffmpeg_args(&self) -> Vec<String> {
+        self.as_key_values()
+            .into_iter()
+            .flat_map(|(k, v)| vec!["-metadata".to_string(), format!("{}={}", k, v)])
+            .collect()
+    }
+}
+
+pub(crate) fn push_chunk(buf: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+    buf.extend_from_slice(id);
+    buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    buf.extend_from_slice(data);
+    // RIFF chunks are word aligned
+    if data.len() % 2 == 1 {
+        buf.push(0);
+    }
+}
+
+fn info_string(value: &str) -> Vec<u8> {
+    let mut bytes = value.as_bytes().to_vec();
+    bytes.push(0);
+    bytes
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Build the `LIST/INFO` chunk for a WAV file
+fn build_info_chunk(tags: &AudioTags) -> Vec<u8> {
+    let mut info = b"INFO".to_vec();
+    if let Some(title) = &tags.title {
+        push_chunk(&mut info, b"INAM", &info_string(title));
+    }
+    if let Some(artist) = &tags.artist {
+        push_chunk(&mut info, b"IART", &info_string(artist));
+    }
+    push_chunk(&mut info, b"ICMT", &info_string(&tags.full_comment()));
+    push_chunk(&mut info, b"ISFT", &info_string(&AudioTags::software()));
+    let mut chunk = Vec::new();
+    push_chunk(&mut chunk, b"LIST", &info);
+    chunk
+}
+
+/// Build the `iXML` chunk for a WAV file, carrying the synthesis parameters as user data
+fn build_ixml_chunk(tags: &AudioTags) -> Vec<u8> {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<BWFXML>\n<IXML_VERSION>2.0</IXML_VERSION>\n");
+    if let Some(title) = &tags.title {
+        xml.push_str(&format!("<PROJECT>{}</PROJECT>\n", xml_escape(title)));
+    }
+    xml.push_str(&format!("<NOTE>{}</NOTE>\n", xml_escape(&tags.full_comment())));
+    xml.push_str("<USER>\n");
+    for (key, value) in &tags.params {
+        xml.push_str(&format!("<{}>{}</{}>\n", key.to_uppercase(), xml_escape(value), key.to_uppercase()));
+    }
+    xml.push_str(&format!("<SOFTWARE>{}</SOFTWARE>\n", xml_escape(&AudioTags::software())));
+    xml.push_str("</USER>\n</BWFXML>\n");
+    let mut chunk = Vec::new();
+    push_chunk(&mut chunk, b"iXML", xml.as_bytes());
+    chunk
+}
+
+/// Append `LIST/INFO` and `iXML` chunks to an existing WAV file and fix up the RIFF size
+pub fn tag_wav(path: &str, tags: &AudioTags) -> std::io::Result<()> {
+    let mut chunks = build_info_chunk(tags);
+    chunks.extend(build_ixml_chunk(tags));
+    append_chunks(path, &chunks)
+}
+
+/// Append already-built chunks to an existing WAV file and fix up the RIFF size
+pub(crate) fn append_chunks(path: &str, chunks: &[u8]) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let mut header = [0u8; 12];
+    file.read_exact(&mut header)?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{} is not a RIFF/WAVE file", path)));
+    }
+
+    let end = file.seek(SeekFrom::End(0))?;
+    // Keep the appended chunks word aligned even if the data chunk was odd-sized
+    if end % 2 == 1 {
+        file.write_all(&[0])?;
+    }
+    file.write_all(chunks)?;
+    let riff_size = (file.seek(SeekFrom::End(0))? - 8) as u32;
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&riff_size.to_le_bytes())?;
+    Ok(())
+}
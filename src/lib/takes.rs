@@ -0,0 +1,89 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// How one take's synthesis differs from the voice's defaults, as multipliers of the model's
+/// inference settings. Take 1 is always the voice's own delivery.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TakeVariation {
+    pub take: usize,
+    /// Multiplier for `noise_scale` (variation in voice quality and intonation)
+    pub noise_scale: f32,
+    /// Multiplier for `noise_w` (variation in phoneme durations)
+    pub noise_w: f32,
+    /// Multiplier for `length_scale` (overall pace)
+    pub length_scale: f32,
+}
+
+impl TakeVariation {
+    /// `take=2 noise_scale=x1.12 noise_w=x0.85 length_scale=x1.03`, for logs and sidecars
+    pub fn describe(&self) -> String {
+        format!("take={} noise_scale=x{:.2} noise_w=x{:.2} length_scale=x{:.2}", self.take, self.noise_scale, self.noise_w, self.length_scale)
+    }
+
+    /// Seed the variation is drawn from, so a take can be reproduced from its sidecar
+    pub fn seed(&self) -> u64 {
+        self.take as u64
+    }
+}
+
+/// SplitMix64: a tiny PRNG, so take N always gets the same variation
+pub(crate) fn split_mix(state: &mut u64) -> f32 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    ((z ^ (z >> 31)) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// The variation for take `take` (1-based). Later takes vary the noise by up to ±25–30% and the
+/// pace by up to ±5%: enough to hear a different reading without changing the character.
+pub fn take_variation(take: usize) -> TakeVariation {
+    if take <= 1 {
+        return TakeVariation { take: 1, noise_scale: 1.0, noise_w: 1.0, length_scale: 1.0 };
+    }
+    // The same seed as [`TakeVariation::seed`]
+    let mut state = take as u64;
+    let mut jitter = |amount: f32| 1.0 + (split_mix(&mut state) * 2.0 - 1.0) * amount;
+    TakeVariation { take, noise_scale: jitter(0.25), noise_w: jitter(0.3), length_scale: jitter(0.05) }
+}
+
+/// `dir/line.wav` becomes `dir/line_take2.wav`
+pub fn take_path(path: &Path, take: usize) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => path.with_file_name(format!("{}_take{}.{}", stem, take, extension)),
+        None => path.with_file_name(format!("{}_take{}", stem, take)),
+    }
+}
+
+/// Variation applied to voices as they're loaded; `None` synthesizes with the voice's defaults
+static VARIATION: Mutex<Option<TakeVariation>> = Mutex::new(None);
+
+pub fn set_variation(variation: Option<TakeVariation>) {
+    *VARIATION.lock().unwrap() = variation;
+}
+
+pub fn variation() -> Option<TakeVariation> {
+    *VARIATION.lock().unwrap()
+}
+
+#[cfg(feature = "full")]
+/// Scale a freshly loaded voice's inference settings by the current [`variation`], if any
+pub fn apply_variation(synth: &piper_rs::synth::PiperSpeechSynthesizer) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(variation) = variation() else {
+        return Ok(());
+    };
+    let model = synth.clone_model();
+    let defaults = model.get_default_synthesis_config()?;
+    let current = model.get_fallback_synthesis_config()?;
+    let (Some(defaults), Some(current)) = (defaults.downcast_ref::<piper_rs::PiperSynthesisConfig>(), current.downcast_ref::<piper_rs::PiperSynthesisConfig>()) else {
+        return Err("Voice doesn't support synthesis settings".into());
+    };
+    model.set_fallback_synthesis_config(&piper_rs::PiperSynthesisConfig {
+        speaker: current.speaker,
+        noise_scale: defaults.noise_scale * variation.noise_scale,
+        noise_w: defaults.noise_w * variation.noise_w,
+        length_scale: defaults.length_scale * variation.length_scale,
+    })?;
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use serde_json::Value;
+
+/// Part of a rendered line to keep: everything from `start` seconds, for at most `max_duration` seconds
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TrimRange {
+    pub start: f32,
+    pub max_duration: Option<f32>,
+}
+
+/// Default `--trim-silence` threshold: windows quieter than this (in dBFS) count as silence
+pub const DEFAULT_SILENCE_THRESHOLD_DB: f32 = -50.0;
+/// Audio kept either side of the speech, so onsets and decays aren't clipped
+const SILENCE_PADDING: f32 = 0.02;
+/// Window over which loudness is measured when looking for silence
+const SILENCE_WINDOW: f32 = 0.01;
+
+impl TrimRange {
+    /// The range from the first to the last window louder than `threshold_db` (dBFS), padded by a
+    /// few milliseconds; the whole audio if nothing is that loud
+    pub fn around_sound(samples: &[f32], sample_rate: u32, threshold_db: f32) -> TrimRange {
+        let window = ((sample_rate as f32 * SILENCE_WINDOW) as usize).max(1);
+        let threshold = 10f32.powf(threshold_db / 20.0);
+        let loud = |chunk: &[f32]| (chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32).sqrt() > threshold;
+        let windows: Vec<bool> = samples.chunks(window).map(loud).collect();
+        let (Some(first), Some(last)) = (windows.iter().position(|l| *l), windows.iter().rposition(|l| *l)) else {
+            return TrimRange::default();
+        };
+        let seconds = |sample: usize| sample as f32 / sample_rate as f32;
+        let start = (seconds(first * window) - SILENCE_PADDING).max(0.0);
+        let end = (seconds(((last + 1) * window).min(samples.len())) + SILENCE_PADDING).min(seconds(samples.len()));
+        TrimRange { start, max_duration: (end < seconds(samples.len())).then_some(end - start) }
+    }
+
+    /// Whether this keeps the whole audio
+    pub fn is_whole(&self) -> bool {
+        self.start <= 0.0 && self.max_duration.is_none()
+    }
+
+    /// `trim=1.50+2.00` (or `trim=1.50+` with no duration limit), for sidecars
+    pub fn describe(&self) -> String {
+        format!("trim={:.2}+{}", self.start, self.max_duration.map(|d| format!("{:.2}", d)).unwrap_or_default())
+    }
+
+    /// The kept samples of a mono signal
+    pub fn samples<'a>(&self, samples: &'a [f32], sample_rate: u32) -> &'a [f32] {
+        let first = ((self.start.max(0.0) * sample_rate as f32) as usize).min(samples.len());
+        let last = match self.max_duration {
+            Some(duration) => (first + (duration.max(0.0) * sample_rate as f32) as usize).min(samples.len()),
+            None => samples.len(),
+        };
+        &samples[first..last]
+    }
+
+    /// Shift a lipsync JSON's timings to the trimmed audio. Words, segments, and phones outside the kept
+    /// range are dropped, and those overlapping an edge are clipped to it. Entries without timings are kept.
+    pub fn lipsync_json(&self, json: &mut Value, duration: f32) {
+        let end = self.max_duration.map_or(duration, |d| duration.min(self.start + d));
+        for key in ["segments", "word_segments"] {
+            if let Some(entries) = json.get_mut(key).and_then(|e| e.as_array_mut()) {
+                clip_entries(entries, self.start as f64, end as f64);
+            }
+        }
+        // Segment text follows the words that are left
+        for segment in json.get_mut("segments").and_then(|s| s.as_array_mut()).into_iter().flatten() {
+            let text = segment.get("words").and_then(|w| w.as_array()).map(|words| words.iter().filter_map(|w| w.get("word")?.as_str()).collect::<Vec<_>>().join(" "));
+            if let (Some(text), Some(object)) = (text, segment.as_object_mut()) {
+                object.insert("text".to_string(), Value::from(text));
+            }
+        }
+    }
+}
+
+/// `samples` without their leading and trailing silence (see [`TrimRange::around_sound`])
+pub fn trim_silence(samples: &[f32], sample_rate: u32, threshold_db: f32) -> &[f32] {
+    TrimRange::around_sound(samples, sample_rate, threshold_db).samples(samples, sample_rate)
+}
+
+/// Keep entries overlapping `start..end`, with times relative to `start`, recursing into `words` and `phones`
+fn clip_entries(entries: &mut Vec<Value>, start: f64, end: f64) {
+    entries.retain_mut(|entry| {
+        let time = |key: &str| entry.get(key).and_then(|t| t.as_f64());
+        if let (Some(entry_start), Some(entry_end)) = (time("start"), time("end")) {
+            if entry_end <= start || entry_start >= end {
+                return false;
+            }
+            // Millisecond precision, so shifted times don't pick up float noise
+            let shift = |t: f64| ((t.clamp(start, end) - start) * 1000.0).round() / 1000.0;
+            entry["start"] = Value::from(shift(entry_start));
+            entry["end"] = Value::from(shift(entry_end));
+        }
+        for key in ["words", "phones"] {
+            if let Some(children) = entry.get_mut(key).and_then(|c| c.as_array_mut()) {
+                clip_entries(children, start, end);
+            }
+        }
+        true
+    });
+}
+
+#[cfg(feature = "full")]
+/// Trim the leading and trailing silence off a rendered WAV in place, shifting its lipsync JSON if given.
+/// Returns the range that was kept.
+pub fn trim_silence_export(wav_path: &str, lipsync_json: Option<&str>, threshold_db: f32) -> Result<TrimRange, Box<dyn std::error::Error>> {
+    let (samples, sample_rate) = crate::read_wav(wav_path)?;
+    let range = TrimRange::around_sound(&samples, sample_rate, threshold_db);
+    if !range.is_whole() {
+        trim_export(wav_path, lipsync_json, &range)?;
+    }
+    Ok(range)
+}
+
+#[cfg(feature = "full")]
+/// Trim a rendered WAV in place, and its lipsync JSON if given, returning the new duration in seconds
+pub fn trim_export(wav_path: &str, lipsync_json: Option<&str>, range: &TrimRange) -> Result<f32, Box<dyn std::error::Error>> {
+    let (samples, sample_rate) = crate::read_wav(wav_path)?;
+    let duration = samples.len() as f32 / sample_rate as f32;
+    if range.start >= duration {
+        return Err(format!("--start-at {:.2}s is past the end of the audio ({:.2}s)", range.start, duration).into());
+    }
+    let kept = range.samples(&samples, sample_rate);
+    crate::write_wav(wav_path, kept, sample_rate)?;
+    if let Some(json_path) = lipsync_json {
+        let mut json: Value = serde_json::from_str(&std::fs::read_to_string(json_path)?)?;
+        range.lipsync_json(&mut json, duration);
+        std::fs::write(json_path, serde_json::to_string_pretty(&json)?)?;
+    }
+    Ok(kept.len() as f32 / sample_rate as f32)
+}
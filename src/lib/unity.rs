@@ -0,0 +1,130 @@
+//! Unity animation clips of the lipsync: blendshape weight keyframes as a `.anim` asset, which
+//! Unity imports as is, or as JSON for loading at runtime.
+
+use crate::gltf::{weight_keyframes, BlendshapeSet};
+use crate::visemes::VisemeEvent;
+use serde::Serialize;
+use std::fmt::Write as _;
+
+/// Unity's class ID for `SkinnedMeshRenderer`, which owns the `blendShape.*` properties
+const SKINNED_MESH_RENDERER: u32 = 137;
+/// Linear in and out tangents, broken so each side keeps its own slope
+const LINEAR_TANGENTS: u32 = 69;
+
+/// One blendshape weight at a time, 0 to 100 as Unity's blendshape weights are
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct UnityKey {
+    pub time: f32,
+    pub value: f32,
+}
+
+/// The keyframes of one blendshape
+#[derive(Debug, Clone, Serialize)]
+pub struct UnityCurve {
+    #[serde(rename = "blendShape")]
+    pub blend_shape: String,
+    pub keys: Vec<UnityKey>,
+}
+
+/// An animation clip keying the blendshapes of the mesh at `mesh_path`, relative to the Animator
+/// (empty for a mesh on the Animator's own object)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnityClip {
+    pub name: String,
+    pub frame_rate: f32,
+    pub length: f32,
+    pub mesh_path: String,
+    pub curves: Vec<UnityCurve>,
+}
+
+/// Build a clip from a viseme track, lasting `duration` seconds. Keys are shared with the glTF export
+/// (see [`weight_keyframes`]); a blendshape's key is skipped where it holds the same weight on both sides.
+pub fn unity_clip(visemes: &[VisemeEvent], set: BlendshapeSet, duration: f32, fps: f32, name: &str, mesh_path: &str) -> UnityClip {
+    let keys = weight_keyframes(visemes, set, duration);
+    let curves = set
+        .target_names()
+        .into_iter()
+        .enumerate()
+        .map(|(target, blend_shape)| {
+            let weight = |i: usize| keys[i].1[target] * 100.0;
+            let keys = (0..keys.len())
+                .filter(|&i| i == 0 || i + 1 == keys.len() || weight(i) != weight(i - 1) || weight(i) != weight(i + 1))
+                .map(|i| UnityKey { time: keys[i].0, value: weight(i) })
+                .collect();
+            UnityCurve { blend_shape, keys }
+        })
+        .collect();
+    let length = keys.last().map_or(duration, |(time, _)| *time);
+    UnityClip { name: name.to_string(), frame_rate: fps, length, mesh_path: mesh_path.to_string(), curves }
+}
+
+/// Slope from one key to another, for linear tangents
+fn slope(from: &UnityKey, to: &UnityKey) -> f32 {
+    if to.time > from.time { (to.value - from.value) / (to.time - from.time) } else { 0.0 }
+}
+
+/// A curve entry as it appears under `m_FloatCurves` and `m_EditorCurves`
+fn write_curve(yaml: &mut String, curve: &UnityCurve, mesh_path: &str) {
+    yaml.push_str("  - curve:\n      serializedVersion: 2\n      m_Curve:\n");
+    for (i, key) in curve.keys.iter().enumerate() {
+        let in_slope = i.checked_sub(1).map_or(0.0, |p| slope(&curve.keys[p], key));
+        let out_slope = curve.keys.get(i + 1).map_or(0.0, |next| slope(key, next));
+        let _ = write!(
+            yaml,
+            "      - serializedVersion: 3\n        time: {}\n        value: {}\n        inSlope: {}\n        outSlope: {}\n        tangentMode: {}\n        weightedMode: 0\n        inWeight: 0.33333334\n        outWeight: 0.33333334\n",
+            key.time, key.value, in_slope, out_slope, LINEAR_TANGENTS
+        );
+    }
+    let _ = write!(
+        yaml,
+        "      m_PreInfinity: 2\n      m_PostInfinity: 2\n      m_RotationOrder: 4\n    attribute: blendShape.{}\n    path: {}\n    classID: {}\n    script: {{fileID: 0}}\n",
+        curve.blend_shape,
+        yaml_string(mesh_path),
+        SKINNED_MESH_RENDERER
+    );
+}
+
+/// A YAML scalar, quoted when Unity's parser would otherwise misread it
+fn yaml_string(value: &str) -> String {
+    if value.is_empty() || value.contains(|c: char| ":#{}[],&*!|>'\"%@`".contains(c)) || value.starts_with(' ') || value.ends_with(' ') {
+        format!("'{}'", value.replace('\'', "''"))
+    } else {
+        value.to_string()
+    }
+}
+
+/// The clip as a Unity `.anim` asset (`AnimationClip` in Unity's YAML), for a Mecanim Animator
+pub fn anim_yaml(clip: &UnityClip) -> String {
+    let mut curves = String::new();
+    for curve in &clip.curves {
+        write_curve(&mut curves, curve, &clip.mesh_path);
+    }
+    let curves = if clip.curves.is_empty() { " []\n".to_string() } else { format!("\n{}", curves) };
+    let mut yaml = String::from("%YAML 1.1\n%TAG !u! tag:unity3d.com,2011:\n--- !u!74 &7400000\nAnimationClip:\n");
+    let _ = write!(
+        yaml,
+        "  m_ObjectHideFlags: 0\n  m_CorrespondingSourceObject: {{fileID: 0}}\n  m_PrefabInstance: {{fileID: 0}}\n  m_PrefabAsset: {{fileID: 0}}\n  m_Name: {}\n  serializedVersion: 6\n  m_Legacy: 0\n  m_Compressed: 0\n  m_UseHighQualityCurve: 1\n  m_RotationCurves: []\n  m_CompressedRotationCurves: []\n  m_EulerCurves: []\n  m_PositionCurves: []\n  m_ScaleCurves: []\n  m_FloatCurves:{}  m_PPtrCurves: []\n  m_SampleRate: {}\n  m_WrapMode: 0\n",
+        yaml_string(&clip.name),
+        curves,
+        clip.frame_rate
+    );
+    yaml.push_str("  m_Bounds:\n    m_Center: {x: 0, y: 0, z: 0}\n    m_Extent: {x: 0, y: 0, z: 0}\n  m_ClipBindingConstant:\n    genericBindings: []\n    pptrCurveMapping: []\n");
+    let _ = write!(
+        yaml,
+        "  m_AnimationClipSettings:\n    serializedVersion: 2\n    m_AdditiveReferencePoseClip: {{fileID: 0}}\n    m_AdditiveReferencePoseTime: 0\n    m_StartTime: 0\n    m_StopTime: {}\n    m_OrientationOffsetY: 0\n    m_Level: 0\n    m_CycleOffset: 0\n    m_HasAdditiveReferencePose: 0\n    m_LoopTime: 0\n    m_LoopBlend: 0\n    m_LoopBlendOrientation: 0\n    m_LoopBlendPositionY: 0\n    m_LoopBlendPositionXZ: 0\n    m_KeepOriginalOrientation: 0\n    m_KeepOriginalPositionY: 1\n    m_KeepOriginalPositionXZ: 0\n    m_HeightFromFeet: 0\n    m_Mirror: 0\n",
+        clip.length
+    );
+    let _ = write!(yaml, "  m_EditorCurves:{}  m_EulerEditorCurves: []\n  m_HasGenericRootTransform: 0\n  m_HasMotionFloatCurves: 0\n  m_Events: []\n", curves);
+    yaml
+}
+
+/// Write the clip as a Unity `.anim` asset, or as JSON for any other extension
+pub fn write_unity_animation(path: &str, clip: &UnityClip) -> Result<(), Box<dyn std::error::Error>> {
+    if path.to_lowercase().ends_with(".anim") {
+        std::fs::write(path, anim_yaml(clip))?;
+    } else {
+        std::fs::write(path, serde_json::to_string_pretty(clip)?)?;
+    }
+    Ok(())
+}
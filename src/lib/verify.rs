@@ -0,0 +1,149 @@
+use crate::WordTiming;
+use serde::Serialize;
+
+/// How a word differs between the input text and what WhisperX heard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MismatchKind {
+    /// A different word was heard in place of the input word
+    Substituted,
+    /// The input word wasn't heard (skipped or swallowed)
+    Missing,
+    /// A word was heard that isn't in the input
+    Inserted,
+}
+
+/// One differing word, with the time range it occurs at in the audio
+#[derive(Debug, Clone, Serialize)]
+pub struct Mismatch {
+    pub kind: MismatchKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recognized: Option<String>,
+    pub start: f32,
+    pub end: f32,
+}
+
+/// Comparison of the input text against the recognized transcript
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptReport {
+    pub input_words: usize,
+    pub recognized_words: usize,
+    pub matched_words: usize,
+    /// (substitutions + deletions + insertions) / input words
+    pub word_error_rate: f32,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl TranscriptReport {
+    pub fn is_clean(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Lowercase alphanumerics only, so punctuation and case don't count as mismatches
+fn normalize(word: &str) -> String {
+    word.chars().filter(|c| c.is_alphanumeric()).flat_map(|c| c.to_lowercase()).collect()
+}
+
+/// Align the input words against the recognized words (minimum edit distance) and report the differences
+pub fn verify_transcript(text: &str, recognized: &[WordTiming]) -> TranscriptReport {
+    let expected: Vec<&str> = text.split_whitespace().filter(|w| !normalize(w).is_empty()).collect();
+    let heard: Vec<&WordTiming> = recognized.iter().filter(|w| !normalize(&w.word).is_empty()).collect();
+    let expected_norm: Vec<String> = expected.iter().map(|w| normalize(w)).collect();
+    let heard_norm: Vec<String> = heard.iter().map(|w| normalize(&w.word)).collect();
+    let (n, m) = (expected.len(), heard.len());
+
+    // Edit-distance table, keeping only the chosen step per cell: 0 = match/substitute, 1 = missing, 2 = inserted
+    let mut steps = vec![0u8; (n + 1) * (m + 1)];
+    let mut prev: Vec<u32> = (0..=m as u32).collect();
+    steps[1..=m].fill(2);
+    for i in 1..=n {
+        let mut row = vec![i as u32; m + 1];
+        steps[i * (m + 1)] = 1;
+        for j in 1..=m {
+            let same = expected_norm[i - 1] == heard_norm[j - 1];
+            let diagonal = prev[j - 1] + u32::from(!same);
+            let missing = prev[j] + 1;
+            let inserted = row[j - 1] + 1;
+            // On ties prefer a match, then a missing/inserted word over a substitution,
+            // so a skipped word doesn't shift every following word into a substitution
+            let (cost, step) = if same && diagonal <= missing && diagonal <= inserted {
+                (diagonal, 0)
+            } else if missing <= inserted && missing <= diagonal {
+                (missing, 1)
+            } else if inserted <= diagonal {
+                (inserted, 2)
+            } else {
+                (diagonal, 0)
+            };
+            row[j] = cost;
+            steps[i * (m + 1) + j] = step;
+        }
+        prev = row;
+    }
+
+    // Walk back from the end to list the differences in order
+    let mut mismatches = Vec::new();
+    let mut matched = 0;
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        match steps[i * (m + 1) + j] {
+            0 if i > 0 && j > 0 => {
+                if expected_norm[i - 1] == heard_norm[j - 1] {
+                    matched += 1;
+                } else {
+                    mismatches.push(Mismatch {
+                        kind: MismatchKind::Substituted,
+                        expected: Some(expected[i - 1].to_string()),
+                        recognized: Some(heard[j - 1].word.clone()),
+                        start: heard[j - 1].start,
+                        end: heard[j - 1].end,
+                    });
+                }
+                i -= 1;
+                j -= 1;
+            }
+            2 if j > 0 => {
+                mismatches.push(Mismatch {
+                    kind: MismatchKind::Inserted,
+                    expected: None,
+                    recognized: Some(heard[j - 1].word.clone()),
+                    start: heard[j - 1].start,
+                    end: heard[j - 1].end,
+                });
+                j -= 1;
+            }
+            _ => {
+                // A missing word sits in the gap between its recognized neighbours
+                let start = if j > 0 { heard[j - 1].end } else { 0.0 };
+                let end = heard.get(j).map(|w| w.start).unwrap_or(start).max(start);
+                mismatches.push(Mismatch {
+                    kind: MismatchKind::Missing,
+                    expected: Some(expected[i - 1].to_string()),
+                    recognized: None,
+                    start,
+                    end,
+                });
+                i -= 1;
+            }
+        }
+    }
+    mismatches.reverse();
+
+    TranscriptReport {
+        input_words: n,
+        recognized_words: m,
+        matched_words: matched,
+        word_error_rate: if n == 0 { 0.0 } else { mismatches.len() as f32 / n as f32 },
+        mismatches,
+    }
+}
+
+/// Verify a lipsync JSON against the input text and write the report as JSON
+pub fn write_transcript_report(text: &str, lipsync_json: &str, output_path: &str) -> Result<TranscriptReport, Box<dyn std::error::Error>> {
+    let report = verify_transcript(text, &crate::read_word_timings(lipsync_json)?);
+    std::fs::write(output_path, serde_json::to_string_pretty(&report)?)?;
+    Ok(report)
+}
@@ -0,0 +1,330 @@
+use crate::WordTiming;
+use serde::{Deserialize, Serialize};
+
+/// Viseme used between words and where no phoneme data is available
+pub const SILENCE: &str = "sil";
+
+/// A mouth shape held over a time range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisemeEvent {
+    pub viseme: String,
+    pub start: f32,
+    pub end: f32,
+}
+
+/// Every viseme [`arpabet_to_viseme`] can produce, apart from silence
+pub const VISEMES: &[&str] = &["AA", "BM", "CH", "D", "E", "FV", "GK", "I", "L", "N", "O", "R", "S", "SH", "TH", "W", "Y"];
+
+/// The 39 ARPAbet phonemes of CMUdict, without stress markers
+pub const ARPABET_PHONEMES: &[&str] = &[
+    "AA", "AE", "AH", "AO", "AW", "AY", "B", "CH", "D", "DH", "EH", "ER", "EY", "F", "G", "HH", "IH", "IY", "JH", "K", "L", "M", "N", "NG", "OW", "OY", "P", "R", "S", "SH", "T", "TH", "UH", "UW", "V", "W", "Y", "Z", "ZH",
+];
+
+/// Map an ARPAbet phoneme (with or without stress marker) onto a viseme name.
+/// Matches the mapping used by the Blender add-on (`viseme_AA`, `viseme_BM`, ...), without the prefix.
+pub fn arpabet_to_viseme(phoneme: &str) -> &'static str {
+    let base = phoneme.trim_end_matches(|c: char| c.is_ascii_digit());
+    match base {
+        "AA" | "AE" | "AH" | "AO" | "AW" | "AY" => "AA",
+        "B" | "M" | "P" => "BM",
+        "CH" | "JH" => "CH",
+        "D" | "DH" | "T" => "D",
+        "EH" | "EY" | "ER" => "E",
+        "F" | "V" => "FV",
+        "G" | "K" | "NG" | "HH" => "GK",
+        "IH" | "IY" => "I",
+        "L" => "L",
+        "N" => "N",
+        "OW" | "OY" | "UH" | "UW" => "O",
+        "R" => "R",
+        "S" | "Z" => "S",
+        "SH" | "ZH" => "SH",
+        "TH" => "TH",
+        "W" => "W",
+        "Y" => "Y",
+        _ => SILENCE,
+    }
+}
+
+/// Map an IPA symbol (as produced by the voices' espeak phonemizer) onto the closest viseme.
+/// Stress and length marks, spaces, and punctuation have no viseme.
+pub fn ipa_to_viseme(symbol: char) -> Option<&'static str> {
+    Some(match symbol {
+        'a' | 'ɑ' | 'ɐ' | 'ʌ' | 'æ' | 'ɒ' | 'ɶ' => "AA",
+        'b' | 'm' | 'p' | 'ɱ' => "BM",
+        'd' | 't' | 'ð' | 'ʈ' | 'ɖ' => "D",
+        'e' | 'ɛ' | 'ə' | 'ɜ' | 'ɚ' | 'ɝ' | 'ø' | 'œ' => "E",
+        'f' | 'v' | 'ɸ' | 'β' | 'ʋ' => "FV",
+        'k' | 'g' | 'ɡ' | 'ŋ' | 'h' | 'x' | 'χ' | 'ɣ' | 'q' | 'ɦ' | 'ʔ' => "GK",
+        'i' | 'ɪ' | 'y' | 'ʏ' | 'ɨ' => "I",
+        'l' | 'ʎ' | 'ɫ' | 'ɭ' => "L",
+        'n' | 'ɲ' | 'ɳ' => "N",
+        'o' | 'ɔ' | 'u' | 'ʊ' | 'ɵ' | 'ɯ' | 'ʉ' => "O",
+        'r' | 'ɹ' | 'ɾ' | 'ʁ' | 'ʀ' | 'ɻ' => "R",
+        's' | 'z' | 'ʂ' | 'ʐ' => "S",
+        'ʃ' | 'ʒ' | 'ɕ' | 'ʑ' | 'ç' | 'ʝ' => "SH",
+        'θ' => "TH",
+        'w' | 'ʍ' | 'ɥ' => "W",
+        'j' => "Y",
+        _ => return None,
+    })
+}
+
+/// How open the jaw is for a viseme, from 0.0 (closed) to 1.0 (wide open)
+pub fn mouth_openness(viseme: &str) -> f32 {
+    match viseme {
+        "AA" => 1.0,
+        "O" => 0.7,
+        "E" => 0.6,
+        "I" | "Y" => 0.4,
+        "CH" | "SH" | "R" | "GK" | "L" => 0.35,
+        "D" | "N" | "TH" | "S" => 0.25,
+        "W" => 0.2,
+        "FV" => 0.1,
+        _ => 0.0,
+    }
+}
+
+/// How wide (smile) or rounded (pucker) the lips are for a viseme, from -1.0 (rounded) to 1.0 (wide)
+pub fn mouth_form(viseme: &str) -> f32 {
+    match viseme {
+        "E" | "I" | "Y" => 0.8,
+        "S" | "D" | "N" | "TH" => 0.4,
+        "AA" | "GK" | "L" => 0.1,
+        "O" => -0.6,
+        "W" | "SH" | "CH" | "R" => -0.8,
+        _ => 0.0,
+    }
+}
+
+fn push(viseme: &str, start: f32, end: f32, events: &mut Vec<VisemeEvent>) {
+    if end <= start {
+        return;
+    }
+    // Merge with the previous event when the shape doesn't change
+    if let Some(last) = events.last_mut() {
+        if last.viseme == viseme && (last.end - start).abs() < 1e-4 {
+            last.end = end;
+            return;
+        }
+    }
+    events.push(VisemeEvent { viseme: viseme.to_string(), start, end });
+}
+
+/// Build a viseme track from aligned words.
+/// Each word's ARPAbet phonemes share the word's duration evenly; gaps between words become silence.
+/// Words without phoneme data are shown as a generic open mouth.
+pub fn viseme_track(words: &[WordTiming]) -> Vec<VisemeEvent> {
+    let mut events: Vec<VisemeEvent> = Vec::new();
+    let mut cursor = 0.0f32;
+    for word in words {
+        if word.start > cursor {
+            push(SILENCE, cursor, word.start, &mut events);
+        }
+        if word.phonemes.is_empty() {
+            push("AA", word.start, word.end, &mut events);
+        } else {
+            let step = (word.end - word.start) / word.phonemes.len() as f32;
+            for (i, phoneme) in word.phonemes.iter().enumerate() {
+                let start = word.start + step * i as f32;
+                push(arpabet_to_viseme(phoneme), start, start + step, &mut events);
+            }
+        }
+        cursor = cursor.max(word.end);
+    }
+    events
+}
+
+/// Read word timings from a lipsync JSON file and convert them to a viseme track
+pub fn viseme_track_from_json(json_path: &str) -> Result<Vec<VisemeEvent>, Box<dyn std::error::Error>> {
+    Ok(viseme_track(&crate::read_word_timings(json_path)?))
+}
+
+/// A published viseme set the lipsync JSON's viseme track can be written in, instead of the
+/// 17 visemes used everywhere else
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "full", derive(clap::ValueEnum))]
+pub enum VisemeScheme {
+    /// The 15 Oculus/Meta visemes (`sil`, `PP`, `FF`, `TH`, `DD`, `kk`, `CH`, `SS`, `nn`, `RR`, `aa`, `E`, `I`, `O`, `U`)
+    Oculus,
+    /// The 10 classic Preston Blair mouth shapes (`rest`, `MBP`, `FV`, `L`, `WQ`, `AI`, `E`, `O`, `U`, `etc`)
+    PrestonBlair,
+    /// The ARKit blendshape that dominates each sound (`jawOpen`, `mouthClose`, `mouthFunnel`, ...);
+    /// `mouthSmile` stands for `mouthSmileLeft` and `mouthSmileRight` together
+    Arkit,
+}
+
+impl VisemeScheme {
+    /// Name as given to `--viseme-scheme` and written to the lipsync JSON
+    pub fn name(&self) -> &'static str {
+        match self {
+            VisemeScheme::Oculus => "oculus",
+            VisemeScheme::PrestonBlair => "preston-blair",
+            VisemeScheme::Arkit => "arkit",
+        }
+    }
+
+    /// Every viseme of the scheme, silence first
+    pub fn visemes(&self) -> &'static [&'static str] {
+        match self {
+            VisemeScheme::Oculus => &["sil", "PP", "FF", "TH", "DD", "kk", "CH", "SS", "nn", "RR", "aa", "E", "I", "O", "U"],
+            VisemeScheme::PrestonBlair => &["rest", "MBP", "FV", "L", "WQ", "AI", "E", "O", "U", "etc"],
+            VisemeScheme::Arkit => &["neutral", "jawOpen", "mouthClose", "mouthFunnel", "mouthPucker", "mouthRollLower", "mouthSmile", "tongueOut"],
+        }
+    }
+
+    /// The viseme for a closed, resting mouth
+    pub fn silence(&self) -> &'static str {
+        self.visemes()[0]
+    }
+
+    /// Map an ARPAbet phoneme (with or without stress marker) onto this scheme; unknown symbols are silence
+    pub fn arpabet_to_viseme(&self, phoneme: &str) -> &'static str {
+        let base = phoneme.trim_end_matches(|c: char| c.is_ascii_digit());
+        if !ARPABET_PHONEMES.contains(&base) {
+            return self.silence();
+        }
+        match self {
+            VisemeScheme::Oculus => match base {
+                "P" | "B" | "M" => "PP",
+                "F" | "V" => "FF",
+                "TH" | "DH" => "TH",
+                "T" | "D" => "DD",
+                "K" | "G" | "NG" | "HH" => "kk",
+                "CH" | "JH" | "SH" | "ZH" => "CH",
+                "S" | "Z" => "SS",
+                "N" | "L" => "nn",
+                "R" | "ER" => "RR",
+                "AA" | "AE" | "AH" | "AW" | "AY" => "aa",
+                "EH" | "EY" => "E",
+                "IH" | "IY" | "Y" => "I",
+                "AO" | "OW" | "OY" => "O",
+                _ => "U",
+            },
+            VisemeScheme::PrestonBlair => match base {
+                "P" | "B" | "M" => "MBP",
+                "F" | "V" => "FV",
+                "L" => "L",
+                "W" => "WQ",
+                "AA" | "AE" | "AH" | "AW" | "AY" | "IH" => "AI",
+                "EH" | "EY" | "IY" => "E",
+                "AO" | "OW" | "OY" => "O",
+                "UH" | "UW" | "ER" => "U",
+                _ => "etc",
+            },
+            VisemeScheme::Arkit => match base {
+                "P" | "B" | "M" => "mouthClose",
+                "F" | "V" => "mouthRollLower",
+                "W" => "mouthPucker",
+                "AO" | "OW" | "OY" | "UH" | "UW" | "R" | "ER" | "CH" | "JH" | "SH" | "ZH" => "mouthFunnel",
+                "EH" | "EY" | "IH" | "IY" | "Y" | "S" | "Z" => "mouthSmile",
+                "TH" | "DH" => "tongueOut",
+                _ => "jawOpen",
+            },
+        }
+    }
+
+    /// Map a phoneme that is either ARPAbet or a single IPA symbol onto this scheme
+    pub fn phoneme_to_viseme(&self, phoneme: &str) -> &'static str {
+        let mut chars = phoneme.chars();
+        match (chars.next(), chars.next()) {
+            (Some(symbol), None) if !symbol.is_ascii_uppercase() => ipa_to_arpabet(symbol).map_or(self.silence(), |p| self.arpabet_to_viseme(p)),
+            _ => self.arpabet_to_viseme(phoneme),
+        }
+    }
+
+    /// The viseme for a word with no phoneme data: an open mouth
+    fn open(&self) -> &'static str {
+        self.arpabet_to_viseme("AA")
+    }
+}
+
+/// The ARPAbet phoneme closest to an IPA symbol, so IPA timings can be mapped like ARPAbet ones.
+/// Stress and length marks, spaces, and punctuation have none.
+pub fn ipa_to_arpabet(symbol: char) -> Option<&'static str> {
+    Some(match symbol {
+        'a' | 'ɑ' | 'ɒ' => "AA",
+        'æ' | 'ɶ' => "AE",
+        'ʌ' | 'ɐ' | 'ə' => "AH",
+        'ɔ' => "AO",
+        'e' | 'ɛ' | 'ø' | 'œ' => "EH",
+        'ɜ' | 'ɚ' | 'ɝ' => "ER",
+        'i' | 'y' => "IY",
+        'ɪ' | 'ʏ' | 'ɨ' => "IH",
+        'o' | 'ɵ' => "OW",
+        'u' | 'ɯ' | 'ʉ' => "UW",
+        'ʊ' => "UH",
+        'b' | 'β' => "B",
+        'p' | 'ɸ' => "P",
+        'm' | 'ɱ' => "M",
+        'f' => "F",
+        'v' | 'ʋ' => "V",
+        'θ' => "TH",
+        'ð' => "DH",
+        't' | 'ʈ' => "T",
+        'd' | 'ɖ' => "D",
+        'k' | 'q' | 'x' | 'χ' | 'ʔ' => "K",
+        'g' | 'ɡ' | 'ɣ' => "G",
+        'ŋ' => "NG",
+        'h' | 'ɦ' => "HH",
+        'n' | 'ɲ' | 'ɳ' => "N",
+        'l' | 'ʎ' | 'ɫ' | 'ɭ' => "L",
+        'r' | 'ɹ' | 'ɾ' | 'ʁ' | 'ʀ' | 'ɻ' => "R",
+        's' | 'ʂ' => "S",
+        'z' | 'ʐ' => "Z",
+        'ʃ' | 'ɕ' | 'ç' => "SH",
+        'ʒ' | 'ʑ' | 'ʝ' => "ZH",
+        'w' | 'ʍ' | 'ɥ' => "W",
+        'j' => "Y",
+        _ => return None,
+    })
+}
+
+/// Build a viseme track in `scheme` from a lipsync JSON value. Words with timed `phones` (estimated,
+/// aligned, or Piper timings) keep those spans; otherwise each word's `phonemes` share its duration
+/// evenly, as in [`viseme_track`].
+pub fn scheme_track(json: &serde_json::Value, scheme: VisemeScheme) -> Vec<VisemeEvent> {
+    let words = crate::word_timings(json).unwrap_or_default();
+    let segments = json.get("word_segments").and_then(|w| w.as_array()).cloned().unwrap_or_default();
+    let mut events: Vec<VisemeEvent> = Vec::new();
+    let mut cursor = 0.0f32;
+    for (word, segment) in words.iter().zip(&segments) {
+        if word.start > cursor {
+            push(scheme.silence(), cursor, word.start, &mut events);
+        }
+        let phones: Vec<(String, f32, f32)> = segment
+            .get("phones")
+            .and_then(|p| p.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|phone| Some((phone.get("phoneme")?.as_str()?.to_string(), phone.get("start")?.as_f64()? as f32, phone.get("end")?.as_f64()? as f32)))
+            .collect();
+        if !phones.is_empty() {
+            for (phoneme, start, end) in &phones {
+                push(scheme.phoneme_to_viseme(phoneme), *start, *end, &mut events);
+            }
+        } else if word.phonemes.is_empty() {
+            push(scheme.open(), word.start, word.end, &mut events);
+        } else {
+            let step = (word.end - word.start) / word.phonemes.len() as f32;
+            for (i, phoneme) in word.phonemes.iter().enumerate() {
+                let start = word.start + step * i as f32;
+                push(scheme.phoneme_to_viseme(phoneme), start, start + step, &mut events);
+            }
+        }
+        cursor = cursor.max(word.end);
+    }
+    events
+}
+
+/// Add the viseme track in `scheme` (see [`scheme_track`]) to a lipsync JSON file as `visemes`, with the
+/// scheme's name under `viseme_scheme`; returns the number of events
+pub fn add_scheme_track_to_json(json_path: &str, scheme: VisemeScheme) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut value: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(json_path)?)?;
+    let track = scheme_track(&value, scheme);
+    let object = value.as_object_mut().ok_or_else(|| format!("{} isn't a JSON object", json_path))?;
+    object.insert("viseme_scheme".to_string(), serde_json::Value::from(scheme.name()));
+    object.insert("visemes".to_string(), serde_json::to_value(&track)?);
+    std::fs::write(json_path, serde_json::to_string_pretty(&value)?)?;
+    Ok(track.len())
+}
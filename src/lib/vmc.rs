@@ -0,0 +1,131 @@
+use crate::visemes::{viseme_track, VisemeEvent, SILENCE};
+use crate::estimate::estimate_word_timings;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// Default VMC receiver port used by VSeeFace, VTube Studio, and other performers
+pub const DEFAULT_VMC_ADDRESS: &str = "127.0.0.1:39539";
+
+/// VRM mouth blendshape presets driven over VMC
+pub const VRM_MOUTH_SHAPES: [&str; 5] = ["A", "I", "U", "E", "O"];
+
+/// How often blendshape values are sent while audio plays
+const SEND_INTERVAL: Duration = Duration::from_millis(1000 / 60);
+
+/// Append an OSC string: UTF-8 bytes, NUL-terminated, padded to a multiple of four
+fn push_osc_string(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(s.as_bytes());
+    buf.push(0);
+    while !buf.len().is_multiple_of(4) {
+        buf.push(0);
+    }
+}
+
+/// Encode an OSC message with string and float arguments
+fn osc_message(address: &str, strings: &[&str], floats: &[f32]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    push_osc_string(&mut buf, address);
+    let tags = format!(",{}{}", "s".repeat(strings.len()), "f".repeat(floats.len()));
+    push_osc_string(&mut buf, &tags);
+    for s in strings {
+        push_osc_string(&mut buf, s);
+    }
+    for f in floats {
+        buf.extend_from_slice(&f.to_be_bytes());
+    }
+    buf
+}
+
+/// Sends blendshape values to a VMC protocol receiver over UDP
+pub struct VmcSender {
+    socket: UdpSocket,
+}
+
+impl VmcSender {
+    /// Connect to a VMC receiver such as `127.0.0.1:39539`
+    pub fn connect(address: &str) -> std::io::Result<Self> {
+        let target = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Invalid VMC address: {}", address)))?;
+        let socket = UdpSocket::bind(if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" })?;
+        socket.connect(target)?;
+        Ok(Self { socket })
+    }
+
+    /// Send blendshape values followed by `/VMC/Ext/Blend/Apply`
+    pub fn send_blendshapes(&self, values: &[(&str, f32)]) -> std::io::Result<()> {
+        for (name, value) in values {
+            self.socket.send(&osc_message("/VMC/Ext/Blend/Val", &[name], &[*value]))?;
+        }
+        self.socket.send(&osc_message("/VMC/Ext/Blend/Apply", &[], &[]))?;
+        Ok(())
+    }
+}
+
+/// Weights of the VRM `A`, `I`, `U`, `E`, `O` mouth shapes for a viseme
+pub fn vrm_weights(viseme: &str) -> [f32; 5] {
+    match viseme {
+        "AA" => [1.0, 0.0, 0.0, 0.0, 0.0],
+        "I" | "Y" => [0.0, 1.0, 0.0, 0.0, 0.0],
+        "W" => [0.0, 0.0, 1.0, 0.0, 0.0],
+        "E" => [0.0, 0.0, 0.0, 1.0, 0.0],
+        "O" => [0.0, 0.0, 0.3, 0.0, 0.8],
+        "CH" | "SH" | "R" => [0.0, 0.0, 0.6, 0.0, 0.2],
+        "S" | "D" | "N" | "TH" => [0.0, 0.5, 0.0, 0.2, 0.0],
+        "GK" | "L" => [0.4, 0.0, 0.0, 0.2, 0.0],
+        "FV" => [0.0, 0.3, 0.0, 0.0, 0.0],
+        _ => [0.0; 5],
+    }
+}
+
+/// Loudness envelope: peak of each `hop`-sample window, normalized so the loudest window is 1.0
+pub fn amplitude_envelope(samples: &[f32], hop: usize) -> Vec<f32> {
+    let peaks: Vec<f32> = samples
+        .chunks(hop.max(1))
+        .map(|chunk| chunk.iter().fold(0.0f32, |peak, s| peak.max(s.abs())))
+        .collect();
+    let max = peaks.iter().cloned().fold(0.0f32, f32::max);
+    if max <= 0.0 {
+        return peaks;
+    }
+    peaks.into_iter().map(|p| p / max).collect()
+}
+
+/// Estimate a viseme track without alignment (see [`crate::estimate::estimate_word_timings`]), so it can
+/// drive a live avatar before WhisperX has run.
+pub fn estimated_viseme_track(phonemes_per_word: &[Vec<String>], samples: &[f32], sample_rate: u32) -> Vec<VisemeEvent> {
+    let words: Vec<(String, Vec<String>)> = phonemes_per_word.iter().map(|p| (String::new(), p.clone())).collect();
+    viseme_track(&estimate_word_timings(&words, samples, sample_rate))
+}
+
+/// Stream mouth blendshapes for audio that started playing at `started`, until `duration` has elapsed.
+/// Shapes come from the viseme track and are scaled by the audio loudness so pauses close the mouth.
+pub fn stream_lipsync(sender: &VmcSender, visemes: &[VisemeEvent], samples: &[f32], sample_rate: u32, started: Instant) -> std::io::Result<()> {
+    let hop = (sample_rate / 100).max(1) as usize;
+    let envelope = amplitude_envelope(samples, hop);
+    let duration = samples.len() as f32 / sample_rate as f32;
+    let mut index = 0;
+    loop {
+        let t = started.elapsed().as_secs_f32();
+        if t >= duration {
+            break;
+        }
+        while index < visemes.len() && visemes[index].end <= t {
+            index += 1;
+        }
+        let viseme = match visemes.get(index) {
+            Some(event) if event.start <= t => event.viseme.as_str(),
+            _ => SILENCE,
+        };
+        let loudness = envelope.get((t * sample_rate as f32) as usize / hop).copied().unwrap_or(0.0);
+        let gain = (loudness * 1.5).min(1.0);
+        let weights = vrm_weights(viseme);
+        let values: Vec<(&str, f32)> = VRM_MOUTH_SHAPES.iter().zip(weights).map(|(name, w)| (*name, w * gain)).collect();
+        sender.send_blendshapes(&values)?;
+        std::thread::sleep(SEND_INTERVAL);
+    }
+    // Leave the mouth closed
+    let closed: Vec<(&str, f32)> = VRM_MOUTH_SHAPES.iter().map(|name| (*name, 0.0)).collect();
+    sender.send_blendshapes(&closed)
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Mutex;
+
+/// WhisperX word scores below this are reported as low confidence
+pub const LOW_ALIGNMENT_SCORE: f32 = 0.5;
+
+/// A non-fatal problem with a synthesis: the output was still produced, but may not be exactly what
+/// was asked for. Collected with [`warn`] and handed back in a [`crate::SynthesisReport`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Warning {
+    /// A word got no phonemes, so it has an empty mouth track
+    OutOfVocabulary { word: String, reason: String },
+    /// A respelling couldn't be read, so the word was read normally
+    UnreadableRespelling { word: String, respelling: String, reason: String },
+    /// Samples beyond full scale were clipped when written as 16-bit audio
+    Clipped { samples: usize, peak: f32 },
+    /// WhisperX placed a word with a low score, so its timing may be off
+    LowAlignmentConfidence { word: String, start: Option<f32>, score: f32 },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::OutOfVocabulary { word, reason } => write!(f, "No phonemes for '{}': {}", word, reason),
+            Warning::UnreadableRespelling { word, respelling, reason } => write!(f, "{}{{{}}}: {}; read the word normally", word, respelling, reason),
+            Warning::Clipped { samples, peak } => write!(f, "{} samples clipped (peak {:.2})", samples, peak),
+            Warning::LowAlignmentConfidence { word, start: Some(start), score } => write!(f, "Low alignment confidence for '{}' at {:.2}s (score {:.2})", word, start, score),
+            Warning::LowAlignmentConfidence { word, start: None, score } => write!(f, "Low alignment confidence for '{}' (score {:.2})", word, score),
+        }
+    }
+}
+
+static WARNINGS: Mutex<Vec<Warning>> = Mutex::new(Vec::new());
+
+/// Record a warning for the synthesis in progress
+pub fn warn(warning: Warning) {
+    WARNINGS.lock().unwrap().push(warning);
+}
+
+/// Every warning recorded since the last call
+pub fn take_warnings() -> Vec<Warning> {
+    std::mem::take(&mut *WARNINGS.lock().unwrap())
+}
+
+/// A [`Warning::Clipped`] if any samples are beyond full scale
+pub fn clipping(samples: &[f32]) -> Option<Warning> {
+    let clipped = samples.iter().filter(|s| s.abs() > 1.0).count();
+    (clipped > 0).then(|| Warning::Clipped { samples: clipped, peak: samples.iter().fold(0.0f32, |p, s| p.max(s.abs())) })
+}
+
+/// [`Warning::LowAlignmentConfidence`] for each word in a WhisperX JSON's `word_segments` scored below
+/// [`LOW_ALIGNMENT_SCORE`]
+pub fn low_confidence_words(json: &serde_json::Value) -> Vec<Warning> {
+    json.get("word_segments")
+        .and_then(|w| w.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|segment| {
+            let score = segment.get("score")?.as_f64()? as f32;
+            (score < LOW_ALIGNMENT_SCORE).then(|| Warning::LowAlignmentConfidence {
+                word: segment.get("word").and_then(|w| w.as_str()).unwrap_or_default().to_string(),
+                start: segment.get("start").and_then(|s| s.as_f64()).map(|s| s as f32),
+                score,
+            })
+        })
+        .collect()
+}
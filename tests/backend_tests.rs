@@ -0,0 +1,1844 @@
+// Hermetic tests using the mock backend: no model downloads, no WhisperX.
+
+use text_to_face::backend::{MockBackend, TtsBackend};
+use text_to_face::coverage::{arpabet_coverage, ipa_coverage};
+use text_to_face::directives::{has_directives, split_directives, strip_directives, VoiceSettings};
+use text_to_face::enrich::{add_phonemes, add_visemes, import_alignment};
+use text_to_face::dsp;
+use text_to_face::emphasis::{apply_styles, has_emphasis, parse_emphasis, spoken_styles, strip_emphasis, WordStyle, EMPHASIS};
+use text_to_face::encode::{encode_flac, AudioFormat, EncodeSettings};
+use text_to_face::estimate::{estimate_word_timings, estimated_lipsync_json, phone_spans, timed_lipsync_json};
+use text_to_face::forced_align::align_word_timings;
+use text_to_face::humanize::{parse_amount, sentence_prosody, split_sentences, SentenceProsody};
+use text_to_face::gltf::{lipsync_glb, weight_keyframes, BlendshapeSet};
+use text_to_face::inspect::inspect_json_value;
+use text_to_face::karaoke::build_karaoke;
+#[cfg(feature = "full")]
+use text_to_face::lipsync_stream::{lipsync_frames, write_ndjson, StreamFrame};
+use text_to_face::limits::{LimitError, ResourceLimits};
+use text_to_face::llm_fallback::{parse_arpabet_response, LlmFallbackSettings};
+use text_to_face::markdown::{markdown_to_text, strip_inline, CodeBlocks};
+use text_to_face::markers::{markers, read_wav_markers, write_wav_markers, Marker, MarkerSet};
+use text_to_face::mixing::{duck_amounts, mix_with_ducking, DuckSettings};
+use text_to_face::overrides::{merge_patch, overlaid_config};
+use text_to_face::pause_markers::{split_pauses_with, strip_markers, PausePart};
+use text_to_face::phoneme_sources::{lexicon_phonemes, register_lexicon, set_lexicon, Lexicon, PhonemeSource, PhonemeSourceSettings, SourceOrder, DEFAULT_SOURCES};
+use text_to_face::prosody::{prosody_contour, DEFAULT_FRAME_RATE};
+use text_to_face::nice::{child_env, thread_args, NiceSettings};
+use text_to_face::normalize::{normalize_text, Locale};
+use text_to_face::rate::{rated_tempo, set_rate, RatePreset, RateSettings};
+use text_to_face::respelling::{arpabet_to_ipa, parse_word, respelling_to_arpabet, split_respellings, strip_respellings, Segment};
+use text_to_face::ssml::{parse_ssml, spoken_text, SpokenPart, SsmlPart};
+use text_to_face::takes::{take_path, take_variation};
+use text_to_face::trim::{trim_silence, TrimRange, DEFAULT_SILENCE_THRESHOLD_DB};
+use text_to_face::visemes::viseme_track;
+use text_to_face::warnings::{clipping, low_confidence_words, take_warnings, warn, Warning};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Held by tests that change process-wide settings, so tests running in parallel don't see each other's changes
+static SETTINGS: Mutex<()> = Mutex::new(());
+
+fn lock_settings() -> MutexGuard<'static, ()> {
+    // A failed test poisons the lock; the tests after it should still run
+    SETTINGS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[test]
+fn test_mock_backend_is_deterministic() {
+    let backend = MockBackend::default();
+    let first = backend.synthesize("Hello there").unwrap();
+    let second = backend.synthesize("Hello there").unwrap();
+    assert_eq!(first, second, "Mock output should be identical across runs");
+    assert!(!first.is_empty(), "Mock output should not be empty");
+}
+
+#[test]
+fn test_mock_word_timings_match_audio() {
+    let backend = MockBackend::default();
+    let text = "one three seventeen";
+    let samples = backend.synthesize(text).unwrap();
+    let timings = backend.word_timings(text).unwrap();
+    assert_eq!(timings.len(), 3);
+
+    let rate = backend.sample_rate() as f32;
+    for word in &timings {
+        // Tone in the middle of each word, silence just before it
+        let middle = ((word.start + word.end) / 2.0 * rate) as usize;
+        let before = (word.start * rate) as usize - 1;
+        let peak = samples[middle - 50..middle + 50].iter().fold(0.0f32, |p, s| p.max(s.abs()));
+        assert!(peak > 0.1, "Expected a tone during '{}'", word.word);
+        assert_eq!(samples[before], 0.0, "Expected silence before '{}'", word.word);
+    }
+    let last = timings.last().unwrap();
+    assert!(last.end <= samples.len() as f32 / rate);
+}
+
+#[test]
+fn test_mock_timings_drive_karaoke() {
+    let backend = MockBackend::default();
+    let text = "Hi there. Bye now!";
+    let document = build_karaoke(text, &backend.word_timings(text).unwrap());
+    assert_eq!(document.words.len(), 4);
+    assert_eq!(document.sentences.len(), 2, "Should group words into two sentences");
+    assert_eq!(document.words[1].char_offsets, [3, 9]);
+}
+
+#[test]
+fn test_karaoke_offsets_and_extra_words() {
+    let timing = |word: &str, start: f32| text_to_face::WordTiming { word: word.to_string(), start, end: start + 0.4, phonemes: Vec::new() };
+    // 😀 is two UTF-16 code units and é one, as JavaScript counts them
+    let text = "Café 😀 time. Bye";
+    let document = build_karaoke(text, &[timing("Café", 0.0), timing("time", 0.5), timing("Bye", 1.0)]);
+    assert_eq!(document.words[1].char_offsets, [8, 13]);
+    assert_eq!(document.sentences[0].char_offsets, [0, 13]);
+    assert_eq!(document.sentences[1].text, "Bye");
+    assert_eq!(document.sentences[1].char_offsets, [14, 17]);
+
+    // Words past the end of the text belong to a sentence that exists
+    for (text, words) in [("Hello there.", vec!["Hello", "there", "again"]), ("Hello there", vec!["Hello", "there", "again", "and", "again"]), ("", vec!["Hello"])] {
+        let timings: Vec<_> = words.iter().enumerate().map(|(i, word)| timing(word, i as f32 * 0.5)).collect();
+        let document = build_karaoke(text, &timings);
+        assert_eq!(document.words.len(), words.len());
+        let last = document.words.last().unwrap();
+        assert!(last.sentence < document.sentences.len(), "{:?}: word in sentence {} of {}", text, last.sentence, document.sentences.len());
+        assert_eq!(last.char_offsets, [text.len(); 2]);
+        assert_eq!(document.sentences[last.sentence].end, last.end, "The sentence lasts until its last word");
+    }
+}
+
+#[test]
+fn test_viseme_schemes() {
+    use text_to_face::visemes::{scheme_track, VisemeScheme};
+    assert_eq!(VisemeScheme::Oculus.arpabet_to_viseme("UW1"), "U");
+    assert_eq!(VisemeScheme::PrestonBlair.arpabet_to_viseme("B"), "MBP");
+    assert_eq!(VisemeScheme::Arkit.arpabet_to_viseme("W"), "mouthPucker");
+    // IPA symbols map through their closest ARPAbet phoneme
+    assert_eq!(VisemeScheme::Oculus.phoneme_to_viseme("ʃ"), "CH");
+    assert_eq!(VisemeScheme::PrestonBlair.phoneme_to_viseme("ˈ"), "rest");
+    for scheme in [VisemeScheme::Oculus, VisemeScheme::PrestonBlair, VisemeScheme::Arkit] {
+        for phoneme in text_to_face::visemes::ARPABET_PHONEMES {
+            assert!(scheme.visemes().contains(&scheme.arpabet_to_viseme(phoneme)), "{:?} {}", scheme, phoneme);
+        }
+    }
+
+    // Timed phones keep their spans; words with only phonemes split evenly
+    let json = serde_json::json!({
+        "word_segments": [
+            { "word": "me", "start": 0.2, "end": 0.5, "phonemes": ["M", "IY1"], "phones": [{ "phoneme": "m", "start": 0.2, "end": 0.3 }, { "phoneme": "i", "start": 0.3, "end": 0.5 }] },
+            { "word": "too", "start": 0.5, "end": 0.9, "phonemes": ["T", "UW1"] },
+        ]
+    });
+    let track = scheme_track(&json, VisemeScheme::Oculus);
+    let names: Vec<&str> = track.iter().map(|e| e.viseme.as_str()).collect();
+    assert_eq!(names, vec!["sil", "PP", "I", "DD", "U"]);
+    assert!((track[1].end - 0.3).abs() < 1e-5 && (track[3].end - 0.7).abs() < 1e-5);
+}
+
+#[test]
+fn test_piper_phoneme_timings() {
+    use text_to_face::piper_timings::{phoneme_ids, phoneme_spans, word_spans};
+    let map: std::collections::HashMap<char, Vec<i64>> = [('_', 0), ('^', 1), ('$', 2), (' ', 3), ('h', 4), ('a', 5), ('i', 6)].into_iter().map(|(c, id)| (c, vec![id])).collect();
+    // Unknown symbols are dropped; each phoneme is followed by PAD, between BOS and EOS
+    let (ids, symbols) = phoneme_ids("hi? a", &map).unwrap();
+    assert_eq!(ids, vec![1, 4, 0, 6, 0, 3, 0, 5, 0, 2]);
+    assert_eq!(symbols[1], Some('h'));
+    assert_eq!(symbols[2], None);
+
+    // Frame counts are scaled to the audio's length; PAD extends the phoneme before it
+    let durations = [2.0, 3.0, 1.0, 4.0, 0.0, 2.0, 0.0, 5.0, 1.0, 2.0];
+    let spans = phoneme_spans(&symbols, &durations, 2.0, 1.0);
+    assert_eq!(spans.len(), 4);
+    assert!((spans[0].start_time - 1.2).abs() < 1e-5 && (spans[0].end_time - 1.6).abs() < 1e-5);
+    assert!((spans[3].end_time - 2.8).abs() < 1e-5, "EOS stays silence: {:?}", spans[3]);
+
+    let data = text_to_face::LipSyncData { phonemes: spans, duration: 3.0, sample_rate: 22050 };
+    let words = word_spans(&data);
+    assert_eq!(words.len(), 2);
+    assert_eq!(words[0].iter().map(|p| p.phoneme.as_str()).collect::<String>(), "hi");
+    let slow = data.scaled(2.0);
+    assert!((slow.phonemes[0].start_time - 2.4).abs() < 1e-5 && slow.duration == 6.0);
+}
+
+#[test]
+fn test_subtitle_cues() {
+    use text_to_face::subtitles::{build_cues, format_srt, format_webvtt};
+    let word = |word: &str, start: f32, end: f32| text_to_face::WordTiming { word: word.to_string(), start, end, phonemes: Vec::new() };
+    let text = "Hello there, friend. Once upon a time by the sea.";
+    let timings = vec![
+        word("hello", 0.0, 0.4),
+        word("there", 0.45, 0.8),
+        word("friend", 0.85, 1.3),
+        word("once", 1.6, 1.9),
+        word("upon", 1.95, 2.2),
+        word("a", 2.25, 2.3),
+        word("time", 2.35, 2.7),
+        word("by", 4.0, 4.2),
+        word("the", 4.25, 4.4),
+        word("sea", 4.45, 5.0),
+    ];
+    let cues = build_cues(text, &timings, 12);
+    // A sentence end and then a long pause split the cues; lines wrap at 12 characters
+    assert_eq!(cues.len(), 3);
+    assert_eq!(cues[0].lines, vec!["Hello there,", "friend."]);
+    assert_eq!((cues[0].start, cues[0].end), (0.0, 1.3));
+    assert_eq!(cues[1].lines, vec!["Once upon a", "time"]);
+    assert_eq!(cues[2].lines, vec!["by the sea."]);
+
+    let srt = format_srt(&cues);
+    assert!(srt.starts_with("1\n00:00:00,000 --> 00:00:01,300\nHello there,\nfriend.\n\n2\n"), "{}", srt);
+    let vtt = format_webvtt(&cues);
+    assert!(vtt.starts_with("WEBVTT\n\n00:00:00.000 --> 00:00:01.300\n"), "{}", vtt);
+    assert!(vtt.contains("00:00:04.000 --> 00:00:05.000\nby the sea.\n"));
+    assert!(text_to_face::subtitles::parse_subtitles_path("captions.txt").is_err());
+}
+
+#[test]
+fn test_music_ducks_under_mock_speech() {
+    let backend = MockBackend::default();
+    let text = "hello world";
+    let speech = backend.synthesize(text).unwrap();
+    let timings = backend.word_timings(text).unwrap();
+    let rate = backend.sample_rate();
+    let settings = DuckSettings { attack: 0.0, release: 0.0, ..DuckSettings::default() };
+
+    let amounts = duck_amounts(&timings, speech.len(), rate, &settings);
+    let inside = (((timings[0].start + timings[0].end) / 2.0) * rate as f32) as usize;
+    assert_eq!(amounts[inside], 1.0, "Music should be fully ducked during a word");
+    assert_eq!(amounts[0], 0.0, "Music should be at full level before speech");
+
+    // Constant music over silence shows the gain directly; the padding keeps the final fade-out clear of the words
+    let silence = vec![0.0; speech.len() + 2 * rate as usize];
+    let mixed = mix_with_ducking(&silence, &[0.5], rate, &timings, &settings);
+    assert_eq!(mixed.len(), silence.len());
+    let drop_db = 20.0 * (mixed[inside] / mixed[0]).log10();
+    assert!((drop_db + settings.duck_db).abs() < 0.01, "Expected a {} dB drop, got {}", settings.duck_db, drop_db);
+}
+
+#[test]
+fn test_inspect_mock_lipsync_json() {
+    let backend = MockBackend::default();
+    let text = "inspect these four words";
+    let stats = inspect_json_value(&backend.lipsync_json(text, 1.0).unwrap());
+    assert_eq!(stats.kind, "lipsync");
+    assert_eq!(stats.word_count, Some(4));
+    assert_eq!(stats.phoneme_coverage, Some(0.0), "Mock timings carry no phonemes");
+    let last = backend.word_timings(text).unwrap().last().unwrap().end;
+    assert_eq!(stats.duration, Some(last));
+}
+
+/// A backend that knows nothing about its words
+struct Silence;
+
+impl TtsBackend for Silence {
+    fn sample_rate(&self) -> u32 {
+        8000
+    }
+
+    fn synthesize(&self, text: &str) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+        Ok(vec![0.0; text.len() * 80])
+    }
+}
+
+#[test]
+fn test_verify_transcript() {
+    use text_to_face::verify::{verify_transcript, MismatchKind};
+    // Recognized words half a second apart, each 0.4s long
+    let heard = |words: &[&str]| -> Vec<text_to_face::WordTiming> {
+        words.iter().enumerate().map(|(i, word)| text_to_face::WordTiming { word: word.to_string(), start: i as f32 * 0.5, end: i as f32 * 0.5 + 0.4, phonemes: Vec::new() }).collect()
+    };
+    let summary = |report: &text_to_face::verify::TranscriptReport| {
+        report.mismatches.iter().map(|m| (m.kind, m.expected.clone(), m.recognized.clone(), m.start, m.end)).collect::<Vec<_>>()
+    };
+
+    // Case and punctuation don't count
+    let clean = verify_transcript("Hello, World!", &heard(&["hello", "world."]));
+    assert!(clean.is_clean());
+    assert_eq!((clean.input_words, clean.recognized_words, clean.matched_words, clean.word_error_rate), (2, 2, 2, 0.0));
+
+    let substituted = verify_transcript("the quick brown fox", &heard(&["the", "quick", "brown", "box"]));
+    assert_eq!(summary(&substituted), [(MismatchKind::Substituted, Some("fox".to_string()), Some("box".to_string()), 1.5, 1.9)]);
+    assert_eq!((substituted.matched_words, substituted.word_error_rate), (3, 0.25));
+
+    // A skipped word sits in the gap between its neighbours, without shifting the rest into substitutions
+    let missing = verify_transcript("the quick brown fox", &heard(&["the", "brown", "fox"]));
+    assert_eq!(summary(&missing), [(MismatchKind::Missing, Some("quick".to_string()), None, 0.4, 0.5)]);
+    assert_eq!((missing.recognized_words, missing.matched_words, missing.word_error_rate), (3, 3, 0.25));
+
+    let inserted = verify_transcript("the brown fox", &heard(&["the", "very", "brown", "fox"]));
+    assert_eq!(summary(&inserted), [(MismatchKind::Inserted, None, Some("very".to_string()), 0.5, 0.9)]);
+    assert_eq!(inserted.matched_words, 3);
+    assert!((inserted.word_error_rate - 1.0 / 3.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_registered_backends() {
+    use text_to_face::backend::{register_backend, registered_backend, MOCK_VOICE};
+    assert_eq!(registered_backend(MOCK_VOICE).unwrap().sample_rate(), 22050);
+    assert!(registered_backend("en_GB-alba-medium").is_none(), "Piper voices aren't registered");
+
+    register_backend("test-silence", std::sync::Arc::new(Silence));
+    let silence = registered_backend("test-silence").unwrap();
+    assert_eq!(silence.synthesize("hush").unwrap().len(), 320);
+    assert!(silence.lipsync_json("hush", 1.0).is_none(), "No timings, so lipsync has to align");
+    // Registering again replaces the backend
+    register_backend("test-silence", std::sync::Arc::new(MockBackend { sample_rate: 16000, ..MockBackend::default() }));
+    assert_eq!(registered_backend("test-silence").unwrap().sample_rate(), 16000);
+    assert!(registered_backend("test-silence").unwrap().lipsync_json("hush", 1.0).is_some());
+
+    #[cfg(feature = "full")]
+    {
+        let (samples, rate) = text_to_face::synthesize_processed("hush now", "test-silence", &text_to_face::PitchArg::Value(1.0), 1.0).unwrap();
+        assert_eq!(rate, 16000, "Synthesis dispatches to the registered backend");
+        assert!(!samples.is_empty());
+    }
+}
+
+#[test]
+fn test_llm_prompt_template_and_response_filtering() {
+    let settings = LlmFallbackSettings { prompt_template: Some("Phonemes for {word}? ({word})".to_string()), ..Default::default() };
+    assert_eq!(settings.prompt("KUBECTL"), "Phonemes for KUBECTL? (KUBECTL)");
+    assert!(LlmFallbackSettings::default().prompt("HELLO").contains("'HELLO'"));
+
+    // Chatty model output keeps only valid ARPAbet tokens
+    assert_eq!(parse_arpabet_response("Sure! K UW1 B K AH0 N T R OW2 L."), vec!["K", "UW1", "B", "K", "AH0", "N", "T", "R", "OW2"]);
+    assert!(parse_arpabet_response("I don't know that word").is_empty());
+}
+
+#[test]
+fn test_prosody_contour_tracks_pitch_and_silence() {
+    // A 180 Hz voice-like tone with harmonics, then silence
+    let rate = 22050;
+    let mut samples: Vec<f32> = (0..rate / 2)
+        .map(|i| {
+            let t = i as f32 / rate as f32;
+            (1..=4).map(|h| 0.3 / h as f32 * (2.0 * std::f32::consts::PI * 180.0 * h as f32 * t).sin()).sum()
+        })
+        .collect();
+    samples.extend(vec![0.0; rate as usize / 2]);
+
+    let contour = prosody_contour(&samples, rate, DEFAULT_FRAME_RATE);
+    assert_eq!(contour.frames.len(), 100);
+    for frame in &contour.frames[5..45] {
+        let f0 = frame.f0.expect("tone should be voiced");
+        assert!((f0 - 180.0).abs() < 2.0, "expected ~180 Hz at {:.2}s, got {}", frame.time, f0);
+        assert!(frame.energy > 0.9);
+    }
+    for frame in &contour.frames[55..] {
+        assert_eq!(frame.f0, None);
+        assert_eq!(frame.energy, 0.0);
+    }
+
+    // Mock speech is voiced during words and silent between them
+    let backend = MockBackend::default();
+    let speech = backend.synthesize("prosody contour").unwrap();
+    let contour = prosody_contour(&speech, backend.sample_rate(), DEFAULT_FRAME_RATE);
+    for word in backend.word_timings("prosody contour").unwrap() {
+        let middle = ((word.start + word.end) / 2.0 * DEFAULT_FRAME_RATE) as usize;
+        assert!(contour.frames[middle].f0.is_some(), "'{}' should be voiced", word.word);
+    }
+    assert_eq!(contour.frames[2].f0, None);
+}
+
+#[test]
+fn test_phoneme_and_viseme_coverage() {
+    let phonemes = |p: &str| Some(p.split_whitespace().map(str::to_string).collect::<Vec<_>>());
+    let words = vec![
+        ("mama".to_string(), phonemes("M AA1 M AH0")),
+        ("papa".to_string(), phonemes("P AA1 P AH0")),
+        ("zyx".to_string(), None),
+    ];
+    let report = arpabet_coverage(&words);
+    assert_eq!(report.words, 3);
+    assert_eq!(report.phoneme_count, 8);
+    assert_eq!(report.phonemes["AA"], 2);
+    assert_eq!(report.phonemes["AH"], 2, "stress markers are merged");
+    assert_eq!(report.visemes["BM"], 4);
+    assert_eq!(report.visemes["AA"], 4);
+    assert_eq!(report.unknown_words, vec!["zyx"]);
+    assert!(report.missing_visemes.contains(&"O".to_string()) && !report.missing_visemes.contains(&"BM".to_string()));
+    assert_eq!(report.missing_phonemes.len(), 39 - 4);
+    assert_eq!(report.share(2), 0.25);
+
+    // IPA from a voice's phonemizer: stress marks and spaces aren't phonemes
+    let report = ipa_coverage(&["ˈmama ʃuː".to_string()]);
+    assert_eq!(report.words, 2);
+    assert_eq!(report.phoneme_count, 6);
+    assert_eq!(report.visemes["SH"], 1);
+    assert_eq!(report.visemes["O"], 1);
+}
+
+#[test]
+fn test_take_variations() {
+    let first = take_variation(1);
+    assert_eq!((first.noise_scale, first.noise_w, first.length_scale), (1.0, 1.0, 1.0), "take 1 is the voice's defaults");
+    assert_eq!(take_variation(2), take_variation(2), "takes are reproducible");
+    assert_ne!(take_variation(2), take_variation(3));
+    for take in 2..20 {
+        let variation = take_variation(take);
+        assert!((0.75..=1.25).contains(&variation.noise_scale));
+        assert!((0.7..=1.3).contains(&variation.noise_w));
+        assert!((0.95..=1.05).contains(&variation.length_scale));
+    }
+    assert_eq!(take_path(Path::new("out/line.wav"), 2), Path::new("out/line_take2.wav"));
+    assert_eq!(take_path(Path::new("line.json"), 3), Path::new("line_take3.json"));
+}
+
+#[test]
+fn test_split_sentences() {
+    let text = "Dr. Watson arrived at 3.15 p.m. on the dot! \"Was J. R. Hartley there?\" he asked. No… not yet";
+    assert_eq!(
+        split_sentences(text),
+        ["Dr. Watson arrived at 3.15 p.m. on the dot!", "\"Was J. R. Hartley there?\"", "he asked.", "No…", "not yet"]
+    );
+    assert!(split_sentences("  ").is_empty());
+}
+
+#[test]
+fn test_humanize_variation() {
+    assert_eq!(sentence_prosody(3, 0.0), SentenceProsody::NONE);
+    assert!(parse_amount("0.5").is_err() && parse_amount("abc").is_err());
+    let amount = parse_amount("0.05").unwrap();
+    let prosody: Vec<_> = (0..20).map(|index| sentence_prosody(index, amount)).collect();
+    assert_eq!(prosody[4], sentence_prosody(4, amount), "sentences vary the same way every render");
+    assert!(prosody.windows(2).all(|pair| pair[0] != pair[1]));
+    for sentence in &prosody {
+        assert!((0.95..=1.05).contains(&sentence.pitch));
+        assert!((0.975..=1.025).contains(&sentence.tempo));
+    }
+}
+
+#[test]
+fn test_markdown_to_text() {
+    let markdown = "---\ntitle: Notes\n---\n# Getting *started*\n\nRun the **installer**, then see [the docs](https://example.com) or `--help`.\n\n```rust\nfn main() {}\n```\n\n- [x] Install it\n- Configure snake_case names\n\n| Flag | Meaning |\n|------|---------|\n| -v | Verbose |\n\nSetup\n-----\n> Quoted ~~old~~ tip![^1]";
+    assert_eq!(
+        markdown_to_text(markdown, CodeBlocks::Skip),
+        "Getting started.\n\nRun the installer, then see the docs or --help.\n\nInstall it.\nConfigure snake_case names.\n\nFlag, Meaning.\n-v, Verbose.\n\nSetup.\n\nQuoted old tip!"
+    );
+    assert!(markdown_to_text(markdown, CodeBlocks::Announce).contains("\n\nRust code block omitted.\n\n"));
+    assert_eq!(strip_inline("![A cat](cat.png) costs 2 * 3 <b>now</b>"), "A cat costs 2 * 3 now");
+}
+
+#[test]
+fn test_pause_markers() {
+    let ms = Duration::from_millis;
+    let parts = split_pauses_with("Ready? [pause 1.5s] Go! [PAUSE][pause 200ms] Now.", None).unwrap();
+    assert_eq!(
+        parts,
+        vec![PausePart::Text("Ready?".into()), PausePart::Pause(ms(1500)), PausePart::Text("Go!".into()), PausePart::Pause(ms(700)), PausePart::Text("Now.".into())]
+    );
+    assert_eq!(split_pauses_with("See [notes] and [paused] text", None).unwrap(), vec![PausePart::Text("See [notes] and [paused] text".into())]);
+    assert!(split_pauses_with("Wait [pause soon]", None).is_err());
+
+    // Ellipses pause only between words, and only when asked to
+    assert_eq!(split_pauses_with("Well... maybe", None).unwrap().len(), 1);
+    assert_eq!(split_pauses_with("Well\u{2026} maybe...", Some(ms(600))).unwrap(), vec![PausePart::Text("Well\u{2026}".into()), PausePart::Pause(ms(600)), PausePart::Text("maybe...".into())]);
+    assert_eq!(split_pauses_with("...and then. Etc.. ok 1...5", Some(ms(600))).unwrap().len(), 1);
+
+    assert_eq!(strip_markers("Ready? [pause 1s] Go [pause]!"), "Ready? Go!");
+}
+
+#[test]
+fn test_emphasis_markup() {
+    let marked = parse_emphasis("I *really* mean {pitch=1.5}it, {volume=0.5}truly{/volume}{/pitch} 2 * 3").unwrap();
+    assert_eq!(marked.text, "I really mean it, truly 2 * 3");
+    let none = WordStyle::NONE;
+    assert_eq!(marked.styles, vec![none, EMPHASIS, none, WordStyle { pitch: 1.5, volume: 1.0 }, WordStyle { pitch: 1.5, volume: 0.5 }, none, none, none]);
+    assert_eq!(strip_emphasis("Hermione{her-MY-oh-nee} is *here*"), "Hermione{her-MY-oh-nee} is here");
+    assert!(!has_emphasis("a*b and *nothing closes"));
+    assert!(parse_emphasis("{volume=loud}no{/volume}").is_err());
+
+    // Styles follow the words as normalization speaks them
+    let english = |text: &str| normalize_text(text, Locale::from_voice_id("en_GB-alba-medium").unwrap());
+    let (spoken, styles) = spoken_styles(&parse_emphasis("It costs 1,234 dollars, *really*").unwrap(), english).unwrap();
+    assert_eq!(styles.len(), spoken.split_whitespace().count());
+    assert_eq!(styles.iter().filter(|style| **style == EMPHASIS).count(), 1);
+    assert_eq!(styles.last(), Some(&EMPHASIS));
+    let (_, styles) = spoken_styles(&parse_emphasis("*1,234* dollars").unwrap(), english).unwrap();
+    assert!(styles.len() > 2 && styles[..styles.len() - 1].iter().all(|style| *style == EMPHASIS), "Every word of a marked number is styled");
+    let merging = |text: &str| text.replace("New York", "NYC");
+    assert!(spoken_styles(&parse_emphasis("*New* York").unwrap(), merging).is_err(), "Words merged across the markup can't be placed");
+
+    // Only the marked word's stretch of audio changes
+    let mock = MockBackend::default();
+    let text = "quiet loud quiet";
+    let mut samples = mock.synthesize(text).unwrap();
+    let original = samples.clone();
+    let timings = mock.word_timings(text).unwrap();
+    apply_styles(&mut samples, mock.sample_rate, &timings, &[WordStyle::NONE, WordStyle { pitch: 1.0, volume: 0.5 }, WordStyle::NONE]);
+    let peak = |samples: &[f32], word: &text_to_face::WordTiming| {
+        let rate = mock.sample_rate as f32;
+        samples[(word.start * rate) as usize..(word.end * rate) as usize].iter().fold(0.0f32, |peak, s| peak.max(s.abs()))
+    };
+    assert!((peak(&samples, &timings[1]) - peak(&original, &timings[1]) * 0.5).abs() < 0.01);
+    assert_eq!(peak(&samples, &timings[0]), peak(&original, &timings[0]));
+    assert_eq!(peak(&samples, &timings[2]), peak(&original, &timings[2]));
+}
+
+#[test]
+fn test_pronunciation_respellings() {
+    let text = "I met Hermione{her-MY-oh-nee} and Siobhan{shiv-AWN}. Sets {braces} alone";
+    assert_eq!(strip_respellings(text), "I met Hermione and Siobhan. Sets {braces} alone");
+    let respelled: Vec<_> = split_respellings(text).into_iter().filter(|s| matches!(s, Segment::Respelled(_))).collect();
+    assert_eq!(respelled.len(), 2, "braces after a space aren't a respelling");
+
+    let word = parse_word("Hermione{her-MY-oh-nee},").unwrap();
+    assert_eq!((word.word.as_str(), word.respelling.as_str()), ("Hermione", "her-MY-oh-nee"));
+    let phonemes = respelling_to_arpabet(&word.respelling).unwrap();
+    assert_eq!(phonemes, vec!["HH", "ER0", "M", "AY1", "OW0", "N", "IY0"]);
+    assert_eq!(arpabet_to_ipa(&phonemes), "hɚmˈaɪoʊniː");
+
+    assert_eq!(respelling_to_arpabet("shiv-AWN").unwrap(), vec!["SH", "IH0", "V", "AO1", "N"]);
+    assert_eq!(respelling_to_arpabet("KASS").unwrap(), vec!["K", "AE1", "S"], "doubled letters are one sound");
+    assert!(respelling_to_arpabet("zz-TOP").is_err(), "a syllable needs a vowel");
+    assert!(respelling_to_arpabet("").is_err());
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_lipsync_stream_interleaves_events_with_audio() {
+    let backend = MockBackend::default();
+    let text = "hello world";
+    let samples = backend.synthesize(text).unwrap();
+    let mut timings = backend.word_timings(text).unwrap();
+    timings[0].phonemes = vec!["HH".to_string(), "AH0".to_string(), "L".to_string(), "OW1".to_string()];
+    let frames = lipsync_frames(&samples, backend.sample_rate(), &timings, 0.1);
+
+    assert!(matches!(frames.first(), Some(StreamFrame::Header { sample_rate: 22050, .. })));
+    assert!(matches!(frames.last(), Some(StreamFrame::End { .. })));
+    let audio_samples: usize = frames.iter().map(|f| if let StreamFrame::Audio { samples, .. } = f { *samples } else { 0 }).sum();
+    assert_eq!(audio_samples, samples.len(), "every sample is sent once");
+
+    // Each event arrives before the audio frame it starts in
+    let mut sent_until = 0.0;
+    for frame in &frames {
+        match frame {
+            StreamFrame::Audio { start, samples, .. } => sent_until = start + *samples as f32 / 22050.0,
+            StreamFrame::Word { start, .. } | StreamFrame::Viseme { start, .. } => assert!(*start >= sent_until, "event at {} sent late", start),
+            _ => {}
+        }
+    }
+    let words: Vec<&str> = frames.iter().filter_map(|f| if let StreamFrame::Word { word, .. } = f { Some(word.as_str()) } else { None }).collect();
+    assert_eq!(words, ["hello", "world"]);
+
+    let mut ndjson = Vec::new();
+    write_ndjson(&mut ndjson, &frames).unwrap();
+    let lines: Vec<&str> = std::str::from_utf8(&ndjson).unwrap().lines().collect();
+    assert_eq!(lines.len(), frames.len());
+    assert!(lines[0].starts_with(r#"{"type":"header""#));
+}
+
+#[test]
+fn test_resource_limits() {
+    let backend = MockBackend::default();
+    let samples = backend.synthesize("a fairly long line of mock speech").unwrap();
+    let rate = backend.sample_rate();
+    let seconds = samples.len() as f32 / rate as f32;
+
+    let unlimited = ResourceLimits::default();
+    assert!(unlimited.is_unlimited());
+    assert!(unlimited.check_text(&"x".repeat(100_000)).is_ok());
+    assert!(unlimited.check_samples(samples.len(), rate).is_ok());
+
+    let limits = ResourceLimits { max_text_chars: Some(10), max_audio_seconds: Some(seconds / 2.0), max_memory_bytes: None };
+    assert_eq!(limits.check_text("short"), Ok(()));
+    assert_eq!(limits.check_text("much too long"), Err(LimitError::TextTooLong { chars: 13, max: 10 }));
+    assert!(matches!(limits.check_samples(samples.len(), rate), Err(LimitError::AudioTooLong { .. })));
+    assert!(limits.check_samples(samples.len() / 4, rate).is_ok());
+
+    let limits = ResourceLimits { max_memory_bytes: Some(4000), ..ResourceLimits::default() };
+    assert_eq!(limits.check_samples(1000, rate), Ok(()));
+    assert_eq!(limits.check_samples(1001, rate), Err(LimitError::MemoryExceeded { bytes: 4004, max: 4000 }));
+
+    // Callers get the error boxed, and can still tell it apart
+    let boxed: Box<dyn std::error::Error> = limits.check_samples(2000, rate).unwrap_err().into();
+    assert!(boxed.downcast_ref::<LimitError>().is_some());
+}
+
+#[test]
+fn test_pitch_and_tempo_ranges() {
+    use text_to_face::limits::{check_pitch, check_tempo};
+    for pitch in [0.25, 0.8, 1.0, 4.0] {
+        assert!(check_pitch(pitch).is_ok(), "{}", pitch);
+    }
+    // Zero, negative, tiny, huge, and non-finite factors would make the shift's resampler blow up
+    for pitch in [0.0, -1.0, 1e-6, 100.0, f32::NAN, f32::INFINITY] {
+        assert!(check_pitch(pitch).is_err(), "{}", pitch);
+    }
+    for tempo in [0.1, 0.5, 2.0, 10.0] {
+        assert!(check_tempo(tempo).is_ok(), "{}", tempo);
+    }
+    for tempo in [0.0, -2.0, 1e-4, 50.0, 1e9, f32::NAN] {
+        assert!(check_tempo(tempo).is_err(), "{}", tempo);
+    }
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_limits_apply_after_tempo_stretch() {
+    use text_to_face::limits::{limits, set_limits};
+    use text_to_face::{backend::MOCK_VOICE, synthesize_processed, Error, PitchArg};
+    let _settings = lock_settings();
+    let text = "A line of mock speech long enough to measure. ".repeat(8);
+    let backend = MockBackend::default();
+    let seconds = backend.synthesize(&text).unwrap().len() as f32 / backend.sample_rate() as f32;
+    let previous = limits();
+    // Room for the speech as synthesized, but not for it at half speed
+    set_limits(ResourceLimits { max_audio_seconds: Some(seconds * 1.5), ..ResourceLimits::default() });
+    let normal = synthesize_processed(&text, MOCK_VOICE, &PitchArg::Value(1.0), 1.0);
+    let slowed = synthesize_processed(&text, MOCK_VOICE, &PitchArg::Value(1.0), 2.0);
+    set_limits(previous);
+    assert!(normal.is_ok());
+    assert!(matches!(slowed, Err(Error::LimitExceeded(LimitError::AudioTooLong { .. }))));
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_server_rejects_bad_pitch_and_tempo() {
+    use std::io::{Read, Write};
+    use text_to_face::server::{serve, ServerOptions};
+    let bind = "127.0.0.1:38517";
+    std::thread::spawn(move || {
+        let _ = serve(&ServerOptions { bind: bind.to_string(), default_voice: "en_GB-alba-medium".to_string(), allow_origin: None });
+    });
+    let connect = || {
+        for _ in 0..50 {
+            if let Ok(stream) = std::net::TcpStream::connect(bind) {
+                return stream;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+        panic!("The server should be listening on {}", bind);
+    };
+    let post = |path: &str, body: &str| {
+        let mut stream = connect();
+        write!(stream, "POST {} HTTP/1.0\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", path, body.len(), body).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    for body in [r#"{"text": "Hi", "pitch": 0}"#, r#"{"text": "Hi", "pitch": -1.5}"#, r#"{"text": "Hi", "pitch": 1000}"#, r#"{"text": "Hi", "tempo": 0}"#, r#"{"text": "Hi", "tempo": 1e30}"#] {
+        for path in ["/synthesize", "/synthesize?stream=ndjson", "/lipsync"] {
+            let response = post(path, body);
+            assert!(response.starts_with("HTTP/1.0 400") || response.starts_with("HTTP/1.1 400"), "{} {} -> {}", path, body, response.lines().next().unwrap_or_default());
+        }
+    }
+}
+
+#[test]
+fn test_structured_errors() {
+    // Boxed errors convert back into the structured error without nesting
+    let boxed: Box<dyn std::error::Error> = text_to_face::Error::VoiceNotFound("xx_XX-nobody-low".to_string()).into();
+    assert!(matches!(text_to_face::Error::find(boxed.as_ref()), Some(text_to_face::Error::VoiceNotFound(id)) if id == "xx_XX-nobody-low"));
+    assert!(matches!(text_to_face::Error::from(boxed), text_to_face::Error::VoiceNotFound(_)));
+
+    let limit = LimitError::TextTooLong { chars: 20, max: 10 };
+    let boxed: Box<dyn std::error::Error> = limit.clone().into();
+    assert_eq!(text_to_face::Error::from(boxed).limit(), Some(&limit));
+
+    let missing = text_to_face::Error::spawn_failed("sox", "Install SoX.", std::io::Error::from(std::io::ErrorKind::NotFound));
+    assert!(matches!(&missing, text_to_face::Error::ExternalToolMissing { tool, .. } if tool == "sox"));
+    assert_eq!(missing.to_string(), "'sox' executable not found in your PATH. Install SoX.");
+    let denied = text_to_face::Error::spawn_failed("sox", "Install SoX.", std::io::Error::from(std::io::ErrorKind::PermissionDenied));
+    assert!(matches!(denied, text_to_face::Error::Io(_)));
+    assert!(matches!(text_to_face::Error::from("something else"), text_to_face::Error::Other(_)));
+}
+
+#[test]
+fn test_estimated_word_timings_fit_the_speech() {
+    let backend = MockBackend::default();
+    let text = "hi, extraordinary day";
+    let samples = backend.synthesize(text).unwrap();
+    let rate = backend.sample_rate();
+    let phonemes = |p: &[&str]| p.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let words = vec![
+        ("hi,".to_string(), phonemes(&["HH", "AY1"])),
+        ("extraordinary".to_string(), phonemes(&["IH0", "K", "S", "T", "R", "AO1", "R", "D", "AH0", "N", "EH2", "R", "IY0"])),
+        ("day".to_string(), phonemes(&["D", "EY1"])),
+    ];
+    let timings = estimate_word_timings(&words, &samples, rate);
+    assert_eq!(timings.len(), 3);
+    let duration = samples.len() as f32 / rate as f32;
+    for pair in timings.windows(2) {
+        assert!(pair[0].end <= pair[1].start, "'{}' overlaps '{}'", pair[0].word, pair[1].word);
+    }
+    assert!(timings[0].start >= 0.0 && timings[2].end <= duration + 0.01);
+    // The comma leaves a gap, and the long word gets the most time
+    assert!(timings[1].start > timings[0].end);
+    assert!(timings[1].end - timings[1].start > timings[2].end - timings[2].start);
+
+    let phones = phone_spans(&timings[0]);
+    assert_eq!(phones.len(), 2);
+    assert!((phones[1].2 - timings[0].end).abs() < 1e-4);
+
+    let json = estimated_lipsync_json(text, &timings, &["cmudict", "cmudict", "cmudict"]);
+    assert_eq!(json["lipsync"], "estimate");
+    assert_eq!(json["word_segments"].as_array().unwrap().len(), 3);
+    assert_eq!(json["word_segments"][2]["phones"][0]["phoneme"], "D");
+
+    // Silence has nothing to fit words to
+    assert!(estimate_word_timings(&words, &vec![0.0; 22050], rate).is_empty());
+}
+
+#[test]
+fn test_forced_alignment_follows_the_audio() {
+    let rate = 16000u32;
+    let seconds = |s: f32| (s * rate as f32) as usize;
+    let tone = |len: usize| (0..len).map(|i| 0.5 * (i as f32 * 2.0 * std::f32::consts::PI * 150.0 / rate as f32).sin()).collect::<Vec<f32>>();
+    // Deterministic white noise stands in for a fricative
+    let mut seed = 12345u32;
+    let noise = |len: usize, seed: &mut u32| {
+        (0..len)
+            .map(|_| {
+                *seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                (*seed >> 8) as f32 / (1u32 << 24) as f32 * 0.3 - 0.15
+            })
+            .collect::<Vec<f32>>()
+    };
+    // 0.2s silence, "ah" for 0.3s, a 0.4s pause, then "so": 0.15s of hiss and 0.25s of vowel
+    let mut samples = vec![0.0; seconds(0.2)];
+    samples.extend(tone(seconds(0.3)));
+    samples.extend(vec![0.0; seconds(0.4)]);
+    samples.extend(noise(seconds(0.15), &mut seed));
+    samples.extend(tone(seconds(0.25)));
+    samples.extend(vec![0.0; seconds(0.2)]);
+
+    let words = vec![("ah".to_string(), vec!["AA1".to_string()]), ("so".to_string(), vec!["S".to_string(), "OW1".to_string()])];
+    let (timings, phones) = align_word_timings(&words, &samples, rate);
+    assert_eq!(timings.len(), 2);
+    let near = |actual: f32, expected: f32| (actual - expected).abs() < 0.04;
+    assert!(near(timings[0].start, 0.2) && near(timings[0].end, 0.5), "ah at {}..{}", timings[0].start, timings[0].end);
+    // The pause isn't in the text, but the aligner finds it in the audio
+    assert!(near(timings[1].start, 0.9) && near(timings[1].end, 1.3), "so at {}..{}", timings[1].start, timings[1].end);
+    assert_eq!(phones[1].len(), 2);
+    assert_eq!(phones[1][0].0, "S");
+    assert!(near(phones[1][0].2, 1.05), "S ends at {}", phones[1][0].2);
+
+    let json = timed_lipsync_json("align", "ah so", &timings, &phones, &["cmudict", "cmudict"]);
+    assert_eq!(json["lipsync"], "align");
+    assert_eq!(json["word_segments"][1]["phones"][1]["phoneme"], "OW1");
+
+    // Silence falls back to the (empty) estimate
+    assert!(align_word_timings(&words, &vec![0.0; 16000], rate).0.is_empty());
+}
+
+#[test]
+fn test_gltf_lipsync_animation() {
+    let backend = MockBackend::default();
+    let text = "hello world";
+    let samples = backend.synthesize(text).unwrap();
+    let duration = samples.len() as f32 / backend.sample_rate() as f32;
+    let visemes = viseme_track(&backend.word_timings(text).unwrap());
+
+    let keys = weight_keyframes(&visemes, BlendshapeSet::Oculus, duration);
+    assert!(keys.windows(2).all(|pair| pair[0].0 < pair[1].0), "keyframe times must strictly increase");
+    assert!(keys.first().unwrap().1.iter().chain(&keys.last().unwrap().1).all(|w| *w == 0.0));
+    assert!(keys.iter().all(|(_, weights)| weights.len() == 15));
+    assert_eq!(BlendshapeSet::Oculus.weights("BM")[1], 1.0);
+    assert!(BlendshapeSet::Arkit.weights("AA")[0] > BlendshapeSet::Arkit.weights("BM")[0]);
+
+    let glb = lipsync_glb(&visemes, BlendshapeSet::Visemes, duration, Some("hello.wav")).unwrap();
+    assert_eq!(&glb[0..4], b"glTF");
+    assert_eq!(u32::from_le_bytes(glb[8..12].try_into().unwrap()) as usize, glb.len());
+    let json_length = u32::from_le_bytes(glb[12..16].try_into().unwrap()) as usize;
+    assert_eq!(&glb[16..20], b"JSON");
+    let gltf: serde_json::Value = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+    let bin_length = u32::from_le_bytes(glb[20 + json_length..24 + json_length].try_into().unwrap()) as usize;
+    assert_eq!(gltf["buffers"][0]["byteLength"], bin_length);
+
+    let targets = gltf["meshes"][0]["extras"]["targetNames"].as_array().unwrap();
+    assert_eq!(targets[0], "viseme_AA");
+    assert_eq!(gltf["meshes"][0]["primitives"][0]["targets"].as_array().unwrap().len(), targets.len());
+    let sampler = &gltf["animations"][0]["samplers"][0];
+    let times = gltf["accessors"][sampler["input"].as_u64().unwrap() as usize]["count"].as_u64().unwrap() as usize;
+    let weights = gltf["accessors"][sampler["output"].as_u64().unwrap() as usize]["count"].as_u64().unwrap() as usize;
+    assert_eq!(weights, times * targets.len());
+    assert_eq!(gltf["animations"][0]["channels"][0]["target"]["path"], "weights");
+}
+
+#[test]
+fn test_unity_animation_clip() {
+    use text_to_face::unity::{anim_yaml, unity_clip};
+    let backend = MockBackend::default();
+    let text = "hello world";
+    let samples = backend.synthesize(text).unwrap();
+    let duration = samples.len() as f32 / backend.sample_rate() as f32;
+    let visemes = viseme_track(&backend.word_timings(text).unwrap());
+
+    let clip = unity_clip(&visemes, BlendshapeSet::Oculus, duration, 30.0, "hello", "Armature/Head");
+    assert_eq!(clip.curves.len(), 15);
+    assert_eq!(clip.curves[1].blend_shape, "viseme_PP");
+    // Weights are Unity's 0-100, and every curve starts and ends at rest
+    assert!(clip.curves.iter().flat_map(|c| &c.keys).any(|k| k.value == 100.0));
+    assert!(clip.curves.iter().all(|c| c.keys.first().unwrap().value == 0.0 && c.keys.last().unwrap().value == 0.0));
+    assert!(clip.length >= duration);
+
+    let yaml = anim_yaml(&clip);
+    assert!(yaml.starts_with("%YAML 1.1\n%TAG !u! tag:unity3d.com,2011:\n--- !u!74 &7400000\nAnimationClip:\n"));
+    assert!(yaml.contains("    attribute: blendShape.viseme_aa\n    path: Armature/Head\n    classID: 137\n"));
+    assert!(yaml.contains("  m_SampleRate: 30\n"));
+    assert_eq!(yaml.matches("attribute: blendShape.").count(), 30, "float and editor curves");
+}
+
+#[test]
+fn test_warnings_are_collected_and_structured() {
+    assert_eq!(clipping(&[0.5, -1.0, 1.0]), None);
+    assert_eq!(clipping(&[0.5, 1.25, -1.5]), Some(Warning::Clipped { samples: 2, peak: 1.5 }));
+
+    let whisperx = serde_json::json!({ "word_segments": [
+        { "word": "Hello", "start": 0.1, "end": 0.4, "score": 0.91 },
+        { "word": "Xochitl", "start": 0.5, "end": 0.9, "score": 0.21 },
+        { "word": "42" },
+    ]});
+    let low = low_confidence_words(&whisperx);
+    assert_eq!(low, vec![Warning::LowAlignmentConfidence { word: "Xochitl".to_string(), start: Some(0.5), score: 0.21 }]);
+
+    warn(low[0].clone());
+    warn(Warning::OutOfVocabulary { word: "Zyzzyva".to_string(), reason: "not in CMUdict".to_string() });
+    let warnings = take_warnings();
+    assert_eq!(warnings.len(), 2);
+    assert!(take_warnings().is_empty(), "taking warnings clears them");
+    assert_eq!(warnings[1].to_string(), "No phonemes for 'Zyzzyva': not in CMUdict");
+    let json = serde_json::to_value(&warnings[1]).unwrap();
+    assert_eq!(json["kind"], "out_of_vocabulary");
+}
+
+#[test]
+fn test_dialogue_script() {
+    use text_to_face::dialogue::parse_script;
+    let script = parse_script(
+        "# Cast\nALBA = en_GB-alba-medium\nOld Man = en_US-ryan-high pitch=deep tempo=1.1\n\nALBA: Hello there.\nold man: Hi! Have we met?\n  I think x = y.\n",
+    )
+    .unwrap();
+    let lines: Vec<(&str, &str)> = script.lines.iter().map(|l| (l.character.as_str(), l.text.as_str())).collect();
+    assert_eq!(lines, [("ALBA", "Hello there."), ("old man", "Hi! Have we met? I think x = y.")]);
+    let old_man = script.settings("old man").unwrap();
+    assert_eq!(old_man.voice, "en_US-ryan-high");
+    assert_eq!((old_man.pitch, old_man.tempo), (0.85, 1.1));
+
+    let error = parse_script("ALBA = en_GB-alba-medium\nRYAN: Hi!").unwrap_err();
+    assert!(error.contains("RYAN has no voice"), "{}", error);
+    assert!(parse_script("Hello there.").is_err());
+    assert!(parse_script("ALBA = en_GB-alba-medium volume=2\nALBA: Hi").unwrap_err().contains("unknown setting"));
+}
+
+#[test]
+fn test_voice_directives() {
+    let narrator = VoiceSettings { voice: "en_GB-alba-medium".to_string(), pitch: 1.0, tempo: 1.0 };
+    let text = "Once upon a time. @voice(en_US-ryan-high) @pitch(0.9) Who goes there? @voice(en_GB-alba-medium) @pitch(1.0) said the guard.\n\n@tempo(1.2) Slowly now.";
+    assert!(has_directives(text));
+    let parts = split_directives(text, &narrator).unwrap();
+    let summary: Vec<(&str, f32, f32, &str)> = parts.iter().map(|p| (p.settings.voice.as_str(), p.settings.pitch, p.settings.tempo, p.text.as_str())).collect();
+    assert_eq!(
+        summary,
+        [
+            ("en_GB-alba-medium", 1.0, 1.0, "Once upon a time."),
+            ("en_US-ryan-high", 0.9, 1.0, "Who goes there?"),
+            ("en_GB-alba-medium", 1.0, 1.0, "said the guard."),
+            ("en_GB-alba-medium", 1.0, 1.2, "Slowly now."),
+        ]
+    );
+    assert_eq!(strip_directives(text), "Once upon a time. Who goes there? said the guard.\n\nSlowly now.");
+
+    // Presets work, and anything else that looks like a directive is just text
+    let parts = split_directives("@pitch(deep) Mail me@pitch.com or @someone(else).", &narrator).unwrap();
+    assert_eq!(parts.len(), 1);
+    assert_eq!(parts[0].settings.pitch, 0.85);
+    assert_eq!(parts[0].text, "Mail me@pitch.com or @someone(else).");
+    assert!(!has_directives("Mail me@pitch.com"));
+    assert!(split_directives("@tempo(fast) Go.", &narrator).is_err());
+}
+
+#[test]
+fn test_phoneme_source_order_and_lexicon() {
+    let _settings = lock_settings();
+    let general: SourceOrder = "cmudict, llm".parse().unwrap();
+    assert_eq!(general, SourceOrder { language: None, sources: vec![PhonemeSource::Cmudict, PhonemeSource::Llm] });
+    let german: SourceOrder = "de=lexicon,llm".parse().unwrap();
+    assert!("cmudict,espeak".parse::<SourceOrder>().is_err());
+    assert!("de=".parse::<SourceOrder>().is_err());
+
+    let mut settings = PhonemeSourceSettings::default();
+    assert_eq!(settings.sources(), DEFAULT_SOURCES);
+    settings.orders = vec![general, german];
+    assert_eq!(settings.sources(), [PhonemeSource::Cmudict, PhonemeSource::Llm]);
+    settings.language = Some("de".to_string());
+    assert_eq!(settings.sources(), [PhonemeSource::Lexicon, PhonemeSource::Llm]);
+    settings.language = Some("fr".to_string());
+    assert_eq!(settings.sources(), [PhonemeSource::Cmudict, PhonemeSource::Llm]);
+
+    let lexicon = Lexicon::parse("# Character names\nHERMIONE  hh er0 m ay1 ah0 n iy0\n\nSiobhan {shiv-AWN}\n").unwrap();
+    assert_eq!(lexicon.get("Hermione,").unwrap(), &["HH", "ER0", "M", "AY1", "AH0", "N", "IY0"]);
+    assert_eq!(lexicon.get("SIOBHAN").unwrap(), &respelling_to_arpabet("shiv-AWN").unwrap());
+    assert!(lexicon.get("Ron").is_none());
+    let error = Lexicon::parse("GOOD G UH1 D\nBAD B XX D\n").unwrap_err();
+    assert!(error.starts_with("line 2"), "{}", error);
+
+    // CMUdict's own format: ;;; comments, alternates, and trailing notes
+    let dict = Lexicon::parse(";;; names\nzelda Z EH1 L D AH0\nzelda(2) Z EH1 L D AA0\nganon G AE1 N AH0 N # villain\n").unwrap();
+    assert_eq!(dict.get("Zelda").unwrap(), &["Z", "EH1", "L", "D", "AH0"]);
+    assert_eq!(dict.get("ganon").unwrap(), &["G", "AE1", "N", "AH0", "N"]);
+
+    // Registered lexicons stack, the most recent winning
+    set_lexicon(Some(lexicon));
+    register_lexicon(Lexicon::parse("HERMIONE HH ER1 M IY0 OW0 N\n").unwrap());
+    assert_eq!(lexicon_phonemes("Hermione").unwrap(), ["HH", "ER1", "M", "IY0", "OW0", "N"]);
+    assert!(lexicon_phonemes("Siobhan").is_some());
+    set_lexicon(None);
+    assert!(lexicon_phonemes("Siobhan").is_none());
+}
+
+#[test]
+fn test_trim_range_cuts_audio_and_shifts_lipsync() {
+    let samples: Vec<f32> = (0..1000).map(|i| i as f32).collect();
+    let range = TrimRange { start: 0.25, max_duration: Some(0.5) };
+    let kept = range.samples(&samples, 1000);
+    assert_eq!((kept.len(), kept[0]), (500, 250.0));
+    assert_eq!(TrimRange { start: 0.9, max_duration: Some(5.0) }.samples(&samples, 1000).len(), 100);
+    assert!(TrimRange::default().is_whole());
+
+    let word = |w: &str, start: f32, end: f32| serde_json::json!({ "word": w, "start": start, "end": end });
+    let words = vec![word("one", 0.0, 0.2), word("two", 0.2, 0.4), word("three", 0.5, 0.7), word("four", 0.7, 0.9)];
+    let mut json = serde_json::json!({ "segments": [{ "text": "one two three four", "start": 0.0, "end": 0.9, "words": words }], "word_segments": words });
+    range.lipsync_json(&mut json, 1.0);
+    let kept: Vec<(&str, f64, f64)> = json["word_segments"].as_array().unwrap().iter().map(|w| (w["word"].as_str().unwrap(), w["start"].as_f64().unwrap(), w["end"].as_f64().unwrap())).collect();
+    assert_eq!(kept, [("two", 0.0, 0.15), ("three", 0.25, 0.45), ("four", 0.45, 0.5)]);
+    assert_eq!(json["segments"][0]["text"], "two three four");
+    assert_eq!(json["segments"][0]["end"], 0.5);
+}
+
+#[test]
+fn test_trim_silence_around_speech() {
+    // 0.5s of near-silence, 1s of tone, 0.3s of silence at 1 kHz
+    let mut samples = vec![0.0005f32; 500];
+    samples.extend((0..1000).map(|i| (i as f32 * 0.3).sin() * 0.5));
+    samples.extend(vec![0.0f32; 300]);
+    let range = TrimRange::around_sound(&samples, 1000, DEFAULT_SILENCE_THRESHOLD_DB);
+    assert!((range.start - 0.48).abs() < 0.011, "start {}", range.start);
+    assert!((range.max_duration.unwrap() - 1.04).abs() < 0.021, "duration {:?}", range.max_duration);
+    assert_eq!(trim_silence(&samples, 1000, DEFAULT_SILENCE_THRESHOLD_DB).len(), range.samples(&samples, 1000).len());
+    // Audio that is silent throughout, or sound throughout, is left whole
+    assert!(TrimRange::around_sound(&[0.0; 400], 1000, -50.0).is_whole());
+    assert!(TrimRange::around_sound(&samples[500..1500], 1000, -50.0).is_whole());
+}
+
+#[test]
+fn test_import_alignment_from_other_shapes() {
+    let whisperx = serde_json::json!({
+        "segments": [{ "text": " Hello there.", "start": 0.1, "end": 0.9, "words": [
+            { "word": "Hello", "start": 0.1, "end": 0.4, "score": 0.9 },
+            { "word": "there.", "start": 0.5, "end": 0.9, "score": 0.8 },
+        ] }],
+        "word_segments": [],
+    });
+    let imported = import_alignment(&whisperx).unwrap();
+    assert_eq!(imported["word_segments"].as_array().unwrap().len(), 2);
+    assert_eq!(imported["segments"][0]["text"], "Hello there.");
+
+    let cloud = serde_json::json!({ "words": [{ "text": "Hello", "start_ms": 100, "end_ms": 400, "confidence": 0.3 }, { "token": "there", "start_time": 0.5, "end_time": 0.9 }] });
+    let mut imported = import_alignment(&cloud).unwrap();
+    assert_eq!(imported["word_segments"][0], serde_json::json!({ "word": "Hello", "start": 0.1, "end": 0.4, "score": 0.3 }));
+    assert_eq!(import_alignment(&cloud["words"]).unwrap(), imported, "a bare list of words works too");
+    assert_eq!(low_confidence_words(&imported).len(), 1);
+
+    add_phonemes(&mut imported, &[(vec!["HH".to_string(), "AH0".to_string(), "L".to_string(), "OW1".to_string()], "cmudict"), (vec![], "user_manual")]);
+    assert_eq!(imported["word_segments"][0]["phoneme_method"], "cmudict");
+    add_visemes(&mut imported);
+    let visemes: Vec<&str> = imported["visemes"].as_array().unwrap().iter().map(|v| v["viseme"].as_str().unwrap()).collect();
+    assert_eq!(visemes, ["sil", "GK", "AA", "L", "O", "sil", "AA"]);
+
+    assert!(import_alignment(&serde_json::json!({ "transcript": "Hello" })).is_err());
+    assert!(import_alignment(&serde_json::json!([])).is_err());
+}
+
+/// Frequency of a steady tone from its rising zero crossings, over the middle half to skip the edges
+fn tone_frequency(samples: &[f32], sample_rate: f32) -> f32 {
+    let middle = &samples[samples.len() / 4..samples.len() * 3 / 4];
+    let crossings = middle.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+    crossings as f32 * sample_rate / middle.len() as f32
+}
+
+#[test]
+fn test_native_pitch_shift_keeps_length() {
+    let rate = 22050.0;
+    let tone: Vec<f32> = (0..22050).map(|i| 0.5 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / rate).sin()).collect();
+    assert_eq!(tone_frequency(&tone, rate).round(), 220.0);
+
+    for (factor, expected) in [(1.5, 330.0), (0.75, 165.0), (2.0, 440.0)] {
+        let shifted = dsp::pitch_shift(&tone, 22050, factor);
+        assert_eq!(shifted.len(), tone.len(), "pitch shifting shouldn't change the length");
+        let frequency = tone_frequency(&shifted, rate);
+        assert!((frequency - expected).abs() < expected * 0.03, "factor {}: {} Hz, expected {}", factor, frequency, expected);
+    }
+    // Stretching changes the length but not the pitch
+    let stretched = dsp::time_stretch(&tone, 1.5);
+    assert_eq!(stretched.len(), 33075);
+    assert!((tone_frequency(&stretched, rate) - 220.0).abs() < 220.0 * 0.03);
+    assert_eq!(dsp::pitch_shift(&tone, 22050, 1.0), tone);
+}
+
+#[test]
+fn test_wsola_stretch_keeps_pitch() {
+    let _settings = lock_settings();
+    let rate = 22050.0;
+    let tone: Vec<f32> = (0..22050).map(|i| 0.5 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / rate).sin()).collect();
+    for (factor, length) in [(1.5, 33075), (0.75, 16538)] {
+        let stretched = dsp::wsola_stretch(&tone, 22050, factor);
+        assert_eq!(stretched.len(), length);
+        let frequency = tone_frequency(&stretched, rate);
+        assert!((frequency - 220.0).abs() < 220.0 * 0.03, "factor {}: {} Hz", factor, frequency);
+        // Windows are lined up with the waveform, so there are no jumps at their edges
+        let steady = &stretched[2000..stretched.len() - 2000];
+        let largest_step = steady.windows(2).map(|w| (w[1] - w[0]).abs()).fold(0.0f32, f32::max);
+        assert!(largest_step < 0.05, "factor {}: step of {}", factor, largest_step);
+    }
+    assert!(dsp::wsola_stretch(&[], 22050, 1.5).is_empty());
+
+    // Both qualities agree on the direction: above 1.0 is slower (longer), below is faster
+    for quality in [dsp::StretchQuality::Fast, dsp::StretchQuality::High] {
+        dsp::set_stretch_quality(quality);
+        let (slower, faster) = (text_to_face::time_stretch(&tone, 22050, 2.0), text_to_face::time_stretch(&tone, 22050, 0.5));
+        dsp::set_stretch_quality(dsp::StretchQuality::Fast);
+        assert_eq!((slower.len(), faster.len()), (44100, 11025), "{:?}", quality);
+    }
+    assert_eq!("HIGH".parse::<dsp::StretchQuality>().unwrap(), dsp::StretchQuality::High);
+}
+
+#[test]
+fn test_rate_presets() {
+    let _settings = lock_settings();
+    assert_eq!("very-fast".parse::<RatePreset>().unwrap(), RatePreset::VeryFast);
+    assert_eq!("Very_Fast".parse::<RatePreset>().unwrap(), RatePreset::VeryFast);
+    assert!("ludicrous".parse::<RatePreset>().is_err());
+
+    assert_eq!(RatePreset::Normal.settings(Some("es")), RateSettings::NORMAL);
+    let presets = [RatePreset::Slow, RatePreset::Normal, RatePreset::Fast, RatePreset::VeryFast];
+    // Each preset is faster overall than the one before it, in every language
+    for language in [None, Some("en"), Some("es")] {
+        let pace: Vec<f32> = presets.iter().map(|p| p.settings(language)).map(|r| r.length_scale * r.tempo).collect();
+        assert!(pace.windows(2).all(|w| w[0] > w[1]), "{:?}: {:?}", language, pace);
+    }
+    let (english, spanish) = (RatePreset::Fast.settings(Some("en")), RatePreset::Fast.settings(Some("es")));
+    assert!(spanish.length_scale > english.length_scale, "brisk languages speed up less");
+    assert_eq!(RatePreset::Fast.settings(None), english);
+
+    set_rate(RatePreset::VeryFast.settings(None));
+    assert!(rated_tempo(1.0) < 1.0);
+    set_rate(RateSettings::NORMAL);
+    assert_eq!(rated_tempo(1.5), 1.5);
+
+    // Rendered durations: the length scale sets how long the model's audio is, then the stretch applies
+    let tone: Vec<f32> = (0..22050).map(|i| 0.5 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / 22050.0).sin()).collect();
+    let durations: Vec<usize> = presets
+        .iter()
+        .map(|p| p.settings(Some("en")))
+        .map(|r| text_to_face::time_stretch(&tone[..(tone.len() as f32 * r.length_scale / 1.25) as usize], 22050, r.tempo).len())
+        .collect();
+    assert!(durations.windows(2).all(|w| w[0] > w[1]), "each preset should render shorter than the one before: {:?}", durations);
+}
+
+#[test]
+fn test_nice_settings() {
+    let cores = std::thread::available_parallelism().map(usize::from).unwrap_or(1);
+    assert_eq!(NiceSettings::new(None).threads, (cores / 4).max(1));
+    assert_eq!(NiceSettings::new(Some(1)).threads, 1);
+    assert_eq!(NiceSettings::new(Some(cores + 8)).threads, cores, "never more cores than the machine has");
+    // Off unless a command turns it on: tools keep their own threading
+    assert!(child_env().is_empty());
+    assert!(thread_args("--threads").is_empty());
+}
+
+#[test]
+fn test_voice_config_overrides() {
+    let mut config = serde_json::json!({ "audio": { "sample_rate": 22050, "quality": "medium" }, "inference": { "noise_scale": 0.667, "length_scale": 1.0 }, "phoneme_id_map": { "a": [14] } });
+    merge_patch(&mut config, &serde_json::json!({ "inference": { "length_scale": 1.1 }, "phoneme_id_map": { "ɐ": [14] }, "audio": { "quality": null } }));
+    assert_eq!(config["inference"], serde_json::json!({ "noise_scale": 0.667, "length_scale": 1.1 }));
+    assert_eq!(config["phoneme_id_map"], serde_json::json!({ "a": [14], "ɐ": [14] }));
+    assert_eq!(config["audio"], serde_json::json!({ "sample_rate": 22050 }));
+
+    // The downloaded files stay as they are; the patched config sits beside a link to the model
+    let dir = std::env::temp_dir().join(format!("pitch_tts_overrides_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let original = r#"{"audio":{"sample_rate":22050},"inference":{"length_scale":1.0}}"#;
+    std::fs::write(dir.join("xx_XX-test-medium.onnx.json"), original).unwrap();
+    std::fs::write(dir.join("xx_XX-test-medium.onnx"), b"model").unwrap();
+    std::fs::write(dir.join("override.json"), r#"{"inference":{"length_scale":0.9}}"#).unwrap();
+    let overlaid = overlaid_config(&dir.join("xx_XX-test-medium.onnx.json"), &dir.join("override.json")).unwrap();
+    assert_eq!(overlaid.file_name().unwrap(), "xx_XX-test-medium.onnx.json");
+    assert_ne!(overlaid.parent().unwrap(), dir);
+    let patched: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&overlaid).unwrap()).unwrap();
+    assert_eq!(patched["inference"]["length_scale"], 0.9);
+    assert_eq!(std::fs::read(overlaid.with_file_name("xx_XX-test-medium.onnx")).unwrap(), b"model");
+    assert_eq!(std::fs::read_to_string(dir.join("xx_XX-test-medium.onnx.json")).unwrap(), original);
+
+    std::fs::write(dir.join("override.json"), "[1, 2]").unwrap();
+    assert!(overlaid_config(&dir.join("xx_XX-test-medium.onnx.json"), &dir.join("override.json")).is_err());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_wav_markers_round_trip() {
+    let word = |w: &str, start: f32, end: f32| text_to_face::WordTiming { word: w.to_string(), start, end, phonemes: vec![] };
+    let words = vec![word("Hello", 0.1, 0.4), word("there.", 0.45, 0.8), word("How's", 1.2, 1.4), word("it?", 1.4, 1.6)];
+    let sentences = markers(&words, 22050, MarkerSet::Sentences);
+    assert_eq!(sentences, [Marker { position: 2205, label: "Hello there.".to_string() }, Marker { position: 26460, label: "How's it?".to_string() }]);
+    let all = markers(&words, 22050, MarkerSet::All);
+    assert_eq!(all.len(), 6);
+    assert_eq!((all[0].label.as_str(), all[1].label.as_str()), ("Hello there.", "Hello"), "a sentence marker comes before its first word");
+    assert_eq!(markers(&words, 48000, MarkerSet::Words)[1].position, 21600);
+
+    let path = std::env::temp_dir().join(format!("pitch_tts_markers_{}.wav", std::process::id()));
+    let path = path.to_str().unwrap();
+    text_to_face::write_wav(path, &vec![0.0; 22050 * 2], 22050).unwrap();
+    text_to_face::tags::tag_wav(path, &text_to_face::tags::AudioTags::for_synthesis("mock", 1.0, 1.0)).unwrap();
+    write_wav_markers(path, &all).unwrap();
+    assert_eq!(read_wav_markers(path).unwrap(), all);
+    // Still a valid WAV with the same audio
+    assert_eq!(text_to_face::read_wav(path).unwrap().0.len(), 22050 * 2);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn test_ssml_parsing() {
+    let speech = |text: &str, pitch: f32, tempo: f32| SsmlPart::Speech(SpokenPart { text: text.to_string(), pitch, tempo });
+    let parts = parse_ssml("<speak>Hello <break time=\"500ms\"/> there\n  friend<break strength='strong'/></speak>").unwrap();
+    assert_eq!(parts, vec![speech("Hello", 1.0, 1.0), SsmlPart::Break(Duration::from_millis(500)), speech("there friend", 1.0, 1.0), SsmlPart::Break(Duration::from_millis(700))]);
+
+    // Nested prosody multiplies; a slower rate is a longer tempo
+    let parts = parse_ssml(r#"<speak><prosody rate="50%" pitch="+12st">up <prosody pitch="50%">back</prosody></prosody> <emphasis level="strong">now</emphasis></speak>"#).unwrap();
+    let SsmlPart::Speech(up) = &parts[0] else { panic!("expected speech") };
+    assert!((up.pitch - 2.0).abs() < 1e-4 && (up.tempo - 2.0).abs() < 1e-4);
+    let SsmlPart::Speech(back) = &parts[1] else { panic!("expected speech") };
+    assert!((back.pitch - 1.0).abs() < 1e-4 && (back.tempo - 2.0).abs() < 1e-4);
+    assert_eq!(parts[2], speech("now", 1.1, 1.15));
+
+    let parts = parse_ssml(r#"<speak><p>Call <say-as interpret-as="digits">555-12</say-as>, say <say-as interpret-as="characters">tts</say-as> &amp; come <say-as interpret-as="ordinal">22</say-as><!-- note --></p></speak>"#).unwrap();
+    assert_eq!(spoken_text(&parts), "Call 5 5 5 1 2 , say T T S & come 22nd");
+
+    assert!(parse_ssml("<speak><prosody rate=\"slow\">oops</emphasis></speak>").is_err());
+    assert!(parse_ssml("<speak>never closed").is_err());
+    assert!(parse_ssml("<speak><prosody pitch=\"shrill\">x</prosody></speak>").is_err());
+}
+
+#[test]
+fn test_flac_encoding() {
+    let samples: Vec<i16> = (0..22050).map(|i| ((i as f32 * 0.05).sin() * 12000.0) as i16).collect();
+    let tags = text_to_face::tags::AudioTags { title: Some("Line".to_string()), ..text_to_face::tags::AudioTags::for_synthesis("mock", 1.0, 1.0) };
+    let flac = encode_flac(&samples, 22050, &tags);
+    assert_eq!(&flac[..4], b"fLaC");
+    // STREAMINFO: 34 bytes, not the last metadata block
+    assert_eq!(&flac[4..8], &[0x00, 0x00, 0x00, 34]);
+    let info = u64::from_be_bytes(flac[18..26].try_into().unwrap());
+    assert_eq!(info >> 44, 22050, "sample rate");
+    assert_eq!((info >> 36) & 0x1F, 15, "16 bits per sample");
+    assert_eq!(info & 0xF_FFFF_FFFF, samples.len() as u64, "total samples");
+    // Vorbis comments follow as the last block
+    assert_eq!(flac[42], 0x84);
+    let text = String::from_utf8_lossy(&flac);
+    assert!(text.contains("TITLE=Line") && text.contains("COMMENT=voice=mock"));
+    // The first frame starts with the sync code, and a smooth tone compresses well below 16-bit PCM
+    let comments_len = u32::from_be_bytes([0, flac[43], flac[44], flac[45]]) as usize;
+    assert_eq!(&flac[46 + comments_len..48 + comments_len], &[0xFF, 0xF8]);
+    assert!(flac.len() < samples.len(), "{} bytes is over half the PCM size", flac.len());
+
+    assert_eq!(AudioFormat::from_path(Path::new("out/line.MP3")), Some(AudioFormat::Mp3));
+    assert_eq!(AudioFormat::from_path(Path::new("line.txt")), None);
+    assert_eq!(AudioFormat::from_extension("Ogg"), Some(AudioFormat::Ogg));
+    assert_eq!(AudioFormat::Mp3.mime_type(), "audio/mpeg");
+    assert_eq!(EncodeSettings { format: AudioFormat::Ogg, ..EncodeSettings::default() }.describe(), "ogg q5");
+    assert_eq!(EncodeSettings { format: AudioFormat::Mp3, bitrate_kbps: Some(96), ..EncodeSettings::default() }.describe(), "mp3 96k");
+
+    assert_eq!(AudioFormat::from_path(Path::new("bank/line.opus")), Some(AudioFormat::Opus));
+    assert_eq!(EncodeSettings { format: AudioFormat::Opus, ..EncodeSettings::default() }.describe(), "opus 32k 20ms");
+    assert_eq!(EncodeSettings { format: AudioFormat::Opus, bitrate_kbps: Some(24), opus_frame_ms: 2.5, ..EncodeSettings::default() }.describe(), "opus 24k 2.5ms");
+    assert_eq!(text_to_face::encode::parse_opus_frame_ms("60ms"), Ok(60.0));
+    assert!(text_to_face::encode::parse_opus_frame_ms("30").is_err());
+}
+
+#[test]
+fn test_sample_rate_from_voice_config() {
+    let config = serde_json::json!({ "audio": { "sample_rate": 16000, "quality": "x_low" } });
+    assert_eq!(text_to_face::config_sample_rate(&config), Some(16000));
+    assert_eq!(text_to_face::config_sample_rate(&serde_json::json!({ "audio": {} })), None);
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_voice_sample_rate() {
+    assert_eq!(text_to_face::voice_sample_rate(text_to_face::backend::MOCK_VOICE), MockBackend::default().sample_rate);
+    // x_low voices are 16 kHz whether or not they're installed
+    assert_eq!(text_to_face::voice_sample_rate("it_IT-riccardo-x_low"), 16000);
+    assert_eq!(text_to_face::voice_sample_rate("not-a-voice"), text_to_face::DEFAULT_SAMPLE_RATE);
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_synth_streaming_mock() {
+    use text_to_face::streaming::synth_streaming;
+    let stream = synth_streaming("Hello there. How are you?", text_to_face::backend::MOCK_VOICE, &text_to_face::PitchArg::Value(1.0), 1.0).unwrap();
+    assert_eq!(stream.sample_rate, MockBackend::default().sample_rate);
+    let samples: Vec<f32> = stream.flat_map(|chunk| chunk.unwrap()).collect();
+    assert!(!samples.is_empty());
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn test_synth_async_mock() {
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let (samples, sample_rate) = runtime
+        .block_on(text_to_face::synth_async("Hello there", text_to_face::backend::MOCK_VOICE, &text_to_face::PitchArg::Value(1.0), 1.0))
+        .unwrap();
+    assert_eq!(sample_rate, MockBackend::default().sample_rate);
+    assert!(!samples.is_empty());
+    assert!(matches!(runtime.block_on(text_to_face::download_voice_async("not-a-voice")), Err(text_to_face::Error::VoiceNotFound(_))));
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn test_ffi_synthesize() {
+    use std::ffi::{CStr, CString};
+    use text_to_face::ffi::*;
+    let text = CString::new("Hello there").unwrap();
+    let voice = CString::new(text_to_face::backend::MOCK_VOICE).unwrap();
+    let mut audio = PitchTtsAudio { samples: std::ptr::null_mut(), len: 0, sample_rate: 0 };
+    unsafe {
+        assert_eq!(pitch_tts_synthesize(text.as_ptr(), voice.as_ptr(), 1.0, 1.0, &mut audio), 0);
+        assert!(audio.len > 0);
+        assert_eq!(audio.sample_rate, MockBackend::default().sample_rate);
+        pitch_tts_free_audio(&mut audio);
+        assert!(audio.samples.is_null());
+        // Failures are reported through the thread's last error
+        assert_eq!(pitch_tts_synthesize(std::ptr::null(), voice.as_ptr(), 1.0, 1.0, &mut audio), -1);
+        assert_eq!(CStr::from_ptr(pitch_tts_last_error()).to_str().unwrap(), "text is null");
+    }
+}
+
+#[test]
+fn test_accelerator_selection() {
+    let _settings = lock_settings();
+    use text_to_face::accelerator::{accelerator, set_accelerator, Accelerator};
+    assert_eq!(accelerator(), Accelerator::Cpu);
+    set_accelerator(Accelerator::Cpu).unwrap();
+    if !Accelerator::Cuda.is_compiled() {
+        let error = set_accelerator(Accelerator::Cuda).unwrap_err();
+        assert!(error.to_string().contains("--features cuda"));
+        assert_eq!(accelerator(), Accelerator::Cpu);
+    }
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_model_cache_capacity() {
+    let _settings = lock_settings();
+    use text_to_face::model_cache;
+    assert_eq!(model_cache::capacity(), model_cache::DEFAULT_CAPACITY);
+    model_cache::set_capacity(0);
+    assert_eq!(model_cache::capacity(), 0);
+    assert!(model_cache::cached_voices().is_empty());
+    model_cache::set_capacity(model_cache::DEFAULT_CAPACITY);
+    model_cache::clear();
+    assert!(model_cache::cached_voices().is_empty());
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_audiobook_chapter_files() {
+    use text_to_face::audiobook::{read_chapter_files, synthesize_chapters, AudiobookMetadata, AudiobookOptions, MANIFEST_FILE};
+    let dir = std::env::temp_dir().join(format!("text_to_face_chapters_{}", std::process::id()));
+    let (input, output) = (dir.join("input"), dir.join("output"));
+    std::fs::create_dir_all(&input).unwrap();
+    std::fs::write(input.join("02.md"), "# The Storm\nThe wind rose.").unwrap();
+    std::fs::write(input.join("01.txt"), "It was a calm day.").unwrap();
+    std::fs::write(input.join("cover.png"), "not text").unwrap();
+    let chapters = read_chapter_files(&input).unwrap();
+    let titles: Vec<&str> = chapters.iter().map(|c| c.title.as_str()).collect();
+    assert_eq!(titles, ["01", "The Storm"]);
+    assert_eq!(chapters[1].text, "The wind rose.");
+
+    let options = AudiobookOptions { chapter_files: true, ..AudiobookOptions::default() };
+    let output_str = output.to_string_lossy();
+    synthesize_chapters(&chapters, text_to_face::backend::MOCK_VOICE, &text_to_face::PitchArg::Value(1.0), 1.0, &output_str, &AudiobookMetadata::default(), &options).unwrap();
+    assert!(output.join("chapter_001.wav").exists() && output.join("chapter_002.wav").exists());
+    let manifest: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(output.join(MANIFEST_FILE)).unwrap()).unwrap();
+    assert_eq!(manifest["chapters"][1]["title"], "The Storm");
+    assert_eq!(manifest["chapters"][1]["file"], "chapter_002.wav");
+    let first = manifest["chapters"][0]["duration"].as_f64().unwrap();
+    assert!(first > 0.0);
+    assert!((manifest["chapters"][1]["start"].as_f64().unwrap() - first).abs() < 1e-3);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_dialogue_mock() {
+    use text_to_face::dialogue::{parse_script, synthesize_dialogue};
+    let mock = text_to_face::backend::MOCK_VOICE;
+    let script = parse_script(&format!("A = {mock}\nB = {mock} pitch=1.2\nA: Hello there.\nB: General greetings.")).unwrap();
+    let gap = std::time::Duration::from_millis(500);
+    let (samples, sample_rate, spoken) = synthesize_dialogue(&script, gap, 1.0, 1.0).unwrap();
+    assert_eq!(spoken.len(), 2);
+    assert_eq!(spoken[1].character, "B");
+    assert!((spoken[1].start - spoken[0].end - 0.5).abs() < 0.01, "lines should be a gap apart");
+    assert!((spoken[1].end - samples.len() as f32 / sample_rate as f32).abs() < 0.01);
+}
+
+#[cfg(all(unix, feature = "full"))]
+#[test]
+fn test_daemon_job_queue() {
+    use text_to_face::daemon::{run, send, DaemonOptions, JobSpec, JobState, Request, Response};
+    let dir = std::env::temp_dir().join(format!("text_to_face_daemon_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let socket = dir.join("daemon.sock").to_string_lossy().into_owned();
+    let options = DaemonOptions { socket: socket.clone(), default_voice: text_to_face::backend::MOCK_VOICE.to_string() };
+    let daemon = std::thread::spawn(move || run(&options).unwrap());
+    while send(&socket, &Request::Status { job: None }).is_err() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let output = dir.join("hello.wav").to_string_lossy().into_owned();
+    let spec = JobSpec { text: "Hello there".to_string(), voice: None, pitch: 1.0, tempo: 1.0, output: output.clone(), lipsync: None };
+    let Ok(Response::Job(job)) = send(&socket, &Request::Enqueue(spec.clone())) else { panic!("enqueue failed") };
+    let Ok(Response::Job(done)) = send(&socket, &Request::Wait { job: job.id }) else { panic!("wait failed") };
+    assert_eq!(done.state, JobState::Done);
+    assert!(std::path::Path::new(&output).exists());
+    assert!(matches!(send(&socket, &Request::Cancel { job: job.id }), Ok(Response::Error(_))));
+    let relative = JobSpec { output: "hello.wav".to_string(), ..spec.clone() };
+    assert!(matches!(send(&socket, &Request::Enqueue(relative)), Ok(Response::Error(_))));
+    // Factors that would make the worker's resampler blow up are refused up front
+    for (pitch, tempo) in [(0.0, 1.0), (-1.0, 1.0), (1.0, 0.0), (1.0, 1000.0)] {
+        let bad = JobSpec { pitch, tempo, ..spec.clone() };
+        assert!(matches!(send(&socket, &Request::Enqueue(bad)), Ok(Response::Error(_))), "pitch {} tempo {}", pitch, tempo);
+    }
+
+    assert!(matches!(send(&socket, &Request::Shutdown), Ok(Response::Ok)));
+    daemon.join().unwrap();
+    assert!(!std::path::Path::new(&socket).exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_voice_language_matching() {
+    assert!(text_to_face::voice_matches_language("en_GB-alba-medium", "en"));
+    assert!(text_to_face::voice_matches_language("en_GB-alba-medium", "en-gb"));
+    assert!(text_to_face::voice_matches_language("en_GB-alba-medium", "en_GB"));
+    assert!(!text_to_face::voice_matches_language("en_GB-alba-medium", "en_US"));
+    assert!(!text_to_face::voice_matches_language("de_DE-thorsten-medium", "en"));
+    assert!(!text_to_face::voice_matches_language("en_GB-alba-medium", ""));
+}
+
+#[test]
+fn test_speaker_selection() {
+    use text_to_face::speakers::{config_speakers, resolve_speaker, SpeakerSelection};
+    let config = serde_json::json!({ "num_speakers": 3, "speaker_id_map": { "p239": 1, "p225": 0, "p227": 2 } });
+    let speakers = config_speakers(&config);
+    assert_eq!(speakers.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![0, 1, 2]);
+    assert_eq!(resolve_speaker(&speakers, &"p239".parse::<SpeakerSelection>().unwrap()), Ok(1));
+    assert_eq!(resolve_speaker(&speakers, &"2".parse::<SpeakerSelection>().unwrap()), Ok(2));
+    assert!(resolve_speaker(&speakers, &SpeakerSelection::Id(7)).is_err());
+    assert!(resolve_speaker(&speakers, &SpeakerSelection::Name("p999".to_string())).is_err());
+    // Single-speaker voices have nobody to pick
+    let single = config_speakers(&serde_json::json!({ "num_speakers": 1, "speaker_id_map": {} }));
+    assert!(single.is_empty());
+    assert!(resolve_speaker(&single, &SpeakerSelection::Id(0)).is_err());
+}
+
+#[test]
+fn test_rhubarb_cues() {
+    use text_to_face::rhubarb::{arpabet_to_rhubarb, mouth_cues, rhubarb_json, rhubarb_tsv};
+    assert_eq!(arpabet_to_rhubarb("M"), 'A');
+    assert_eq!(arpabet_to_rhubarb("AA1"), 'D');
+    assert_eq!(arpabet_to_rhubarb("UW0"), 'F');
+    assert_eq!(arpabet_to_rhubarb("V"), 'G');
+    assert_eq!(arpabet_to_rhubarb("L"), 'H');
+    let words = vec![text_to_face::WordTiming { word: "mama".to_string(), start: 0.5, end: 0.9, phonemes: vec!["M".into(), "AA1".into(), "M".into(), "AH0".into()] }];
+    let cues = mouth_cues(&words, 1.2);
+    let shapes: String = cues.iter().map(|c| c.value).collect();
+    assert_eq!(shapes, "XADACX");
+    assert_eq!(cues.last().unwrap().end, 1.2);
+    let tsv = rhubarb_tsv(&cues);
+    assert!(tsv.starts_with("0.00\tX\n0.50\tA\n"));
+    assert!(tsv.ends_with("0.90\tX\n"));
+    let json = rhubarb_json(&cues, "mama.wav", 1.2);
+    assert_eq!(json["metadata"]["duration"], 1.2);
+    assert_eq!(json["mouthCues"][1]["value"], "A");
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_batch_parsing_and_resume() {
+    use text_to_face::batch::{parse_batch_csv, parse_batch_jsonl, run_batch, BatchOptions};
+    let rows = parse_batch_jsonl("{\"text\": \"Hello.\", \"pitch\": \"deep\", \"output\": \"a.wav\"}\n\n{\"text\": \"Bye.\", \"tempo\": 1.5}\n").unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].output.as_deref(), Some("a.wav"));
+    assert!(rows[0].pitch.is_some());
+    assert_eq!(rows[1].tempo, Some(1.5));
+    assert!(parse_batch_jsonl("{\"voice\": \"mock\"}").is_err());
+
+    let rows = parse_batch_csv("text,voice,notes\n\"Say \"\"hi\"\", then\nleave\",mock,x\nSecond line,,\n").unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].text, "Say \"hi\", then\nleave");
+    assert_eq!(rows[0].voice.as_deref(), Some("mock"));
+    assert_eq!(rows[1].row, 2);
+    assert_eq!(rows[1].voice, None);
+    assert!(parse_batch_csv("voice\nmock\n").is_err());
+
+    let dir = std::env::temp_dir().join(format!("text_to_face_batch_{}", std::process::id()));
+    let rows = parse_batch_jsonl("{\"text\": \"One line.\"}\n{\"text\": \"Another line.\", \"output\": \"two.wav\"}\n{\"text\": \"Bad.\", \"output\": \"three.xyz\"}\n").unwrap();
+    let options = BatchOptions { default_voice: text_to_face::backend::MOCK_VOICE.to_string(), default_tempo: 1.0, output_dir: dir.clone(), jobs: 2, force: false };
+    let report = run_batch(&rows, &options);
+    assert_eq!((report.rendered, report.skipped), (2, 0));
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].row, 3);
+    assert!(dir.join("0001.wav").exists() && dir.join("two.wav").exists());
+    // WAV rows are tagged with their synthesis parameters, like exports
+    let wav = std::fs::read(dir.join("two.wav")).unwrap();
+    assert!(wav.windows(10).any(|w| w == b"voice=mock"));
+    // A second run only retries the row that failed
+    let report = run_batch(&rows, &options);
+    assert_eq!((report.rendered, report.skipped, report.failures.len()), (0, 2, 1));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_voice_effects() {
+    use text_to_face::effects::VoiceEffect;
+    let rate = 16000;
+    let tone = |hz: f32| -> Vec<f32> { (0..rate).map(|i| (2.0 * std::f32::consts::PI * hz * i as f32 / rate as f32).sin() * 0.5).collect() };
+    let rms = |s: &[f32]| (s.iter().map(|x| x * x).sum::<f32>() / s.len() as f32).sqrt();
+    let speech: Vec<f32> = tone(150.0).iter().zip(tone(1000.0)).map(|(a, b)| a + b).collect();
+    for name in ["robot", "megaphone", "telephone", "whisper"] {
+        let effect: VoiceEffect = name.parse().unwrap();
+        assert_eq!(effect.name(), name);
+        let output = effect.apply(&speech, rate);
+        // Lipsync timings stay valid, and the level matches the dry signal's peak
+        assert_eq!(output.len(), speech.len());
+        let peak = output.iter().fold(0.0f32, |p, s| p.max(s.abs()));
+        assert!((peak - speech.iter().fold(0.0f32, |p, s| p.max(s.abs()))).abs() < 1e-3, "{} peak {}", name, peak);
+        assert_eq!(output, effect.apply(&speech, rate), "{} is not deterministic", name);
+    }
+    // The telephone band drops a 100 Hz hum far more than a 1 kHz tone
+    let hum = VoiceEffect::Telephone.apply(&[tone(100.0), tone(1000.0)].concat(), rate);
+    assert!(rms(&hum[4000..rate as usize]) < rms(&hum[rate as usize + 4000..]) * 0.3);
+    assert!("kazoo".parse::<VoiceEffect>().is_err());
+}
+
+#[test]
+fn test_stereo_panning() {
+    use text_to_face::stereo::{downmix, pan_gains, StereoOutput};
+    // Constant power: left² + right² is 1 wherever the voice is placed
+    for pan in [-1.0, -0.5, 0.0, 0.3, 1.0] {
+        let (left, right) = pan_gains(pan);
+        assert!((left * left + right * right - 1.0).abs() < 1e-6, "pan {}", pan);
+    }
+    assert!((pan_gains(-1.0).0 - 1.0).abs() < 1e-6 && pan_gains(-1.0).1.abs() < 1e-6);
+    assert!((pan_gains(0.0).0 - pan_gains(0.0).1).abs() < 1e-6);
+    assert_eq!(pan_gains(3.0), pan_gains(1.0));
+
+    let layout = StereoOutput::new(None, Some(0.5)).unwrap();
+    assert_eq!((layout.channels, layout.pan), (2, 0.5));
+    assert_eq!(StereoOutput::new(None, None).unwrap(), StereoOutput::default());
+    assert!(StereoOutput::new(Some(1), Some(0.5)).is_err());
+    let stereo = layout.interleave(&[0.5, -0.25]);
+    assert_eq!(stereo.len(), 4);
+    assert!(stereo[1] > stereo[0], "panned right");
+    assert_eq!(StereoOutput::default().interleave(&[0.5, -0.25]), vec![0.5, -0.25]);
+    assert_eq!(downmix(&[1.0, 0.0, 0.5, 0.5], 2), vec![0.5, 0.5]);
+
+    // A stereo FLAC reports two channels and the frame count in STREAMINFO
+    let frames: Vec<i16> = (0..8000).flat_map(|i| { let s = ((i as f32 * 0.05).sin() * 8000.0) as i16; [s, s / 2] }).collect();
+    let flac = text_to_face::encode::encode_flac_interleaved(&frames, 2, 16000, &text_to_face::tags::AudioTags::for_synthesis("mock", 1.0, 1.0));
+    let info = u64::from_be_bytes(flac[18..26].try_into().unwrap());
+    assert_eq!((info >> 41) & 0x7, 1, "two channels");
+    assert_eq!(info & 0xF_FFFF_FFFF, 8000, "total frames");
+}
+
+/// Records every report, for the progress tests
+#[derive(Default)]
+struct ProgressRecorder {
+    sentences: std::sync::Mutex<Vec<(usize, usize)>>,
+    samples: std::sync::Mutex<Vec<(usize, usize)>>,
+}
+
+impl text_to_face::progress::ProgressHandler for ProgressRecorder {
+    fn sentences(&self, done: usize, total: usize) {
+        self.sentences.lock().unwrap().push((done, total));
+    }
+
+    fn samples(&self, done: usize, total: usize) {
+        self.samples.lock().unwrap().push((done, total));
+    }
+}
+
+#[test]
+fn test_progress_handler_reports_samples() {
+    use text_to_face::progress;
+    let recorder = std::sync::Arc::new(ProgressRecorder::default());
+    let samples: Vec<f32> = (0..12345).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+    let stretched = progress::with_handler(recorder.clone(), || dsp::time_stretch(&samples, 1.5));
+    assert!(!stretched.is_empty());
+    // Other tests' processing may be reported too while the handler is installed
+    let reports: Vec<(usize, usize)> = recorder.samples.lock().unwrap().iter().copied().filter(|(_, total)| *total == samples.len()).collect();
+    assert_eq!(reports.last(), Some(&(12345, 12345)), "A pass should finish at its total: {:?}", reports);
+    assert!(reports.windows(2).all(|w| w[0].0 <= w[1].0), "Progress should only go forward: {:?}", reports);
+    assert!(progress::handler().is_none(), "The handler should be removed afterwards");
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_progress_handler_reports_sentences() {
+    let recorder = std::sync::Arc::new(ProgressRecorder::default());
+    let (samples, _) = text_to_face::progress::with_handler(recorder.clone(), || {
+        text_to_face::synthesize_processed("Hello there.", text_to_face::backend::MOCK_VOICE, &text_to_face::PitchArg::Value(1.2), 1.0)
+    })
+    .unwrap();
+    assert!(recorder.sentences.lock().unwrap().contains(&(1, 1)));
+    assert!(recorder.samples.lock().unwrap().iter().any(|&(done, total)| done == total && total > 0 && total <= samples.len()));
+}
+
+#[test]
+fn test_cancel_token_scope() {
+    use text_to_face::cancel::{self, CancelToken};
+    let token = CancelToken::new();
+    assert!(cancel::with_token(&token, cancel::check).is_ok());
+    token.cancel();
+    assert!(matches!(cancel::with_token(&token, cancel::check), Err(text_to_face::Error::Cancelled)));
+    // Only the thread running the work sees its token
+    assert!(!cancel::with_token(&token, || std::thread::spawn(cancel::is_cancelled).join().unwrap()));
+    assert!(!cancel::is_cancelled(), "The token should be removed afterwards");
+    let samples: Vec<f32> = (0..48000).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+    let stretched = cancel::with_token(&token, || dsp::time_stretch(&samples, 1.5));
+    assert!(stretched.iter().all(|s| *s == 0.0), "A stretch cancelled before it starts should process nothing");
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_cancelled_synthesis_writes_nothing() {
+    use text_to_face::cancel::{self, CancelToken};
+    let path = std::env::temp_dir().join(format!("text_to_face_cancelled_{}.wav", std::process::id()));
+    let token = CancelToken::new();
+    token.cancel();
+    let result = cancel::with_token(&token, || {
+        text_to_face::synthesize_and_handle("Hello there.", text_to_face::backend::MOCK_VOICE, &text_to_face::PitchArg::Value(1.0), 1.0, path.to_str(), false, text_to_face::LipsyncLevel::Low, None, None)
+    });
+    let error = result.expect_err("A cancelled synthesis should fail");
+    assert!(matches!(text_to_face::Error::find(&*error), Some(text_to_face::Error::Cancelled)));
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_voice_filter() {
+    use text_to_face::{Voice, VoiceFilter};
+    let voice = |id: &str, language: &str, quality: &str| Voice {
+        id: id.to_string(),
+        display_name: format!("{} ({})", id.replace('_', " "), quality),
+        language: language.to_string(),
+        quality: quality.to_string(),
+        model_path: String::new(),
+        config_path: String::new(),
+    };
+    let alba = voice("en_GB-alba-medium", "Scottish English", "medium");
+    let lessac = voice("en_US-lessac-high", "American English", "high");
+    let thorsten = voice("de_DE-thorsten-high", "German", "high");
+    let matching = |filter: &VoiceFilter| [&alba, &lessac, &thorsten].into_iter().filter(|v| filter.matches(v)).map(|v| v.id.as_str()).collect::<Vec<_>>();
+    assert!(VoiceFilter::default().is_empty());
+    assert_eq!(matching(&VoiceFilter::default()).len(), 3);
+    assert_eq!(matching(&VoiceFilter { language: Some("en".into()), ..Default::default() }), ["en_GB-alba-medium", "en_US-lessac-high"]);
+    assert_eq!(matching(&VoiceFilter { quality: Some("high".into()), ..Default::default() }), ["en_US-lessac-high", "de_DE-thorsten-high"]);
+    assert_eq!(matching(&VoiceFilter { country: Some("gb".into()), ..Default::default() }), ["en_GB-alba-medium"]);
+    assert_eq!(matching(&VoiceFilter { search: Some("SCOTTISH".into()), ..Default::default() }), ["en_GB-alba-medium"]);
+    assert!(matching(&VoiceFilter { language: Some("en".into()), search: Some("german".into()), ..Default::default() }).is_empty());
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_catalog_manifest() {
+    use text_to_face::catalog::CatalogManifest;
+    let path = std::env::temp_dir().join(format!("text_to_face_voices_{}.toml", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"
+base_url = "https://mirror.example.com/voices/"
+hide = ["en_US-ryan-high"]
+
+[[voice]]
+id = "en_GB-alba-medium"
+config = "https://cdn.example.com/alba.onnx.json"
+
+[[voice]]
+id = "en_US-studio-high"
+language = "American English"
+model = "https://cdn.example.com/studio.onnx"
+config = "https://cdn.example.com/studio.onnx.json"
+"#,
+    )
+    .unwrap();
+    let manifest = CatalogManifest::load(&path).unwrap();
+    let voices = manifest.apply(text_to_face::builtin_voices()).unwrap();
+    assert!(!voices.iter().any(|v| v.id == "en_US-ryan-high"), "Hidden voices should be left out");
+    let alba = voices.iter().find(|v| v.id == "en_GB-alba-medium").unwrap();
+    assert_eq!(alba.model_path, "https://mirror.example.com/voices/en/en_GB/alba/medium/en_GB-alba-medium.onnx");
+    assert_eq!(alba.config_path, "https://cdn.example.com/alba.onnx.json");
+    let studio = voices.iter().find(|v| v.id == "en_US-studio-high").unwrap();
+    assert_eq!((studio.language.as_str(), studio.quality.as_str()), ("American English", "high"));
+
+    let incomplete = CatalogManifest { voices: vec![text_to_face::catalog::ManifestVoice { id: "xx_XX-new-low".into(), ..Default::default() }], ..Default::default() };
+    assert!(incomplete.apply(text_to_face::builtin_voices()).is_err(), "A new voice needs its URLs");
+    std::fs::write(&path, "hide = \"not a list\"").unwrap();
+    assert!(CatalogManifest::load(&path).is_err());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_sidecar_up_to_date_check() {
+    let _settings = lock_settings();
+    use text_to_face::sidecar::{is_up_to_date, sidecar_path, write_sidecar, ExportSidecar};
+    let audio = std::env::temp_dir().join(format!("text_to_face_sidecar_{}.wav", std::process::id()));
+    let audio_path = audio.to_str().unwrap();
+    let sidecar = || ExportSidecar::new("Hello there.", "mock", 1.0, 1.0, "low", audio_path);
+    assert!(!is_up_to_date(&audio, &sidecar()), "A missing export is never up to date");
+    std::fs::write(&audio, b"rendered audio").unwrap();
+    assert!(!is_up_to_date(&audio, &sidecar()), "An export without a sidecar is never up to date");
+
+    let mut written = sidecar();
+    written.finalize(None);
+    write_sidecar(&audio, &written).unwrap();
+    assert!(is_up_to_date(&audio, &sidecar()));
+    assert!(!is_up_to_date(&audio, &ExportSidecar::new("Hello there!", "mock", 1.0, 1.0, "low", audio_path)), "Different text");
+    assert!(!is_up_to_date(&audio, &ExportSidecar::new("Hello there.", "mock", 1.2, 1.0, "low", audio_path)), "Different pitch");
+
+    text_to_face::set_prefer_quantized(true);
+    let quantized = sidecar();
+    text_to_face::set_prefer_quantized(false);
+    assert!(!is_up_to_date(&audio, &quantized), "--quantized renders with another model");
+    text_to_face::normalize::set_enabled(false);
+    let unnormalized = sidecar();
+    text_to_face::normalize::set_enabled(true);
+    assert!(!is_up_to_date(&audio, &unnormalized), "--no-normalize speaks different text");
+    assert_eq!(written.seed, None);
+    text_to_face::takes::set_variation(Some(text_to_face::takes::take_variation(2)));
+    let take = sidecar();
+    text_to_face::takes::set_variation(None);
+    assert_eq!(take.seed, Some(2), "A take records the seed of its variation");
+    assert!(!is_up_to_date(&audio, &take));
+
+    // Export and audiobook options land in the effects, and so in the hash
+    let with_effects = |effects: Vec<String>| {
+        let mut meta = sidecar();
+        meta.effects.extend(effects);
+        meta
+    };
+    let tags = text_to_face::tags::AudioTags { title: Some("Greeting".to_string()), ..text_to_face::tags::AudioTags::for_synthesis("mock", 1.0, 1.0) };
+    assert_eq!(tags.describe(), ["title=Greeting"], "Synthesis parameters are recorded by the sidecar itself");
+    assert!(!is_up_to_date(&audio, &with_effects(tags.describe())), "Different tags");
+    assert!(!is_up_to_date(&audio, &with_effects(vec![DuckSettings::default().describe()])), "Music mixed in");
+    assert_ne!(DuckSettings { duck_db: 6.0, ..DuckSettings::default() }.describe(), DuckSettings::default().describe());
+    let book = text_to_face::audiobook::AudiobookMetadata { author: Some("A. Writer".to_string()), ..Default::default() };
+    assert!(!is_up_to_date(&audio, &with_effects(book.describe())), "Different audiobook metadata");
+    let options = text_to_face::audiobook::AudiobookOptions::default();
+    let higher = text_to_face::audiobook::AudiobookOptions { bitrate_kbps: 128, ..options.clone() };
+    assert_ne!(options.describe(), higher.describe(), "Different bitrate");
+    assert_ne!(options.describe(), text_to_face::audiobook::AudiobookOptions { verify: true, ..options.clone() }.describe(), "Verified");
+    assert!(is_up_to_date(&audio, &with_effects(Vec::new())));
+
+    std::fs::write(&audio, b"edited audio").unwrap();
+    assert!(!is_up_to_date(&audio, &sidecar()), "An export changed since it was rendered");
+    let _ = std::fs::remove_file(&audio);
+    let _ = std::fs::remove_file(sidecar_path(&audio));
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_chapter_headings() {
+    use text_to_face::audiobook::split_chapters;
+    let titles = |text: &str| split_chapters(text).into_iter().map(|chapter| chapter.title).collect::<Vec<_>>();
+    assert_eq!(titles("# The Beginning\nOnce.\n## A Section\nMore.\n# The End\nDone."), ["The Beginning", "The End"], "## headings stay inside # chapters");
+    assert_eq!(titles("## One\nFirst.\n## Two\nSecond."), ["One", "Two"], "## headings are chapters when there are no # headings");
+    assert_eq!(titles("Chapter 1\nFirst.\nCHAPTER IV: The Storm\nRain.\nChapter Twenty-One. Home\nAt last.\nchapter xii\nEnd."), ["Chapter 1", "CHAPTER IV: The Storm", "Chapter Twenty-One. Home", "chapter xii"]);
+
+    // None of these start a chapter
+    for line in ["#1 fan of the show.", "### A subsection", "#hashtag #another", "Chapter one was the hardest of my life.", "Chapter and verse, he said.", "Chapter IIII", "#", "Chapter 3: the part where the narrator goes on for much longer than any real chapter title would"] {
+        assert_eq!(titles(&format!("Intro.\n{}\nMore text.", line)), ["Chapter 1"], "{:?} isn't a chapter heading", line);
+    }
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_morph_spec_parsing() {
+    use text_to_face::morph::MorphSpec;
+    let spec: MorphSpec = "en_GB-alba-medium:en_US-joe-medium:0.4".parse().unwrap();
+    assert_eq!(spec, MorphSpec { voice_a: "en_GB-alba-medium".to_string(), voice_b: "en_US-joe-medium".to_string(), amount: 0.4 });
+    assert_eq!(spec.label(), "en_GB-alba-medium+en_US-joe-medium@0.4");
+    assert_eq!("a:b:1".parse::<MorphSpec>().unwrap().amount, 1.0);
+    for bad in ["a:b", "a:b:c:0.5", "a:b:half", "a:b:1.5", "a:b:-0.1"] {
+        assert!(bad.parse::<MorphSpec>().is_err(), "{}", bad);
+    }
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_spectral_morph() {
+    use text_to_face::morph::spectral_morph;
+    let rate = 22050.0;
+    let tone = |length: usize| (0..length).map(|i| 0.5 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / rate).sin()).collect::<Vec<f32>>();
+    let (a, b) = (tone(22050), tone(11025));
+    // Either end of the blend is just that voice
+    assert_eq!(spectral_morph(&a, &b, 0.0), a);
+    assert_eq!(spectral_morph(&a, &b, 1.0), b);
+    assert_eq!(spectral_morph(&[], &b, 0.5), b);
+    assert_eq!(spectral_morph(&a, &[], 0.5), a);
+
+    for (amount, length) in [(0.25, 19293), (0.5, 16537), (0.75, 13781)] {
+        let morphed = spectral_morph(&a, &b, amount);
+        // The length lies between the two renderings', weighted by the amount
+        assert_eq!(morphed.len(), length, "amount {}", amount);
+        // Blending a voice with itself keeps its pitch and level
+        let frequency = tone_frequency(&morphed, rate);
+        assert!((frequency - 220.0).abs() < 220.0 * 0.1, "amount {}: {} Hz", amount, frequency);
+        let rms = (morphed.iter().map(|s| s * s).sum::<f32>() / morphed.len() as f32).sqrt();
+        assert!(rms > 0.2, "amount {}: RMS {}", amount, rms);
+    }
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_singing_note_lists() {
+    use text_to_face::singing::{parse_note_list, Note};
+    let note = |midi: u8, beats: f32| Note { midi: Some(midi), beats };
+    let rest = |beats: f32| Note { midi: None, beats };
+    // Notes and rests, `:beats`, and commas or spaces between them
+    assert_eq!(parse_note_list("C4 D4:0.5, e4:0.5 R G4:2 r:1.5").unwrap(), [note(60, 1.0), note(62, 0.5), note(64, 0.5), rest(1.0), note(67, 2.0), rest(1.5)]);
+    // Sharps and flats, and both ends of the MIDI range
+    assert_eq!(parse_note_list("F#3 Bb2 A4 C-1 G9").unwrap(), [note(54, 1.0), note(46, 1.0), note(69, 1.0), note(0, 1.0), note(127, 1.0)]);
+    assert_eq!(note(69, 1.0).frequency(), Some(440.0));
+    assert_eq!(rest(1.0).frequency(), None);
+    assert!(parse_note_list("").unwrap().is_empty());
+
+    for (bad, error) in [
+        ("H4", "Invalid note 'H4'"),
+        ("C", "Invalid octave in note 'C'"),
+        ("C#x", "Invalid octave in note 'C#x'"),
+        ("G10", "Note 'G10' is out of range"),
+        ("Cb-1", "Note 'Cb-1' is out of range"),
+        ("C4:0", "Duration must be positive in 'C4:0'"),
+        ("C4:-1", "Duration must be positive in 'C4:-1'"),
+        ("C4:abc", "Invalid duration in 'C4:abc'"),
+    ] {
+        assert_eq!(parse_note_list(&format!("C4 {} D4", bad)).unwrap_err(), error);
+    }
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_singing_midi_melody() {
+    use text_to_face::singing::{read_midi_melody, Note};
+    let write_midi = |name: &str, events: &[u8]| {
+        let path = std::env::temp_dir().join(format!("pitch_tts_{}_{}.mid", name, std::process::id()));
+        let mut data = b"MThd".to_vec();
+        data.extend(6u32.to_be_bytes());
+        // Format 0, one track, 480 ticks per beat
+        data.extend([0, 0, 0, 1, 0x01, 0xe0]);
+        data.extend(b"MTrk");
+        data.extend((events.len() as u32).to_be_bytes());
+        data.extend(events);
+        std::fs::write(&path, data).unwrap();
+        path.to_string_lossy().to_string()
+    };
+    // 120 BPM; C4 for a beat, half a beat's gap, then E4 for half a beat with running status
+    let melody = write_midi("melody", &[
+        0x00, 0xff, 0x51, 0x03, 0x07, 0xa1, 0x20,
+        0x00, 0x90, 60, 100, 0x83, 0x60, 0x80, 60, 0,
+        0x81, 0x70, 0x90, 64, 100, 0x81, 0x70, 64, 0,
+        0x00, 0xff, 0x2f, 0x00,
+    ]);
+    let (notes, bpm) = read_midi_melody(&melody).unwrap();
+    assert_eq!(notes, [Note { midi: Some(60), beats: 1.0 }, Note { midi: None, beats: 0.5 }, Note { midi: Some(64), beats: 0.5 }]);
+    assert_eq!(bpm, Some(120.0));
+
+    let empty = write_midi("empty", &[0x00, 0xff, 0x2f, 0x00]);
+    assert_eq!(read_midi_melody(&empty).unwrap_err().to_string(), "MIDI file contains no notes");
+    let not_midi = std::env::temp_dir().join(format!("pitch_tts_not_midi_{}.mid", std::process::id()));
+    std::fs::write(&not_midi, b"not midi").unwrap();
+    assert_eq!(read_midi_melody(not_midi.to_str().unwrap()).unwrap_err().to_string(), "Not a standard MIDI file");
+    for path in [melody, empty, not_midi.to_string_lossy().to_string()] {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_singing_syllables_and_pitch() {
+    use text_to_face::singing::{estimate_pitch, split_syllables};
+    // Hyphens mark the breaks; empty pieces are dropped
+    assert_eq!(split_syllables("won-der-ful -a-"), [vec!["won", "der", "ful"], vec!["a"]]);
+    // Otherwise each vowel group after the first starts a syllable, taking one consonant with it
+    assert_eq!(split_syllables("wonderful music hello beautiful Open AGAIN"), [
+        vec!["won", "der", "ful"],
+        vec!["mu", "sic"],
+        vec!["hel", "lo"],
+        vec!["beau", "ti", "ful"],
+        vec!["O", "pen"],
+        vec!["A", "GAIN"],
+    ]);
+    // A trailing silent "e" stays with the syllable before it
+    assert_eq!(split_syllables("make star"), [vec!["make"], vec!["star"]]);
+    assert!(split_syllables("  ").is_empty());
+
+    let tone = |hz: f32, rate: u32| (0..rate).map(|i| 0.5 * (2.0 * std::f32::consts::PI * hz * i as f32 / rate as f32).sin()).collect::<Vec<f32>>();
+    for (hz, rate) in [(220.0, 22050), (130.0, 16000)] {
+        let estimate = estimate_pitch(&tone(hz, rate), rate).unwrap();
+        assert!((estimate - hz).abs() < hz * 0.02, "{} Hz at {}: {}", hz, rate, estimate);
+    }
+    assert_eq!(estimate_pitch(&vec![0.0; 22050], 22050), None, "Silence has no pitch");
+    assert_eq!(estimate_pitch(&tone(220.0, 22050)[..100], 22050), None, "Too short to measure");
+}
+
+#[cfg(feature = "full")]
+#[test]
+fn test_voice_lockfile_round_trip() {
+    use text_to_face::pinning::{forget_voice, record_voice, set_lockfile_path, url_at_revision, verify_voice, voice_lock, VoiceLockfile};
+    let _settings = lock_settings();
+    let dir = std::env::temp_dir().join(format!("pitch_tts_pinning_{}", std::process::id()));
+    let models = dir.join("models");
+    let lockfile = dir.join("nested").join("voices.lock.json");
+    std::fs::create_dir_all(&models).unwrap();
+    text_to_face::set_models_dir(Some(models.clone()));
+    set_lockfile_path(Some(lockfile.clone()));
+
+    assert!(VoiceLockfile::load(&lockfile).unwrap().voices.is_empty(), "A missing lockfile is empty");
+    std::fs::write(models.join("test-voice.onnx"), b"model").unwrap();
+    std::fs::write(models.join("test-voice.onnx.json"), b"{}").unwrap();
+    let lock = record_voice("test-voice", "0123456789abcdef").unwrap();
+    assert_eq!(lock.revision, "0123456789abcdef");
+    assert_eq!(lock.model_sha256, text_to_face::sidecar::sha256_file(&models.join("test-voice.onnx")).unwrap());
+    assert!(!lock.pinned);
+    // Saved, parent directory and all, and read back the same
+    assert_eq!(voice_lock("test-voice"), Some(lock.clone()));
+    assert!(verify_voice("test-voice", &lock).is_ok());
+    std::fs::write(models.join("test-voice.onnx"), b"retrained").unwrap();
+    assert!(verify_voice("test-voice", &lock).unwrap_err().to_string().contains("doesn't match the locked hash"));
+
+    // Recording again keeps a voice pinned, and forgetting leaves a pinned voice alone
+    let mut pinned = VoiceLockfile::load(&lockfile).unwrap();
+    pinned.voices.get_mut("test-voice").unwrap().pinned = true;
+    pinned.save(&lockfile).unwrap();
+    assert!(record_voice("test-voice", "fedcba9876543210").unwrap().pinned);
+    forget_voice("test-voice").unwrap();
+    assert_eq!(voice_lock("test-voice").unwrap().revision, "fedcba9876543210");
+    let mut unpinned = VoiceLockfile::load(&lockfile).unwrap();
+    unpinned.voices.get_mut("test-voice").unwrap().pinned = false;
+    unpinned.save(&lockfile).unwrap();
+    forget_voice("test-voice").unwrap();
+    assert_eq!(voice_lock("test-voice"), None);
+
+    std::fs::write(&lockfile, "not json").unwrap();
+    assert!(VoiceLockfile::load(&lockfile).unwrap_err().to_string().starts_with("Invalid voice lockfile"));
+    set_lockfile_path(None);
+    text_to_face::set_models_dir(None);
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let url = "https://huggingface.co/rhasspy/piper-voices/resolve/main/en/en_GB/alba/medium/en_GB-alba-medium.onnx";
+    assert_eq!(url_at_revision(url, "abc123"), "https://huggingface.co/rhasspy/piper-voices/resolve/abc123/en/en_GB/alba/medium/en_GB-alba-medium.onnx");
+    assert_eq!(url_at_revision("https://example.com/voice.onnx", "abc123"), "https://example.com/voice.onnx", "Only catalog URLs track a branch");
+}
@@ -219,6 +219,7 @@ fn test_cli_say_help() {
             assert!(stdout.contains("Synthesize speech and play it"), "Should show say description");
             assert!(stdout.contains("--voice"), "Should show voice option");
             assert!(stdout.contains("--pitch"), "Should show pitch option");
+            assert!(stdout.contains("--input-format"), "Should show input format option");
         }
         Err(e) => {
             eprintln!("CLI say help test failed: {}", e);
@@ -386,4 +387,254 @@ fn manual_audio_test() {
     }
     
     println!("🎵 Manual audio test completed!");
-} 
\ No newline at end of file
+} 
+#[test]
+fn test_cli_audiobook_help() {
+    // Test audiobook command help
+    let mut cmd = Command::new("cargo");
+    cmd.args(["run", "--", "audiobook", "--help"]);
+
+    let output = cmd.output();
+
+    match output {
+        Ok(result) => {
+            assert!(result.status.success(), "Audiobook help should succeed");
+
+            let stdout = String::from_utf8_lossy(&result.stdout);
+
+            assert!(stdout.contains("chaptered M4B"), "Should show audiobook description");
+            assert!(stdout.contains("--title"), "Should show title option");
+            assert!(stdout.contains("--author"), "Should show author option");
+        }
+        Err(e) => {
+            eprintln!("CLI audiobook help test failed: {}", e);
+        }
+    }
+}
+
+#[test]
+fn test_cli_voices_install_help() {
+    // Test voices install help output
+    let mut cmd = Command::new("cargo");
+    cmd.args(["run", "--", "voices", "install", "--help"]);
+
+    let output = cmd.output();
+
+    match output {
+        Ok(result) => {
+            assert!(result.status.success(), "Voices install help should succeed");
+
+            let stdout = String::from_utf8_lossy(&result.stdout);
+
+            assert!(stdout.contains("--quantized"), "Should show quantized option");
+            assert!(stdout.contains("--jobs"), "Should show jobs option for bulk installs");
+        }
+        Err(e) => {
+            eprintln!("CLI voices install help test failed: {}", e);
+        }
+    }
+}
+
+#[test]
+fn test_cli_replay_help() {
+    // Test replay help output
+    let mut cmd = Command::new("cargo");
+    cmd.args(["run", "--", "replay", "--help"]);
+
+    let output = cmd.output();
+
+    match output {
+        Ok(result) => {
+            assert!(result.status.success(), "Replay help should succeed");
+
+            let stdout = String::from_utf8_lossy(&result.stdout);
+
+            assert!(stdout.contains("--entry"), "Should show entry option");
+            assert!(stdout.contains("--session-log"), "Should show the global session log option");
+        }
+        Err(e) => {
+            eprintln!("CLI replay help test failed: {}", e);
+        }
+    }
+}
+
+#[test]
+fn test_cli_interactive_help() {
+    // Test interactive help output
+    let mut cmd = Command::new("cargo");
+    cmd.args(["run", "--", "interactive", "--help"]);
+
+    let output = cmd.output();
+
+    match output {
+        Ok(result) => {
+            assert!(result.status.success(), "Interactive help should succeed");
+
+            let stdout = String::from_utf8_lossy(&result.stdout);
+
+            assert!(stdout.contains("/voice"), "Should mention the /voice command");
+            assert!(stdout.contains("--mode"), "Should show mode option");
+        }
+        Err(e) => {
+            eprintln!("CLI interactive help test failed: {}", e);
+        }
+    }
+}
+
+#[test]
+fn test_cli_gallery() {
+    // A directory with one rendered line and its lipsync JSON
+    let dir = std::env::temp_dir().join(format!("pitch_tts_gallery_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let spec = hound::WavSpec { channels: 1, sample_rate: 22050, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+    let mut writer = hound::WavWriter::create(dir.join("line_one.wav"), spec).unwrap();
+    for _ in 0..11025 {
+        writer.write_sample(0i16).unwrap();
+    }
+    writer.finalize().unwrap();
+    std::fs::write(dir.join("line_one.json"), r#"{"word_segments":[{"word":"Hello","start":0.1,"end":0.4}]}"#).unwrap();
+
+    let mut cmd = Command::new("cargo");
+    cmd.args(["run", "--", "gallery"]).arg(&dir);
+
+    match cmd.output() {
+        Ok(result) => {
+            assert!(result.status.success(), "Gallery should succeed");
+            let html = std::fs::read_to_string(dir.join("index.html")).expect("index.html should be written");
+            assert!(html.contains("\"audio\":\"line_one.wav\""), "Should list the line's audio");
+            assert!(html.contains("\"lipsync\":\"line_one.json\""), "Should link the lipsync JSON");
+            assert!(html.contains("\"text\":\"Hello\""), "Should fall back to the aligned words for the text");
+        }
+        Err(e) => {
+            eprintln!("CLI gallery test failed: {}", e);
+        }
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_cli_json_output() {
+    // An unknown key is an error: reported in the JSON result, with a failing exit status
+    let mut cmd = Command::new("cargo");
+    cmd.args(["run", "--", "--json", "config", "get", "no_such_key"]);
+
+    match cmd.output() {
+        Ok(result) => {
+            assert!(!result.status.success(), "An error should fail the command");
+            let stdout = String::from_utf8_lossy(&result.stdout);
+            let value: serde_json::Value = serde_json::from_str(&stdout).expect("stdout should be a single JSON object");
+            assert_eq!(value["command"], "config");
+            assert_eq!(value["ok"], false);
+            assert!(value["errors"][0].as_str().unwrap().contains("no_such_key"), "Should record the error message");
+        }
+        Err(e) => {
+            eprintln!("CLI JSON output test failed: {}", e);
+        }
+    }
+}
+
+#[test]
+fn test_cli_status_without_daemon() {
+    // Nothing listens on the socket, so the client says how to start the daemon
+    let socket = std::env::temp_dir().join("text_to_face_no_daemon.sock");
+    let mut cmd = Command::new("cargo");
+    cmd.args(["run", "--", "status", "--socket", socket.to_str().unwrap()]);
+
+    match cmd.output() {
+        Ok(result) => {
+            assert!(!result.status.success(), "status should fail without a daemon");
+            let stderr = String::from_utf8_lossy(&result.stderr);
+            assert!(stderr.contains("start it with `daemon`"), "Should explain how to start the daemon");
+        }
+        Err(e) => {
+            eprintln!("CLI status test failed: {}", e);
+        }
+    }
+}
+
+#[test]
+fn test_cli_data_dir_override() {
+    let dir = std::env::temp_dir().join("text_to_face_data_dir");
+    let mut cmd = Command::new("cargo");
+    cmd.args(["run", "--", "--data-dir", dir.to_str().unwrap(), "info"]);
+
+    match cmd.output() {
+        Ok(result) => {
+            let stdout = String::from_utf8_lossy(&result.stdout);
+            assert!(stdout.contains(&format!("App data directory: {}", dir.display())), "--data-dir should replace the app data directory");
+            assert!(stdout.contains(&format!("CMUdict path: {}", dir.join("cmudict-0.7b.txt").display())), "CMUdict should live under --data-dir");
+        }
+        Err(e) => {
+            eprintln!("CLI data dir test failed: {}", e);
+        }
+    }
+}
+
+#[test]
+fn test_cli_voices_add_local() {
+    let dir = std::env::temp_dir().join(format!("text_to_face_local_voice_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let model = dir.join("narrator.onnx");
+    std::fs::write(&model, b"not really a model").unwrap();
+    std::fs::write(dir.join("narrator.onnx.json"), r#"{"audio": {"sample_rate": 22050, "quality": "high"}, "language": {"code": "en_US", "name_english": "English"}}"#).unwrap();
+    let data_dir = dir.join("data");
+    let run = |args: &[&str]| {
+        let mut cmd = Command::new("cargo");
+        cmd.args(["run", "--", "--data-dir", data_dir.to_str().unwrap()]).args(args);
+        cmd.output()
+    };
+
+    match run(&["voices", "add-local", "--id", "narrator", "--model", model.to_str().unwrap()]) {
+        Ok(result) => {
+            assert!(result.status.success(), "add-local failed: {}", String::from_utf8_lossy(&result.stderr));
+            assert!(data_dir.join("local_voices.json").exists(), "The voice should be registered");
+        }
+        Err(e) => {
+            eprintln!("CLI add-local test failed: {}", e);
+            return;
+        }
+    }
+    if let Ok(result) = run(&["--json", "list", "--search", "narrator"]) {
+        let listing: serde_json::Value = serde_json::from_slice(&result.stdout).expect("list --json should print JSON");
+        assert_eq!(listing[0]["id"], "narrator");
+        assert_eq!(listing[0]["quality"], "high");
+        assert_eq!(listing[0]["installed"], true);
+    }
+    if let Ok(result) = run(&["voices", "add-local", "--id", "en_GB-alba-medium", "--model", model.to_str().unwrap()]) {
+        assert!(!result.status.success(), "Catalog IDs shouldn't be reused");
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_cli_config_file() {
+    // A config file of its own, so the user's isn't touched
+    let config_home = std::env::temp_dir().join(format!("text_to_face_config_{}", std::process::id()));
+    let run = |args: &[&str]| Command::new("cargo").args(["run", "--"]).args(args).env("XDG_CONFIG_HOME", &config_home).output();
+
+    // Values are checked the way their flags would be
+    for args in [["config", "set", "pitch", "not-a-pitch"], ["config", "set", "tempo", "-1"], ["config", "set", "lipsync", "perfect"], ["config", "set", "no_such_key", "1"]] {
+        match run(&args) {
+            Ok(result) => assert!(!result.status.success(), "{:?} should be rejected", args),
+            Err(e) => eprintln!("CLI config test failed: {}", e),
+        }
+    }
+    for args in [["config", "set", "pitch", "deep"], ["config", "set", "tempo", "1.5"]] {
+        match run(&args) {
+            Ok(result) => assert!(result.status.success(), "{:?} should be saved: {}", args, String::from_utf8_lossy(&result.stderr)),
+            Err(e) => eprintln!("CLI config test failed: {}", e),
+        }
+    }
+    if let Ok(result) = run(&["config", "get", "pitch"]) {
+        assert!(String::from_utf8_lossy(&result.stdout).contains("deep"));
+    }
+
+    // The config's pitch and tempo become the defaults of `say`, but not of `dialogue`, where they would
+    // scale every character's own
+    let help = |command: &str| run(&[command, "--help"]).map(|result| String::from_utf8_lossy(&result.stdout).to_string());
+    if let (Ok(say), Ok(dialogue)) = (help("say"), help("dialogue")) {
+        assert!(say.contains("[default: deep]") && say.contains("[default: 1.5]"), "say should default to the config: {}", say);
+        assert!(!dialogue.contains("[default: deep]") && !dialogue.contains("[default: 1.5]"), "dialogue should keep its own defaults: {}", dialogue);
+    }
+    let _ = std::fs::remove_dir_all(&config_home);
+}
@@ -0,0 +1,91 @@
+// Locale-aware number, ordinal, and date normalization, abbreviation expansion, and emoji handling
+
+use text_to_face::abbreviations::{expand_with, AbbreviationSettings, AcronymMode};
+use text_to_face::emoji::{apply_with, EmojiMode};
+use text_to_face::normalize::{normalize_text, Locale};
+
+fn normalize(text: &str, voice: &str) -> String {
+    normalize_text(text, Locale::from_voice_id(voice).unwrap())
+}
+
+#[test]
+fn test_english_numbers_and_dates() {
+    assert_eq!(normalize("I have 1,234 apples.", "en_GB-alba-medium"), "I have one thousand two hundred thirty-four apples.");
+    assert_eq!(normalize("the 21st time", "en_GB-alba-medium"), "the twenty-first time");
+    assert_eq!(normalize("On 12/03/2024 we met.", "en_GB-alba-medium"), "On the twelfth of March twenty twenty-four we met.");
+    assert_eq!(normalize("On 03/12/2024 we met.", "en_US-joe-medium"), "On March twelfth, twenty twenty-four we met.");
+    assert_eq!(normalize("It costs 3.5 pounds", "en_GB-alba-medium"), "It costs three point five pounds");
+}
+
+#[test]
+fn test_german_numbers_and_dates() {
+    assert_eq!(normalize("21 Katzen", "de_DE-thorsten-medium"), "einundzwanzig Katzen");
+    assert_eq!(normalize("Am 3. Mai", "de_DE-thorsten-medium"), "Am dritten Mai");
+    assert_eq!(normalize("am 12.03.2024", "de_DE-thorsten-medium"), "am zwölften März zweitausendvierundzwanzig");
+    assert_eq!(normalize("1.500 Euro", "de_DE-thorsten-medium"), "eintausendfünfhundert Euro");
+    assert_eq!(normalize("3,5 Liter", "de_DE-thorsten-medium"), "drei Komma fünf Liter");
+    assert_eq!(normalize("am 1.1.1999", "de_DE-thorsten-medium"), "am ersten Januar neunzehnhundertneunundneunzig");
+}
+
+#[test]
+fn test_french_spanish_italian() {
+    assert_eq!(normalize("le 1er mai", "fr_FR-siwis-medium"), "le premier mai");
+    assert_eq!(normalize("71 et 80", "fr_FR-siwis-medium"), "soixante et onze et quatre-vingts");
+    assert_eq!(normalize("la 3e fois", "fr_FR-siwis-medium"), "la troisième fois");
+    assert_eq!(normalize("21000", "es_ES-davefx-medium"), "veintiún mil");
+    assert_eq!(normalize("12/03/2024", "es_ES-davefx-medium"), "doce de marzo de dos mil veinticuatro");
+    assert_eq!(normalize("la 2.ª vez", "es_ES-davefx-medium"), "la segunda vez");
+    assert_eq!(normalize("23 gatti", "it_IT-riccardo-x_low"), "ventitré gatti");
+    assert_eq!(normalize("il 1 maggio", "it_IT-riccardo-x_low"), "il primo maggio");
+    assert_eq!(normalize("l'11º posto", "it_IT-riccardo-x_low"), "l'undicesimo posto");
+}
+
+#[test]
+fn test_russian_ordinals_and_dates() {
+    assert_eq!(normalize("21 кот", "ru_RU-irina-medium"), "двадцать один кот");
+    assert_eq!(normalize("12.03.2024", "ru_RU-irina-medium"), "двенадцатое марта две тысячи двадцать четвёртого года");
+    assert_eq!(normalize("3-я глава", "ru_RU-irina-medium"), "третья глава");
+    assert_eq!(normalize("в 2000 году", "ru_RU-irina-medium"), "в двухтысячном году");
+}
+
+#[test]
+fn test_unsupported_and_untouched_text() {
+    assert!(Locale::from_voice_id("zh_CN-huayan-medium").is_none());
+    assert_eq!(normalize("Version 2.0.1 and A4 paper", "en_GB-alba-medium"), "Version 2.0.1 and A4 paper");
+    assert_eq!(normalize("Agent 007", "en_GB-alba-medium"), "Agent 007");
+}
+
+fn expand(text: &str, voice: &str, settings: &AbbreviationSettings) -> String {
+    expand_with(text, Locale::from_voice_id(voice), settings)
+}
+
+#[test]
+fn test_abbreviations_and_units() {
+    let settings = AbbreviationSettings::default();
+    assert_eq!(expand("Dr. Smith ran 5 km (about 3mi) with Mrs. Jones, etc.", "en_GB-alba-medium", &settings), "Doctor Smith ran 5 kilometres (about 3 miles) with Missus Jones, et cetera.");
+    assert_eq!(expand("1 km at 30 km/h", "en_US-joe-medium", &settings), "1 kilometer at 30 kilometers per hour");
+    assert_eq!(expand("Dr. Müller", "de_DE-thorsten-medium", &settings), "Dr. Müller", "built-ins are English");
+    let mut user = AbbreviationSettings::default();
+    user.user.insert("Str.".to_string(), "Straße".to_string());
+    assert_eq!(expand("Hauptstr. 5 und Str. 7", "de_DE-thorsten-medium", &user), "Hauptstr. 5 und Straße 7");
+}
+
+#[test]
+fn test_acronym_modes() {
+    let mode = |acronyms| AbbreviationSettings { acronyms, ..Default::default() };
+    let text = "NASA and the FBI watched the BBC. STOP!";
+    assert_eq!(expand(text, "en_GB-alba-medium", &mode(AcronymMode::Auto)), "Nasa and the ef bee eye watched the bee bee see. STOP!");
+    assert_eq!(expand(text, "en_GB-alba-medium", &mode(AcronymMode::Spell)), "Nasa and the ef bee eye watched the bee bee see. ess tee oh pee!");
+    assert_eq!(expand(text, "en_GB-alba-medium", &mode(AcronymMode::Word)), "Nasa and the Fbi watched the Bbc. Stop!");
+    assert_eq!(expand("ZZ Top", "en_US-joe-medium", &mode(AcronymMode::Auto)), "zee zee Top");
+}
+
+#[test]
+fn test_emoji_modes() {
+    let en = Locale::from_voice_id("en_GB-alba-medium");
+    let text = "Great job👍🏽! See you 🙂 🙂 soon 🇬🇧 🦜";
+    assert_eq!(apply_with(text, en, EmojiMode::Strip), "Great job! See you soon");
+    assert_eq!(apply_with(text, en, EmojiMode::Speak), "Great job thumbs up! See you smiley face soon flag");
+    assert_eq!(apply_with(text, en, EmojiMode::Ignore), text);
+    assert_eq!(apply_with("Family 👨‍👩‍👧 time ❤️", Locale::from_voice_id("de_DE-thorsten-medium"), EmojiMode::Speak), "Family time", "names are English");
+}
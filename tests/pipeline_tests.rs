@@ -0,0 +1,109 @@
+// End-to-end tests against the generated fixture voice (`cargo test --features fixtures`).
+// Real ONNX inference and espeak-ng phonemization, but no model downloads and no WhisperX. If
+// espeak-ng isn't installed system-wide, point PIPER_ESPEAKNG_DATA_DIRECTORY at the directory holding
+// `espeak-ng-data`.
+
+use text_to_face::fixtures::{write_fixture_voice, FIXTURE_SAMPLE_RATE, SAMPLES_PER_PHONEME_ID};
+use text_to_face::runtime::VoiceRuntime;
+use std::path::PathBuf;
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pitch_tts_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_fixture_voice_synthesizes() {
+    let dir = scratch_dir("fixture_voice");
+    write_fixture_voice(&dir, "en_US-fixture-low").unwrap();
+    let runtime = VoiceRuntime::new(&dir);
+    assert_eq!(runtime.voices(), vec!["en_US-fixture-low".to_string()]);
+
+    let voice = runtime.load("en_US-fixture-low").unwrap();
+    assert_eq!(voice.sample_rate, FIXTURE_SAMPLE_RATE);
+    let first = voice.synthesize("Hello world. Goodbye world.").unwrap();
+    let second = voice.synthesize("Hello world. Goodbye world.").unwrap();
+    assert!(!first.is_empty());
+    assert_eq!(first, second, "fixture audio should be identical across runs");
+    assert_eq!(first.len() as i64 % SAMPLES_PER_PHONEME_ID, 0, "every phoneme ID becomes a fixed run of samples");
+    assert!(first.iter().all(|s| s.abs() <= 0.3 + f32::EPSILON));
+
+    let slower = voice.synthesize_with("Hello world. Goodbye world.", 1.0, 2.0).unwrap();
+    let ratio = slower.len() as f32 / first.len() as f32;
+    assert!((ratio - 2.0).abs() < 0.1, "tempo 2.0 should double the length, got {}", ratio);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_mock_aligner_spreads_transcript() {
+    let dir = scratch_dir("mock_aligner");
+    let aligner = text_to_face::fixtures::write_mock_aligner(&dir, "Don't \"panic\" now").unwrap();
+    let wav = dir.join("line.wav");
+    let spec = hound::WavSpec { channels: 1, sample_rate: FIXTURE_SAMPLE_RATE, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+    let mut writer = hound::WavWriter::create(&wav, spec).unwrap();
+    for _ in 0..FIXTURE_SAMPLE_RATE * 3 {
+        writer.write_sample(0i16).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let out = dir.join("aligned");
+    std::fs::create_dir_all(&out).unwrap();
+    let status = std::process::Command::new(&aligner)
+        .arg(&wav)
+        .args(["--output_dir", out.to_str().unwrap(), "--output_format", "json"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(out.join("line.json")).unwrap()).unwrap();
+    let words = text_to_face::word_timings(&json).unwrap();
+    let names: Vec<&str> = words.iter().map(|w| w.word.as_str()).collect();
+    assert_eq!(names, vec!["Don't", "\"panic\"", "now"]);
+    // Three seconds, one second per word
+    assert_eq!(words[1].start, 1.0);
+    assert_eq!(words[2].end, 2.9);
+    assert_eq!(json["segments"][0]["words"], json["word_segments"]);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(all(feature = "full", target_os = "linux"))]
+#[test]
+fn test_export_pipeline_with_fixture_voice() {
+    // The fixture stands in for a catalog voice in a private data directory, so nothing is downloaded
+    let dir = scratch_dir("export_pipeline");
+    let data_home = dir.join("data");
+    write_fixture_voice(&data_home.join("text-to-face").join("models"), "en_GB-alba-medium").unwrap();
+    let bin = dir.join("bin");
+    text_to_face::fixtures::write_mock_aligner(&bin, "Hello world").unwrap();
+    // A lexicon as the only phoneme source keeps CMUdict (and its download) out of the test
+    let lexicon = dir.join("lexicon.txt");
+    std::fs::write(&lexicon, "HELLO HH AH0 L OW1\nWORLD W ER1 L D\n").unwrap();
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap_or_default());
+
+    let wav = dir.join("line.wav");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_text-to-face"))
+        .args(["export", "Hello world", "-v", "en_GB-alba-medium", "--lipsync", "high", "--markers", "words", "--phoneme-sources", "lexicon"])
+        .arg("--lexicon")
+        .arg(&lexicon)
+        .arg("-o")
+        .arg(&wav)
+        .env("XDG_DATA_HOME", &data_home)
+        .env("PATH", path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "export failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let reader = hound::WavReader::open(&wav).unwrap();
+    assert_eq!(reader.spec().sample_rate, FIXTURE_SAMPLE_RATE);
+    assert!(reader.duration() > 0);
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("line.json")).unwrap()).unwrap();
+    let words = text_to_face::word_timings(&json).unwrap();
+    assert_eq!(words.len(), 2);
+    assert_eq!(words[0].phonemes, vec!["HH", "AH0", "L", "OW1"]);
+    assert_eq!(json["word_segments"][1]["phoneme_method"], "lexicon");
+    let markers = text_to_face::markers::read_wav_markers(wav.to_str().unwrap()).unwrap();
+    assert_eq!(markers.iter().map(|m| m.label.as_str()).collect::<Vec<_>>(), vec!["Hello", "world"]);
+    let _ = std::fs::remove_dir_all(&dir);
+}